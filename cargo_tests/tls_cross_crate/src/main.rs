@@ -0,0 +1,24 @@
+#![feature(thread_local)]
+use std::cell::Cell;
+use tls_provider::{bump, COUNTER, EAGER, LAZY};
+// Makes sure the thread locals of both crates get different addresses.
+#[thread_local]
+static LOCAL: Cell<u32> = Cell::new(11);
+fn main() {
+    assert_eq!(COUNTER.get(), 7);
+    assert_eq!(bump(), 8);
+    COUNTER.set(COUNTER.get() + 1);
+    assert_eq!(COUNTER.get(), 9);
+    assert_eq!(LOCAL.get(), 11);
+    LAZY.with(|lazy| lazy.set(lazy.get() * 2));
+    assert_eq!(LAZY.with(Cell::get), 6);
+    assert_eq!(EAGER.with(Cell::get), 5);
+    std::thread::spawn(|| {
+        // Other threads see their own, freshly initialized, copies.
+        assert_eq!(COUNTER.get(), 7);
+        assert_eq!(LAZY.with(Cell::get), 3);
+    })
+    .join()
+    .unwrap();
+    assert_eq!(COUNTER.get(), 9);
+}
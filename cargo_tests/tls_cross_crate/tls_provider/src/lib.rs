@@ -0,0 +1,14 @@
+#![feature(thread_local)]
+use std::cell::Cell;
+// Read directly by other crates. On some targets, this can only be done trough a thread local shim.
+#[thread_local]
+pub static COUNTER: Cell<u32> = Cell::new(7);
+thread_local! {
+    pub static LAZY: Cell<u32> = Cell::new(std::hint::black_box(3));
+    pub static EAGER: Cell<u32> = const { Cell::new(5) };
+}
+#[inline(never)]
+pub fn bump() -> u32 {
+    COUNTER.set(COUNTER.get() + 1);
+    COUNTER.get()
+}
@@ -0,0 +1,40 @@
+//! Computes `CILLY_BUILD_HASH`: a hash of the sources of this crate, stored in serialized assemblies. Assemblies are
+//! only compatible with builds of `cilly` using the exact same sources, since the encoding of the IR is not stable.
+use std::path::{Path, PathBuf};
+
+fn collect_sources(dir: &Path, out: &mut Vec<PathBuf>) {
+    for entry in std::fs::read_dir(dir).expect("Could not read the source directory") {
+        let path = entry.expect("Could not read a directory entry").path();
+        if path.is_dir() {
+            collect_sources(&path, out);
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            out.push(path);
+        }
+    }
+}
+fn main() {
+    println!("cargo:rerun-if-changed=src");
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut sources = vec![];
+    collect_sources(&root.join("src"), &mut sources);
+    sources.sort();
+    // FNV-1a: simple, and stable across Rust versions.
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    feed(env!("CARGO_PKG_VERSION").as_bytes());
+    for source in sources {
+        let relative = source.strip_prefix(root).unwrap_or(&source);
+        // Hash the components, so that the hash does not depend on the path separator.
+        for component in relative.components() {
+            feed(component.as_os_str().to_string_lossy().as_bytes());
+            feed(b"/");
+        }
+        feed(&std::fs::read(&source).expect("Could not read a source file"));
+    }
+    println!("cargo:rustc-env=CILLY_BUILD_HASH={hash:016x}");
+}
@@ -11,6 +11,7 @@ use cilly::{
     bimap::{Interned, IntoBiMapIndex},
     c_exporter::CExporter,
     cillyir_exporter::CillyIRExpoter,
+    cillytext::CillyTextExporter,
     il_exporter::ILExporter,
     Access, BasicBlock, CILIter, CILNode, CILRoot, MethodDef, MethodImpl, MethodRef,
 };
@@ -65,6 +66,15 @@ fn main() {
                     asm.class_defs().len()
                 );
             }
+            "mergetypes" => {
+                let start = std::time::Instant::now();
+                let merged = asm.merge_identical_valuetypes();
+                eprintln!(
+                    "Merged {merged} identical value types in {} ms. Total types:{}",
+                    start.elapsed().as_millis(),
+                    asm.class_defs().len()
+                );
+            }
             "deadcode" => asm.eliminate_dead_code(),
             "opt" => {
                 let mut fuel = asm.fuel_from_env();
@@ -95,7 +105,20 @@ fn main() {
                     .read_to_end(&mut asm_bytes)
                     .expect("ERROR: Could not load the assembly file!");
                 println!("Loading an assembly");
-                let loaded_asm = postcard::from_bytes(&asm_bytes).unwrap();
+                match cilly::container::read_header(&asm_bytes) {
+                    Ok(header) => print!("{header}"),
+                    Err(err) => {
+                        eprintln!("Could not read the assembly header: {err}");
+                        continue;
+                    }
+                }
+                let loaded_asm = match cilly::container::decode(&asm_bytes) {
+                    Ok(loaded_asm) => loaded_asm,
+                    Err(err) => {
+                        eprintln!("Could not load the assembly: {err}");
+                        continue;
+                    }
+                };
                 // Loading an assembly
 
                 if asm.class_defs().is_empty() {
@@ -171,6 +194,46 @@ fn main() {
                 #[cfg(not(miri))]
                 asm.export(path, CillyIRExpoter::default())
             }
+            "totext" => {
+                let path = body;
+                let path = path.trim().trim_matches('\'').trim();
+                println!("Preparing to export the assembly");
+                asm.export(path, CillyTextExporter::default())
+            }
+            "opentext" => {
+                let path = body;
+                let path = path.trim().trim_matches('\'').trim();
+                let src = std::fs::read_to_string(path)
+                    .expect("ERROR: Could not load the assembly file!");
+                println!("Parsing an assembly");
+                let loaded_asm = match cilly::cillytext::parse(&src) {
+                    Ok(loaded_asm) => loaded_asm,
+                    Err(err) => {
+                        eprintln!("{path}:{err}");
+                        continue;
+                    }
+                };
+                if asm.class_defs().is_empty() {
+                    asm = loaded_asm;
+                } else {
+                    println!("Linking an assembly");
+                    asm = asm.clone().link(loaded_asm);
+                }
+                println!("Loaded assembly");
+            }
+            "run" => {
+                let mut interpreter = cilly::interpreter::Interpreter::new(&asm);
+                let res = interpreter.run_entrypoint();
+                print!("{}", String::from_utf8_lossy(interpreter.stdout()));
+                eprint!("{}", String::from_utf8_lossy(interpreter.stderr()));
+                match res {
+                    Ok(code) => println!("Exited with code {code}"),
+                    Err(err) => match interpreter.location() {
+                        Some((file, line)) => eprintln!("ERROR: {err} (last at {file}:{line})"),
+                        None => eprintln!("ERROR: {err}"),
+                    },
+                }
+            }
             "mmakemissing" => {
                 let id = parse_id(body, &asm);
                 let Some(id) = asm.method_ref_to_def(id) else {
@@ -16,8 +16,13 @@ fn load_asm(mut file: impl std::io::Read) -> Assembly {
         "Reading the assembly took {} ms",
         end.duration_since(start).as_millis()
     );
+    match cilly::container::read_header(&asm_bytes) {
+        Ok(header) => print!("{header}"),
+        Err(err) => eprintln!("ERROR: Could not read the assembly header: {err}"),
+    }
     let start = std::time::Instant::now();
-    let res = postcard::from_bytes(&asm_bytes).expect("ERROR:Could not decode the assembly file!");
+    let res = cilly::container::decode(&asm_bytes)
+        .unwrap_or_else(|err| panic!("ERROR: Could not decode the assembly file: {err}"));
     let end = std::time::Instant::now();
     println!(
         "Decoding the assembly took {} ms",
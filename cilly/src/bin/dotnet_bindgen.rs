@@ -0,0 +1,30 @@
+//! Generates `mycorrhiza` bindings to a .NET assembly.
+//!
+//! Usage: `dotnet_bindgen <ASSEMBLY.dll> [OUTPUT.rs] [--crate PATH]`. The bindings are written to stdout if no output
+//! file is given. `--crate` sets the path to `mycorrhiza`, as seen from the bindings(`crate` when regenerating the
+//! bindings of `mycorrhiza` itself). It defaults to `mycorrhiza`.
+fn main() {
+    let mut krate = "mycorrhiza".to_string();
+    let mut paths = vec![];
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--crate" {
+            krate = args.next().expect("ERROR: `--crate` expects a path");
+        } else {
+            paths.push(arg);
+        }
+    }
+    let Some(input) = paths.first() else {
+        eprintln!("Usage: dotnet_bindgen <ASSEMBLY.dll> [OUTPUT.rs] [--crate PATH]");
+        std::process::exit(1);
+    };
+    let image =
+        std::fs::read(input).unwrap_or_else(|err| panic!("ERROR: Could not read {input:?}: {err}"));
+    let bindings = cilly::pe_exporter::bindgen::generate_bindings(&image, &krate)
+        .unwrap_or_else(|err| panic!("ERROR: Could not generate bindings to {input:?}: {err}"));
+    match paths.get(1) {
+        Some(output) => std::fs::write(output, bindings)
+            .unwrap_or_else(|err| panic!("ERROR: Could not write {output:?}: {err}")),
+        None => print!("{bindings}"),
+    }
+}
@@ -38,8 +38,8 @@ fn load_ar(r: &mut impl std::io::Read) -> std::io::Result<(cilly::Assembly, Vec<
             entry
                 .read_to_end(&mut asm_bytes)
                 .expect("ERROR: Could not load the assembly file!");
-            let assembly = postcard::from_bytes(&asm_bytes)
-                .unwrap_or_else(|_| panic!("ERROR:Could not decode the assembly file {name}!"));
+            let assembly = cilly::container::decode(&asm_bytes)
+                .unwrap_or_else(|err| panic!("ERROR: Could not load the assembly {name}: {err}"));
             final_assembly = final_assembly.link(assembly);
         } else if ext.contains("o") {
             let mut file_bytes = Vec::with_capacity(0x100);
@@ -67,8 +67,8 @@ pub fn load_assemblies(
         asm_file
             .read_to_end(&mut asm_bytes)
             .expect("ERROR: Could not load the assembly file!");
-        let asm: cilly::Assembly =
-            postcard::from_bytes(&asm_bytes).expect("ERROR:Could not decode the assembly file!");
+        let asm = cilly::container::decode(&asm_bytes)
+            .unwrap_or_else(|err| panic!("ERROR: Could not load the assembly {asm_path}: {err}"));
 
         final_assembly = final_assembly.link(asm);
     }
@@ -390,20 +390,6 @@ fn main() {
             }
         }),
     );
-    overrides.insert(
-        final_assembly.alloc_string("__cxa_thread_atexit_impl"),
-        Box::new(|_, asm| {
-            let blocks = vec![BasicBlock::new(
-                vec![asm.alloc_root(CILRoot::VoidRet)],
-                0,
-                None,
-            )];
-            MethodImpl::MethodBody {
-                blocks,
-                locals: vec![],
-            }
-        }),
-    );
     cilly::builtins::select::generate_int_selects(&mut final_assembly, &mut overrides);
     cilly::builtins::insert_swap_at_generic(&mut final_assembly, &mut overrides);
     cilly::builtins::insert_bounds_check(&mut final_assembly, &mut overrides);
@@ -416,6 +402,7 @@ fn main() {
     cilly::builtins::int128::i128_mul_ovf_check(&mut final_assembly, &mut overrides);
     cilly::builtins::int128::u128_mul_ovf_check(&mut final_assembly, &mut overrides);
     cilly::builtins::f16::generate_f16_ops(&mut final_assembly, &mut overrides, *C_MODE);
+    cilly::builtins::f128::generate_f128_ops(&mut final_assembly, &mut overrides, *C_MODE);
     cilly::builtins::atomics::generate_all_atomics(&mut final_assembly, &mut overrides);
     cilly::builtins::transmute(&mut final_assembly, &mut overrides);
     cilly::builtins::create_slice(&mut final_assembly, &mut overrides);
@@ -444,6 +431,7 @@ fn main() {
             "pthread_key_delete",
             "pthread_join",
             "pthread_setspecific",
+            "__cxa_thread_atexit_impl",
             "ldexpf",
             "ldexp",
         ] {
@@ -500,6 +488,14 @@ fn main() {
     final_assembly.patch_missing_methods(&externs, &modifies_errno, &overrides);
 
     add_mandatory_statics(&mut final_assembly);
+    // C uses real linker sections, .NET needs them emulated.
+    if !*C_MODE {
+        final_assembly.lower_link_sections();
+    }
+    // C and .NET on Windows support calling C-variadic functions directly, everywhere else each call gets a fixed signature.
+    if !*C_MODE && !*VARARG_CALLS {
+        final_assembly.lower_vararg_calls();
+    }
 
     if *DEAD_CODE_ELIMINATION {
         println!("Eliminating dead code");
@@ -507,6 +503,15 @@ fn main() {
     }
     let mut fuel = final_assembly.fuel_from_env().fraction(0.25);
     final_assembly.opt(&mut fuel);
+    if *MERGE_IDENTICAL_TYPES {
+        println!(
+            "Merged {} identical value types",
+            final_assembly.merge_identical_valuetypes()
+        );
+    }
+    if *FOLD_IDENTICAL_METHODS {
+        println!("{}", final_assembly.fold_identical_methods());
+    }
     final_assembly.eliminate_dead_code();
     final_assembly.fix_aligement();
     final_assembly
@@ -546,10 +551,14 @@ fn main() {
             );
         }
     } else {
-        final_assembly.export(
-            &path,
-            cilly::il_exporter::ILExporter::new(*ILASM_FLAVOUR, is_lib),
-        );
+        if *DIRECT_PE {
+            final_assembly.export(&path, cilly::pe_exporter::PEExporter::new(is_lib));
+        } else {
+            final_assembly.export(
+                &path,
+                cilly::il_exporter::ILExporter::new(*ILASM_FLAVOUR, is_lib),
+            );
+        }
         if cargo_support {
             let bootstrap = bootstrap_source(
                 &path.with_extension("exe"),
@@ -593,16 +602,15 @@ fn bootstrap_source(fpath: &Path, output_file_path: &str, jumpstart_cmd: &str) -
         jumpstart_cmd = jumpstart_cmd,
         exec_file = fpath.file_name().unwrap().to_string_lossy(),
         has_native_companion = *NATIVE_PASSTROUGH,
-        has_pdb = match *ILASM_FLAVOUR {
-            IlasmFlavour::Clasic => false,
-            IlasmFlavour::Modern => true,
-        },
-        pdb_file = match *ILASM_FLAVOUR {
-            IlasmFlavour::Clasic => String::new(),
-            IlasmFlavour::Modern => format!(
+        has_pdb = *DIRECT_PE || *ILASM_FLAVOUR == IlasmFlavour::Modern,
+        // `DIRECT_PE` is checked first, so that ilasm is not required when the PE file is written directly.
+        pdb_file = if *DIRECT_PE || *ILASM_FLAVOUR == IlasmFlavour::Modern {
+            format!(
                 "{output_file_path}.pdb",
                 output_file_path = fpath.file_stem().unwrap().to_string_lossy()
-            ),
+            )
+        } else {
+            String::new()
         },
         native_companion_file = if *NATIVE_PASSTROUGH {
             format!(
@@ -617,6 +625,15 @@ fn bootstrap_source(fpath: &Path, output_file_path: &str, jumpstart_cmd: &str) -
 config!(NATIVE_PASSTROUGH, bool, false);
 config!(ABORT_ON_ERROR, bool, false);
 config!(C_MODE, bool, false);
+config!(DIRECT_PE, bool, false);
 config!(NO_UNWIND, bool, false);
 config!(JAVA_MODE, bool, false);
+config!(MERGE_IDENTICAL_TYPES, bool, true);
+config!(FOLD_IDENTICAL_METHODS, bool, true);
+config!(
+    VARARG_CALLS,
+    bool,
+    cfg!(target_os = "windows"),
+    "Emit `call vararg` for calls to C-variadic functions. CoreCLR only supports this on Windows."
+);
 config!(PANIC_MANAGED_BT, bool, false);
@@ -35,7 +35,8 @@ fn main() {
         .read_to_end(&mut asm_bytes)
         .expect("ERROR: Could not load the assembly file!");
     println!("Loading an assembly");
-    let asm: Assembly = postcard::from_bytes(&asm_bytes).unwrap();
+    let asm: Assembly = cilly::container::decode(&asm_bytes)
+        .unwrap_or_else(|err| panic!("ERROR: Could not load the assembly: {err}"));
     let fail = std::env::args().nth(2).expect("no fail string");
     let args = std::env::args().nth(3).expect("no dotnet args");
     let args: Vec<&str> = args.split_whitespace().collect();
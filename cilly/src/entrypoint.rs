@@ -5,8 +5,10 @@ use crate::{
     {cilnode::MethodKind, Assembly, Int, MethodRef},
 };
 
-/// Creates a wrapper method around entypoint represented by `Interned<MethodRef>`
-pub fn wrapper(entrypoint: MethodRef, asm: &mut Assembly) -> MethodDefIdx {
+/// Creates a wrapper method around entypoint represented by `Interned<MethodRef>`.
+/// If `call_fini` is set, the wrapper runs the [`crate::asm::USER_FINI`] after the entrypoint returns. Targets, which
+/// register it with `atexit` instead(C), should not set it, since it would run twice.
+pub fn wrapper(entrypoint: MethodRef, asm: &mut Assembly, call_fini: bool) -> MethodDefIdx {
     let uint8_ptr = asm.nptr(Type::Int(Int::U8));
     let uint8_ptr_idx = asm.alloc_type(uint8_ptr);
     let uint8_ptr_ptr = asm.nptr(uint8_ptr);
@@ -18,6 +20,10 @@ pub fn wrapper(entrypoint: MethodRef, asm: &mut Assembly) -> MethodDefIdx {
     // TODO: check if user_init is used, and only call that method in wrapper if so.
     // This is just a hack that forces user_init to be always present, even when unneded.
     asm.user_init();
+    let fini = call_fini.then(|| {
+        let user_fini = asm.user_fini();
+        asm.alloc_root(CILRoot::call(user_fini.0, []))
+    });
     if entry_sig.inputs() == [Type::Int(Int::ISize), uint8_ptr_ptr]
         && entry_sig.output() == &Type::Int(Int::ISize)
     {
@@ -55,16 +61,14 @@ pub fn wrapper(entrypoint: MethodRef, asm: &mut Assembly) -> MethodDefIdx {
 
         let call_main = CILNode::call(entrypoint, args);
         let call_main = asm.alloc_node(call_main);
-        let blocks = vec![BasicBlock::new(
-            vec![
-                asm.alloc_root(CILRoot::call(tcctor, [])),
-                asm.alloc_root(CILRoot::call(static_init, [])),
-                asm.alloc_root(CILRoot::Pop(call_main)),
-                asm.alloc_root(CILRoot::VoidRet),
-            ],
-            2,
-            None,
-        )];
+        let mut roots = vec![
+            asm.alloc_root(CILRoot::call(tcctor, [])),
+            asm.alloc_root(CILRoot::call(static_init, [])),
+            asm.alloc_root(CILRoot::Pop(call_main)),
+        ];
+        roots.extend(fini);
+        roots.push(asm.alloc_root(CILRoot::VoidRet));
+        let blocks = vec![BasicBlock::new(roots, 2, None)];
         let mimpl = MethodImpl::MethodBody {
             blocks,
             locals: vec![],
@@ -98,17 +102,15 @@ pub fn wrapper(entrypoint: MethodRef, asm: &mut Assembly) -> MethodDefIdx {
             vec![].into(),
         );
         let static_init = asm.alloc_methodref(static_init);
-        let blocks = vec![BasicBlock::new(
-            vec![
-                asm.alloc_root(CILRoot::call(tcctor, [])),
-                asm.alloc_root(CILRoot::call(static_init, [])),
-                asm.alloc_root(CILRoot::call(entrypoint, [])),
-                //CILRoot::debug(&format!("Preparing to execute the main program.")).into(),
-                asm.alloc_root(CILRoot::VoidRet),
-            ],
-            0,
-            None,
-        )];
+        let mut roots = vec![
+            asm.alloc_root(CILRoot::call(tcctor, [])),
+            asm.alloc_root(CILRoot::call(static_init, [])),
+            asm.alloc_root(CILRoot::call(entrypoint, [])),
+            //CILRoot::debug(&format!("Preparing to execute the main program.")).into(),
+        ];
+        roots.extend(fini);
+        roots.push(asm.alloc_root(CILRoot::VoidRet));
+        let blocks = vec![BasicBlock::new(roots, 0, None)];
         let method = MethodDef::new(
             Access::Extern,
             main_module,
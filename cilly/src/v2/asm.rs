@@ -2,8 +2,8 @@ use super::{
     bimap::{BiMap, BiMapIndex, Interned, IntoBiMapIndex},
     cilnode::{BinOp, ExtendKind, IsPure, MethodKind, PtrCastRes, UnOp},
     class::{ClassDefIdx, LayoutError, StaticFieldDef},
-    opt::{OptFuel, SideEffectInfoCache},
-    Access, CILNode, CILRoot, ClassDef, ClassRef, Const, Exporter, FieldDesc, FnSig, Int,
+    opt::{OptFuel, PassManager, SideEffectInfoCache},
+    Access, AsmPiece, CILNode, CILRoot, ClassDef, ClassRef, Const, Exporter, FieldDesc, FnSig, Int,
     IntoAsmIndex, MethodDef, MethodDefIdx, MethodRef, StaticFieldDesc, Type,
 };
 use crate::{config, utilis::assert_unique, IString};
@@ -35,6 +35,12 @@ pub struct Assembly {
     sections: FxHashMap<String, Vec<u8>>,
     /// A list of all buffers within this assembly.
     pub(crate) const_data: BiMap<Box<[u8]>>,
+    /// Assembly emitted at file scope(`global_asm!`). Only supported when exporting C.
+    global_asm: Vec<Box<[AsmPiece]>>,
+    /// Statics placed in named linker sections(`#[link_section]`), in the order they were added.
+    link_sections: FxHashMap<Interned<IString>, Vec<Interned<StaticFieldDesc>>>,
+    /// Statics which must be kept, even if nothing references them(`#[used]`).
+    used_statics: Vec<Interned<StaticFieldDesc>>,
 }
 impl Index<Interned<IString>> for Assembly {
     type Output = str;
@@ -229,12 +235,21 @@ impl Assembly {
             "Could not return a methoddef, because a method def is already present."
         );
     }
-    /// Optimizes the assembly uitill all fuel is consumed, or no more progress can be made
+    /// Optimizes the assembly uitill all fuel is consumed, or no more progress can be made. The passes run are
+    /// selected by [`PassManager::from_env`].
     pub fn opt(&mut self, fuel: &mut OptFuel) {
+        let mut passes = PassManager::from_env();
+        self.opt_with(&mut passes, fuel);
+        if *super::opt::passes::OPT_PASS_STATS {
+            eprintln!("{passes}");
+        }
+    }
+    /// Optimizes the assembly using `passes`, uitill all fuel is consumed, or no more progress can be made.
+    pub fn opt_with(&mut self, passes: &mut PassManager, fuel: &mut OptFuel) {
         let mut cache = SideEffectInfoCache::default();
         while !fuel.exchausted() {
             let prev = fuel.clone();
-            self.opt_sigle_pass(fuel, &mut cache);
+            self.opt_sigle_pass(passes, fuel, &mut cache);
             // No fuel consumed, progress can't be made, break.
             if *fuel == prev {
                 break;
@@ -242,12 +257,18 @@ impl Assembly {
             //let _pass_min_cost: bool = fuel.consume(1);
         }
     }
-    /// Optimizes the assembly, cosuming some fuel. This performs a single optimization pass.
-    pub fn opt_sigle_pass(&mut self, fuel: &mut OptFuel, cache: &mut SideEffectInfoCache) {
+    /// Optimizes the assembly, cosuming some fuel. This runs the pipeline of `passes` over each method once.
+    pub fn opt_sigle_pass(
+        &mut self,
+        passes: &mut PassManager,
+        fuel: &mut OptFuel,
+        cache: &mut SideEffectInfoCache,
+    ) {
+        passes.analyze(self);
         let method_def_idxs: Box<[_]> = self.method_defs.keys().copied().collect();
         for method in method_def_idxs {
             let mut tmp_method = self.borrow_methoddef(method);
-            tmp_method.optimize(self, cache, fuel);
+            passes.run(&mut tmp_method, self, cache, fuel);
             tmp_method.remove_dead_blocks(self);
             self.return_methoddef(method, tmp_method);
             if fuel.exchausted() {
@@ -578,48 +599,24 @@ impl Assembly {
         MethodDefIdx::from_raw(ref_idx)
     }
     pub fn user_init(&mut self) -> MethodDefIdx {
-        let main_module = self.main_module();
-        let user_init = self.alloc_string(USER_INIT);
-        let ctor_sig = self.sig([], Type::Void);
-        let mref = MethodRef::new(
-            *main_module,
-            user_init,
-            ctor_sig,
-            MethodKind::Static,
-            vec![].into(),
-        );
-        let mref = self.alloc_methodref(mref);
-        if self.method_defs.contains_key(&MethodDefIdx::from_raw(mref)) {
-            MethodDefIdx::from_raw(mref)
-        } else {
-            let mimpl = MethodImpl::MethodBody {
-                blocks: vec![super::BasicBlock::new(
-                    vec![self.alloc_root(CILRoot::VoidRet)],
-                    0,
-                    None,
-                )],
-                locals: vec![],
-            };
-            let cctor_def = MethodDef::new(
-                Access::Extern,
-                main_module,
-                user_init,
-                ctor_sig,
-                MethodKind::Static,
-                mimpl,
-                vec![],
-            );
-            self.new_method(cctor_def)
-        }
+        self.special_method(USER_INIT)
+    }
+    /// Returns a reference to the user finalizer, which runs at exit(`.fini_array`).
+    pub fn user_fini(&mut self) -> MethodDefIdx {
+        self.special_method(USER_FINI)
     }
     /// Returns a reference to tht thread local constructor.
     pub fn tcctor(&mut self) -> MethodDefIdx {
+        self.special_method(TCCTOR)
+    }
+    /// Returns a reference to a special, argumentless method of the main module, creating an empty one if needed.
+    fn special_method(&mut self, name: &str) -> MethodDefIdx {
         let main_module = self.main_module();
-        let user_init = self.alloc_string(TCCTOR);
+        let name = self.alloc_string(name);
         let ctor_sig = self.sig([], Type::Void);
         let mref = MethodRef::new(
             *main_module,
-            user_init,
+            name,
             ctor_sig,
             MethodKind::Static,
             vec![].into(),
@@ -639,7 +636,7 @@ impl Assembly {
             let cctor_def = MethodDef::new(
                 Access::Extern,
                 main_module,
-                user_init,
+                name,
                 ctor_sig,
                 MethodKind::Static,
                 mimpl,
@@ -712,6 +709,9 @@ impl Assembly {
     pub fn has_tcctor(&self) -> bool {
         self.has_builtin(TCCTOR, [], Type::Void)
     }
+    pub fn has_user_fini(&self) -> bool {
+        self.has_builtin(USER_FINI, [], Type::Void)
+    }
     pub fn get_prealllocated_class_ref(&self, cref: ClassRef) -> Option<Interned<ClassRef>> {
         self.class_refs.1.get(&cref).copied()
     }
@@ -771,6 +771,22 @@ impl Assembly {
             last.roots_mut().insert(idx + last_root_idx, *root);
         }
     }
+    /// Adds new roots to the user finalizer. Roots are inserted before all the previously added ones, so they run in
+    /// reverse order, like the entries of `.fini_array`.
+    pub fn add_user_fini(&mut self, roots: &[Interned<CILRoot>]) {
+        let user_fini = self.user_fini();
+        let user_fini = self.method_defs.get_mut(&user_fini).unwrap();
+        let blocks = user_fini
+            .implementation_mut()
+            .blocks_mut()
+            .expect("EROROR: {USER_FINI} has no body.");
+        let first = blocks
+            .first_mut()
+            .expect("ERROR: {USER_FINI} has a body without blocks.");
+        for root in roots {
+            first.roots_mut().insert(0, *root);
+        }
+    }
     /// Adds new rooots to the thread local intiailzer .
     pub fn add_tcctor(&mut self, roots: &[Interned<CILRoot>]) {
         let user_init = self.tcctor();
@@ -814,9 +830,60 @@ impl Assembly {
             last.roots_mut().insert(idx + last_root_idx, *root);
         }
     }
-    /// Serializes and saves this assembly
+    /// Serializes and saves this assembly, wrapped in a [`super::container`].
     pub fn save_tmp<W: std::io::Write>(&self, w: &mut W) -> std::io::Result<()> {
-        w.write_all(&postcard::to_stdvec(&self).unwrap())
+        w.write_all(&super::container::encode(self))
+    }
+    /// Serializes every part of this assembly separately, in the order of [`super::container::SECTIONS`].
+    pub(crate) fn to_sections(&self) -> Vec<Vec<u8>> {
+        fn ser(val: &impl Serialize) -> Vec<u8> {
+            postcard::to_stdvec(val).expect("Could not serialize the assembly")
+        }
+        vec![
+            ser(&self.strings),
+            ser(&self.types),
+            ser(&self.class_refs),
+            ser(&self.class_defs),
+            ser(&self.nodes),
+            ser(&self.roots),
+            ser(&self.sigs),
+            ser(&self.method_refs),
+            ser(&self.fields),
+            ser(&self.statics),
+            ser(&self.method_defs),
+            ser(&self.sections),
+            ser(&self.const_data),
+            ser(&self.global_asm),
+            ser(&self.link_sections),
+            ser(&self.used_statics),
+        ]
+    }
+    /// Inverse of [`Self::to_sections`]. On failure, returns the index of the section which could not be decoded.
+    pub(crate) fn from_sections(sections: &[&[u8]]) -> Result<Self, (usize, postcard::Error)> {
+        fn de<'a, T: Deserialize<'a>>(
+            sections: &[&'a [u8]],
+            idx: usize,
+        ) -> Result<T, (usize, postcard::Error)> {
+            postcard::from_bytes(sections[idx]).map_err(|err| (idx, err))
+        }
+        Ok(Self {
+            strings: de(sections, 0)?,
+            types: de(sections, 1)?,
+            class_refs: de(sections, 2)?,
+            class_defs: de(sections, 3)?,
+            nodes: de(sections, 4)?,
+            roots: de(sections, 5)?,
+            sigs: de(sections, 6)?,
+            method_refs: de(sections, 7)?,
+            fields: de(sections, 8)?,
+            statics: de(sections, 9)?,
+            method_defs: de(sections, 10)?,
+            sections: de(sections, 11)?,
+            const_data: de(sections, 12)?,
+            global_asm: de(sections, 13)?,
+            link_sections: de(sections, 14)?,
+            used_statics: de(sections, 15)?,
+        })
     }
     pub(crate) fn rust_void(&mut self) -> ClassDefIdx {
         let rust_void = self.alloc_string("RustVoid");
@@ -888,6 +955,21 @@ impl Assembly {
     pub(crate) fn method_def_from_ref(&self, mref: Interned<MethodRef>) -> Option<&MethodDef> {
         self.method_defs.get(&MethodDefIdx::from_raw(mref))
     }
+    /// Returns the methods defined in this assembly, which are referenced by name from global assembly.
+    pub(crate) fn global_asm_methods(&self) -> FxHashSet<MethodDefIdx> {
+        self.global_asm
+            .iter()
+            .flatten()
+            .filter_map(|piece| match piece {
+                AsmPiece::Sym(sym) => match self.get_node(*sym) {
+                    CILNode::LdFtn(mref) => Some(MethodDefIdx::from_raw(*mref)),
+                    _ => None,
+                },
+                AsmPiece::Str(_) => None,
+            })
+            .filter(|def| self.method_defs.contains_key(def))
+            .collect()
+    }
     pub(crate) fn eliminate_dead_fns(&mut self, only_imports: bool) {
         // 1st. Collect all "extern" method definitons, since those are always alive.
         let mut previosly_ressurected: FxHashSet<MethodDefIdx> = self
@@ -896,6 +978,8 @@ impl Assembly {
             .filter(|(_, def)| def.access().is_extern())
             .map(|(idx, _)| *idx)
             .collect();
+        // Methods referenced by global assembly are always alive too.
+        previosly_ressurected.extend(self.global_asm_methods());
         let mut to_resurrect: FxHashSet<MethodDefIdx> = FxHashSet::default();
         let mut alive: FxHashSet<MethodDefIdx> = FxHashSet::default();
         // If only cleaning up imports, assume all non-import fns are alive.
@@ -978,6 +1062,19 @@ impl Assembly {
                 None
             }
         }));
+        // Used statics must be kept alive, together with the classes they belong to.
+        let used: Vec<_> = self
+            .used_statics()
+            .flat_map(|sfld| {
+                let sfld = self[sfld];
+                let tpe = sfld.tpe();
+                let mut crefs: Vec<_> = tpe.iter_class_refs(self).collect();
+                crefs.push(sfld.owner());
+                crefs
+            })
+            .filter_map(|cref| self.class_ref_to_def(cref))
+            .collect();
+        previosly_ressurected.extend(used);
         let rust_void = self.alloc_string("RustVoid");
         let rust_void = self.alloc_class_ref(ClassRef::new(rust_void, None, true, vec![].into()));
         if let Some(cref) = self.class_ref_to_def(rust_void) {
@@ -1177,6 +1274,24 @@ impl Assembly {
             }
         }
         assert_eq!(self.alloc_string(MAIN_MODULE), original_str);
+        for pieces in &other.global_asm {
+            let pieces = self.translate_asm_pieces(&other, pieces);
+            self.add_global_asm(pieces);
+        }
+        for (section, statics) in &other.link_sections {
+            let section = self.alloc_string(other[*section].as_ref());
+            self.declare_link_section(section);
+            for sfld in statics {
+                let sfld = self.translate_static_field(&other, other[*sfld]);
+                let sfld = self.alloc_sfld(sfld);
+                self.add_to_link_section(section, sfld);
+            }
+        }
+        for sfld in &other.used_statics {
+            let sfld = self.translate_static_field(&other, other[*sfld]);
+            let sfld = self.alloc_sfld(sfld);
+            self.add_used_static(sfld);
+        }
         self.sections.extend(other.sections);
         self
     }
@@ -1284,6 +1399,7 @@ impl Assembly {
                 | CILRoot::Break
                 | CILRoot::Nop
                 | CILRoot::Branch(_)
+                | CILRoot::Switch(_)
                 | CILRoot::SourceFileInfo { .. }
                 | CILRoot::SetField(_)
                 | CILRoot::StInd(_)
@@ -1294,6 +1410,9 @@ impl Assembly {
                 | CILRoot::ReThrow
                 | CILRoot::SetStaticField { .. }
                 | CILRoot::CpObj { .. }
+                | CILRoot::InlineAsm(_)
+                | CILRoot::VaStart(_)
+                | CILRoot::VaArg(_)
                 | CILRoot::Unreachable(_) => None,
             }))
             .collect();
@@ -1421,6 +1540,82 @@ impl Assembly {
         })
     }
 
+    /// Merges value types with identical layouts, which only differ by name(and the names of their fields), into one.
+    /// All types, field descriptors and method references using the merged types are rewritten to use the type they
+    /// were merged into. Returns the number of removed class definitons.
+    ///
+    /// Only private value types without methods and static fields are merged. Public and extern types are visible
+    /// outside of this assembly, or referred to by name(e.g. by builtins and the exporters), so they are never removed.
+    /// Since merging some types may make types containing them identical, this is repeated until no more types can be
+    /// merged.
+    pub fn merge_identical_valuetypes(&mut self) -> usize {
+        /// Value types with equal keys have the same layout.
+        type LayoutKey = (
+            Option<std::num::NonZeroU32>,
+            Option<std::num::NonZeroU32>,
+            bool,
+            Vec<(Type, Option<u32>)>,
+        );
+        let mut removed = 0;
+        loop {
+            let mut groups: FxHashMap<LayoutKey, Vec<ClassDefIdx>> = FxHashMap::default();
+            for (idx, def) in &self.class_defs {
+                if !def.is_valuetype()
+                    || *def.access() != Access::Private
+                    || def.generics() != 0
+                    || !def.methods().is_empty()
+                    || !def.static_fields().is_empty()
+                {
+                    continue;
+                }
+                let key = (
+                    def.explict_size(),
+                    def.align(),
+                    def.has_nonveralpping_layout(),
+                    def.fields()
+                        .iter()
+                        .map(|(tpe, _, offset)| (*tpe, *offset))
+                        .collect(),
+                );
+                groups.entry(key).or_default().push(*idx);
+            }
+            // Maps the merged types to the type they are merged into, and the names of their fields to the names of
+            // fields of that type.
+            let mut class_refs = FxHashMap::default();
+            let mut field_names = FxHashMap::default();
+            for mut group in groups.into_values().filter(|group| group.len() > 1) {
+                // Sort by name, to make this deterministic.
+                group.sort_by_key(|def| &self[self[*def].name()]);
+                let (target, merged) = group.split_first().unwrap();
+                for merged in merged {
+                    let def = self.class_defs.remove(merged).unwrap();
+                    for ((_, name, _), (_, target_name, _)) in
+                        def.fields().iter().zip(self[*target].fields())
+                    {
+                        field_names.insert((**merged, *name), *target_name);
+                    }
+                    class_refs.insert(self[**merged].clone(), self[**target].clone());
+                    removed += 1;
+                }
+            }
+            if class_refs.is_empty() {
+                return removed;
+            }
+            // Renaming the fields and classes only changes the values stored in this assembly, not the interned
+            // indices pointing to them. Re-linking the assembly re-interns everything, rewriting all references.
+            self.fields.map_values(|field| {
+                if let Some(name) = field_names.get(&(field.owner(), field.name())) {
+                    *field = FieldDesc::new(field.owner(), *name, field.tpe());
+                }
+            });
+            self.class_refs.map_values(|cref| {
+                if let Some(target) = class_refs.get(cref) {
+                    *cref = target.clone();
+                }
+            });
+            *self = Self::default().link(std::mem::take(self));
+        }
+    }
     fn link_gc(self) -> Self {
         let mut clone = self.clone();
         clone = clone.link(self);
@@ -1457,9 +1652,96 @@ impl Assembly {
         self.sections.insert(arg.into(), packed_metadata.into());
     }
 
+    /// Adds a block of assembly, emitted at file scope. Identical blocks are only emitted once.
+    pub fn add_global_asm(&mut self, pieces: impl Into<Box<[AsmPiece]>>) {
+        let pieces = pieces.into();
+        if !self.global_asm.contains(&pieces) {
+            self.global_asm.push(pieces);
+        }
+    }
+    /// Returns all the blocks of assembly emitted at file scope.
+    pub fn global_asm(&self) -> &[Box<[AsmPiece]>] {
+        &self.global_asm
+    }
+    /// Declares the linker section `section`, without placing anything in it.
+    pub fn declare_link_section(&mut self, section: Interned<IString>) {
+        self.link_sections.entry(section).or_default();
+    }
+    /// Places a static in the linker section `section`. Statics in a section are kept alive, and laid out in the order
+    /// they were added.
+    pub fn add_to_link_section(
+        &mut self,
+        section: Interned<IString>,
+        sfld: Interned<StaticFieldDesc>,
+    ) {
+        let statics = self.link_sections.entry(section).or_default();
+        if !statics.contains(&sfld) {
+            statics.push(sfld);
+        }
+    }
+    /// Returns all the linker sections, and the statics placed in them.
+    pub fn link_sections(
+        &self,
+    ) -> impl Iterator<Item = (Interned<IString>, &[Interned<StaticFieldDesc>])> {
+        self.link_sections
+            .iter()
+            .map(|(section, statics)| (*section, statics.as_slice()))
+    }
+    /// Returns the section `sfld` is placed in, if any.
+    pub fn link_section_of(&self, sfld: Interned<StaticFieldDesc>) -> Option<Interned<IString>> {
+        self.link_sections
+            .iter()
+            .find(|(_, statics)| statics.contains(&sfld))
+            .map(|(section, _)| *section)
+    }
+    /// Marks a static as used(`#[used]`), keeping it alive even if nothing references it.
+    pub fn add_used_static(&mut self, sfld: Interned<StaticFieldDesc>) {
+        if !self.used_statics.contains(&sfld) {
+            self.used_statics.push(sfld);
+        }
+    }
+    /// Checks if `sfld` must be kept alive, either because it is `#[used]`, or placed in a linker section.
+    pub fn is_used_static(&self, sfld: Interned<StaticFieldDesc>) -> bool {
+        self.used_statics.contains(&sfld) || self.link_section_of(sfld).is_some()
+    }
+    /// Returns all the statics which must be kept alive, even if nothing references them.
+    pub fn used_statics(&self) -> impl Iterator<Item = Interned<StaticFieldDesc>> + '_ {
+        self.used_statics
+            .iter()
+            .chain(self.link_sections.values().flatten())
+            .copied()
+    }
+    /// Returns a static holding the address of the start(`__start_{section}`) or end(`__stop_{section}`) of a linker
+    /// section.
+    pub fn link_section_bound(
+        &mut self,
+        section: Interned<IString>,
+        end: bool,
+    ) -> Interned<StaticFieldDesc> {
+        self.declare_link_section(section);
+        let name = link_section_bound_name(&self[section], end);
+        let main_module = self.main_module();
+        let tpe = self.nptr(Type::Int(Int::U8));
+        self.add_static(tpe, name, false, main_module, None, false)
+    }
+    /// If the static named `name` holds the bound of a linker section, returns that section, and if it is its end.
+    pub fn link_section_bound_of(&self, name: &str) -> Option<(Interned<IString>, bool)> {
+        let (end, section) = if let Some(section) = name.strip_prefix(LINK_SECTION_START) {
+            (false, section)
+        } else {
+            (true, name.strip_prefix(LINK_SECTION_STOP)?)
+        };
+        let section = self.get_prealllocated_string(section)?;
+        self.link_sections
+            .contains_key(&section)
+            .then_some((section, end))
+    }
     pub(crate) fn get_section(&self, arg: &str) -> Option<&Vec<u8>> {
         self.sections.get(arg)
     }
+    pub(crate) fn sections(&self) -> impl Iterator<Item = (&String, &Vec<u8>)> {
+        self.sections.iter()
+    }
 
     pub(crate) fn guaranted_align(&self) -> u8 {
         *GUARANTEED_ALIGN
@@ -1562,6 +1844,19 @@ pub const CCTOR: &str = ".cctor";
 pub const TCCTOR: &str = ".tcctor";
 /// An initializer, which runs after the [`CCTOR`] and [`TCCTOR`], but before the [`ENTRYPOINT`]. Meant to execute user code, is roughly equivalnt to `.init_array` on GNU.
 pub const USER_INIT: &str = "static_init";
+/// A finalizer, which runs when the program exits. Meant to execute user code, is roughly equivalnt to `.fini_array` on GNU.
+pub const USER_FINI: &str = "static_fini";
+/// Prefix of the statics holding the start of a linker section.
+const LINK_SECTION_START: &str = "link_section_start_";
+/// Prefix of the statics holding the end of a linker section.
+const LINK_SECTION_STOP: &str = "link_section_stop_";
+fn link_section_bound_name(section: &str, end: bool) -> String {
+    if end {
+        format!("{LINK_SECTION_STOP}{section}")
+    } else {
+        format!("{LINK_SECTION_START}{section}")
+    }
+}
 /// The entrypoint of a program
 pub const ENTRYPOINT: &str = "entrypoint";
 /// Main class of this module
@@ -1875,4 +2170,80 @@ fn link() {
     #[cfg(not(miri))]
     asm.export("/tmp/link_test.exe", ILExporter::new(*ILASM_FLAVOUR, false));
 }
+#[test]
+fn merge_identical_valuetypes() {
+    let src = r#"
+(classdef private valuetype "Tuple0"
+  (field i32 "Item1")
+  (field i64 "Item2"))
+(classdef private valuetype "Closure1"
+  (field i32 "f0")
+  (field i64 "f1"))
+(classdef private valuetype "Wrapper0"
+  (field (valuetype "Tuple0") "inner"))
+(classdef private valuetype "Wrapper1"
+  (field (valuetype "Closure1") "inner"))
+(classdef public class "MainModule"
+  (method public static "sum" (sig (i32) i64) (args "val")
+    (locals (local (valuetype "Wrapper1")) (local (valuetype "Wrapper0")))
+    (block 0
+      (stfld (field (valuetype "Closure1") "f0" i32) (ldflda (field (valuetype "Wrapper1") "inner" (valuetype "Closure1")) (ldloca 0)) (ldarg 0))
+      (stfld (field (valuetype "Closure1") "f1" i64) (ldflda (field (valuetype "Wrapper1") "inner" (valuetype "Closure1")) (ldloca 0)) (const i64 5))
+      (stfld (field (valuetype "Tuple0") "Item1" i32) (ldflda (field (valuetype "Wrapper0") "inner" (valuetype "Tuple0")) (ldloca 1)) (const i32 7))
+      (ret (add
+        (conv i64 sext (ldfld (field (valuetype "Closure1") "f0" i32) (ldflda (field (valuetype "Wrapper1") "inner" (valuetype "Closure1")) (ldloca 0))))
+        (ldfld (field (valuetype "Closure1") "f1" i64) (ldflda (field (valuetype "Wrapper1") "inner" (valuetype "Closure1")) (ldloca 0))))))))
+"#;
+    let mut asm = super::cillytext::parse(src).unwrap();
+    // `Tuple0` is merged into `Closure1`, which makes `Wrapper1` identical to `Wrapper0`.
+    assert_eq!(asm.merge_identical_valuetypes(), 2);
+    let merged = super::cillytext::print(&asm);
+    assert!(!merged.contains("Tuple0"), "{merged}");
+    assert!(!merged.contains("Item1"), "{merged}");
+    assert!(!merged.contains("Wrapper1"), "{merged}");
+    let sum = asm
+        .method_defs()
+        .keys()
+        .copied()
+        .find(|def| &asm[asm[*def].name()] == "sum")
+        .unwrap();
+    let mut method = asm.method_def(sum).clone();
+    method.typecheck(&mut asm).unwrap();
+    let res = super::interpreter::Interpreter::new(&asm).call_method(
+        *sum,
+        vec![super::interpreter::Value::int(super::Int::I32, 3)],
+    );
+    assert_eq!(res, Ok(super::interpreter::Value::int(super::Int::I64, 8)));
+}
+#[test]
+fn merge_identical_valuetypes_keeps_public() {
+    // Public types, like `f128`, may be referred to by name, so only the private ones are merged.
+    let src = r#"
+(classdef private valuetype "Pair"
+  (size 16)
+  (align 16)
+  (field u64 "a" 0)
+  (field u64 "b" 8))
+(classdef public valuetype "f128"
+  (size 16)
+  (align 16)
+  (field u64 "low" 0)
+  (field u64 "high" 8))
+(classdef private valuetype "Duo"
+  (size 16)
+  (align 16)
+  (field u64 "x" 0)
+  (field u64 "y" 8))
+(classdef public valuetype "RustVoid")
+(classdef public valuetype "Empty")
+"#;
+    let mut asm = super::cillytext::parse(src).unwrap();
+    assert_eq!(asm.merge_identical_valuetypes(), 1);
+    let merged = super::cillytext::print(&asm);
+    assert!(merged.contains("\"f128\""), "{merged}");
+    assert!(merged.contains("\"RustVoid\""), "{merged}");
+    assert!(merged.contains("\"Empty\""), "{merged}");
+    assert!(merged.contains("\"Duo\""), "{merged}");
+    assert!(!merged.contains("Pair"), "{merged}");
+}
 config! {LINKER_RECOVER,bool,false}
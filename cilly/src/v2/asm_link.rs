@@ -1,7 +1,8 @@
 use super::{
-    asm::{CCTOR, TCCTOR, USER_INIT},
+    asm::{CCTOR, TCCTOR, USER_FINI, USER_INIT},
     bimap::Interned,
     class::{ClassDefIdx, StaticFieldDef},
+    inline_asm::{AsmInput, AsmOutput, AsmPiece, InlineAsm},
     Assembly, BasicBlock, CILNode, CILRoot, ClassDef, ClassRef, Const, FieldDesc, FnSig, MethodDef,
     MethodDefIdx, MethodRef, StaticFieldDesc, Type,
 };
@@ -59,13 +60,17 @@ impl Assembly {
         self.alloc_class_ref(ClassRef::new(name, asm, cref.is_valuetype(), generics))
     }
     pub(crate) fn translate_sig(&mut self, source: &Assembly, sig: &FnSig) -> FnSig {
-        FnSig::new(
-            sig.inputs()
-                .iter()
-                .map(|tpe| self.translate_type(source, *tpe))
-                .collect::<Box<_>>(),
-            self.translate_type(source, *sig.output()),
-        )
+        let inputs = sig
+            .inputs()
+            .iter()
+            .map(|tpe| self.translate_type(source, *tpe))
+            .collect::<Box<_>>();
+        let output = self.translate_type(source, *sig.output());
+        if sig.is_variadic() {
+            FnSig::new_variadic(inputs, output)
+        } else {
+            FnSig::new(inputs, output)
+        }
     }
     pub(crate) fn translate_field(&mut self, source: &Assembly, field: FieldDesc) -> FieldDesc {
         let name = self.alloc_string(source[field.name()].as_ref());
@@ -509,6 +514,12 @@ impl Assembly {
             CILRoot::ExitSpecialRegion { target, source } => {
                 CILRoot::ExitSpecialRegion { target, source }
             }
+            CILRoot::Switch(switch) => {
+                let (value, targets, default) = *switch;
+                let value = self.translate_node(source, source.get_node(value).clone());
+                let value = self.alloc_node(value);
+                CILRoot::Switch(Box::new((value, targets, default)))
+            }
             CILRoot::SetStaticField { field, val } => {
                 let val = self.translate_node(source, source.get_node(val).clone());
                 let val = self.alloc_node(val);
@@ -516,6 +527,89 @@ impl Assembly {
                 let field = self.alloc_sfld(field);
                 CILRoot::SetStaticField { field, val }
             }
+            CILRoot::InlineAsm(inline_asm) => {
+                CILRoot::InlineAsm(Box::new(self.translate_inline_asm(source, &inline_asm)))
+            }
+            CILRoot::VaStart(list) => {
+                let list = self.translate_node(source, source.get_node(list).clone());
+                CILRoot::VaStart(self.alloc_node(list))
+            }
+            CILRoot::VaArg(info) => {
+                let (list, dst, tpe) = *info;
+                let list = self.translate_node(source, source.get_node(list).clone());
+                let list = self.alloc_node(list);
+                let dst = self.translate_node(source, source.get_node(dst).clone());
+                let dst = self.alloc_node(dst);
+                let tpe = self.translate_type(source, source[tpe]);
+                let tpe = self.alloc_type(tpe);
+                CILRoot::VaArg(Box::new((list, dst, tpe)))
+            }
+        }
+    }
+    pub(crate) fn translate_asm_pieces(
+        &mut self,
+        source: &Assembly,
+        pieces: &[AsmPiece],
+    ) -> Box<[AsmPiece]> {
+        pieces
+            .iter()
+            .map(|piece| match piece {
+                AsmPiece::Str(str) => AsmPiece::Str(self.alloc_string(&source[*str])),
+                AsmPiece::Sym(sym) => {
+                    let sym = self.translate_node(source, source.get_node(*sym).clone());
+                    AsmPiece::Sym(self.alloc_node(sym))
+                }
+            })
+            .collect()
+    }
+    pub(crate) fn translate_inline_asm(
+        &mut self,
+        source: &Assembly,
+        inline_asm: &InlineAsm,
+    ) -> InlineAsm {
+        let translate_node = |asm: &mut Self, node: Interned<CILNode>| {
+            let node = asm.translate_node(source, source.get_node(node).clone());
+            asm.alloc_node(node)
+        };
+        let template = self.translate_asm_pieces(source, &inline_asm.template);
+        let outputs = inline_asm
+            .outputs
+            .iter()
+            .map(|output| {
+                let tpe = self.translate_type(source, source[output.tpe]);
+                AsmOutput {
+                    constraint: self.alloc_string(&source[output.constraint]),
+                    reg: output.reg.map(|reg| self.alloc_string(&source[reg])),
+                    tpe: self.alloc_type(tpe),
+                    init: output.init.map(|init| translate_node(self, init)),
+                    dst: output.dst.map(|dst| translate_node(self, dst)),
+                }
+            })
+            .collect();
+        let inputs = inline_asm
+            .inputs
+            .iter()
+            .map(|input| {
+                let tpe = self.translate_type(source, source[input.tpe]);
+                AsmInput {
+                    constraint: self.alloc_string(&source[input.constraint]),
+                    reg: input.reg.map(|reg| self.alloc_string(&source[reg])),
+                    tpe: self.alloc_type(tpe),
+                    val: translate_node(self, input.val),
+                }
+            })
+            .collect();
+        let clobbers = inline_asm
+            .clobbers
+            .iter()
+            .map(|clobber| self.alloc_string(&source[*clobber]))
+            .collect();
+        InlineAsm {
+            template,
+            outputs,
+            inputs,
+            clobbers,
+            is_volatile: inline_asm.is_volatile,
         }
     }
     pub(crate) fn translate_block(&mut self, source: &Assembly, block: &BasicBlock) -> BasicBlock {
@@ -689,4 +783,4 @@ impl Assembly {
         translated
     }
 }
-const SPECIAL_METHOD_NAMES: &[&str] = &[CCTOR, TCCTOR, USER_INIT];
+const SPECIAL_METHOD_NAMES: &[&str] = &[CCTOR, TCCTOR, USER_INIT, USER_FINI];
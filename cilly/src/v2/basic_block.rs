@@ -18,22 +18,26 @@ impl BasicBlock {
         &'block self,
         asm: &'asm Assembly,
     ) -> impl Iterator<Item = BlockId> + 'block {
-        self.roots().iter().filter_map(|root| {
-            match asm.get_root(*root) {
+        self.roots()
+            .iter()
+            .flat_map(|root| match asm.get_root(*root) {
                 CILRoot::Branch(info) => {
                     let (target, sub_target, _) = info.as_ref();
                     //Some(*sub_target)
                     //(eprintln!("{target} {sub_target}");
-                    if *sub_target == 0 {
-                        Some(*target)
-                    } else {
-                        Some(*sub_target)
-                    }
+                    vec![opt::blockid_from_jump(*target, *sub_target)]
                 }
-                CILRoot::ExitSpecialRegion { target, .. } => Some(*target),
-                _ => None,
-            }
-        })
+                CILRoot::Switch(switch) => {
+                    let (_, targets, default) = switch.as_ref();
+                    targets
+                        .iter()
+                        .chain(std::iter::once(default))
+                        .map(|(target, sub_target)| opt::blockid_from_jump(*target, *sub_target))
+                        .collect()
+                }
+                CILRoot::ExitSpecialRegion { target, .. } => vec![*target],
+                _ => vec![],
+            })
     }
     /// Creates a new block with a given unique id, roots, and an optional list of handler blocks.
     /// The handler ought not have a handler of its own, and the roots should end with a diverging root.
@@ -0,0 +1,208 @@
+use super::super::{
+    cilnode::{MethodKind, PtrCastRes},
+    Access, Assembly, BasicBlock, CILNode, CILRoot, ClassDef, ClassRef, FieldDesc, FnSig, Interned,
+    MethodDef, MethodImpl, MethodRef, Type,
+};
+/// Prefix of the names of closure holder classes.
+pub const CLOSURE_HOLDER: &str = "RustcCLRInteropClosureHolder";
+/// Returns a class holding a Rust closure with the signature `sig`, defining it if it does not exist yet.
+///
+/// The holder stores 3 pointers: `data`(the boxed closure), `invoke`(a function taking `data`, followed by the arguments
+/// in `sig`) and `drop`(a function taking `data`, which drops the closure). Its `Invoke` method calls `invoke`, and can be
+/// wrapped in a delegate. `drop` is called by the finalizer of the holder, once it is collected by the GC.
+pub fn closure_holder(sig: Interned<FnSig>, asm: &mut Assembly) -> Interned<ClassRef> {
+    let mangled = Type::FnPtr(sig).mangle(asm).replace('.', "_");
+    let name = asm.alloc_string(format!("{CLOSURE_HOLDER}_{mangled}"));
+    let holder = asm.alloc_class_ref(ClassRef::new(name, None, false, [].into()));
+    if asm.class_ref_to_def(holder).is_some() {
+        return holder;
+    }
+    let void_ptr = asm.nptr(Type::Void);
+    let object = ClassRef::object(asm);
+    let data = asm.alloc_string("data");
+    let invoke = asm.alloc_string("invoke");
+    let drop = asm.alloc_string("drop");
+    let holder_def = asm
+        .class_def(ClassDef::new(
+            name,
+            false,
+            0,
+            Some(object),
+            vec![
+                (void_ptr, invoke, None),
+                (void_ptr, drop, None),
+                (void_ptr, data, None),
+            ],
+            vec![],
+            Access::Public,
+            None,
+            None,
+            true,
+        ))
+        .unwrap();
+    let data_field = asm.alloc_field(FieldDesc::new(holder, data, void_ptr));
+    let invoke_field = asm.alloc_field(FieldDesc::new(holder, invoke, void_ptr));
+    let drop_field = asm.alloc_field(FieldDesc::new(holder, drop, void_ptr));
+    let this = asm.alloc_node(CILNode::LdArg(0));
+    let ret = asm.alloc_root(CILRoot::VoidRet);
+    // .ctor(invoke, drop, data)
+    let sets: Vec<_> = [invoke_field, drop_field, data_field]
+        .into_iter()
+        .zip(1..)
+        .map(|(field, arg)| {
+            let arg = asm.alloc_node(CILNode::LdArg(arg));
+            asm.alloc_root(CILRoot::SetField(Box::new((field, this, arg))))
+        })
+        .chain(std::iter::once(ret))
+        .collect();
+    let ctor_sig = asm.sig(
+        [Type::ClassRef(holder), void_ptr, void_ptr, void_ptr],
+        Type::Void,
+    );
+    let ctor = asm.alloc_string(".ctor");
+    asm.new_method(MethodDef::new(
+        Access::Public,
+        holder_def,
+        ctor,
+        ctor_sig,
+        MethodKind::Constructor,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(sets, 0, None)],
+            locals: vec![],
+        },
+        vec![None, Some(invoke), Some(drop), Some(data)],
+    ));
+    let data_node = asm.alloc_node(CILNode::LdField {
+        addr: this,
+        field: data_field,
+    });
+    // Invoke(args) => invoke(data, args)
+    let inputs = asm[sig].inputs().to_vec();
+    let output = *asm[sig].output();
+    let thunk_sig = asm.sig(
+        std::iter::once(void_ptr)
+            .chain(inputs.iter().copied())
+            .collect::<Vec<_>>(),
+        output,
+    );
+    let invoke_ptr = asm.alloc_node(CILNode::LdField {
+        addr: this,
+        field: invoke_field,
+    });
+    let invoke_ptr = asm.alloc_node(CILNode::PtrCast(
+        invoke_ptr,
+        Box::new(PtrCastRes::FnPtr(thunk_sig)),
+    ));
+    let args: Box<[_]> = std::iter::once(data_node)
+        .chain((1..=inputs.len()).map(|arg| asm.alloc_node(CILNode::LdArg(arg as u32))))
+        .collect();
+    let body = if output == Type::Void {
+        let call = asm.alloc_root(CILRoot::CallI(Box::new((invoke_ptr, thunk_sig, args))));
+        vec![call, ret]
+    } else {
+        let call = asm.alloc_node(CILNode::CallI(Box::new((invoke_ptr, thunk_sig, args))));
+        vec![asm.alloc_root(CILRoot::Ret(call))]
+    };
+    let invoke_sig = asm.sig(
+        std::iter::once(Type::ClassRef(holder))
+            .chain(inputs.iter().copied())
+            .collect::<Vec<_>>(),
+        output,
+    );
+    let invoke_name = asm.alloc_string("Invoke");
+    asm.new_method(MethodDef::new(
+        Access::Public,
+        holder_def,
+        invoke_name,
+        invoke_sig,
+        MethodKind::Instance,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(body, 0, None)],
+            locals: vec![],
+        },
+        vec![None; inputs.len() + 1],
+    ));
+    // Finalize() => drop(data). Nothing calls the finalizer directly, so it has to be marked as extern to stay alive.
+    let drop_sig = asm.sig([void_ptr], Type::Void);
+    let drop_ptr = asm.alloc_node(CILNode::LdField {
+        addr: this,
+        field: drop_field,
+    });
+    let drop_ptr = asm.alloc_node(CILNode::PtrCast(
+        drop_ptr,
+        Box::new(PtrCastRes::FnPtr(drop_sig)),
+    ));
+    let drop_call = asm.alloc_root(CILRoot::CallI(Box::new((
+        drop_ptr,
+        drop_sig,
+        [data_node].into(),
+    ))));
+    let finalize_sig = asm.sig([Type::ClassRef(holder)], Type::Void);
+    let finalize = asm.alloc_string("Finalize");
+    asm.new_method(MethodDef::new(
+        Access::Extern,
+        holder_def,
+        finalize,
+        finalize_sig,
+        MethodKind::Virtual,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![drop_call, ret], 0, None)],
+            locals: vec![],
+        },
+        vec![None],
+    ));
+    holder
+}
+/// Creates a new instance of the delegate type `delegate`, which calls the closure `data` using the thunk `invoke`.
+/// `drop` is called with `data` once the delegate is collected. `sig` is the signature of the delegate, excluding `data`.
+pub fn closure_delegate(
+    delegate: Interned<ClassRef>,
+    sig: Interned<FnSig>,
+    invoke: Interned<CILNode>,
+    drop: Interned<CILNode>,
+    data: Interned<CILNode>,
+    asm: &mut Assembly,
+) -> Interned<CILNode> {
+    let holder = closure_holder(sig, asm);
+    let void_ptr = asm.nptr(Type::Void);
+    let holder_ctor = asm[holder]
+        .clone()
+        .ctor(&[void_ptr, void_ptr, void_ptr], asm);
+    let holder_obj = asm.alloc_node(CILNode::call(holder_ctor, [invoke, drop, data]));
+    let mut inputs = vec![Type::ClassRef(holder)];
+    inputs.extend(asm[sig].inputs());
+    let output = *asm[sig].output();
+    let invoke_sig = asm.sig(inputs, output);
+    let invoke_name = asm.alloc_string("Invoke");
+    let invoke_mref = asm.alloc_methodref(MethodRef::new(
+        holder,
+        invoke_name,
+        invoke_sig,
+        MethodKind::Instance,
+        [].into(),
+    ));
+    let invoke_ftn = asm.alloc_node(CILNode::LdFtn(invoke_mref));
+    let invoke_ftn = asm.alloc_node(CILNode::PtrCast(invoke_ftn, Box::new(PtrCastRes::ISize)));
+    let delegate_ctor = asm[delegate]
+        .clone()
+        .ctor(&[Type::PlatformObject, Type::Int(crate::Int::ISize)], asm);
+    asm.alloc_node(CILNode::call(delegate_ctor, [holder_obj, invoke_ftn]))
+}
+#[test]
+fn closure_holder_typecheck() {
+    use crate::Int;
+    let mut asm = Assembly::default();
+    let sig = asm.sig([Type::Int(Int::I32), Type::Bool], Type::Int(Int::I64));
+    let holder = closure_holder(sig, &mut asm);
+    // Holders are defined only once for each signature.
+    assert_eq!(closure_holder(sig, &mut asm), holder);
+    let def = asm.class_ref_to_def(holder).unwrap();
+    assert_eq!(asm.get_class_def(def).methods().len(), 3);
+    let void_sig = asm.sig([], Type::Void);
+    assert_ne!(closure_holder(void_sig, &mut asm), holder);
+    let methods: Vec<_> = asm.method_defs().keys().copied().collect();
+    for method in methods {
+        let mut method = asm.method_def(method).clone();
+        method.typecheck(&mut asm).unwrap();
+    }
+}
@@ -0,0 +1,474 @@
+use crate::{
+    asm::MissingMethodPatcher,
+    cilnode::{ExtendKind, UnOp},
+    cilroot::{BranchCond, CmpKind},
+    Assembly, BasicBlock, BinOp, CILNode, CILRoot, Float, Int, MethodImpl,
+};
+mod soft;
+#[cfg(test)]
+use crate::interpreter::{Interpreter, Value};
+/// All the integer types a `f128` can be cast to and from.
+const INTS: [Int; 12] = [
+    Int::U8,
+    Int::U16,
+    Int::U32,
+    Int::U64,
+    Int::U128,
+    Int::USize,
+    Int::I8,
+    Int::I16,
+    Int::I32,
+    Int::I64,
+    Int::I128,
+    Int::ISize,
+];
+/// Implements a given BinOp directly, as an operation on two native 128 bit floats.
+fn op_direct(asm: &mut Assembly, patcher: &mut MissingMethodPatcher, op: BinOp) {
+    let name = asm.alloc_string(format!("{op}_f128", op = op.name()));
+    let generator = move |_, asm: &mut Assembly| {
+        let op = asm.biop(CILNode::LdArg(0), CILNode::LdArg(1), op);
+        let ret = asm.alloc_root(CILRoot::Ret(op));
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![ret], 0, None)],
+            locals: vec![],
+        }
+    };
+    patcher.insert(name, Box::new(generator));
+}
+/// Implements negation of a native 128 bit float.
+fn neg_direct(asm: &mut Assembly, patcher: &mut MissingMethodPatcher) {
+    let name = asm.alloc_string("neg_f128");
+    let generator = move |_, asm: &mut Assembly| {
+        let neg = asm.unop(CILNode::LdArg(0), UnOp::Neg);
+        let neg = asm.alloc_node(neg);
+        let ret = asm.alloc_root(CILRoot::Ret(neg));
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![ret], 0, None)],
+            locals: vec![],
+        }
+    };
+    patcher.insert(name, Box::new(generator));
+}
+/// Implements a cast between a native 128 bit float, and some other float.
+fn float_cast_direct(
+    asm: &mut Assembly,
+    patcher: &mut MissingMethodPatcher,
+    src: Float,
+    dst: Float,
+) {
+    let name = asm.alloc_string(format!("cast_{}_{}", src.name(), dst.name()));
+    let generator = move |_, asm: &mut Assembly| {
+        let input = asm.alloc_node(CILNode::LdArg(0));
+        let cast = asm.alloc_node(CILNode::FloatCast {
+            input,
+            target: dst,
+            is_signed: true,
+        });
+        let ret = asm.alloc_root(CILRoot::Ret(cast));
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![ret], 0, None)],
+            locals: vec![],
+        }
+    };
+    patcher.insert(name, Box::new(generator));
+}
+/// Implements a cast from an intieger to a native 128 bit float.
+fn int_to_f128_direct(asm: &mut Assembly, patcher: &mut MissingMethodPatcher, int: Int) {
+    let name = asm.alloc_string(format!("cast_{}_f128", int.name()));
+    let generator = move |_, asm: &mut Assembly| {
+        let input = asm.alloc_node(CILNode::LdArg(0));
+        let cast = asm.alloc_node(CILNode::FloatCast {
+            input,
+            target: Float::F128,
+            is_signed: int.is_signed(),
+        });
+        let ret = asm.alloc_root(CILRoot::Ret(cast));
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![ret], 0, None)],
+            locals: vec![],
+        }
+    };
+    patcher.insert(name, Box::new(generator));
+}
+/// Implements a saturating cast from a native 128 bit float to an intiger, with NaNs becoming 0.
+fn f128_to_int_direct(asm: &mut Assembly, patcher: &mut MissingMethodPatcher, int: Int) {
+    let name = asm.alloc_string(format!("cast_f128_{}", int.name()));
+    let generator = move |_, asm: &mut Assembly| {
+        // Consts
+        let imax = int.max(asm);
+        let imax = asm.alloc_node(imax);
+        let fmax = asm.alloc_node(CILNode::FloatCast {
+            input: imax,
+            target: Float::F128,
+            is_signed: int.is_signed(),
+        });
+        let imin = int.min(asm);
+        let imin = asm.alloc_node(imin);
+        let fmin = asm.alloc_node(CILNode::FloatCast {
+            input: imin,
+            target: Float::F128,
+            is_signed: int.is_signed(),
+        });
+        let zero = asm.alloc_node(int.zero());
+        // Args
+        let ld_arg_0 = asm.alloc_node(CILNode::LdArg(0));
+        // NaN is the only value not equal to itself.
+        let nan = asm.alloc_root(CILRoot::Branch(Box::new((
+            1,
+            0,
+            Some(BranchCond::Ne(ld_arg_0, ld_arg_0)),
+        ))));
+        let overflow = asm.alloc_root(CILRoot::Branch(Box::new((
+            2,
+            0,
+            Some(BranchCond::Ge(ld_arg_0, fmax, CmpKind::Ordered)),
+        ))));
+        let underflow = asm.alloc_root(CILRoot::Branch(Box::new((
+            3,
+            0,
+            Some(BranchCond::Le(ld_arg_0, fmin, CmpKind::Ordered)),
+        ))));
+        let cast = asm.alloc_node(CILNode::IntCast {
+            input: ld_arg_0,
+            target: int,
+            extend: if int.is_signed() {
+                ExtendKind::SignExtend
+            } else {
+                ExtendKind::ZeroExtend
+            },
+        });
+        let return_cast = asm.alloc_root(CILRoot::Ret(cast));
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(vec![nan, overflow, underflow, return_cast], 0, None),
+                BasicBlock::new(vec![asm.alloc_root(CILRoot::Ret(zero))], 1, None),
+                BasicBlock::new(vec![asm.alloc_root(CILRoot::Ret(imax))], 2, None),
+                BasicBlock::new(vec![asm.alloc_root(CILRoot::Ret(imin))], 3, None),
+            ],
+            locals: vec![],
+        }
+    };
+    patcher.insert(name, Box::new(generator));
+}
+/// Generates all ops operating on a 128 bit float. If `direct` is set, those ops use the native `_Float128` type,
+/// otherwise they are implemented in software.
+pub fn generate_f128_ops(asm: &mut Assembly, patcher: &mut MissingMethodPatcher, direct: bool) {
+    const OPS: [BinOp; 5] = [BinOp::Add, BinOp::Sub, BinOp::Mul, BinOp::Div, BinOp::Rem];
+    const CMPS: [BinOp; 3] = [BinOp::Lt, BinOp::Gt, BinOp::Eq];
+    const FLOATS: [Float; 2] = [Float::F32, Float::F64];
+    if !direct {
+        soft::generate_soft_f128_ops(asm, patcher, &INTS, &FLOATS);
+        return;
+    }
+    for op in OPS.iter().chain(CMPS.iter()) {
+        op_direct(asm, patcher, *op);
+    }
+    neg_direct(asm, patcher);
+    for float in FLOATS {
+        float_cast_direct(asm, patcher, float, Float::F128);
+        float_cast_direct(asm, patcher, Float::F128, float);
+    }
+    for int in INTS {
+        int_to_f128_direct(asm, patcher, int);
+        f128_to_int_direct(asm, patcher, int);
+    }
+}
+#[test]
+fn soft_f128_typechecks() {
+    use crate::{cilnode::MethodKind, Access, MethodRef, Type};
+    let mut asm = Assembly::default();
+    let mut patcher = MissingMethodPatcher::default();
+    generate_f128_ops(&mut asm, &mut patcher, false);
+    let f128 = Type::Float(Float::F128);
+    let f32 = Type::Float(Float::F32);
+    let f64 = Type::Float(Float::F64);
+    let big = Type::ClassRef(crate::ClassRef::big_integer(&mut asm));
+    let pack_args = [Type::Bool, big, Type::Int(Int::I32), Type::Bool];
+    let sigs = [
+        ("add_f128", asm.sig([f128, f128], f128)),
+        ("sub_f128", asm.sig([f128, f128], f128)),
+        ("mul_f128", asm.sig([f128, f128], f128)),
+        ("div_f128", asm.sig([f128, f128], f128)),
+        ("mod_f128", asm.sig([f128, f128], f128)),
+        ("lt_f128", asm.sig([f128, f128], Type::Bool)),
+        ("gt_f128", asm.sig([f128, f128], Type::Bool)),
+        ("eq_f128", asm.sig([f128, f128], Type::Bool)),
+        ("neg_f128", asm.sig([f128], f128)),
+        ("copysignf128", asm.sig([f128, f128], f128)),
+        ("cast_f32_f128", asm.sig([f32], f128)),
+        ("cast_f128_f64", asm.sig([f128], f64)),
+        ("cast_i128_f128", asm.sig([Type::Int(Int::I128)], f128)),
+        ("cast_f128_u8", asm.sig([f128], Type::Int(Int::U8))),
+        ("round_pack_f32", asm.sig(pack_args, f32)),
+        ("round_pack_f128", asm.sig(pack_args, f128)),
+    ];
+    let main_module = *asm.main_module();
+    for (name, sig) in sigs {
+        let name = asm.alloc_string(name);
+        let mref = asm.alloc_methodref(MethodRef::new(
+            main_module,
+            name,
+            sig,
+            MethodKind::Static,
+            vec![].into(),
+        ));
+        let implementation = patcher[&name](mref, &mut asm);
+        let mut def = asm[mref]
+            .clone()
+            .into_def(implementation, Access::Public, &asm);
+        def.typecheck(&mut asm)
+            .unwrap_or_else(|err| panic!("{}: {err:?}", &asm[name]));
+    }
+}
+/// The software `f128` ops, patched into an assembly the interpreter can run.
+#[cfg(test)]
+struct SoftF128(Assembly);
+#[cfg(test)]
+impl SoftF128 {
+    fn new() -> Self {
+        use crate::{cilnode::MethodKind, Type};
+        let mut asm = Assembly::default();
+        let mut patcher = MissingMethodPatcher::default();
+        generate_f128_ops(&mut asm, &mut patcher, false);
+        let f128 = Type::Float(Float::F128);
+        let mut sigs = vec![];
+        for op in ["add", "sub", "mul", "div", "mod"] {
+            sigs.push((format!("{op}_f128"), asm.sig([f128, f128], f128)));
+        }
+        for cmp in ["lt", "gt", "eq"] {
+            sigs.push((format!("{cmp}_f128"), asm.sig([f128, f128], Type::Bool)));
+        }
+        sigs.push(("neg_f128".into(), asm.sig([f128], f128)));
+        sigs.push(("copysignf128".into(), asm.sig([f128, f128], f128)));
+        for float in [Float::F32, Float::F64] {
+            let tpe = Type::Float(float);
+            sigs.push((format!("cast_{}_f128", float.name()), asm.sig([tpe], f128)));
+            sigs.push((format!("cast_f128_{}", float.name()), asm.sig([f128], tpe)));
+        }
+        for int in INTS {
+            let tpe = Type::Int(int);
+            sigs.push((format!("cast_{}_f128", int.name()), asm.sig([tpe], f128)));
+            sigs.push((format!("cast_f128_{}", int.name()), asm.sig([f128], tpe)));
+        }
+        let main_module = *asm.main_module();
+        for (name, sig) in sigs {
+            asm.new_methodref(main_module, name, sig, MethodKind::Static, vec![]);
+        }
+        // The ops call `round_pack`, which only gets a method reference once they are generated.
+        for _ in 0..2 {
+            asm.patch_missing_methods(&Default::default(), &Default::default(), &patcher);
+        }
+        Self(asm)
+    }
+    fn call(&self, name: &str, args: &[Value]) -> Value {
+        let asm = &self.0;
+        let op = asm
+            .method_defs()
+            .keys()
+            .find(|def| &asm[asm[**def].name()] == name)
+            .unwrap_or_else(|| panic!("{name} missing"));
+        Interpreter::new(asm)
+            .call_method(**op, args.to_vec())
+            .unwrap_or_else(|err| panic!("{name}{args:?}: {err}"))
+    }
+    /// Calls a binary op, returning the bits of the result.
+    fn op(&self, name: &str, lhs: u128, rhs: u128) -> u128 {
+        bits(&self.call(&format!("{name}_f128"), &[f128(lhs), f128(rhs)]))
+    }
+    fn cmp(&self, name: &str, lhs: u128, rhs: u128) -> bool {
+        self.call(&format!("{name}_f128"), &[f128(lhs), f128(rhs)]) == Value::Bool(true)
+    }
+}
+#[cfg(test)]
+fn f128(bits: u128) -> Value {
+    Value::Struct(bits.to_le_bytes().into())
+}
+#[cfg(test)]
+fn bits(val: &Value) -> u128 {
+    let Value::Struct(bytes) = val else {
+        panic!("expected a f128, got {val:?}")
+    };
+    u128::from_le_bytes(bytes.as_ref().try_into().unwrap())
+}
+/// The bits of `2^exp`, which must be a normal `f128`.
+#[cfg(test)]
+fn pow2(exp: i32) -> u128 {
+    u128::try_from(16383 + exp).unwrap() << 112
+}
+/// The bits of the normal `f64` `val`, widened to a `f128`.
+#[cfg(test)]
+fn from_f64(val: f64) -> u128 {
+    if val == 0.0 {
+        return u128::from(val.is_sign_negative()) << 127;
+    }
+    let bits = val.to_bits();
+    let sign = u128::from(bits >> 63) << 127;
+    let exp = u128::from((bits >> 52) & 0x7FF) + 16383 - 1023;
+    let fraction = u128::from(bits & ((1 << 52) - 1)) << 60;
+    sign | exp << 112 | fraction
+}
+#[cfg(test)]
+fn is_nan(bits: u128) -> bool {
+    bits & !(1 << 127) > u128::from(0x7FFF_u64) << 112
+}
+#[cfg(test)]
+const NEG: u128 = 1 << 127;
+#[cfg(test)]
+const INF: u128 = 0x7FFF << 112;
+#[test]
+fn soft_f128_arithmetic() {
+    let soft = SoftF128::new();
+    let f = from_f64;
+    assert_eq!(soft.op("add", f(1.5), f(2.25)), f(3.75));
+    assert_eq!(soft.op("sub", f(1.0), f(1.0)), 0);
+    assert_eq!(soft.op("sub", f(1.0), f(4.0)), f(-3.0));
+    assert_eq!(soft.op("mul", f(3.0), f(-0.5)), f(-1.5));
+    assert_eq!(
+        soft.op("div", f(1.0), f(3.0)),
+        0x3FFD_5555_5555_5555_5555_5555_5555_5555
+    );
+    assert_eq!(soft.op("mod", f(5.5), f(2.0)), f(1.5));
+    assert_eq!(soft.op("mod", f(-5.5), f(2.0)), f(-1.5));
+    assert_eq!(bits(&soft.call("neg_f128", &[f128(f(1.0))])), f(-1.0));
+    assert_eq!(
+        bits(&soft.call("copysignf128", &[f128(f(2.0)), f128(f(-0.0))])),
+        f(-2.0)
+    );
+    assert!(soft.cmp("lt", f(-1.0), f(0.5)));
+    assert!(!soft.cmp("gt", f(-1.0), f(0.5)));
+    assert!(soft.cmp("eq", 0, NEG));
+}
+#[test]
+fn soft_f128_rounding() {
+    let soft = SoftF128::new();
+    let one = pow2(0);
+    // 1 + 2^-113 lies exactly between 1 and the next `f128`, so it rounds to the even one of them.
+    assert_eq!(soft.op("add", one, pow2(-113)), one);
+    assert_eq!(soft.op("add", one | 1, pow2(-113)), one | 2);
+    // Not a tie: rounds to nearest.
+    assert_eq!(soft.op("add", one, pow2(-113) | 1 << 111), one | 1);
+    assert_eq!(soft.op("sub", one, pow2(-114)), one);
+    assert_eq!(soft.op("sub", one, pow2(-113)), pow2(-1) | ((1 << 112) - 1));
+}
+#[test]
+fn soft_f128_subnormals() {
+    let soft = SoftF128::new();
+    let min_normal = pow2(-16382);
+    assert_eq!(soft.op("add", 1, 1), 2);
+    assert_eq!(soft.op("sub", min_normal, 1), min_normal - 1);
+    assert_eq!(soft.op("add", min_normal - 1, 1), min_normal);
+    assert_eq!(soft.op("mul", min_normal, from_f64(0.5)), 1 << 111);
+    assert_eq!(soft.op("mul", 3, from_f64(0.25)), 1);
+    // Halfway between 0 and the smallest subnormal rounds to 0, and 1.5 times it rounds to 2 times it.
+    assert_eq!(soft.op("div", 1, from_f64(2.0)), 0);
+    assert_eq!(soft.op("div", 3, from_f64(2.0)), 2);
+    assert_eq!(soft.op("div", 1 | NEG, from_f64(4.0)), NEG);
+    assert_eq!(soft.op("div", min_normal, pow2(112)), 1);
+}
+#[test]
+fn soft_f128_special_values() {
+    let soft = SoftF128::new();
+    let one = pow2(0);
+    let max = (0x7FFE << 112) | ((1 << 112) - 1);
+    let nan = 0x7FFF_8000 << 96;
+    assert!(is_nan(soft.op("add", INF, INF | NEG)));
+    assert!(is_nan(soft.op("mul", 0, INF)));
+    assert!(is_nan(soft.op("div", 0, 0)));
+    assert!(is_nan(soft.op("mod", INF, one)));
+    assert!(is_nan(soft.op("add", nan, one)));
+    assert!(is_nan(soft.op("mul", one, nan | NEG)));
+    assert_eq!(soft.op("add", INF, one), INF);
+    assert_eq!(soft.op("div", one, 0), INF);
+    assert_eq!(soft.op("div", one | NEG, 0), INF | NEG);
+    assert_eq!(soft.op("mul", max, pow2(1)), INF);
+    assert_eq!(soft.op("mod", one, INF), one);
+    for cmp in ["lt", "gt", "eq"] {
+        assert!(!soft.cmp(cmp, nan, nan), "{cmp}");
+        assert!(!soft.cmp(cmp, nan, one), "{cmp}");
+    }
+    assert!(soft.cmp("lt", INF | NEG, max | NEG));
+}
+#[test]
+fn soft_f128_int_casts() {
+    let soft = SoftF128::new();
+    let to_int =
+        |int: Int, val: u128| soft.call(&format!("cast_f128_{}", int.name()), &[f128(val)]);
+    let from_int = |val: Value| {
+        let Value::Int(int, _) = val else {
+            unreachable!()
+        };
+        bits(&soft.call(&format!("cast_{}_f128", int.name()), &[val]))
+    };
+    // `u128::MAX` needs more than 113 bits, so it rounds up to 2^128.
+    assert_eq!(from_int(Value::Int(Int::U128, u128::MAX)), pow2(128));
+    assert_eq!(from_int(Value::int(Int::I128, i128::MIN)), pow2(127) | NEG);
+    assert_eq!(from_int(Value::int(Int::I32, -3)), from_f64(-3.0));
+    assert_eq!(from_int(Value::int(Int::U8, 0)), 0);
+    assert_eq!(
+        from_int(Value::int(Int::U64, u64::MAX)),
+        pow2(63) | ((1 << 63) - 1) << 49
+    );
+    // Casts to ints truncate, and saturate, with NaNs becoming 0.
+    assert_eq!(to_int(Int::U8, from_f64(255.5)), Value::int(Int::U8, 255));
+    assert_eq!(to_int(Int::U8, from_f64(256.0)), Value::int(Int::U8, 255));
+    assert_eq!(to_int(Int::U8, from_f64(-1.0)), Value::int(Int::U8, 0));
+    assert_eq!(to_int(Int::U8, 0x7FFF_8000 << 96), Value::int(Int::U8, 0));
+    assert_eq!(to_int(Int::I32, from_f64(-0.9)), Value::int(Int::I32, 0));
+    assert_eq!(
+        to_int(Int::I32, from_f64(-2_147_483_648.0)),
+        Value::int(Int::I32, i32::MIN)
+    );
+    assert_eq!(
+        to_int(Int::I128, pow2(200)),
+        Value::int(Int::I128, i128::MAX)
+    );
+    assert_eq!(
+        to_int(Int::I128, pow2(200) | NEG),
+        Value::int(Int::I128, i128::MIN)
+    );
+    assert_eq!(
+        to_int(Int::U128, pow2(128) - 1),
+        Value::Int(Int::U128, u128::MAX << 15)
+    );
+    assert_eq!(to_int(Int::U64, INF), Value::int(Int::U64, u64::MAX));
+}
+#[test]
+fn soft_f128_float_casts() {
+    let soft = SoftF128::new();
+    let to_f64 = |val: u128| match soft.call("cast_f128_f64", &[f128(val)]) {
+        Value::F64(val) => val.to_bits(),
+        val => panic!("{val:?}"),
+    };
+    let to_f32 = |val: u128| match soft.call("cast_f128_f32", &[f128(val)]) {
+        Value::F32(val) => val.to_bits(),
+        val => panic!("{val:?}"),
+    };
+    let from_f64 = |val: f64| bits(&soft.call("cast_f64_f128", &[Value::F64(val)]));
+    let one = pow2(0);
+    let third = 0x3FFD_5555_5555_5555_5555_5555_5555_5555;
+    assert_eq!(to_f64(third), (1.0_f64 / 3.0).to_bits());
+    assert_eq!(to_f32(third), (1.0_f32 / 3.0).to_bits());
+    // 1 + 2^-53 is exactly between two `f64`s.
+    assert_eq!(to_f64(one | 1 << 59), 1.0_f64.to_bits());
+    assert_eq!(to_f64(one | 3 << 59), (1.0 + f64::EPSILON * 2.0).to_bits());
+    assert_eq!(to_f64(one | 1 << 59 | 1), (1.0 + f64::EPSILON).to_bits());
+    // Subnormal results, and underflow.
+    assert_eq!(to_f64(pow2(-1074)), 1);
+    assert_eq!(to_f64(pow2(-1075)), 0);
+    assert_eq!(to_f64(pow2(-1075) | 1), 1);
+    assert_eq!(to_f64(pow2(-1075) | NEG), (-0.0_f64).to_bits());
+    assert_eq!(to_f64(pow2(2000)), f64::INFINITY.to_bits());
+    assert_eq!(to_f64(INF | NEG), f64::NEG_INFINITY.to_bits());
+    assert!(f64::from_bits(to_f64(0x7FFF_8000 << 96)).is_nan());
+    assert!(f32::from_bits(to_f32(0x7FFF_8000 << 96)).is_nan());
+    // Widening is always exact.
+    assert_eq!(from_f64(f64::from_bits(1)), pow2(-1074));
+    assert_eq!(from_f64(f64::MAX), self::from_f64(f64::MAX));
+    assert_eq!(from_f64(-0.0), NEG);
+    assert_eq!(from_f64(f64::INFINITY), INF);
+    assert!(is_nan(from_f64(f64::NAN)));
+    assert_eq!(
+        bits(&soft.call("cast_f32_f128", &[Value::F32(0.1)])),
+        self::from_f64(0.1_f32.into())
+    );
+}
@@ -0,0 +1,1123 @@
+//! Software implementation of `f128`, for targets without a native 128 bit float.
+//!
+//! Each value gets unpacked into its sign, exponent and mantissa. The mantissa is a `System.Numerics.BigInteger`, so
+//! every operation can be computed exactly, and then rounded(to nearest, ties to even) by a shared `round_pack`
+//! routine.
+use crate::{
+    asm::MissingMethodPatcher,
+    bimap::Interned,
+    cilnode::{ExtendKind, MethodKind, PtrCastRes},
+    cilroot::{BranchCond, CmpKind},
+    hashable::{HashableF32, HashableF64},
+    Assembly, BasicBlock, BinOp, CILNode, CILRoot, ClassRef, Const, Float, Int, MethodImpl, Type,
+};
+/// A binary IEEE floating-point format, which exact results can be rounded to.
+#[derive(Clone, Copy)]
+struct Format {
+    float: Float,
+    /// The number of bits in the significand, including the implicit bit.
+    precision: u32,
+    exponent_bits: u32,
+}
+impl Format {
+    const F32: Self = Self {
+        float: Float::F32,
+        precision: 24,
+        exponent_bits: 8,
+    };
+    const F64: Self = Self {
+        float: Float::F64,
+        precision: 53,
+        exponent_bits: 11,
+    };
+    const F128: Self = Self {
+        float: Float::F128,
+        precision: 113,
+        exponent_bits: 15,
+    };
+    fn of(float: Float) -> Self {
+        match float {
+            Float::F32 => Self::F32,
+            Float::F64 => Self::F64,
+            Float::F128 => Self::F128,
+            Float::F16 => todo!("f16 soft-float is not supported yet"),
+        }
+    }
+    fn bias(self) -> i32 {
+        (1 << (self.exponent_bits - 1)) - 1
+    }
+    /// The biased exponent of infinities and NaNs.
+    fn max_exponent(self) -> u64 {
+        (1 << self.exponent_bits) - 1
+    }
+    fn width(self) -> u32 {
+        self.precision + self.exponent_bits
+    }
+    /// The name of the routine rounding an exact value to this format.
+    fn round_pack_name(self) -> String {
+        format!("round_pack_{}", self.float.name())
+    }
+}
+/// The sign bit of a `f128`, within its upper half.
+const SIGN: u64 = 1 << 63;
+/// The upper half of an infinite `f128`.
+const INF_HI: u64 = 0x7FFF_0000_0000_0000;
+/// The upper half of the canonical, quiet `f128` NaN.
+const NAN_HI: u64 = 0x7FFF_8000_0000_0000;
+
+fn big_integer(asm: &mut Assembly) -> Type {
+    Type::ClassRef(ClassRef::big_integer(asm))
+}
+/// Calls a static method of `System.Numerics.BigInteger`.
+fn big_call(
+    asm: &mut Assembly,
+    name: &str,
+    args: &[(Interned<CILNode>, Type)],
+    output: Type,
+) -> Interned<CILNode> {
+    let class = ClassRef::big_integer(asm);
+    let class = asm[class].clone();
+    let inputs: Vec<_> = args.iter().map(|(_, tpe)| *tpe).collect();
+    let name = asm.alloc_string(name);
+    let mref = class.static_mref(&inputs, output, name, asm);
+    let args: Vec<_> = args.iter().map(|(arg, _)| *arg).collect();
+    asm.alloc_node(CILNode::call(mref, args))
+}
+/// Applies a `BigInteger` operator, such as `op_Addition`, to two `BigInteger`s.
+fn big_op(
+    asm: &mut Assembly,
+    name: &str,
+    lhs: Interned<CILNode>,
+    rhs: Interned<CILNode>,
+) -> Interned<CILNode> {
+    let big = big_integer(asm);
+    big_call(asm, name, &[(lhs, big), (rhs, big)], big)
+}
+/// Compares two `BigInteger`s, using an operator such as `op_LessThan`.
+fn big_cmp(
+    asm: &mut Assembly,
+    name: &str,
+    lhs: Interned<CILNode>,
+    rhs: Interned<CILNode>,
+) -> Interned<CILNode> {
+    let big = big_integer(asm);
+    big_call(asm, name, &[(lhs, big), (rhs, big)], Type::Bool)
+}
+/// Shifts a `BigInteger` left by an `i32`. Negative shifts are right shifts.
+fn big_shl(asm: &mut Assembly, val: Interned<CILNode>, by: Interned<CILNode>) -> Interned<CILNode> {
+    let big = big_integer(asm);
+    big_call(
+        asm,
+        "op_LeftShift",
+        &[(val, big), (by, Type::Int(Int::I32))],
+        big,
+    )
+}
+fn big_shr(asm: &mut Assembly, val: Interned<CILNode>, by: Interned<CILNode>) -> Interned<CILNode> {
+    let big = big_integer(asm);
+    big_call(
+        asm,
+        "op_RightShift",
+        &[(val, big), (by, Type::Int(Int::I32))],
+        big,
+    )
+}
+/// Converts an intiger to a `BigInteger`.
+fn big_from(asm: &mut Assembly, val: Interned<CILNode>, int: Int) -> Interned<CILNode> {
+    let big = big_integer(asm);
+    big_call(asm, "op_Implicit", &[(val, Type::Int(int))], big)
+}
+/// Converts a `BigInteger` to an intiger. The value must fit within that intiger.
+fn big_to(asm: &mut Assembly, val: Interned<CILNode>, int: Int) -> Interned<CILNode> {
+    let big = big_integer(asm);
+    big_call(asm, "op_Explicit", &[(val, big)], Type::Int(int))
+}
+/// A constant `BigInteger`, equal to `val << shift`.
+fn big_const(asm: &mut Assembly, val: u64, shift: u32) -> Interned<CILNode> {
+    let val = asm.alloc_node(val);
+    let val = big_from(asm, val, Int::U64);
+    if shift == 0 {
+        return val;
+    }
+    let shift = asm.alloc_node(i32::try_from(shift).unwrap());
+    big_shl(asm, val, shift)
+}
+fn not(asm: &mut Assembly, val: Interned<CILNode>) -> Interned<CILNode> {
+    asm.biop(val, CILNode::from(false), BinOp::Eq)
+}
+fn to_i32(asm: &mut Assembly, val: Interned<CILNode>) -> Interned<CILNode> {
+    asm.int_cast(val, Int::I32, ExtendKind::ZeroExtend)
+}
+fn branch(asm: &mut Assembly, target: u32, cond: BranchCond) -> Interned<CILRoot> {
+    asm.alloc_root(CILRoot::Branch(Box::new((target, 0, Some(cond)))))
+}
+fn jump(asm: &mut Assembly, target: u32) -> Interned<CILRoot> {
+    asm.alloc_root(CILRoot::Branch(Box::new((target, 0, None))))
+}
+fn ret(asm: &mut Assembly, val: Interned<CILNode>) -> Interned<CILRoot> {
+    asm.alloc_root(CILRoot::Ret(val))
+}
+/// Adress of one of the 64 bit halves of a `f128`.
+fn half_addr(asm: &mut Assembly, addr: CILNode, high: bool) -> Interned<CILNode> {
+    let u64_tpe = asm.alloc_type(Type::Int(Int::U64));
+    let addr = asm.alloc_node(addr);
+    let addr = asm.alloc_node(CILNode::RefToPtr(addr));
+    let addr = asm.alloc_node(CILNode::PtrCast(addr, Box::new(PtrCastRes::Ptr(u64_tpe))));
+    if high {
+        let offset = asm.alloc_node(Const::USize(8));
+        asm.biop(addr, offset, BinOp::Add)
+    } else {
+        addr
+    }
+}
+/// Returns a `f128` made from its two halves. Uses local 0 to assemble that value.
+fn ret_halves(
+    asm: &mut Assembly,
+    hi: Interned<CILNode>,
+    lo: Interned<CILNode>,
+) -> [Interned<CILRoot>; 3] {
+    let hi_addr = half_addr(asm, CILNode::LdLocA(0), true);
+    let lo_addr = half_addr(asm, CILNode::LdLocA(0), false);
+    let set_hi = asm.alloc_root(CILRoot::StInd(Box::new((
+        hi_addr,
+        hi,
+        Type::Int(Int::U64),
+        false,
+    ))));
+    let set_lo = asm.alloc_root(CILRoot::StInd(Box::new((
+        lo_addr,
+        lo,
+        Type::Int(Int::U64),
+        false,
+    ))));
+    let res = asm.alloc_node(CILNode::LdLoc(0));
+    [set_hi, set_lo, ret(asm, res)]
+}
+/// Returns a `f128` NaN. Uses local 0.
+fn ret_nan(asm: &mut Assembly) -> [Interned<CILRoot>; 3] {
+    let hi = asm.alloc_node(NAN_HI);
+    let lo = asm.alloc_node(0_u64);
+    ret_halves(asm, hi, lo)
+}
+/// The local holding the result of a soft-float operation.
+fn result_local(asm: &mut Assembly) -> (Option<Interned<crate::IString>>, Interned<Type>) {
+    (None, asm.alloc_type(Type::Float(Float::F128)))
+}
+/// The raw bits of a `f128`, split into two 64 bit halves.
+#[derive(Clone, Copy)]
+struct Halves {
+    hi: Interned<CILNode>,
+    lo: Interned<CILNode>,
+}
+impl Halves {
+    /// Reads the halves of a `f128` argument.
+    fn arg(asm: &mut Assembly, arg: u32) -> Self {
+        let u64_tpe = asm.alloc_type(Type::Int(Int::U64));
+        let hi = half_addr(asm, CILNode::LdArgA(arg), true);
+        let lo = half_addr(asm, CILNode::LdArgA(arg), false);
+        Self {
+            hi: asm.alloc_node(CILNode::LdInd {
+                addr: hi,
+                tpe: u64_tpe,
+                volatile: false,
+            }),
+            lo: asm.alloc_node(CILNode::LdInd {
+                addr: lo,
+                tpe: u64_tpe,
+                volatile: false,
+            }),
+        }
+    }
+    /// The same value, with its sign flipped.
+    fn negated(self, asm: &mut Assembly) -> Self {
+        let sign = asm.alloc_node(SIGN);
+        Self {
+            hi: asm.biop(self.hi, sign, BinOp::XOr),
+            lo: self.lo,
+        }
+    }
+    /// The upper half, without the sign bit.
+    fn abs_hi(self, asm: &mut Assembly) -> Interned<CILNode> {
+        let mask = asm.alloc_node(!SIGN);
+        asm.biop(self.hi, mask, BinOp::And)
+    }
+    /// The sign bit, as an `u64` equal to 0 or 1.
+    fn sign_bit(self, asm: &mut Assembly) -> Interned<CILNode> {
+        let shift = asm.alloc_node(63_i32);
+        asm.biop(self.hi, shift, BinOp::ShrUn)
+    }
+    /// True if this value is negative.
+    fn sign(self, asm: &mut Assembly) -> Interned<CILNode> {
+        let sign = self.sign_bit(asm);
+        asm.biop(sign, CILNode::from(1_u64), BinOp::Eq)
+    }
+    /// 1 for positive values, -1 for negative ones, as a `BigInteger`.
+    fn signum(self, asm: &mut Assembly) -> Interned<CILNode> {
+        let sign = self.sign_bit(asm);
+        let sign = to_i32(asm, sign);
+        let twice = asm.biop(sign, sign, BinOp::Add);
+        let signum = asm.biop(CILNode::from(1_i32), twice, BinOp::Sub);
+        big_from(asm, signum, Int::I32)
+    }
+    /// The biased exponent, as an `i32`.
+    fn biased_exponent(self, asm: &mut Assembly) -> Interned<CILNode> {
+        let shift = asm.alloc_node(48_i32);
+        let exp = asm.biop(self.hi, shift, BinOp::ShrUn);
+        let mask = asm.alloc_node(Format::F128.max_exponent());
+        let exp = asm.biop(exp, mask, BinOp::And);
+        to_i32(asm, exp)
+    }
+    /// 1 if this value is normal(has an implicit leading bit), and 0 otherwise, as an `i32`.
+    fn is_normal(self, asm: &mut Assembly) -> Interned<CILNode> {
+        // Only a biased exponent of 0 stays below 1 << 15, after adding the max exponent to it.
+        let exp = self.biased_exponent(asm);
+        let max = asm.alloc_node(0x7FFF_i32);
+        let exp = asm.biop(exp, max, BinOp::Add);
+        let shift = asm.alloc_node(15_i32);
+        asm.biop(exp, shift, BinOp::Shr)
+    }
+    /// True if this value is an infinity or a NaN.
+    fn is_special(self, asm: &mut Assembly) -> Interned<CILNode> {
+        let exp = self.biased_exponent(asm);
+        asm.biop(exp, CILNode::from(0x7FFF_i32), BinOp::Eq)
+    }
+    fn is_nan(self, asm: &mut Assembly) -> Interned<CILNode> {
+        let abs_hi = self.abs_hi(asm);
+        let inf_hi = asm.alloc_node(INF_HI);
+        let above_inf = asm.biop(abs_hi, inf_hi, BinOp::GtUn);
+        let is_inf_hi = asm.biop(abs_hi, inf_hi, BinOp::Eq);
+        let lo_zero = asm.biop(self.lo, CILNode::from(0_u64), BinOp::Eq);
+        let lo_nonzero = not(asm, lo_zero);
+        let nan_lo = asm.biop(is_inf_hi, lo_nonzero, BinOp::And);
+        asm.biop(above_inf, nan_lo, BinOp::Or)
+    }
+    /// True for both positive and negative zero.
+    fn is_zero(self, asm: &mut Assembly) -> Interned<CILNode> {
+        let abs_hi = self.abs_hi(asm);
+        let bits = asm.biop(abs_hi, self.lo, BinOp::Or);
+        asm.biop(bits, CILNode::from(0_u64), BinOp::Eq)
+    }
+    /// The significand, including the implicit bit, as a `BigInteger`.
+    fn mantissa(self, asm: &mut Assembly) -> Interned<CILNode> {
+        let mask = asm.alloc_node(0xFFFF_FFFF_FFFF_u64);
+        let fraction = asm.biop(self.hi, mask, BinOp::And);
+        let is_normal = self.is_normal(asm);
+        let implicit = asm.int_cast(is_normal, Int::U64, ExtendKind::ZeroExtend);
+        let shift = asm.alloc_node(48_i32);
+        let implicit = asm.biop(implicit, shift, BinOp::Shl);
+        let hi = asm.biop(fraction, implicit, BinOp::Or);
+        let hi = big_from(asm, hi, Int::U64);
+        let shift = asm.alloc_node(64_i32);
+        let hi = big_shl(asm, hi, shift);
+        let lo = big_from(asm, self.lo, Int::U64);
+        big_op(asm, "op_BitwiseOr", hi, lo)
+    }
+    /// The exponent, such that this value is equal to `mantissa * 2^exponent`.
+    fn exponent(self, asm: &mut Assembly) -> Interned<CILNode> {
+        // Subnormals have the same exponent as the smallest normal values.
+        let exp = self.biased_exponent(asm);
+        let is_normal = self.is_normal(asm);
+        let exp = asm.biop(exp, is_normal, BinOp::Sub);
+        let offset = Format::F128.bias() + i32::try_from(Format::F128.precision).unwrap() - 2;
+        let offset = asm.alloc_node(offset);
+        asm.biop(exp, offset, BinOp::Sub)
+    }
+}
+/// Calls the routine rounding `sign * mantissa * 2^exponent` to `format`. `sticky` is set if the exact value is
+/// slightly bigger than `mantissa`, by less than half of its last bit.
+fn round_pack(
+    asm: &mut Assembly,
+    format: Format,
+    sign: Interned<CILNode>,
+    mantissa: Interned<CILNode>,
+    exponent: Interned<CILNode>,
+    sticky: Interned<CILNode>,
+) -> Interned<CILNode> {
+    let big = big_integer(asm);
+    let sig = asm.sig(
+        [Type::Bool, big, Type::Int(Int::I32), Type::Bool],
+        Type::Float(format.float),
+    );
+    let main_module = *asm.main_module();
+    let mref = asm.new_methodref(
+        main_module,
+        format.round_pack_name(),
+        sig,
+        MethodKind::Static,
+        vec![],
+    );
+    asm.alloc_node(CILNode::call(mref, [sign, mantissa, exponent, sticky]))
+}
+/// Rounds `sign * mantissa * 2^exponent` to nearest, ties to even, and packs it into `format`.
+fn insert_round_pack(asm: &mut Assembly, patcher: &mut MissingMethodPatcher, format: Format) {
+    let name = asm.alloc_string(format.round_pack_name());
+    let generator = move |_, asm: &mut Assembly| {
+        let big = big_integer(asm);
+        let i32_tpe = asm.alloc_type(Type::Int(Int::I32));
+        let big_tpe = asm.alloc_type(big);
+        let precision = i32::try_from(format.precision).unwrap();
+        let sign = asm.alloc_node(CILNode::LdArg(0));
+        let mantissa = asm.alloc_node(CILNode::LdArg(1));
+        let exponent = asm.alloc_node(CILNode::LdArg(2));
+        let sticky = asm.alloc_node(CILNode::LdArg(3));
+        let shift = asm.alloc_node(CILNode::LdLoc(1));
+        let base = asm.alloc_node(CILNode::LdLoc(2));
+        let quotient = asm.alloc_node(CILNode::LdLoc(3));
+        let reminder = asm.alloc_node(CILNode::LdLoc(4));
+        let half = asm.alloc_node(CILNode::LdLoc(5));
+        let bits = asm.alloc_node(CILNode::LdLoc(6));
+        let zero_i32 = asm.alloc_node(0_i32);
+        let zero = big_const(asm, 0, 0);
+        let one = big_const(asm, 1, 0);
+        // Block 0: find the amount of bits to discard, and the biased exponent(minus one) of the result.
+        let is_zero = big_cmp(asm, "op_Equality", mantissa, zero);
+        let is_zero = branch(asm, 5, BranchCond::True(is_zero));
+        let log2 = big_call(asm, "Log2", &[(mantissa, big)], big);
+        let log2 = big_to(asm, log2, Int::I32);
+        let discarded = asm.biop(log2, CILNode::from(1 - precision), BinOp::Add);
+        let set_shift = asm.alloc_root(CILRoot::StLoc(1, discarded));
+        let base_val = asm.biop(exponent, log2, BinOp::Add);
+        let base_val = asm.biop(base_val, CILNode::from(format.bias() - 1), BinOp::Add);
+        let set_base = asm.alloc_root(CILRoot::StLoc(2, base_val));
+        let is_normal = branch(asm, 1, BranchCond::Ge(base, zero_i32, CmpKind::Signed));
+        // Subnormal: discard more bits, to keep the exponent at its minimum.
+        let subnormal_shift = asm.biop(shift, base, BinOp::Sub);
+        let subnormal_shift = asm.alloc_root(CILRoot::StLoc(1, subnormal_shift));
+        let subnormal_base = asm.alloc_root(CILRoot::StLoc(2, zero_i32));
+        let to_round = jump(asm, 1);
+        // Block 1: round the discarded bits to nearest, ties to even.
+        let exact = branch(asm, 3, BranchCond::Le(shift, zero_i32, CmpKind::Signed));
+        let kept = big_shr(asm, mantissa, shift);
+        let set_quotient = asm.alloc_root(CILRoot::StLoc(3, kept));
+        let kept_bits = big_shl(asm, quotient, shift);
+        let discarded = big_op(asm, "op_Subtraction", mantissa, kept_bits);
+        let set_reminder = asm.alloc_root(CILRoot::StLoc(4, discarded));
+        let half_shift = asm.biop(shift, CILNode::from(1_i32), BinOp::Sub);
+        let half_val = big_shl(asm, one, half_shift);
+        let set_half = asm.alloc_root(CILRoot::StLoc(5, half_val));
+        let above_half = big_cmp(asm, "op_GreaterThan", reminder, half);
+        let above_half = branch(asm, 2, BranchCond::True(above_half));
+        let below_half = big_cmp(asm, "op_LessThan", reminder, half);
+        let below_half = branch(asm, 4, BranchCond::True(below_half));
+        let tie_sticky = branch(asm, 2, BranchCond::True(sticky));
+        let is_even = big_call(asm, "IsEvenInteger", &[(quotient, big)], Type::Bool);
+        let tie_even = branch(asm, 4, BranchCond::True(is_even));
+        let round_up = jump(asm, 2);
+        // Block 2: round up.
+        let inc = big_op(asm, "op_Addition", quotient, one);
+        let inc = asm.alloc_root(CILRoot::StLoc(3, inc));
+        let inc_done = jump(asm, 4);
+        // Block 3: no bits are discarded.
+        let neg_shift = asm.biop(zero_i32, shift, BinOp::Sub);
+        let widened = big_shl(asm, mantissa, neg_shift);
+        let widened = asm.alloc_root(CILRoot::StLoc(3, widened));
+        let widened_done = jump(asm, 4);
+        // Block 4: assemble the bits. The implicit bit gets added to the exponent, which also handles the mantissa
+        // overflowing after rounding, and subnormals becoming normal.
+        let base_big = big_from(asm, base, Int::I32);
+        let exp_shift = asm.alloc_node(precision - 1);
+        let base_big = big_shl(asm, base_big, exp_shift);
+        let bits_val = big_op(asm, "op_Addition", base_big, quotient);
+        let set_bits = asm.alloc_root(CILRoot::StLoc(6, bits_val));
+        let inf = big_const(asm, format.max_exponent(), format.precision - 1);
+        let finite = big_cmp(asm, "op_LessThan", bits, inf);
+        let finite = branch(asm, 6, BranchCond::True(finite));
+        let overflow = asm.alloc_root(CILRoot::StLoc(6, inf));
+        let overflow_done = jump(asm, 6);
+        // Block 5: zero.
+        let set_zero = asm.alloc_root(CILRoot::StLoc(6, zero));
+        let zero_done = jump(asm, 6);
+        // Block 6: apply the sign.
+        let positive = branch(asm, 7, BranchCond::False(sign));
+        let sign_bit = big_const(asm, 1, format.width() - 1);
+        let signed = big_op(asm, "op_BitwiseOr", bits, sign_bit);
+        let signed = asm.alloc_root(CILRoot::StLoc(6, signed));
+        let signed_done = jump(asm, 7);
+        // Block 7: convert the bits to a float.
+        let ret_bits: Vec<_> = match format.float {
+            Float::F128 => {
+                let low_mask = big_const(asm, u64::MAX, 0);
+                let lo = big_op(asm, "op_BitwiseAnd", bits, low_mask);
+                let lo = big_to(asm, lo, Int::U64);
+                let shift = asm.alloc_node(64_i32);
+                let hi = big_shr(asm, bits, shift);
+                let hi = big_to(asm, hi, Int::U64);
+                ret_halves(asm, hi, lo).into()
+            }
+            Float::F64 | Float::F32 => {
+                let (int, name) = if matches!(format.float, Float::F64) {
+                    (Int::U64, "UInt64BitsToDouble")
+                } else {
+                    (Int::U32, "UInt32BitsToSingle")
+                };
+                let bits = big_to(asm, bits, int);
+                let converter = ClassRef::bit_converter(asm);
+                let converter = asm[converter].clone();
+                let name = asm.alloc_string(name);
+                let mref =
+                    converter.static_mref(&[Type::Int(int)], Type::Float(format.float), name, asm);
+                let float = asm.alloc_node(CILNode::call(mref, [bits]));
+                vec![ret(asm, float)]
+            }
+            Float::F16 => unreachable!(),
+        };
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(
+                    vec![
+                        is_zero,
+                        set_shift,
+                        set_base,
+                        is_normal,
+                        subnormal_shift,
+                        subnormal_base,
+                        to_round,
+                    ],
+                    0,
+                    None,
+                ),
+                BasicBlock::new(
+                    vec![
+                        exact,
+                        set_quotient,
+                        set_reminder,
+                        set_half,
+                        above_half,
+                        below_half,
+                        tie_sticky,
+                        tie_even,
+                        round_up,
+                    ],
+                    1,
+                    None,
+                ),
+                BasicBlock::new(vec![inc, inc_done], 2, None),
+                BasicBlock::new(vec![widened, widened_done], 3, None),
+                BasicBlock::new(vec![set_bits, finite, overflow, overflow_done], 4, None),
+                BasicBlock::new(vec![set_zero, zero_done], 5, None),
+                BasicBlock::new(vec![positive, signed, signed_done], 6, None),
+                BasicBlock::new(ret_bits, 7, None),
+            ],
+            locals: vec![
+                (None, asm.alloc_type(Type::Float(format.float))),
+                (None, i32_tpe),
+                (None, i32_tpe),
+                (None, big_tpe),
+                (None, big_tpe),
+                (None, big_tpe),
+                (None, big_tpe),
+            ],
+        }
+    };
+    patcher.insert(name, Box::new(generator));
+}
+/// Addition, or subtraction if `negate_rhs` is set.
+fn insert_add(asm: &mut Assembly, patcher: &mut MissingMethodPatcher, negate_rhs: bool) {
+    let name = asm.alloc_string(if negate_rhs { "sub_f128" } else { "add_f128" });
+    let generator = move |_, asm: &mut Assembly| {
+        let big = big_integer(asm);
+        let lhs = Halves::arg(asm, 0);
+        let rhs = Halves::arg(asm, 1);
+        let rhs = if negate_rhs { rhs.negated(asm) } else { rhs };
+        // Block 0: exact sum, with both mantissas aligned to the smaller exponent.
+        let lhs_nan = lhs.is_nan(asm);
+        let rhs_nan = rhs.is_nan(asm);
+        let any_nan = asm.biop(lhs_nan, rhs_nan, BinOp::Or);
+        let any_nan = branch(asm, 1, BranchCond::True(any_nan));
+        let lhs_inf = lhs.is_special(asm);
+        let lhs_inf = branch(asm, 2, BranchCond::True(lhs_inf));
+        let rhs_inf = rhs.is_special(asm);
+        let rhs_inf = branch(asm, 3, BranchCond::True(rhs_inf));
+        let lhs_zero = lhs.is_zero(asm);
+        let rhs_zero = rhs.is_zero(asm);
+        let both_zero = asm.biop(lhs_zero, rhs_zero, BinOp::And);
+        let both_zero = branch(asm, 4, BranchCond::True(both_zero));
+        let lhs_exp = lhs.exponent(asm);
+        let rhs_exp = rhs.exponent(asm);
+        let math = ClassRef::math(asm);
+        let math = asm[math].clone();
+        let min = asm.alloc_string("Min");
+        let min = math.static_mref(
+            &[Type::Int(Int::I32), Type::Int(Int::I32)],
+            Type::Int(Int::I32),
+            min,
+            asm,
+        );
+        let min = asm.alloc_node(CILNode::call(min, [lhs_exp, rhs_exp]));
+        let set_exp = asm.alloc_root(CILRoot::StLoc(2, min));
+        let exp = asm.alloc_node(CILNode::LdLoc(2));
+        let [lhs_signed, rhs_signed] = [lhs, rhs].map(|val| {
+            let mantissa = val.mantissa(asm);
+            let signum = val.signum(asm);
+            let mantissa = big_op(asm, "op_Multiply", mantissa, signum);
+            let val_exp = val.exponent(asm);
+            let shift = asm.biop(val_exp, exp, BinOp::Sub);
+            big_shl(asm, mantissa, shift)
+        });
+        let sum = big_op(asm, "op_Addition", lhs_signed, rhs_signed);
+        let set_sum = asm.alloc_root(CILRoot::StLoc(1, sum));
+        let sum = asm.alloc_node(CILNode::LdLoc(1));
+        let is_neg = big_call(asm, "IsNegative", &[(sum, big)], Type::Bool);
+        let abs = big_call(asm, "Abs", &[(sum, big)], big);
+        let sticky = asm.alloc_node(false);
+        let res = round_pack(asm, Format::F128, is_neg, abs, exp, sticky);
+        let ret_sum = ret(asm, res);
+        // Block 2: lhs is infinite. Infinities of different signs cancel out into a NaN.
+        let rhs_special = rhs.is_special(asm);
+        let lhs_sign = lhs.sign(asm);
+        let rhs_sign = rhs.sign(asm);
+        let same_sign = asm.biop(lhs_sign, rhs_sign, BinOp::Eq);
+        let different_sign = not(asm, same_sign);
+        let cancels = asm.biop(rhs_special, different_sign, BinOp::And);
+        let cancels = branch(asm, 1, BranchCond::True(cancels));
+        let ret_lhs = ret_halves(asm, lhs.hi, lhs.lo);
+        // Block 3: rhs is infinite, and lhs is finite.
+        let ret_rhs = ret_halves(asm, rhs.hi, rhs.lo);
+        // Block 4: both are zero. The result is only negative if both of them are.
+        let sign = asm.alloc_node(SIGN);
+        let both_neg = asm.biop(lhs.hi, rhs.hi, BinOp::And);
+        let both_neg = asm.biop(both_neg, sign, BinOp::And);
+        let zero = asm.alloc_node(0_u64);
+        let ret_zero = ret_halves(asm, both_neg, zero);
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(
+                    vec![
+                        any_nan, lhs_inf, rhs_inf, both_zero, set_exp, set_sum, ret_sum,
+                    ],
+                    0,
+                    None,
+                ),
+                BasicBlock::new(ret_nan(asm).into(), 1, None),
+                BasicBlock::new([&[cancels][..], &ret_lhs[..]].concat(), 2, None),
+                BasicBlock::new(ret_rhs.into(), 3, None),
+                BasicBlock::new(ret_zero.into(), 4, None),
+            ],
+            locals: vec![
+                result_local(asm),
+                (None, asm.alloc_type(big)),
+                (None, asm.alloc_type(Type::Int(Int::I32))),
+            ],
+        }
+    };
+    patcher.insert(name, Box::new(generator));
+}
+fn insert_mul(asm: &mut Assembly, patcher: &mut MissingMethodPatcher) {
+    let name = asm.alloc_string("mul_f128");
+    let generator = move |_, asm: &mut Assembly| {
+        let lhs = Halves::arg(asm, 0);
+        let rhs = Halves::arg(asm, 1);
+        let sign_hi = asm.biop(lhs.hi, rhs.hi, BinOp::XOr);
+        let sign = Halves {
+            hi: sign_hi,
+            lo: lhs.lo,
+        };
+        // Block 0: exact product.
+        let lhs_nan = lhs.is_nan(asm);
+        let rhs_nan = rhs.is_nan(asm);
+        let any_nan = asm.biop(lhs_nan, rhs_nan, BinOp::Or);
+        let any_nan = branch(asm, 1, BranchCond::True(any_nan));
+        let lhs_inf = lhs.is_special(asm);
+        let rhs_inf = rhs.is_special(asm);
+        let any_inf = asm.biop(lhs_inf, rhs_inf, BinOp::Or);
+        let any_inf = branch(asm, 2, BranchCond::True(any_inf));
+        let lhs_mantissa = lhs.mantissa(asm);
+        let rhs_mantissa = rhs.mantissa(asm);
+        let mantissa = big_op(asm, "op_Multiply", lhs_mantissa, rhs_mantissa);
+        let lhs_exp = lhs.exponent(asm);
+        let rhs_exp = rhs.exponent(asm);
+        let exp = asm.biop(lhs_exp, rhs_exp, BinOp::Add);
+        let is_neg = sign.sign(asm);
+        let sticky = asm.alloc_node(false);
+        let res = round_pack(asm, Format::F128, is_neg, mantissa, exp, sticky);
+        let ret_product = ret(asm, res);
+        // Block 2: infinity times zero is NaN, and infinity otherwise.
+        let lhs_zero = lhs.is_zero(asm);
+        let rhs_zero = rhs.is_zero(asm);
+        let any_zero = asm.biop(lhs_zero, rhs_zero, BinOp::Or);
+        let any_zero = branch(asm, 1, BranchCond::True(any_zero));
+        let ret_inf = ret_signed(asm, sign, INF_HI);
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(vec![any_nan, any_inf, ret_product], 0, None),
+                BasicBlock::new(ret_nan(asm).into(), 1, None),
+                BasicBlock::new([&[any_zero][..], &ret_inf[..]].concat(), 2, None),
+            ],
+            locals: vec![result_local(asm)],
+        }
+    };
+    patcher.insert(name, Box::new(generator));
+}
+/// Returns a `f128`, with the upper half `hi`, the lower half of 0, and the sign of `sign`.
+fn ret_signed(asm: &mut Assembly, sign: Halves, hi: u64) -> [Interned<CILRoot>; 3] {
+    let sign_mask = asm.alloc_node(SIGN);
+    let sign = asm.biop(sign.hi, sign_mask, BinOp::And);
+    let hi = asm.alloc_node(hi);
+    let hi = asm.biop(sign, hi, BinOp::Or);
+    let lo = asm.alloc_node(0_u64);
+    ret_halves(asm, hi, lo)
+}
+fn insert_div(asm: &mut Assembly, patcher: &mut MissingMethodPatcher) {
+    // Enough extra bits for the quotient of any two mantissas to have 2 more bits than a `f128`, so it can be rounded.
+    const EXTRA_BITS: i32 = 2 * 113 + 2;
+    let name = asm.alloc_string("div_f128");
+    let generator = move |_, asm: &mut Assembly| {
+        let big = big_integer(asm);
+        let big = asm.alloc_type(big);
+        let lhs = Halves::arg(asm, 0);
+        let rhs = Halves::arg(asm, 1);
+        let sign_hi = asm.biop(lhs.hi, rhs.hi, BinOp::XOr);
+        let sign = Halves {
+            hi: sign_hi,
+            lo: lhs.lo,
+        };
+        // Block 0: quotient, with the reminder being used as the sticky bit.
+        let lhs_nan = lhs.is_nan(asm);
+        let rhs_nan = rhs.is_nan(asm);
+        let any_nan = asm.biop(lhs_nan, rhs_nan, BinOp::Or);
+        let any_nan = branch(asm, 1, BranchCond::True(any_nan));
+        let lhs_inf = lhs.is_special(asm);
+        let lhs_inf = branch(asm, 2, BranchCond::True(lhs_inf));
+        let rhs_inf = rhs.is_special(asm);
+        let rhs_inf = branch(asm, 3, BranchCond::True(rhs_inf));
+        let rhs_zero = rhs.is_zero(asm);
+        let rhs_zero = branch(asm, 4, BranchCond::True(rhs_zero));
+        let lhs_mantissa = lhs.mantissa(asm);
+        let extra_bits = asm.alloc_node(EXTRA_BITS);
+        let numerator = big_shl(asm, lhs_mantissa, extra_bits);
+        let set_numerator = asm.alloc_root(CILRoot::StLoc(1, numerator));
+        let numerator = asm.alloc_node(CILNode::LdLoc(1));
+        let rhs_mantissa = rhs.mantissa(asm);
+        let quotient = big_op(asm, "op_Division", numerator, rhs_mantissa);
+        let reminder = big_op(asm, "op_Modulus", numerator, rhs_mantissa);
+        let zero = big_const(asm, 0, 0);
+        let exact = big_cmp(asm, "op_Equality", reminder, zero);
+        let sticky = not(asm, exact);
+        let lhs_exp = lhs.exponent(asm);
+        let rhs_exp = rhs.exponent(asm);
+        let exp = asm.biop(lhs_exp, rhs_exp, BinOp::Sub);
+        let exp = asm.biop(exp, extra_bits, BinOp::Sub);
+        let is_neg = sign.sign(asm);
+        let res = round_pack(asm, Format::F128, is_neg, quotient, exp, sticky);
+        let ret_quotient = ret(asm, res);
+        // Block 2: lhs is infinite.
+        let rhs_special = rhs.is_special(asm);
+        let inf_by_inf = branch(asm, 1, BranchCond::True(rhs_special));
+        let ret_inf = ret_signed(asm, sign, INF_HI);
+        // Block 3: rhs is infinite, and lhs is finite.
+        let ret_zero = ret_signed(asm, sign, 0);
+        // Block 4: rhs is zero.
+        let lhs_zero = lhs.is_zero(asm);
+        let zero_by_zero = branch(asm, 1, BranchCond::True(lhs_zero));
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(
+                    vec![
+                        any_nan,
+                        lhs_inf,
+                        rhs_inf,
+                        rhs_zero,
+                        set_numerator,
+                        ret_quotient,
+                    ],
+                    0,
+                    None,
+                ),
+                BasicBlock::new(ret_nan(asm).into(), 1, None),
+                BasicBlock::new([&[inf_by_inf][..], &ret_inf[..]].concat(), 2, None),
+                BasicBlock::new(ret_zero.into(), 3, None),
+                BasicBlock::new([&[zero_by_zero][..], &ret_inf[..]].concat(), 4, None),
+            ],
+            locals: vec![result_local(asm), (None, big)],
+        }
+    };
+    patcher.insert(name, Box::new(generator));
+}
+/// The reminder of a truncated division, like C's `fmod`. It is always exact.
+fn insert_rem(asm: &mut Assembly, patcher: &mut MissingMethodPatcher) {
+    let name = asm.alloc_string("mod_f128");
+    let generator = move |_, asm: &mut Assembly| {
+        let lhs = Halves::arg(asm, 0);
+        let rhs = Halves::arg(asm, 1);
+        // Block 0: reminder of the mantissas, aligned to the smaller exponent.
+        let lhs_nan = lhs.is_nan(asm);
+        let rhs_nan = rhs.is_nan(asm);
+        let lhs_inf = lhs.is_special(asm);
+        let rhs_zero = rhs.is_zero(asm);
+        let nan = asm.biop(lhs_nan, rhs_nan, BinOp::Or);
+        let nan = asm.biop(nan, lhs_inf, BinOp::Or);
+        let nan = asm.biop(nan, rhs_zero, BinOp::Or);
+        let nan = branch(asm, 1, BranchCond::True(nan));
+        let rhs_inf = rhs.is_special(asm);
+        let lhs_zero = lhs.is_zero(asm);
+        let unchanged = asm.biop(rhs_inf, lhs_zero, BinOp::Or);
+        let unchanged = branch(asm, 2, BranchCond::True(unchanged));
+        let lhs_exp = lhs.exponent(asm);
+        let rhs_exp = rhs.exponent(asm);
+        let math = ClassRef::math(asm);
+        let math = asm[math].clone();
+        let min = asm.alloc_string("Min");
+        let min = math.static_mref(
+            &[Type::Int(Int::I32), Type::Int(Int::I32)],
+            Type::Int(Int::I32),
+            min,
+            asm,
+        );
+        let min = asm.alloc_node(CILNode::call(min, [lhs_exp, rhs_exp]));
+        let set_exp = asm.alloc_root(CILRoot::StLoc(1, min));
+        let exp = asm.alloc_node(CILNode::LdLoc(1));
+        let [lhs_aligned, rhs_aligned] = [lhs, rhs].map(|val| {
+            let mantissa = val.mantissa(asm);
+            let val_exp = val.exponent(asm);
+            let shift = asm.biop(val_exp, exp, BinOp::Sub);
+            big_shl(asm, mantissa, shift)
+        });
+        let reminder = big_op(asm, "op_Modulus", lhs_aligned, rhs_aligned);
+        let is_neg = lhs.sign(asm);
+        let sticky = asm.alloc_node(false);
+        let res = round_pack(asm, Format::F128, is_neg, reminder, exp, sticky);
+        let ret_reminder = ret(asm, res);
+        // Block 2: lhs is returned as-is.
+        let ret_lhs = ret_halves(asm, lhs.hi, lhs.lo);
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(vec![nan, unchanged, set_exp, ret_reminder], 0, None),
+                BasicBlock::new(ret_nan(asm).into(), 1, None),
+                BasicBlock::new(ret_lhs.into(), 2, None),
+            ],
+            locals: vec![
+                result_local(asm),
+                (None, asm.alloc_type(Type::Int(Int::I32))),
+            ],
+        }
+    };
+    patcher.insert(name, Box::new(generator));
+}
+/// Ordered comparisons: `lt_f128`, or `gt_f128` if `swap` is set. Comparisons with NaN are always false.
+fn insert_lt(asm: &mut Assembly, patcher: &mut MissingMethodPatcher, swap: bool) {
+    let name = asm.alloc_string(if swap { "gt_f128" } else { "lt_f128" });
+    let generator = move |_, asm: &mut Assembly| {
+        let (lhs, rhs) = if swap { (1, 0) } else { (0, 1) };
+        let lhs = Halves::arg(asm, lhs);
+        let rhs = Halves::arg(asm, rhs);
+        // Block 0: compare signs, and then magnitudes.
+        let lhs_nan = lhs.is_nan(asm);
+        let rhs_nan = rhs.is_nan(asm);
+        let lhs_zero = lhs.is_zero(asm);
+        let rhs_zero = rhs.is_zero(asm);
+        let unordered = asm.biop(lhs_nan, rhs_nan, BinOp::Or);
+        let both_zero = asm.biop(lhs_zero, rhs_zero, BinOp::And);
+        let unordered = asm.biop(unordered, both_zero, BinOp::Or);
+        let unordered = branch(asm, 1, BranchCond::True(unordered));
+        let lhs_sign = lhs.sign_bit(asm);
+        let rhs_sign = rhs.sign_bit(asm);
+        let different_signs = branch(asm, 2, BranchCond::Ne(lhs_sign, rhs_sign));
+        let lhs_neg = lhs.sign(asm);
+        let both_neg = branch(asm, 3, BranchCond::True(lhs_neg));
+        let mag_lt = magnitude_lt(asm, lhs, rhs);
+        let ret_pos = ret(asm, mag_lt);
+        // Block 1: unordered, or equal zeroes.
+        let false_val = asm.alloc_node(false);
+        let ret_false = ret(asm, false_val);
+        // Block 2: the negative value is smaller.
+        let ret_sign = ret(asm, lhs_neg);
+        // Block 3: both negative, so the bigger magnitude is smaller.
+        let mag_gt = magnitude_lt(asm, rhs, lhs);
+        let ret_neg = ret(asm, mag_gt);
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(vec![unordered, different_signs, both_neg, ret_pos], 0, None),
+                BasicBlock::new(vec![ret_false], 1, None),
+                BasicBlock::new(vec![ret_sign], 2, None),
+                BasicBlock::new(vec![ret_neg], 3, None),
+            ],
+            locals: vec![],
+        }
+    };
+    patcher.insert(name, Box::new(generator));
+}
+/// Checks if the absolute value of `lhs` is smaller than the one of `rhs`, assuming neither is NaN.
+fn magnitude_lt(asm: &mut Assembly, lhs: Halves, rhs: Halves) -> Interned<CILNode> {
+    let lhs_hi = lhs.abs_hi(asm);
+    let rhs_hi = rhs.abs_hi(asm);
+    let hi_lt = asm.biop(lhs_hi, rhs_hi, BinOp::LtUn);
+    let hi_eq = asm.biop(lhs_hi, rhs_hi, BinOp::Eq);
+    let lo_lt = asm.biop(lhs.lo, rhs.lo, BinOp::LtUn);
+    let lo_lt = asm.biop(hi_eq, lo_lt, BinOp::And);
+    asm.biop(hi_lt, lo_lt, BinOp::Or)
+}
+fn insert_eq(asm: &mut Assembly, patcher: &mut MissingMethodPatcher) {
+    let name = asm.alloc_string("eq_f128");
+    let generator = move |_, asm: &mut Assembly| {
+        let lhs = Halves::arg(asm, 0);
+        let rhs = Halves::arg(asm, 1);
+        // Equal bits, or zeroes of any sign, are equal. NaNs are never equal.
+        let lhs_nan = lhs.is_nan(asm);
+        let rhs_nan = rhs.is_nan(asm);
+        let any_nan = asm.biop(lhs_nan, rhs_nan, BinOp::Or);
+        let ordered = not(asm, any_nan);
+        let hi_eq = asm.biop(lhs.hi, rhs.hi, BinOp::Eq);
+        let lo_eq = asm.biop(lhs.lo, rhs.lo, BinOp::Eq);
+        let bits_eq = asm.biop(hi_eq, lo_eq, BinOp::And);
+        let lhs_zero = lhs.is_zero(asm);
+        let rhs_zero = rhs.is_zero(asm);
+        let both_zero = asm.biop(lhs_zero, rhs_zero, BinOp::And);
+        let eq = asm.biop(bits_eq, both_zero, BinOp::Or);
+        let eq = asm.biop(ordered, eq, BinOp::And);
+        let ret_eq = ret(asm, eq);
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![ret_eq], 0, None)],
+            locals: vec![],
+        }
+    };
+    patcher.insert(name, Box::new(generator));
+}
+fn insert_neg(asm: &mut Assembly, patcher: &mut MissingMethodPatcher) {
+    let name = asm.alloc_string("neg_f128");
+    let generator = move |_, asm: &mut Assembly| {
+        let val = Halves::arg(asm, 0).negated(asm);
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(
+                ret_halves(asm, val.hi, val.lo).into(),
+                0,
+                None,
+            )],
+            locals: vec![result_local(asm)],
+        }
+    };
+    patcher.insert(name, Box::new(generator));
+}
+fn insert_copysign(asm: &mut Assembly, patcher: &mut MissingMethodPatcher) {
+    let name = asm.alloc_string("copysignf128");
+    let generator = move |_, asm: &mut Assembly| {
+        let magnitude = Halves::arg(asm, 0);
+        let sign = Halves::arg(asm, 1);
+        let abs_hi = magnitude.abs_hi(asm);
+        let sign_mask = asm.alloc_node(SIGN);
+        let sign = asm.biop(sign.hi, sign_mask, BinOp::And);
+        let hi = asm.biop(abs_hi, sign, BinOp::Or);
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(
+                ret_halves(asm, hi, magnitude.lo).into(),
+                0,
+                None,
+            )],
+            locals: vec![result_local(asm)],
+        }
+    };
+    patcher.insert(name, Box::new(generator));
+}
+/// Widens a `f32` or a `f64` to a `f128`. This is always exact.
+fn insert_float_to_f128(asm: &mut Assembly, patcher: &mut MissingMethodPatcher, float: Float) {
+    let name = asm.alloc_string(format!("cast_{}_f128", float.name()));
+    let generator = move |_, asm: &mut Assembly| {
+        let f64_format = Format::F64;
+        // A `f32` can be widened to a `f64` exactly, so only the bits of a `f64` need to be handled.
+        let input = asm.alloc_node(CILNode::LdArg(0));
+        let input = asm.alloc_node(CILNode::FloatCast {
+            input,
+            target: Float::F64,
+            is_signed: true,
+        });
+        let converter = ClassRef::bit_converter(asm);
+        let converter = asm[converter].clone();
+        let to_bits = asm.alloc_string("DoubleToUInt64Bits");
+        let to_bits = converter.static_mref(
+            &[Type::Float(Float::F64)],
+            Type::Int(Int::U64),
+            to_bits,
+            asm,
+        );
+        let bits = asm.alloc_node(CILNode::call(to_bits, [input]));
+        let set_bits = asm.alloc_root(CILRoot::StLoc(1, bits));
+        let bits = asm.alloc_node(CILNode::LdLoc(1));
+        let fraction_bits = i32::try_from(f64_format.precision - 1).unwrap();
+        let fraction_mask = asm.alloc_node((1_u64 << fraction_bits) - 1);
+        let fraction = asm.biop(bits, fraction_mask, BinOp::And);
+        let shift = asm.alloc_node(fraction_bits);
+        let exp = asm.biop(bits, shift, BinOp::ShrUn);
+        let max_exp = asm.alloc_node(f64_format.max_exponent());
+        let exp = asm.biop(exp, max_exp, BinOp::And);
+        // Block 0: normal and subnormal values.
+        let special = branch(asm, 1, BranchCond::Eq(exp, max_exp));
+        // Only a biased exponent of 0 stays below 1 << 11, after adding the max exponent to it.
+        let is_normal = asm.biop(exp, max_exp, BinOp::Add);
+        let exp_bits = asm.alloc_node(i32::try_from(f64_format.exponent_bits).unwrap());
+        let is_normal = asm.biop(is_normal, exp_bits, BinOp::ShrUn);
+        let implicit = asm.biop(is_normal, shift, BinOp::Shl);
+        let mantissa = asm.biop(fraction, implicit, BinOp::Or);
+        let mantissa = big_from(asm, mantissa, Int::U64);
+        let exponent = asm.biop(exp, is_normal, BinOp::Sub);
+        let exponent = to_i32(asm, exponent);
+        let offset = f64_format.bias() + fraction_bits - 1;
+        let exponent = asm.biop(exponent, CILNode::from(offset), BinOp::Sub);
+        let sign_shift = asm.alloc_node(63_i32);
+        let sign = asm.biop(bits, sign_shift, BinOp::ShrUn);
+        let sign = asm.biop(sign, CILNode::from(1_u64), BinOp::Eq);
+        let sticky = asm.alloc_node(false);
+        let res = round_pack(asm, Format::F128, sign, mantissa, exponent, sticky);
+        let ret_finite = ret(asm, res);
+        // Block 1: infinities and NaNs keep their sign and payload, aligned to the top of the `f128` fraction.
+        let sign_mask = asm.alloc_node(SIGN);
+        let sign = asm.biop(bits, sign_mask, BinOp::And);
+        let inf_hi = asm.alloc_node(INF_HI);
+        let hi = asm.biop(sign, inf_hi, BinOp::Or);
+        let widen = i32::try_from(Format::F128.precision - 1).unwrap() - fraction_bits;
+        let payload_hi_shift = asm.alloc_node(64 - widen);
+        let payload_hi = asm.biop(fraction, payload_hi_shift, BinOp::ShrUn);
+        let hi = asm.biop(hi, payload_hi, BinOp::Or);
+        let widen = asm.alloc_node(widen);
+        let lo = asm.biop(fraction, widen, BinOp::Shl);
+        let ret_special = ret_halves(asm, hi, lo);
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(vec![set_bits, special, ret_finite], 0, None),
+                BasicBlock::new(ret_special.into(), 1, None),
+            ],
+            locals: vec![
+                result_local(asm),
+                (None, asm.alloc_type(Type::Int(Int::U64))),
+            ],
+        }
+    };
+    patcher.insert(name, Box::new(generator));
+}
+/// Narrows a `f128` to a `f32` or a `f64`, rounding it to nearest.
+fn insert_f128_to_float(asm: &mut Assembly, patcher: &mut MissingMethodPatcher, float: Float) {
+    let name = asm.alloc_string(format!("cast_f128_{}", float.name()));
+    let generator = move |_, asm: &mut Assembly| {
+        let val = Halves::arg(asm, 0);
+        // Block 0: finite values.
+        let nan = val.is_nan(asm);
+        let nan = branch(asm, 1, BranchCond::True(nan));
+        let inf = val.is_special(asm);
+        let inf = branch(asm, 2, BranchCond::True(inf));
+        let sign = val.sign(asm);
+        let mantissa = val.mantissa(asm);
+        let exponent = val.exponent(asm);
+        let sticky = asm.alloc_node(false);
+        let res = round_pack(asm, Format::of(float), sign, mantissa, exponent, sticky);
+        let ret_finite = ret(asm, res);
+        // Block 1: NaN.
+        let (nan_val, inf_val, neg_inf_val) = match float {
+            Float::F32 => (
+                Const::F32(HashableF32(f32::NAN)),
+                Const::F32(HashableF32(f32::INFINITY)),
+                Const::F32(HashableF32(f32::NEG_INFINITY)),
+            ),
+            Float::F64 => (
+                Const::F64(HashableF64(f64::NAN)),
+                Const::F64(HashableF64(f64::INFINITY)),
+                Const::F64(HashableF64(f64::NEG_INFINITY)),
+            ),
+            Float::F16 | Float::F128 => unreachable!(),
+        };
+        let nan_val = asm.alloc_node(nan_val);
+        let ret_nan = ret(asm, nan_val);
+        // Block 2: positive infinity, Block 3: negative infinity.
+        let neg = branch(asm, 3, BranchCond::True(sign));
+        let inf_val = asm.alloc_node(inf_val);
+        let ret_inf = ret(asm, inf_val);
+        let neg_inf_val = asm.alloc_node(neg_inf_val);
+        let ret_neg_inf = ret(asm, neg_inf_val);
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(vec![nan, inf, ret_finite], 0, None),
+                BasicBlock::new(vec![ret_nan], 1, None),
+                BasicBlock::new(vec![neg, ret_inf], 2, None),
+                BasicBlock::new(vec![ret_neg_inf], 3, None),
+            ],
+            locals: vec![],
+        }
+    };
+    patcher.insert(name, Box::new(generator));
+}
+/// Converts an intiger to a `f128`. Only 128 bit intigers can need rounding.
+fn insert_int_to_f128(asm: &mut Assembly, patcher: &mut MissingMethodPatcher, int: Int) {
+    let name = asm.alloc_string(format!("cast_{}_f128", int.name()));
+    let generator = move |_, asm: &mut Assembly| {
+        let big = big_integer(asm);
+        let val = asm.alloc_node(CILNode::LdArg(0));
+        let val = big_from(asm, val, int);
+        let sign = big_call(asm, "IsNegative", &[(val, big)], Type::Bool);
+        let abs = big_call(asm, "Abs", &[(val, big)], big);
+        let exponent = asm.alloc_node(0_i32);
+        let sticky = asm.alloc_node(false);
+        let res = round_pack(asm, Format::F128, sign, abs, exponent, sticky);
+        let ret_res = ret(asm, res);
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![ret_res], 0, None)],
+            locals: vec![],
+        }
+    };
+    patcher.insert(name, Box::new(generator));
+}
+/// Converts a `f128` to an intiger, truncating it. Out of range values saturate, and NaNs become 0, just like in Rust.
+fn insert_f128_to_int(asm: &mut Assembly, patcher: &mut MissingMethodPatcher, int: Int) {
+    let name = asm.alloc_string(format!("cast_f128_{}", int.name()));
+    let generator = move |_, asm: &mut Assembly| {
+        let big = big_integer(asm);
+        let val = Halves::arg(asm, 0);
+        // Block 0: the mantissa gets shifted right for negative exponents, which truncates it.
+        let nan = val.is_nan(asm);
+        let nan = branch(asm, 1, BranchCond::True(nan));
+        let mantissa = val.mantissa(asm);
+        let exponent = val.exponent(asm);
+        let truncated = big_shl(asm, mantissa, exponent);
+        let signum = val.signum(asm);
+        let truncated = big_op(asm, "op_Multiply", truncated, signum);
+        let min = int.min(asm);
+        let min = asm.alloc_node(min);
+        let min = big_from(asm, min, int);
+        let max = int.max(asm);
+        let max = asm.alloc_node(max);
+        let max = big_from(asm, max, int);
+        let clamped = big_call(
+            asm,
+            "Clamp",
+            &[(truncated, big), (min, big), (max, big)],
+            big,
+        );
+        let res = big_to(asm, clamped, int);
+        let ret_res = ret(asm, res);
+        // Block 1: NaN.
+        let zero = asm.alloc_node(int.zero());
+        let ret_zero = ret(asm, zero);
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(vec![nan, ret_res], 0, None),
+                BasicBlock::new(vec![ret_zero], 1, None),
+            ],
+            locals: vec![],
+        }
+    };
+    patcher.insert(name, Box::new(generator));
+}
+/// Generates the software implementations of all `f128` ops.
+pub(super) fn generate_soft_f128_ops(
+    asm: &mut Assembly,
+    patcher: &mut MissingMethodPatcher,
+    ints: &[Int],
+    floats: &[Float],
+) {
+    insert_add(asm, patcher, false);
+    insert_add(asm, patcher, true);
+    insert_mul(asm, patcher);
+    insert_div(asm, patcher);
+    insert_rem(asm, patcher);
+    insert_lt(asm, patcher, false);
+    insert_lt(asm, patcher, true);
+    insert_eq(asm, patcher);
+    insert_neg(asm, patcher);
+    insert_copysign(asm, patcher);
+    insert_round_pack(asm, patcher, Format::F128);
+    for float in floats {
+        insert_round_pack(asm, patcher, Format::of(*float));
+        insert_float_to_f128(asm, patcher, *float);
+        insert_f128_to_float(asm, patcher, *float);
+    }
+    for int in ints {
+        insert_int_to_f128(asm, patcher, *int);
+        insert_f128_to_int(asm, patcher, *int);
+    }
+}
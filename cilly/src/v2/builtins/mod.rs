@@ -13,12 +13,14 @@ use super::{
 
 pub mod atomics;
 pub mod casts;
+pub mod delegate;
 pub mod math;
 pub mod select;
 pub mod thread;
 pub use thread::*;
 pub mod int128;
 pub use int128::*;
+pub mod f128;
 pub mod f16;
 pub use f16::*;
 pub mod simd;
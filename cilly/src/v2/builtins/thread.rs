@@ -1,7 +1,8 @@
 use super::{
     super::{
         asm::MissingMethodPatcher, cilnode::MethodKind, Access, Assembly, BasicBlock, CILNode,
-        CILRoot, ClassDef, ClassRef, Const, FieldDesc, Int, MethodDef, MethodImpl, Type,
+        CILRoot, ClassDef, ClassRef, Const, FieldDesc, FnSig, Int, MethodDef, MethodImpl,
+        MethodRef, Type,
     },
     UNMANAGED_THREAD_START,
 };
@@ -361,6 +362,168 @@ fn insert_pthread_create(asm: &mut Assembly, patcher: &mut MissingMethodPatcher)
     };
     patcher.insert(fn_name, Box::new(generator));
 }
+/// The head of this thread's list of thread local destructors, registered by `__cxa_thread_atexit_impl`.
+/// Each node of the list consists of 3 pointer-sized slots: the destructor, the object it drops, and the next node.
+fn tls_dtors(asm: &mut Assembly) -> Interned<StaticFieldDesc> {
+    let main_module = asm.main_module();
+    let void_ptr = asm.nptr(Type::Void);
+    asm.add_static(void_ptr, "tls_dtors", true, main_module, None, false)
+}
+/// The signature of a thread local destructor.
+fn tls_dtor_sig(asm: &mut Assembly) -> Interned<FnSig> {
+    let void_ptr = asm.nptr(Type::Void);
+    asm.sig([void_ptr], Type::Void)
+}
+/// Returns the address of the slot `slot` of a destructor list node, as a pointer to `tpe`.
+fn tls_dtor_slot(
+    asm: &mut Assembly,
+    node: Interned<CILNode>,
+    slot: i32,
+    tpe: Type,
+) -> Interned<CILNode> {
+    let tpe = asm.alloc_type(tpe);
+    let addr = if slot == 0 {
+        node
+    } else {
+        let usize_size = asm.size_of(Int::USize);
+        let slot = asm.alloc_node(Const::I32(slot));
+        let offset = asm.alloc_node(CILNode::BinOp(usize_size, slot, BinOp::Mul));
+        asm.biop(
+            CILNode::IntCast {
+                input: offset,
+                target: Int::USize,
+                extend: ExtendKind::ZeroExtend,
+            },
+            node,
+            BinOp::Add,
+        )
+    };
+    asm.alloc_node(CILNode::PtrCast(addr, Box::new(PtrCastRes::Ptr(tpe))))
+}
+fn insert_cxa_thread_atexit_impl(asm: &mut Assembly, patcher: &mut MissingMethodPatcher) {
+    let name = asm.alloc_string("__cxa_thread_atexit_impl");
+    let generator = move |_, asm: &mut Assembly| {
+        let void_ptr = asm.nptr(Type::Void);
+        let dtor_tpe = Type::FnPtr(tls_dtor_sig(asm));
+        let tls_dtors = tls_dtors(asm);
+        // Allocate a new node
+        let usize_size = asm.size_of(Int::USize);
+        let three = asm.alloc_node(Const::I32(3));
+        let node_size = asm.alloc_node(CILNode::BinOp(usize_size, three, BinOp::Mul));
+        let node_size = asm.alloc_node(CILNode::IntCast {
+            input: node_size,
+            target: Int::USize,
+            extend: ExtendKind::ZeroExtend,
+        });
+        let align = asm.alloc_node(CILNode::IntCast {
+            input: usize_size,
+            target: Int::USize,
+            extend: ExtendKind::ZeroExtend,
+        });
+        let aligned_alloc = MethodRef::aligned_alloc(asm);
+        let aligned_alloc = asm.alloc_methodref(aligned_alloc);
+        let node = asm.alloc_node(CILNode::call(aligned_alloc, [node_size, align]));
+        let alloc_node = asm.alloc_root(CILRoot::StLoc(0, node));
+        let node = asm.alloc_node(CILNode::LdLoc(0));
+        // Fill it with the destructor, its argument, and the previous head of the list.
+        let dtor = asm.alloc_node(CILNode::LdArg(0));
+        let dtor_addr = tls_dtor_slot(asm, node, 0, dtor_tpe);
+        let set_dtor = asm.alloc_root(CILRoot::StInd(Box::new((dtor_addr, dtor, dtor_tpe, false))));
+        let obj = asm.alloc_node(CILNode::LdArg(1));
+        let obj_addr = tls_dtor_slot(asm, node, 1, void_ptr);
+        let set_obj = asm.alloc_root(CILRoot::StInd(Box::new((obj_addr, obj, void_ptr, false))));
+        let head = asm.alloc_node(CILNode::LdStaticField(tls_dtors));
+        let next_addr = tls_dtor_slot(asm, node, 2, void_ptr);
+        let set_next = asm.alloc_root(CILRoot::StInd(Box::new((next_addr, head, void_ptr, false))));
+        // Make the new node the head of the list.
+        let set_head = asm.alloc_root(CILRoot::SetStaticField {
+            field: tls_dtors,
+            val: node,
+        });
+        let ret = asm.alloc_root(CILRoot::VoidRet);
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(
+                vec![alloc_node, set_dtor, set_obj, set_next, set_head, ret],
+                0,
+                None,
+            )],
+            locals: vec![(None, asm.alloc_type(void_ptr))],
+        }
+    };
+    patcher.insert(name, Box::new(generator));
+}
+/// Runs all the thread local destructors registered on this thread, in the reverse order of their registration.
+fn insert_run_tls_dtors(asm: &mut Assembly) -> Interned<MethodRef> {
+    let name = asm.alloc_string("run_tls_dtors");
+    let main_module = asm.main_module();
+    let void_ptr = asm.nptr(Type::Void);
+    let dtor_sig = tls_dtor_sig(asm);
+    let dtor_tpe = asm.alloc_type(Type::FnPtr(dtor_sig));
+    let void_ptr_idx = asm.alloc_type(void_ptr);
+    let tls_dtors = tls_dtors(asm);
+    // Pop the head of the list, returning if it is empty.
+    let head = asm.alloc_node(CILNode::LdStaticField(tls_dtors));
+    let get_head = asm.alloc_root(CILRoot::StLoc(0, head));
+    let node = asm.alloc_node(CILNode::LdLoc(0));
+    let node_addr = asm.alloc_node(CILNode::PtrCast(node, Box::new(PtrCastRes::USize)));
+    let null = asm.alloc_node(Const::USize(0));
+    let is_empty = asm.alloc_root(CILRoot::Branch(Box::new((
+        1,
+        0,
+        Some(BranchCond::Eq(node_addr, null)),
+    ))));
+    let next_addr = tls_dtor_slot(asm, node, 2, void_ptr);
+    let next = asm.alloc_node(CILNode::LdInd {
+        addr: next_addr,
+        tpe: void_ptr_idx,
+        volatile: false,
+    });
+    let pop_head = asm.alloc_root(CILRoot::SetStaticField {
+        field: tls_dtors,
+        val: next,
+    });
+    // Call the destructor. It may register new destructors, so the node must be popped first.
+    let dtor_addr = tls_dtor_slot(asm, node, 0, Type::FnPtr(dtor_sig));
+    let dtor = asm.alloc_node(CILNode::LdInd {
+        addr: dtor_addr,
+        tpe: dtor_tpe,
+        volatile: false,
+    });
+    let obj_addr = tls_dtor_slot(asm, node, 1, void_ptr);
+    let obj = asm.alloc_node(CILNode::LdInd {
+        addr: obj_addr,
+        tpe: void_ptr_idx,
+        volatile: false,
+    });
+    let call_dtor = asm.alloc_root(CILRoot::CallI(Box::new((dtor, dtor_sig, [obj].into()))));
+    let aligned_free = MethodRef::aligned_free(asm);
+    let free_node = asm.alloc_root(CILRoot::call(aligned_free, [node]));
+    let next_dtor = asm.alloc_root(CILRoot::Branch(Box::new((0, 0, None))));
+    let ret = asm.alloc_root(CILRoot::VoidRet);
+    let sig = asm.sig([], Type::Void);
+    let def = asm.new_method(MethodDef::new(
+        Access::Public,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(
+                    vec![
+                        get_head, is_empty, pop_head, call_dtor, free_node, next_dtor,
+                    ],
+                    0,
+                    None,
+                ),
+                BasicBlock::new(vec![ret], 1, None),
+            ],
+            locals: vec![(None, void_ptr_idx)],
+        },
+        vec![],
+    ));
+    *def
+}
 pub fn instert_threading(asm: &mut Assembly, patcher: &mut MissingMethodPatcher) {
     insert_pthread_attr_init(asm, patcher);
     insert_pthread_attr_setstacksize(asm, patcher);
@@ -371,6 +534,7 @@ pub fn instert_threading(asm: &mut Assembly, patcher: &mut MissingMethodPatcher)
     insert_pthread_attr_destroy(asm, patcher);
     insert_pthread_setname_np(asm, patcher);
     insert_pthread_key_delete(asm, patcher);
+    insert_cxa_thread_atexit_impl(asm, patcher);
     let main_mod = asm.main_module();
     asm.add_static(
         Type::Int(PTHREAD_KEY_T),
@@ -488,6 +652,9 @@ pub fn instert_threading(asm: &mut Assembly, patcher: &mut MissingMethodPatcher)
         [data_node].into(),
     ))));
     let call = asm.alloc_root(CILRoot::StLoc(0, call));
+    // Drop the thread locals of this thread.
+    let run_tls_dtors = insert_run_tls_dtors(asm);
+    let run_tls_dtors = asm.alloc_root(CILRoot::call(run_tls_dtors, []));
     // Get the ID of this thread
     let thread = ClassRef::thread(asm);
     let current_thread = asm.alloc_string("get_CurrentThread");
@@ -552,7 +719,7 @@ pub fn instert_threading(asm: &mut Assembly, patcher: &mut MissingMethodPatcher)
         MethodKind::Virtual,
         MethodImpl::MethodBody {
             blocks: vec![BasicBlock::new(
-                vec![call_tcctor, call, set_result, ret],
+                vec![call_tcctor, call, run_tls_dtors, set_result, ret],
                 0,
                 None,
             )],
@@ -699,3 +866,35 @@ fn insert_pthread_key_delete(asm: &mut Assembly, patcher: &mut MissingMethodPatc
     };
     patcher.insert(name, Box::new(generator));
 }
+#[test]
+fn tls_dtors_typecheck() {
+    use crate::cilnode::MethodKind;
+    let mut asm = Assembly::default();
+    let mut patcher = MissingMethodPatcher::default();
+    instert_threading(&mut asm, &mut patcher);
+    let void_ptr = asm.nptr(Type::Void);
+    let dtor_sig = tls_dtor_sig(&mut asm);
+    let sig = asm.sig([Type::FnPtr(dtor_sig), void_ptr, void_ptr], Type::Void);
+    let name = asm.alloc_string("__cxa_thread_atexit_impl");
+    let main_module = *asm.main_module();
+    let mref = asm.alloc_methodref(MethodRef::new(
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        vec![].into(),
+    ));
+    let implementation = patcher[&name](mref, &mut asm);
+    let mut def = asm[mref]
+        .clone()
+        .into_def(implementation, Access::Public, &asm);
+    def.typecheck(&mut asm).unwrap();
+    let run_tls_dtors = insert_run_tls_dtors(&mut asm);
+    let mut def = asm
+        .method_defs()
+        .values()
+        .find(|def| def.ref_to() == asm[run_tls_dtors])
+        .unwrap()
+        .clone();
+    def.typecheck(&mut asm).unwrap();
+}
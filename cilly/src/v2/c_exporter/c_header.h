@@ -1,5 +1,6 @@
 #include <stdio.h>
 #include <stdint.h>
+#include <stdarg.h>
 /* Backup for targets that don't support i128 - TODO: replace this with software emulation!*/
 #if !defined(__SIZEOF_INT128__) || defined(__LCC__)
 #define __int128_t long long
@@ -31,7 +31,7 @@ use super::{
     method::LocalDef,
     typecheck::TypeCheckError,
     Assembly, BinOp, CILNode, CILRoot, ClassRef, Const, Exporter, FnSig, Int, MethodDef, MethodRef,
-    Type,
+    StaticFieldDesc, Type,
 };
 use utilis::*;
 
@@ -99,6 +99,7 @@ impl CExporter {
             let owner = nonvoid_c_type(Type::ClassRef(mref.class()), asm);
             inputs = format!("{owner},{inputs}")
         }
+        let inputs = variadic_inputs(inputs, &asm[mref.sig()]);
         writeln!(method_decls, "{output} {method_name}({inputs});")
     }
     #[allow(clippy::too_many_arguments)]
@@ -265,7 +266,7 @@ impl CExporter {
                     super::Float::F16 => todo!(),
                     super::Float::F32 => format!("(float)fmod((double)({lhs}),((double)({rhs}))"),
                     super::Float::F64 => format!("fmod(({lhs}),({rhs}))"),
-                    super::Float::F128 => todo!(),
+                    super::Float::F128 => format!("fmodf128(({lhs}),({rhs}))"),
                 },
                 // TODO: reminder of a bool can only be false or a segfault. Is this a valid operation?
                 Type::Bool => "false".into(),
@@ -584,7 +585,7 @@ impl CExporter {
                     super::Float::F16 => todo!(),
                     super::Float::F32 => format!("(float)({input})"),
                     super::Float::F64 => format!("(double)({input})"),
-                    super::Float::F128 => todo!(),
+                    super::Float::F128 => format!("(_Float128)({input})"),
                 }
             }
             CILNode::RefToPtr(node_idx) => {
@@ -712,6 +713,7 @@ impl CExporter {
                     .map(|i| nonvoid_c_type(*i, asm))
                     .intersperse(",".into())
                     .collect::<String>();
+                let args = variadic_inputs(args, &fn_ptr_sig);
                 let fn_ptr = Self::node_to_string(asm[*fn_ptr].clone(), asm, locals, inputs, sig)?;
                 format!("((*({ret}(*)({args}))({fn_ptr})))({call_args})")
             }
@@ -997,9 +999,26 @@ impl CExporter {
                     .map(|i| nonvoid_c_type(*i, asm))
                     .intersperse(",".into())
                     .collect::<String>();
+                let args = variadic_inputs(args, &fn_ptr_sig);
                 let fn_ptr = Self::node_to_string(asm[*fn_ptr].clone(), asm, locals, inputs, sig)?;
                 format!("((*({ret}(*)({args}))({fn_ptr})))({call_args});")
             }
+            CILRoot::Switch(switch) => {
+                let (value, targets, default) = switch.as_ref();
+                let value = Self::node_to_string(asm[*value].clone(), asm, locals, inputs, sig)?;
+                let mut cases: String = targets
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, (target, sub_target))| {
+                        let label =
+                            branch_cond_to_name(*target, *sub_target, has_handler, is_handler);
+                        format!("case {idx}: goto {label};\n")
+                    })
+                    .collect();
+                let label = branch_cond_to_name(default.0, default.1, has_handler, is_handler);
+                cases.push_str(&format!("default: goto {label};\n"));
+                format!("switch((uintptr_t)({value})){{\n{cases}}}")
+            }
             CILRoot::ExitSpecialRegion { target, source } => {
                 if is_handler {
                     format!("h{source}_{target}: goto bb{target};")
@@ -1022,6 +1041,98 @@ impl CExporter {
             CILRoot::Unreachable(string_idx) => {
                 format!("\neprintf({:?});\nabort();\n", &asm[string_idx])
             }
+            CILRoot::VaStart(list) => {
+                let list = Self::node_to_string(asm[list].clone(), asm, locals, inputs, sig)?;
+                // `va_start` needs the name of the last fixed argument.
+                match inputs.len().checked_sub(1) {
+                    Some(last) => {
+                        let last = match inputs[last].1 {
+                            Some(arg_name) => escape_nonfn_name(&asm[arg_name]),
+                            None => format!("A{last}"),
+                        };
+                        format!("va_start(*(va_list*)({list}),{last});")
+                    }
+                    None => format!("va_start(*(va_list*)({list}));"),
+                }
+            }
+            CILRoot::VaArg(info) => {
+                let (list, dst, tpe) = *info;
+                let list = Self::node_to_string(asm[list].clone(), asm, locals, inputs, sig)?;
+                let dst = Self::node_to_string(asm[dst].clone(), asm, locals, inputs, sig)?;
+                let tpe = c_tpe(asm[tpe], asm);
+                format!("*({tpe}*)({dst}) = va_arg(*(va_list*)({list}),{tpe});")
+            }
+            CILRoot::InlineAsm(inline_asm) => {
+                // Operands are passed trough variables declared in a new scope, which lets them be placed in an
+                // explicit register, and keeps the outputs addressable.
+                let mut vars = String::new();
+                let mut stores = String::new();
+                let mut outputs = Vec::new();
+                for (idx, output) in inline_asm.outputs.iter().enumerate() {
+                    let tpe = c_tpe(asm[output.tpe], asm);
+                    let reg = output.reg.map_or(String::new(), |reg| {
+                        format!(" __asm__({})", c_string_literal(&asm[reg]))
+                    });
+                    let init = match output.init {
+                        Some(init) => format!(
+                            " = {}",
+                            Self::node_to_string(asm[init].clone(), asm, locals, inputs, sig)?
+                        ),
+                        None => String::new(),
+                    };
+                    let register = if output.reg.is_some() {
+                        "register "
+                    } else {
+                        ""
+                    };
+                    vars.push_str(&format!("{register}{tpe} asm_out{idx}{reg}{init};\n"));
+                    outputs.push(format!(
+                        "{}(asm_out{idx})",
+                        c_string_literal(&asm[output.constraint])
+                    ));
+                    if let Some(dst) = output.dst {
+                        let dst = Self::node_to_string(asm[dst].clone(), asm, locals, inputs, sig)?;
+                        stores.push_str(&format!("*({tpe}*)({dst}) = asm_out{idx};\n"));
+                    }
+                }
+                let mut asm_inputs = Vec::new();
+                for (idx, input) in inline_asm.inputs.iter().enumerate() {
+                    let val =
+                        Self::node_to_string(asm[input.val].clone(), asm, locals, inputs, sig)?;
+                    let val = match input.reg {
+                        Some(reg) => {
+                            let tpe = c_tpe(asm[input.tpe], asm);
+                            let reg = c_string_literal(&asm[reg]);
+                            vars.push_str(&format!(
+                                "register {tpe} asm_in{idx} __asm__({reg}) = {val};\n"
+                            ));
+                            format!("asm_in{idx}")
+                        }
+                        None => val,
+                    };
+                    asm_inputs.push(format!(
+                        "{}({val})",
+                        c_string_literal(&asm[input.constraint])
+                    ));
+                }
+                let clobbers: Vec<_> = inline_asm
+                    .clobbers
+                    .iter()
+                    .map(|clobber| c_string_literal(&asm[*clobber]))
+                    .collect();
+                let volatile = if inline_asm.is_volatile {
+                    " __volatile__"
+                } else {
+                    ""
+                };
+                format!(
+                    "{{\n{vars}__asm__{volatile}({template} : {outputs} : {asm_inputs} : {clobbers});\n{stores}}}",
+                    template = asm_template(&inline_asm.template, asm),
+                    outputs = outputs.join(", "),
+                    asm_inputs = asm_inputs.join(", "),
+                    clobbers = clobbers.join(", "),
+                )
+            }
         })
     }
     fn export_method_def(
@@ -1078,6 +1189,7 @@ impl CExporter {
                         .map(|i| nonvoid_c_type(*i, asm))
                         .intersperse(",".into())
                         .collect::<String>();
+                    let inputs = variadic_inputs(inputs, &asm[def.sig()]);
                     writeln!(method_decls, "{output} {method_name}({inputs});")?;
                     return Ok(());
                 }
@@ -1091,6 +1203,7 @@ impl CExporter {
                     .map(|(idx, i)| format!("{} A{}", nonvoid_c_type(*i, asm), idx))
                     .intersperse(",".into())
                     .collect::<String>();
+                let inputs = variadic_inputs(inputs, &asm[def.sig()]);
                 writeln!(
                     method_defs,
                     "{output} {method_name}({inputs}){{eprintf(\"Missing method {method_name}\\n\");abort();}}"
@@ -1114,6 +1227,7 @@ impl CExporter {
             })
             .intersperse(",".into())
             .collect::<String>();
+        let inputs = variadic_inputs(inputs, &asm[sig]);
         writeln!(method_defs, "{output} {method_name}({inputs}){{")?;
         let locals: Vec<_> = def.iter_locals(asm).copied().collect();
         for (idx, (lname, local_type)) in locals.iter().enumerate() {
@@ -1340,6 +1454,29 @@ impl CExporter {
             let fname = escape_nonfn_name(&asm[*sfname]);
             let field_tpe = nonvoid_c_type(*sfield_tpe, asm);
             let fname = class_member_name(&class_name, &fname);
+            let sfld = asm.alloc_sfld(StaticFieldDesc::new(*defid, *sfname, *sfield_tpe));
+            // The bounds of a linker section are provided by the linker. They are weak, since empty sections have none.
+            if let Some((section, end)) = asm.link_section_bound_of(&asm[*sfname]) {
+                let bound = if end { "__stop_" } else { "__start_" };
+                let bound = format!("{bound}{section}", section = &asm[section]);
+                writeln!(type_defs, "extern uint8_t {bound}[] __attribute__((weak));")?;
+                if extrn {
+                    writeln!(type_defs, "extern {field_tpe} {fname};")?;
+                } else {
+                    writeln!(type_defs, "{field_tpe} {fname} = {bound};")?;
+                }
+                continue;
+            }
+            let attrs = if let Some(section) = asm.link_section_of(sfld) {
+                format!(
+                    " __attribute__((section({section}), used))",
+                    section = c_string_literal(&asm[section])
+                )
+            } else if asm.is_used_static(sfld) {
+                " __attribute__((used))".into()
+            } else {
+                String::new()
+            };
             let extrn = if extrn { "extern" } else { "" };
             if let Some(default) = default_value {
                 assert!(!*is_thread_local);
@@ -1368,9 +1505,12 @@ impl CExporter {
                 continue;
             }
             if *is_thread_local {
-                writeln!(type_defs, "{extrn} _Thread_local {field_tpe} {fname};")?;
+                writeln!(
+                    type_defs,
+                    "{extrn} _Thread_local {field_tpe} {fname}{attrs};"
+                )?;
             } else {
-                writeln!(type_defs, "{extrn} {field_tpe} {fname};")?;
+                writeln!(type_defs, "{extrn} {field_tpe} {fname}{attrs};")?;
             }
             //writeln!(type_defs, "// {sttic:?}")?;
         }
@@ -1390,7 +1530,7 @@ impl CExporter {
         defined_types.insert(defid);
         Ok(())
     }
-    fn export_to_write(
+    pub(crate) fn export_to_write(
         &mut self,
         asm: &super::Assembly,
         out: &mut impl Write,
@@ -1466,6 +1606,9 @@ impl CExporter {
         out.write_all(b"\n/*END OF TYPEDEFS*/\n")?;
         out.write_all(&method_decls)?;
         out.write_all(b"\n/*END OF METHODECLS*/\n")?;
+        for pieces in asm.global_asm() {
+            writeln!(out, "__asm__({});", asm_template(pieces, &asm))?;
+        }
         out.write_all(&method_defs)?;
         if !lib {
             call_entry(out, &asm)?;
@@ -1489,8 +1632,14 @@ impl CExporter {
 }
 fn call_entry(out: &mut impl Write, asm: &Assembly) -> Result<(), std::io::Error> {
     let cctor_call = if asm.has_cctor() { "_cctor();" } else { "" };
+    // Registered with `atexit`, so that `.fini_array` also runs when the program calls `exit`.
+    let fini_call = if asm.has_user_fini() {
+        "atexit(static_fini);"
+    } else {
+        ""
+    };
 
-    writeln!(out,"int main(int argc_input, char** argv_input){{\n#ifndef __SDCC\nargc = argc_input;if(argc < 1)abort();\nargv = argv_input;if(argv == (char**)0)abort();\n#endif\n{cctor_call}entrypoint();\nreturn 0;}}")?;
+    writeln!(out,"int main(int argc_input, char** argv_input){{\n#ifndef __SDCC\nargc = argc_input;if(argc < 1)abort();\nargv = argv_input;if(argv == (char**)0)abort();\n#endif\n{cctor_call}{fini_call}entrypoint();\nreturn 0;}}")?;
     Ok(())
 }
 impl CExporter {
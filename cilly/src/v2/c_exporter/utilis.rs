@@ -1,6 +1,9 @@
 use crate::utilis::encode;
 
-use super::super::{asm::MAIN_MODULE, method::LocalDef, Assembly, Float, Int, MethodRef, Type};
+use super::super::{
+    asm::MAIN_MODULE, method::LocalDef, AsmPiece, Assembly, CILNode, Float, FnSig, Int, MethodRef,
+    Type,
+};
 
 use fxhash::{hash64, FxHashSet, FxHasher};
 /// Finds the name of this local
@@ -58,6 +61,14 @@ pub(super) fn escape_ident(ident: &str) -> String {
         _ => escaped,
     }
 }
+/// Appends the `...` of a C-variadic function to a comma-separated list of its fixed inputs.
+pub(super) fn variadic_inputs(inputs: String, sig: &FnSig) -> String {
+    match (sig.is_variadic(), inputs.is_empty()) {
+        (false, _) => inputs,
+        (true, true) => "...".into(),
+        (true, false) => format!("{inputs},..."),
+    }
+}
 /// Returns a string representing this type, with special handling for `void`.
 /// In other cases, this is equivalent to [`c_tpe`].
 pub(super) fn nonvoid_c_type(field_tpe: Type, asm: &Assembly) -> String {
@@ -166,3 +177,39 @@ pub(super) fn escape_nonfn_name(name: &str) -> String {
         _ => res,
     }
 }
+/// Turns `text` into a C string literal.
+pub(super) fn c_string_literal(text: &str) -> String {
+    let mut literal = String::with_capacity(text.len() + 2);
+    literal.push('"');
+    for byte in text.bytes() {
+        match byte {
+            b'"' => literal.push_str("\\\""),
+            b'\\' => literal.push_str("\\\\"),
+            b'\n' => literal.push_str("\\n"),
+            b'\t' => literal.push_str("\\t"),
+            b' '..=b'~' => literal.push(byte as char),
+            _ => literal.push_str(&format!("\\{byte:03o}")),
+        }
+    }
+    literal.push('"');
+    literal
+}
+/// Turns the pieces of an assembly template into a C string literal, replacing symbols with their C names.
+pub(super) fn asm_template(pieces: &[AsmPiece], asm: &Assembly) -> String {
+    let template: String = pieces
+        .iter()
+        .map(|piece| match piece {
+            AsmPiece::Str(text) => asm[*text].to_owned(),
+            AsmPiece::Sym(sym) => match asm[*sym] {
+                CILNode::LdFtn(mref) => mref_to_name(&asm[mref], asm),
+                CILNode::LdStaticFieldAddress(sfld) => {
+                    let field = asm[sfld];
+                    let class = &asm[field.owner()];
+                    class_member_name(&asm[class.name()], &escape_nonfn_name(&asm[field.name()]))
+                }
+                ref other => panic!("{other:?} is not a symbol, and can't be used in assembly."),
+            },
+        })
+        .collect();
+    c_string_literal(&template)
+}
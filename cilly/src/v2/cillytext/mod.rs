@@ -0,0 +1,368 @@
+//! A human-readable, S-expression based representation of cilly IR.
+//!
+//! The text produced by [`print`] can be loaded back by [`parse`], and printing the resulting
+//! assembly again yields identical text. Items are printed in a deterministic order, so the
+//! output can be diffed and checked into golden files.
+//! ```
+//! # use cilly::*;
+//! # use cilly::cillytext::{parse,print};
+//! let asm = parse(r#"
+//! (classdef public class "MainModule"
+//!   (method public static "add" (sig (i32 i32) i32) (args "a" "b")
+//!     (locals)
+//!     (block 0 (ret (add (ldarg 0) (ldarg 1))))))
+//! "#).unwrap();
+//! assert!(print(&asm).contains("(ret (add (ldarg 0) (ldarg 1)))"));
+//! ```
+use std::io::Write;
+
+use super::{Assembly, Exporter, MethodDef};
+
+mod parse;
+mod print;
+mod sexpr;
+pub use sexpr::{Pos, SExpr};
+
+/// An error encountered while parsing the textual IR.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// The line(starting from 1) this error occured at.
+    pub line: u32,
+    /// The column(starting from 1) this error occured at.
+    pub col: u32,
+    /// Description of the error.
+    pub msg: String,
+}
+impl ParseError {
+    fn new(pos: Pos, msg: impl Into<String>) -> Self {
+        Self {
+            line: pos.line,
+            col: pos.col,
+            msg: msg.into(),
+        }
+    }
+}
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.col, self.msg)
+    }
+}
+impl std::error::Error for ParseError {}
+/// Prints the whole assembly as text.
+#[must_use]
+pub fn print(asm: &Assembly) -> String {
+    let mut out = String::new();
+    for item in print::assembly(asm) {
+        item.write_pretty(&mut out, 0);
+        out.push('\n');
+    }
+    out
+}
+/// Prints a single method definition as text.
+#[must_use]
+pub fn print_method(def: &MethodDef, asm: &Assembly) -> String {
+    let mut out = String::new();
+    print::method_def(def, asm).write_pretty(&mut out, 0);
+    out
+}
+/// Parses the textual IR into a new assembly.
+pub fn parse(src: &str) -> Result<Assembly, ParseError> {
+    let mut asm = Assembly::default();
+    parse_into(src, &mut asm)?;
+    Ok(asm)
+}
+/// Parses the textual IR, adding all of its items to `asm`.
+pub fn parse_into(src: &str, asm: &mut Assembly) -> Result<(), ParseError> {
+    let mut parser = parse::Parser::new(asm);
+    for item in sexpr::read_all(src)? {
+        parser.top_level(&item)?;
+    }
+    Ok(())
+}
+/// Exports the assembly as a `.cillytext` file.
+#[derive(Default)]
+pub struct CillyTextExporter {}
+impl Exporter for CillyTextExporter {
+    type Error = std::io::Error;
+
+    fn export(&mut self, asm: &Assembly, target: &std::path::Path) -> Result<(), Self::Error> {
+        let mut out =
+            std::io::BufWriter::new(std::fs::File::create(target.with_extension("cillytext"))?);
+        out.write_all(print(asm).as_bytes())?;
+        out.flush()
+    }
+}
+#[cfg(test)]
+fn roundtrip(asm: &Assembly) {
+    let text = print(asm);
+    let parsed = parse(&text).unwrap_or_else(|err| panic!("{err}\n{text}"));
+    assert_eq!(text, print(&parsed));
+}
+#[test]
+fn roundtrip_method() {
+    use super::{
+        cilnode::{ExtendKind, MethodKind},
+        cilroot::{BranchCond, CmpKind},
+        BasicBlock, BinOp, CILNode, CILRoot, ClassRef, Const, Int, MethodImpl, Type,
+    };
+    use crate::Access;
+    let mut asm = Assembly::default();
+    let main_module = asm.main_module();
+    let name = asm.alloc_string("test");
+    let sig = asm.sig(
+        [Type::Int(Int::I32), Type::Bool],
+        Type::Float(crate::Float::F64),
+    );
+    let arg = asm.alloc_node(CILNode::LdArg(0));
+    let one = asm.alloc_node(Const::I32(1));
+    let sum = asm.alloc_node(CILNode::BinOp(arg, one, BinOp::Add));
+    let cast = asm.alloc_node(CILNode::IntCast {
+        input: sum,
+        target: Int::U64,
+        extend: ExtendKind::SignExtend,
+    });
+    let nan = asm.alloc_node(Const::F64(crate::hashable::HashableF64(f64::from_bits(
+        0x7ff8_0000_dead_beef,
+    ))));
+    let string = asm.alloc_string("hello \"world\"\n");
+    let string = asm.alloc_node(Const::PlatformString(string));
+    let object = ClassRef::object(&mut asm);
+    let exception = asm.alloc_node(CILNode::GetException);
+    let roots = [
+        asm.alloc_root(CILRoot::StLoc(0, cast)),
+        asm.alloc_root(CILRoot::Pop(string)),
+        asm.alloc_root(CILRoot::Branch(Box::new((
+            1,
+            0,
+            Some(BranchCond::Lt(arg, one, CmpKind::Signed)),
+        )))),
+        asm.alloc_root(CILRoot::Ret(nan)),
+    ];
+    let handler = [asm.alloc_root(CILRoot::Pop(exception))];
+    let null = asm.alloc_node(Const::Null(object));
+    let throw = asm.alloc_root(CILRoot::Throw(null));
+    let loc_name = asm.alloc_string("tmp");
+    let u64_tpe = asm.alloc_type(Type::Int(Int::U64));
+    let blocks = vec![
+        BasicBlock::new(
+            roots.into(),
+            0,
+            Some(vec![BasicBlock::new(handler.into(), 2, None)]),
+        ),
+        BasicBlock::new(vec![throw], 1, None),
+    ];
+    asm.new_method(MethodDef::new(
+        Access::Public,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks,
+            locals: vec![(Some(loc_name), u64_tpe)],
+        },
+        vec![Some(loc_name), None],
+    ));
+    let lib = asm.alloc_string("libc");
+    let puts = asm.alloc_string("puts");
+    let u8_ptr = asm.nptr(Type::Int(Int::U8));
+    let puts_sig = asm.sig([u8_ptr], Type::Int(Int::I32));
+    asm.new_method(MethodDef::new(
+        Access::Extern,
+        main_module,
+        puts,
+        puts_sig,
+        MethodKind::Static,
+        MethodImpl::Extern {
+            lib,
+            preserve_errno: true,
+        },
+        vec![None],
+    ));
+    asm.add_section("metadata", [0xde, 0xad, 0x00, 0x01]);
+    roundtrip(&asm);
+}
+#[test]
+fn roundtrip_class() {
+    use super::{class::StaticFieldDef, ClassDef, Const, Int, Type};
+    use crate::Access;
+    let mut asm = Assembly::default();
+    let name = asm.alloc_string("Point");
+    let x = asm.alloc_string("x");
+    let y = asm.alloc_string("y");
+    let count = asm.alloc_string("count");
+    asm.class_def(ClassDef::new(
+        name,
+        true,
+        0,
+        None,
+        vec![
+            (Type::Int(Int::I32), x, Some(0)),
+            (Type::Int(Int::I32), y, Some(4)),
+        ],
+        vec![StaticFieldDef {
+            tpe: Type::Int(Int::U64),
+            name: count,
+            is_tls: true,
+            default_value: Some(Const::U64(u64::MAX)),
+            is_const: false,
+        }],
+        Access::Private,
+        std::num::NonZeroU32::new(8),
+        std::num::NonZeroU32::new(4),
+        true,
+    ))
+    .unwrap();
+    roundtrip(&asm);
+}
+#[test]
+fn roundtrip_asm() {
+    use super::{
+        cilnode::MethodKind, AsmInput, AsmOutput, AsmPiece, BasicBlock, CILNode, CILRoot, Const,
+        InlineAsm, Int, MethodImpl, Type,
+    };
+    use crate::Access;
+    let mut asm = Assembly::default();
+    let main_module = asm.main_module();
+    let name = asm.alloc_string("rdtsc");
+    let sig = asm.sig([], Type::Int(Int::U64));
+    let u32_tpe = asm.alloc_type(Type::Int(Int::U32));
+    let lo = asm.alloc_node(CILNode::LdLocA(0));
+    let five = asm.alloc_node(Const::U32(5));
+    let template = ["rdtsc\n\tadd ", "%1, %0"].map(|text| AsmPiece::Str(asm.alloc_string(text)));
+    let constraints = ["=a", "r", "memory"].map(|text| asm.alloc_string(text));
+    let rdx = asm.alloc_string("rdx");
+    let block = asm.alloc_root(CILRoot::InlineAsm(Box::new(InlineAsm {
+        template: template.into(),
+        outputs: [
+            AsmOutput {
+                constraint: constraints[0],
+                reg: None,
+                tpe: u32_tpe,
+                init: None,
+                dst: Some(lo),
+            },
+            AsmOutput {
+                constraint: constraints[0],
+                reg: Some(rdx),
+                tpe: u32_tpe,
+                init: Some(five),
+                dst: None,
+            },
+        ]
+        .into(),
+        inputs: [AsmInput {
+            constraint: constraints[1],
+            reg: Some(rdx),
+            tpe: u32_tpe,
+            val: five,
+        }]
+        .into(),
+        clobbers: [constraints[2]].into(),
+        is_volatile: true,
+    })));
+    let lo = asm.alloc_node(CILNode::LdLoc(0));
+    let ret = asm.alloc_root(CILRoot::Ret(lo));
+    let def = asm.new_method(MethodDef::new(
+        Access::Public,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![block, ret], 0, None)],
+            locals: vec![(None, u32_tpe)],
+        },
+        vec![],
+    ));
+    let ftn = asm.alloc_node(CILNode::LdFtn(def.0));
+    let directive = asm.alloc_string(".globl rdtsc_alias\nrdtsc_alias = ");
+    asm.add_global_asm([AsmPiece::Str(directive), AsmPiece::Sym(ftn)]);
+    roundtrip(&asm);
+}
+#[test]
+fn roundtrip_link_sections() {
+    use super::{Int, Type};
+    let mut asm = Assembly::default();
+    let main_module = asm.main_module();
+    let plugin = asm.add_static(
+        Type::Int(Int::U64),
+        "plugin",
+        false,
+        main_module,
+        None,
+        false,
+    );
+    let kept = asm.add_static(Type::Int(Int::U8), "kept", false, main_module, None, false);
+    let plugins = asm.alloc_string("plugins");
+    asm.add_to_link_section(plugins, plugin);
+    asm.add_used_static(kept);
+    let empty = asm.alloc_string("empty");
+    asm.link_section_bound(empty, false);
+    asm.link_section_bound(empty, true);
+    roundtrip(&asm);
+}
+#[test]
+fn roundtrip_variadic() {
+    use super::{
+        cilnode::MethodKind, BasicBlock, CILNode, CILRoot, Const, FnSig, Int, MethodImpl, Type,
+    };
+    use crate::Access;
+    let mut asm = Assembly::default();
+    let main_module = asm.main_module();
+    let name = asm.alloc_string("first_vararg");
+    let sig = asm.alloc_sig(FnSig::new_variadic(
+        [Type::Int(Int::I32)],
+        Type::Int(Int::I32),
+    ));
+    let list_tpe = asm.alloc_type(Type::Int(Int::U64));
+    let i32_tpe = asm.alloc_type(Type::Int(Int::I32));
+    let list = asm.alloc_node(CILNode::LdLocA(0));
+    let dst = asm.alloc_node(CILNode::LdLocA(1));
+    let start = asm.alloc_root(CILRoot::VaStart(list));
+    let arg = asm.alloc_root(CILRoot::VaArg(Box::new((list, dst, i32_tpe))));
+    let val = asm.alloc_node(CILNode::LdLoc(1));
+    let ret = asm.alloc_root(CILRoot::Ret(val));
+    asm.new_method(MethodDef::new(
+        Access::Public,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![start, arg, ret], 0, None)],
+            locals: vec![(None, list_tpe), (None, i32_tpe)],
+        },
+        vec![None],
+    ));
+    // Calls pass the additional arguments after the fixed ones.
+    let mref = asm.new_methodref(*main_module, "first_vararg", sig, MethodKind::Static, []);
+    let args = [Const::I32(1), Const::I32(2), Const::I32(3)].map(|arg| asm.alloc_node(arg));
+    let call = asm.alloc_node(CILNode::call(mref, args));
+    let ret = asm.alloc_root(CILRoot::Ret(call));
+    let name = asm.alloc_string("caller");
+    let caller_sig = asm.sig([], Type::Int(Int::I32));
+    asm.new_method(MethodDef::new(
+        Access::Public,
+        main_module,
+        name,
+        caller_sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![ret], 0, None)],
+            locals: vec![],
+        },
+        vec![],
+    ));
+    roundtrip(&asm);
+    assert!(print(&asm).contains("(sig (i32 ...) i32)"));
+}
+#[test]
+fn parse_errors() {
+    let Err(err) = parse("(classdef public class \"A\"\n  (field i33 \"x\"))") else {
+        panic!("parsing an invalid type should fail")
+    };
+    assert_eq!((err.line, err.col), (2, 10));
+    assert!(parse("(classdef public class \"A\") (classdef public class \"A\")").is_err());
+    assert!(parse("(section \"a\" \"abc\")").is_err());
+}
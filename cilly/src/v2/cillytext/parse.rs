@@ -0,0 +1,1080 @@
+use std::{
+    num::{NonZeroU32, NonZeroU8},
+    str::FromStr,
+};
+
+use super::{super::method::LocalDef, sexpr::SExpr, ParseError};
+use crate::{
+    cilnode::{ExtendKind, IsPure, MethodKind, PtrCastRes, UnOp},
+    cilroot::{BranchCond, CmpKind},
+    class::{ClassDefIdx, StaticFieldDef},
+    hashable::{HashableF32, HashableF64},
+    tpe::{
+        simd::{SIMDElem, SIMDVector},
+        GenericKind,
+    },
+    Access, AsmInput, AsmOutput, AsmPiece, Assembly, BasicBlock, BinOp, CILNode, CILRoot, ClassDef,
+    ClassRef, Const, FieldDesc, Float, FnSig, IString, InlineAsm, Int, Interned, MethodDef,
+    MethodImpl, MethodRef, StaticFieldDesc, Type,
+};
+type PResult<T> = Result<T, ParseError>;
+type CallInfo = (Interned<MethodRef>, Box<[Interned<CILNode>]>, IsPure);
+type CallIInfo = (Interned<CILNode>, Interned<FnSig>, Box<[Interned<CILNode>]>);
+fn err<T>(expr: &SExpr, msg: impl Into<String>) -> PResult<T> {
+    Err(ParseError::new(expr.pos(), msg))
+}
+fn tagged<'a>(expr: &'a SExpr, what: &str) -> PResult<(&'a str, &'a [SExpr])> {
+    match expr.as_tagged() {
+        Some(res) => Ok(res),
+        None => err(expr, format!("expected {what}")),
+    }
+}
+/// Checks that the list `expr`, with its head removed, has exactly `N` elements.
+fn args<'a, const N: usize>(expr: &SExpr, rest: &'a [SExpr]) -> PResult<&'a [SExpr; N]> {
+    match rest.try_into() {
+        Ok(res) => Ok(res),
+        Err(_) => err(
+            expr,
+            format!("expected {N} arguments, found {}", rest.len()),
+        ),
+    }
+}
+fn atom<'a>(expr: &'a SExpr, what: &str) -> PResult<&'a str> {
+    match expr.as_atom() {
+        Some(atom) => Ok(atom),
+        None => err(expr, format!("expected {what}")),
+    }
+}
+fn string(expr: &SExpr) -> PResult<&str> {
+    match expr {
+        SExpr::Str(string, _) => Ok(string),
+        _ => err(expr, "expected a string literal"),
+    }
+}
+fn num<T: FromStr>(expr: &SExpr) -> PResult<T> {
+    let atom = atom(expr, "a number")?;
+    match atom.parse() {
+        Ok(val) => Ok(val),
+        Err(_) => err(
+            expr,
+            format!("{atom:?} is not a valid number of the expected type"),
+        ),
+    }
+}
+fn unhex(expr: &SExpr) -> PResult<Vec<u8>> {
+    let hex = string(expr)?;
+    if hex.len() % 2 != 0 {
+        return err(expr, "hex string has an odd length");
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|idx| {
+            hex.get(idx..idx + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .map_or_else(|| err(expr, "invalid hex string"), Ok)
+        })
+        .collect()
+}
+fn int(name: &str) -> Option<Int> {
+    Some(match name {
+        "u8" => Int::U8,
+        "u16" => Int::U16,
+        "u32" => Int::U32,
+        "u64" => Int::U64,
+        "u128" => Int::U128,
+        "usize" => Int::USize,
+        "i8" => Int::I8,
+        "i16" => Int::I16,
+        "i32" => Int::I32,
+        "i64" => Int::I64,
+        "i128" => Int::I128,
+        "isize" => Int::ISize,
+        _ => return None,
+    })
+}
+fn float(name: &str) -> Option<Float> {
+    Some(match name {
+        "f16" => Float::F16,
+        "f32" => Float::F32,
+        "f64" => Float::F64,
+        "f128" => Float::F128,
+        _ => return None,
+    })
+}
+fn access(expr: &SExpr) -> PResult<Access> {
+    match atom(expr, "an access modifier")? {
+        "extern" => Ok(Access::Extern),
+        "public" => Ok(Access::Public),
+        "private" => Ok(Access::Private),
+        other => err(expr, format!("unknown access modifier {other:?}")),
+    }
+}
+fn method_kind(expr: &SExpr) -> PResult<MethodKind> {
+    match atom(expr, "a method kind")? {
+        "static" => Ok(MethodKind::Static),
+        "instance" => Ok(MethodKind::Instance),
+        "virtual" => Ok(MethodKind::Virtual),
+        "ctor" => Ok(MethodKind::Constructor),
+        other => err(expr, format!("unknown method kind {other:?}")),
+    }
+}
+fn bin_op(name: &str) -> Option<BinOp> {
+    BinOp::ALL_OPS
+        .into_iter()
+        .find(|op| super::print::bin_op(*op) == name)
+}
+/// Turns s-expressions into IR, allocating it within `asm`.
+pub(super) struct Parser<'asm> {
+    asm: &'asm mut Assembly,
+}
+impl<'asm> Parser<'asm> {
+    pub(super) fn new(asm: &'asm mut Assembly) -> Self {
+        Self { asm }
+    }
+    pub(super) fn top_level(&mut self, expr: &SExpr) -> PResult<()> {
+        let (head, rest) = tagged(expr, "a top-level item")?;
+        match head {
+            "classdef" => self.class_def(expr, rest),
+            "section" => {
+                let [name, data] = args(expr, rest)?;
+                let data = unhex(data)?;
+                self.asm.add_section(string(name)?, data);
+                Ok(())
+            }
+            "global_asm" => {
+                let pieces = self.asm_pieces(rest)?;
+                self.asm.add_global_asm(pieces);
+                Ok(())
+            }
+            "link_section" => {
+                let Some((name, statics)) = rest.split_first() else {
+                    return err(expr, "a linker section needs a name");
+                };
+                let section = self.asm.alloc_string(string(name)?);
+                self.asm.declare_link_section(section);
+                for sfld in statics {
+                    let sfld = self.static_field(sfld)?;
+                    self.asm.add_to_link_section(section, sfld);
+                }
+                Ok(())
+            }
+            "used" => {
+                for sfld in rest {
+                    let sfld = self.static_field(sfld)?;
+                    self.asm.add_used_static(sfld);
+                }
+                Ok(())
+            }
+            _ => err(expr, format!("unknown top-level item {head:?}")),
+        }
+    }
+    fn class_def(&mut self, expr: &SExpr, rest: &[SExpr]) -> PResult<()> {
+        if rest.len() < 3 {
+            return err(expr, "a class definition needs an access, kind and name");
+        }
+        let access = access(&rest[0])?;
+        let is_valuetype = match atom(&rest[1], "`class` or `valuetype`")? {
+            "valuetype" => true,
+            "class" => false,
+            other => {
+                return err(
+                    &rest[1],
+                    format!("expected `class` or `valuetype`, got {other:?}"),
+                )
+            }
+        };
+        let name = self.asm.alloc_string(string(&rest[2])?);
+        let mut extends = None;
+        let mut explict_size = None;
+        let mut align = None;
+        let mut has_nonveralpping_layout = true;
+        let mut fields = vec![];
+        let mut static_fields = vec![];
+        let mut methods = vec![];
+        for item in &rest[3..] {
+            let (head, args) = tagged(item, "a class item")?;
+            match head {
+                "generics" => {
+                    return err(item, "generic class definitions are not supported");
+                }
+                "extends" => {
+                    let [cref] = self::args(item, args)?;
+                    extends = Some(self.class_ref(cref)?);
+                }
+                "size" => {
+                    let [size] = self::args(item, args)?;
+                    explict_size = Some(num::<NonZeroU32>(size)?);
+                }
+                "align" => {
+                    let [size] = self::args(item, args)?;
+                    align = Some(num::<NonZeroU32>(size)?);
+                }
+                "overlapping" => {
+                    self::args::<0>(item, args)?;
+                    has_nonveralpping_layout = false;
+                }
+                "field" => {
+                    if !(2..=3).contains(&args.len()) {
+                        return err(item, "expected a field type, name and an optional offset");
+                    }
+                    let tpe = self.ty(&args[0])?;
+                    let name = self.asm.alloc_string(string(&args[1])?);
+                    let offset = args.get(2).map(num::<u32>).transpose()?;
+                    fields.push((tpe, name, offset));
+                }
+                "static" => {
+                    if args.len() < 2 {
+                        return err(item, "expected a static field type and name");
+                    }
+                    let tpe = self.ty(&args[0])?;
+                    let name = self.asm.alloc_string(string(&args[1])?);
+                    let mut is_tls = false;
+                    let mut is_const = false;
+                    let mut default_value = None;
+                    for flag in &args[2..] {
+                        match flag {
+                            SExpr::Atom(flag, _) if flag == "tls" => is_tls = true,
+                            SExpr::Atom(flag, _) if flag == "const" => is_const = true,
+                            _ => {
+                                let (head, val) = tagged(flag, "a static field flag")?;
+                                if head != "default" {
+                                    return err(
+                                        flag,
+                                        format!("unknown static field flag {head:?}"),
+                                    );
+                                }
+                                let [val] = self::args(flag, val)?;
+                                default_value = Some(self.cst(val)?);
+                            }
+                        }
+                    }
+                    static_fields.push(StaticFieldDef {
+                        tpe,
+                        name,
+                        is_tls,
+                        default_value,
+                        is_const,
+                    });
+                }
+                "method" => methods.push(item),
+                _ => return err(item, format!("unknown class item {head:?}")),
+            }
+        }
+        let def = ClassDef::new(
+            name,
+            is_valuetype,
+            0,
+            extends,
+            fields,
+            static_fields,
+            access,
+            explict_size,
+            align,
+            has_nonveralpping_layout,
+        );
+        let cref = self
+            .asm
+            .alloc_class_ref(ClassRef::new(name, None, is_valuetype, [].into()));
+        if self.asm.class_defs().contains_key(&ClassDefIdx(cref)) {
+            return err(
+                expr,
+                format!("class {:?} is defined twice", &self.asm[name]),
+            );
+        }
+        let class = match self.asm.class_def(def) {
+            Ok(class) => class,
+            Err(layout) => return err(expr, format!("invalid class layout: {layout:?}")),
+        };
+        for method in methods {
+            self.method_def(method, class)?;
+        }
+        Ok(())
+    }
+    fn method_def(&mut self, expr: &SExpr, class: ClassDefIdx) -> PResult<()> {
+        let (_, rest) = tagged(expr, "a method")?;
+        if rest.len() < 6 {
+            return err(
+                expr,
+                "a method needs an access, kind, name, signature, argument names and a body",
+            );
+        }
+        let access = access(&rest[0])?;
+        let kind = method_kind(&rest[1])?;
+        let name = self.asm.alloc_string(string(&rest[2])?);
+        let sig = self.sig(&rest[3])?;
+        let (head, names) = tagged(&rest[4], "argument names")?;
+        if head != "args" {
+            return err(&rest[4], "expected argument names");
+        }
+        let arg_names = names
+            .iter()
+            .map(|name| match name {
+                SExpr::Atom(atom, _) if atom == "_" => Ok(None),
+                _ => Ok(Some(self.asm.alloc_string(string(name)?))),
+            })
+            .collect::<PResult<_>>()?;
+        let body = &rest[5..];
+        let implementation = match &body[0] {
+            SExpr::Atom(atom, _) if atom == "missing" && body.len() == 1 => MethodImpl::Missing,
+            first => {
+                let (head, args) = tagged(first, "a method body")?;
+                match head {
+                    "extern" if body.len() == 1 => {
+                        let (lib, preserve_errno) = match args {
+                            [lib] => (lib, false),
+                            [lib, flag] if flag.as_atom() == Some("preserve_errno") => (lib, true),
+                            _ => return err(first, "expected a library name and `preserve_errno`"),
+                        };
+                        MethodImpl::Extern {
+                            lib: self.asm.alloc_string(string(lib)?),
+                            preserve_errno,
+                        }
+                    }
+                    "alias" if body.len() == 1 => {
+                        let [mref] = self::args(first, args)?;
+                        MethodImpl::AliasFor(self.method_ref(mref)?)
+                    }
+                    "locals" => {
+                        let locals = args
+                            .iter()
+                            .map(|local| self.local(local))
+                            .collect::<PResult<_>>()?;
+                        let blocks = body[1..]
+                            .iter()
+                            .map(|block| self.basic_block(block, true))
+                            .collect::<PResult<_>>()?;
+                        MethodImpl::MethodBody { blocks, locals }
+                    }
+                    _ => return err(first, "expected a method body"),
+                }
+            }
+        };
+        let mref = MethodRef::new(*class, name, sig, kind, [].into());
+        if let Some(mref) = self.asm.get_prealllocated_methodref(mref) {
+            if self.asm.method_ref_to_def(mref).is_some() {
+                return err(
+                    expr,
+                    format!("method {:?} is defined twice", &self.asm[name]),
+                );
+            }
+        }
+        self.asm.new_method(MethodDef::new(
+            access,
+            class,
+            name,
+            sig,
+            kind,
+            implementation,
+            arg_names,
+        ));
+        Ok(())
+    }
+    fn local(&mut self, expr: &SExpr) -> PResult<LocalDef> {
+        let (head, args) = tagged(expr, "a local")?;
+        if head != "local" {
+            return err(expr, "expected a local");
+        }
+        let (tpe, name) = match args {
+            [tpe] => (tpe, None),
+            [tpe, name] => (tpe, Some(self.asm.alloc_string(string(name)?))),
+            _ => return err(expr, "expected a local type and an optional name"),
+        };
+        let tpe = self.ty(tpe)?;
+        Ok((name, self.asm.alloc_type(tpe)))
+    }
+    fn basic_block(&mut self, expr: &SExpr, allow_handler: bool) -> PResult<BasicBlock> {
+        let (head, args) = tagged(expr, "a basic block")?;
+        if head != "block" || args.is_empty() {
+            return err(expr, "expected a basic block");
+        }
+        let block_id = num(&args[0])?;
+        let mut roots = vec![];
+        let mut handler = None;
+        for (idx, elem) in args[1..].iter().enumerate() {
+            if let Some(("handler", blocks)) = elem.as_tagged() {
+                if !allow_handler {
+                    return err(elem, "handlers can't be nested");
+                }
+                if idx != args.len() - 2 {
+                    return err(elem, "the handler must be the last element of a block");
+                }
+                handler = Some(
+                    blocks
+                        .iter()
+                        .map(|block| self.basic_block(block, false))
+                        .collect::<PResult<_>>()?,
+                );
+            } else {
+                let root = self.root(elem)?;
+                roots.push(self.asm.alloc_root(root));
+            }
+        }
+        Ok(BasicBlock::new(roots, block_id, handler))
+    }
+    fn ty(&mut self, expr: &SExpr) -> PResult<Type> {
+        if let Some(name) = expr.as_atom() {
+            if let Some(int) = int(name) {
+                return Ok(Type::Int(int));
+            }
+            if let Some(float) = float(name) {
+                return Ok(Type::Float(float));
+            }
+            return match name {
+                "bool" => Ok(Type::Bool),
+                "void" => Ok(Type::Void),
+                "string" => Ok(Type::PlatformString),
+                "char" => Ok(Type::PlatformChar),
+                "object" => Ok(Type::PlatformObject),
+                _ => err(expr, format!("unknown type {name:?}")),
+            };
+        }
+        let (head, rest) = tagged(expr, "a type")?;
+        match head {
+            "ptr" => {
+                let [inner] = args(expr, rest)?;
+                let inner = self.ty(inner)?;
+                Ok(self.asm.nptr(inner))
+            }
+            "ref" => {
+                let [inner] = args(expr, rest)?;
+                let inner = self.ty(inner)?;
+                Ok(self.asm.nref(inner))
+            }
+            "class" | "valuetype" => Ok(Type::ClassRef(self.class_ref(expr)?)),
+            "generic" => {
+                let [idx, kind] = args(expr, rest)?;
+                let kind = match atom(kind, "a generic kind")? {
+                    "method" => GenericKind::MethodGeneric,
+                    "call" => GenericKind::CallGeneric,
+                    "type" => GenericKind::TypeGeneric,
+                    other => return err(kind, format!("unknown generic kind {other:?}")),
+                };
+                Ok(Type::PlatformGeneric(num(idx)?, kind))
+            }
+            "array" => {
+                let [elem, dims] = args(expr, rest)?;
+                let elem = self.ty(elem)?;
+                Ok(Type::PlatformArray {
+                    elem: self.asm.alloc_type(elem),
+                    dims: num::<NonZeroU8>(dims)?,
+                })
+            }
+            "fnptr" => {
+                let [inputs, output] = args(expr, rest)?;
+                Ok(Type::FnPtr(self.sig_parts(inputs, output)?))
+            }
+            "simd" => {
+                let [elem, count] = args(expr, rest)?;
+                let name = atom(elem, "a SIMD element type")?;
+                let (elem, bits) = if let Some(int) = int(name) {
+                    (SIMDElem::Int(int), int.bits().unwrap_or(64))
+                } else if let Some(float) = float(name) {
+                    (SIMDElem::Float(float), float.bits())
+                } else {
+                    return err(elem, format!("{name:?} is not a valid SIMD element type"));
+                };
+                let count: u8 = num(count)?;
+                if ![64, 128, 256, 512].contains(&(u32::from(bits) * u32::from(count))) {
+                    return err(expr, "unsupported SIMD vector size");
+                }
+                Ok(Type::SIMDVector(SIMDVector::new(elem, count)))
+            }
+            _ => err(expr, format!("unknown type {head:?}")),
+        }
+    }
+    fn class_ref(&mut self, expr: &SExpr) -> PResult<Interned<ClassRef>> {
+        let (head, rest) = tagged(expr, "a class reference")?;
+        let is_valuetype = match head {
+            "valuetype" => true,
+            "class" => false,
+            _ => return err(expr, "expected a class reference"),
+        };
+        let Some((name, mut rest)) = rest.split_first() else {
+            return err(expr, "expected a class name");
+        };
+        let name = self.asm.alloc_string(string(name)?);
+        let mut cref_asm = None;
+        if let Some((first, tail)) = rest.split_first() {
+            if let Some(("in", in_asm)) = first.as_tagged() {
+                let [in_asm] = args(first, in_asm)?;
+                cref_asm = Some(self.asm.alloc_string(string(in_asm)?));
+                rest = tail;
+            }
+        }
+        let generics = rest
+            .iter()
+            .map(|tpe| self.ty(tpe))
+            .collect::<PResult<_>>()?;
+        Ok(self
+            .asm
+            .alloc_class_ref(ClassRef::new(name, cref_asm, is_valuetype, generics)))
+    }
+    fn sig_parts(&mut self, inputs: &SExpr, output: &SExpr) -> PResult<Interned<FnSig>> {
+        let SExpr::List(inputs, _) = inputs else {
+            return err(inputs, "expected a list of argument types");
+        };
+        let (inputs, variadic) = match inputs.split_last() {
+            Some((last, fixed)) if last.as_atom() == Some("...") => (fixed, true),
+            _ => (inputs.as_slice(), false),
+        };
+        let inputs = inputs
+            .iter()
+            .map(|tpe| self.ty(tpe))
+            .collect::<PResult<Box<[_]>>>()?;
+        let output = self.ty(output)?;
+        let sig = if variadic {
+            FnSig::new_variadic(inputs, output)
+        } else {
+            FnSig::new(inputs, output)
+        };
+        Ok(self.asm.alloc_sig(sig))
+    }
+    fn sig(&mut self, expr: &SExpr) -> PResult<Interned<FnSig>> {
+        let (head, rest) = tagged(expr, "a signature")?;
+        if head != "sig" {
+            return err(expr, "expected a signature");
+        }
+        let [inputs, output] = args(expr, rest)?;
+        self.sig_parts(inputs, output)
+    }
+    fn method_ref(&mut self, expr: &SExpr) -> PResult<Interned<MethodRef>> {
+        let (head, rest) = tagged(expr, "a method reference")?;
+        if head != "mref" || !(4..=5).contains(&rest.len()) {
+            return err(expr, "expected a method reference");
+        }
+        let class = self.class_ref(&rest[0])?;
+        let name = self.asm.alloc_string(string(&rest[1])?);
+        let kind = method_kind(&rest[2])?;
+        let sig = self.sig(&rest[3])?;
+        let generics = match rest.get(4) {
+            Some(generics) => {
+                let (head, generics) = tagged(generics, "method generics")?;
+                if head != "generics" {
+                    return err(&rest[4], "expected method generics");
+                }
+                generics
+                    .iter()
+                    .map(|tpe| self.ty(tpe))
+                    .collect::<PResult<_>>()?
+            }
+            None => [].into(),
+        };
+        Ok(self
+            .asm
+            .alloc_methodref(MethodRef::new(class, name, sig, kind, generics)))
+    }
+    fn field(&mut self, expr: &SExpr) -> PResult<Interned<FieldDesc>> {
+        let (head, rest) = tagged(expr, "a field")?;
+        if head != "field" {
+            return err(expr, "expected a field");
+        }
+        let [owner, name, tpe] = args(expr, rest)?;
+        let owner = self.class_ref(owner)?;
+        let name = self.asm.alloc_string(string(name)?);
+        let tpe = self.ty(tpe)?;
+        Ok(self.asm.alloc_field(FieldDesc::new(owner, name, tpe)))
+    }
+    fn static_field(&mut self, expr: &SExpr) -> PResult<Interned<StaticFieldDesc>> {
+        let (head, rest) = tagged(expr, "a static field")?;
+        if head != "sfield" {
+            return err(expr, "expected a static field");
+        }
+        let [owner, name, tpe] = args(expr, rest)?;
+        let owner = self.class_ref(owner)?;
+        let name = self.asm.alloc_string(string(name)?);
+        let tpe = self.ty(tpe)?;
+        Ok(self.asm.alloc_sfld(StaticFieldDesc::new(owner, name, tpe)))
+    }
+    fn cst(&mut self, expr: &SExpr) -> PResult<Const> {
+        let (head, rest) = tagged(expr, "a constant")?;
+        if head != "const" {
+            return err(expr, "expected a constant");
+        }
+        if let [kind, tpe, data] = rest {
+            if kind.as_atom() == Some("bytes") {
+                let tpe = self.ty(tpe)?;
+                let data = unhex(data)?;
+                return Ok(Const::ByteBuffer {
+                    data: self.asm.alloc_const_data(&data),
+                    tpe: self.asm.alloc_type(tpe),
+                });
+            }
+        }
+        let [kind, val] = args(expr, rest)?;
+        Ok(match atom(kind, "a constant type")? {
+            "i8" => Const::I8(num(val)?),
+            "i16" => Const::I16(num(val)?),
+            "i32" => Const::I32(num(val)?),
+            "i64" => Const::I64(num(val)?),
+            "i128" => Const::I128(num(val)?),
+            "isize" => Const::ISize(num(val)?),
+            "u8" => Const::U8(num(val)?),
+            "u16" => Const::U16(num(val)?),
+            "u32" => Const::U32(num(val)?),
+            "u64" => Const::U64(num(val)?),
+            "u128" => Const::U128(num(val)?),
+            "usize" => Const::USize(num(val)?),
+            "bool" => Const::Bool(num(val)?),
+            "string" => Const::PlatformString(self.asm.alloc_string(string(val)?)),
+            "null" => Const::Null(self.class_ref(val)?),
+            "f32" => Const::F32(HashableF32(match nan_bits(val)? {
+                Some(bits) => match u32::try_from(bits) {
+                    Ok(bits) => f32::from_bits(bits),
+                    Err(_) => return err(val, "NaN payload does not fit in a f32"),
+                },
+                None => num(val)?,
+            })),
+            "f64" => Const::F64(HashableF64(match nan_bits(val)? {
+                Some(bits) => f64::from_bits(bits),
+                None => num(val)?,
+            })),
+            other => return err(kind, format!("unknown constant type {other:?}")),
+        })
+    }
+    fn node(&mut self, expr: &SExpr) -> PResult<Interned<CILNode>> {
+        let node = self.node_inner(expr)?;
+        Ok(self.asm.alloc_node(node))
+    }
+    fn nodes(&mut self, exprs: &[SExpr]) -> PResult<Box<[Interned<CILNode>]>> {
+        exprs.iter().map(|expr| self.node(expr)).collect()
+    }
+    fn type_idx(&mut self, expr: &SExpr) -> PResult<Interned<Type>> {
+        let tpe = self.ty(expr)?;
+        Ok(self.asm.alloc_type(tpe))
+    }
+    fn call_info(&mut self, expr: &SExpr, rest: &[SExpr], pure: IsPure) -> PResult<Box<CallInfo>> {
+        let Some((mref, args)) = rest.split_first() else {
+            return err(expr, "expected a method reference");
+        };
+        let mref = self.method_ref(mref)?;
+        Ok(Box::new((mref, self.nodes(args)?, pure)))
+    }
+    fn calli_info(&mut self, expr: &SExpr, rest: &[SExpr]) -> PResult<Box<CallIInfo>> {
+        if rest.len() < 2 {
+            return err(expr, "expected a signature and a function pointer");
+        }
+        let sig = self.sig(&rest[0])?;
+        let ptr = self.node(&rest[1])?;
+        Ok(Box::new((ptr, sig, self.nodes(&rest[2..])?)))
+    }
+    fn node_inner(&mut self, expr: &SExpr) -> PResult<CILNode> {
+        let (head, rest) = tagged(expr, "a node")?;
+        if let Some(op) = bin_op(head) {
+            let [lhs, rhs] = args(expr, rest)?;
+            return Ok(CILNode::BinOp(self.node(lhs)?, self.node(rhs)?, op));
+        }
+        Ok(match head {
+            "const" => CILNode::Const(Box::new(self.cst(expr)?)),
+            "not" | "neg" => {
+                let [val] = args(expr, rest)?;
+                let op = if head == "not" { UnOp::Not } else { UnOp::Neg };
+                CILNode::UnOp(self.node(val)?, op)
+            }
+            "ldloc" => CILNode::LdLoc(num(&args::<1>(expr, rest)?[0])?),
+            "ldloca" => CILNode::LdLocA(num(&args::<1>(expr, rest)?[0])?),
+            "ldarg" => CILNode::LdArg(num(&args::<1>(expr, rest)?[0])?),
+            "ldarga" => CILNode::LdArgA(num(&args::<1>(expr, rest)?[0])?),
+            "call" => CILNode::Call(self.call_info(expr, rest, IsPure::NOT)?),
+            "call.pure" => CILNode::Call(self.call_info(expr, rest, IsPure::PURE)?),
+            "conv" => {
+                let [target, extend, input] = args(expr, rest)?;
+                let Some(target) = int(atom(target, "an integer type")?) else {
+                    return err(target, "expected an integer type");
+                };
+                let extend = match atom(extend, "`zext` or `sext`")? {
+                    "zext" => ExtendKind::ZeroExtend,
+                    "sext" => ExtendKind::SignExtend,
+                    _ => return err(extend, "expected `zext` or `sext`"),
+                };
+                CILNode::IntCast {
+                    input: self.node(input)?,
+                    target,
+                    extend,
+                }
+            }
+            "conv.f" => {
+                let [target, signed, input] = args(expr, rest)?;
+                let Some(target) = float(atom(target, "a float type")?) else {
+                    return err(target, "expected a float type");
+                };
+                let is_signed = match atom(signed, "`signed` or `unsigned`")? {
+                    "signed" => true,
+                    "unsigned" => false,
+                    _ => return err(signed, "expected `signed` or `unsigned`"),
+                };
+                CILNode::FloatCast {
+                    input: self.node(input)?,
+                    target,
+                    is_signed,
+                }
+            }
+            "ref_to_ptr" => CILNode::RefToPtr(self.node(&args::<1>(expr, rest)?[0])?),
+            "ptr_cast" => {
+                let [res, val] = args(expr, rest)?;
+                let res = match self.ty(res)? {
+                    Type::Ptr(inner) => PtrCastRes::Ptr(inner),
+                    Type::Ref(inner) => PtrCastRes::Ref(inner),
+                    Type::FnPtr(sig) => PtrCastRes::FnPtr(sig),
+                    Type::Int(Int::USize) => PtrCastRes::USize,
+                    Type::Int(Int::ISize) => PtrCastRes::ISize,
+                    _ => return err(res, "a pointer can't be cast to this type"),
+                };
+                CILNode::PtrCast(self.node(val)?, Box::new(res))
+            }
+            "ldflda" | "ldfld" => {
+                let [field, addr] = args(expr, rest)?;
+                let field = self.field(field)?;
+                let addr = self.node(addr)?;
+                if head == "ldflda" {
+                    CILNode::LdFieldAddress { addr, field }
+                } else {
+                    CILNode::LdField { addr, field }
+                }
+            }
+            "ldind" | "ldind.volatile" => {
+                let [tpe, addr] = args(expr, rest)?;
+                CILNode::LdInd {
+                    tpe: self.type_idx(tpe)?,
+                    addr: self.node(addr)?,
+                    volatile: head == "ldind.volatile",
+                }
+            }
+            "sizeof" => CILNode::SizeOf(self.type_idx(&args::<1>(expr, rest)?[0])?),
+            "get_exception" => {
+                args::<0>(expr, rest)?;
+                CILNode::GetException
+            }
+            "isinst" | "castclass" => {
+                let [tpe, val] = args(expr, rest)?;
+                let tpe = self.type_idx(tpe)?;
+                let val = self.node(val)?;
+                if head == "isinst" {
+                    CILNode::IsInst(val, tpe)
+                } else {
+                    CILNode::CheckedCast(val, tpe)
+                }
+            }
+            "calli" => CILNode::CallI(self.calli_info(expr, rest)?),
+            "localloc" => CILNode::LocAlloc {
+                size: self.node(&args::<1>(expr, rest)?[0])?,
+            },
+            "ldsfld" => CILNode::LdStaticField(self.static_field(&args::<1>(expr, rest)?[0])?),
+            "ldsflda" => {
+                CILNode::LdStaticFieldAddress(self.static_field(&args::<1>(expr, rest)?[0])?)
+            }
+            "ldftn" => CILNode::LdFtn(self.method_ref(&args::<1>(expr, rest)?[0])?),
+            "ldtoken" => CILNode::LdTypeToken(self.type_idx(&args::<1>(expr, rest)?[0])?),
+            "ldlen" => CILNode::LdLen(self.node(&args::<1>(expr, rest)?[0])?),
+            "localloc.aligned" => {
+                let [tpe, align] = args(expr, rest)?;
+                CILNode::LocAllocAlgined {
+                    tpe: self.type_idx(tpe)?,
+                    align: num(align)?,
+                }
+            }
+            "ldelema" => {
+                let [array, index] = args(expr, rest)?;
+                CILNode::LdElelemRef {
+                    array: self.node(array)?,
+                    index: self.node(index)?,
+                }
+            }
+            "unbox.any" => {
+                let [tpe, object] = args(expr, rest)?;
+                CILNode::UnboxAny {
+                    tpe: self.type_idx(tpe)?,
+                    object: self.node(object)?,
+                }
+            }
+            _ => return err(expr, format!("unknown node {head:?}")),
+        })
+    }
+    fn branch_cond(&mut self, expr: &SExpr) -> PResult<BranchCond> {
+        let (head, rest) = tagged(expr, "a branch condition")?;
+        Ok(match head {
+            "true" => BranchCond::True(self.node(&args::<1>(expr, rest)?[0])?),
+            "false" => BranchCond::False(self.node(&args::<1>(expr, rest)?[0])?),
+            "eq" | "ne" => {
+                let [lhs, rhs] = args(expr, rest)?;
+                let (lhs, rhs) = (self.node(lhs)?, self.node(rhs)?);
+                if head == "eq" {
+                    BranchCond::Eq(lhs, rhs)
+                } else {
+                    BranchCond::Ne(lhs, rhs)
+                }
+            }
+            "lt" | "gt" | "le" | "ge" => {
+                let [kind, lhs, rhs] = args(expr, rest)?;
+                let kind = match atom(kind, "a comparison kind")? {
+                    "ordered" => CmpKind::Ordered,
+                    "unordered" => CmpKind::Unordered,
+                    "signed" => CmpKind::Signed,
+                    "unsigned" => CmpKind::Unsigned,
+                    other => return err(kind, format!("unknown comparison kind {other:?}")),
+                };
+                let (lhs, rhs) = (self.node(lhs)?, self.node(rhs)?);
+                match head {
+                    "lt" => BranchCond::Lt(lhs, rhs, kind),
+                    "gt" => BranchCond::Gt(lhs, rhs, kind),
+                    "le" => BranchCond::Le(lhs, rhs, kind),
+                    _ => BranchCond::Ge(lhs, rhs, kind),
+                }
+            }
+            _ => return err(expr, format!("unknown branch condition {head:?}")),
+        })
+    }
+    fn root(&mut self, expr: &SExpr) -> PResult<CILRoot> {
+        let (head, rest) = tagged(expr, "a root")?;
+        Ok(match head {
+            "stloc" | "starg" => {
+                let [idx, val] = args(expr, rest)?;
+                let (idx, val) = (num(idx)?, self.node(val)?);
+                if head == "stloc" {
+                    CILRoot::StLoc(idx, val)
+                } else {
+                    CILRoot::StArg(idx, val)
+                }
+            }
+            "ret" => CILRoot::Ret(self.node(&args::<1>(expr, rest)?[0])?),
+            "pop" => CILRoot::Pop(self.node(&args::<1>(expr, rest)?[0])?),
+            "throw" => CILRoot::Throw(self.node(&args::<1>(expr, rest)?[0])?),
+            "ret_void" | "break" | "nop" | "rethrow" => {
+                args::<0>(expr, rest)?;
+                match head {
+                    "ret_void" => CILRoot::VoidRet,
+                    "break" => CILRoot::Break,
+                    "nop" => CILRoot::Nop,
+                    _ => CILRoot::ReThrow,
+                }
+            }
+            "br" => {
+                let (target, sub_target, cond) = match rest {
+                    [target, sub_target] => (target, sub_target, None),
+                    [target, sub_target, cond] => {
+                        (target, sub_target, Some(self.branch_cond(cond)?))
+                    }
+                    _ => {
+                        return err(
+                            expr,
+                            "expected a target, sub target and an optional condition",
+                        )
+                    }
+                };
+                CILRoot::Branch(Box::new((num(target)?, num(sub_target)?, cond)))
+            }
+            "sfi" => {
+                let [file, line_start, line_len, col_start, col_len] = args(expr, rest)?;
+                CILRoot::SourceFileInfo {
+                    line_start: num(line_start)?,
+                    line_len: num(line_len)?,
+                    col_start: num(col_start)?,
+                    col_len: num(col_len)?,
+                    file: self.asm.alloc_string(string(file)?),
+                }
+            }
+            "stfld" => {
+                let [field, addr, val] = args(expr, rest)?;
+                CILRoot::SetField(Box::new((
+                    self.field(field)?,
+                    self.node(addr)?,
+                    self.node(val)?,
+                )))
+            }
+            "call" => CILRoot::Call(self.call_info(expr, rest, IsPure::NOT)?),
+            "call.pure" => CILRoot::Call(self.call_info(expr, rest, IsPure::PURE)?),
+            "stind" | "stind.volatile" => {
+                let [tpe, addr, val] = args(expr, rest)?;
+                let tpe = self.ty(tpe)?;
+                CILRoot::StInd(Box::new((
+                    self.node(addr)?,
+                    self.node(val)?,
+                    tpe,
+                    head == "stind.volatile",
+                )))
+            }
+            "initblk" | "cpblk" => {
+                let [a, b, c] = args(expr, rest)?;
+                let args = Box::new((self.node(a)?, self.node(b)?, self.node(c)?));
+                if head == "initblk" {
+                    CILRoot::InitBlk(args)
+                } else {
+                    CILRoot::CpBlk(args)
+                }
+            }
+            "calli" => CILRoot::CallI(self.calli_info(expr, rest)?),
+            "switch" => {
+                let [value, default, targets @ ..] = rest else {
+                    return err(
+                        expr,
+                        "expected a value, a default target and a list of targets",
+                    );
+                };
+                let default = match default.as_tagged() {
+                    Some(("default", default)) => {
+                        let [target, sub_target] = args(expr, default)?;
+                        (num(target)?, num(sub_target)?)
+                    }
+                    _ => return err(default, "expected a default target"),
+                };
+                let targets = targets
+                    .iter()
+                    .map(|target| match target {
+                        SExpr::List(elems, _) => {
+                            let [target, sub_target] = args(expr, elems)?;
+                            Ok((num(target)?, num(sub_target)?))
+                        }
+                        _ => err(target, "expected a target and a sub target"),
+                    })
+                    .collect::<PResult<Box<[_]>>>()?;
+                CILRoot::Switch(Box::new((self.node(value)?, targets, default)))
+            }
+            "exit_region" => {
+                let [target, source] = args(expr, rest)?;
+                CILRoot::ExitSpecialRegion {
+                    target: num(target)?,
+                    source: num(source)?,
+                }
+            }
+            "stsfld" => {
+                let [field, val] = args(expr, rest)?;
+                CILRoot::SetStaticField {
+                    field: self.static_field(field)?,
+                    val: self.node(val)?,
+                }
+            }
+            "cpobj" => {
+                let [tpe, dst, src] = args(expr, rest)?;
+                CILRoot::CpObj {
+                    tpe: self.type_idx(tpe)?,
+                    dst: self.node(dst)?,
+                    src: self.node(src)?,
+                }
+            }
+            "unreachable" => {
+                let [msg] = args(expr, rest)?;
+                CILRoot::Unreachable(self.asm.alloc_string(string(msg)?))
+            }
+            "initobj" => {
+                let [tpe, addr] = args(expr, rest)?;
+                let tpe = self.type_idx(tpe)?;
+                CILRoot::InitObj(self.node(addr)?, tpe)
+            }
+            "asm" => CILRoot::InlineAsm(Box::new(self.inline_asm(expr, rest)?)),
+            "va_start" => {
+                let [list] = args(expr, rest)?;
+                CILRoot::VaStart(self.node(list)?)
+            }
+            "va_arg" => {
+                let [tpe, list, dst] = args(expr, rest)?;
+                let tpe = self.type_idx(tpe)?;
+                CILRoot::VaArg(Box::new((self.node(list)?, self.node(dst)?, tpe)))
+            }
+            _ => return err(expr, format!("unknown root {head:?}")),
+        })
+    }
+    fn asm_pieces(&mut self, exprs: &[SExpr]) -> PResult<Box<[AsmPiece]>> {
+        exprs
+            .iter()
+            .map(|expr| match expr {
+                SExpr::Str(text, _) => Ok(AsmPiece::Str(self.asm.alloc_string(text.as_str()))),
+                _ => match tagged(expr, "a string or a symbol")? {
+                    ("sym", rest) => Ok(AsmPiece::Sym(self.node(&args::<1>(expr, rest)?[0])?)),
+                    _ => err(expr, "expected a string or a symbol"),
+                },
+            })
+            .collect()
+    }
+    /// Parses the explicit register of an assembly operand, written as `(reg "name")`.
+    fn asm_reg(&mut self, expr: &SExpr) -> PResult<Interned<IString>> {
+        match tagged(expr, "a register")? {
+            ("reg", rest) => Ok(self.asm.alloc_string(string(&args::<1>(expr, rest)?[0])?)),
+            _ => err(expr, "expected a register"),
+        }
+    }
+    fn inline_asm(&mut self, expr: &SExpr, rest: &[SExpr]) -> PResult<InlineAsm> {
+        let (is_volatile, rest) = match rest {
+            [first, rest @ ..] if first.as_atom() == Some("volatile") => (true, rest),
+            _ => (false, rest),
+        };
+        let mut res = InlineAsm {
+            template: [].into(),
+            outputs: [].into(),
+            inputs: [].into(),
+            clobbers: [].into(),
+            is_volatile,
+        };
+        let (mut outputs, mut inputs) = (vec![], vec![]);
+        for part in rest {
+            let (head, rest) = tagged(part, "a part of an assembly block")?;
+            match head {
+                "template" => res.template = self.asm_pieces(rest)?,
+                "clobbers" => {
+                    res.clobbers = rest
+                        .iter()
+                        .map(|clobber| Ok(self.asm.alloc_string(string(clobber)?)))
+                        .collect::<PResult<_>>()?
+                }
+                "out" => {
+                    let [constraint, tpe, parts @ ..] = rest else {
+                        return err(part, "expected a constraint and a type");
+                    };
+                    let mut output = AsmOutput {
+                        constraint: self.asm.alloc_string(string(constraint)?),
+                        reg: None,
+                        tpe: self.type_idx(tpe)?,
+                        init: None,
+                        dst: None,
+                    };
+                    for expr in parts {
+                        match tagged(expr, "a register, init or dst")? {
+                            ("reg", _) => output.reg = Some(self.asm_reg(expr)?),
+                            ("init", rest) => {
+                                output.init = Some(self.node(&args::<1>(expr, rest)?[0])?)
+                            }
+                            ("dst", rest) => {
+                                output.dst = Some(self.node(&args::<1>(expr, rest)?[0])?)
+                            }
+                            _ => return err(expr, "expected a register, init or dst"),
+                        }
+                    }
+                    outputs.push(output);
+                }
+                "in" => {
+                    let (constraint, tpe, val, reg) =
+                        match rest {
+                            [constraint, tpe, val] => (constraint, tpe, val, None),
+                            [constraint, tpe, val, reg] => (constraint, tpe, val, Some(reg)),
+                            _ => return err(
+                                part,
+                                "expected a constraint, a type, a value and an optional register",
+                            ),
+                        };
+                    let reg = reg.map(|reg| self.asm_reg(reg)).transpose()?;
+                    inputs.push(AsmInput {
+                        constraint: self.asm.alloc_string(string(constraint)?),
+                        reg,
+                        tpe: self.type_idx(tpe)?,
+                        val: self.node(val)?,
+                    });
+                }
+                _ => return err(part, format!("unknown assembly block part {head:?}")),
+            }
+        }
+        if res.template.is_empty() {
+            return err(expr, "expected an assembly template");
+        }
+        res.outputs = outputs.into();
+        res.inputs = inputs.into();
+        Ok(res)
+    }
+}
+/// Parses the bits of a NaN written as `nan:0x...`, if `expr` is such a NaN.
+fn nan_bits(expr: &SExpr) -> PResult<Option<u64>> {
+    let Some(bits) = atom(expr, "a float")?.strip_prefix("nan:0x") else {
+        return Ok(None);
+    };
+    match u64::from_str_radix(bits, 16) {
+        Ok(bits) => Ok(Some(bits)),
+        Err(_) => err(expr, "invalid NaN bits"),
+    }
+}
@@ -0,0 +1,690 @@
+use super::sexpr::SExpr;
+use crate::{
+    cilnode::{ExtendKind, IsPure, MethodKind, PtrCastRes, UnOp},
+    cilroot::{BranchCond, CmpKind},
+    class::StaticFieldDef,
+    tpe::{simd::SIMDElem, GenericKind},
+    Access, AsmPiece, Assembly, BasicBlock, BinOp, CILNode, CILRoot, ClassDef, ClassRef, Const,
+    FieldDesc, FnSig, InlineAsm, Interned, MethodDef, MethodImpl, MethodRef, StaticFieldDesc, Type,
+};
+
+pub(super) fn assembly(asm: &Assembly) -> Vec<SExpr> {
+    let mut defs: Vec<_> = asm.class_defs().values().collect();
+    // Class defs are stored in a hash map, so they need to be sorted to get a stable output.
+    defs.sort_by(|a, b| {
+        (&asm[a.name()], a.is_valuetype()).cmp(&(&asm[b.name()], b.is_valuetype()))
+    });
+    let mut res: Vec<_> = defs.into_iter().map(|def| class_def(def, asm)).collect();
+    let mut sections: Vec<_> = asm.sections().collect();
+    sections.sort_by_key(|(name, _)| *name);
+    res.extend(
+        sections.into_iter().map(|(name, data)| {
+            SExpr::tagged("section", [SExpr::str(name), SExpr::str(hex(data))])
+        }),
+    );
+    res.extend(
+        asm.global_asm()
+            .iter()
+            .map(|pieces| SExpr::tagged("global_asm", asm_pieces(pieces, asm))),
+    );
+    let mut link_sections: Vec<_> = asm.link_sections().collect();
+    link_sections.sort_by_key(|(name, _)| &asm[*name]);
+    res.extend(link_sections.into_iter().map(|(name, statics)| {
+        let mut res = vec![SExpr::str(&asm[name])];
+        res.extend(statics.iter().map(|sfld| static_field(&asm[*sfld], asm)));
+        SExpr::tagged("link_section", res)
+    }));
+    // Statics in a linker section are always used, so they don't need to be listed again.
+    let used: Vec<_> = asm
+        .used_statics()
+        .filter(|sfld| asm.link_section_of(*sfld).is_none())
+        .map(|sfld| static_field(&asm[sfld], asm))
+        .collect();
+    if !used.is_empty() {
+        res.push(SExpr::tagged("used", used));
+    }
+    res
+}
+fn asm_pieces(pieces: &[AsmPiece], asm: &Assembly) -> Vec<SExpr> {
+    pieces
+        .iter()
+        .map(|piece| match piece {
+            AsmPiece::Str(text) => SExpr::str(&asm[*text]),
+            AsmPiece::Sym(sym) => SExpr::tagged("sym", [node(*sym, asm)]),
+        })
+        .collect()
+}
+fn inline_asm(inline_asm: &InlineAsm, asm: &Assembly) -> SExpr {
+    let n = |idx: &Interned<CILNode>| node(*idx, asm);
+    let reg = |reg: &Interned<_>| SExpr::tagged("reg", [SExpr::str(&asm[*reg])]);
+    let mut res = vec![SExpr::atom("asm")];
+    if inline_asm.is_volatile {
+        res.push(SExpr::atom("volatile"));
+    }
+    res.push(SExpr::tagged(
+        "template",
+        asm_pieces(&inline_asm.template, asm),
+    ));
+    res.extend(inline_asm.outputs.iter().map(|output| {
+        let mut res = vec![
+            SExpr::atom("out"),
+            SExpr::str(&asm[output.constraint]),
+            ty(asm[output.tpe], asm),
+        ];
+        res.extend(output.reg.as_ref().map(reg));
+        res.extend(
+            output
+                .init
+                .as_ref()
+                .map(|init| SExpr::tagged("init", [n(init)])),
+        );
+        res.extend(
+            output
+                .dst
+                .as_ref()
+                .map(|dst| SExpr::tagged("dst", [n(dst)])),
+        );
+        SExpr::list(res)
+    }));
+    res.extend(inline_asm.inputs.iter().map(|input| {
+        let mut res = vec![
+            SExpr::atom("in"),
+            SExpr::str(&asm[input.constraint]),
+            ty(asm[input.tpe], asm),
+            n(&input.val),
+        ];
+        res.extend(input.reg.as_ref().map(reg));
+        SExpr::list(res)
+    }));
+    res.push(SExpr::tagged(
+        "clobbers",
+        inline_asm
+            .clobbers
+            .iter()
+            .map(|clobber| SExpr::str(&asm[*clobber])),
+    ));
+    SExpr::list(res)
+}
+pub(super) fn class_def(def: &ClassDef, asm: &Assembly) -> SExpr {
+    let mut res = vec![
+        SExpr::atom("classdef"),
+        access(*def.access()),
+        SExpr::atom(if def.is_valuetype() {
+            "valuetype"
+        } else {
+            "class"
+        }),
+        SExpr::str(&asm[def.name()]),
+    ];
+    if def.generics() != 0 {
+        res.push(SExpr::tagged(
+            "generics",
+            [SExpr::atom(def.generics().to_string())],
+        ));
+    }
+    if let Some(extends) = def.extends() {
+        res.push(SExpr::tagged("extends", [class_ref(&asm[extends], asm)]));
+    }
+    if let Some(size) = def.explict_size() {
+        res.push(SExpr::tagged("size", [SExpr::atom(size.to_string())]));
+    }
+    if let Some(align) = def.align() {
+        res.push(SExpr::tagged("align", [SExpr::atom(align.to_string())]));
+    }
+    if !def.has_nonveralpping_layout() {
+        res.push(SExpr::list([SExpr::atom("overlapping")]));
+    }
+    for (tpe, name, offset) in def.fields() {
+        let mut field = vec![SExpr::atom("field"), ty(*tpe, asm), SExpr::str(&asm[*name])];
+        if let Some(offset) = offset {
+            field.push(SExpr::atom(offset.to_string()));
+        }
+        res.push(SExpr::list(field));
+    }
+    for StaticFieldDef {
+        tpe,
+        name,
+        is_tls,
+        default_value,
+        is_const,
+    } in def.static_fields()
+    {
+        let mut field = vec![
+            SExpr::atom("static"),
+            ty(*tpe, asm),
+            SExpr::str(&asm[*name]),
+        ];
+        if *is_tls {
+            field.push(SExpr::atom("tls"));
+        }
+        if *is_const {
+            field.push(SExpr::atom("const"));
+        }
+        if let Some(default_value) = default_value {
+            field.push(SExpr::tagged("default", [cst(default_value, asm)]));
+        }
+        res.push(SExpr::list(field));
+    }
+    res.extend(
+        def.methods()
+            .iter()
+            .map(|method| method_def(&asm[*method], asm)),
+    );
+    SExpr::list(res)
+}
+pub(super) fn method_def(def: &MethodDef, asm: &Assembly) -> SExpr {
+    let arg_names = def.arg_names().iter().map(|name| match name {
+        Some(name) => SExpr::str(&asm[*name]),
+        None => SExpr::atom("_"),
+    });
+    let mut res = vec![
+        SExpr::atom("method"),
+        access(*def.access()),
+        method_kind(def.kind()),
+        SExpr::str(&asm[def.name()]),
+        sig(&asm[def.sig()], asm),
+        SExpr::tagged("args", arg_names),
+    ];
+    match def.implementation() {
+        MethodImpl::MethodBody { blocks, locals } => {
+            res.push(SExpr::tagged(
+                "locals",
+                locals.iter().map(|(name, tpe)| {
+                    let mut local = vec![SExpr::atom("local"), ty(asm[*tpe], asm)];
+                    if let Some(name) = name {
+                        local.push(SExpr::str(&asm[*name]));
+                    }
+                    SExpr::list(local)
+                }),
+            ));
+            res.extend(blocks.iter().map(|block| basic_block(block, asm)));
+        }
+        MethodImpl::Extern {
+            lib,
+            preserve_errno,
+        } => {
+            let mut ext = vec![SExpr::atom("extern"), SExpr::str(&asm[*lib])];
+            if *preserve_errno {
+                ext.push(SExpr::atom("preserve_errno"));
+            }
+            res.push(SExpr::list(ext));
+        }
+        MethodImpl::AliasFor(mref) => {
+            res.push(SExpr::tagged("alias", [method_ref(&asm[*mref], asm)]));
+        }
+        MethodImpl::Missing => res.push(SExpr::atom("missing")),
+    }
+    SExpr::list(res)
+}
+fn basic_block(block: &BasicBlock, asm: &Assembly) -> SExpr {
+    let mut res = vec![
+        SExpr::atom("block"),
+        SExpr::atom(block.block_id().to_string()),
+    ];
+    res.extend(block.roots().iter().map(|root| self::root(*root, asm)));
+    if let Some(handler) = block.handler() {
+        res.push(SExpr::tagged(
+            "handler",
+            handler.iter().map(|block| basic_block(block, asm)),
+        ));
+    }
+    SExpr::list(res)
+}
+fn access(access: Access) -> SExpr {
+    SExpr::atom(match access {
+        Access::Extern => "extern",
+        Access::Public => "public",
+        Access::Private => "private",
+    })
+}
+fn method_kind(kind: MethodKind) -> SExpr {
+    SExpr::atom(match kind {
+        MethodKind::Static => "static",
+        MethodKind::Instance => "instance",
+        MethodKind::Virtual => "virtual",
+        MethodKind::Constructor => "ctor",
+    })
+}
+pub(super) fn ty(tpe: Type, asm: &Assembly) -> SExpr {
+    match tpe {
+        Type::Ptr(inner) => SExpr::tagged("ptr", [ty(asm[inner], asm)]),
+        Type::Ref(inner) => SExpr::tagged("ref", [ty(asm[inner], asm)]),
+        Type::Int(int) => SExpr::atom(int.name()),
+        Type::ClassRef(cref) => class_ref(&asm[cref], asm),
+        Type::Float(float) => SExpr::atom(float.name()),
+        Type::PlatformString => SExpr::atom("string"),
+        Type::PlatformChar => SExpr::atom("char"),
+        Type::PlatformGeneric(idx, kind) => SExpr::tagged(
+            "generic",
+            [
+                SExpr::atom(idx.to_string()),
+                SExpr::atom(match kind {
+                    GenericKind::MethodGeneric => "method",
+                    GenericKind::CallGeneric => "call",
+                    GenericKind::TypeGeneric => "type",
+                }),
+            ],
+        ),
+        Type::PlatformObject => SExpr::atom("object"),
+        Type::Bool => SExpr::atom("bool"),
+        Type::Void => SExpr::atom("void"),
+        Type::PlatformArray { elem, dims } => {
+            SExpr::tagged("array", [ty(asm[elem], asm), SExpr::atom(dims.to_string())])
+        }
+        Type::FnPtr(fn_sig) => {
+            let (inputs, output) = sig_parts(&asm[fn_sig], asm);
+            SExpr::tagged("fnptr", [inputs, output])
+        }
+        Type::SIMDVector(vec) => {
+            let elem = match vec.elem() {
+                SIMDElem::Int(int) => int.name().to_owned(),
+                SIMDElem::Float(float) => float.name().to_owned(),
+            };
+            SExpr::tagged(
+                "simd",
+                [SExpr::atom(elem), SExpr::atom(vec.count().to_string())],
+            )
+        }
+    }
+}
+/// The inputs of a C-variadic signature end with `...`, like in C.
+fn sig_parts(sig: &FnSig, asm: &Assembly) -> (SExpr, SExpr) {
+    (
+        SExpr::list(
+            sig.inputs()
+                .iter()
+                .map(|tpe| ty(*tpe, asm))
+                .chain(sig.is_variadic().then(|| SExpr::atom("...")))
+                .collect::<Vec<_>>(),
+        ),
+        ty(*sig.output(), asm),
+    )
+}
+fn sig(sig: &FnSig, asm: &Assembly) -> SExpr {
+    let (inputs, output) = sig_parts(sig, asm);
+    SExpr::tagged("sig", [inputs, output])
+}
+pub(super) fn class_ref(cref: &ClassRef, asm: &Assembly) -> SExpr {
+    let mut res = vec![
+        SExpr::atom(if cref.is_valuetype() {
+            "valuetype"
+        } else {
+            "class"
+        }),
+        SExpr::str(&asm[cref.name()]),
+    ];
+    if let Some(cref_asm) = cref.asm() {
+        res.push(SExpr::tagged("in", [SExpr::str(&asm[cref_asm])]));
+    }
+    res.extend(cref.generics().iter().map(|tpe| ty(*tpe, asm)));
+    SExpr::list(res)
+}
+fn method_ref(mref: &MethodRef, asm: &Assembly) -> SExpr {
+    let mut res = vec![
+        SExpr::atom("mref"),
+        class_ref(&asm[mref.class()], asm),
+        SExpr::str(&asm[mref.name()]),
+        method_kind(mref.kind()),
+        sig(&asm[mref.sig()], asm),
+    ];
+    if !mref.generics().is_empty() {
+        res.push(SExpr::tagged(
+            "generics",
+            mref.generics().iter().map(|tpe| ty(*tpe, asm)),
+        ));
+    }
+    SExpr::list(res)
+}
+fn field(field: &FieldDesc, asm: &Assembly) -> SExpr {
+    SExpr::tagged(
+        "field",
+        [
+            class_ref(&asm[field.owner()], asm),
+            SExpr::str(&asm[field.name()]),
+            ty(field.tpe(), asm),
+        ],
+    )
+}
+fn static_field(field: &StaticFieldDesc, asm: &Assembly) -> SExpr {
+    SExpr::tagged(
+        "sfield",
+        [
+            class_ref(&asm[field.owner()], asm),
+            SExpr::str(&asm[field.name()]),
+            ty(field.tpe(), asm),
+        ],
+    )
+}
+pub(super) fn hex(data: &[u8]) -> String {
+    use std::fmt::Write;
+    data.iter()
+        .fold(String::with_capacity(data.len() * 2), |mut res, byte| {
+            write!(res, "{byte:02x}").unwrap();
+            res
+        })
+}
+/// Floats are printed in their shortest round-tripping form. NaNs are printed by their bits, to preserve the payload.
+pub(super) fn cst(cst: &Const, asm: &Assembly) -> SExpr {
+    let (tpe, val) = match cst {
+        Const::I8(val) => ("i8", SExpr::atom(val.to_string())),
+        Const::I16(val) => ("i16", SExpr::atom(val.to_string())),
+        Const::I32(val) => ("i32", SExpr::atom(val.to_string())),
+        Const::I64(val) => ("i64", SExpr::atom(val.to_string())),
+        Const::I128(val) => ("i128", SExpr::atom(val.to_string())),
+        Const::ISize(val) => ("isize", SExpr::atom(val.to_string())),
+        Const::U8(val) => ("u8", SExpr::atom(val.to_string())),
+        Const::U16(val) => ("u16", SExpr::atom(val.to_string())),
+        Const::U32(val) => ("u32", SExpr::atom(val.to_string())),
+        Const::U64(val) => ("u64", SExpr::atom(val.to_string())),
+        Const::U128(val) => ("u128", SExpr::atom(val.to_string())),
+        Const::USize(val) => ("usize", SExpr::atom(val.to_string())),
+        Const::PlatformString(string) => ("string", SExpr::str(&asm[*string])),
+        Const::Bool(val) => ("bool", SExpr::atom(val.to_string())),
+        Const::F32(val) => (
+            "f32",
+            if val.is_nan() {
+                SExpr::atom(format!("nan:{:#x}", val.to_bits()))
+            } else {
+                SExpr::atom(format!("{:?}", val.0))
+            },
+        ),
+        Const::F64(val) => (
+            "f64",
+            if val.is_nan() {
+                SExpr::atom(format!("nan:{:#x}", val.to_bits()))
+            } else {
+                SExpr::atom(format!("{:?}", val.0))
+            },
+        ),
+        Const::Null(cref) => ("null", class_ref(&asm[*cref], asm)),
+        Const::ByteBuffer { data, tpe } => {
+            return SExpr::tagged(
+                "const",
+                [
+                    SExpr::atom("bytes"),
+                    ty(asm[*tpe], asm),
+                    SExpr::str(hex(&asm.const_data[*data])),
+                ],
+            )
+        }
+    };
+    SExpr::tagged("const", [SExpr::atom(tpe), val])
+}
+pub(super) fn bin_op(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "add",
+        BinOp::Eq => "eq",
+        BinOp::Sub => "sub",
+        BinOp::Mul => "mul",
+        BinOp::LtUn => "lt.un",
+        BinOp::Lt => "lt",
+        BinOp::GtUn => "gt.un",
+        BinOp::Gt => "gt",
+        BinOp::Or => "or",
+        BinOp::XOr => "xor",
+        BinOp::And => "and",
+        BinOp::Rem => "rem",
+        BinOp::RemUn => "rem.un",
+        BinOp::Shl => "shl",
+        BinOp::Shr => "shr",
+        BinOp::ShrUn => "shr.un",
+        BinOp::DivUn => "div.un",
+        BinOp::Div => "div",
+    }
+}
+fn call(head: &str, pure: IsPure, rest: impl IntoIterator<Item = SExpr>) -> SExpr {
+    if pure.0 {
+        SExpr::tagged(&format!("{head}.pure"), rest)
+    } else {
+        SExpr::tagged(head, rest)
+    }
+}
+fn nodes<'a>(
+    nodes: &'a [Interned<CILNode>],
+    asm: &'a Assembly,
+) -> impl Iterator<Item = SExpr> + 'a {
+    nodes.iter().map(|arg| node(*arg, asm))
+}
+pub(super) fn node(node_idx: Interned<CILNode>, asm: &Assembly) -> SExpr {
+    let n = |idx: &Interned<CILNode>| node(*idx, asm);
+    let t = |idx: &Interned<Type>| ty(asm[*idx], asm);
+    let num = |val: u64| SExpr::atom(val.to_string());
+    match &asm[node_idx] {
+        CILNode::Const(val) => cst(val, asm),
+        CILNode::BinOp(lhs, rhs, op) => SExpr::tagged(bin_op(*op), [n(lhs), n(rhs)]),
+        CILNode::UnOp(val, op) => SExpr::tagged(
+            match op {
+                UnOp::Not => "not",
+                UnOp::Neg => "neg",
+            },
+            [n(val)],
+        ),
+        CILNode::LdLoc(idx) => SExpr::tagged("ldloc", [num((*idx).into())]),
+        CILNode::LdLocA(idx) => SExpr::tagged("ldloca", [num((*idx).into())]),
+        CILNode::LdArg(idx) => SExpr::tagged("ldarg", [num((*idx).into())]),
+        CILNode::LdArgA(idx) => SExpr::tagged("ldarga", [num((*idx).into())]),
+        CILNode::Call(info) => {
+            let (mref, args, pure) = info.as_ref();
+            call(
+                "call",
+                *pure,
+                std::iter::once(method_ref(&asm[*mref], asm)).chain(nodes(args, asm)),
+            )
+        }
+        CILNode::IntCast {
+            input,
+            target,
+            extend,
+        } => SExpr::tagged(
+            "conv",
+            [
+                SExpr::atom(target.name()),
+                SExpr::atom(match extend {
+                    ExtendKind::ZeroExtend => "zext",
+                    ExtendKind::SignExtend => "sext",
+                }),
+                n(input),
+            ],
+        ),
+        CILNode::FloatCast {
+            input,
+            target,
+            is_signed,
+        } => SExpr::tagged(
+            "conv.f",
+            [
+                SExpr::atom(target.name()),
+                SExpr::atom(if *is_signed { "signed" } else { "unsigned" }),
+                n(input),
+            ],
+        ),
+        CILNode::RefToPtr(val) => SExpr::tagged("ref_to_ptr", [n(val)]),
+        CILNode::PtrCast(val, res) => {
+            let res = match res.as_ref() {
+                PtrCastRes::Ptr(inner) => SExpr::tagged("ptr", [t(inner)]),
+                PtrCastRes::Ref(inner) => SExpr::tagged("ref", [t(inner)]),
+                PtrCastRes::FnPtr(fn_sig) => ty(Type::FnPtr(*fn_sig), asm),
+                PtrCastRes::USize => SExpr::atom("usize"),
+                PtrCastRes::ISize => SExpr::atom("isize"),
+            };
+            SExpr::tagged("ptr_cast", [res, n(val)])
+        }
+        CILNode::LdFieldAddress { addr, field: fld } => {
+            SExpr::tagged("ldflda", [field(&asm[*fld], asm), n(addr)])
+        }
+        CILNode::LdField { addr, field: fld } => {
+            SExpr::tagged("ldfld", [field(&asm[*fld], asm), n(addr)])
+        }
+        CILNode::LdInd {
+            addr,
+            tpe,
+            volatile,
+        } => SExpr::tagged(
+            if *volatile { "ldind.volatile" } else { "ldind" },
+            [t(tpe), n(addr)],
+        ),
+        CILNode::SizeOf(tpe) => SExpr::tagged("sizeof", [t(tpe)]),
+        CILNode::GetException => SExpr::list([SExpr::atom("get_exception")]),
+        CILNode::IsInst(val, tpe) => SExpr::tagged("isinst", [t(tpe), n(val)]),
+        CILNode::CheckedCast(val, tpe) => SExpr::tagged("castclass", [t(tpe), n(val)]),
+        CILNode::CallI(info) => {
+            let (ptr, fn_sig, args) = info.as_ref();
+            SExpr::tagged(
+                "calli",
+                [sig(&asm[*fn_sig], asm), n(ptr)]
+                    .into_iter()
+                    .chain(nodes(args, asm)),
+            )
+        }
+        CILNode::LocAlloc { size } => SExpr::tagged("localloc", [n(size)]),
+        CILNode::LdStaticField(sfld) => SExpr::tagged("ldsfld", [static_field(&asm[*sfld], asm)]),
+        CILNode::LdStaticFieldAddress(sfld) => {
+            SExpr::tagged("ldsflda", [static_field(&asm[*sfld], asm)])
+        }
+        CILNode::LdFtn(mref) => SExpr::tagged("ldftn", [method_ref(&asm[*mref], asm)]),
+        CILNode::LdTypeToken(tpe) => SExpr::tagged("ldtoken", [t(tpe)]),
+        CILNode::LdLen(arr) => SExpr::tagged("ldlen", [n(arr)]),
+        CILNode::LocAllocAlgined { tpe, align } => {
+            SExpr::tagged("localloc.aligned", [t(tpe), num(*align)])
+        }
+        CILNode::LdElelemRef { array, index } => SExpr::tagged("ldelema", [n(array), n(index)]),
+        CILNode::UnboxAny { object, tpe } => SExpr::tagged("unbox.any", [t(tpe), n(object)]),
+    }
+}
+fn branch_cond(cond: &BranchCond, asm: &Assembly) -> SExpr {
+    let n = |idx: &Interned<CILNode>| node(*idx, asm);
+    let cmp = |head: &str, lhs, rhs, kind: &CmpKind| {
+        SExpr::tagged(
+            head,
+            [
+                SExpr::atom(match kind {
+                    CmpKind::Ordered => "ordered",
+                    CmpKind::Unordered => "unordered",
+                    CmpKind::Signed => "signed",
+                    CmpKind::Unsigned => "unsigned",
+                }),
+                n(lhs),
+                n(rhs),
+            ],
+        )
+    };
+    match cond {
+        BranchCond::True(val) => SExpr::tagged("true", [n(val)]),
+        BranchCond::False(val) => SExpr::tagged("false", [n(val)]),
+        BranchCond::Eq(lhs, rhs) => SExpr::tagged("eq", [n(lhs), n(rhs)]),
+        BranchCond::Ne(lhs, rhs) => SExpr::tagged("ne", [n(lhs), n(rhs)]),
+        BranchCond::Lt(lhs, rhs, kind) => cmp("lt", lhs, rhs, kind),
+        BranchCond::Gt(lhs, rhs, kind) => cmp("gt", lhs, rhs, kind),
+        BranchCond::Le(lhs, rhs, kind) => cmp("le", lhs, rhs, kind),
+        BranchCond::Ge(lhs, rhs, kind) => cmp("ge", lhs, rhs, kind),
+    }
+}
+pub(super) fn root(root_idx: Interned<CILRoot>, asm: &Assembly) -> SExpr {
+    let n = |idx: &Interned<CILNode>| node(*idx, asm);
+    let num = |val: u64| SExpr::atom(val.to_string());
+    match &asm[root_idx] {
+        CILRoot::StLoc(idx, val) => SExpr::tagged("stloc", [num((*idx).into()), n(val)]),
+        CILRoot::StArg(idx, val) => SExpr::tagged("starg", [num((*idx).into()), n(val)]),
+        CILRoot::Ret(val) => SExpr::tagged("ret", [n(val)]),
+        CILRoot::Pop(val) => SExpr::tagged("pop", [n(val)]),
+        CILRoot::Throw(val) => SExpr::tagged("throw", [n(val)]),
+        CILRoot::VoidRet => SExpr::list([SExpr::atom("ret_void")]),
+        CILRoot::Break => SExpr::list([SExpr::atom("break")]),
+        CILRoot::Nop => SExpr::list([SExpr::atom("nop")]),
+        CILRoot::Branch(info) => {
+            let (target, sub_target, cond) = info.as_ref();
+            let mut res = vec![
+                SExpr::atom("br"),
+                num((*target).into()),
+                num((*sub_target).into()),
+            ];
+            if let Some(cond) = cond {
+                res.push(branch_cond(cond, asm));
+            }
+            SExpr::list(res)
+        }
+        CILRoot::SourceFileInfo {
+            line_start,
+            line_len,
+            col_start,
+            col_len,
+            file,
+        } => SExpr::tagged(
+            "sfi",
+            [
+                SExpr::str(&asm[*file]),
+                num((*line_start).into()),
+                num((*line_len).into()),
+                num((*col_start).into()),
+                num((*col_len).into()),
+            ],
+        ),
+        CILRoot::SetField(info) => {
+            let (fld, addr, val) = info.as_ref();
+            SExpr::tagged("stfld", [field(&asm[*fld], asm), n(addr), n(val)])
+        }
+        CILRoot::Call(info) => {
+            let (mref, args, pure) = info.as_ref();
+            call(
+                "call",
+                *pure,
+                std::iter::once(method_ref(&asm[*mref], asm)).chain(nodes(args, asm)),
+            )
+        }
+        CILRoot::StInd(info) => {
+            let (addr, val, tpe, volatile) = info.as_ref();
+            SExpr::tagged(
+                if *volatile { "stind.volatile" } else { "stind" },
+                [ty(*tpe, asm), n(addr), n(val)],
+            )
+        }
+        CILRoot::InitBlk(info) => {
+            let (dst, val, count) = info.as_ref();
+            SExpr::tagged("initblk", [n(dst), n(val), n(count)])
+        }
+        CILRoot::CpBlk(info) => {
+            let (dst, src, len) = info.as_ref();
+            SExpr::tagged("cpblk", [n(dst), n(src), n(len)])
+        }
+        CILRoot::CallI(info) => {
+            let (ptr, fn_sig, args) = info.as_ref();
+            SExpr::tagged(
+                "calli",
+                [sig(&asm[*fn_sig], asm), n(ptr)]
+                    .into_iter()
+                    .chain(nodes(args, asm)),
+            )
+        }
+        CILRoot::Switch(switch) => {
+            let (value, targets, default) = switch.as_ref();
+            let mut res = vec![
+                SExpr::atom("switch"),
+                n(value),
+                SExpr::tagged("default", [num(default.0.into()), num(default.1.into())]),
+            ];
+            res.extend(targets.iter().map(|(target, sub_target)| {
+                SExpr::list([num((*target).into()), num((*sub_target).into())])
+            }));
+            SExpr::list(res)
+        }
+        CILRoot::ExitSpecialRegion { target, source } => SExpr::tagged(
+            "exit_region",
+            [num((*target).into()), num((*source).into())],
+        ),
+        CILRoot::ReThrow => SExpr::list([SExpr::atom("rethrow")]),
+        CILRoot::SetStaticField { field, val } => {
+            SExpr::tagged("stsfld", [static_field(&asm[*field], asm), n(val)])
+        }
+        CILRoot::CpObj { src, dst, tpe } => {
+            SExpr::tagged("cpobj", [ty(asm[*tpe], asm), n(dst), n(src)])
+        }
+        CILRoot::Unreachable(msg) => SExpr::tagged("unreachable", [SExpr::str(&asm[*msg])]),
+        CILRoot::InitObj(addr, tpe) => SExpr::tagged("initobj", [ty(asm[*tpe], asm), n(addr)]),
+        CILRoot::InlineAsm(info) => inline_asm(info, asm),
+        CILRoot::VaStart(list) => SExpr::tagged("va_start", [n(list)]),
+        CILRoot::VaArg(info) => {
+            let (list, dst, tpe) = info.as_ref();
+            SExpr::tagged("va_arg", [ty(asm[*tpe], asm), n(list), n(dst)])
+        }
+    }
+}
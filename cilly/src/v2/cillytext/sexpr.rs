@@ -0,0 +1,269 @@
+use std::fmt::Write;
+
+use super::ParseError;
+/// Position of an element within the source text, used for error reporting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Pos {
+    pub line: u32,
+    pub col: u32,
+}
+/// A single element of the textual cillyIR format.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SExpr {
+    /// A bare word or number, eg. `ldloc`, `i32` or `-5`.
+    Atom(String, Pos),
+    /// A quoted, escaped string, eg. `"System.Object"`.
+    Str(String, Pos),
+    /// A parenthesized list of elements.
+    List(Vec<SExpr>, Pos),
+}
+impl SExpr {
+    /// Creates a new atom.
+    pub fn atom(atom: impl Into<String>) -> Self {
+        Self::Atom(atom.into(), Pos::default())
+    }
+    /// Creates a new string literal.
+    pub fn str(string: impl Into<String>) -> Self {
+        Self::Str(string.into(), Pos::default())
+    }
+    /// Creates a new list.
+    pub fn list(elems: impl Into<Vec<SExpr>>) -> Self {
+        Self::List(elems.into(), Pos::default())
+    }
+    /// Creates a list starting with the atom `head`.
+    pub fn tagged(head: &str, elems: impl IntoIterator<Item = SExpr>) -> Self {
+        let mut list = vec![Self::atom(head)];
+        list.extend(elems);
+        Self::List(list, Pos::default())
+    }
+    /// The position of this element in the source text.
+    pub fn pos(&self) -> Pos {
+        match self {
+            SExpr::Atom(_, pos) | SExpr::Str(_, pos) | SExpr::List(_, pos) => *pos,
+        }
+    }
+    /// Returns the atom, if this element is an atom.
+    pub fn as_atom(&self) -> Option<&str> {
+        match self {
+            SExpr::Atom(atom, _) => Some(atom),
+            _ => None,
+        }
+    }
+    /// If this is a list starting with an atom, returns that atom and the rest of the list.
+    pub fn as_tagged(&self) -> Option<(&str, &[SExpr])> {
+        match self {
+            SExpr::List(elems, _) => match elems.split_first() {
+                Some((SExpr::Atom(head, _), rest)) => Some((head, rest)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+    fn flat_len(&self) -> usize {
+        match self {
+            SExpr::Atom(atom, _) => atom.len(),
+            SExpr::Str(string, _) => string.len() + 2,
+            SExpr::List(elems, _) => {
+                elems.iter().map(SExpr::flat_len).sum::<usize>() + elems.len() + 1
+            }
+        }
+    }
+    fn write_flat(&self, out: &mut String) {
+        match self {
+            SExpr::Atom(atom, _) => out.push_str(atom),
+            SExpr::Str(string, _) => write!(out, "{string:?}").unwrap(),
+            SExpr::List(elems, _) => {
+                out.push('(');
+                for (idx, elem) in elems.iter().enumerate() {
+                    if idx != 0 {
+                        out.push(' ');
+                    }
+                    elem.write_flat(out);
+                }
+                out.push(')');
+            }
+        }
+    }
+    /// Writes this element, breaking lists which do not fit in `MAX_WIDTH` columns into multiple lines.
+    pub fn write_pretty(&self, out: &mut String, indent: usize) {
+        const MAX_WIDTH: usize = 100;
+        let SExpr::List(elems, _) = self else {
+            self.write_flat(out);
+            return;
+        };
+        if indent + self.flat_len() <= MAX_WIDTH {
+            self.write_flat(out);
+            return;
+        }
+        out.push('(');
+        // Leading atoms and strings stay on the same line as the opening parenthesis.
+        let head_len = elems
+            .iter()
+            .take_while(|elem| !matches!(elem, SExpr::List(..)))
+            .count();
+        for (idx, elem) in elems[..head_len].iter().enumerate() {
+            if idx != 0 {
+                out.push(' ');
+            }
+            elem.write_flat(out);
+        }
+        for elem in &elems[head_len..] {
+            out.push('\n');
+            out.extend(std::iter::repeat_n(' ', indent + 2));
+            elem.write_pretty(out, indent + 2);
+        }
+        out.push(')');
+    }
+}
+/// Reads all the top-level elements in `src`.
+pub fn read_all(src: &str) -> Result<Vec<SExpr>, ParseError> {
+    let mut reader = Reader {
+        chars: src.chars().peekable(),
+        pos: Pos { line: 1, col: 1 },
+    };
+    let mut res = vec![];
+    loop {
+        reader.skip_whitespace();
+        if reader.chars.peek().is_none() {
+            return Ok(res);
+        }
+        res.push(reader.read()?);
+    }
+}
+struct Reader<'src> {
+    chars: std::iter::Peekable<std::str::Chars<'src>>,
+    pos: Pos,
+}
+impl Reader<'_> {
+    fn next(&mut self) -> Option<char> {
+        let char = self.chars.next()?;
+        if char == '\n' {
+            self.pos.line += 1;
+            self.pos.col = 1;
+        } else {
+            self.pos.col += 1;
+        }
+        Some(char)
+    }
+    /// Skips whitespace and `;` line comments.
+    fn skip_whitespace(&mut self) {
+        while let Some(char) = self.chars.peek() {
+            if char.is_whitespace() {
+                self.next();
+            } else if *char == ';' {
+                while self.next().is_some_and(|char| char != '\n') {}
+            } else {
+                break;
+            }
+        }
+    }
+    fn read(&mut self) -> Result<SExpr, ParseError> {
+        let pos = self.pos;
+        match self.chars.peek() {
+            Some('(') => {
+                self.next();
+                let mut elems = vec![];
+                loop {
+                    self.skip_whitespace();
+                    match self.chars.peek() {
+                        Some(')') => {
+                            self.next();
+                            return Ok(SExpr::List(elems, pos));
+                        }
+                        Some(_) => elems.push(self.read()?),
+                        None => return Err(ParseError::new(pos, "unclosed `(`")),
+                    }
+                }
+            }
+            Some(')') => Err(ParseError::new(pos, "unexpected `)`")),
+            Some('"') => {
+                self.next();
+                self.read_str(pos).map(|string| SExpr::Str(string, pos))
+            }
+            Some(_) => {
+                let mut atom = String::new();
+                while let Some(char) = self.chars.peek() {
+                    if char.is_whitespace() || matches!(char, '(' | ')' | '"' | ';') {
+                        break;
+                    }
+                    atom.push(*char);
+                    self.next();
+                }
+                Ok(SExpr::Atom(atom, pos))
+            }
+            None => Err(ParseError::new(pos, "unexpected end of input")),
+        }
+    }
+    /// Reads the rest of a string literal, using the same escapes as Rust's `Debug` impl for `str`.
+    fn read_str(&mut self, start: Pos) -> Result<String, ParseError> {
+        let mut res = String::new();
+        loop {
+            let pos = self.pos;
+            match self.next() {
+                Some('"') => return Ok(res),
+                Some('\\') => match self.next() {
+                    Some('n') => res.push('\n'),
+                    Some('t') => res.push('\t'),
+                    Some('r') => res.push('\r'),
+                    Some('0') => res.push('\0'),
+                    Some(char @ ('\\' | '"' | '\'')) => res.push(char),
+                    Some('u') => {
+                        if self.next() != Some('{') {
+                            return Err(ParseError::new(pos, "expected `{` after `\\u`"));
+                        }
+                        let mut hex = String::new();
+                        loop {
+                            match self.next() {
+                                Some('}') => break,
+                                Some(char) => hex.push(char),
+                                None => {
+                                    return Err(ParseError::new(pos, "unterminated `\\u` escape"))
+                                }
+                            }
+                        }
+                        let char = u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| {
+                                ParseError::new(pos, format!("invalid unicode escape {hex:?}"))
+                            })?;
+                        res.push(char);
+                    }
+                    other => {
+                        return Err(ParseError::new(
+                            pos,
+                            format!("invalid escape sequence `\\{other:?}`"),
+                        ))
+                    }
+                },
+                Some(char) => res.push(char),
+                None => return Err(ParseError::new(start, "unterminated string literal")),
+            }
+        }
+    }
+}
+#[test]
+fn read_nested() {
+    let exprs = read_all("(a (b \"c d\") -1) ; comment\n()").unwrap();
+    assert_eq!(exprs.len(), 2);
+    let (head, rest) = exprs[0].as_tagged().unwrap();
+    assert_eq!(head, "a");
+    assert_eq!(rest.len(), 2);
+    assert!(matches!(&rest[0], SExpr::List(elems, _) if elems.len() == 2));
+    assert_eq!(rest[1].as_atom(), Some("-1"));
+    assert!(matches!(&exprs[1], SExpr::List(elems, _) if elems.is_empty()));
+}
+#[test]
+fn string_escapes() {
+    let original = "a\"b\\c\nd\te\u{7f}\0'";
+    let mut printed = String::new();
+    SExpr::str(original).write_flat(&mut printed);
+    let exprs = read_all(&printed).unwrap();
+    assert!(matches!(&exprs[0], SExpr::Str(string, _) if string == original));
+}
+#[test]
+fn unclosed() {
+    assert!(read_all("(a (b)").is_err());
+    assert!(read_all("a)").is_err());
+    assert!(read_all("\"abc").is_err());
+}
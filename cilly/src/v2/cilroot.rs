@@ -1,11 +1,13 @@
 use serde::{Deserialize, Serialize};
 
 use super::{
-    bimap::Interned, cilnode::IsPure, Assembly, CILNode, FieldDesc, Float, FnSig, Int, MethodRef,
-    StaticFieldDesc, Type,
+    bimap::Interned, cilnode::IsPure, Assembly, CILNode, FieldDesc, Float, FnSig, InlineAsm, Int,
+    MethodRef, StaticFieldDesc, Type,
 };
-use crate::{cil_root::V1Root as V1Root, IString};
+use crate::{cil_root::V1Root, IString};
 //use crate::cil_node::CILNode as V1Node;
+/// value, targets, default of a [`CILRoot::Switch`].
+pub type SwitchData = (Interned<CILNode>, Box<[(u32, u32)]>, (u32, u32));
 #[derive(PartialEq, Hash, Eq, Clone, Debug, Serialize, Deserialize)]
 pub enum CILRoot {
     StLoc(u32, Interned<CILNode>),
@@ -57,6 +59,17 @@ pub enum CILRoot {
     Unreachable(Interned<IString>),
     /// Zero-initializes the value at *address* of *type*.
     InitObj(Interned<CILNode>, Interned<Type>),
+    /// value, targets, default. Jumps to the target at index *value*, or to the default target if *value*(treated as
+    /// unsigned) is out of range. The targets are (target, subtarget) pairs, just like in [`CILRoot::Branch`].
+    Switch(Box<SwitchData>),
+    /// A block of inline assembly. Only supported when exporting C.
+    InlineAsm(Box<InlineAsm>),
+    /// Initializes the C `va_list` at *address*, to point to the first additional argument of the current C-variadic
+    /// method. Only supported when exporting C.
+    VaStart(Interned<CILNode>),
+    /// list, dst, type. Reads the next additional argument of *type* from the C `va_list` at *list*, and writes it to
+    /// *dst*. Only supported when exporting C.
+    VaArg(Box<(Interned<CILNode>, Interned<CILNode>, Interned<Type>)>),
 }
 
 #[derive(Hash, PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
@@ -107,6 +120,14 @@ impl CILRoot {
     pub fn call(mref: Interned<MethodRef>, args: impl Into<Box<[Interned<CILNode>]>>) -> Self {
         Self::Call(Box::new((mref, args.into(), IsPure::NOT)))
     }
+    /// Creates a switch over *value*, jumping to `targets[value]`, or to *default* if value is out of range.
+    pub fn switch(
+        value: Interned<CILNode>,
+        targets: impl Into<Box<[(u32, u32)]>>,
+        default: (u32, u32),
+    ) -> Self {
+        Self::Switch(Box::new((value, targets.into(), default)))
+    }
     /// Checks if this root has any effect on the execution of this program.
     pub fn is_meaningufull(&self) -> bool {
         !matches!(self, CILRoot::Nop | CILRoot::SourceFileInfo { .. })
@@ -121,7 +142,9 @@ impl CILRoot {
             | CILRoot::Pop(tree)
             | CILRoot::Throw(tree)
             | CILRoot::InitObj(tree, _)
+            | CILRoot::VaStart(tree)
             | CILRoot::SetStaticField { val: tree, .. } => [tree].into(),
+            CILRoot::Switch(info) => [&mut info.0].into(),
             CILRoot::SourceFileInfo { .. }
             | CILRoot::ExitSpecialRegion { .. }
             | CILRoot::VoidRet
@@ -161,6 +184,11 @@ impl CILRoot {
                 args.into()
             }
             CILRoot::CpObj { src, dst, .. } => [src, dst].into(),
+            CILRoot::InlineAsm(asm) => asm.nodes_mut().into(),
+            CILRoot::VaArg(info) => {
+                let (list, dst, _) = info.as_mut();
+                [list, dst].into()
+            }
         }
     }
     pub fn nodes(&self) -> Box<[&Interned<CILNode>]> {
@@ -172,7 +200,9 @@ impl CILRoot {
             | CILRoot::Pop(tree)
             | CILRoot::Throw(tree)
             | CILRoot::InitObj(tree, _)
+            | CILRoot::VaStart(tree)
             | CILRoot::SetStaticField { val: tree, .. } => [tree].into(),
+            CILRoot::Switch(info) => [&info.0].into(),
             CILRoot::SourceFileInfo { .. }
             | CILRoot::ExitSpecialRegion { .. }
             | CILRoot::VoidRet
@@ -212,6 +242,11 @@ impl CILRoot {
                 args.into()
             }
             CILRoot::CpObj { src, dst, .. } => [src, dst].into(),
+            CILRoot::InlineAsm(asm) => asm.nodes().into(),
+            CILRoot::VaArg(info) => {
+                let (list, dst, _) = info.as_ref();
+                [list, dst].into()
+            }
         }
     }
     #[allow(clippy::too_many_lines)]
@@ -582,6 +617,32 @@ impl CILRoot {
                 let root = CILRoot::Branch(Box::new((a, b, cond)));
                 root_map(root, asm)
             }
+            CILRoot::Switch(switch) => {
+                let (value, targets, default) = *switch;
+                let value = asm.get_node(value).clone().map(asm, node_map);
+                let root = CILRoot::Switch(Box::new((asm.alloc_node(value), targets, default)));
+                root_map(root, asm)
+            }
+            CILRoot::InlineAsm(mut inline_asm) => {
+                for node in inline_asm.nodes_mut() {
+                    let mapped = asm.get_node(*node).clone().map(asm, node_map);
+                    *node = asm.alloc_node(mapped);
+                }
+                root_map(CILRoot::InlineAsm(inline_asm), asm)
+            }
+            CILRoot::VaStart(list) => {
+                let list = asm.get_node(list).clone().map(asm, node_map);
+                let root = CILRoot::VaStart(asm.alloc_node(list));
+                root_map(root, asm)
+            }
+            CILRoot::VaArg(info) => {
+                let (list, dst, tpe) = *info;
+                let list = asm.get_node(list).clone().map(asm, node_map);
+                let dst = asm.get_node(dst).clone().map(asm, node_map);
+                let root =
+                    CILRoot::VaArg(Box::new((asm.alloc_node(list), asm.alloc_node(dst), tpe)));
+                root_map(root, asm)
+            }
             CILRoot::SetStaticField { field, val } => {
                 let val = asm.get_node(val).clone().map(asm, node_map);
                 let root = CILRoot::SetStaticField {
@@ -90,6 +90,23 @@ impl ClassRef {
     pub fn generics(&self) -> &[Type] {
         &self.generics
     }
+    /// Checks if `self` and `other` refer to the same generic class, and their generic arguments are either assignable,
+    /// or one of them is a generic parameter(e.g. `List<!0>` and `List<int32>`).
+    #[must_use]
+    pub fn is_generic_instance_of(&self, other: &Self, asm: &Assembly) -> bool {
+        !self.generics.is_empty()
+            && self.name == other.name
+            && self.asm == other.asm
+            && self.is_valuetype == other.is_valuetype
+            && self.generics.len() == other.generics.len()
+            && self
+                .generics
+                .iter()
+                .zip(other.generics.iter())
+                .all(|(arg, other)| {
+                    matches!(arg, Type::PlatformGeneric(_, _)) || arg.is_assignable_to(*other, asm)
+                })
+    }
     /// The .NET math class
     pub fn math(asm: &mut Assembly) -> Interned<ClassRef> {
         let name = asm.alloc_string("System.Math");
@@ -317,6 +334,20 @@ impl ClassRef {
         let asm_name = Some(asm.alloc_string("System.Memory"));
         asm.alloc_class_ref(ClassRef::new(name, asm_name, false, [].into()))
     }
+    /// Returns a reference to the type `System.Numerics.BigInteger`
+    #[must_use]
+    pub fn big_integer(asm: &mut Assembly) -> Interned<ClassRef> {
+        let name: Interned<IString> = asm.alloc_string("System.Numerics.BigInteger");
+        let asm_name = Some(asm.alloc_string("System.Runtime.Numerics"));
+        asm.alloc_class_ref(ClassRef::new(name, asm_name, true, [].into()))
+    }
+    /// Returns a reference to the class `System.BitConverter`
+    #[must_use]
+    pub fn bit_converter(asm: &mut Assembly) -> Interned<ClassRef> {
+        let name: Interned<IString> = asm.alloc_string("System.BitConverter");
+        let asm_name = Some(asm.alloc_string("System.Runtime"));
+        asm.alloc_class_ref(ClassRef::new(name, asm_name, false, [].into()))
+    }
     /// Returns a reference to the class `System.MidpointRounding`
     #[must_use]
     pub fn midpoint_rounding(asm: &mut Assembly) -> Interned<ClassRef> {
@@ -995,3 +1026,35 @@ fn class_ref() {
         }
     }
 }
+#[test]
+fn generic_instance() {
+    use crate::{tpe::GenericKind, Int};
+    let mut asm = Assembly::default();
+    let list = asm.alloc_string("System.Collections.Generic.List");
+    let collections = Some(asm.alloc_string("System.Collections"));
+    let int_list = ClassRef::new(list, collections, false, [Type::Int(Int::I32)].into());
+    let open_list = ClassRef::new(
+        list,
+        collections,
+        false,
+        [Type::PlatformGeneric(0, GenericKind::TypeGeneric)].into(),
+    );
+    let bool_list = ClassRef::new(list, collections, false, [Type::Bool].into());
+    assert!(open_list.is_generic_instance_of(&int_list, &asm));
+    assert!(int_list.is_generic_instance_of(&open_list, &asm));
+    assert!(!bool_list.is_generic_instance_of(&int_list, &asm));
+    // `List<int32>.get_Item` returns `!0`, which is `int32` in this instantiation.
+    let int_list = asm.alloc_class_ref(int_list);
+    let sig = asm.sig(
+        [Type::ClassRef(int_list), Type::Int(Int::I32)],
+        Type::PlatformGeneric(0, GenericKind::TypeGeneric),
+    );
+    let get_item = MethodRef::new(
+        int_list,
+        asm.alloc_string("get_Item"),
+        sig,
+        crate::cilnode::MethodKind::Instance,
+        [].into(),
+    );
+    assert_eq!(get_item.output(&asm), Type::Int(Int::I32));
+}
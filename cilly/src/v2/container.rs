@@ -0,0 +1,228 @@
+//! The format of `.bc` / `.cilly` files, and the assemblies stored in rlibs.
+//!
+//! The encoding of the IR changes between builds of the backend, so an assembly can only be decoded by a build of
+//! `cilly` made from the exact same sources. To turn misdecoding into a clear error, serialized assemblies start
+//! with a header:
+//!
+//! | Field          | Size                    | Description                                         |
+//! |----------------|-------------------------|-----------------------------------------------------|
+//! | magic          | 8                       | [`MAGIC`]                                           |
+//! | format version | 4                       | [`FORMAT_VERSION`]                                  |
+//! | build hash     | 8                       | [`build_hash`] of the backend, which wrote the file |
+//! | section count  | 4                       | Number of sections                                  |
+//! | section sizes  | 8 * section count       | Length of each section, in bytes                    |
+//!
+//! The header is followed by the sections, each storing a part of the assembly(see [`SECTIONS`]). All integers are
+//! little-endian.
+use super::Assembly;
+
+/// Magic number, at the start of every container.
+pub const MAGIC: [u8; 8] = *b"CILLYASM";
+/// Version of the container layout. Bump this when changing the header.
+pub const FORMAT_VERSION: u32 = 1;
+/// Names of the sections, in the order they are stored in.
+pub const SECTIONS: [&str; 16] = [
+    "strings",
+    "types",
+    "class_refs",
+    "class_defs",
+    "nodes",
+    "roots",
+    "sigs",
+    "method_refs",
+    "fields",
+    "statics",
+    "method_defs",
+    "sections",
+    "const_data",
+    "global_asm",
+    "link_sections",
+    "used_statics",
+];
+/// Hash of the sources this build of `cilly` was made from.
+#[must_use]
+pub fn build_hash() -> u64 {
+    u64::from_str_radix(env!("CILLY_BUILD_HASH"), 16).expect("Invalid build hash")
+}
+/// The header of a container.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContainerHeader {
+    pub version: u32,
+    pub build_hash: u64,
+    /// The length of every section.
+    pub sections: Vec<u64>,
+}
+impl ContainerHeader {
+    /// The size of the header, in bytes.
+    #[must_use]
+    pub fn size(&self) -> usize {
+        24 + self.sections.len() * 8
+    }
+}
+impl std::fmt::Display for ContainerHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "format version: {}", self.version)?;
+        write!(f, "build hash: {:016x}", self.build_hash)?;
+        if self.build_hash != build_hash() {
+            write!(f, " (this build: {:016x})", build_hash())?;
+        }
+        writeln!(f)?;
+        for (idx, len) in self.sections.iter().enumerate() {
+            let name = SECTIONS.get(idx).copied().unwrap_or("unknown");
+            writeln!(f, "{name}:\t{len} bytes")?;
+        }
+        Ok(())
+    }
+}
+/// An error encountered while decoding a container.
+#[derive(Debug)]
+pub enum ContainerError {
+    /// The file does not start with [`MAGIC`].
+    NoHeader,
+    /// The file ends before the end of the header, or of a section.
+    Truncated,
+    /// The container uses a different layout.
+    UnsupportedVersion(u32),
+    /// The assembly was serialized by a different build of the backend.
+    BuildMismatch(u64),
+    /// The sections don't match [`SECTIONS`].
+    SectionCount(usize),
+    /// A section could not be decoded.
+    Decode(&'static str, postcard::Error),
+}
+impl std::fmt::Display for ContainerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const REBUILD: &str = "Rebuild it(e.g. after `cargo clean`) with the same build of the backend as the linker.";
+        match self {
+            Self::NoHeader => write!(
+                f,
+                "not a cilly assembly, or an assembly produced by a backend predating versioned assemblies. {REBUILD}"
+            ),
+            Self::Truncated => write!(f, "the assembly file is truncated. {REBUILD}"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "the assembly uses container format version {version}, but only version {FORMAT_VERSION} is supported. {REBUILD}"
+            ),
+            Self::BuildMismatch(hash) => write!(
+                f,
+                "the assembly was produced by backend build {hash:016x}, but this is build {:016x}. {REBUILD}",
+                build_hash()
+            ),
+            Self::SectionCount(count) => write!(
+                f,
+                "the assembly has {count} sections, but {} were expected. {REBUILD}",
+                SECTIONS.len()
+            ),
+            Self::Decode(section, err) => write!(
+                f,
+                "could not decode the {section} section of the assembly: {err}. {REBUILD}"
+            ),
+        }
+    }
+}
+impl std::error::Error for ContainerError {}
+/// Serializes `asm`, wrapping it in a container.
+#[must_use]
+pub fn encode(asm: &Assembly) -> Vec<u8> {
+    let sections = asm.to_sections();
+    let mut out = MAGIC.to_vec();
+    out.extend(FORMAT_VERSION.to_le_bytes());
+    out.extend(build_hash().to_le_bytes());
+    out.extend((sections.len() as u32).to_le_bytes());
+    for section in &sections {
+        out.extend((section.len() as u64).to_le_bytes());
+    }
+    for section in sections {
+        out.extend(section);
+    }
+    out
+}
+/// Reads the header of a container, without checking if it is compatible with this build.
+/// # Errors
+/// Returns an error if `bytes` do not start with a valid header.
+pub fn read_header(bytes: &[u8]) -> Result<ContainerHeader, ContainerError> {
+    if bytes.get(..8) != Some(&MAGIC) {
+        return Err(ContainerError::NoHeader);
+    }
+    let u32_at = |offset: usize| {
+        bytes
+            .get(offset..offset + 4)
+            .map(|val| u32::from_le_bytes(val.try_into().unwrap()))
+            .ok_or(ContainerError::Truncated)
+    };
+    let u64_at = |offset: usize| {
+        bytes
+            .get(offset..offset + 8)
+            .map(|val| u64::from_le_bytes(val.try_into().unwrap()))
+            .ok_or(ContainerError::Truncated)
+    };
+    let version = u32_at(8)?;
+    // Later versions may change the rest of the header.
+    if version != FORMAT_VERSION {
+        return Err(ContainerError::UnsupportedVersion(version));
+    }
+    let build_hash = u64_at(12)?;
+    let count = u32_at(20)? as usize;
+    let sections = (0..count)
+        .map(|idx| u64_at(24 + idx * 8))
+        .collect::<Result<_, _>>()?;
+    Ok(ContainerHeader {
+        version,
+        build_hash,
+        sections,
+    })
+}
+/// Decodes an assembly stored in a container, checking that it was produced by this build.
+/// # Errors
+/// Returns an error if the container is malformed, or incompatible with this build.
+pub fn decode(bytes: &[u8]) -> Result<Assembly, ContainerError> {
+    let header = read_header(bytes)?;
+    if header.build_hash != build_hash() {
+        return Err(ContainerError::BuildMismatch(header.build_hash));
+    }
+    if header.sections.len() != SECTIONS.len() {
+        return Err(ContainerError::SectionCount(header.sections.len()));
+    }
+    let mut offset = header.size();
+    let mut sections = vec![];
+    for len in &header.sections {
+        let end = usize::try_from(*len)
+            .ok()
+            .and_then(|len| offset.checked_add(len))
+            .ok_or(ContainerError::Truncated)?;
+        sections.push(bytes.get(offset..end).ok_or(ContainerError::Truncated)?);
+        offset = end;
+    }
+    Assembly::from_sections(&sections)
+        .map_err(|(idx, err)| ContainerError::Decode(SECTIONS[idx], err))
+}
+#[test]
+fn roundtrip() {
+    let mut asm = Assembly::default();
+    let _ = asm.alloc_string("Hello");
+    let encoded = encode(&asm);
+    let header = read_header(&encoded).unwrap();
+    assert_eq!(header.build_hash, build_hash());
+    assert_eq!(header.sections.len(), SECTIONS.len());
+    assert_eq!(
+        header.size() as u64 + header.sections.iter().sum::<u64>(),
+        encoded.len() as u64
+    );
+    let decoded = decode(&encoded).unwrap();
+    assert_eq!(encode(&decoded).len(), encoded.len());
+    // Raw, unversioned assemblies are rejected.
+    assert!(matches!(
+        decode(&postcard::to_stdvec(&asm).unwrap()),
+        Err(ContainerError::NoHeader)
+    ));
+    assert!(matches!(
+        decode(&encoded[..encoded.len() - 1]),
+        Err(ContainerError::Truncated)
+    ));
+    let mut other_build = encoded.clone();
+    other_build[12] ^= 0xFF;
+    assert!(matches!(
+        decode(&other_build),
+        Err(ContainerError::BuildMismatch(_))
+    ));
+}
@@ -5,6 +5,8 @@ use super::Type;
 pub struct FnSig {
     inputs: Box<[Type]>,
     output: Type,
+    /// If set, this is a C-variadic function: any number of additional arguments may be passed after `inputs`.
+    variadic: bool,
 }
 
 impl FnSig {
@@ -13,8 +15,29 @@ impl FnSig {
         Self {
             inputs: input.into(),
             output,
+            variadic: false,
         }
     }
+    /// Creates the signature of a C-variadic function, taking `input` followed by any number of additional arguments.
+    /// ```
+    /// # use cilly::{Type,FnSig,Int};
+    /// let sig = FnSig::new_variadic([Type::Int(Int::I32)],Type::Void);
+    /// assert!(sig.is_variadic());
+    /// assert_eq!(sig.inputs().len(),1);
+    /// ```
+    #[must_use]
+    pub fn new_variadic(input: impl Into<Box<[Type]>>, output: Type) -> Self {
+        Self {
+            inputs: input.into(),
+            output,
+            variadic: true,
+        }
+    }
+    /// Returns true if this function is C-variadic. The additional arguments are not a part of [`Self::inputs`].
+    #[must_use]
+    pub fn is_variadic(&self) -> bool {
+        self.variadic
+    }
 
     #[must_use]
     pub fn inputs(&self) -> &[Type] {
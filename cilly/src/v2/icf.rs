@@ -0,0 +1,250 @@
+//! Identical code folding: merges methods with identical bodies, redirecting all references to a single copy.
+use fxhash::{FxHashMap, FxHashSet};
+
+use super::{
+    asm::{CCTOR, ENTRYPOINT, TCCTOR, USER_FINI, USER_INIT},
+    bimap::Interned,
+    cilnode::MethodKind,
+    class::ClassDefIdx,
+    Assembly, BasicBlock, CILIter, CILIterElem, CILNode, CILRoot, FnSig, MethodDef, MethodDefIdx,
+    MethodImpl, MethodRef,
+};
+/// Statistics of identical code folding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FoldStats {
+    /// The number of methods removed, because they were identical to some other method.
+    pub folded: usize,
+    /// The number of methods, which were identical to some other method, but had to be kept, since they are visible
+    /// outside the assembly, or referenced by global assembly. References to them within the assembly are still
+    /// redirected.
+    pub kept: usize,
+    /// The encoded size of the removed methods.
+    pub bytes_saved: usize,
+}
+impl std::fmt::Display for FoldStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Folded {} identical methods({} kept, since they are externaly visible), saving {} bytes",
+            self.folded, self.kept, self.bytes_saved
+        )
+    }
+}
+/// Methods with equal keys have identical behaviour, and can be merged.
+type FoldKey = (ClassDefIdx, Interned<FnSig>, MethodKind, MethodImpl);
+/// Returns the key methods are compared by, or `None` if `def` can't be folded.
+fn fold_key(def: &MethodDef, asm: &Assembly) -> Option<FoldKey> {
+    if !matches!(def.kind(), MethodKind::Static | MethodKind::Instance)
+        || [CCTOR, TCCTOR, USER_INIT, USER_FINI, ENTRYPOINT].contains(&&asm[def.name()])
+    {
+        return None;
+    }
+    let MethodImpl::MethodBody { blocks, locals } = def.implementation() else {
+        return None;
+    };
+    // Local names don't change the behaviour of a method.
+    let locals = locals.iter().map(|(_, tpe)| (None, *tpe)).collect();
+    Some((
+        def.class(),
+        def.sig(),
+        def.kind(),
+        MethodImpl::MethodBody {
+            blocks: blocks.clone(),
+            locals,
+        },
+    ))
+}
+/// The encoded size of a method, and all of its CIL.
+fn encoded_size(def: &MethodDef, asm: &Assembly) -> usize {
+    fn encoded_len(val: &impl serde::Serialize) -> usize {
+        postcard::to_allocvec(val).map_or(0, |buff| buff.len())
+    }
+    let cil = def
+        .implementation()
+        .blocks()
+        .into_iter()
+        .flatten()
+        .flat_map(BasicBlock::iter_roots)
+        .flat_map(|root| CILIter::new(asm[root].clone(), asm))
+        .map(|elem| match elem {
+            CILIterElem::Node(node) => encoded_len(&node),
+            CILIterElem::Root(root) => encoded_len(&root),
+        })
+        .sum::<usize>();
+    encoded_len(def) + cil
+}
+impl Assembly {
+    /// Merges methods with identical bodies, repeating until no more methods can be merged, since merging methods can
+    /// make their callers identical. All references to a merged method are redirected to the method it was merged
+    /// with, and it is removed, unless it is visible outside this assembly. Global assembly refers to methods by their
+    /// symbol name, so methods it references are kept too.
+    pub fn fold_identical_methods(&mut self) -> FoldStats {
+        let mut stats = FoldStats::default();
+        let mut kept = FxHashSet::default();
+        let global_asm_methods = self.global_asm_methods();
+        let must_keep = |idx: MethodDefIdx, asm: &Self| {
+            asm.method_def(idx).access().is_extern() || global_asm_methods.contains(&idx)
+        };
+        loop {
+            let mut groups: FxHashMap<FoldKey, Vec<MethodDefIdx>> = FxHashMap::default();
+            for (idx, def) in self.method_defs() {
+                if let Some(key) = fold_key(def, self) {
+                    groups.entry(key).or_default().push(*idx);
+                }
+            }
+            let mut redirects: FxHashMap<Interned<MethodRef>, Interned<MethodRef>> =
+                FxHashMap::default();
+            for mut group in groups.into_values().filter(|group| group.len() > 1) {
+                // Prefer keeping a method which can't be removed anyway. Sort by name, to make this deterministic.
+                group.sort_by_cached_key(|idx| {
+                    let name = self[self.method_def(*idx).name()].to_owned();
+                    (!must_keep(*idx, self), name)
+                });
+                let (canonical, duplicates) = group.split_first().unwrap();
+                redirects.extend(duplicates.iter().map(|dup| (**dup, **canonical)));
+            }
+            self.redirect_method_refs(&redirects);
+            let folded = stats.folded;
+            for dup in redirects.keys() {
+                let idx = MethodDefIdx::from_raw(*dup);
+                if must_keep(idx, self) {
+                    kept.insert(idx);
+                    continue;
+                }
+                let def = self.borrow_methoddef(idx);
+                stats.bytes_saved += encoded_size(&def, self);
+                stats.folded += 1;
+                self.class_mut(def.class())
+                    .methods_mut()
+                    .retain(|method| *method != idx);
+            }
+            // Methods which were kept stay identical, so only removing methods can lead to more folding.
+            if stats.folded == folded {
+                stats.kept = kept.len();
+                return stats;
+            }
+        }
+    }
+    /// Replaces all calls to, and uses of addresses of methods in `redirects`, with the method they map to.
+    fn redirect_method_refs(
+        &mut self,
+        redirects: &FxHashMap<Interned<MethodRef>, Interned<MethodRef>>,
+    ) {
+        let redirect = |mref: Interned<MethodRef>| redirects.get(&mref).copied().unwrap_or(mref);
+        let method_def_idxs: Box<[_]> = self.method_defs().keys().copied().collect();
+        for method in method_def_idxs {
+            let mut def = self.borrow_methoddef(method);
+            if let MethodImpl::AliasFor(alias) = def.implementation_mut() {
+                *alias = redirect(*alias);
+            }
+            def.map_roots(
+                self,
+                &mut |root, _| match root {
+                    CILRoot::Call(info) => {
+                        let (mref, args, is_pure) = *info;
+                        CILRoot::Call(Box::new((redirect(mref), args, is_pure)))
+                    }
+                    _ => root,
+                },
+                &mut |node, _| match node {
+                    CILNode::Call(info) => {
+                        let (mref, args, is_pure) = *info;
+                        CILNode::Call(Box::new((redirect(mref), args, is_pure)))
+                    }
+                    CILNode::LdFtn(mref) => CILNode::LdFtn(redirect(mref)),
+                    _ => node,
+                },
+            );
+            self.return_methoddef(method, def);
+        }
+    }
+}
+#[test]
+fn fold() {
+    let src = r#"
+(classdef public class "MainModule"
+  (method public static "double_a" (sig (i32) i32) (args "val")
+    (locals (local i32 "a"))
+    (block 0
+      (stloc 0 (add (ldarg 0) (ldarg 0)))
+      (ret (ldloc 0))))
+  (method public static "double_b" (sig (i32) i32) (args "val")
+    (locals (local i32 "b"))
+    (block 0
+      (stloc 0 (add (ldarg 0) (ldarg 0)))
+      (ret (ldloc 0))))
+  (method extern static "double_c" (sig (i32) i32) (args "val")
+    (locals (local i32))
+    (block 0
+      (stloc 0 (add (ldarg 0) (ldarg 0)))
+      (ret (ldloc 0))))
+  (method public static "quad_a" (sig (i32) i32) (args "val")
+    (locals)
+    (block 0 (ret (call (mref (class "MainModule") "double_a" static (sig (i32) i32)) (call (mref (class "MainModule") "double_a" static (sig (i32) i32)) (ldarg 0))))))
+  (method public static "quad_b" (sig (i32) i32) (args "val")
+    (locals)
+    (block 0 (ret (call (mref (class "MainModule") "double_b" static (sig (i32) i32)) (call (mref (class "MainModule") "double_b" static (sig (i32) i32)) (ldarg 0))))))
+  (method extern static "entry" (sig () i32) (args)
+    (locals)
+    (block 0
+      (ret (add (call (mref (class "MainModule") "quad_a" static (sig (i32) i32)) (const i32 1)) (call (mref (class "MainModule") "quad_b" static (sig (i32) i32)) (const i32 2)))))))
+"#;
+    let mut asm = super::cillytext::parse(src).unwrap();
+    let stats = asm.fold_identical_methods();
+    // `double_a` and `double_b` are merged into `double_c`, since it is visible outside the assembly. This makes
+    // `quad_a` and `quad_b` identical too.
+    assert_eq!(stats.folded, 3, "{stats}");
+    assert_eq!(stats.kept, 0, "{stats}");
+    assert!(stats.bytes_saved > 0);
+    let mut names: Vec<_> = asm
+        .method_defs()
+        .values()
+        .map(|def| asm[def.name()].to_owned())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["double_c", "entry", "quad_a"]);
+    let entry = asm
+        .method_defs()
+        .keys()
+        .copied()
+        .find(|def| &asm[asm[*def].name()] == "entry")
+        .unwrap();
+    let res = super::interpreter::Interpreter::new(&asm).call_method(*entry, vec![]);
+    assert_eq!(res, Ok(super::interpreter::Value::int(super::Int::I32, 12)));
+}
+#[test]
+fn fold_keeps_global_asm_syms() {
+    let src = r#"
+(classdef public class "MainModule"
+  (method public static "handler_a" (sig (i32) i32) (args "val")
+    (locals)
+    (block 0 (ret (mul (ldarg 0) (const i32 3)))))
+  (method public static "handler_b" (sig (i32) i32) (args "val")
+    (locals)
+    (block 0 (ret (mul (ldarg 0) (const i32 3)))))
+  (method extern static "entry" (sig () i32) (args)
+    (locals)
+    (block 0
+      (ret (add (call (mref (class "MainModule") "handler_a" static (sig (i32) i32)) (const i32 1)) (call (mref (class "MainModule") "handler_b" static (sig (i32) i32)) (const i32 2)))))))
+(global_asm ".globl handler_alias\nhandler_alias = " (sym (ldftn (mref (class "MainModule") "handler_b" static (sig (i32) i32)))))
+"#;
+    let mut asm = super::cillytext::parse(src).unwrap();
+    let stats = asm.fold_identical_methods();
+    // `handler_b` sorts after `handler_a`, but it is referenced by name from global assembly, so it is the one kept.
+    assert_eq!(stats.folded, 1, "{stats}");
+    let names: Vec<_> = asm
+        .method_defs()
+        .values()
+        .map(|def| asm[def.name()].to_owned())
+        .collect();
+    assert!(names.contains(&"handler_b".to_owned()), "{names:?}");
+    assert!(!names.contains(&"handler_a".to_owned()), "{names:?}");
+    let entry = asm
+        .method_defs()
+        .keys()
+        .copied()
+        .find(|def| &asm[asm[*def].name()] == "entry")
+        .unwrap();
+    let res = super::interpreter::Interpreter::new(&asm).call_method(*entry, vec![]);
+    assert_eq!(res, Ok(super::interpreter::Value::int(super::Int::I32, 9)));
+}
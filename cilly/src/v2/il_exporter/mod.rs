@@ -15,7 +15,7 @@ use super::{
     method::LocalDef,
     tpe::simd::SIMDElem,
     Assembly, BinOp, CILIter, CILIterElem, CILNode, CILRoot, ClassRef, Const, Exporter, FnSig, Int,
-    MethodDefIdx, Type,
+    MethodDefIdx, MethodRef, Type,
 };
 
 pub struct ILExporter {
@@ -28,7 +28,11 @@ impl ILExporter {
         Self { flavour, is_lib }
     }
 
-    fn export_to_write(&self, asm: &super::Assembly, out: &mut impl Write) -> std::io::Result<()> {
+    pub(crate) fn export_to_write(
+        &self,
+        asm: &super::Assembly,
+        out: &mut impl Write,
+    ) -> std::io::Result<()> {
         let asm_mut = &mut asm.clone();
         writeln!(out, ".assembly _{{}}")?;
         for (const_data, idx) in asm.const_data.1.iter() {
@@ -168,7 +172,7 @@ impl ILExporter {
                     crate::cilnode::MethodKind::Static => "static",
                     crate::cilnode::MethodKind::Instance => "instance",
                     crate::cilnode::MethodKind::Virtual => "virtual instance",
-                    crate::cilnode::MethodKind::Constructor => "rtspecialname specialname",
+                    crate::cilnode::MethodKind::Constructor => "rtspecialname specialname instance",
                 };
                 let pinvoke = if let MethodImpl::Extern {
                     lib,
@@ -186,6 +190,7 @@ impl ILExporter {
                 };
                 let name = &asm[method.name()];
                 let sig = &asm[method.sig()];
+                let vararg = if sig.is_variadic() { "vararg" } else { "" };
                 let ret = type_il(sig.output(), asm);
                 assert_eq!(method.arg_names().len(), sig.inputs().len(), "{name:?}");
                 let inputs = match method.kind() {
@@ -213,7 +218,7 @@ impl ILExporter {
                 };
                 writeln!(
                     out,
-                    ".method {vis} hidebysig {kind} {pinvoke} {ret} '{name}'({inputs}) cil managed {preservesig}{{// Method ID {method_id:?}"
+                    ".method {vis} hidebysig {kind} {pinvoke} {vararg} {ret} '{name}'({inputs}) cil managed {preservesig}{{// Method ID {method_id:?}"
                 )?;
                 debug_assert!(ensure_unqiue.insert(*method_id));
                 let stack_size = match method.resolved_implementation(asm_mut) {
@@ -495,13 +500,14 @@ impl ILExporter {
                 for arg in &call.1 {
                     self.export_node(asm, out, *arg, sig, locals)?;
                 }
-                let mref = &asm[call.0];
+                let mref = asm[call.0].clone();
                 let call_op = match mref.kind() {
                     crate::cilnode::MethodKind::Static => "call",
                     crate::cilnode::MethodKind::Instance => "call instance",
                     crate::cilnode::MethodKind::Virtual => " callvirt instance",
                     crate::cilnode::MethodKind::Constructor => "newobj instance",
                 };
+                let (call_op, extra) = vararg_call(call_op, &call.1, sig, locals, &mref, asm);
                 let sig = &asm[mref.sig()];
                 let output = type_il(sig.output(), asm);
                 let inputs = match mref.kind() {
@@ -521,6 +527,7 @@ impl ILExporter {
                 let inputs: String = inputs
                     .iter()
                     .map(|tpe| non_void_type_il(tpe, asm))
+                    .chain(extra)
                     .intersperse(",".to_owned())
                     .collect();
                 let generic = if mref.generics().is_empty() {
@@ -753,13 +760,22 @@ impl ILExporter {
                 let fn_sig = asm[*fn_sig].clone();
                 let output = type_il(fn_sig.output(), asm);
                 self.export_node(asm, out, *fn_ptr, sig, locals)?;
+                let (call_op, extra) = vararg_sentinel(
+                    "calli",
+                    &fn_sig,
+                    &args[fn_sig.inputs().len()..],
+                    sig,
+                    locals,
+                    asm,
+                );
                 let inputs: String = fn_sig
                     .inputs()
                     .iter()
                     .map(|tpe| non_void_type_il(tpe, asm))
+                    .chain(extra)
                     .intersperse(",".to_owned())
                     .collect();
-                writeln!(out, "calli {output} ({inputs})")
+                writeln!(out, "{call_op} {output} ({inputs})")
             }
             CILNode::LocAlloc { size } => {
                 self.export_node(asm, out, size, sig, locals)?;
@@ -802,9 +818,11 @@ impl ILExporter {
                     crate::cilnode::MethodKind::Virtual => " ldftn instance",
                     crate::cilnode::MethodKind::Constructor => "ldftn instance",
                 };
+                // The calling convention is a part of the signature, and must match the definition.
+                let vararg = if sig.is_variadic() { " vararg" } else { "" };
                 writeln!(
                     out,
-                    "{ldftn_op} {output} {class}::'{name}'({inputs}) //{ftn:?}"
+                    "{ldftn_op}{vararg} {output} {class}::'{name}'({inputs}) //{ftn:?}"
                 )
             }
             CILNode::LdTypeToken(tok) => {
@@ -1033,7 +1051,7 @@ impl ILExporter {
                 for arg in &call.1 {
                     self.export_node(asm, out, *arg, sig, locals)?;
                 }
-                let mref = &asm[call.0];
+                let mref = asm[call.0].clone();
                 let call_op = match mref.kind() {
                     crate::cilnode::MethodKind::Static => "call",
                     crate::cilnode::MethodKind::Instance => "call instance",
@@ -1042,6 +1060,7 @@ impl ILExporter {
                         panic!("A constructor can't be a CIL root")
                     }
                 };
+                let (call_op, extra) = vararg_call(call_op, &call.1, sig, locals, &mref, asm);
                 let sig = &asm[mref.sig()];
                 let output = type_il(sig.output(), asm);
                 let inputs = match mref.kind() {
@@ -1053,6 +1072,7 @@ impl ILExporter {
                 let inputs: String = inputs
                     .iter()
                     .map(|tpe| non_void_type_il(tpe, asm))
+                    .chain(extra)
                     .intersperse(",".to_owned())
                     .collect();
                 let name = &asm[mref.name()];
@@ -1074,6 +1094,12 @@ impl ILExporter {
                 self.export_node(asm, out, addr, sig, locals)?;
                 writeln!(out, "initobj {}", type_il(&asm[tpe], asm))
             }
+            super::CILRoot::InlineAsm(_) => Err(std::io::Error::other(
+                "inline assembly can't be exported to .NET",
+            )),
+            super::CILRoot::VaStart(_) | super::CILRoot::VaArg(_) => Err(std::io::Error::other(
+                "C-variadic methods can't be defined in .NET",
+            )),
             super::CILRoot::StInd(stind) => {
                 self.export_node(asm, out, stind.0, sig, locals)?;
                 self.export_node(asm, out, stind.1, sig, locals)?;
@@ -1151,13 +1177,39 @@ impl ILExporter {
                 let fn_sig = asm[*fn_sig].clone();
                 let output = type_il(fn_sig.output(), asm);
                 self.export_node(asm, out, *fn_ptr, sig, locals)?;
+                let (call_op, extra) = vararg_sentinel(
+                    "calli",
+                    &fn_sig,
+                    &args[fn_sig.inputs().len()..],
+                    sig,
+                    locals,
+                    asm,
+                );
                 let inputs: String = fn_sig
                     .inputs()
                     .iter()
                     .map(|tpe| non_void_type_il(tpe, asm))
+                    .chain(extra)
                     .intersperse(",".to_owned())
                     .collect();
-                writeln!(out, "calli {output} ({inputs})")
+                writeln!(out, "{call_op} {output} ({inputs})")
+            }
+            super::CILRoot::Switch(switch) => {
+                let (value, targets, default) = switch.as_ref();
+                self.export_node(asm, out, *value, sig, locals)?;
+                let targets: String = targets
+                    .iter()
+                    .map(|(target, sub_target)| {
+                        branch_cond_to_name(*target, *sub_target, has_handler, is_handler)
+                    })
+                    .intersperse(",".to_owned())
+                    .collect();
+                writeln!(out, "switch ({targets})")?;
+                writeln!(
+                    out,
+                    "br {}",
+                    branch_cond_to_name(default.0, default.1, has_handler, is_handler)
+                )
             }
             super::CILRoot::ExitSpecialRegion { target, source } => {
                 if is_handler {
@@ -1342,6 +1394,49 @@ pub(crate) fn class_ref(cref: Interned<ClassRef>, asm: &Assembly) -> String {
         format!("{prefix} '{name}{generic_postfix}'{generic_list}")
     }
 }
+/// Like [`vararg_sentinel`], for a direct call to `mref`.
+fn vararg_call(
+    call_op: &'static str,
+    args: &[Interned<CILNode>],
+    sig: Interned<FnSig>,
+    locals: &[LocalDef],
+    mref: &MethodRef,
+    asm: &mut Assembly,
+) -> (String, Vec<String>) {
+    let called_sig = asm[mref.sig()].clone();
+    let fixed = called_sig.inputs().len().min(args.len());
+    vararg_sentinel(call_op, &called_sig, &args[fixed..], sig, locals, asm)
+}
+/// Returns the call opcode, and the types of the additional arguments passed to a C-variadic method: the sentinel
+/// (`...`), followed by the types of the arguments.
+fn vararg_sentinel(
+    call_op: &'static str,
+    called_sig: &FnSig,
+    extra: &[Interned<CILNode>],
+    sig: Interned<FnSig>,
+    locals: &[LocalDef],
+    asm: &mut Assembly,
+) -> (String, Vec<String>) {
+    if !called_sig.is_variadic() {
+        return (call_op.to_owned(), vec![]);
+    }
+    let extra: Vec<String> = extra
+        .iter()
+        .map(|arg| {
+            let tpe = asm[*arg]
+                .clone()
+                .typecheck(sig, locals, asm)
+                .expect("Could not typecheck an additional argument of a vararg call");
+            non_void_type_il(&tpe, asm)
+        })
+        .collect();
+    let sentinel = if extra.is_empty() {
+        vec![]
+    } else {
+        std::iter::once("...".to_owned()).chain(extra).collect()
+    };
+    (format!("{call_op} vararg"), sentinel)
+}
 fn non_void_type_il(tpe: &Type, asm: &Assembly) -> String {
     match tpe {
         Type::Void => "valuetype RustVoid".into(),
@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+
+use super::{bimap::Interned, Assembly, CILNode, Type};
+use crate::IString;
+/// A piece of an assembly template, using the syntax of GCC's extended assembly.
+#[derive(Hash, PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub enum AsmPiece {
+    /// Raw assembly text. Operands are referred to by their GCC index(`%0`, `%k1`), so a literal `%` has to be
+    /// written as `%%`.
+    Str(Interned<IString>),
+    /// The symbol name of a method or static, given as the node taking its address(`LdFtn` or
+    /// `LdStaticFieldAddress`). This keeps the symbol alive, and lets the exporter spell its name.
+    Sym(Interned<CILNode>),
+}
+/// An output operand of an [`InlineAsm`] block. Outputs come first, so the GCC index of an output is its position.
+#[derive(Hash, PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct AsmOutput {
+    /// The constraint of this operand, including the `=`(write-only) or `+`(read-write) prefix, and the `&`
+    /// early-clobber marker.
+    pub constraint: Interned<IString>,
+    /// The explicit register this operand has to be placed in, if any.
+    pub reg: Option<Interned<IString>>,
+    /// The type of this operand.
+    pub tpe: Interned<Type>,
+    /// The initial value of a read-write operand.
+    pub init: Option<Interned<CILNode>>,
+    /// The address the value of this operand is written to, after the block runs. If `None`, the value is discarded.
+    pub dst: Option<Interned<CILNode>>,
+}
+/// An input operand of an [`InlineAsm`] block. The GCC index of an input is its position, plus the number of outputs.
+#[derive(Hash, PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct AsmInput {
+    /// The constraint of this operand.
+    pub constraint: Interned<IString>,
+    /// The explicit register this operand has to be placed in, if any.
+    pub reg: Option<Interned<IString>>,
+    /// The type of this operand.
+    pub tpe: Interned<Type>,
+    /// The value of this operand.
+    pub val: Interned<CILNode>,
+}
+/// A block of inline assembly, in the form of GCC's extended assembly. Only the C exporter supports it.
+#[derive(Hash, PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct InlineAsm {
+    pub template: Box<[AsmPiece]>,
+    pub outputs: Box<[AsmOutput]>,
+    pub inputs: Box<[AsmInput]>,
+    /// Registers and other resources(`"memory"`, `"cc"`) this block clobbers.
+    pub clobbers: Box<[Interned<IString>]>,
+    /// If set, this block has side effects, and can't be removed, or moved around.
+    pub is_volatile: bool,
+}
+impl InlineAsm {
+    /// Returns all the nodes used by this block, in the order they are evaluated: the initial values of the outputs,
+    /// the inputs, the output addresses, and finally the symbols referenced by the template.
+    pub fn nodes(&self) -> Vec<&Interned<CILNode>> {
+        self.outputs
+            .iter()
+            .filter_map(|output| output.init.as_ref())
+            .chain(self.inputs.iter().map(|input| &input.val))
+            .chain(self.outputs.iter().filter_map(|output| output.dst.as_ref()))
+            .chain(self.template.iter().filter_map(|piece| match piece {
+                AsmPiece::Str(_) => None,
+                AsmPiece::Sym(sym) => Some(sym),
+            }))
+            .collect()
+    }
+    /// Returns mutable references to all the nodes used by this block, in the same order as [`Self::nodes`].
+    pub fn nodes_mut(&mut self) -> Vec<&mut Interned<CILNode>> {
+        let (inits, dsts): (Vec<_>, Vec<_>) = self
+            .outputs
+            .iter_mut()
+            .map(|output| (output.init.as_mut(), output.dst.as_mut()))
+            .unzip();
+        inits
+            .into_iter()
+            .flatten()
+            .chain(self.inputs.iter_mut().map(|input| &mut input.val))
+            .chain(dsts.into_iter().flatten())
+            .chain(self.template.iter_mut().filter_map(|piece| match piece {
+                AsmPiece::Str(_) => None,
+                AsmPiece::Sym(sym) => Some(sym),
+            }))
+            .collect()
+    }
+    /// Returns all the types of the operands of this block.
+    pub fn types<'a>(&'a self, asm: &'a Assembly) -> impl Iterator<Item = Type> + 'a {
+        self.outputs
+            .iter()
+            .map(|output| asm[output.tpe])
+            .chain(self.inputs.iter().map(|input| asm[input.tpe]))
+    }
+}
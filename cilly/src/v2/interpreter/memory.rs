@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+
+use super::InterpreterError;
+/// Describes where an allocation came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AllocKind {
+    /// Allocated by `malloc` and friends.
+    Heap,
+    /// A local, argument or `localloc` buffer. Freed when the function returns.
+    Stack,
+    /// A static field.
+    Static,
+    /// Constant data, eg. the contents of a byte buffer.
+    Const,
+    /// The fields of a managed object.
+    Managed,
+}
+struct Allocation {
+    data: Box<[u8]>,
+    kind: AllocKind,
+}
+/// The memory of the interpreted program. Every allocation gets a fresh address, and addresses are never reused,
+/// so out-of-bounds accesses and use-after-frees are always detected.
+pub struct Memory {
+    allocs: BTreeMap<u64, Allocation>,
+    next_addr: u64,
+}
+impl Default for Memory {
+    fn default() -> Self {
+        Self {
+            allocs: BTreeMap::new(),
+            // Keep the first page unmapped, so that null pointer dereferences fail.
+            next_addr: 0x1000,
+        }
+    }
+}
+impl Memory {
+    /// Allocates `size` zeroed bytes, aligned to `align`.
+    pub fn alloc(&mut self, size: u64, align: u64, kind: AllocKind) -> u64 {
+        self.alloc_with(vec![0; size as usize].into(), align, kind)
+    }
+    /// Allocates memory initialized with `data`, aligned to `align`.
+    pub fn alloc_with(&mut self, data: Box<[u8]>, align: u64, kind: AllocKind) -> u64 {
+        let addr = self.next_addr.next_multiple_of(align.max(1));
+        // Leave a gap between allocations, so that one-past-the-end pointers never point into another allocation.
+        self.next_addr = addr + data.len() as u64 + 1;
+        self.allocs.insert(addr, Allocation { data, kind });
+        addr
+    }
+    /// Frees the allocation starting at `addr`, checking that it is of kind `kind`.
+    pub fn free(&mut self, addr: u64, kind: AllocKind) -> Result<(), InterpreterError> {
+        match self.allocs.get(&addr) {
+            Some(alloc) if alloc.kind == kind => {
+                self.allocs.remove(&addr);
+                Ok(())
+            }
+            _ => Err(InterpreterError::InvalidFree(addr)),
+        }
+    }
+    /// Returns the size of the allocation starting at `addr`.
+    pub fn alloc_size(&self, addr: u64) -> Option<u64> {
+        self.allocs.get(&addr).map(|alloc| alloc.data.len() as u64)
+    }
+    fn find(&self, addr: u64, len: u64) -> Result<(u64, &Allocation), InterpreterError> {
+        let err = InterpreterError::InvalidAccess { addr, len };
+        let (base, alloc) = self.allocs.range(..=addr).next_back().ok_or(err.clone())?;
+        if addr + len > base + alloc.data.len() as u64 {
+            return Err(err);
+        }
+        Ok((*base, alloc))
+    }
+    /// Reads `len` bytes at `addr`.
+    pub fn read(&self, addr: u64, len: u64) -> Result<&[u8], InterpreterError> {
+        if len == 0 {
+            return Ok(&[]);
+        }
+        let (base, alloc) = self.find(addr, len)?;
+        let start = (addr - base) as usize;
+        Ok(&alloc.data[start..start + len as usize])
+    }
+    /// Writes `data` at `addr`.
+    pub fn write(&mut self, addr: u64, data: &[u8]) -> Result<(), InterpreterError> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let (base, alloc) = self.find(addr, data.len() as u64)?;
+        if alloc.kind == AllocKind::Const {
+            return Err(InterpreterError::InvalidAccess {
+                addr,
+                len: data.len() as u64,
+            });
+        }
+        let start = (addr - base) as usize;
+        self.allocs.get_mut(&base).unwrap().data[start..start + data.len()].copy_from_slice(data);
+        Ok(())
+    }
+    /// Copies `len` bytes from `src` to `dst`. The regions may overlap.
+    pub fn copy(&mut self, dst: u64, src: u64, len: u64) -> Result<(), InterpreterError> {
+        let data = self.read(src, len)?.to_vec();
+        self.write(dst, &data)
+    }
+    /// Sets `len` bytes at `dst` to `val`.
+    pub fn fill(&mut self, dst: u64, val: u8, len: u64) -> Result<(), InterpreterError> {
+        self.write(dst, &vec![val; len as usize])
+    }
+    /// Reads a nul-terminated string starting at `addr`, without the terminator.
+    pub fn read_cstr(&self, addr: u64) -> Result<&[u8], InterpreterError> {
+        let (base, alloc) = self.find(addr, 1)?;
+        let tail = &alloc.data[(addr - base) as usize..];
+        match tail.iter().position(|byte| *byte == 0) {
+            Some(len) => Ok(&tail[..len]),
+            None => Err(InterpreterError::InvalidAccess {
+                addr,
+                len: tail.len() as u64 + 1,
+            }),
+        }
+    }
+}
+#[test]
+fn bounds() {
+    let mut mem = Memory::default();
+    let a = mem.alloc(4, 8, AllocKind::Heap);
+    let b = mem.alloc(4, 8, AllocKind::Heap);
+    mem.write(a, &[1, 2, 3, 4]).unwrap();
+    assert_eq!(mem.read(a + 1, 2).unwrap(), &[2, 3]);
+    // Out of bounds, even though `b` is nearby.
+    assert!(mem.read(a + 2, 4).is_err());
+    assert!(mem.read(0, 1).is_err());
+    mem.free(a, AllocKind::Heap).unwrap();
+    assert!(mem.read(a, 1).is_err());
+    assert!(mem.free(a, AllocKind::Heap).is_err());
+    assert!(mem.free(b + 1, AllocKind::Heap).is_err());
+    assert!(mem.free(b, AllocKind::Stack).is_err());
+}
@@ -0,0 +1,1242 @@
+//! An interpreter, capable of running linked assemblies without .NET or a C compiler.
+//!
+//! All locals, arguments, statics and objects live in a single, checked [`Memory`], so any out-of-bounds access or
+//! use-after-free is reported as an [`InterpreterError`], instead of silently corrupting the state of the program.
+//! Calls to external functions are handled by a small set of built-in shims(`malloc`, `memcpy`, `write`,
+//! `System.Console.WriteLine`, `System.Numerics.BigInteger`, ...). Output of the program is captured, and can be
+//! inspected after it finishes.
+//! ```
+//! # use cilly::*;
+//! # use cilly::interpreter::{Interpreter, Value};
+//! let asm = cilly::cillytext::parse(r#"
+//! (classdef public class "MainModule"
+//!   (method public static "square" (sig (i32) i32) (args "x")
+//!     (locals)
+//!     (block 0 (ret (mul (ldarg 0) (ldarg 0))))))
+//! "#).unwrap();
+//! let square = asm.find_methods_matching("square").unwrap().next().unwrap();
+//! let mut interpreter = Interpreter::new(&asm);
+//! let res = interpreter.call_method(*square, vec![Value::int(Int::I32, 7)]);
+//! assert_eq!(res, Ok(Value::int(Int::I32, 49)));
+//! ```
+use fxhash::FxHashMap;
+
+use super::{
+    asm::{CCTOR, ENTRYPOINT, MAIN_MODULE},
+    bimap::Interned,
+    cilnode::{ExtendKind, MethodKind, PtrCastRes, UnOp},
+    cilroot::{BranchCond, CmpKind},
+    Assembly, BasicBlock, BinOp, CILNode, CILRoot, ClassRef, Const, Float, Int, MethodImpl,
+    MethodRef, StaticFieldDesc, Type,
+};
+use crate::IString;
+
+mod memory;
+mod shims;
+mod value;
+pub use memory::{AllocKind, Memory};
+pub use value::Value;
+
+/// An error, which stopped the execution of the interpreted program.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InterpreterError {
+    /// The program called `exit` with this code.
+    Exit(i32),
+    /// The program called `abort`.
+    Abort,
+    /// An exception was thrown, and not caught.
+    Exception(Value),
+    /// The program executed an unreachable root.
+    Unreachable(String),
+    /// The program accessed memory outside of any live allocation.
+    InvalidAccess { addr: u64, len: u64 },
+    /// The program tried to free memory which was not allocated, or was already freed.
+    InvalidFree(u64),
+    /// The program called a method which has no implementation, and no shim.
+    MissingMethod(String),
+    /// The program used a feature the interpreter does not support.
+    Unsupported(String),
+    /// A value of an unexpected type was encountered.
+    TypeMismatch(String),
+    /// Integer division by zero.
+    DivideByZero,
+    /// The program executed more roots than it was allowed to.
+    OutOfFuel,
+    /// The program exceeded the maximum call depth.
+    StackOverflow,
+}
+impl std::fmt::Display for InterpreterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Exit(code) => write!(f, "the program exited with code {code}"),
+            Self::Abort => write!(f, "the program aborted"),
+            Self::Exception(exception) => write!(f, "uncaught exception {exception:?}"),
+            Self::Unreachable(msg) => write!(f, "reached unreachable code: {msg}"),
+            Self::InvalidAccess { addr, len } => {
+                write!(f, "invalid access of {len} bytes at {addr:#x}")
+            }
+            Self::InvalidFree(addr) => write!(f, "invalid free of {addr:#x}"),
+            Self::MissingMethod(name) => write!(f, "missing method {name}"),
+            Self::Unsupported(what) => write!(f, "unsupported: {what}"),
+            Self::TypeMismatch(msg) => write!(f, "type mismatch: {msg}"),
+            Self::DivideByZero => write!(f, "division by zero"),
+            Self::OutOfFuel => write!(f, "the program ran out of fuel"),
+            Self::StackOverflow => write!(f, "stack overflow"),
+        }
+    }
+}
+impl std::error::Error for InterpreterError {}
+type IResult<T> = Result<T, InterpreterError>;
+/// Function pointers are represented by indices into a table, offset by this value.
+const FN_PTR_BASE: u64 = 0x7FFF_0000_0000_0000;
+/// Maximum depth of interpreted calls.
+const MAX_DEPTH: usize = 512;
+/// The default amount of roots a program may execute.
+const DEFAULT_FUEL: u64 = 1 << 32;
+/// The name of the .NET arbitrary-precision integer type.
+const BIG_INTEGER: &str = "System.Numerics.BigInteger";
+/// A managed object.
+enum Object {
+    Str(String),
+    /// A `System.Numerics.BigInteger`. Those are immutable value types, so they are stored like strings, and passed
+    /// around by handle.
+    BigInt(num_bigint::BigInt),
+    Instance {
+        class: Interned<ClassRef>,
+        addr: u64,
+    },
+}
+/// Memory layout of a class.
+struct ClassLayout {
+    size: u64,
+    align: u64,
+    fields: FxHashMap<Interned<IString>, u64>,
+}
+/// Where the execution should continue after a root.
+enum Next {
+    /// Jump to a block in the method body.
+    Block(u32),
+    /// Jump to a block within the current handler.
+    HandlerBlock(u32),
+    Ret(Value),
+}
+struct Frame {
+    args: Vec<(u64, Type)>,
+    locals: Vec<(u64, Type)>,
+    /// Exception currently being handled.
+    exception: Option<Value>,
+    /// All the allocations of this frame, freed on return.
+    allocs: Vec<u64>,
+}
+/// Interprets cilly IR.
+pub struct Interpreter<'asm> {
+    asm: &'asm Assembly,
+    memory: Memory,
+    objects: Vec<Object>,
+    strings: FxHashMap<Interned<IString>, u64>,
+    statics: FxHashMap<Interned<StaticFieldDesc>, u64>,
+    const_data: FxHashMap<Interned<Box<[u8]>>, u64>,
+    layouts: FxHashMap<Interned<ClassRef>, std::rc::Rc<ClassLayout>>,
+    fn_ptrs: Vec<Interned<MethodRef>>,
+    fuel: u64,
+    depth: usize,
+    location: Option<(Interned<IString>, u32)>,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+}
+impl<'asm> Interpreter<'asm> {
+    #[must_use]
+    pub fn new(asm: &'asm Assembly) -> Self {
+        Self {
+            asm,
+            memory: Memory::default(),
+            objects: vec![],
+            strings: FxHashMap::default(),
+            statics: FxHashMap::default(),
+            const_data: FxHashMap::default(),
+            layouts: FxHashMap::default(),
+            fn_ptrs: vec![],
+            fuel: DEFAULT_FUEL,
+            depth: 0,
+            location: None,
+            stdout: vec![],
+            stderr: vec![],
+        }
+    }
+    /// Sets the maximum number of roots the program may execute.
+    pub fn set_fuel(&mut self, fuel: u64) {
+        self.fuel = fuel;
+    }
+    /// Everything the program wrote to the standard output.
+    #[must_use]
+    pub fn stdout(&self) -> &[u8] {
+        &self.stdout
+    }
+    /// Everything the program wrote to the standard error.
+    #[must_use]
+    pub fn stderr(&self) -> &[u8] {
+        &self.stderr
+    }
+    #[must_use]
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+    pub fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+    /// The file and line of the last source file info root executed. Useful for finding where an error occured.
+    #[must_use]
+    pub fn location(&self) -> Option<(&str, u32)> {
+        self.location.map(|(file, line)| (&self.asm[file], line))
+    }
+    /// Returns the contents of the string object `handle`, if it is a string.
+    #[must_use]
+    pub fn string(&self, handle: u64) -> Option<&str> {
+        match self.object(handle).ok()? {
+            Object::Str(string) => Some(string),
+            Object::BigInt(_) | Object::Instance { .. } => None,
+        }
+    }
+    /// Creates a new managed string object.
+    pub fn alloc_string(&mut self, string: impl Into<String>) -> Value {
+        self.objects.push(Object::Str(string.into()));
+        Value::Obj(self.objects.len() as u64)
+    }
+    fn object(&self, handle: u64) -> IResult<&Object> {
+        handle
+            .checked_sub(1)
+            .and_then(|idx| self.objects.get(idx as usize))
+            .ok_or(InterpreterError::InvalidAccess {
+                addr: handle,
+                len: 0,
+            })
+    }
+    fn find_main_module_method(&self, name: &str) -> Option<Interned<MethodRef>> {
+        self.asm.method_defs().iter().find_map(|(idx, def)| {
+            (&self.asm[def.name()] == name
+                && &self.asm[self.asm[def.class()].name()] == MAIN_MODULE)
+                .then_some(**idx)
+        })
+    }
+    /// Runs the static constructor and the entrypoint of this assembly, returning the exit code of the program.
+    pub fn run_entrypoint(&mut self) -> IResult<i32> {
+        let entrypoint = self
+            .find_main_module_method(ENTRYPOINT)
+            .ok_or_else(|| InterpreterError::MissingMethod(ENTRYPOINT.into()))?;
+        let res = (|| {
+            if let Some(cctor) = self.find_main_module_method(CCTOR) {
+                self.call_method(cctor, vec![])?;
+            }
+            // The entrypoint receives the command line arguments as a managed array, which are not supported.
+            let args = self.asm[self.asm[entrypoint].sig()]
+                .inputs()
+                .iter()
+                .map(|_| Value::Obj(0))
+                .collect();
+            self.call_method(entrypoint, args)
+        })();
+        match res {
+            Ok(_) => Ok(0),
+            Err(InterpreterError::Exit(code)) => Ok(code),
+            Err(err) => Err(err),
+        }
+    }
+    /// Calls a method, returning its result. Constructors allocate and return a new object.
+    pub fn call_method(
+        &mut self,
+        mref: Interned<MethodRef>,
+        mut args: Vec<Value>,
+    ) -> IResult<Value> {
+        let method = &self.asm[mref];
+        if method.kind() != MethodKind::Constructor {
+            return self.invoke(mref, args);
+        }
+        let class = method.class();
+        if self.asm[class].is_valuetype() {
+            return Err(InterpreterError::Unsupported(
+                "constructing a value type".into(),
+            ));
+        }
+        let layout = self.layout(class)?;
+        let addr = self
+            .memory
+            .alloc(layout.size, layout.align, AllocKind::Managed);
+        self.objects.push(Object::Instance { class, addr });
+        let obj = Value::Obj(self.objects.len() as u64);
+        args.insert(0, obj.clone());
+        self.invoke(mref, args)?;
+        Ok(obj)
+    }
+    /// Finds the override of a virtual method for the runtime type of `this`.
+    fn resolve_virtual(&self, mref: Interned<MethodRef>, this: &Value) -> Interned<MethodRef> {
+        let Value::Obj(handle) = this else {
+            return mref;
+        };
+        let Ok(Object::Instance { class, .. }) = self.object(*handle) else {
+            return mref;
+        };
+        let method = &self.asm[mref];
+        let mut class = Some(*class);
+        while let Some(curr) = class {
+            let candidate = MethodRef::new(
+                curr,
+                method.name(),
+                method.sig(),
+                method.kind(),
+                method.generics().into(),
+            );
+            if let Some(candidate) = self.asm.get_prealllocated_methodref(candidate) {
+                if self.asm.method_ref_to_def(candidate).is_some() {
+                    return candidate;
+                }
+            }
+            class = self
+                .asm
+                .class_ref_to_def(curr)
+                .and_then(|def| self.asm[def].extends());
+        }
+        mref
+    }
+    fn invoke(&mut self, mref: Interned<MethodRef>, args: Vec<Value>) -> IResult<Value> {
+        let asm = self.asm;
+        let mref = match (asm[mref].kind(), args.first()) {
+            (MethodKind::Virtual, Some(this)) => self.resolve_virtual(mref, this),
+            _ => mref,
+        };
+        let method = &asm[mref];
+        let name = &asm[method.name()];
+        let output = *asm[method.sig()].output();
+        let Some(def) = asm.method_ref_to_def(mref) else {
+            let class = &asm[asm[method.class()].name()];
+            return self
+                .dotnet_shim(class, name, &args, output)?
+                .ok_or_else(|| InterpreterError::MissingMethod(format!("{class}::{name}")));
+        };
+        let def = &asm[def];
+        match def.implementation() {
+            MethodImpl::MethodBody { blocks, locals } => {
+                if self.depth >= MAX_DEPTH {
+                    return Err(InterpreterError::StackOverflow);
+                }
+                let inputs = asm[def.sig()].inputs();
+                if inputs.len() != args.len() {
+                    return Err(InterpreterError::TypeMismatch(format!(
+                        "{name} expects {} arguments, got {}",
+                        inputs.len(),
+                        args.len()
+                    )));
+                }
+                let mut frame = Frame {
+                    args: vec![],
+                    locals: vec![],
+                    exception: None,
+                    allocs: vec![],
+                };
+                self.depth += 1;
+                let res = (|| {
+                    for (arg, tpe) in args.into_iter().zip(inputs) {
+                        let addr = self.alloc_slot(*tpe, &mut frame)?;
+                        self.write_value(addr, *tpe, &arg)?;
+                        frame.args.push((addr, *tpe));
+                    }
+                    for (_, tpe) in locals {
+                        let tpe = asm[*tpe];
+                        let addr = self.alloc_slot(tpe, &mut frame)?;
+                        frame.locals.push((addr, tpe));
+                    }
+                    self.exec_blocks(blocks, &mut frame)
+                })();
+                self.depth -= 1;
+                for alloc in frame.allocs {
+                    self.memory.free(alloc, AllocKind::Stack)?;
+                }
+                res
+            }
+            MethodImpl::Extern { .. } => self
+                .libc_shim(name, &args, output)?
+                .ok_or_else(|| InterpreterError::MissingMethod(name.into())),
+            MethodImpl::AliasFor(alias) => self.invoke(*alias, args),
+            MethodImpl::Missing => Err(InterpreterError::MissingMethod(name.into())),
+        }
+    }
+    fn alloc_slot(&mut self, tpe: Type, frame: &mut Frame) -> IResult<u64> {
+        let (size, align) = self.size_align(tpe)?;
+        let addr = self.memory.alloc(size, align, AllocKind::Stack);
+        frame.allocs.push(addr);
+        Ok(addr)
+    }
+    fn exec_blocks(&mut self, blocks: &[BasicBlock], frame: &mut Frame) -> IResult<Value> {
+        let Some(mut block) = blocks.first() else {
+            return Err(InterpreterError::TypeMismatch(
+                "method body has no blocks".into(),
+            ));
+        };
+        loop {
+            let exception = match self.exec_roots(block.roots(), frame) {
+                Ok(Next::Block(id) | Next::HandlerBlock(id)) => {
+                    block = find_block(blocks, id)?;
+                    continue;
+                }
+                Ok(Next::Ret(val)) => return Ok(val),
+                Err(InterpreterError::Exception(exception)) => exception,
+                Err(err) => return Err(err),
+            };
+            let Some(handler) = block.handler() else {
+                return Err(InterpreterError::Exception(exception));
+            };
+            // The handler starts at its first block, regardless of its id.
+            let mut handler_block = handler
+                .first()
+                .ok_or_else(|| InterpreterError::TypeMismatch("empty exception handler".into()))?;
+            frame.exception = Some(exception);
+            loop {
+                match self.exec_roots(handler_block.roots(), frame)? {
+                    Next::Block(id) => {
+                        block = find_block(blocks, id)?;
+                        break;
+                    }
+                    Next::HandlerBlock(id) => handler_block = find_block(handler, id)?,
+                    Next::Ret(val) => return Ok(val),
+                }
+            }
+            frame.exception = None;
+        }
+    }
+    fn exec_roots(&mut self, roots: &[Interned<CILRoot>], frame: &mut Frame) -> IResult<Next> {
+        for root in roots {
+            self.fuel = self
+                .fuel
+                .checked_sub(1)
+                .ok_or(InterpreterError::OutOfFuel)?;
+            if let Some(next) = self.exec_root(*root, frame)? {
+                return Ok(next);
+            }
+        }
+        Err(InterpreterError::TypeMismatch(
+            "execution fell off the end of a block".into(),
+        ))
+    }
+    /// Resolves the target of a jump, with the same semantics as [`CILRoot::Branch`].
+    fn jump(target: u32, sub_target: u32, frame: &Frame) -> Next {
+        if sub_target == 0 {
+            Next::Block(target)
+        } else if frame.exception.is_some() {
+            Next::HandlerBlock(sub_target)
+        } else {
+            Next::Block(sub_target)
+        }
+    }
+    fn exec_root(&mut self, root: Interned<CILRoot>, frame: &mut Frame) -> IResult<Option<Next>> {
+        let asm = self.asm;
+        match &asm[root] {
+            CILRoot::StLoc(idx, val) | CILRoot::StArg(idx, val) => {
+                let slots = if matches!(asm[root], CILRoot::StLoc(..)) {
+                    &frame.locals
+                } else {
+                    &frame.args
+                };
+                let (addr, tpe) = *slots.get(*idx as usize).ok_or_else(|| {
+                    InterpreterError::TypeMismatch(format!("no local or argument {idx}"))
+                })?;
+                let val = self.eval(*val, frame)?;
+                self.write_value(addr, tpe, &val)?;
+            }
+            CILRoot::Ret(val) => return Ok(Some(Next::Ret(self.eval(*val, frame)?))),
+            CILRoot::Pop(val) => {
+                self.eval(*val, frame)?;
+            }
+            CILRoot::Throw(val) => {
+                return Err(InterpreterError::Exception(self.eval(*val, frame)?))
+            }
+            CILRoot::VoidRet => return Ok(Some(Next::Ret(Value::Void))),
+            CILRoot::Break | CILRoot::Nop => (),
+            CILRoot::Branch(info) => {
+                let (target, sub_target, cond) = info.as_ref();
+                if let Some(cond) = cond {
+                    if !self.branch_cond(cond, frame)? {
+                        return Ok(None);
+                    }
+                }
+                return Ok(Some(Self::jump(*target, *sub_target, frame)));
+            }
+            CILRoot::Switch(switch) => {
+                let (value, targets, default) = switch.as_ref();
+                let value = self.eval(*value, frame)?.as_i128()?;
+                let (target, sub_target) = usize::try_from(value)
+                    .ok()
+                    .and_then(|idx| targets.get(idx))
+                    .unwrap_or(default);
+                return Ok(Some(Self::jump(*target, *sub_target, frame)));
+            }
+            CILRoot::SourceFileInfo {
+                line_start, file, ..
+            } => self.location = Some((*file, *line_start)),
+            CILRoot::SetField(info) => {
+                let (field, addr, val) = info.as_ref();
+                let field = asm[*field];
+                let addr = self.eval(*addr, frame)?;
+                let addr = self.field_addr(&addr, field.owner(), field.name())?;
+                let val = self.eval(*val, frame)?;
+                self.write_value(addr, field.tpe(), &val)?;
+            }
+            CILRoot::Call(info) => {
+                let (mref, args, _) = info.as_ref();
+                let args = self.eval_all(args, frame)?;
+                self.call_method(*mref, args)?;
+            }
+            CILRoot::StInd(info) => {
+                let (addr, val, tpe, _) = info.as_ref();
+                let addr = self.eval(*addr, frame)?.as_addr()?;
+                let val = self.eval(*val, frame)?;
+                self.write_value(addr, *tpe, &val)?;
+            }
+            CILRoot::InitBlk(info) => {
+                let (dst, val, count) = info.as_ref();
+                let dst = self.eval(*dst, frame)?.as_addr()?;
+                let val = self.eval(*val, frame)?.as_i128()? as u8;
+                let count = self.eval(*count, frame)?.as_i128()? as u64;
+                self.memory.fill(dst, val, count)?;
+            }
+            CILRoot::CpBlk(info) => {
+                let (dst, src, len) = info.as_ref();
+                let dst = self.eval(*dst, frame)?.as_addr()?;
+                let src = self.eval(*src, frame)?.as_addr()?;
+                let len = self.eval(*len, frame)?.as_i128()? as u64;
+                self.memory.copy(dst, src, len)?;
+            }
+            CILRoot::CallI(info) => {
+                let (ptr, _, args) = info.as_ref();
+                let ptr = self.eval(*ptr, frame)?;
+                let args = self.eval_all(args, frame)?;
+                let mref = self.resolve_fn_ptr(&ptr)?;
+                self.invoke(mref, args)?;
+            }
+            CILRoot::ExitSpecialRegion { target, .. } => return Ok(Some(Next::Block(*target))),
+            CILRoot::ReThrow => {
+                let exception = frame.exception.clone().ok_or_else(|| {
+                    InterpreterError::TypeMismatch("rethrow outside of a handler".into())
+                })?;
+                return Err(InterpreterError::Exception(exception));
+            }
+            CILRoot::SetStaticField { field, val } => {
+                let addr = self.static_addr(*field)?;
+                let val = self.eval(*val, frame)?;
+                self.write_value(addr, asm[*field].tpe(), &val)?;
+            }
+            CILRoot::CpObj { src, dst, tpe } => {
+                let src = self.eval(*src, frame)?.as_addr()?;
+                let dst = self.eval(*dst, frame)?.as_addr()?;
+                let (size, _) = self.size_align(asm[*tpe])?;
+                self.memory.copy(dst, src, size)?;
+            }
+            CILRoot::Unreachable(msg) => {
+                return Err(InterpreterError::Unreachable(asm[*msg].into()))
+            }
+            CILRoot::InitObj(addr, tpe) => {
+                let addr = self.eval(*addr, frame)?.as_addr()?;
+                let (size, _) = self.size_align(asm[*tpe])?;
+                self.memory.fill(addr, 0, size)?;
+            }
+            CILRoot::InlineAsm(_) => {
+                return Err(InterpreterError::Unsupported("inline assembly".into()));
+            }
+            CILRoot::VaStart(_) | CILRoot::VaArg(_) => {
+                return Err(InterpreterError::Unsupported(
+                    "C-variadic method definitions".into(),
+                ));
+            }
+        }
+        Ok(None)
+    }
+    fn branch_cond(&mut self, cond: &BranchCond, frame: &mut Frame) -> IResult<bool> {
+        let (lhs, rhs, op, negate) = match cond {
+            BranchCond::True(val) => return self.eval(*val, frame)?.is_true(),
+            BranchCond::False(val) => return Ok(!self.eval(*val, frame)?.is_true()?),
+            BranchCond::Eq(lhs, rhs) => (lhs, rhs, BinOp::Eq, false),
+            BranchCond::Ne(lhs, rhs) => (lhs, rhs, BinOp::Eq, true),
+            BranchCond::Lt(lhs, rhs, CmpKind::Unordered) => (lhs, rhs, BinOp::LtUn, false),
+            BranchCond::Lt(lhs, rhs, _) => (lhs, rhs, BinOp::Lt, false),
+            BranchCond::Gt(lhs, rhs, CmpKind::Unordered) => (lhs, rhs, BinOp::GtUn, false),
+            BranchCond::Gt(lhs, rhs, _) => (lhs, rhs, BinOp::Gt, false),
+            // `a <= b` is `!(a > b)`, with the opposite treatment of NaNs.
+            BranchCond::Le(lhs, rhs, CmpKind::Unordered) => (lhs, rhs, BinOp::Gt, true),
+            BranchCond::Le(lhs, rhs, _) => (lhs, rhs, BinOp::GtUn, true),
+            BranchCond::Ge(lhs, rhs, CmpKind::Unordered) => (lhs, rhs, BinOp::Lt, true),
+            BranchCond::Ge(lhs, rhs, _) => (lhs, rhs, BinOp::LtUn, true),
+        };
+        let lhs = self.eval(*lhs, frame)?;
+        let rhs = self.eval(*rhs, frame)?;
+        Ok(value::bin_op(op, lhs, rhs)?.is_true()? != negate)
+    }
+    fn eval_all(&mut self, nodes: &[Interned<CILNode>], frame: &mut Frame) -> IResult<Vec<Value>> {
+        nodes.iter().map(|node| self.eval(*node, frame)).collect()
+    }
+    fn slot(slots: &[(u64, Type)], idx: u32) -> IResult<(u64, Type)> {
+        slots
+            .get(idx as usize)
+            .copied()
+            .ok_or_else(|| InterpreterError::TypeMismatch(format!("no local or argument {idx}")))
+    }
+    fn eval(&mut self, node: Interned<CILNode>, frame: &mut Frame) -> IResult<Value> {
+        let asm = self.asm;
+        Ok(match &asm[node] {
+            CILNode::Const(cst) => self.constant(cst)?,
+            CILNode::BinOp(lhs, rhs, op) => {
+                let lhs = self.eval(*lhs, frame)?;
+                let rhs = self.eval(*rhs, frame)?;
+                value::bin_op(*op, lhs, rhs)?
+            }
+            CILNode::UnOp(val, op) => match (self.eval(*val, frame)?, op) {
+                (Value::Int(int, val), UnOp::Not) => Value::Int(int, value::truncate(int, !val)),
+                (Value::Int(int, val), UnOp::Neg) => {
+                    Value::Int(int, value::truncate(int, val.wrapping_neg()))
+                }
+                (Value::Bool(val), UnOp::Not) => Value::Bool(!val),
+                (Value::F32(val), UnOp::Neg) => Value::F32(-val),
+                (Value::F64(val), UnOp::Neg) => Value::F64(-val),
+                (val, op) => {
+                    return Err(InterpreterError::TypeMismatch(format!("{op:?} on {val:?}")))
+                }
+            },
+            CILNode::LdLoc(idx) => {
+                let (addr, tpe) = Self::slot(&frame.locals, *idx)?;
+                self.read_value(addr, tpe)?
+            }
+            CILNode::LdArg(idx) => {
+                let (addr, tpe) = Self::slot(&frame.args, *idx)?;
+                self.read_value(addr, tpe)?
+            }
+            CILNode::LdLocA(idx) => Value::Ptr(Self::slot(&frame.locals, *idx)?.0),
+            CILNode::LdArgA(idx) => Value::Ptr(Self::slot(&frame.args, *idx)?.0),
+            CILNode::Call(info) => {
+                let (mref, args, _) = info.as_ref();
+                let args = self.eval_all(args, frame)?;
+                self.call_method(*mref, args)?
+            }
+            CILNode::IntCast {
+                input,
+                target,
+                extend,
+            } => value::int_cast(
+                &self.eval(*input, frame)?,
+                *target,
+                *extend == ExtendKind::SignExtend,
+            )?,
+            CILNode::FloatCast {
+                input,
+                target,
+                is_signed,
+            } => value::float_cast(&self.eval(*input, frame)?, *target, *is_signed)?,
+            CILNode::RefToPtr(val) => self.eval(*val, frame)?,
+            CILNode::PtrCast(val, res) => {
+                let val = self.eval(*val, frame)?;
+                match res.as_ref() {
+                    PtrCastRes::USize => Value::Int(Int::USize, val.as_addr()?.into()),
+                    PtrCastRes::ISize => Value::Int(Int::ISize, val.as_addr()?.into()),
+                    PtrCastRes::Ptr(_) | PtrCastRes::Ref(_) | PtrCastRes::FnPtr(_) => {
+                        Value::Ptr(val.as_addr()?)
+                    }
+                }
+            }
+            CILNode::LdFieldAddress { addr, field } => {
+                let field = asm[*field];
+                let addr = self.eval(*addr, frame)?;
+                Value::Ptr(self.field_addr(&addr, field.owner(), field.name())?)
+            }
+            CILNode::LdField { addr, field } => {
+                let field = asm[*field];
+                match self.eval(*addr, frame)? {
+                    // Fields of value types passed by value.
+                    Value::Struct(bytes) => {
+                        let layout = self.layout(field.owner())?;
+                        let offset = Self::field_offset(&layout, field.name())? as usize;
+                        let (size, _) = self.size_align(field.tpe())?;
+                        let bytes = bytes.get(offset..offset + size as usize).ok_or(
+                            InterpreterError::InvalidAccess {
+                                addr: offset as u64,
+                                len: size,
+                            },
+                        )?;
+                        self.decode(bytes, field.tpe())
+                    }
+                    addr => {
+                        let addr = self.field_addr(&addr, field.owner(), field.name())?;
+                        self.read_value(addr, field.tpe())?
+                    }
+                }
+            }
+            CILNode::LdInd { addr, tpe, .. } => {
+                let addr = self.eval(*addr, frame)?.as_addr()?;
+                self.read_value(addr, asm[*tpe])?
+            }
+            CILNode::SizeOf(tpe) => Value::int(Int::I32, self.size_align(asm[*tpe])?.0 as i128),
+            CILNode::GetException => frame.exception.clone().ok_or_else(|| {
+                InterpreterError::TypeMismatch("no exception is being handled".into())
+            })?,
+            CILNode::IsInst(val, tpe) => {
+                let val = self.eval(*val, frame)?;
+                if self.is_inst(&val, asm[*tpe])? {
+                    val
+                } else {
+                    Value::Obj(0)
+                }
+            }
+            CILNode::CheckedCast(val, tpe) => {
+                let val = self.eval(*val, frame)?;
+                if !self.is_inst(&val, asm[*tpe])? {
+                    return Err(InterpreterError::TypeMismatch(format!(
+                        "can't cast {val:?} to {}",
+                        asm[*tpe].mangle(asm)
+                    )));
+                }
+                val
+            }
+            CILNode::CallI(info) => {
+                let (ptr, _, args) = info.as_ref();
+                let ptr = self.eval(*ptr, frame)?;
+                let args = self.eval_all(args, frame)?;
+                let mref = self.resolve_fn_ptr(&ptr)?;
+                self.invoke(mref, args)?
+            }
+            CILNode::LocAlloc { size } => {
+                let size = self.eval(*size, frame)?.as_i128()? as u64;
+                let addr = self.memory.alloc(size, 16, AllocKind::Stack);
+                frame.allocs.push(addr);
+                Value::Ptr(addr)
+            }
+            CILNode::LocAllocAlgined { tpe, align } => {
+                let (size, _) = self.size_align(asm[*tpe])?;
+                let addr = self.memory.alloc(size, *align, AllocKind::Stack);
+                frame.allocs.push(addr);
+                Value::Ptr(addr)
+            }
+            CILNode::LdStaticField(field) => {
+                let addr = self.static_addr(*field)?;
+                self.read_value(addr, asm[*field].tpe())?
+            }
+            CILNode::LdStaticFieldAddress(field) => Value::Ptr(self.static_addr(*field)?),
+            CILNode::LdFtn(mref) => {
+                let idx = match self.fn_ptrs.iter().position(|ptr| ptr == mref) {
+                    Some(idx) => idx,
+                    None => {
+                        self.fn_ptrs.push(*mref);
+                        self.fn_ptrs.len() - 1
+                    }
+                };
+                Value::Ptr(FN_PTR_BASE + idx as u64)
+            }
+            CILNode::LdTypeToken(_)
+            | CILNode::LdLen(_)
+            | CILNode::LdElelemRef { .. }
+            | CILNode::UnboxAny { .. } => {
+                return Err(InterpreterError::Unsupported(format!(
+                    "{:?}",
+                    std::mem::discriminant(&asm[node])
+                )))
+            }
+        })
+    }
+    fn constant(&mut self, cst: &Const) -> IResult<Value> {
+        Ok(match cst {
+            Const::I8(val) => Value::int(Int::I8, *val),
+            Const::I16(val) => Value::int(Int::I16, *val),
+            Const::I32(val) => Value::int(Int::I32, *val),
+            Const::I64(val) => Value::int(Int::I64, *val),
+            Const::I128(val) => Value::int(Int::I128, *val),
+            Const::ISize(val) => Value::int(Int::ISize, *val),
+            Const::U8(val) => Value::int(Int::U8, *val),
+            Const::U16(val) => Value::int(Int::U16, *val),
+            Const::U32(val) => Value::int(Int::U32, *val),
+            Const::U64(val) => Value::int(Int::U64, *val),
+            Const::U128(val) => Value::Int(Int::U128, *val),
+            Const::USize(val) => Value::int(Int::USize, *val),
+            Const::PlatformString(string) => {
+                if let Some(handle) = self.strings.get(string) {
+                    return Ok(Value::Obj(*handle));
+                }
+                let Value::Obj(handle) = self.alloc_string(&self.asm[*string]) else {
+                    unreachable!()
+                };
+                self.strings.insert(*string, handle);
+                Value::Obj(handle)
+            }
+            Const::Bool(val) => Value::Bool(*val),
+            Const::F32(val) => Value::F32(val.0),
+            Const::F64(val) => Value::F64(val.0),
+            Const::Null(_) => Value::Obj(0),
+            Const::ByteBuffer { data, .. } => {
+                if let Some(addr) = self.const_data.get(data) {
+                    return Ok(Value::Ptr(*addr));
+                }
+                let bytes = self.asm.const_data[*data].clone();
+                let addr = self.memory.alloc_with(bytes, 16, AllocKind::Const);
+                self.const_data.insert(*data, addr);
+                Value::Ptr(addr)
+            }
+        })
+    }
+    fn resolve_fn_ptr(&self, ptr: &Value) -> IResult<Interned<MethodRef>> {
+        let addr = ptr.as_addr()?;
+        addr.checked_sub(FN_PTR_BASE)
+            .and_then(|idx| self.fn_ptrs.get(idx as usize))
+            .copied()
+            .ok_or(InterpreterError::InvalidAccess { addr, len: 0 })
+    }
+    /// Checks if the object `val` is an instance of `tpe`. `null` is an instance of every type.
+    fn is_inst(&self, val: &Value, tpe: Type) -> IResult<bool> {
+        let Value::Obj(handle) = val else {
+            return Err(InterpreterError::TypeMismatch(format!(
+                "expected an object, got {val:?}"
+            )));
+        };
+        if *handle == 0 || tpe == Type::PlatformObject {
+            return Ok(true);
+        }
+        let Type::ClassRef(target) = tpe else {
+            return Ok(matches!(
+                (self.object(*handle)?, tpe),
+                (Object::Str(_), Type::PlatformString)
+            ));
+        };
+        let target_name = &self.asm[self.asm[target].name()];
+        if target_name == "System.Object" {
+            return Ok(true);
+        }
+        match self.object(*handle)? {
+            Object::Str(_) => Ok(target_name == "System.String"),
+            Object::BigInt(_) => Ok(target_name == BIG_INTEGER),
+            Object::Instance { class, .. } => {
+                let mut class = Some(*class);
+                while let Some(curr) = class {
+                    if curr == target {
+                        return Ok(true);
+                    }
+                    class = self
+                        .asm
+                        .class_ref_to_def(curr)
+                        .and_then(|def| self.asm[def].extends());
+                }
+                Ok(false)
+            }
+        }
+    }
+    fn layout(&mut self, class: Interned<ClassRef>) -> IResult<std::rc::Rc<ClassLayout>> {
+        if let Some(layout) = self.layouts.get(&class) {
+            return Ok(layout.clone());
+        }
+        let asm = self.asm;
+        let Some(def) = asm.class_ref_to_def(class) else {
+            if asm[class].is_valuetype() {
+                return Err(InterpreterError::Unsupported(format!(
+                    "layout of the external type {}",
+                    &asm[asm[class].name()]
+                )));
+            }
+            // Fields of external classes are not accessible.
+            return Ok(std::rc::Rc::new(ClassLayout {
+                size: 0,
+                align: 1,
+                fields: FxHashMap::default(),
+            }));
+        };
+        let def = &asm[def];
+        let mut size: u64 = 0;
+        let mut max_align: u64 = 1;
+        let mut fields = FxHashMap::default();
+        for (tpe, name, offset) in def.fields() {
+            let (field_size, field_align) = self.size_align(*tpe)?;
+            let offset = match offset {
+                Some(offset) => u64::from(*offset),
+                None => size.next_multiple_of(field_align.max(1)),
+            };
+            size = size.max(offset + field_size);
+            max_align = max_align.max(field_align);
+            fields.insert(*name, offset);
+        }
+        let align = def.align().map_or(max_align, |align| align.get().into());
+        let size = def
+            .explict_size()
+            .map_or(size.next_multiple_of(align), |size| size.get().into());
+        let layout = std::rc::Rc::new(ClassLayout {
+            size,
+            align,
+            fields,
+        });
+        self.layouts.insert(class, layout.clone());
+        Ok(layout)
+    }
+    fn field_offset(layout: &ClassLayout, name: Interned<IString>) -> IResult<u64> {
+        layout
+            .fields
+            .get(&name)
+            .copied()
+            .ok_or_else(|| InterpreterError::TypeMismatch("access to a missing field".into()))
+    }
+    fn field_addr(
+        &mut self,
+        base: &Value,
+        owner: Interned<ClassRef>,
+        name: Interned<IString>,
+    ) -> IResult<u64> {
+        let base = match base {
+            Value::Obj(handle) => match self.object(*handle)? {
+                Object::Instance { addr, .. } => *addr,
+                Object::Str(_) | Object::BigInt(_) => {
+                    return Err(InterpreterError::TypeMismatch(
+                        "access to a field of a string or a BigInteger".into(),
+                    ))
+                }
+            },
+            base => base.as_addr()?,
+        };
+        let layout = self.layout(owner)?;
+        Ok(base + Self::field_offset(&layout, name)?)
+    }
+    /// Returns the address of a static field, initializing it on first use.
+    fn static_addr(&mut self, field: Interned<StaticFieldDesc>) -> IResult<u64> {
+        if let Some(addr) = self.statics.get(&field) {
+            return Ok(*addr);
+        }
+        let asm = self.asm;
+        let desc = asm[field];
+        let (size, align) = self.size_align(desc.tpe())?;
+        let addr = self.memory.alloc(size, align, AllocKind::Static);
+        self.statics.insert(field, addr);
+        let default = asm.class_ref_to_def(desc.owner()).and_then(|def| {
+            asm[def]
+                .static_fields()
+                .iter()
+                .find(|def| def.name == desc.name())
+                .and_then(|def| def.default_value.as_ref())
+        });
+        if let Some(default) = default {
+            let val = self.constant(default)?;
+            self.write_value(addr, desc.tpe(), &val)?;
+        }
+        Ok(addr)
+    }
+    /// Returns the size and alignment of a type, in bytes. Assumes a 64 bit target.
+    fn size_align(&mut self, tpe: Type) -> IResult<(u64, u64)> {
+        Ok(match tpe {
+            Type::Int(int) => {
+                let size = u64::from(value::int_bits(int) / 8);
+                (size, size)
+            }
+            Type::Float(float) => {
+                let size = u64::from(float.bits() / 8);
+                (size, size)
+            }
+            Type::Bool => (1, 1),
+            Type::PlatformChar => (2, 2),
+            Type::Void => (0, 1),
+            Type::ClassRef(class) if self.is_big_integer(class) => (8, 8),
+            Type::ClassRef(class) if self.asm[class].is_valuetype() => {
+                let layout = self.layout(class)?;
+                (layout.size, layout.align)
+            }
+            Type::SIMDVector(vec) => {
+                let size = u64::from(vec.bits() / 8);
+                (size, size)
+            }
+            Type::Ptr(_)
+            | Type::Ref(_)
+            | Type::FnPtr(_)
+            | Type::ClassRef(_)
+            | Type::PlatformString
+            | Type::PlatformObject
+            | Type::PlatformArray { .. }
+            | Type::PlatformGeneric(..) => (8, 8),
+        })
+    }
+    fn decode(&self, bytes: &[u8], tpe: Type) -> Value {
+        let mut buff = [0; 16];
+        let len = bytes.len().min(16);
+        buff[..len].copy_from_slice(&bytes[..len]);
+        let bits = u128::from_le_bytes(buff);
+        match tpe {
+            Type::Int(int) => Value::Int(int, bits),
+            Type::Bool => Value::Bool(bits != 0),
+            Type::PlatformChar => Value::Int(Int::U16, bits),
+            Type::Float(Float::F32) => Value::F32(f32::from_bits(bits as u32)),
+            Type::Float(Float::F64) => Value::F64(f64::from_bits(bits as u64)),
+            Type::Ptr(_) | Type::Ref(_) | Type::FnPtr(_) => Value::Ptr(bits as u64),
+            Type::ClassRef(class)
+                if !self.asm[class].is_valuetype() || self.is_big_integer(class) =>
+            {
+                Value::Obj(bits as u64)
+            }
+            Type::PlatformString
+            | Type::PlatformObject
+            | Type::PlatformArray { .. }
+            | Type::PlatformGeneric(..) => Value::Obj(bits as u64),
+            // f16 and f128 can be moved around, but not operated on.
+            Type::Float(_) | Type::ClassRef(_) | Type::SIMDVector(_) => Value::Struct(bytes.into()),
+            Type::Void => Value::Void,
+        }
+    }
+    /// Checks if `class` is `System.Numerics.BigInteger`, whose values are stored as handles to [`Object::BigInt`].
+    fn is_big_integer(&self, class: Interned<ClassRef>) -> bool {
+        let class = &self.asm[class];
+        class.asm().is_some() && &self.asm[class.name()] == BIG_INTEGER
+    }
+    fn read_value(&mut self, addr: u64, tpe: Type) -> IResult<Value> {
+        let (size, _) = self.size_align(tpe)?;
+        let bytes = self.memory.read(addr, size)?;
+        Ok(self.decode(bytes, tpe))
+    }
+    fn write_value(&mut self, addr: u64, tpe: Type, val: &Value) -> IResult<()> {
+        let (size, _) = self.size_align(tpe)?;
+        match val {
+            Value::Struct(bytes) if bytes.len() as u64 == size => self.memory.write(addr, bytes),
+            Value::Void if size == 0 => Ok(()),
+            Value::Struct(_) | Value::Void => Err(InterpreterError::TypeMismatch(format!(
+                "can't store {val:?} as {}",
+                tpe.mangle(self.asm)
+            ))),
+            _ => {
+                let bits = val.raw_bits()?.to_le_bytes();
+                let bits = bits.get(..size as usize).ok_or_else(|| {
+                    InterpreterError::TypeMismatch(format!("can't store {val:?} as {size} bytes"))
+                })?;
+                self.memory.write(addr, bits)
+            }
+        }
+    }
+}
+fn find_block(blocks: &[BasicBlock], id: u32) -> IResult<&BasicBlock> {
+    blocks
+        .iter()
+        .find(|block| block.block_id() == id)
+        .ok_or_else(|| InterpreterError::TypeMismatch(format!("no block with id {id}")))
+}
+#[cfg(test)]
+fn run(src: &str, name: &str, args: Vec<Value>) -> (IResult<Value>, Vec<u8>) {
+    let asm = super::cillytext::parse(src).unwrap_or_else(|err| panic!("{err}"));
+    let def = asm
+        .method_defs()
+        .keys()
+        .find(|def| &asm[asm[**def].name()] == name)
+        .unwrap();
+    let mut interpreter = Interpreter::new(&asm);
+    interpreter.set_fuel(10_000);
+    let res = interpreter.call_method(**def, args);
+    (res, interpreter.stdout().to_vec())
+}
+#[test]
+fn loops() {
+    let src = r#"
+(classdef public class "MainModule"
+  (method public static "sum" (sig (u32) u32) (args "n")
+    (locals (local u32) (local u32))
+    (block 0
+      (stloc 0 (const u32 0))
+      (stloc 1 (const u32 0))
+      (br 1 0))
+    (block 1
+      (br 2 0 (gt unsigned (ldloc 1) (ldarg 0)))
+      (stloc 0 (add (ldloc 0) (ldloc 1)))
+      (stloc 1 (add (ldloc 1) (const u32 1)))
+      (br 1 0))
+    (block 2 (ret (ldloc 0))))
+  (method public static "forever" (sig () void) (args)
+    (locals)
+    (block 0 (br 0 0))))
+"#;
+    let (res, _) = run(src, "sum", vec![Value::int(Int::U32, 10)]);
+    assert_eq!(res, Ok(Value::int(Int::U32, 55)));
+    let (res, _) = run(src, "forever", vec![]);
+    assert_eq!(res, Err(InterpreterError::OutOfFuel));
+}
+#[test]
+fn heap() {
+    let src = r#"
+(classdef public class "MainModule"
+  (method extern static "malloc" (sig (usize) (ptr u8)) (args _) (extern "libc"))
+  (method extern static "free" (sig ((ptr u8)) void) (args _) (extern "libc"))
+  (method extern static "puts" (sig ((ptr u8)) i32) (args _) (extern "libc"))
+  (method public static "hello" (sig (usize) void) (args "len")
+    (locals (local (ptr u8)))
+    (block 0
+      (stloc 0 (call (mref (class "MainModule") "malloc" static (sig (usize) (ptr u8))) (ldarg 0)))
+      (stind u8 (ldloc 0) (const u8 104))
+      (stind u8 (add (ldloc 0) (const usize 1)) (const u8 105))
+      (stind u8 (add (ldloc 0) (const usize 2)) (const u8 0))
+      (pop (call (mref (class "MainModule") "puts" static (sig ((ptr u8)) i32)) (ldloc 0)))
+      (call (mref (class "MainModule") "free" static (sig ((ptr u8)) void)) (ldloc 0))
+      (ret_void))))
+"#;
+    let (res, stdout) = run(src, "hello", vec![Value::int(Int::USize, 3)]);
+    assert_eq!(res, Ok(Value::Void));
+    assert_eq!(stdout, b"hi\n");
+    // The terminator is written out of bounds.
+    let (res, _) = run(src, "hello", vec![Value::int(Int::USize, 2)]);
+    assert!(matches!(
+        res,
+        Err(InterpreterError::InvalidAccess { len: 1, .. })
+    ));
+}
+#[test]
+fn exceptions() {
+    let src = r#"
+(classdef public class "MainModule"
+  (method public static "catch" (sig (bool) i32) (args "throw")
+    (locals)
+    (block 0
+      (br 1 0 (true (ldarg 0)))
+      (ret (const i32 0)))
+    (block 1
+      (throw (const string "oops"))
+      (handler
+        (block 3
+          (br 4 4 (eq (get_exception) (const string "oops")))
+          (rethrow))
+        (block 4 (exit_region 2 3))))
+    (block 2 (ret (const i32 1))))
+  (method public static "uncaught" (sig () void) (args)
+    (locals)
+    (block 0 (throw (const string "oops")))))
+"#;
+    let (res, _) = run(src, "catch", vec![Value::Bool(false)]);
+    assert_eq!(res, Ok(Value::int(Int::I32, 0)));
+    let (res, _) = run(src, "catch", vec![Value::Bool(true)]);
+    assert_eq!(res, Ok(Value::int(Int::I32, 1)));
+    let (res, _) = run(src, "uncaught", vec![]);
+    assert!(matches!(
+        res,
+        Err(InterpreterError::Exception(Value::Obj(_)))
+    ));
+}
+#[test]
+fn switch() {
+    use super::opt::{OptFuel, PassManager};
+    let src = r#"
+(classdef public class "MainModule"
+  (method public static "classify" (sig (i32) i32) (args "val")
+    (locals)
+    (block 0
+      (br 1 0 (eq (ldarg 0) (const i32 -2)))
+      (br 2 0 (eq (ldarg 0) (const i32 -1)))
+      (br 3 0 (eq (ldarg 0) (const i32 1)))
+      (br 4 0))
+    (block 1 (ret (const i32 10)))
+    (block 2 (ret (const i32 20)))
+    (block 3 (ret (const i32 30)))
+    (block 4 (ret (const i32 0)))))
+"#;
+    let expected = [
+        (-3, 0),
+        (-2, 10),
+        (-1, 20),
+        (0, 0),
+        (1, 30),
+        (2, 0),
+        (i32::MIN, 0),
+    ];
+    let mut asm = super::cillytext::parse(src).unwrap();
+    asm.opt_with(
+        &mut PassManager::from_names(["build_switch"]).unwrap(),
+        &mut OptFuel::new(100),
+    );
+    let optimized = super::cillytext::print(&asm);
+    assert!(optimized.contains("(switch"), "{optimized}");
+    for src in [src, &optimized] {
+        for (arg, res) in expected {
+            assert_eq!(
+                run(src, "classify", vec![Value::int(Int::I32, arg)]).0,
+                Ok(Value::int(Int::I32, res))
+            );
+        }
+    }
+}
+#[test]
+fn inline_bodies() {
+    use super::opt::{OptFuel, PassManager};
+    let src = r#"
+(classdef public class "MainModule"
+  (method public static "clamp" (sig (i32 i32) i32) (args "val" "max")
+    (locals (local i32))
+    (block 0
+      (stloc 0 (ldarg 0))
+      (br 1 0 (gt signed (ldloc 0) (ldarg 1)))
+      (br 2 0))
+    (block 1
+      (starg 0 (ldarg 1))
+      (ret (ldarg 0)))
+    (block 2 (ret (ldloc 0))))
+  (method public static "check" (sig (i32) void) (args "val")
+    (locals)
+    (block 0
+      (br 1 0 (lt signed (ldarg 0) (const i32 0)))
+      (ret_void))
+    (block 1 (ret_void)))
+  (method public static "clamp_twice" (sig (i32) i32) (args "val")
+    (locals (local i32) (local i32))
+    (block 0
+      (stloc 0 (call (mref (class "MainModule") "clamp" static (sig (i32 i32) i32)) (ldarg 0) (const i32 10)))
+      (pop (call (mref (class "MainModule") "clamp" static (sig (i32 i32) i32)) (ldloc 0) (const i32 0)))
+      (call (mref (class "MainModule") "check" static (sig (i32) void)) (ldloc 0))
+      (stloc 1 (add (ldloc 0) (const i32 1)))
+      (ret (call (mref (class "MainModule") "clamp" static (sig (i32 i32) i32)) (ldloc 1) (const i32 5))))))
+"#;
+    let mut asm = super::cillytext::parse(src).unwrap();
+    asm.opt_with(
+        &mut PassManager::from_names(["inline_bodies"])
+            .unwrap()
+            .with_typecheck(true),
+        &mut OptFuel::new(100),
+    );
+    let optimized = super::cillytext::print(&asm);
+    let caller = optimized
+        .split("\"clamp_twice\"")
+        .nth(1)
+        .expect("Caller missing");
+    assert!(!caller.contains("(call"), "{optimized}");
+    for src in [src, &optimized] {
+        for val in [-3, 4, 7, 20] {
+            assert_eq!(
+                run(src, "clamp_twice", vec![Value::int(Int::I32, val)]).0,
+                Ok(Value::int(Int::I32, (val.min(10) + 1).min(5)))
+            );
+        }
+    }
+}
+#[test]
+fn split_structs() {
+    use super::opt::{OptFuel, PassManager};
+    let src = r#"
+(classdef public valuetype "Pair"
+  (field i32 "a")
+  (field i32 "b"))
+(classdef public class "MainModule"
+  (method public static "swap_sub" (sig (i32 i32) i32) (args "a" "b")
+    (locals (local (valuetype "Pair") "pair") (local (valuetype "Pair") "escaped") (local (valuetype "Pair") "copy"))
+    (block 0
+      (stfld (field (valuetype "Pair") "a" i32) (ldloca 0) (ldarg 1))
+      (stfld (field (valuetype "Pair") "b" i32) (ldloca 1) (ldarg 0))
+      (stloc 2 (ldloc 1))
+      (ret (sub (ldfld (field (valuetype "Pair") "a" i32) (ldloca 0)) (ldfld (field (valuetype "Pair") "b" i32) (ldloca 2)))))))
+"#;
+    let mut asm = super::cillytext::parse(src).unwrap();
+    asm.opt_with(
+        &mut PassManager::from_names(["split_structs"])
+            .unwrap()
+            .with_typecheck(true),
+        &mut OptFuel::new(100),
+    );
+    let optimized = super::cillytext::print(&asm);
+    // Only the first local is accessed field-by-field, the others are copied as a whole.
+    assert!(!optimized.contains("(ldloca 0)"), "{optimized}");
+    assert!(optimized.contains("(ldloca 1)"), "{optimized}");
+    assert!(optimized.contains("(ldloca 2)"), "{optimized}");
+    for src in [src, &optimized] {
+        assert_eq!(
+            run(
+                src,
+                "swap_sub",
+                vec![Value::int(Int::I32, 3), Value::int(Int::I32, 10)]
+            )
+            .0,
+            Ok(Value::int(Int::I32, 7))
+        );
+    }
+}
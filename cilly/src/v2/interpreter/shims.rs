@@ -0,0 +1,405 @@
+//! Built-in implementations of external functions.
+use num_bigint::{BigInt, Sign};
+
+use super::{value, AllocKind, IResult, Interpreter, InterpreterError, Object, Value, BIG_INTEGER};
+use crate::{Float, Int, Type};
+
+/// Alignment of memory returned by `malloc`.
+const MALLOC_ALIGN: u64 = 16;
+fn arg(args: &[Value], idx: usize) -> IResult<i128> {
+    args.get(idx)
+        .ok_or_else(|| InterpreterError::TypeMismatch(format!("missing argument {idx}")))?
+        .as_i128()
+}
+fn addr_arg(args: &[Value], idx: usize) -> IResult<u64> {
+    Ok(arg(args, idx)? as u64)
+}
+/// Converts the integer result of a shim to the return type of the shimmed function.
+fn ret(output: Type, val: i128) -> Value {
+    match output {
+        Type::Int(int) => Value::int(int, val),
+        Type::Bool => Value::Bool(val != 0),
+        Type::Void => Value::Void,
+        Type::Ptr(_) | Type::Ref(_) | Type::FnPtr(_) => Value::Ptr(val as u64),
+        _ => Value::int(Int::ISize, val),
+    }
+}
+impl Interpreter<'_> {
+    fn realloc(&mut self, ptr: u64, size: u64, align: u64, kind: AllocKind) -> IResult<u64> {
+        let new = self.memory.alloc(size, align, kind);
+        if ptr != 0 {
+            let old_size = self
+                .memory
+                .alloc_size(ptr)
+                .ok_or(InterpreterError::InvalidFree(ptr))?;
+            self.memory.copy(new, ptr, old_size.min(size))?;
+            self.memory.free(ptr, kind)?;
+        }
+        Ok(new)
+    }
+    fn free_nonnull(&mut self, ptr: u64, kind: AllocKind) -> IResult<()> {
+        if ptr == 0 {
+            return Ok(());
+        }
+        self.memory.free(ptr, kind)
+    }
+    /// Implements an external, C function. Returns `None` if there is no shim for this function.
+    pub(super) fn libc_shim(
+        &mut self,
+        name: &str,
+        args: &[Value],
+        output: Type,
+    ) -> IResult<Option<Value>> {
+        let res: i128 = match name {
+            "malloc" => self
+                .memory
+                .alloc(addr_arg(args, 0)?, MALLOC_ALIGN, AllocKind::Heap)
+                .into(),
+            "calloc" => {
+                let size = addr_arg(args, 0)? * addr_arg(args, 1)?;
+                self.memory
+                    .alloc(size, MALLOC_ALIGN, AllocKind::Heap)
+                    .into()
+            }
+            "aligned_alloc" => self
+                .memory
+                .alloc(addr_arg(args, 1)?, addr_arg(args, 0)?, AllocKind::Heap)
+                .into(),
+            "posix_memalign" => {
+                let ptr =
+                    self.memory
+                        .alloc(addr_arg(args, 2)?, addr_arg(args, 1)?, AllocKind::Heap);
+                self.memory.write(addr_arg(args, 0)?, &ptr.to_le_bytes())?;
+                0
+            }
+            "realloc" => self
+                .realloc(
+                    addr_arg(args, 0)?,
+                    addr_arg(args, 1)?,
+                    MALLOC_ALIGN,
+                    AllocKind::Heap,
+                )?
+                .into(),
+            "free" => {
+                self.free_nonnull(addr_arg(args, 0)?, AllocKind::Heap)?;
+                0
+            }
+            "memcpy" | "memmove" => {
+                self.memory
+                    .copy(addr_arg(args, 0)?, addr_arg(args, 1)?, addr_arg(args, 2)?)?;
+                arg(args, 0)?
+            }
+            "memset" => {
+                self.memory
+                    .fill(addr_arg(args, 0)?, arg(args, 1)? as u8, addr_arg(args, 2)?)?;
+                arg(args, 0)?
+            }
+            "memcmp" => {
+                let len = addr_arg(args, 2)?;
+                let lhs = self.memory.read(addr_arg(args, 0)?, len)?;
+                let rhs = self.memory.read(addr_arg(args, 1)?, len)?;
+                lhs.cmp(rhs) as i128
+            }
+            "strlen" => self.memory.read_cstr(addr_arg(args, 0)?)?.len() as i128,
+            "write" => {
+                let len = addr_arg(args, 2)?;
+                let data = self.memory.read(addr_arg(args, 1)?, len)?;
+                match arg(args, 0)? {
+                    1 => self.stdout.extend_from_slice(data),
+                    2 => self.stderr.extend_from_slice(data),
+                    _ => return Ok(Some(ret(output, -1))),
+                }
+                len.into()
+            }
+            "puts" => {
+                let string = self.memory.read_cstr(addr_arg(args, 0)?)?;
+                self.stdout.extend_from_slice(string);
+                self.stdout.push(b'\n');
+                0
+            }
+            "putchar" => {
+                self.stdout.push(arg(args, 0)? as u8);
+                arg(args, 0)?
+            }
+            // The interpreted program sees an empty enviroment.
+            "getenv" => 0,
+            "abort" => return Err(InterpreterError::Abort),
+            "exit" | "_exit" => return Err(InterpreterError::Exit(arg(args, 0)? as i32)),
+            _ => return Ok(None),
+        };
+        Ok(Some(ret(output, res)))
+    }
+    /// Formats a value the way `System.Console.Write` would.
+    fn display(&self, val: &Value) -> IResult<String> {
+        Ok(match val {
+            Value::Int(Int::U16, val) => char::from_u32(*val as u32)
+                .unwrap_or(char::REPLACEMENT_CHARACTER)
+                .to_string(),
+            Value::Int(..) | Value::Ptr(_) => val.as_i128()?.to_string(),
+            Value::F32(val) => val.to_string(),
+            Value::F64(val) => val.to_string(),
+            Value::Bool(true) => "True".into(),
+            Value::Bool(false) => "False".into(),
+            Value::Obj(0) => String::new(),
+            Value::Obj(handle) => match self.object(*handle)? {
+                Object::Str(string) => string.clone(),
+                Object::BigInt(val) => val.to_string(),
+                Object::Instance { class, .. } => self.asm[self.asm[*class].name()].to_string(),
+            },
+            Value::Struct(_) | Value::Void => {
+                return Err(InterpreterError::Unsupported(format!("printing {val:?}")))
+            }
+        })
+    }
+    fn big_int_arg(&self, args: &[Value], idx: usize) -> IResult<&BigInt> {
+        let arg = args
+            .get(idx)
+            .ok_or_else(|| InterpreterError::TypeMismatch(format!("missing argument {idx}")))?;
+        match arg {
+            Value::Obj(handle) => match self.object(*handle)? {
+                Object::BigInt(val) => Ok(val),
+                _ => Err(InterpreterError::TypeMismatch(format!(
+                    "expected a BigInteger, got {arg:?}"
+                ))),
+            },
+            _ => Err(InterpreterError::TypeMismatch(format!(
+                "expected a BigInteger, got {arg:?}"
+            ))),
+        }
+    }
+    fn alloc_big_int(&mut self, val: BigInt) -> Value {
+        self.objects.push(Object::BigInt(val));
+        Value::Obj(self.objects.len() as u64)
+    }
+    /// Implements a static method or an operator of `System.Numerics.BigInteger`. Returns `None` if there is no shim
+    /// for this method.
+    fn big_integer_shim(
+        &mut self,
+        name: &str,
+        args: &[Value],
+        output: Type,
+    ) -> IResult<Option<Value>> {
+        let res = match name {
+            "op_Implicit" => match args.first() {
+                Some(Value::Int(int, bits)) if int.is_signed() => {
+                    BigInt::from(value::sign_extend(*int, *bits))
+                }
+                Some(Value::Int(_, bits)) => BigInt::from(*bits),
+                _ => return Ok(None),
+            },
+            "op_Explicit" => {
+                let Type::Int(int) = output else {
+                    return Ok(None);
+                };
+                let val = self.big_int_arg(args, 0)?;
+                let bits = value::int_bits(int);
+                let (min, max) = if int.is_signed() {
+                    (
+                        -(BigInt::from(1) << (bits - 1)),
+                        (BigInt::from(1) << (bits - 1)) - 1,
+                    )
+                } else {
+                    (BigInt::ZERO, (BigInt::from(1) << bits) - 1)
+                };
+                if *val < min || *val > max {
+                    let exception = self.alloc_string("System.OverflowException");
+                    return Err(InterpreterError::Exception(exception));
+                }
+                // Both conversions succeed, since the value is in range.
+                return Ok(Some(if int.is_signed() {
+                    Value::int(int, i128::try_from(val).unwrap())
+                } else {
+                    Value::Int(int, u128::try_from(val).unwrap())
+                }));
+            }
+            "op_Addition" | "op_Subtraction" | "op_Multiply" | "op_Division" | "op_Modulus"
+            | "op_BitwiseAnd" | "op_BitwiseOr" | "op_ExclusiveOr" => {
+                let lhs = self.big_int_arg(args, 0)?;
+                let rhs = self.big_int_arg(args, 1)?;
+                if matches!(name, "op_Division" | "op_Modulus") && *rhs == BigInt::ZERO {
+                    return Err(InterpreterError::DivideByZero);
+                }
+                match name {
+                    "op_Addition" => lhs + rhs,
+                    "op_Subtraction" => lhs - rhs,
+                    "op_Multiply" => lhs * rhs,
+                    // Like in .NET, division truncates, and the reminder has the sign of the dividend.
+                    "op_Division" => lhs / rhs,
+                    "op_Modulus" => lhs % rhs,
+                    "op_BitwiseAnd" => lhs & rhs,
+                    "op_BitwiseOr" => lhs | rhs,
+                    _ => lhs ^ rhs,
+                }
+            }
+            "op_LeftShift" | "op_RightShift" => {
+                let val = self.big_int_arg(args, 0)?;
+                // Shifting by a negative amount shifts in the other direction.
+                let shift = arg(args, 1)? as i32;
+                let shift = if name == "op_LeftShift" {
+                    shift
+                } else {
+                    -shift
+                };
+                if shift >= 0 {
+                    val << shift.unsigned_abs()
+                } else {
+                    val >> shift.unsigned_abs()
+                }
+            }
+            "op_Equality"
+            | "op_Inequality"
+            | "op_LessThan"
+            | "op_LessThanOrEqual"
+            | "op_GreaterThan"
+            | "op_GreaterThanOrEqual" => {
+                let ord = self.big_int_arg(args, 0)?.cmp(self.big_int_arg(args, 1)?);
+                return Ok(Some(Value::Bool(match name {
+                    "op_Equality" => ord.is_eq(),
+                    "op_Inequality" => ord.is_ne(),
+                    "op_LessThan" => ord.is_lt(),
+                    "op_LessThanOrEqual" => ord.is_le(),
+                    "op_GreaterThan" => ord.is_gt(),
+                    _ => ord.is_ge(),
+                })));
+            }
+            "IsNegative" => {
+                return Ok(Some(Value::Bool(
+                    self.big_int_arg(args, 0)?.sign() == Sign::Minus,
+                )))
+            }
+            "IsEvenInteger" => return Ok(Some(Value::Bool(!self.big_int_arg(args, 0)?.bit(0)))),
+            "Abs" => BigInt::from(self.big_int_arg(args, 0)?.magnitude().clone()),
+            "Log2" => {
+                let val = self.big_int_arg(args, 0)?;
+                if val.sign() == Sign::Minus {
+                    let exception = self.alloc_string("System.ArgumentOutOfRangeException");
+                    return Err(InterpreterError::Exception(exception));
+                }
+                BigInt::from(val.bits().saturating_sub(1))
+            }
+            "Clamp" => {
+                let val = self.big_int_arg(args, 0)?;
+                let min = self.big_int_arg(args, 1)?;
+                let max = self.big_int_arg(args, 2)?;
+                val.max(min).min(max).clone()
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(self.alloc_big_int(res)))
+    }
+    /// Implements a method of an external .NET class. Returns `None` if there is no shim for this method.
+    pub(super) fn dotnet_shim(
+        &mut self,
+        class: &str,
+        name: &str,
+        args: &[Value],
+        output: Type,
+    ) -> IResult<Option<Value>> {
+        match (class, name) {
+            (BIG_INTEGER, _) => return self.big_integer_shim(name, args, output),
+            // Reinterprets the bits of a float as an integer, or the other way around.
+            ("System.BitConverter", _) if name.contains("Bits") => {
+                let bits = args
+                    .first()
+                    .ok_or_else(|| InterpreterError::TypeMismatch("missing argument 0".into()))?
+                    .raw_bits()?;
+                return Ok(Some(match output {
+                    Type::Float(Float::F32) => Value::F32(f32::from_bits(bits as u32)),
+                    Type::Float(Float::F64) => Value::F64(f64::from_bits(bits as u64)),
+                    Type::Int(int) => Value::Int(int, value::truncate(int, bits)),
+                    _ => return Ok(None),
+                }));
+            }
+            _ => (),
+        }
+        let res: i128 = match (class, name) {
+            ("System.Runtime.InteropServices.NativeMemory", "Alloc") => {
+                // `Alloc(nuint size)` and `Alloc(nuint count, nuint size)`.
+                let size = args
+                    .iter()
+                    .try_fold(1, |size, arg| Ok(size * arg.as_i128()? as u64))?;
+                self.memory
+                    .alloc(size, MALLOC_ALIGN, AllocKind::Heap)
+                    .into()
+            }
+            ("System.Runtime.InteropServices.NativeMemory", "AlignedAlloc") => self
+                .memory
+                .alloc(addr_arg(args, 0)?, addr_arg(args, 1)?, AllocKind::Heap)
+                .into(),
+            ("System.Runtime.InteropServices.NativeMemory", "Realloc") => self
+                .realloc(
+                    addr_arg(args, 0)?,
+                    addr_arg(args, 1)?,
+                    MALLOC_ALIGN,
+                    AllocKind::Heap,
+                )?
+                .into(),
+            ("System.Runtime.InteropServices.NativeMemory", "AlignedRealloc") => self
+                .realloc(
+                    addr_arg(args, 0)?,
+                    addr_arg(args, 1)?,
+                    addr_arg(args, 2)?,
+                    AllocKind::Heap,
+                )?
+                .into(),
+            ("System.Runtime.InteropServices.NativeMemory", "Free" | "AlignedFree")
+            | ("System.Runtime.InteropServices.Marshal", "FreeHGlobal") => {
+                self.free_nonnull(addr_arg(args, 0)?, AllocKind::Heap)?;
+                0
+            }
+            ("System.Runtime.InteropServices.Marshal", "AllocHGlobal") => self
+                .memory
+                .alloc(addr_arg(args, 0)?, MALLOC_ALIGN, AllocKind::Heap)
+                .into(),
+            ("System.Runtime.InteropServices.Marshal", "ReAllocHGlobal") => self
+                .realloc(
+                    addr_arg(args, 0)?,
+                    addr_arg(args, 1)?,
+                    MALLOC_ALIGN,
+                    AllocKind::Heap,
+                )?
+                .into(),
+            ("System.Console", "Write" | "WriteLine") => {
+                let mut text = String::new();
+                for arg in args {
+                    text.push_str(&self.display(arg)?);
+                }
+                if name == "WriteLine" {
+                    text.push('\n');
+                }
+                self.stdout.extend_from_slice(text.as_bytes());
+                0
+            }
+            (
+                "System.Int128" | "System.UInt128" | "System.IntPtr" | "System.UIntPtr",
+                "get_MinValue" | "get_MaxValue",
+            ) => {
+                let Type::Int(int) = output else {
+                    return Ok(None);
+                };
+                let bits = value::int_bits(int);
+                match (int.is_signed(), name) {
+                    (true, "get_MinValue") => {
+                        return Ok(Some(Value::int(int, i128::MIN >> (128 - bits))))
+                    }
+                    (true, _) => return Ok(Some(Value::int(int, i128::MAX >> (128 - bits)))),
+                    (false, "get_MinValue") => 0,
+                    (false, _) => return Ok(Some(Value::Int(int, u128::MAX >> (128 - bits)))),
+                }
+            }
+            ("System.Math", "Min" | "Max") if matches!(output, Type::Int(_)) => {
+                let (lhs, rhs) = (arg(args, 0)?, arg(args, 1)?);
+                if name == "Min" {
+                    lhs.min(rhs)
+                } else {
+                    lhs.max(rhs)
+                }
+            }
+            ("System.Environment", "Exit") => {
+                return Err(InterpreterError::Exit(arg(args, 0)? as i32))
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(ret(output, res)))
+    }
+}
@@ -0,0 +1,293 @@
+use super::InterpreterError;
+use crate::{BinOp, Float, Int};
+
+/// A value on the interpreter's evaluation stack.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    /// An integer of a given type. The bits above the width of the type are always zero.
+    Int(Int, u128),
+    F32(f32),
+    F64(f64),
+    Bool(bool),
+    /// An unmanaged pointer, a managed reference or a function pointer.
+    Ptr(u64),
+    /// A handle to a managed object. The handle 0 represents `null`.
+    Obj(u64),
+    /// The bytes of a value type.
+    Struct(Box<[u8]>),
+    Void,
+}
+/// Returns the width of `int`, in bits. Assumes a 64 bit target.
+pub(super) fn int_bits(int: Int) -> u32 {
+    int.bits().unwrap_or(64).into()
+}
+/// Truncates `val` to the width of `int`.
+pub(super) fn truncate(int: Int, val: u128) -> u128 {
+    match int_bits(int) {
+        128 => val,
+        bits => val & ((1 << bits) - 1),
+    }
+}
+/// Sign-extends the value `val` of type `int` to 128 bits.
+pub(super) fn sign_extend(int: Int, val: u128) -> i128 {
+    let shift = 128 - int_bits(int);
+    ((val << shift) as i128) >> shift
+}
+impl Value {
+    /// Creates an integer value, truncating `val` to the size of `int`.
+    #[must_use]
+    pub fn int(int: Int, val: impl Into<i128>) -> Self {
+        Self::Int(int, truncate(int, val.into() as u128))
+    }
+    /// Retrives the raw bits of this value, used when storing it in memory.
+    pub(super) fn raw_bits(&self) -> Result<u128, InterpreterError> {
+        match self {
+            Value::Int(_, val) => Ok(*val),
+            Value::F32(val) => Ok(val.to_bits().into()),
+            Value::F64(val) => Ok(val.to_bits().into()),
+            Value::Bool(val) => Ok((*val).into()),
+            Value::Ptr(val) | Value::Obj(val) => Ok((*val).into()),
+            Value::Struct(_) | Value::Void => Err(InterpreterError::TypeMismatch(format!(
+                "expected a primitive value, got {self:?}"
+            ))),
+        }
+    }
+    /// Interprets this value as an address.
+    pub fn as_addr(&self) -> Result<u64, InterpreterError> {
+        match self {
+            Value::Ptr(addr) => Ok(*addr),
+            Value::Int(_, val) => Ok(*val as u64),
+            _ => Err(InterpreterError::TypeMismatch(format!(
+                "expected an address, got {self:?}"
+            ))),
+        }
+    }
+    /// Interprets this value as an integer, sign or zero extending it based on its type.
+    pub fn as_i128(&self) -> Result<i128, InterpreterError> {
+        match self {
+            Value::Int(int, val) if int.is_signed() => Ok(sign_extend(*int, *val)),
+            Value::Int(_, val) => Ok(*val as i128),
+            Value::Bool(val) => Ok((*val).into()),
+            Value::Ptr(val) => Ok((*val).into()),
+            _ => Err(InterpreterError::TypeMismatch(format!(
+                "expected an integer, got {self:?}"
+            ))),
+        }
+    }
+    /// Checks if this value is "true" - not zero, and not null.
+    pub fn is_true(&self) -> Result<bool, InterpreterError> {
+        match self {
+            Value::Bool(val) => Ok(*val),
+            Value::Obj(val) => Ok(*val != 0),
+            _ => Ok(self.raw_bits()? != 0),
+        }
+    }
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::F32(val) => Some((*val).into()),
+            Value::F64(val) => Some(*val),
+            _ => None,
+        }
+    }
+}
+/// Converts the value `val` to an integer of type `target`. If `val` is an integer, it is sign or zero extended first.
+pub(super) fn int_cast(
+    val: &Value,
+    target: Int,
+    sign_extend: bool,
+) -> Result<Value, InterpreterError> {
+    let wide: i128 = match val {
+        Value::Int(int, bits) if sign_extend => self::sign_extend(*int, *bits),
+        Value::Int(_, bits) => *bits as i128,
+        // Float to int conversions saturate, like in Rust.
+        Value::F32(_) | Value::F64(_) => {
+            let float = val.as_f64().unwrap();
+            if target.is_signed() {
+                float as i128
+            } else {
+                float as u128 as i128
+            }
+        }
+        _ => val.as_i128()?,
+    };
+    Ok(Value::int(target, wide))
+}
+pub(super) fn float_cast(
+    val: &Value,
+    target: Float,
+    is_signed: bool,
+) -> Result<Value, InterpreterError> {
+    let float = match val {
+        Value::Int(int, bits) if is_signed => sign_extend(*int, *bits) as f64,
+        Value::Int(_, bits) => *bits as f64,
+        Value::F32(float) => {
+            if target == Float::F32 {
+                return Ok(Value::F32(*float));
+            }
+            (*float).into()
+        }
+        Value::F64(float) => *float,
+        _ => {
+            return Err(InterpreterError::TypeMismatch(format!(
+                "can't convert {val:?} to a float"
+            )))
+        }
+    };
+    match target {
+        Float::F32 => Ok(Value::F32(float as f32)),
+        Float::F64 => Ok(Value::F64(float)),
+        Float::F16 | Float::F128 => Err(InterpreterError::Unsupported(format!(
+            "{} values",
+            target.name()
+        ))),
+    }
+}
+fn int_op(op: BinOp, int: Int, lhs: u128, rhs: u128) -> Result<Value, InterpreterError> {
+    let signed = int.is_signed();
+    let (slhs, srhs) = (sign_extend(int, lhs), sign_extend(int, rhs));
+    let val = match op {
+        BinOp::Add => lhs.wrapping_add(rhs),
+        BinOp::Sub => lhs.wrapping_sub(rhs),
+        BinOp::Mul => lhs.wrapping_mul(rhs),
+        BinOp::And => lhs & rhs,
+        BinOp::Or => lhs | rhs,
+        BinOp::XOr => lhs ^ rhs,
+        BinOp::Eq => return Ok(Value::Bool(lhs == rhs)),
+        BinOp::Lt | BinOp::LtUn if signed => return Ok(Value::Bool(slhs < srhs)),
+        BinOp::Lt | BinOp::LtUn => return Ok(Value::Bool(lhs < rhs)),
+        BinOp::Gt | BinOp::GtUn if signed => return Ok(Value::Bool(slhs > srhs)),
+        BinOp::Gt | BinOp::GtUn => return Ok(Value::Bool(lhs > rhs)),
+        BinOp::Div | BinOp::DivUn | BinOp::Rem | BinOp::RemUn if rhs == 0 => {
+            return Err(InterpreterError::DivideByZero)
+        }
+        BinOp::Div | BinOp::DivUn if signed => slhs.wrapping_div(srhs) as u128,
+        BinOp::Div | BinOp::DivUn => lhs / rhs,
+        BinOp::Rem | BinOp::RemUn if signed => slhs.wrapping_rem(srhs) as u128,
+        BinOp::Rem | BinOp::RemUn => lhs % rhs,
+        // Shifts are masked, like in .NET.
+        BinOp::Shl => lhs << (rhs as u32 % int_bits(int)),
+        BinOp::Shr if signed => (slhs >> (rhs as u32 % int_bits(int))) as u128,
+        BinOp::Shr | BinOp::ShrUn => lhs >> (rhs as u32 % int_bits(int)),
+    };
+    Ok(Value::Int(int, truncate(int, val)))
+}
+fn float_op(op: BinOp, lhs: f64, rhs: f64) -> Result<Result<f64, bool>, InterpreterError> {
+    Ok(match op {
+        BinOp::Add => Ok(lhs + rhs),
+        BinOp::Sub => Ok(lhs - rhs),
+        BinOp::Mul => Ok(lhs * rhs),
+        BinOp::Div | BinOp::DivUn => Ok(lhs / rhs),
+        BinOp::Rem | BinOp::RemUn => Ok(lhs % rhs),
+        BinOp::Eq => Err(lhs == rhs),
+        BinOp::Lt => Err(lhs < rhs),
+        BinOp::Gt => Err(lhs > rhs),
+        // Unordered comparisons are true if any of the operands is NaN.
+        BinOp::LtUn => Err(lhs < rhs || lhs.is_nan() || rhs.is_nan()),
+        BinOp::GtUn => Err(lhs > rhs || lhs.is_nan() || rhs.is_nan()),
+        BinOp::Or | BinOp::XOr | BinOp::And | BinOp::Shl | BinOp::Shr | BinOp::ShrUn => {
+            return Err(InterpreterError::TypeMismatch(format!(
+                "{} is not a valid float operation",
+                op.name()
+            )))
+        }
+    })
+}
+/// Evaluates a binary operation.
+pub(super) fn bin_op(op: BinOp, lhs: Value, rhs: Value) -> Result<Value, InterpreterError> {
+    match (lhs, rhs) {
+        (Value::Int(int, lhs), Value::Int(rhs_int, rhs)) => {
+            // The shift amount may be of a different type than the shifted value.
+            if int != rhs_int && !matches!(op, BinOp::Shl | BinOp::Shr | BinOp::ShrUn) {
+                return Err(InterpreterError::TypeMismatch(format!(
+                    "{} on {int:?} and {rhs_int:?}",
+                    op.name()
+                )));
+            }
+            int_op(op, int, lhs, rhs)
+        }
+        (Value::F32(lhs), Value::F32(rhs)) => Ok(match float_op(op, lhs.into(), rhs.into())? {
+            Ok(res) => Value::F32(res as f32),
+            Err(res) => Value::Bool(res),
+        }),
+        (Value::F64(lhs), Value::F64(rhs)) => Ok(match float_op(op, lhs, rhs)? {
+            Ok(res) => Value::F64(res),
+            Err(res) => Value::Bool(res),
+        }),
+        (Value::Bool(lhs), Value::Bool(rhs)) => {
+            match int_op(op, Int::U8, lhs.into(), rhs.into())? {
+                Value::Int(_, val) => Ok(Value::Bool(val != 0)),
+                cmp => Ok(cmp),
+            }
+        }
+        (Value::Obj(lhs), Value::Obj(rhs)) if op == BinOp::Eq => Ok(Value::Bool(lhs == rhs)),
+        // Pointer arithmetic is performed in bytes, and yields a pointer.
+        (lhs @ (Value::Ptr(_) | Value::Int(..)), rhs @ (Value::Ptr(_) | Value::Int(..))) => {
+            match int_op(op, Int::USize, lhs.as_addr()?.into(), rhs.as_addr()?.into())? {
+                Value::Int(_, val) => Ok(Value::Ptr(val as u64)),
+                cmp => Ok(cmp),
+            }
+        }
+        (lhs, rhs) => Err(InterpreterError::TypeMismatch(format!(
+            "{} on {lhs:?} and {rhs:?}",
+            op.name()
+        ))),
+    }
+}
+#[test]
+fn int_ops() {
+    assert_eq!(
+        bin_op(BinOp::Add, Value::int(Int::U8, 255), Value::int(Int::U8, 1)),
+        Ok(Value::int(Int::U8, 0))
+    );
+    assert_eq!(
+        bin_op(BinOp::Lt, Value::int(Int::I32, -1), Value::int(Int::I32, 0)),
+        Ok(Value::Bool(true))
+    );
+    assert_eq!(
+        bin_op(BinOp::Lt, Value::int(Int::U32, -1), Value::int(Int::U32, 0)),
+        Ok(Value::Bool(false))
+    );
+    assert_eq!(
+        bin_op(
+            BinOp::Shr,
+            Value::int(Int::I64, -8),
+            Value::int(Int::I32, 1)
+        ),
+        Ok(Value::int(Int::I64, -4))
+    );
+    assert_eq!(
+        bin_op(
+            BinOp::Div,
+            Value::int(Int::I128, -9),
+            Value::int(Int::I128, 2)
+        ),
+        Ok(Value::int(Int::I128, -4))
+    );
+    assert_eq!(
+        bin_op(BinOp::Rem, Value::int(Int::U16, 5), Value::int(Int::U16, 0)),
+        Err(InterpreterError::DivideByZero)
+    );
+    assert_eq!(
+        int_cast(&Value::int(Int::I8, -1), Int::U32, true),
+        Ok(Value::int(Int::U32, 0xFFFF_FFFF_u32))
+    );
+    assert_eq!(
+        int_cast(&Value::int(Int::I8, -1), Int::U32, false),
+        Ok(Value::int(Int::U32, 0xFF))
+    );
+}
+#[test]
+fn float_ops() {
+    assert_eq!(
+        bin_op(BinOp::LtUn, Value::F64(f64::NAN), Value::F64(1.0)),
+        Ok(Value::Bool(true))
+    );
+    assert_eq!(
+        bin_op(BinOp::Lt, Value::F64(f64::NAN), Value::F64(1.0)),
+        Ok(Value::Bool(false))
+    );
+    assert_eq!(
+        float_cast(&Value::int(Int::U64, -1), Float::F64, false),
+        Ok(Value::F64(u64::MAX as f64))
+    );
+}
@@ -89,6 +89,27 @@ impl Iterator for CILIter<'_> {
                         continue;
                     }
                 },
+                CILIterElem::Root(root @ (CILRoot::InlineAsm(_) | CILRoot::VaArg(_))) => {
+                    let node = root.nodes().get(*idx - 1).map(|node| **node);
+                    if let Some(node) = node {
+                        *idx += 1;
+                        let node = self.asm.get_node(node);
+                        self.elems.push((CILIterElem::Node(node.clone()), 0));
+                    } else {
+                        self.elems.pop();
+                    }
+                    continue;
+                }
+                CILIterElem::Root(CILRoot::Switch(switch)) => {
+                    if idx == &1 {
+                        *idx += 1;
+                        let val = self.asm.get_node(switch.0);
+                        self.elems.push((CILIterElem::Node(val.clone()), 0));
+                    } else {
+                        self.elems.pop();
+                    }
+                    continue;
+                }
                 CILIterElem::Root(CILRoot::CpObj { src, dst, .. }) => match idx {
                     1 => {
                         *idx += 1;
@@ -184,6 +205,7 @@ impl Iterator for CILIter<'_> {
                     | CILRoot::InitObj(val, _)
                     | CILRoot::Pop(val)
                     | CILRoot::Throw(val)
+                    | CILRoot::VaStart(val)
                     | CILRoot::SetStaticField { val, .. },
                 ) => {
                     if idx == &1 {
@@ -519,11 +541,13 @@ impl<'this, T: Iterator<Item = CILIterElem> + 'this> TpeIter<'this> for T {
                     | CILRoot::Break
                     | CILRoot::Nop
                     | CILRoot::Branch(_)
+                    | CILRoot::Switch(_)
                     | CILRoot::SourceFileInfo { .. }
                     | CILRoot::ExitSpecialRegion { .. }
                     | CILRoot::InitBlk(_)
                     | CILRoot::CpBlk(_)
                     | CILRoot::ReThrow
+                    | CILRoot::VaStart(_)
                     | CILRoot::Unreachable(_) => None,
                     CILRoot::SetStaticField { field, .. } => {
                         let field = asm.get_static_field(field);
@@ -543,6 +567,10 @@ impl<'this, T: Iterator<Item = CILIterElem> + 'this> TpeIter<'this> for T {
                     // Since this method is called, then if it uses an "internal" type, we must assume it is defined in this module. Thus, its types are already included, and we don't need to include them again.
                     CILRoot::Call(_) | CILRoot::CallI(_) => None,
                     CILRoot::StInd(info) => Some(Box::new(std::iter::once(info.2))),
+                    CILRoot::VaArg(info) => Some(Box::new(std::iter::once(asm[info.2]))),
+                    CILRoot::InlineAsm(inline_asm) => Some(Box::new(
+                        inline_asm.types(asm).collect::<Vec<_>>().into_iter(),
+                    )),
                 },
             };
             iter
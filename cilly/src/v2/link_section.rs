@@ -0,0 +1,153 @@
+//! Linker sections(`#[link_section]`) on targets without a linker.
+//!
+//! When exporting C, statics in a section are placed in a real section, and the linker provides its bounds. .NET has
+//! no such thing, so [`Assembly::lower_link_sections`] emulates it: each section becomes a buffer, holding copies of
+//! all the statics placed in it.
+use std::num::NonZeroU32;
+
+use super::{
+    bimap::Interned, cilnode::PtrCastRes, Access, Assembly, BinOp, CILNode, CILRoot, ClassDef,
+    Const, Int, StaticFieldDesc, Type,
+};
+use crate::IString;
+
+impl Assembly {
+    /// Turns every linker section into a buffer static. The statics placed in a section are copied into its buffer at
+    /// the end of the [`super::asm::CCTOR`], after they are initialized. The bounds of a section are set before
+    /// anything else, since the initializers of other statics may refer to them.
+    pub fn lower_link_sections(&mut self) {
+        let sections: Vec<(Interned<IString>, Vec<Interned<StaticFieldDesc>>)> = self
+            .link_sections()
+            .map(|(section, statics)| (section, statics.to_vec()))
+            .collect();
+        let main_module = self.main_module();
+        let u8_ptr = self.nptr(Type::Int(Int::U8));
+        let u8_idx = self.alloc_type(Type::Int(Int::U8));
+        let mut bounds = vec![];
+        let mut copies = vec![];
+        for (section, statics) in sections {
+            // Lay the statics out one after another, like a linker would.
+            let mut size = 0_u64;
+            let mut align = 1_u64;
+            let layout: Vec<_> = statics
+                .iter()
+                .map(|sfld| {
+                    let tpe = self[*sfld].tpe();
+                    let tpe_idx = self.alloc_type(tpe);
+                    let field_align = self.alignof_type(tpe_idx).max(1);
+                    align = align.max(field_align);
+                    let offset = size.next_multiple_of(field_align);
+                    let field_size = u64::from(self.sizeof_type(tpe));
+                    size = offset + field_size;
+                    (*sfld, offset, field_size)
+                })
+                .collect();
+            // Empty sections still need a valid address.
+            let buffer_size = size.max(1).next_multiple_of(align);
+            let name = format!("link_section_buffer_{}", &self[section]);
+            let class_name = self.alloc_string(name.as_str());
+            let buffer = self
+                .class_def(ClassDef::new(
+                    class_name,
+                    true,
+                    0,
+                    None,
+                    vec![],
+                    vec![],
+                    Access::Public,
+                    NonZeroU32::new(buffer_size.try_into().unwrap()),
+                    NonZeroU32::new(align.try_into().unwrap()),
+                    true,
+                ))
+                .unwrap();
+            let buffer = self.add_static(
+                Type::ClassRef(*buffer),
+                name,
+                false,
+                main_module,
+                None,
+                false,
+            );
+            let buffer = self.alloc_node(CILNode::LdStaticFieldAddress(buffer));
+            let buffer =
+                self.alloc_node(CILNode::PtrCast(buffer, Box::new(PtrCastRes::Ptr(u8_idx))));
+            for (sfld, offset, field_size) in layout {
+                if field_size == 0 {
+                    continue;
+                }
+                let dst = self.biop(buffer, Const::USize(offset), BinOp::Add);
+                let src = self.alloc_node(CILNode::LdStaticFieldAddress(sfld));
+                let src = self.alloc_node(CILNode::PtrCast(src, Box::new(PtrCastRes::Ptr(u8_idx))));
+                let len = self.alloc_node(Const::USize(field_size));
+                copies.push(self.alloc_root(CILRoot::CpBlk(Box::new((dst, src, len)))));
+            }
+            let start = self.link_section_bound(section, false);
+            let stop = self.link_section_bound(section, true);
+            debug_assert_eq!(self[start].tpe(), u8_ptr);
+            let end = self.biop(buffer, Const::USize(size), BinOp::Add);
+            bounds.push(self.alloc_root(CILRoot::SetStaticField {
+                field: start,
+                val: buffer,
+            }));
+            bounds.push(self.alloc_root(CILRoot::SetStaticField {
+                field: stop,
+                val: end,
+            }));
+        }
+        if bounds.is_empty() {
+            return;
+        }
+        let cctor = self.cctor();
+        self.modify_methodef(
+            |_, cctor| {
+                let entry = cctor
+                    .implementation_mut()
+                    .blocks_mut()
+                    .and_then(|blocks| blocks.first_mut())
+                    .expect("ERROR: the cctor has a body without blocks.");
+                for (idx, root) in bounds.into_iter().enumerate() {
+                    entry.roots_mut().insert(idx, root);
+                }
+            },
+            cctor,
+        );
+        self.add_cctor(&copies);
+    }
+}
+#[test]
+fn lower_link_sections() {
+    let mut asm = Assembly::default();
+    let main_module = asm.main_module();
+    let section = asm.alloc_string("plugins");
+    let a = asm.add_static(Type::Int(Int::U32), "a", false, main_module, None, false);
+    let b = asm.add_static(Type::Int(Int::U64), "b", false, main_module, None, false);
+    asm.add_to_link_section(section, a);
+    asm.add_to_link_section(section, b);
+    asm.lower_link_sections();
+    let cctor = asm.cctor();
+    let roots: Vec<_> = asm[cctor]
+        .iter_cil(&asm)
+        .unwrap()
+        .filter_map(|elem| match elem {
+            crate::CILIterElem::Root(root) => Some(root),
+            crate::CILIterElem::Node(_) => None,
+        })
+        .collect();
+    // Both bounds are set before the statics are copied.
+    assert!(matches!(roots[0], CILRoot::SetStaticField { .. }));
+    assert!(matches!(roots[1], CILRoot::SetStaticField { .. }));
+    assert_eq!(
+        roots
+            .iter()
+            .filter(|root| matches!(root, CILRoot::CpBlk(_)))
+            .count(),
+        2
+    );
+    // `b` is aligned to 8 bytes, so the section is 16 bytes long.
+    let buffer = asm.alloc_string("link_section_buffer_plugins");
+    let buffer = asm
+        .iter_class_defs()
+        .find(|def| def.name() == buffer)
+        .unwrap();
+    assert_eq!(buffer.explict_size().unwrap().get(), 16);
+}
@@ -7,7 +7,7 @@ use super::{
     class::ClassDefIdx,
     Access, Assembly, BasicBlock, CILIterElem, CILNode, ClassRef, FnSig, Int, IntoAsmIndex, Type,
 };
-use crate::{cilnode::PtrCastRes, iter::TpeIter};
+use crate::{cilnode::PtrCastRes, iter::TpeIter, tpe::GenericKind};
 use crate::{CILRoot, IString};
 pub type LocalId = u32;
 #[derive(Hash, PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
@@ -114,16 +114,30 @@ impl MethodRef {
             MethodKind::Constructor => &sig.inputs()[1..],
         }
     }
-    /// Returns the output of this method.
+    /// Returns the output of this method. If the output is a generic parameter, its argument is returned instead.
     pub fn output(&self, asm: &Assembly) -> Type {
         let sig = &asm[self.sig];
         match self.kind() {
-            MethodKind::Static => *sig.output(),
-            MethodKind::Instance => *sig.output(),
-            MethodKind::Virtual => *sig.output(),
+            MethodKind::Static => self.instantiate(*sig.output(), asm),
+            MethodKind::Instance => self.instantiate(*sig.output(), asm),
+            MethodKind::Virtual => self.instantiate(*sig.output(), asm),
             MethodKind::Constructor => Type::ClassRef(self.class()),
         }
     }
+    /// Replaces a generic parameter of this method(`!!N`) or of its class(`!N`) with the argument it is instantiated
+    /// with. Other types are returned unchanged.
+    pub fn instantiate(&self, tpe: Type, asm: &Assembly) -> Type {
+        let Type::PlatformGeneric(idx, kind) = tpe else {
+            return tpe;
+        };
+        let generics = match kind {
+            GenericKind::CallGeneric => self.generics(),
+            GenericKind::TypeGeneric | GenericKind::MethodGeneric => {
+                asm.class_ref(self.class()).generics()
+            }
+        };
+        generics.get(idx as usize).copied().unwrap_or(tpe)
+    }
 
     pub fn aligned_alloc(asm: &mut crate::Assembly) -> MethodRef {
         let void_ptr = asm.nptr(Type::Void);
@@ -16,6 +16,7 @@ pub use class::{ClassDef, ClassRef};
 pub use cst::Const;
 pub use field::{FieldDesc, StaticFieldDesc};
 pub use fnsig::FnSig;
+pub use inline_asm::{AsmInput, AsmOutput, AsmPiece, InlineAsm};
 pub use iter::{CILIter, CILIterElem};
 pub use method::{MethodDef, MethodDefIdx, MethodImpl, MethodRef};
 
@@ -38,8 +39,12 @@ pub mod builtins;
 pub mod c_exporter;
 /// Exports modules to IR builders. Used for quickly implementing intrinsics
 pub mod cillyir_exporter;
+/// Human-readable text format of the IR, which can be parsed back
+pub mod cillytext;
 pub mod cilnode;
 pub mod cilroot;
+/// Versioned container format of serialized assemblies
+pub mod container;
 /// Definitons of a value / byref type
 pub mod class;
 /// IR constant
@@ -50,8 +55,16 @@ pub mod field;
 pub mod fnsig;
 /// Defines hashable and equable floating point types. All NaNs are compared by bits, and -0.0 != 0.0.
 pub mod hashable;
+/// Identical code folding: merges methods with identical bodies
+pub mod icf;
 /// Exports IR to .NET bytecode
 pub mod il_exporter;
+/// GCC-style inline assembly, only supported by the C exporter
+pub mod inline_asm;
+/// Emulation of linker sections, for targets without a linker
+pub mod link_section;
+/// Interpreter for the IR, used for running assemblies without .NET or a C compiler
+pub mod interpreter;
 /// IR iterator
 pub mod iter;
 /// Exports IR to JVM bytecode
@@ -63,10 +76,14 @@ pub mod method;
 pub mod method_builder;
 /// IR optimization functions
 pub mod opt;
+/// Exports IR straight to a .NET PE file and a portable PDB, without ilasm
+pub mod pe_exporter;
 /// IR type repr
 pub mod tpe;
 /// IR typechecker
 pub mod typecheck;
+/// Lowers calls to C-variadic functions, for runtimes which do not support them
+pub mod vararg;
 #[test]
 fn types() {
     let mut asm = Assembly::default();
@@ -1,10 +1,15 @@
-use super::OptFuel;
-use crate::{
-    bimap::Interned, cilnode::MethodKind, Assembly, CILIter, CILIterElem, CILNode, CILRoot,
-    MethodDef, MethodImpl, MethodRef,
+use fxhash::FxHashMap;
+
+use super::{
+    super::{class::ClassDefIdx, method::LocalDef},
+    Analysis, OptFuel,
 };
 #[cfg(test)]
-use crate::{BasicBlock, Type};
+use crate::Type;
+use crate::{
+    bimap::Interned, cilnode::MethodKind, Assembly, BasicBlock, CILIter, CILIterElem, CILNode,
+    CILRoot, MethodDef, MethodImpl, MethodRef,
+};
 fn trivial_inline_block<'def, 'asm: 'def>(
     def: &'def MethodDef,
     asm: &'asm mut Assembly,
@@ -14,6 +19,10 @@ fn trivial_inline_block<'def, 'asm: 'def>(
     let MethodImpl::MethodBody { blocks, locals } = method else {
         return None;
     };
+    // The additional arguments of a C-variadic method only exist in its own frame.
+    if asm[def.sig()].is_variadic() {
+        return None;
+    }
     // Can only trivialy inline methods with one and exactly one block
     let [ref block] = blocks[..] else {
         return None;
@@ -133,7 +142,7 @@ fn trivial_inline_root(
     }
 
     let root = root.clone().map(asm, &mut |root,_|{
-        assert!(matches!(root,CILRoot::Nop | CILRoot::Break |CILRoot::Call(_) | CILRoot::CallI(_) | CILRoot::SetField(_) | CILRoot::SetStaticField { .. } | CILRoot::StInd(_) | CILRoot::Pop(_) | CILRoot::InitBlk(_) | CILRoot::CpBlk(_) | CILRoot::Throw(_) | CILRoot::InitObj(_, _)), "Can't inline root {root:?}");
+        assert!(matches!(root,CILRoot::Nop | CILRoot::Break |CILRoot::Call(_) | CILRoot::CallI(_) | CILRoot::SetField(_) | CILRoot::SetStaticField { .. } | CILRoot::StInd(_) | CILRoot::Pop(_) | CILRoot::InitBlk(_) | CILRoot::CpBlk(_) | CILRoot::Throw(_) | CILRoot::InitObj(_, _) | CILRoot::InlineAsm(_)), "Can't inline root {root:?}");
         root
     },&mut |node, asm| match node {
         CILNode::LdArg(arg) => asm.get_node(call_args[arg as usize]).clone(),
@@ -162,6 +171,298 @@ pub fn inline_trivial_call_root(
         None => CILRoot::call(calle, call_args),
     }
 }
+/// Callees with at most this many nodes are always inlined.
+const INLINE_SIZE_LIMIT: usize = 32;
+/// Callees called from a single place are inlined if they have at most this many nodes.
+const SINGLE_CALLER_SIZE_LIMIT: usize = 256;
+/// Inlining will not grow a method past this many nodes.
+const MAX_CALLER_SIZE: usize = 4096;
+/// The maximal number of calls inlined into a method by a single run of the inliner.
+const MAX_INLINES_PER_RUN: usize = 8;
+/// Where the value returned by an inlined method goes.
+#[derive(Clone, Copy, Debug)]
+enum RetDst {
+    /// The value is discarded.
+    Discard,
+    /// The value is stored in a local.
+    Local(u32),
+    /// The value is returned from the caller.
+    Ret,
+}
+/// The callee, arguments and return value destination of a call.
+type CallSite = (Interned<MethodRef>, Box<[Interned<CILNode>]>, RetDst);
+/// If `root` is a call the inliner can splice a method body into, returns the parts of that call.
+fn call_site(root: &CILRoot, asm: &Assembly) -> Option<CallSite> {
+    let (call, dst) = match root {
+        CILRoot::Call(info) => return Some((info.0, info.1.clone(), RetDst::Discard)),
+        CILRoot::StLoc(loc, val) => (*val, RetDst::Local(*loc)),
+        CILRoot::Pop(val) => (*val, RetDst::Discard),
+        CILRoot::Ret(val) => (*val, RetDst::Ret),
+        _ => return None,
+    };
+    let CILNode::Call(info) = &asm[call] else {
+        return None;
+    };
+    Some((info.0, info.1.clone(), dst))
+}
+/// The number of nodes and roots in `blocks`, including their handlers.
+fn body_size(blocks: &[BasicBlock], asm: &Assembly) -> usize {
+    blocks
+        .iter()
+        .flat_map(BasicBlock::iter_roots)
+        .map(|root| CILIter::new(asm[root].clone(), asm).count())
+        .sum()
+}
+/// Checks if the body of `callee` can be spliced into a method of `class`, and returns its size if so.
+fn inlinable_size(
+    callee: Interned<MethodRef>,
+    class: ClassDefIdx,
+    asm: &Assembly,
+) -> Option<usize> {
+    // The method being optimized is not in the assembly, so it will never be inlined into itself.
+    let def = asm.method_def_from_ref(callee)?;
+    // Only static methods within the same class can be inlined, to get around access.
+    if def.kind() != MethodKind::Static || def.class() != class {
+        return None;
+    }
+    // The additional arguments of a C-variadic method only exist in its own frame.
+    if asm[def.sig()].is_variadic() {
+        return None;
+    }
+    let MethodImpl::MethodBody { blocks, .. } = def.resolved_implementation(asm) else {
+        return None;
+    };
+    // Returns from within protected regions would have to become `leave`s.
+    if blocks
+        .iter()
+        .filter(|block| block.handler().is_some())
+        .flat_map(BasicBlock::iter_roots)
+        .any(|root| matches!(asm[root], CILRoot::Ret(_) | CILRoot::VoidRet))
+    {
+        return None;
+    }
+    // Recursive methods, and methods allocating on the stack(which could be inlined into a loop) can't be inlined.
+    let cant_inline = blocks
+        .iter()
+        .flat_map(BasicBlock::iter_roots)
+        .flat_map(|root| CILIter::new(asm[root].clone(), asm))
+        .any(|elem| match elem {
+            CILIterElem::Node(CILNode::Call(info)) | CILIterElem::Root(CILRoot::Call(info)) => {
+                info.0 == callee
+            }
+            CILIterElem::Node(CILNode::LocAlloc { .. } | CILNode::LocAllocAlgined { .. }) => true,
+            _ => false,
+        });
+    if cant_inline {
+        return None;
+    }
+    Some(body_size(blocks, asm))
+}
+/// Decides if a callee of `callee_size` nodes is worth inlining into a caller of `caller_size` nodes.
+fn worth_inlining(
+    callee: Interned<MethodRef>,
+    callee_size: usize,
+    caller_size: usize,
+    analysis: &Analysis,
+) -> bool {
+    if caller_size + callee_size > MAX_CALLER_SIZE {
+        return false;
+    }
+    callee_size <= INLINE_SIZE_LIMIT
+        || (analysis.call_count(callee) == Some(1) && callee_size <= SINGLE_CALLER_SIZE_LIMIT)
+}
+/// The id after the highest block id used by `blocks` or their handlers.
+fn next_block_id(blocks: &[BasicBlock]) -> u32 {
+    blocks
+        .iter()
+        .flat_map(|block| std::iter::once(block).chain(block.handler().into_iter().flatten()))
+        .map(|block| block.block_id() + 1)
+        .max()
+        .unwrap_or(0)
+}
+/// Splices the body of `callee` into `blocks`, replacing the call at `site`(block index, root index).
+/// The block containing the call is split in two: the first part stores the arguments and jumps to the callee body,
+/// which then jumps to the second part instead of returning.
+fn splice(
+    blocks: &mut Vec<BasicBlock>,
+    locals: &mut Vec<LocalDef>,
+    (block_idx, root_idx): (usize, usize),
+    args: &[Interned<CILNode>],
+    dst: RetDst,
+    callee: &MethodDef,
+    asm: &mut Assembly,
+) {
+    let MethodImpl::MethodBody {
+        blocks: callee_blocks,
+        locals: callee_locals,
+    } = callee.resolved_implementation(asm).clone()
+    else {
+        panic!("Attempted to inline a method without a body.");
+    };
+    // Each callee block gets a new id, unique within the caller.
+    let mut next_id = next_block_id(blocks);
+    let mut ids = FxHashMap::default();
+    for block in callee_blocks
+        .iter()
+        .flat_map(|block| std::iter::once(block).chain(block.handler().into_iter().flatten()))
+    {
+        ids.insert(block.block_id(), next_id);
+        next_id += 1;
+    }
+    let continuation = next_id;
+    // Arguments become locals, placed before the locals of the callee.
+    let inputs = asm[callee.sig()].inputs().to_vec();
+    let arg_base = u32::try_from(locals.len()).expect("Too many locals");
+    locals.extend(inputs.into_iter().map(|tpe| (None, asm.alloc_type(tpe))));
+    let local_base = u32::try_from(locals.len()).expect("Too many locals");
+    locals.extend(callee_locals.iter().map(|(_, tpe)| (None, *tpe)));
+    let map_id = |id: u32| ids[&id];
+    // A sub target of 0 means "no sub target", so it is kept as is.
+    let map_target =
+        |(target, sub): (u32, u32)| (map_id(target), if sub == 0 { 0 } else { map_id(sub) });
+    let map_root = |root: Interned<CILRoot>, asm: &mut Assembly| {
+        let root = asm[root].clone().map(
+            asm,
+            &mut |root, _| match root {
+                CILRoot::StLoc(loc, val) => CILRoot::StLoc(local_base + loc, val),
+                CILRoot::StArg(arg, val) => CILRoot::StLoc(arg_base + arg, val),
+                CILRoot::Branch(info) => {
+                    let (target, sub, cond) = *info;
+                    let (target, sub) = map_target((target, sub));
+                    CILRoot::Branch(Box::new((target, sub, cond)))
+                }
+                CILRoot::Switch(info) => {
+                    let (value, targets, default) = *info;
+                    let targets: Box<[_]> = targets.iter().copied().map(map_target).collect();
+                    CILRoot::switch(value, targets, map_target(default))
+                }
+                CILRoot::ExitSpecialRegion { target, source } => CILRoot::ExitSpecialRegion {
+                    target: map_id(target),
+                    source: map_id(source),
+                },
+                _ => root,
+            },
+            &mut |node, _| match node {
+                CILNode::LdLoc(loc) => CILNode::LdLoc(local_base + loc),
+                CILNode::LdLocA(loc) => CILNode::LdLocA(local_base + loc),
+                CILNode::LdArg(arg) => CILNode::LdLoc(arg_base + arg),
+                CILNode::LdArgA(arg) => CILNode::LdLocA(arg_base + arg),
+                _ => node,
+            },
+        );
+        // Returns jump to the continuation instead.
+        let jump = CILRoot::Branch(Box::new((continuation, 0, None)));
+        let roots = match (root, dst) {
+            (CILRoot::Ret(val), RetDst::Local(loc)) => vec![CILRoot::StLoc(loc, val), jump],
+            (CILRoot::Ret(val), RetDst::Discard) => vec![CILRoot::Pop(val), jump],
+            (CILRoot::VoidRet, _) => vec![jump],
+            (root, _) => vec![root],
+        };
+        roots
+            .into_iter()
+            .map(|root| asm.alloc_root(root))
+            .collect::<Vec<_>>()
+    };
+    let map_block = |block: &BasicBlock, handler, asm: &mut Assembly| {
+        let roots = block
+            .roots()
+            .iter()
+            .flat_map(|root| map_root(*root, asm))
+            .collect();
+        BasicBlock::new(roots, map_id(block.block_id()), handler)
+    };
+    let callee_blocks: Vec<_> = callee_blocks
+        .iter()
+        .map(|block| {
+            let handler = block.handler().map(|handler| {
+                handler
+                    .iter()
+                    .map(|block| map_block(block, None, asm))
+                    .collect()
+            });
+            map_block(block, handler, asm)
+        })
+        .collect();
+    // Split the calling block.
+    let entry = callee_blocks[0].block_id();
+    let roots = blocks[block_idx].roots_mut();
+    let rest = roots.split_off(root_idx + 1);
+    roots.pop();
+    for (arg, val) in (arg_base..).zip(args) {
+        roots.push(asm.alloc_root(CILRoot::StLoc(arg, *val)));
+    }
+    roots.push(asm.alloc_root(CILRoot::Branch(Box::new((entry, 0, None)))));
+    blocks.extend(callee_blocks);
+    // The roots after a return are dead.
+    if !matches!(dst, RetDst::Ret) {
+        blocks.push(BasicBlock::new(rest, continuation, None));
+    }
+}
+impl MethodDef {
+    /// Inlines whole bodies of small, or rarely called methods into this method.
+    pub(super) fn inline_bodies(
+        &mut self,
+        asm: &mut Assembly,
+        analysis: &Analysis,
+        fuel: &mut OptFuel,
+    ) {
+        let class = self.class();
+        let MethodImpl::MethodBody { blocks, locals } = self.implementation_mut() else {
+            return;
+        };
+        let mut size = body_size(blocks, asm);
+        let mut inlined = 0;
+        let mut block_idx = 0;
+        while block_idx < blocks.len() && inlined < MAX_INLINES_PER_RUN {
+            // Calls within protected regions are not inlined, since this would require turning jumps into `leave`s.
+            if blocks[block_idx].handler().is_some() {
+                block_idx += 1;
+                continue;
+            }
+            let site = blocks[block_idx]
+                .roots()
+                .iter()
+                .enumerate()
+                .find_map(|(root_idx, root)| {
+                    let (callee, args, dst) = call_site(&asm[*root], asm)?;
+                    let callee_size = inlinable_size(callee, class, asm)?;
+                    worth_inlining(callee, callee_size, size, analysis).then_some((
+                        root_idx,
+                        callee,
+                        callee_size,
+                        args,
+                        dst,
+                    ))
+                });
+            let Some((root_idx, callee, callee_size, args, dst)) = site else {
+                block_idx += 1;
+                continue;
+            };
+            let cost = u32::try_from(callee_size / 4)
+                .unwrap_or(u32::MAX)
+                .saturating_add(4);
+            if !fuel.consume(cost) {
+                return;
+            }
+            let callee = asm
+                .method_def_from_ref(callee)
+                .cloned()
+                .expect("Inlining candidate not in assembly");
+            // The calling block is split, and its new end is rescanned, since arguments may contain calls too.
+            splice(
+                blocks,
+                locals,
+                (block_idx, root_idx),
+                &args,
+                dst,
+                &callee,
+                asm,
+            );
+            size += callee_size;
+            inlined += 1;
+        }
+    }
+}
 #[test]
 fn test_inline() {
     let mut asm = Assembly::default();
@@ -12,13 +12,17 @@ use super::{
 };
 use crate::{Assembly, MethodDef};
 pub use opt_fuel::OptFuel;
+pub use passes::{Analysis, Pass, PassManager, PassStats, UnknownPass};
 pub use side_effect::*;
 mod inline;
 mod opt_fuel;
 mod opt_node;
+pub mod passes;
 mod root;
 mod side_effect;
 mod simplify_handlers;
+mod sroa;
+mod switch;
 mod test;
 pub fn opt_if_fuel<T>(new: T, original: T, fuel: &mut OptFuel) -> T {
     if fuel.consume(1) {
@@ -608,7 +612,6 @@ impl MethodImpl {
                 _ => (),
             }
         }
-        self.remove_nops(asm);
     }
     pub fn remove_nops(&mut self, asm: &mut Assembly) {
         // Optimization only suported for methods with locals
@@ -667,41 +670,14 @@ impl MethodDef {
         }
         Ok(())
     }
+    /// Optimizes this method, using the default pipeline of passes.
     pub fn optimize(
         &mut self,
         asm: &mut Assembly,
         cache: &mut SideEffectInfoCache,
         fuel: &mut OptFuel,
     ) {
-        let sig = self.sig();
-        self.implementation_mut()
-            .propagate_locals(asm, cache, fuel, sig);
-        self.implementation_mut()
-            .remove_dead_writes(asm, cache, fuel);
-        if fuel.consume(1) {
-            self.implementation_mut().realloc_locals(asm);
-        }
-
-        if fuel.consume(15) {
-            self.dedup_roots(asm);
-        }
-        if fuel.consume(1) {
-            self.opt_roots(fuel, cache, asm);
-        }
-        if fuel.consume(1) {
-            self.implementation_mut().remove_duplicate_sfi(asm);
-        }
-        if let MethodImpl::MethodBody { blocks, .. } = self.implementation_mut() {
-            if let Some(block) = linearize_blocks(blocks, asm) {
-                *blocks = vec![block];
-            }
-            // Better, not yet done OPT.
-            // linearilze_best_span(blocks, asm);
-            // Linear, so supports some additional opts.
-            if blocks.len() == 1 {}
-        }
-
-        self.remove_useless_handlers(asm, fuel, cache);
+        PassManager::default().run(self, asm, cache, fuel);
     }
     fn remove_useless_handlers(
         &mut self,
@@ -810,21 +786,43 @@ impl MethodDef {
         }
 
         // TODO: this is a hack, which makes root inlining optimizations not consume fuel.
-        let fuel = std::sync::Mutex::new(&mut *fuel);
+        let fuel = std::cell::RefCell::new(&mut *fuel);
         let locals = self.locals().map(|locs| locs.to_vec()).unwrap();
         let mut cache2 = SideEffectInfoCache::default();
         self.map_roots(
             asm,
             &mut |root, asm| {
-                let mut root_fuel = fuel.lock().unwrap();
+                let mut root_fuel = fuel.borrow_mut();
                 root_opt(root, asm, &mut root_fuel, cache, &locals)
             },
             &mut |node, asm| {
-                let mut fuel = fuel.lock().unwrap();
+                let mut fuel = fuel.borrow_mut();
                 opt_node::opt_node(node, asm, *fuel, &mut cache2)
             },
         );
     }
+    /// Inlines calls to trivial methods.
+    fn inline_calls(&mut self, fuel: &mut OptFuel, asm: &mut Assembly) {
+        if self.implementation().blocks().is_none() {
+            return;
+        }
+        let fuel = std::cell::RefCell::new(&mut *fuel);
+        self.map_roots(
+            asm,
+            &mut |root, asm| match root {
+                CILRoot::Call(info) => {
+                    inline::inline_trivial_call_root(info.0, &info.1, &mut fuel.borrow_mut(), asm)
+                }
+                _ => root,
+            },
+            &mut |node, asm| match node {
+                CILNode::Call(info) => {
+                    inline::trivial_inline_call(info.0, &info.1, &mut fuel.borrow_mut(), asm)
+                }
+                _ => node,
+            },
+        );
+    }
 }
 
 #[must_use]
@@ -991,7 +989,7 @@ fn is_linearizable(blocks: &[BasicBlock], asm: &Assembly) -> bool {
         .flat_map(|block| block.roots().iter())
         .any(|root| match &asm[*root] {
             CILRoot::Branch(info) => !is_branch_unconditional(info),
-            CILRoot::ExitSpecialRegion { .. } => true,
+            CILRoot::ExitSpecialRegion { .. } | CILRoot::Switch(_) => true,
             _ => false,
         })
     {
@@ -123,7 +123,6 @@ pub fn opt_node(
             target,
             extend,
         } => opt_int_cast(original, asm, fuel, input, target, extend),
-        CILNode::Call(info) => super::inline::trivial_inline_call(info.0, &info.1, fuel, asm),
         CILNode::LdInd {
             addr,
             tpe,
@@ -0,0 +1,455 @@
+//! The pass manager of the optimizer. Each rewrite of a method is a named [`Pass`], and a [`PassManager`] runs a
+//! pipeline of them, keeping per-pass statistics.
+//!
+//! The pipeline can be changed using enviroment variables:
+//! - `OPT_PASSES` - a comma-separated list of passes to run, in order. Defaults to [`DEFAULT_PIPELINE`].
+//! - `OPT_DISABLE_PASSES` - a comma-separated list of passes, removed from the pipeline.
+//! - `OPT_PASS_TYPECHECK` - typecheck a method after each pass, and panic, naming the pass, if the IR became invalid.
+//! - `OPT_PASS_STATS` - print the time spent in, and changes made by each pass after optimizing an assembly.
+//! - `INLINE_SIMPLE_FUNCTIONS` - add the `inline_bodies` pass, which inlines whole method bodies, to the default
+//!   pipeline.
+//! - `SPLIT_LOCAL_STRUCTS` - add the `split_structs` pass, which splits local structs into a local per field, to the
+//!   default pipeline.
+use std::time::{Duration, Instant};
+
+use fxhash::FxHashMap;
+
+use super::{linearize_blocks, OptFuel, SideEffectInfoCache};
+use crate::{
+    bimap::Interned, config, Assembly, CILIterElem, CILNode, CILRoot, MethodDef, MethodImpl,
+    MethodRef,
+};
+
+config!(OPT_PASS_TYPECHECK, bool, false);
+config!(OPT_PASS_STATS, bool, false);
+config!(
+    INLINE_SIMPLE_FUNCTIONS,
+    bool,
+    false,
+    "Allows the optimizer to inline whole bodies of small, or rarely called functions."
+);
+config!(
+    SPLIT_LOCAL_STRUCTS,
+    bool,
+    false,
+    "Allows the optimizer to split local structs, only accessed field-by-field, into a local per field."
+);
+/// Signature of a function implementing a pass.
+pub type PassFn =
+    fn(&mut MethodDef, &mut Assembly, &mut SideEffectInfoCache, &Analysis, &mut OptFuel);
+/// A single, named rewrite of a method.
+pub struct Pass {
+    name: &'static str,
+    run: PassFn,
+    /// Does this pass use the assembly-wide [`Analysis`]?
+    uses_analysis: bool,
+}
+impl Pass {
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+/// All the passes known to the optimizer.
+pub const PASSES: &[Pass] = &[
+    Pass {
+        name: "propagate_locals",
+        uses_analysis: false,
+        run: |method, asm, cache, _, fuel| {
+            let sig = method.sig();
+            method
+                .implementation_mut()
+                .propagate_locals(asm, cache, fuel, sig);
+        },
+    },
+    Pass {
+        name: "remove_dead_writes",
+        uses_analysis: false,
+        run: |method, asm, cache, _, fuel| {
+            method
+                .implementation_mut()
+                .remove_dead_writes(asm, cache, fuel);
+        },
+    },
+    Pass {
+        name: "remove_nops",
+        uses_analysis: false,
+        run: |method, asm, _, _, _| method.implementation_mut().remove_nops(asm),
+    },
+    Pass {
+        name: "realloc_locals",
+        uses_analysis: false,
+        run: |method, asm, _, _, fuel| {
+            if fuel.consume(1) {
+                method.implementation_mut().realloc_locals(asm);
+            }
+        },
+    },
+    Pass {
+        name: "dedup_roots",
+        uses_analysis: false,
+        run: |method, asm, _, _, fuel| {
+            if fuel.consume(15) {
+                method.dedup_roots(asm);
+            }
+        },
+    },
+    Pass {
+        name: "peephole",
+        uses_analysis: false,
+        run: |method, asm, cache, _, fuel| {
+            if fuel.consume(1) {
+                method.opt_roots(fuel, cache, asm);
+            }
+        },
+    },
+    Pass {
+        name: "inline",
+        uses_analysis: false,
+        run: |method, asm, _, _, fuel| method.inline_calls(fuel, asm),
+    },
+    Pass {
+        name: "inline_bodies",
+        uses_analysis: true,
+        run: |method, asm, _, analysis, fuel| method.inline_bodies(asm, analysis, fuel),
+    },
+    Pass {
+        name: "split_structs",
+        uses_analysis: false,
+        run: |method, asm, _, _, fuel| method.split_local_structs(asm, fuel),
+    },
+    Pass {
+        name: "build_switch",
+        uses_analysis: false,
+        run: |method, asm, cache, _, fuel| method.build_switches(asm, cache, fuel),
+    },
+    Pass {
+        name: "dedup_sfi",
+        uses_analysis: false,
+        run: |method, asm, _, _, fuel| {
+            if fuel.consume(1) {
+                method.implementation_mut().remove_duplicate_sfi(asm);
+            }
+        },
+    },
+    Pass {
+        name: "linearize",
+        uses_analysis: false,
+        run: |method, asm, _, _, _| {
+            if let MethodImpl::MethodBody { blocks, .. } = method.implementation_mut() {
+                if let Some(block) = linearize_blocks(blocks, asm) {
+                    *blocks = vec![block];
+                }
+            }
+        },
+    },
+    Pass {
+        name: "simplify_handlers",
+        uses_analysis: false,
+        run: |method, asm, cache, _, fuel| method.remove_useless_handlers(asm, fuel, cache),
+    },
+];
+/// The passes run by default, in order.
+pub const DEFAULT_PIPELINE: &[&str] = &[
+    "propagate_locals",
+    "remove_dead_writes",
+    "remove_nops",
+    "realloc_locals",
+    "dedup_roots",
+    "peephole",
+    "inline",
+    "build_switch",
+    "dedup_sfi",
+    "linearize",
+    "simplify_handlers",
+];
+/// The passes run by default, in order, including the ones enabled by config variables.
+fn default_pipeline() -> impl Iterator<Item = &'static str> {
+    // Optional passes, the pass they run after, and if they are enabled.
+    let optional = [
+        ("inline_bodies", "inline", *INLINE_SIMPLE_FUNCTIONS),
+        ("split_structs", "inline", *SPLIT_LOCAL_STRUCTS),
+    ];
+    DEFAULT_PIPELINE.iter().flat_map(move |name| {
+        let enabled = optional
+            .into_iter()
+            .filter(move |(_, after, enabled)| after == name && *enabled)
+            .map(|(pass, _, _)| pass);
+        std::iter::once(*name).chain(enabled)
+    })
+}
+/// Returns the pass named `name`, if it exists.
+#[must_use]
+pub fn pass_by_name(name: &str) -> Option<&'static Pass> {
+    PASSES.iter().find(|pass| pass.name == name)
+}
+/// A pass name, not matching any known pass.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownPass(pub String);
+impl std::fmt::Display for UnknownPass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown optimization pass {:?}. Known passes: ", self.0)?;
+        for (idx, pass) in PASSES.iter().enumerate() {
+            if idx != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", pass.name)?;
+        }
+        Ok(())
+    }
+}
+impl std::error::Error for UnknownPass {}
+/// Facts about the whole assembly, gathered before each round of optimization, for passes which need to know more
+/// than the method they optimize.
+#[derive(Debug, Default)]
+pub struct Analysis {
+    /// How many times each method is referenced. `None` if not gathered.
+    call_counts: Option<FxHashMap<Interned<MethodRef>, u32>>,
+}
+impl Analysis {
+    /// Analyzes `asm`.
+    #[must_use]
+    pub fn new(asm: &Assembly) -> Self {
+        let mut call_counts = FxHashMap::default();
+        let roots = asm
+            .method_defs()
+            .values()
+            .filter_map(|method| method.implementation().blocks())
+            .flatten()
+            .flat_map(crate::BasicBlock::iter_roots);
+        for root in roots {
+            for elem in crate::CILIter::new(asm[root].clone(), asm) {
+                let method = match elem {
+                    CILIterElem::Node(CILNode::Call(info))
+                    | CILIterElem::Root(CILRoot::Call(info)) => info.0,
+                    CILIterElem::Node(CILNode::LdFtn(method)) => method,
+                    _ => continue,
+                };
+                *call_counts.entry(method).or_insert(0) += 1;
+            }
+        }
+        Self {
+            call_counts: Some(call_counts),
+        }
+    }
+    /// The number of calls to `method`, including uses of its address, or `None` if unknown.
+    #[must_use]
+    pub fn call_count(&self, method: Interned<MethodRef>) -> Option<u32> {
+        self.call_counts
+            .as_ref()
+            .map(|counts| counts.get(&method).copied().unwrap_or(0))
+    }
+}
+/// Statistics of a single pass. `changes` and `time` are only collected if statistics are enabled.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PassStats {
+    /// How many times this pass was run.
+    pub runs: u64,
+    /// How many of those runs changed the method.
+    pub changes: u64,
+    /// The amount of fuel consumed by this pass.
+    pub fuel: u64,
+    /// Time spent running this pass.
+    pub time: Duration,
+}
+/// Runs a pipeline of passes over methods.
+pub struct PassManager {
+    pipeline: Vec<&'static Pass>,
+    stats: Vec<PassStats>,
+    collect_stats: bool,
+    typecheck: bool,
+    analysis: Analysis,
+}
+impl Default for PassManager {
+    fn default() -> Self {
+        Self::from_names(default_pipeline()).expect("Invalid default pipeline")
+    }
+}
+impl PassManager {
+    /// Creates a pass manager running the passes named `names`, in order.
+    /// # Errors
+    /// Returns an error if any of the names does not match a known pass.
+    pub fn from_names<'a>(names: impl IntoIterator<Item = &'a str>) -> Result<Self, UnknownPass> {
+        let pipeline = names
+            .into_iter()
+            .map(|name| pass_by_name(name).ok_or_else(|| UnknownPass(name.to_string())))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            stats: vec![PassStats::default(); pipeline.len()],
+            pipeline,
+            collect_stats: false,
+            typecheck: false,
+            analysis: Analysis::default(),
+        })
+    }
+    /// Creates a pass manager configured by the `OPT_PASSES`, `OPT_DISABLE_PASSES`, `OPT_PASS_TYPECHECK` and
+    /// `OPT_PASS_STATS` enviroment variables.
+    /// # Panics
+    /// Panics if any of the variables names an unknown pass.
+    #[must_use]
+    pub fn from_env() -> Self {
+        let split = |list: &str| {
+            list.split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_owned)
+                .collect::<Vec<_>>()
+        };
+        let pipeline = match std::env::var("OPT_PASSES") {
+            Ok(passes) => split(&passes),
+            Err(_) => default_pipeline().map(str::to_owned).collect(),
+        };
+        let disabled = std::env::var("OPT_DISABLE_PASSES")
+            .map(|passes| split(&passes))
+            .unwrap_or_default();
+        if let Some(name) = disabled.iter().find(|name| pass_by_name(name).is_none()) {
+            panic!("OPT_DISABLE_PASSES: {}", UnknownPass(name.clone()));
+        }
+        let manager = Self::from_names(
+            pipeline
+                .iter()
+                .filter(|name| !disabled.contains(name))
+                .map(String::as_str),
+        )
+        .unwrap_or_else(|err| panic!("OPT_PASSES: {err}"));
+        manager
+            .with_typecheck(*OPT_PASS_TYPECHECK)
+            .with_stats(*OPT_PASS_STATS)
+    }
+    /// Enables or disables typechecking a method after each pass.
+    #[must_use]
+    pub fn with_typecheck(mut self, typecheck: bool) -> Self {
+        self.typecheck = typecheck;
+        self
+    }
+    /// Enables or disables collecting the time spent in, and changes made by each pass. This requires copying the
+    /// method before each pass, so it is disabled by default.
+    #[must_use]
+    pub fn with_stats(mut self, collect_stats: bool) -> Self {
+        self.collect_stats = collect_stats;
+        self
+    }
+    /// The passes in this pipeline, in order.
+    pub fn pipeline(&self) -> impl Iterator<Item = &'static Pass> + '_ {
+        self.pipeline.iter().copied()
+    }
+    /// The statistics of each pass in the pipeline, in order.
+    pub fn stats(&self) -> impl Iterator<Item = (&'static str, &PassStats)> + '_ {
+        self.pipeline
+            .iter()
+            .map(|pass| pass.name)
+            .zip(self.stats.iter())
+    }
+    /// Gathers the [`Analysis`] of `asm`, if any pass in the pipeline needs it. Should be called before each round
+    /// of optimization, since the analysis is not updated by passes.
+    pub fn analyze(&mut self, asm: &Assembly) {
+        if self.pipeline.iter().any(|pass| pass.uses_analysis) {
+            self.analysis = Analysis::new(asm);
+        }
+    }
+    /// Runs the pipeline over `method`.
+    /// # Panics
+    /// If typechecking is enabled, panics if a pass turns a method which typechecked into one which does not.
+    pub fn run(
+        &mut self,
+        method: &mut MethodDef,
+        asm: &mut Assembly,
+        cache: &mut SideEffectInfoCache,
+        fuel: &mut OptFuel,
+    ) {
+        // Methods which are already invalid can't be blamed on any pass.
+        let typecheck = self.typecheck && method.typecheck(asm).is_ok();
+        for (pass, stats) in self.pipeline.iter().zip(self.stats.iter_mut()) {
+            let fuel_before = fuel.raw();
+            if self.collect_stats {
+                let original = method.implementation().clone();
+                let start = Instant::now();
+                (pass.run)(method, asm, cache, &self.analysis, fuel);
+                stats.time += start.elapsed();
+                if *method.implementation() != original {
+                    stats.changes += 1;
+                }
+            } else {
+                (pass.run)(method, asm, cache, &self.analysis, fuel);
+            }
+            stats.runs += 1;
+            stats.fuel += u64::from(fuel_before - fuel.raw());
+            if typecheck {
+                if let Err(err) = method.typecheck(asm) {
+                    panic!(
+                        "The optimization pass {:?} produced invalid IR in method {:?}: {err:?}",
+                        pass.name,
+                        &asm[method.name()]
+                    );
+                }
+            }
+        }
+    }
+}
+impl std::fmt::Display for PassManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "pass\truns\tchanges\tfuel\ttime")?;
+        for (name, stats) in self.stats() {
+            writeln!(
+                f,
+                "{name}\t{}\t{}\t{}\t{:?}",
+                stats.runs, stats.changes, stats.fuel, stats.time
+            )?;
+        }
+        Ok(())
+    }
+}
+#[test]
+fn pipeline() {
+    assert!(DEFAULT_PIPELINE
+        .iter()
+        .all(|name| pass_by_name(name).is_some()));
+    assert_eq!(
+        PassManager::from_names(["remove_nops", "linearize"])
+            .unwrap()
+            .pipeline()
+            .map(Pass::name)
+            .collect::<Vec<_>>(),
+        ["remove_nops", "linearize"]
+    );
+    assert_eq!(
+        PassManager::from_names(["remove_nops", "not_a_pass"]).err(),
+        Some(UnknownPass("not_a_pass".into()))
+    );
+}
+#[test]
+fn stats() {
+    use crate::{cilnode::MethodKind, BasicBlock, CILRoot, Type};
+    let mut asm = Assembly::default();
+    let nop = asm.alloc_root(CILRoot::Nop);
+    let ret = asm.alloc_root(CILRoot::VoidRet);
+    let main_module = asm.main_module();
+    let name = asm.alloc_string("nops");
+    let sig = asm.sig([], Type::Void);
+    let mut method = MethodDef::new(
+        crate::Access::Extern,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![nop, nop, ret], 0, None)],
+            locals: vec![],
+        },
+        vec![],
+    );
+    let mut passes = PassManager::from_names(["remove_nops", "linearize", "remove_nops"])
+        .unwrap()
+        .with_typecheck(true)
+        .with_stats(true);
+    let mut cache = SideEffectInfoCache::default();
+    let mut fuel = OptFuel::new(100);
+    passes.run(&mut method, &mut asm, &mut cache, &mut fuel);
+    let changes: Vec<_> = passes.stats().map(|(_, stats)| stats.changes).collect();
+    assert_eq!(changes, [1, 0, 0]);
+    assert!(passes.stats().all(|(_, stats)| stats.runs == 1));
+    assert_eq!(
+        method.implementation().blocks().unwrap()[0].roots(),
+        &[ret][..]
+    );
+}
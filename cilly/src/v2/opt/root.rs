@@ -1,5 +1,3 @@
-use super::inline::inline_trivial_call_root;
-
 use super::super::{cilroot::BranchCond, method::LocalDef, BinOp, CILNode, CILRoot, Const, Type};
 pub use super::opt_fuel::OptFuel;
 use super::opt_if_fuel;
@@ -27,8 +25,6 @@ pub fn root_opt(
                 }
             }
         },
-        CILRoot::Call(info) => inline_trivial_call_root(info.0, &info.1, root_fuel, asm),
-
         CILRoot::StInd(ref info) => match asm.get_node(info.0) {
             CILNode::LdLocA(loc) if asm[locals[*loc as usize].1] == info.2 => {
                 CILRoot::StLoc(*loc, info.1)
@@ -43,6 +39,25 @@ pub fn root_opt(
             CILRoot::SetField(Box::new((*field, addr, *val)))
         }
         CILRoot::InitObj(addr, tpe) => opt_init_obj(addr, tpe, asm, root_fuel),
+        CILRoot::Switch(ref switch) => {
+            let (value, targets, default) = switch.as_ref();
+            // A switch over a constant always jumps to the same target.
+            let CILNode::Const(cst) = asm.get_node(*value) else {
+                return root;
+            };
+            let Some((_, val)) = super::switch::int_const(cst.as_ref()) else {
+                return root;
+            };
+            let (target, sub_target) = usize::try_from(val)
+                .ok()
+                .and_then(|idx| targets.get(idx))
+                .unwrap_or(default);
+            opt_if_fuel(
+                CILRoot::Branch(Box::new((*target, *sub_target, None))),
+                root,
+                root_fuel,
+            )
+        }
         CILRoot::Branch(ref info) => {
             let (target, sub_target, cond) = info.as_ref();
             match cond {
@@ -0,0 +1,131 @@
+//! Scalar replacement of aggregates: splits local structs, only ever accessed field-by-field, into a local per field.
+use fxhash::FxHashMap;
+
+use super::{super::method::LocalDef, OptFuel};
+use crate::{
+    bimap::Interned, Assembly, BasicBlock, CILIter, CILIterElem, CILNode, CILRoot, ClassRef,
+    FieldDesc, MethodDef, MethodImpl, Type,
+};
+/// If `addr` is a local, or its address, returns the index of that local.
+fn local_of(addr: Interned<CILNode>, asm: &Assembly) -> Option<u32> {
+    match asm[addr] {
+        CILNode::LdLoc(loc) | CILNode::LdLocA(loc) => Some(loc),
+        _ => None,
+    }
+}
+/// If `tpe` is a valuetype defined in this assembly, whose fields don't overlap, returns it.
+fn splittable(tpe: Type, asm: &Assembly) -> Option<Interned<ClassRef>> {
+    let Type::ClassRef(cref) = tpe else {
+        return None;
+    };
+    let def = asm.class_ref_to_def(cref)?;
+    (asm[cref].is_valuetype() && asm[def].has_nonveralpping_layout()).then_some(cref)
+}
+/// Finds the locals which can be split, and the fields of them which are used.
+fn split_candidates(
+    blocks: &[BasicBlock],
+    locals: &[LocalDef],
+    asm: &Assembly,
+) -> FxHashMap<u32, Vec<Interned<FieldDesc>>> {
+    // How many times is each local used at all, and how many of those uses are field accesses.
+    let mut uses: FxHashMap<u32, usize> = FxHashMap::default();
+    let mut field_uses: FxHashMap<u32, usize> = FxHashMap::default();
+    let mut fields: FxHashMap<u32, Vec<Interned<FieldDesc>>> = FxHashMap::default();
+    let mut whole_writes = vec![];
+    let mut field_use = |addr: Interned<CILNode>, field: Interned<FieldDesc>| {
+        let Some(loc) = local_of(addr, asm) else {
+            return;
+        };
+        let Some(owner) = locals
+            .get(loc as usize)
+            .and_then(|(_, tpe)| splittable(asm[*tpe], asm))
+        else {
+            return;
+        };
+        // Accessing a field of some other type is type punning, and can't be split.
+        if asm[field].owner() != owner {
+            return;
+        }
+        *field_uses.entry(loc).or_default() += 1;
+        let fields = fields.entry(loc).or_default();
+        if !fields.contains(&field) {
+            fields.push(field);
+        }
+    };
+    for root in blocks.iter().flat_map(BasicBlock::iter_roots) {
+        for elem in CILIter::new(asm[root].clone(), asm) {
+            match elem {
+                CILIterElem::Node(CILNode::LdLoc(loc) | CILNode::LdLocA(loc)) => {
+                    *uses.entry(loc).or_default() += 1;
+                }
+                CILIterElem::Node(CILNode::LdField { addr, field }) => field_use(addr, field),
+                CILIterElem::Root(CILRoot::SetField(info)) => {
+                    // Only the address of a local can be written to.
+                    if matches!(asm[info.1], CILNode::LdLocA(_)) {
+                        field_use(info.1, info.0);
+                    }
+                }
+                CILIterElem::Root(CILRoot::StLoc(loc, _)) => whole_writes.push(loc),
+                _ => (),
+            }
+        }
+    }
+    fields.retain(|loc, _| uses.get(loc) == field_uses.get(loc) && !whole_writes.contains(loc));
+    fields
+}
+impl MethodDef {
+    /// Splits local structs, whose address never escapes, into a local per field.
+    pub(super) fn split_local_structs(&mut self, asm: &mut Assembly, fuel: &mut OptFuel) {
+        let MethodImpl::MethodBody { blocks, locals } = self.implementation_mut() else {
+            return;
+        };
+        let mut candidates: Vec<_> = split_candidates(blocks, locals, asm).into_iter().collect();
+        // Keep the order of new locals deterministic.
+        candidates.sort_unstable_by_key(|(loc, _)| *loc);
+        let mut field_locals = FxHashMap::default();
+        for (loc, fields) in candidates {
+            if !fuel.consume(2) {
+                break;
+            }
+            let struct_name = locals[loc as usize].0;
+            for field in fields {
+                let name = struct_name.map(|struct_name| {
+                    let name = format!("{}_{}", &asm[struct_name], &asm[asm[field].name()]);
+                    asm.alloc_string(name)
+                });
+                let tpe = asm.alloc_type(asm[field].tpe());
+                let new_loc = u32::try_from(locals.len()).expect("Too many locals");
+                locals.push((name, tpe));
+                field_locals.insert((loc, field), new_loc);
+            }
+        }
+        if field_locals.is_empty() {
+            return;
+        }
+        for block in blocks.iter_mut() {
+            block.map_roots(
+                asm,
+                &mut |root, asm| match root {
+                    CILRoot::SetField(ref info) => {
+                        let (field, addr, val) = info.as_ref();
+                        match local_of(*addr, asm).and_then(|loc| field_locals.get(&(loc, *field)))
+                        {
+                            Some(new_loc) => CILRoot::StLoc(*new_loc, *val),
+                            None => root,
+                        }
+                    }
+                    _ => root,
+                },
+                &mut |node, asm| match node {
+                    CILNode::LdField { addr, field } => {
+                        match local_of(addr, asm).and_then(|loc| field_locals.get(&(loc, field))) {
+                            Some(new_loc) => CILNode::LdLoc(*new_loc),
+                            None => node,
+                        }
+                    }
+                    _ => node,
+                },
+            );
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Turns chains of branches, comparing a single value against many integer constants, into [`CILRoot::Switch`]es.
+use std::collections::BTreeMap;
+
+use super::{OptFuel, SideEffectInfoCache};
+use crate::{
+    bimap::Interned,
+    cilnode::ExtendKind,
+    cilroot::{BranchCond, CmpKind},
+    Assembly, BinOp, CILNode, CILRoot, Const, Int, MethodDef,
+};
+/// The minimal number of distinct cases worth turning into a switch.
+const MIN_CASES: usize = 3;
+/// The maximal number of jump table entries per case. Limits the size of the table for sparse values.
+const MAX_ENTRIES_PER_CASE: i128 = 3;
+/// Returns the type and value of an integer constant. 128 bit ints are not supported, since they can't be switched
+/// on directly.
+pub(super) fn int_const(cst: &Const) -> Option<(Int, i128)> {
+    Some(match *cst {
+        Const::I8(val) => (Int::I8, val.into()),
+        Const::I16(val) => (Int::I16, val.into()),
+        Const::I32(val) => (Int::I32, val.into()),
+        Const::I64(val) => (Int::I64, val.into()),
+        Const::ISize(val) => (Int::ISize, val.into()),
+        Const::U8(val) => (Int::U8, val.into()),
+        Const::U16(val) => (Int::U16, val.into()),
+        Const::U32(val) => (Int::U32, val.into()),
+        Const::U64(val) => (Int::U64, val.into()),
+        Const::USize(val) => (Int::USize, val.into()),
+        _ => return None,
+    })
+}
+/// Creates a constant of type `int`, truncating `val`.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn const_of(int: Int, val: i128) -> Const {
+    match int {
+        Int::I8 => Const::I8(val as i8),
+        Int::I16 => Const::I16(val as i16),
+        Int::I32 => Const::I32(val as i32),
+        Int::I64 => Const::I64(val as i64),
+        Int::ISize => Const::ISize(val as i64),
+        Int::U8 => Const::U8(val as u8),
+        Int::U16 => Const::U16(val as u16),
+        Int::U32 => Const::U32(val as u32),
+        Int::U64 => Const::U64(val as u64),
+        Int::USize => Const::USize(val as u64),
+        Int::I128 => Const::I128(val),
+        Int::U128 => Const::U128(val as u128),
+    }
+}
+/// The value compared, the type and value of the constant it is compared against, and the target of a branch.
+type EqConstBranch = (Interned<CILNode>, Int, i128, (u32, u32));
+/// If `root` is a branch taken when a value is equal to an integer constant, returns the parts of that comparison.
+fn eq_const_branch(root: &CILRoot, asm: &Assembly) -> Option<EqConstBranch> {
+    let CILRoot::Branch(info) = root else {
+        return None;
+    };
+    let (target, sub_target, cond) = info.as_ref();
+    let (lhs, rhs) = match cond.as_ref()? {
+        BranchCond::Eq(lhs, rhs) => (*lhs, *rhs),
+        BranchCond::True(cond) => match asm[*cond] {
+            CILNode::BinOp(lhs, rhs, BinOp::Eq) => (lhs, rhs),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let (value, cst) = match (&asm[lhs], &asm[rhs]) {
+        (_, CILNode::Const(cst)) => (lhs, cst),
+        (CILNode::Const(cst), _) => (rhs, cst),
+        _ => return None,
+    };
+    let (int, cst) = int_const(cst.as_ref())?;
+    Some((value, int, cst, (*target, *sub_target)))
+}
+/// Builds the roots replacing a chain of comparisons of `value` against `cases`, followed by a jump to `default`.
+/// Returns `None` if the cases are too few, or too sparse.
+fn switch_roots(
+    value: Interned<CILNode>,
+    int: Int,
+    cases: &[(i128, (u32, u32))],
+    default: (u32, u32),
+    asm: &mut Assembly,
+) -> Option<Vec<CILRoot>> {
+    // If a value is compared against more than once, only the first comparison can be taken.
+    let mut table = BTreeMap::new();
+    for (val, target) in cases {
+        table.entry(*val).or_insert(*target);
+    }
+    if table.len() < MIN_CASES {
+        return None;
+    }
+    let (lo, hi) = (*table.first_key_value()?.0, *table.last_key_value()?.0);
+    let cases = table.len() as i128;
+    // Small, positive values are indexed directly, without subtracting the lowest one.
+    let base = if (0..cases).contains(&lo) { 0 } else { lo };
+    let len = hi - base + 1;
+    if len > cases * MAX_ENTRIES_PER_CASE {
+        return None;
+    }
+    let targets: Box<[_]> = (base..=hi)
+        .map(|val| table.get(&val).copied().unwrap_or(default))
+        .collect();
+    let mut index = value;
+    if base != 0 {
+        let base = asm.alloc_node(const_of(int, base));
+        index = asm.alloc_node(CILNode::BinOp(index, base, BinOp::Sub));
+    }
+    let mut roots = vec![];
+    // Values wider than 32 bits have to be range-checked before being truncated.
+    if !matches!(int.bits(), Some(8 | 16 | 32)) {
+        index = asm.alloc_node(CILNode::IntCast {
+            input: index,
+            target: int.as_unsigned(),
+            extend: ExtendKind::ZeroExtend,
+        });
+        let max = asm.alloc_node(const_of(int.as_unsigned(), len - 1));
+        roots.push(CILRoot::Branch(Box::new((
+            default.0,
+            default.1,
+            Some(BranchCond::Gt(index, max, CmpKind::Unsigned)),
+        ))));
+    }
+    let index = asm.alloc_node(CILNode::IntCast {
+        input: index,
+        target: Int::U32,
+        extend: ExtendKind::ZeroExtend,
+    });
+    roots.push(CILRoot::switch(index, targets, default));
+    Some(roots)
+}
+/// Replaces branch chains within `roots` with switches.
+fn build_switches_in(
+    roots: &mut Vec<Interned<CILRoot>>,
+    asm: &mut Assembly,
+    cache: &mut SideEffectInfoCache,
+    fuel: &mut OptFuel,
+) {
+    let mut start = 0;
+    while start < roots.len() {
+        let Some((value, int, _, _)) = eq_const_branch(&asm[roots[start]], asm) else {
+            start += 1;
+            continue;
+        };
+        let cases: Vec<_> = roots[start..]
+            .iter()
+            .map_while(|root| match eq_const_branch(&asm[*root], asm) {
+                Some((case_value, case_int, cst, target))
+                    if case_value == value && case_int == int =>
+                {
+                    Some((cst, target))
+                }
+                _ => None,
+            })
+            .collect();
+        let end = start + cases.len();
+        // The chain must end with an unconditional jump, and the value must not be changed by evaluating it.
+        let default = match roots.get(end).map(|root| &asm[*root]) {
+            Some(CILRoot::Branch(info)) if info.2.is_none() => Some((info.0, info.1)),
+            _ => None,
+        };
+        let replacement = default
+            .filter(|_| cases.len() >= MIN_CASES && !cache.has_side_effects(value, asm))
+            .and_then(|default| switch_roots(value, int, &cases, default, asm));
+        match replacement {
+            Some(replacement) if fuel.consume(8) => {
+                let replacement: Vec<_> = replacement
+                    .into_iter()
+                    .map(|root| asm.alloc_root(root))
+                    .collect();
+                let len = replacement.len();
+                roots.splice(start..=end, replacement);
+                start += len;
+            }
+            _ => start = end.max(start + 1),
+        }
+    }
+}
+impl MethodDef {
+    /// Replaces chains of branches comparing a value against integer constants with switches.
+    pub(super) fn build_switches(
+        &mut self,
+        asm: &mut Assembly,
+        cache: &mut SideEffectInfoCache,
+        fuel: &mut OptFuel,
+    ) {
+        let Some(blocks) = self.implementation_mut().blocks_mut() else {
+            return;
+        };
+        for block in blocks.iter_mut() {
+            build_switches_in(block.roots_mut(), asm, cache, fuel);
+            for handler in block.handler_mut().into_iter().flatten() {
+                build_switches_in(handler.roots_mut(), asm, cache, fuel);
+            }
+        }
+    }
+}
+#[test]
+fn branch_chain() {
+    use crate::BasicBlock;
+    let mut asm = Assembly::default();
+    let value = asm.alloc_node(CILNode::LdArg(0));
+    let mut roots: Vec<_> = [(5, 1), (6, 2), (8, 3), (6, 4)]
+        .into_iter()
+        .map(|(val, target)| {
+            let cst = asm.alloc_node(Const::I32(val));
+            asm.alloc_root(CILRoot::Branch(Box::new((
+                target,
+                0,
+                Some(BranchCond::Eq(value, cst)),
+            ))))
+        })
+        .collect();
+    roots.push(asm.alloc_root(CILRoot::Branch(Box::new((9, 0, None)))));
+    let mut block = BasicBlock::new(roots, 0, None);
+    build_switches_in(
+        block.roots_mut(),
+        &mut asm,
+        &mut SideEffectInfoCache::default(),
+        &mut OptFuel::new(100),
+    );
+    let [switch] = block.roots()[..] else {
+        panic!("Expected a single switch, got {:?}", block.roots());
+    };
+    let CILRoot::Switch(switch) = &asm[switch] else {
+        panic!("Expected a switch");
+    };
+    let (_, targets, default) = switch.as_ref();
+    // 5 is the lowest value, so it is subtracted. The second comparison against 6 is dead.
+    assert_eq!(&targets[..], &[(1, 0), (2, 0), (9, 0), (3, 0)]);
+    assert_eq!(*default, (9, 0));
+    let mut targets: Vec<_> = block.targets(&asm).collect();
+    targets.sort_unstable();
+    targets.dedup();
+    assert_eq!(targets, [1, 2, 3, 9]);
+}
@@ -0,0 +1,951 @@
+//! Generates `mycorrhiza` bindings from the metadata of a .NET assembly(usually a reference assembly).
+//!
+//! Every public, non-generic class becomes a type alias of `RustcCLRInteropManagedClass`, placed in modules mirroring
+//! its namespace. Its constructors and methods become typed wrappers around the interop intrinsics, gathered in a
+//! `{Class}Methods` trait implemented by the alias. A trait is used, since inherent impls on the alias are only
+//! possible within `mycorrhiza` itself. Properties are exposed trough their `get_`/`set_` accessors.
+//!
+//! Value types defined in the assembly become `RustcCLRInteropManagedStruct`s, and enums their underlying integer type.
+//! Their size is computed from their fields, so bindings using value types should be generated from an implementation
+//! assembly: reference assemblies only keep placeholder fields. Members using types which can't be expressed trough
+//! the intrinsics yet(value types from other assemblies, generics, by-refs) are skipped, and listed in a comment.
+use std::{collections::BTreeMap, fmt::Write};
+
+use fxhash::FxHashSet;
+
+use super::{
+    heaps::decompress_u32,
+    reader::{MetadataReader, PEReader},
+    tables::{CodedIndex, TableId},
+    PEError,
+};
+
+/// Rust keywords, which can't be used as identifiers.
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+/// Methods of `RustcCLRInteropManagedClass` and of traits in the prelude, which would shadow, or clash with, a wrapper.
+const RESERVED: &[&str] = &[
+    "ctor0",
+    "ctor1",
+    "ctor2",
+    "ctor3",
+    "static0",
+    "static1",
+    "static2",
+    "instance0",
+    "instance1",
+    "instance2",
+    "virt0",
+    "virt1",
+    "virt2",
+    "ctor",
+    "call_static",
+    "call_instance",
+    "call_virt",
+    "call_static_generic",
+    "call_instance_generic",
+    "call_virt_generic",
+    "to_mstring",
+    "equality",
+    "null",
+    "is_null",
+    "ld_fld",
+    "st_fld",
+    "clone",
+    "clone_from",
+    "from",
+    "into",
+    "try_from",
+    "try_into",
+    "to_owned",
+];
+/// A type used in a signature(ECMA-335 II.23.2.12).
+#[derive(Clone, Debug, PartialEq)]
+enum SigType {
+    Void,
+    Bool,
+    Char,
+    /// A primitive type, with a direct Rust equivalent.
+    Prim(&'static str),
+    String,
+    Object,
+    Class {
+        asm: String,
+        path: String,
+    },
+    /// A value type, with its size and alignment on 32 and 64 bit targets.
+    Struct {
+        asm: String,
+        path: String,
+        size: [u32; 2],
+        align: [u32; 2],
+    },
+    SzArray(Box<SigType>),
+    Ptr(Box<SigType>),
+    /// A type, which can't be expressed trough the interop intrinsics.
+    Unsupported,
+}
+/// A decoded method signature(ECMA-335 II.23.2.1).
+struct MethodSig {
+    has_this: bool,
+    /// Generic and vararg methods can't be called trough the intrinsics.
+    unsupported: bool,
+    ret: SigType,
+    params: Vec<SigType>,
+}
+/// A constructor or method of a class, about to be turned into a wrapper.
+struct Member {
+    /// The name of the wrapper, before overloads are disambiguated.
+    name: String,
+    dotnet_name: String,
+    kind: MemberKind,
+    params: Vec<(String, SigType)>,
+    ret: SigType,
+}
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MemberKind {
+    Ctor,
+    Static,
+    Instance,
+    Virtual,
+}
+/// A module of the generated bindings, corresponding to a namespace.
+#[derive(Default)]
+struct Module {
+    items: Vec<String>,
+    children: BTreeMap<String, Module>,
+}
+impl Module {
+    fn write(&self, depth: usize, out: &mut String) {
+        let indent = "    ".repeat(depth);
+        for item in &self.items {
+            for line in item.lines() {
+                if line.is_empty() {
+                    out.push('\n');
+                } else {
+                    writeln!(out, "{indent}{line}").unwrap();
+                }
+            }
+        }
+        for (name, child) in &self.children {
+            writeln!(out, "{indent}pub mod {name} {{").unwrap();
+            child.write(depth + 1, out);
+            writeln!(out, "{indent}}}").unwrap();
+        }
+    }
+}
+fn bad_image(msg: impl Into<String>) -> PEError {
+    PEError::BadImage(msg.into())
+}
+/// Reads a signature blob.
+struct SigReader<'data> {
+    data: &'data [u8],
+}
+impl SigReader<'_> {
+    fn byte(&mut self) -> Result<u8, PEError> {
+        let (first, rest) = self
+            .data
+            .split_first()
+            .ok_or_else(|| bad_image("truncated signature"))?;
+        self.data = rest;
+        Ok(*first)
+    }
+    fn peek(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+    fn compressed(&mut self) -> Result<u32, PEError> {
+        let (val, len) =
+            decompress_u32(self.data).ok_or_else(|| bad_image("bad compressed integer"))?;
+        self.data = &self.data[len..];
+        Ok(val)
+    }
+}
+/// Converts a .NET name(`GetHashCode`, `IOStream`) to snake case(`get_hash_code`, `io_stream`).
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::with_capacity(name.len() + 4);
+    for (idx, chr) in chars.iter().enumerate() {
+        if chr.is_ascii_uppercase() && idx > 0 {
+            let prev = chars[idx - 1];
+            let next_lower = chars.get(idx + 1).is_some_and(char::is_ascii_lowercase);
+            if prev.is_ascii_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_ascii_uppercase() && next_lower)
+            {
+                out.push('_');
+            }
+        }
+        out.push(chr.to_ascii_lowercase());
+    }
+    out
+}
+fn is_ident(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|chr| chr.is_ascii_alphanumeric() || chr == '_')
+        && name != "_"
+}
+/// Formats a tuple of `elems`.
+fn tuple(elems: &[impl std::fmt::Display]) -> String {
+    match elems {
+        [elem] => format!("({elem},)"),
+        _ => format!(
+            "({})",
+            elems
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+/// Appends an `_` to names, which can't be used as-is.
+fn escape(name: String) -> String {
+    if KEYWORDS.contains(&name.as_str()) || RESERVED.contains(&name.as_str()) {
+        name + "_"
+    } else {
+        name
+    }
+}
+struct Bindgen<'data, 'reader> {
+    metadata: &'reader MetadataReader<'data>,
+    /// The name of the assembly the bindings are for.
+    asm: String,
+    /// The path to `mycorrhiza`, as seen from the generated code.
+    krate: String,
+    /// `TypeDef` rows of nested types.
+    nested: FxHashSet<u32>,
+    /// Types and methods with generic parameters.
+    generic: FxHashSet<(TableId, u32)>,
+}
+impl Bindgen<'_, '_> {
+    fn str(&self, idx: u32) -> Result<&str, PEError> {
+        self.metadata.string(idx)
+    }
+    fn full_name(&self, namespace: u32, name: u32) -> Result<String, PEError> {
+        let (namespace, name) = (self.str(namespace)?, self.str(name)?);
+        Ok(if namespace.is_empty() {
+            name.to_string()
+        } else {
+            format!("{namespace}.{name}")
+        })
+    }
+    /// Resolves a `TypeDefOrRef` coded index to a reference type.
+    fn class(&self, coded: u32) -> Result<SigType, PEError> {
+        let Some((table, row)) = CodedIndex::TypeDefOrRef.decode(coded) else {
+            return Err(bad_image(format!("bad TypeDefOrRef {coded:#x}")));
+        };
+        let rows = self.metadata.rows(table);
+        let Some(data) = row.checked_sub(1).and_then(|idx| rows.get(idx as usize)) else {
+            return Err(bad_image(format!("{table:?} row {row} is out of bounds")));
+        };
+        Ok(match table {
+            TableId::TypeDef if !self.nested.contains(&row) => SigType::Class {
+                asm: self.asm.clone(),
+                path: self.full_name(data[2], data[1])?,
+            },
+            TableId::TypeRef => match CodedIndex::ResolutionScope.decode(data[0]) {
+                Some((TableId::AssemblyRef, scope)) => {
+                    let asm_ref = self
+                        .metadata
+                        .rows(TableId::AssemblyRef)
+                        .get(scope as usize - 1)
+                        .ok_or_else(|| bad_image("AssemblyRef out of bounds"))?;
+                    SigType::Class {
+                        asm: self.str(asm_ref[6])?.to_string(),
+                        path: self.full_name(data[2], data[1])?,
+                    }
+                }
+                Some((TableId::Module | TableId::ModuleRef, _)) => SigType::Class {
+                    asm: self.asm.clone(),
+                    path: self.full_name(data[2], data[1])?,
+                },
+                // Nested and exported types
+                _ => SigType::Unsupported,
+            },
+            _ => SigType::Unsupported,
+        })
+    }
+    /// Resolves a `TypeDefOrRef` coded index to a value type. Only value types defined in this assembly are supported,
+    /// since the layout of value types from other assemblies is not known.
+    fn value_type(&self, coded: u32) -> Result<SigType, PEError> {
+        let Some((TableId::TypeDef, row)) = CodedIndex::TypeDefOrRef.decode(coded) else {
+            return Ok(SigType::Unsupported);
+        };
+        let types = self.metadata.rows(TableId::TypeDef);
+        let Some(data) = row.checked_sub(1).and_then(|idx| types.get(idx as usize)) else {
+            return Err(bad_image(format!("TypeDef row {row} is out of bounds")));
+        };
+        if self.nested.contains(&row) || self.generic.contains(&(TableId::TypeDef, row)) {
+            return Ok(SigType::Unsupported);
+        }
+        let fields = self.instance_fields(row)?;
+        let base = if data[3] == 0 {
+            None
+        } else {
+            Some(self.class(data[3])?)
+        };
+        // Enums are passed as their underlying type.
+        if let Some(SigType::Class { path, .. }) = &base {
+            if path == "System.Enum" {
+                return Ok(match fields.as_slice() {
+                    [tpe @ (SigType::Prim(_) | SigType::Bool | SigType::Char)] => tpe.clone(),
+                    _ => SigType::Unsupported,
+                });
+            }
+        }
+        // The runtime decides where fields with an explicit layout go.
+        if data[0] & 0x18 == 0x10 {
+            return Ok(SigType::Unsupported);
+        }
+        let explicit_size = self
+            .metadata
+            .rows(TableId::ClassLayout)
+            .iter()
+            .find(|layout| layout[2] == row)
+            .map_or(0, |layout| layout[1]);
+        let mut size = [0_u32; 2];
+        let mut align = [1; 2];
+        for (idx, ptr_size) in [4, 8].into_iter().enumerate() {
+            for field in &fields {
+                let Some((field_size, field_align)) = Self::layout(field, ptr_size) else {
+                    return Ok(SigType::Unsupported);
+                };
+                size[idx] = size[idx].next_multiple_of(field_align) + field_size;
+                align[idx] = align[idx].max(field_align);
+            }
+            // Empty value types still take up a byte.
+            size[idx] = size[idx]
+                .max(explicit_size)
+                .max(1)
+                .next_multiple_of(align[idx]);
+        }
+        Ok(SigType::Struct {
+            asm: self.asm.clone(),
+            path: self.full_name(data[2], data[1])?,
+            size,
+            align,
+        })
+    }
+    /// Decodes the types of the instance fields of the `TypeDef` at `type_row`.
+    fn instance_fields(&self, type_row: u32) -> Result<Vec<SigType>, PEError> {
+        let types = self.metadata.rows(TableId::TypeDef);
+        let fields = self.metadata.rows(TableId::Field);
+        let start = types[type_row as usize - 1][4] as usize;
+        let end = types
+            .get(type_row as usize)
+            .map_or(fields.len() + 1, |next| next[4] as usize);
+        let mut tpes = vec![];
+        for field in fields.get(start - 1..end - 1).unwrap_or(&[]) {
+            // Static fields are not a part of the layout.
+            if field[0] & 0x10 != 0 {
+                continue;
+            }
+            let mut sig = SigReader {
+                data: self.metadata.blob(field[2])?,
+            };
+            if sig.byte()? != 0x06 {
+                return Err(bad_image("bad field signature"));
+            }
+            tpes.push(self.ty(&mut sig)?);
+        }
+        Ok(tpes)
+    }
+    /// Returns the size and alignment of a field of type `tpe`, with pointers of size `ptr_size`.
+    fn layout(tpe: &SigType, ptr_size: u32) -> Option<(u32, u32)> {
+        let size = match tpe {
+            SigType::Bool => 1,
+            SigType::Char => 2,
+            SigType::Prim("i8" | "u8") => 1,
+            SigType::Prim("i16" | "u16") => 2,
+            SigType::Prim("i32" | "u32" | "f32") => 4,
+            SigType::Prim("i64" | "u64" | "f64") => 8,
+            SigType::Prim(_)
+            | SigType::String
+            | SigType::Object
+            | SigType::Class { .. }
+            | SigType::SzArray(_)
+            | SigType::Ptr(_) => ptr_size,
+            SigType::Struct { size, align, .. } => {
+                let idx = usize::from(ptr_size == 8);
+                return Some((size[idx], align[idx]));
+            }
+            SigType::Void | SigType::Unsupported => return None,
+        };
+        Some((size, size))
+    }
+    /// Decodes a type, skipping over the parts of types, which are not supported.
+    fn ty(&self, sig: &mut SigReader) -> Result<SigType, PEError> {
+        Ok(match sig.byte()? {
+            0x01 => SigType::Void,
+            0x02 => SigType::Bool,
+            0x03 => SigType::Char,
+            0x04 => SigType::Prim("i8"),
+            0x05 => SigType::Prim("u8"),
+            0x06 => SigType::Prim("i16"),
+            0x07 => SigType::Prim("u16"),
+            0x08 => SigType::Prim("i32"),
+            0x09 => SigType::Prim("u32"),
+            0x0A => SigType::Prim("i64"),
+            0x0B => SigType::Prim("u64"),
+            0x0C => SigType::Prim("f32"),
+            0x0D => SigType::Prim("f64"),
+            0x18 => SigType::Prim("isize"),
+            0x19 => SigType::Prim("usize"),
+            0x0E => SigType::String,
+            0x1C => SigType::Object,
+            // Pointers to managed objects are not valid.
+            0x0F => match self.ty(sig)? {
+                tpe @ (SigType::Void | SigType::Bool | SigType::Prim(_) | SigType::Ptr(_)) => {
+                    SigType::Ptr(Box::new(tpe))
+                }
+                _ => SigType::Unsupported,
+            },
+            0x1D => match self.ty(sig)? {
+                SigType::Void | SigType::Unsupported => SigType::Unsupported,
+                tpe => SigType::SzArray(Box::new(tpe)),
+            },
+            0x12 => self.class(sig.compressed()?)?,
+            // By-refs, and pinned locals
+            0x10 | 0x45 => {
+                self.ty(sig)?;
+                SigType::Unsupported
+            }
+            0x11 => self.value_type(sig.compressed()?)?,
+            // Generic parameters
+            0x13 | 0x1E => {
+                sig.compressed()?;
+                SigType::Unsupported
+            }
+            // Multi-dimensional arrays
+            0x14 => {
+                self.ty(sig)?;
+                sig.compressed()?;
+                for _ in 0..2 {
+                    for _ in 0..sig.compressed()? {
+                        sig.compressed()?;
+                    }
+                }
+                SigType::Unsupported
+            }
+            0x15 => {
+                sig.byte()?;
+                sig.compressed()?;
+                for _ in 0..sig.compressed()? {
+                    self.ty(sig)?;
+                }
+                SigType::Unsupported
+            }
+            0x16 => SigType::Unsupported,
+            0x1B => {
+                self.method_sig(sig)?;
+                SigType::Unsupported
+            }
+            // Custom modifiers don't change how a type is passed.
+            0x1F | 0x20 => {
+                sig.compressed()?;
+                self.ty(sig)?
+            }
+            other => return Err(bad_image(format!("unknown element type {other:#x}"))),
+        })
+    }
+    fn method_sig(&self, sig: &mut SigReader) -> Result<MethodSig, PEError> {
+        let conv = sig.byte()?;
+        let mut unsupported = conv & 0x0F != 0;
+        if conv & 0x10 != 0 {
+            sig.compressed()?;
+            unsupported = true;
+        }
+        let count = sig.compressed()?;
+        let ret = self.ty(sig)?;
+        let mut params = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            // The sentinel, separating the fixed arguments from the variadic ones.
+            if sig.peek() == Some(0x41) {
+                sig.byte()?;
+                unsupported = true;
+            }
+            params.push(self.ty(sig)?);
+        }
+        Ok(MethodSig {
+            has_this: conv & 0x20 != 0,
+            unsupported,
+            ret,
+            params,
+        })
+    }
+    fn rust_class(&self, asm: &str, path: &str) -> String {
+        format!(
+            "{}::intrinsics::RustcCLRInteropManagedClass<\"{asm}\", \"{path}\">",
+            self.krate
+        )
+    }
+    fn rust_type(&self, tpe: &SigType) -> String {
+        match tpe {
+            SigType::Void => "()".into(),
+            SigType::Bool => "bool".into(),
+            SigType::Char => format!("{}::DotNetChar", self.krate),
+            SigType::Prim(prim) => (*prim).into(),
+            SigType::String => self.rust_class("System.Runtime", "System.String"),
+            SigType::Object => self.rust_class("System.Runtime", "System.Object"),
+            SigType::Class { asm, path } => self.rust_class(asm, path),
+            SigType::Struct {
+                asm, path, size, ..
+            } => {
+                let size = if size[0] == size[1] {
+                    size[0].to_string()
+                } else {
+                    format!(
+                        "{{ if size_of::<usize>() == 8 {{ {} }} else {{ {} }} }}",
+                        size[1], size[0]
+                    )
+                };
+                format!(
+                    "{}::intrinsics::RustcCLRInteropManagedStruct<\"{asm}\", \"{path}\", {size}>",
+                    self.krate
+                )
+            }
+            SigType::SzArray(elem) => format!(
+                "{}::intrinsics::RustcCLRInteropManagedArray<{}, 1>",
+                self.krate,
+                self.rust_type(elem)
+            ),
+            SigType::Ptr(inner) if **inner == SigType::Void => "*mut core::ffi::c_void".into(),
+            SigType::Ptr(inner) => format!("*mut {}", self.rust_type(inner)),
+            SigType::Unsupported => unreachable!("unsupported types are never emitted"),
+        }
+    }
+    /// A short name of a type, used to tell overloads apart.
+    fn short_name(tpe: &SigType) -> String {
+        match tpe {
+            SigType::Void => "void".into(),
+            SigType::Bool => "bool".into(),
+            SigType::Char => "char".into(),
+            SigType::Prim(prim) => (*prim).into(),
+            SigType::String => "string".into(),
+            SigType::Object => "object".into(),
+            SigType::Class { path, .. } | SigType::Struct { path, .. } => {
+                snake_case(path.rsplit('.').next().unwrap_or(path))
+            }
+            SigType::SzArray(elem) => format!("{}_array", Self::short_name(elem)),
+            SigType::Ptr(inner) => format!("{}_ptr", Self::short_name(inner)),
+            SigType::Unsupported => "unsupported".into(),
+        }
+    }
+    /// Collects the members of the `TypeDef` at `type_row`, which can be called trough the intrinsics, and the names of
+    /// the ones which can't.
+    fn members(
+        &self,
+        type_row: u32,
+        is_abstract: bool,
+    ) -> Result<(Vec<Member>, Vec<String>), PEError> {
+        let types = self.metadata.rows(TableId::TypeDef);
+        let methods = self.metadata.rows(TableId::MethodDef);
+        let params = self.metadata.rows(TableId::Param);
+        let start = types[type_row as usize - 1][5] as usize;
+        let end = types
+            .get(type_row as usize)
+            .map_or(methods.len() + 1, |next| next[5] as usize);
+        let mut members = vec![];
+        let mut skipped = vec![];
+        for row in start..end {
+            let method = &methods[row - 1];
+            let flags = method[2];
+            // Only public methods
+            if flags & 0x7 != 0x6 || self.generic.contains(&(TableId::MethodDef, row as u32)) {
+                continue;
+            }
+            let dotnet_name = self.str(method[3])?;
+            let is_static = flags & 0x10 != 0;
+            let kind = match dotnet_name {
+                ".ctor" if !is_static && !is_abstract => MemberKind::Ctor,
+                _ if !is_ident(dotnet_name) => continue,
+                _ if is_static => MemberKind::Static,
+                _ if flags & 0x40 != 0 => MemberKind::Virtual,
+                _ => MemberKind::Instance,
+            };
+            let sig = self.method_sig(&mut SigReader {
+                data: self.metadata.blob(method[4])?,
+            })?;
+            if sig.unsupported
+                || sig.has_this == is_static
+                || sig.ret == SigType::Unsupported
+                || sig.params.contains(&SigType::Unsupported)
+            {
+                if !skipped.iter().any(|name| name == dotnet_name) {
+                    skipped.push(dotnet_name.to_string());
+                }
+                continue;
+            }
+            // Parameter names, by sequence number.
+            let param_end = methods
+                .get(row)
+                .map_or(params.len() + 1, |next| next[5] as usize);
+            let mut names = vec![None; sig.params.len()];
+            for param in params
+                .get(method[5] as usize - 1..param_end - 1)
+                .unwrap_or(&[])
+            {
+                if let Some(name) = (param[1] as usize)
+                    .checked_sub(1)
+                    .and_then(|idx| names.get_mut(idx))
+                {
+                    *name = Some(self.str(param[2])?);
+                }
+            }
+            let mut used = FxHashSet::default();
+            let params = names
+                .into_iter()
+                .zip(sig.params)
+                .enumerate()
+                .map(|(idx, (name, tpe))| {
+                    let mut name = name
+                        .map(snake_case)
+                        .filter(|name| is_ident(name))
+                        .map_or_else(|| format!("arg{idx}"), escape);
+                    if !used.insert(name.clone()) {
+                        name = format!("{name}{idx}");
+                        used.insert(name.clone());
+                    }
+                    (name, tpe)
+                })
+                .collect();
+            members.push(Member {
+                name: if kind == MemberKind::Ctor {
+                    "new".into()
+                } else {
+                    snake_case(dotnet_name)
+                },
+                dotnet_name: dotnet_name.to_string(),
+                kind,
+                params,
+                ret: sig.ret,
+            });
+        }
+        Ok((members, skipped))
+    }
+    /// Gives every member an unique name. A member, which is not overloaded, or has no parameters, keeps its name.
+    /// Other overloads get the short names of their parameter types appended.
+    fn mangle(members: &[Member]) -> Vec<String> {
+        let mut used = FxHashSet::default();
+        members
+            .iter()
+            .map(|member| {
+                let overloaded = members
+                    .iter()
+                    .filter(|other| other.name == member.name)
+                    .count()
+                    > 1;
+                let mut name = member.name.clone();
+                if overloaded && !member.params.is_empty() {
+                    for (_, tpe) in &member.params {
+                        name.push('_');
+                        name.push_str(&Self::short_name(tpe));
+                    }
+                }
+                let name = escape(name);
+                let mut unique = name.clone();
+                let mut idx = 2;
+                while !used.insert(unique.clone()) {
+                    unique = format!("{name}_{idx}");
+                    idx += 1;
+                }
+                unique
+            })
+            .collect()
+    }
+    /// Emits the wrapper of a member, calling the matching intrinsic.
+    fn wrapper(&self, member: &Member, name: &str, path: &str, class: &str) -> String {
+        let krate = &self.krate;
+        let asm = &self.asm;
+        let dotnet_name = &member.dotnet_name;
+        let mut params: Vec<String> = member
+            .params
+            .iter()
+            .map(|(name, tpe)| format!("{name}: {}", self.rust_type(tpe)))
+            .collect();
+        let arg_types: Vec<String> = member
+            .params
+            .iter()
+            .map(|(_, tpe)| self.rust_type(tpe))
+            .collect();
+        let arg_types = tuple(&arg_types);
+        let args = tuple(
+            &member
+                .params
+                .iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>(),
+        );
+        let ret = match member.kind {
+            MemberKind::Ctor => class.to_string(),
+            _ => self.rust_type(&member.ret),
+        };
+        let call = match member.kind {
+            MemberKind::Ctor => format!("tuple_ctor::<\"{asm}\", \"{path}\", false, {arg_types}>({args})"),
+            MemberKind::Static => format!(
+                "tuple_call_static::<\"{asm}\", \"{path}\", false, \"{dotnet_name}\", {ret}, {arg_types}>({args})"
+            ),
+            MemberKind::Instance | MemberKind::Virtual => {
+                params.insert(0, "self".into());
+                let intrinsic = if member.kind == MemberKind::Virtual {
+                    "tuple_call_virt"
+                } else {
+                    "tuple_call_instance"
+                };
+                format!(
+                    "{intrinsic}::<\"{asm}\", \"{path}\", false, \"{dotnet_name}\", {ret}, Self, {arg_types}>(self, {args})"
+                )
+            }
+        };
+        let ret_arrow = if ret == "()" {
+            String::new()
+        } else {
+            format!(" -> {ret}")
+        };
+        format!(
+            "#[inline(always)]\nfn {name}({params}){ret_arrow} {{\n    {krate}::intrinsics::rustc_clr_interop_managed_{call}\n}}\n",
+            params = params.join(", "),
+        )
+    }
+    /// Emits the alias, upcast and wrappers of the `TypeDef` at `type_row`, returning its namespace and code.
+    fn class_def(
+        &self,
+        type_row: u32,
+        names: &FxHashSet<String>,
+    ) -> Result<Option<(String, String)>, PEError> {
+        let row = &self.metadata.rows(TableId::TypeDef)[type_row as usize - 1];
+        let flags = row[0];
+        let (name, namespace) = (self.str(row[1])?, self.str(row[2])?);
+        // Only public, top-level, non-generic types
+        if flags & 0x7 != 0x1
+            || self.nested.contains(&type_row)
+            || self.generic.contains(&(TableId::TypeDef, type_row))
+            || !is_ident(name)
+            || KEYWORDS.contains(&name)
+            || !(namespace.is_empty()
+                || namespace
+                    .split('.')
+                    .all(|part| is_ident(part) && !KEYWORDS.contains(&part)))
+        {
+            return Ok(None);
+        }
+        let base = if row[3] == 0 {
+            None
+        } else {
+            Some(self.class(row[3])?)
+        };
+        if let Some(SigType::Class { path, .. }) = &base {
+            if path == "System.ValueType" || path == "System.Enum" {
+                return Ok(None);
+            }
+        }
+        let path = self.full_name(row[2], row[1])?;
+        let class = self.rust_class(&self.asm, &path);
+        let mut code = format!("pub type {name} = {class};\n");
+        // Outside of `mycorrhiza`, this impl would break the orphan rules.
+        if let (
+            Some(
+                base @ SigType::Class {
+                    path: base_path, ..
+                },
+            ),
+            "crate",
+        ) = (&base, self.krate.as_str())
+        {
+            if base_path != "System.Object" {
+                let base = self.rust_type(base);
+                write!(
+                    code,
+                    "impl From<{name}> for {base} {{\n    fn from(v: {name}) -> {base} {{\n        crate::intrinsics::rustc_clr_interop_managed_checked_cast::<{base}, {name}>(v)\n    }}\n}}\n"
+                )
+                .unwrap();
+            }
+        }
+        // Interfaces and abstract classes can't be constructed.
+        let (members, skipped) = self.members(type_row, flags & 0xA0 != 0)?;
+        if !skipped.is_empty() {
+            let skipped: Vec<_> = skipped.iter().map(|name| format!("`{name}`")).collect();
+            writeln!(
+                code,
+                "// Not bound, since the interop intrinsics can't express some of their types yet: {}.",
+                skipped.join(", ")
+            )
+            .unwrap();
+        }
+        if !members.is_empty() {
+            let mut trait_name = format!("{name}Methods");
+            while names.contains(&trait_name) {
+                trait_name.push('_');
+            }
+            writeln!(code, "pub trait {trait_name}: Sized {{").unwrap();
+            for (member, member_name) in members.iter().zip(Self::mangle(&members)) {
+                for line in self.wrapper(member, &member_name, &path, name).lines() {
+                    writeln!(code, "    {line}").unwrap();
+                }
+            }
+            writeln!(code, "}}\nimpl {trait_name} for {name} {{}}").unwrap();
+        }
+        Ok(Some((namespace.to_string(), code)))
+    }
+}
+/// Generates the bindings to the assembly in `image`. `krate` is the path to `mycorrhiza`, as seen from the
+/// generated code: `crate` when regenerating the bindings of `mycorrhiza` itself, `mycorrhiza` otherwise.
+/// # Errors
+/// Returns an error if the image or its metadata is malformed.
+pub fn generate_bindings(image: &[u8], krate: &str) -> Result<String, PEError> {
+    let reader = PEReader::open(image)?;
+    let metadata = &reader.metadata;
+    let asm = metadata
+        .rows(TableId::Assembly)
+        .first()
+        .ok_or_else(|| bad_image("the image has no assembly manifest"))?;
+    let mut bindgen = Bindgen {
+        metadata,
+        asm: metadata.string(asm[7])?.to_string(),
+        krate: krate.to_string(),
+        nested: metadata
+            .rows(TableId::NestedClass)
+            .iter()
+            .map(|row| row[0])
+            .collect(),
+        generic: FxHashSet::default(),
+    };
+    for param in metadata.rows(TableId::GenericParam) {
+        if let Some(owner) = CodedIndex::TypeOrMethodDef.decode(param[2]) {
+            bindgen.generic.insert(owner);
+        }
+    }
+    // Names of all types, by namespace, so that the traits don't clash with them.
+    let mut names: BTreeMap<&str, FxHashSet<String>> = BTreeMap::new();
+    for row in metadata.rows(TableId::TypeDef) {
+        names
+            .entry(metadata.string(row[2])?)
+            .or_default()
+            .insert(metadata.string(row[1])?.to_string());
+    }
+    let mut root = Module::default();
+    for type_row in 1..=metadata.rows(TableId::TypeDef).len() as u32 {
+        let namespace =
+            metadata.string(metadata.rows(TableId::TypeDef)[type_row as usize - 1][2])?;
+        let Some((namespace, code)) = bindgen.class_def(type_row, &names[namespace])? else {
+            continue;
+        };
+        let mut module = &mut root;
+        for part in namespace.split('.').filter(|part| !part.is_empty()) {
+            module = module.children.entry(part.to_string()).or_default();
+        }
+        module.items.push(code);
+    }
+    let mut out = format!(
+        "//! Bindings to `{}`, generated by `dotnet_bindgen`. Do not edit by hand.\n#![allow(non_snake_case, non_camel_case_types, clippy::all)]\n",
+        bindgen.asm
+    );
+    root.write(0, &mut out);
+    Ok(out)
+}
+#[test]
+fn snake_case_names() {
+    assert_eq!(snake_case("GetHashCode"), "get_hash_code");
+    assert_eq!(snake_case("IOStream"), "io_stream");
+    assert_eq!(snake_case("get_Length"), "get_length");
+    assert_eq!(snake_case("ToUInt32"), "to_u_int32");
+    assert_eq!(escape(snake_case("Type")), "type_");
+    assert_eq!(escape(snake_case("Clone")), "clone_");
+}
+#[test]
+fn bindings_roundtrip() {
+    let asm = super::super::cillytext::parse(
+        r#"
+(classdef public class "Demo.Widget"
+  (method public ctor ".ctor" (sig ((class "Demo.Widget")) void) (args "this")
+    (locals) (block 0 (ret_void)))
+  (method public ctor ".ctor" (sig ((class "Demo.Widget") i32 string) void) (args "this" "size" "label")
+    (locals) (block 0 (ret_void)))
+  (method public static "Parse" (sig (string) (class "Demo.Widget")) (args "text")
+    (locals) (block 0 (throw (const string "stub"))))
+  (method public static "Parse" (sig ((array char 1)) (class "Demo.Widget")) (args "chars")
+    (locals) (block 0 (throw (const string "stub"))))
+  (method public instance "get_Size" (sig ((class "Demo.Widget")) i32) (args "this")
+    (locals) (block 0 (throw (const string "stub"))))
+  (method public virtual "Describe" (sig ((class "Demo.Widget")) string) (args "this")
+    (locals) (block 0 (throw (const string "stub"))))
+  (method public static "Sum" (sig (i32 i32 i32 i32 i32) i32) (args "a" "b" "c" "d" "e")
+    (locals) (block 0 (ret (ldarg 0))))
+  (method public static "Ref" (sig ((ptr u8)) void) (args "type")
+    (locals) (block 0 (ret_void)))
+  (method public static "Unsupported" (sig ((ref i32)) void) (args "val")
+    (locals) (block 0 (ret_void)))
+  (method public static "Offset" (sig ((valuetype "Demo.Point") (valuetype "Demo.Color")) (valuetype "Demo.Point")) (args "point" "color")
+    (locals) (block 0 (ret (ldarg 0))))
+  (method public static "Nested" (sig ((valuetype "Demo.Line")) void) (args "line")
+    (locals) (block 0 (ret_void)))
+  (method public static "Foreign" (sig ((valuetype "System.Guid" (in "System.Runtime"))) void) (args "guid")
+    (locals) (block 0 (ret_void)))
+  (method private static "Hidden" (sig () void) (args)
+    (locals) (block 0 (ret_void))))
+(classdef public class "Demo.Gadget" (extends (class "Demo.Widget")))
+(classdef public valuetype "Demo.Point" (field i32 "X") (field i32 "Y"))
+(classdef public valuetype "Demo.Line" (field (valuetype "Demo.Point") "Start") (field (ptr u8) "Label"))
+(classdef public valuetype "Demo.Color" (extends (class "System.Enum" (in "System.Runtime"))) (field u8 "value__"))
+"#,
+    )
+    .unwrap();
+    let (image, _) = super::build_image(&asm, "Demo.dll", "Demo.pdb", true).unwrap();
+    let bindings = generate_bindings(&image, "mycorrhiza").unwrap();
+    let widget = "mycorrhiza::intrinsics::RustcCLRInteropManagedClass<\"_\", \"Demo.Widget\">";
+    let string = "mycorrhiza::intrinsics::RustcCLRInteropManagedClass<\"System.Runtime\", \"System.String\">";
+    for expected in [
+        "pub mod Demo {".to_string(),
+        format!("pub type Widget = {widget};"),
+        "pub trait WidgetMethods: Sized {".into(),
+        "impl WidgetMethods for Widget {}".into(),
+        format!("pub type Gadget = mycorrhiza::intrinsics::RustcCLRInteropManagedClass<\"_\", \"Demo.Gadget\">;"),
+        // Overloads
+        "fn new() -> Widget {".into(),
+        format!("fn new_i32_string(size: i32, label: {string}) -> Widget {{"),
+        format!("mycorrhiza::intrinsics::rustc_clr_interop_managed_tuple_ctor::<\"_\", \"Demo.Widget\", false, (i32, {string})>((size, label))"),
+        format!("fn parse_string(text: {string}) -> {widget} {{"),
+        format!("fn parse_char_array(chars: mycorrhiza::intrinsics::RustcCLRInteropManagedArray<mycorrhiza::DotNetChar, 1>) -> {widget} {{"),
+        // Property accessors, instance and virtual methods
+        "fn get_size(self) -> i32 {".into(),
+        "mycorrhiza::intrinsics::rustc_clr_interop_managed_tuple_call_instance::<\"_\", \"Demo.Widget\", false, \"get_Size\", i32, Self, ()>(self, ())".into(),
+        format!("mycorrhiza::intrinsics::rustc_clr_interop_managed_tuple_call_virt::<\"_\", \"Demo.Widget\", false, \"Describe\", {string}, Self, ()>(self, ())"),
+        // Any number of arguments
+        "fn sum(a: i32, b: i32, c: i32, d: i32, e: i32) -> i32 {".into(),
+        "rustc_clr_interop_managed_tuple_call_static::<\"_\", \"Demo.Widget\", false, \"Sum\", i32, (i32, i32, i32, i32, i32)>((a, b, c, d, e))".into(),
+        // Keywords are escaped.
+        "fn ref_(type_: *mut u8) {".into(),
+        // Value types are passed as managed structs, and enums as their underlying type.
+        "fn offset(point: mycorrhiza::intrinsics::RustcCLRInteropManagedStruct<\"_\", \"Demo.Point\", 8>, color: u8) -> mycorrhiza::intrinsics::RustcCLRInteropManagedStruct<\"_\", \"Demo.Point\", 8> {".into(),
+        "fn nested(line: mycorrhiza::intrinsics::RustcCLRInteropManagedStruct<\"_\", \"Demo.Line\", { if size_of::<usize>() == 8 { 16 } else { 12 } }>) {".into(),
+        // Skipped members are listed.
+        "// Not bound, since the interop intrinsics can't express some of their types yet: `Unsupported`, `Foreign`.".into(),
+    ] {
+        assert!(bindings.contains(&expected), "missing {expected:?} in:\n{bindings}");
+    }
+    // By-refs, value types from other assemblies and private methods are skipped, value types don't get an alias, and
+    // `From` is only emitted inside `mycorrhiza`.
+    for unexpected in [
+        "fn unsupported",
+        "fn foreign",
+        "hidden",
+        "Hidden",
+        "type Point",
+        "impl From",
+    ] {
+        assert!(
+            !bindings.contains(unexpected),
+            "unexpected {unexpected:?} in:\n{bindings}"
+        );
+    }
+    let bindings = generate_bindings(&image, "crate").unwrap();
+    assert!(bindings.contains("impl From<Gadget> for crate::intrinsics::RustcCLRInteropManagedClass<\"_\", \"Demo.Widget\">"));
+}
@@ -0,0 +1,205 @@
+//! Metadata heaps(ECMA-335 II.24.2.2 - II.24.2.5), and the compressed integer encoding used by them.
+use fxhash::FxHashMap;
+
+/// Appends `val` encoded as a compressed, unsigned integer(ECMA-335 II.23.2).
+pub(super) fn compress_u32(val: u32, out: &mut Vec<u8>) {
+    match val {
+        0..0x80 => out.push(val as u8),
+        0x80..0x4000 => out.extend((0x8000 | val as u16).to_be_bytes()),
+        0x4000..0x2000_0000 => out.extend((0xC000_0000 | val).to_be_bytes()),
+        _ => panic!("{val:#x} is too big to be compressed."),
+    }
+}
+/// Appends `val` encoded as a compressed, signed integer(ECMA-335 II.23.2).
+pub(super) fn compress_i32(val: i32, out: &mut Vec<u8>) {
+    // The value is truncated to 7, 14 or 29 bits, and then rotated left, so that the sign bit is last.
+    let rotate = |bits: u32| {
+        let mask = (1 << bits) - 1;
+        let val = val as u32 & mask;
+        ((val << 1) | (val >> (bits - 1))) & mask
+    };
+    match val {
+        -0x40..0x40 => out.push(rotate(7) as u8),
+        -0x2000..0x2000 => out.extend((0x8000 | rotate(14) as u16).to_be_bytes()),
+        -0x1000_0000..0x1000_0000 => out.extend((0xC000_0000 | rotate(29)).to_be_bytes()),
+        _ => panic!("{val} is too big to be compressed."),
+    }
+}
+/// Reads a compressed, unsigned integer, returning it and the number of bytes it took up.
+pub(super) fn decompress_u32(data: &[u8]) -> Option<(u32, usize)> {
+    let first = *data.first()?;
+    match first {
+        0..0x80 => Some((first.into(), 1)),
+        0x80..0xC0 => Some((
+            u32::from(u16::from_be_bytes(data.get(..2)?.try_into().ok()?) & 0x3FFF),
+            2,
+        )),
+        0xC0..0xE0 => Some((
+            u32::from_be_bytes(data.get(..4)?.try_into().ok()?) & 0x1FFF_FFFF,
+            4,
+        )),
+        _ => None,
+    }
+}
+/// Reads a compressed, signed integer, returning it and the number of bytes it took up.
+pub(super) fn decompress_i32(data: &[u8]) -> Option<(i32, usize)> {
+    let (raw, len) = decompress_u32(data)?;
+    let bits = match len {
+        1 => 7,
+        2 => 14,
+        _ => 29,
+    };
+    // Undo the rotation, and sign-extend the result.
+    let val = (raw >> 1) | ((raw & 1) << (bits - 1));
+    let shift = 32 - bits;
+    Some((((val << shift) as i32) >> shift, len))
+}
+/// Pads `data` with zeroes, until its length is a multiple of 4.
+pub(super) fn pad4(data: &mut Vec<u8>) {
+    data.resize(data.len().next_multiple_of(4), 0);
+}
+/// The `#Strings`, `#US`, `#Blob` and `#GUID` heaps of a metadata image. All entries are deduplicated.
+pub(super) struct Heaps {
+    strings: Vec<u8>,
+    string_map: FxHashMap<Box<str>, u32>,
+    user_strings: Vec<u8>,
+    user_string_map: FxHashMap<Box<str>, u32>,
+    blobs: Vec<u8>,
+    blob_map: FxHashMap<Box<[u8]>, u32>,
+    guids: Vec<[u8; 16]>,
+}
+impl Default for Heaps {
+    fn default() -> Self {
+        // Index 0 of the string, user string and blob heaps is always the empty entry.
+        Self {
+            strings: vec![0],
+            string_map: FxHashMap::default(),
+            user_strings: vec![0],
+            user_string_map: FxHashMap::default(),
+            blobs: vec![0],
+            blob_map: FxHashMap::default(),
+            guids: vec![],
+        }
+    }
+}
+impl Heaps {
+    /// Adds a string to the `#Strings` heap, returning its index.
+    pub fn string(&mut self, string: &str) -> u32 {
+        if string.is_empty() {
+            return 0;
+        }
+        if let Some(idx) = self.string_map.get(string) {
+            return *idx;
+        }
+        debug_assert!(!string.contains('\0'), "{string:?} contains a nul byte");
+        let idx = self.strings.len() as u32;
+        self.strings.extend(string.as_bytes());
+        self.strings.push(0);
+        self.string_map.insert(string.into(), idx);
+        idx
+    }
+    /// Adds a string to the `#US` heap, returning its index.
+    pub fn user_string(&mut self, string: &str) -> u32 {
+        if let Some(idx) = self.user_string_map.get(string) {
+            return *idx;
+        }
+        let idx = self.user_strings.len() as u32;
+        let utf16: Vec<u16> = string.encode_utf16().collect();
+        compress_u32(utf16.len() as u32 * 2 + 1, &mut self.user_strings);
+        // The final byte marks strings, which contain characters that need special handling(II.24.2.4).
+        let mut special = false;
+        for char in utf16 {
+            self.user_strings.extend(char.to_le_bytes());
+            special |=
+                char > 0xFF || matches!(char, 0x01..=0x08 | 0x0E..=0x1F | 0x27 | 0x2D | 0x7F);
+        }
+        self.user_strings.push(special.into());
+        self.user_string_map.insert(string.into(), idx);
+        idx
+    }
+    /// Adds a blob to the `#Blob` heap, returning its index.
+    pub fn blob(&mut self, blob: &[u8]) -> u32 {
+        if blob.is_empty() {
+            return 0;
+        }
+        if let Some(idx) = self.blob_map.get(blob) {
+            return *idx;
+        }
+        let idx = self.blobs.len() as u32;
+        compress_u32(blob.len() as u32, &mut self.blobs);
+        self.blobs.extend(blob);
+        self.blob_map.insert(blob.into(), idx);
+        idx
+    }
+    /// Adds a GUID to the `#GUID` heap, returning its(1-based) index.
+    pub fn guid(&mut self, guid: [u8; 16]) -> u32 {
+        match self.guids.iter().position(|old| *old == guid) {
+            Some(idx) => idx as u32 + 1,
+            None => {
+                self.guids.push(guid);
+                self.guids.len() as u32
+            }
+        }
+    }
+    /// Replaces the GUID at the(1-based) index `idx`.
+    pub fn set_guid(&mut self, idx: u32, guid: [u8; 16]) {
+        self.guids[idx as usize - 1] = guid;
+    }
+    /// Returns the heap size flags of the `#~` stream.
+    pub fn size_flags(&self) -> u8 {
+        u8::from(self.strings.len() > 0xFFFF)
+            | (u8::from(self.guids.len() > 0xFFFF) << 1)
+            | (u8::from(self.blobs.len() > 0xFFFF) << 2)
+    }
+    /// Returns the name and contents of every non-empty heap, padded to 4 bytes.
+    pub fn streams(&self) -> Vec<(&'static str, Vec<u8>)> {
+        let mut streams = vec![("#Strings", self.strings.clone())];
+        if self.user_strings.len() > 1 {
+            streams.push(("#US", self.user_strings.clone()));
+        }
+        streams.push(("#GUID", self.guids.concat()));
+        streams.push(("#Blob", self.blobs.clone()));
+        for (_, data) in &mut streams {
+            pad4(data);
+        }
+        streams
+    }
+}
+#[test]
+fn compressed_ints() {
+    // Examples from ECMA-335 II.23.2
+    let mut out = vec![];
+    compress_u32(0x03, &mut out);
+    compress_u32(0x80, &mut out);
+    compress_u32(0x4000, &mut out);
+    assert_eq!(out, [0x03, 0x80, 0x80, 0xC0, 0x00, 0x40, 0x00]);
+    let mut out = vec![];
+    compress_i32(3, &mut out);
+    compress_i32(-3, &mut out);
+    compress_i32(-8192, &mut out);
+    compress_i32(-268_435_456, &mut out);
+    assert_eq!(out, [0x06, 0x7B, 0x80, 0x01, 0xC0, 0x00, 0x00, 0x01]);
+    for val in [0, 1, 0x7F, 0x80, 0x3FFF, 0x4000, 0x1FFF_FFFF] {
+        let mut out = vec![];
+        compress_u32(val, &mut out);
+        assert_eq!(decompress_u32(&out), Some((val, out.len())));
+    }
+    for val in [
+        0,
+        1,
+        -1,
+        63,
+        -64,
+        64,
+        -65,
+        0x1FFF,
+        -0x2000,
+        0x2000,
+        0x0FFF_FFFF,
+        -0x1000_0000,
+    ] {
+        let mut out = vec![];
+        compress_i32(val, &mut out);
+        assert_eq!(decompress_i32(&out), Some((val, out.len())), "{val}");
+    }
+}
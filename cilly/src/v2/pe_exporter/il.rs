@@ -0,0 +1,366 @@
+//! Encoding of CIL instructions(ECMA-335 III), and method bodies(ECMA-335 II.25.4).
+use fxhash::FxHashMap;
+
+use super::{heaps::pad4, PEError};
+
+/// Opcodes used by the exporter. Two-byte opcodes are stored with their `0xFE` prefix.
+#[allow(dead_code)]
+pub(super) mod op {
+    pub const NOP: u16 = 0x00;
+    pub const BREAK: u16 = 0x01;
+    pub const LDARG_0: u16 = 0x02;
+    pub const LDLOC_0: u16 = 0x06;
+    pub const STLOC_0: u16 = 0x0A;
+    pub const LDARG_S: u16 = 0x0E;
+    pub const LDARGA_S: u16 = 0x0F;
+    pub const STARG_S: u16 = 0x10;
+    pub const LDLOC_S: u16 = 0x11;
+    pub const LDLOCA_S: u16 = 0x12;
+    pub const STLOC_S: u16 = 0x13;
+    pub const LDNULL: u16 = 0x14;
+    pub const LDC_I4_M1: u16 = 0x15;
+    pub const LDC_I4_0: u16 = 0x16;
+    pub const LDC_I4_S: u16 = 0x1F;
+    pub const LDC_I4: u16 = 0x20;
+    pub const LDC_I8: u16 = 0x21;
+    pub const LDC_R4: u16 = 0x22;
+    pub const LDC_R8: u16 = 0x23;
+    pub const DUP: u16 = 0x25;
+    pub const POP: u16 = 0x26;
+    pub const CALL: u16 = 0x28;
+    pub const CALLI: u16 = 0x29;
+    pub const RET: u16 = 0x2A;
+    pub const BR: u16 = 0x38;
+    pub const BRFALSE: u16 = 0x39;
+    pub const BRTRUE: u16 = 0x3A;
+    pub const BEQ: u16 = 0x3B;
+    pub const BGE: u16 = 0x3C;
+    pub const BGT: u16 = 0x3D;
+    pub const BLE: u16 = 0x3E;
+    pub const BLT: u16 = 0x3F;
+    pub const BNE_UN: u16 = 0x40;
+    pub const BGE_UN: u16 = 0x41;
+    pub const BGT_UN: u16 = 0x42;
+    pub const BLE_UN: u16 = 0x43;
+    pub const BLT_UN: u16 = 0x44;
+    pub const SWITCH: u16 = 0x45;
+    pub const LDIND_I1: u16 = 0x46;
+    pub const LDIND_U1: u16 = 0x47;
+    pub const LDIND_I2: u16 = 0x48;
+    pub const LDIND_U2: u16 = 0x49;
+    pub const LDIND_I4: u16 = 0x4A;
+    pub const LDIND_U4: u16 = 0x4B;
+    pub const LDIND_I8: u16 = 0x4C;
+    pub const LDIND_I: u16 = 0x4D;
+    pub const LDIND_R4: u16 = 0x4E;
+    pub const LDIND_R8: u16 = 0x4F;
+    pub const LDIND_REF: u16 = 0x50;
+    pub const STIND_REF: u16 = 0x51;
+    pub const STIND_I1: u16 = 0x52;
+    pub const STIND_I2: u16 = 0x53;
+    pub const STIND_I4: u16 = 0x54;
+    pub const STIND_I8: u16 = 0x55;
+    pub const STIND_R4: u16 = 0x56;
+    pub const STIND_R8: u16 = 0x57;
+    pub const ADD: u16 = 0x58;
+    pub const SUB: u16 = 0x59;
+    pub const MUL: u16 = 0x5A;
+    pub const DIV: u16 = 0x5B;
+    pub const DIV_UN: u16 = 0x5C;
+    pub const REM: u16 = 0x5D;
+    pub const REM_UN: u16 = 0x5E;
+    pub const AND: u16 = 0x5F;
+    pub const OR: u16 = 0x60;
+    pub const XOR: u16 = 0x61;
+    pub const SHL: u16 = 0x62;
+    pub const SHR: u16 = 0x63;
+    pub const SHR_UN: u16 = 0x64;
+    pub const NEG: u16 = 0x65;
+    pub const NOT: u16 = 0x66;
+    pub const CONV_I1: u16 = 0x67;
+    pub const CONV_I2: u16 = 0x68;
+    pub const CONV_I4: u16 = 0x69;
+    pub const CONV_I8: u16 = 0x6A;
+    pub const CONV_R4: u16 = 0x6B;
+    pub const CONV_R8: u16 = 0x6C;
+    pub const CONV_U4: u16 = 0x6D;
+    pub const CONV_U8: u16 = 0x6E;
+    pub const CALLVIRT: u16 = 0x6F;
+    pub const CPOBJ: u16 = 0x70;
+    pub const LDOBJ: u16 = 0x71;
+    pub const LDSTR: u16 = 0x72;
+    pub const NEWOBJ: u16 = 0x73;
+    pub const CASTCLASS: u16 = 0x74;
+    pub const ISINST: u16 = 0x75;
+    pub const CONV_R_UN: u16 = 0x76;
+    pub const THROW: u16 = 0x7A;
+    pub const LDFLD: u16 = 0x7B;
+    pub const LDFLDA: u16 = 0x7C;
+    pub const STFLD: u16 = 0x7D;
+    pub const LDSFLD: u16 = 0x7E;
+    pub const LDSFLDA: u16 = 0x7F;
+    pub const STSFLD: u16 = 0x80;
+    pub const STOBJ: u16 = 0x81;
+    pub const LDLEN: u16 = 0x8E;
+    pub const LDELEM_REF: u16 = 0x9A;
+    pub const UNBOX_ANY: u16 = 0xA5;
+    pub const LDTOKEN: u16 = 0xD0;
+    pub const CONV_U2: u16 = 0xD1;
+    pub const CONV_U1: u16 = 0xD2;
+    pub const CONV_I: u16 = 0xD3;
+    pub const LEAVE: u16 = 0xDD;
+    pub const STIND_I: u16 = 0xDF;
+    pub const CONV_U: u16 = 0xE0;
+    pub const CEQ: u16 = 0xFE01;
+    pub const CGT: u16 = 0xFE02;
+    pub const CGT_UN: u16 = 0xFE03;
+    pub const CLT: u16 = 0xFE04;
+    pub const CLT_UN: u16 = 0xFE05;
+    pub const LDFTN: u16 = 0xFE06;
+    pub const LDARG: u16 = 0xFE09;
+    pub const LDARGA: u16 = 0xFE0A;
+    pub const STARG: u16 = 0xFE0B;
+    pub const LDLOC: u16 = 0xFE0C;
+    pub const LDLOCA: u16 = 0xFE0D;
+    pub const STLOC: u16 = 0xFE0E;
+    pub const LOCALLOC: u16 = 0xFE0F;
+    pub const VOLATILE: u16 = 0xFE13;
+    pub const INITOBJ: u16 = 0xFE15;
+    pub const CPBLK: u16 = 0xFE17;
+    pub const INITBLK: u16 = 0xFE18;
+    pub const RETHROW: u16 = 0xFE1A;
+    pub const SIZEOF: u16 = 0xFE1C;
+}
+/// The kind of operand an instruction takes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Operand {
+    None,
+    I8,
+    U8,
+    U16,
+    I32,
+    I64,
+    F32,
+    F64,
+    Token,
+    /// A 1-byte branch offset
+    ShortTarget,
+    /// A 4-byte branch offset
+    Target,
+    /// A jump table
+    Switch,
+}
+/// Returns the operand of the instruction `opcode`, or `None` if the opcode is not valid.
+pub(super) fn operand(opcode: u16) -> Option<Operand> {
+    Some(match opcode {
+        0x00..=0x0D | 0x14..=0x1E | 0x25 | 0x26 | 0x2A => Operand::None,
+        0x0E..=0x13 => Operand::U8,
+        0x1F => Operand::I8,
+        0x20 => Operand::I32,
+        0x21 => Operand::I64,
+        0x22 => Operand::F32,
+        0x23 => Operand::F64,
+        0x27..=0x29 => Operand::Token,
+        0x2B..=0x37 | 0xDE => Operand::ShortTarget,
+        0x38..=0x44 | 0xDD => Operand::Target,
+        0x45 => Operand::Switch,
+        0x46..=0x6E | 0x76 | 0x7A | 0x82..=0x8B | 0x8E | 0x90..=0xA2 | 0xB3..=0xBA | 0xC3 => {
+            Operand::None
+        }
+        0x6F..=0x75 | 0x79 | 0x7B..=0x81 | 0x8C | 0x8D | 0x8F | 0xA3..=0xA5 => Operand::Token,
+        0xC2 | 0xC6 | 0xD0 => Operand::Token,
+        0xD1..=0xDC | 0xDF | 0xE0 => Operand::None,
+        0xFE00..=0xFE05 | 0xFE0F | 0xFE11 | 0xFE13 | 0xFE14 | 0xFE17 | 0xFE18 => Operand::None,
+        0xFE1A | 0xFE1D | 0xFE1E => Operand::None,
+        0xFE06 | 0xFE07 | 0xFE15 | 0xFE16 | 0xFE1C => Operand::Token,
+        0xFE09..=0xFE0E => Operand::U16,
+        0xFE12 | 0xFE19 => Operand::U8,
+        _ => return None,
+    })
+}
+/// A branch target within a method body. Mirrors the labels used by the IL exporter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(super) enum Label {
+    /// `bb{id}`: the start of a block.
+    Block(u32),
+    /// `h{owner}_{id}`: a block inside the handler of `owner`, or a jump out of that handler.
+    Handler(u32, u32),
+    /// `jp{source}_{target}`: a jump out of the protected block `source`.
+    Jump(u32, u32),
+}
+/// A `catch System.Object` exception handling clause.
+pub(super) struct Clause {
+    pub try_start: u32,
+    pub try_end: u32,
+    pub handler_start: u32,
+    pub handler_end: u32,
+}
+/// Builds the code of a method body.
+#[derive(Default)]
+pub(super) struct IlWriter {
+    code: Vec<u8>,
+    labels: FxHashMap<Label, u32>,
+    /// Position of the offset, the position its relative to, and the target.
+    fixups: Vec<(usize, usize, Label)>,
+    clauses: Vec<Clause>,
+}
+impl IlWriter {
+    /// Offset of the next instruction.
+    pub fn offset(&self) -> u32 {
+        self.code.len() as u32
+    }
+    pub fn op(&mut self, opcode: u16) {
+        if opcode > 0xFF {
+            self.code.extend(opcode.to_be_bytes());
+        } else {
+            self.code.push(opcode as u8);
+        }
+    }
+    pub fn op_u8(&mut self, opcode: u16, val: u8) {
+        self.op(opcode);
+        self.code.push(val);
+    }
+    pub fn op_u16(&mut self, opcode: u16, val: u16) {
+        self.op(opcode);
+        self.code.extend(val.to_le_bytes());
+    }
+    pub fn op_i32(&mut self, opcode: u16, val: i32) {
+        self.op(opcode);
+        self.code.extend(val.to_le_bytes());
+    }
+    pub fn op_i64(&mut self, opcode: u16, val: i64) {
+        self.op(opcode);
+        self.code.extend(val.to_le_bytes());
+    }
+    pub fn op_bytes(&mut self, opcode: u16, bytes: &[u8]) {
+        self.op(opcode);
+        self.code.extend(bytes);
+    }
+    pub fn op_token(&mut self, opcode: u16, token: u32) {
+        self.op(opcode);
+        self.code.extend(token.to_le_bytes());
+    }
+    /// Emits a (long form) branch to `label`.
+    pub fn branch(&mut self, opcode: u16, label: Label) {
+        self.op(opcode);
+        self.fixups
+            .push((self.code.len(), self.code.len() + 4, label));
+        self.code.extend(0_i32.to_le_bytes());
+    }
+    /// Emits a jump table, with offsets relative to the end of the whole instruction.
+    pub fn switch(&mut self, labels: &[Label]) {
+        self.op(op::SWITCH);
+        self.code.extend((labels.len() as u32).to_le_bytes());
+        let end = self.code.len() + labels.len() * 4;
+        for label in labels {
+            self.fixups.push((self.code.len(), end, *label));
+            self.code.extend(0_i32.to_le_bytes());
+        }
+    }
+    /// Places `label` at the current offset.
+    pub fn label(&mut self, label: Label) -> Result<(), PEError> {
+        if self.labels.insert(label, self.offset()).is_some() {
+            return Err(PEError::Unsupported(format!(
+                "label {label:?} defined twice"
+            )));
+        }
+        Ok(())
+    }
+    pub fn clause(&mut self, clause: Clause) {
+        self.clauses.push(clause);
+    }
+    /// Resolves all the branches, and encodes the whole method body, with its header and exception handling
+    /// clauses. `catch_token` is the token of the type caught by all the clauses.
+    pub fn finish(
+        mut self,
+        max_stack: u16,
+        locals_token: u32,
+        catch_token: u32,
+    ) -> Result<Vec<u8>, PEError> {
+        for (pos, base, label) in std::mem::take(&mut self.fixups) {
+            let Some(target) = self.labels.get(&label) else {
+                return Err(PEError::Unsupported(format!(
+                    "branch to an undefined label {label:?}"
+                )));
+            };
+            let offset = *target as i32 - base as i32;
+            self.code[pos..pos + 4].copy_from_slice(&offset.to_le_bytes());
+        }
+        let mut body = vec![];
+        // The tiny format can only be used for small methods, without locals or exception handlers.
+        if self.code.len() < 64 && max_stack <= 8 && locals_token == 0 && self.clauses.is_empty() {
+            body.push(((self.code.len() as u8) << 2) | 0x2);
+            body.extend(self.code);
+            return Ok(body);
+        }
+        let flags: u16 = 0x3003 | if self.clauses.is_empty() { 0 } else { 0x8 };
+        body.extend(flags.to_le_bytes());
+        body.extend(max_stack.to_le_bytes());
+        body.extend((self.code.len() as u32).to_le_bytes());
+        body.extend(locals_token.to_le_bytes());
+        body.extend(self.code);
+        if !self.clauses.is_empty() {
+            pad4(&mut body);
+            // A fat exception handling section.
+            let size = self.clauses.len() as u32 * 24 + 4;
+            body.extend((0x41 | (size << 8)).to_le_bytes());
+            for clause in self.clauses {
+                // Flags: `COR_ILEXCEPTION_CLAUSE_EXCEPTION`
+                body.extend(0_u32.to_le_bytes());
+                body.extend(clause.try_start.to_le_bytes());
+                body.extend((clause.try_end - clause.try_start).to_le_bytes());
+                body.extend(clause.handler_start.to_le_bytes());
+                body.extend((clause.handler_end - clause.handler_start).to_le_bytes());
+                body.extend(catch_token.to_le_bytes());
+            }
+        }
+        Ok(body)
+    }
+}
+#[test]
+fn method_body() {
+    let mut il = IlWriter::default();
+    il.label(Label::Block(0)).unwrap();
+    il.op(op::LDC_I4_0);
+    il.branch(op::BRTRUE, Label::Block(0));
+    il.op(op::RET);
+    // Tiny header, with a backwards branch.
+    assert_eq!(
+        il.finish(8, 0, 0).unwrap(),
+        [(7 << 2) | 2, 0x16, 0x3A, 0xFA, 0xFF, 0xFF, 0xFF, 0x2A]
+    );
+    let mut il = IlWriter::default();
+    il.label(Label::Block(0)).unwrap();
+    il.op(op::LDC_I4_0);
+    il.switch(&[Label::Block(1), Label::Block(0)]);
+    il.label(Label::Block(1)).unwrap();
+    il.op(op::RET);
+    // Switch offsets are relative to the end of the jump table.
+    assert_eq!(
+        il.finish(8, 0, 0).unwrap(),
+        [
+            (15 << 2) | 2,
+            0x16,
+            0x45,
+            2,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0xF2,
+            0xFF,
+            0xFF,
+            0xFF,
+            0x2A
+        ]
+    );
+    let mut il = IlWriter::default();
+    il.branch(op::BR, Label::Jump(0, 1));
+    assert!(il.finish(8, 0, 0).is_err());
+    assert_eq!(operand(op::LDC_I4_S), Some(Operand::I8));
+    assert_eq!(operand(op::LDARG), Some(Operand::U16));
+    assert_eq!(operand(0x24), None);
+}
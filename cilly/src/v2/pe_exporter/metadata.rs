@@ -0,0 +1,1447 @@
+//! Translates an [`Assembly`] into metadata tables, heaps and IL method bodies.
+use fxhash::FxHashMap;
+
+use super::super::{
+    asm::ENTRYPOINT,
+    bimap::Interned,
+    cilnode::{ExtendKind, MethodKind, UnOp},
+    cilroot::{BranchCond, CmpKind},
+    class::StaticFieldDef,
+    method::LocalDef,
+    tpe::GenericKind,
+    Assembly, BasicBlock, BinOp, CILIter, CILIterElem, CILNode, CILRoot, ClassDef, ClassRef, Const,
+    Float, FnSig, Int, MethodDefIdx, MethodImpl, MethodRef, Type,
+};
+use super::{
+    heaps::{compress_u32, Heaps},
+    il::{op, Clause, IlWriter, Label},
+    tables::{CodedIndex, TableId, Tables},
+    PEError, CODE_RVA,
+};
+use crate::{utilis::encode, Access, IString};
+
+/// A source location of an instruction.
+#[derive(Clone, Copy)]
+pub(super) struct SequencePoint {
+    pub il_offset: u32,
+    pub line_start: u32,
+    pub line_len: u16,
+    pub col_start: u16,
+    pub col_len: u16,
+    pub file: Interned<IString>,
+}
+/// Debug information of a single method, stored in the PDB.
+#[derive(Default)]
+pub(super) struct MethodDebugInfo {
+    /// Row of the `StandAloneSig` describing the locals of this method, or 0.
+    pub locals_sig: u32,
+    pub sequence_points: Vec<SequencePoint>,
+}
+/// Everything needed to write the metadata of an image.
+pub(super) struct Metadata {
+    pub tables: Tables,
+    pub heaps: Heaps,
+    /// Method bodies and field data, placed at [`CODE_RVA`].
+    pub code: Vec<u8>,
+    /// Token of the entrypoint, or 0.
+    pub entrypoint: u32,
+    /// Index of the module version ID in the `#GUID` heap.
+    pub mvid: u32,
+    /// Debug information of every method, in `MethodDef` order.
+    pub debug: Vec<MethodDebugInfo>,
+}
+// Signature element types(ECMA-335 II.23.1.16)
+const ELEMENT_TYPE_VOID: u8 = 0x01;
+const ELEMENT_TYPE_BOOLEAN: u8 = 0x02;
+const ELEMENT_TYPE_CHAR: u8 = 0x03;
+const ELEMENT_TYPE_STRING: u8 = 0x0E;
+const ELEMENT_TYPE_PTR: u8 = 0x0F;
+const ELEMENT_TYPE_BYREF: u8 = 0x10;
+const ELEMENT_TYPE_VALUETYPE: u8 = 0x11;
+const ELEMENT_TYPE_CLASS: u8 = 0x12;
+const ELEMENT_TYPE_VAR: u8 = 0x13;
+const ELEMENT_TYPE_ARRAY: u8 = 0x14;
+const ELEMENT_TYPE_GENERICINST: u8 = 0x15;
+const ELEMENT_TYPE_FNPTR: u8 = 0x1B;
+const ELEMENT_TYPE_OBJECT: u8 = 0x1C;
+const ELEMENT_TYPE_SZARRAY: u8 = 0x1D;
+const ELEMENT_TYPE_MVAR: u8 = 0x1E;
+/// Returns the metadata token of `row` in `table`.
+pub(super) fn token(table: TableId, row: u32) -> u32 {
+    ((table as u32) << 24) | row
+}
+/// Splits a full type name into its namespace and name.
+fn split_name(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(dot) => (&name[..dot], &name[dot + 1..]),
+        None => ("", name),
+    }
+}
+fn int_elem(int: Int) -> Option<u8> {
+    Some(match int {
+        Int::I8 => 0x04,
+        Int::U8 => 0x05,
+        Int::I16 => 0x06,
+        Int::U16 => 0x07,
+        Int::I32 => 0x08,
+        Int::U32 => 0x09,
+        Int::I64 => 0x0A,
+        Int::U64 => 0x0B,
+        Int::ISize => 0x18,
+        Int::USize => 0x19,
+        Int::I128 | Int::U128 => return None,
+    })
+}
+/// Builds the metadata of an assembly.
+pub(super) struct MetadataBuilder<'asm> {
+    asm: &'asm mut Assembly,
+    tables: Tables,
+    heaps: Heaps,
+    type_defs: FxHashMap<Interned<ClassRef>, u32>,
+    type_refs: FxHashMap<(Option<Interned<IString>>, Box<str>), u32>,
+    type_specs: FxHashMap<Box<[u8]>, u32>,
+    assembly_refs: FxHashMap<Interned<IString>, u32>,
+    module_refs: FxHashMap<Interned<IString>, u32>,
+    member_refs: FxHashMap<(u32, u32, u32), u32>,
+    method_specs: FxHashMap<(u32, u32), u32>,
+    stand_alone_sigs: FxHashMap<u32, u32>,
+    /// Field rows, by owner, name, type and staticness.
+    fields: FxHashMap<(Interned<ClassRef>, Interned<IString>, Type, bool), u32>,
+    methods: FxHashMap<MethodDefIdx, u32>,
+    const_data: FxHashMap<Interned<Box<[u8]>>, u32>,
+    /// Initial values of fields: field row, data and alignment.
+    field_data: Vec<(u32, Box<[u8]>, u32)>,
+    code: Vec<u8>,
+}
+impl<'asm> MetadataBuilder<'asm> {
+    pub fn new(asm: &'asm mut Assembly) -> Self {
+        Self {
+            asm,
+            tables: Tables::default(),
+            heaps: Heaps::default(),
+            type_defs: FxHashMap::default(),
+            type_refs: FxHashMap::default(),
+            type_specs: FxHashMap::default(),
+            assembly_refs: FxHashMap::default(),
+            module_refs: FxHashMap::default(),
+            member_refs: FxHashMap::default(),
+            method_specs: FxHashMap::default(),
+            stand_alone_sigs: FxHashMap::default(),
+            fields: FxHashMap::default(),
+            methods: FxHashMap::default(),
+            const_data: FxHashMap::default(),
+            field_data: vec![],
+            code: vec![],
+        }
+    }
+    /// Translates the whole assembly.
+    pub fn build(mut self, module_name: &str, is_lib: bool) -> Result<Metadata, PEError> {
+        // Void arguments are passed as `RustVoid`.
+        self.asm.rust_void();
+        let name = self.heaps.string(module_name);
+        // The MVID is filled in once the whole image is known.
+        let mvid = self.heaps.guid([0; 16]);
+        self.tables.push(TableId::Module, [0, name, mvid, 0, 0]);
+        let name = self.heaps.string("_");
+        self.tables
+            .push(TableId::Assembly, [0x8004, 0, 0, 0, 0, 0, 0, name, 0]);
+        let mut classes: Vec<ClassDef> = self.asm.class_defs().values().cloned().collect();
+        classes.sort_by(|a, b| self.asm[a.name()].cmp(&self.asm[b.name()]));
+        // Row 1 is the `<Module>` type, which holds the constant data.
+        for (idx, class) in classes.iter().enumerate() {
+            let cref = self.asm.alloc_class_ref(class.ref_to());
+            self.type_defs.insert(cref, idx as u32 + 2);
+        }
+        self.module_type()?;
+        let mut methods = vec![];
+        for class in &classes {
+            self.class_def(class, &mut methods)?;
+        }
+        let mut debug = vec![];
+        let mut entrypoint = 0;
+        for (method, row) in methods {
+            let def = self.asm.method_def(method).clone();
+            if !is_lib && entrypoint == 0 && self.asm[def.name()] == *ENTRYPOINT {
+                entrypoint = token(TableId::MethodDef, row);
+            }
+            let mimpl = def.resolved_implementation(self.asm).clone();
+            let name = self.asm[def.name()].to_string();
+            let (body, info) = match mimpl {
+                MethodImpl::MethodBody { blocks, locals } => self.method_body(&blocks, &locals)?,
+                MethodImpl::Extern { .. } => {
+                    debug.push(MethodDebugInfo::default());
+                    continue;
+                }
+                MethodImpl::AliasFor(_) => {
+                    panic!("resolved_implementation returned `AliasFor`")
+                }
+                MethodImpl::Missing => {
+                    let mut il = IlWriter::default();
+                    self.throw_msg(&mut il, &format!("missing methiod {name}"));
+                    (il.finish(3, 0, 0)?, MethodDebugInfo::default())
+                }
+            };
+            self.code.resize(self.code.len().next_multiple_of(4), 0);
+            self.tables.set(
+                TableId::MethodDef,
+                row,
+                0,
+                CODE_RVA + self.code.len() as u32,
+            );
+            self.code.extend(body);
+            debug.push(info);
+        }
+        for (field, data, align) in std::mem::take(&mut self.field_data) {
+            self.code
+                .resize(self.code.len().next_multiple_of(align as usize), 0);
+            self.tables.push(
+                TableId::FieldRVA,
+                [CODE_RVA + self.code.len() as u32, field],
+            );
+            self.code.extend(data);
+        }
+        Ok(Metadata {
+            tables: self.tables,
+            heaps: self.heaps,
+            code: self.code,
+            entrypoint,
+            mvid,
+            debug,
+        })
+    }
+    /// Emits the `<Module>` type, and the fields holding constant data.
+    fn module_type(&mut self) -> Result<(), PEError> {
+        let name = self.heaps.string("<Module>");
+        self.tables.push(TableId::TypeDef, [0, name, 0, 0, 1, 1]);
+        let sig = self.heaps.blob(&[0x06, 0x05]);
+        let mut const_data: Vec<_> = self
+            .asm
+            .const_data
+            .1
+            .iter()
+            .map(|(data, idx)| (*idx, data.clone()))
+            .collect();
+        const_data.sort_by_key(|(idx, _)| idx.inner());
+        for (idx, data) in const_data {
+            let name = self
+                .heaps
+                .string(&format!("c_{}", encode(idx.inner() as u64)));
+            // Assembly | Static | HasFieldRVA
+            let row = self.tables.push(TableId::Field, [0x0113, name, sig]);
+            self.const_data.insert(idx, row);
+            let align = (data.len() as u32).next_power_of_two().clamp(1, 16);
+            self.field_data.push((row, data, align));
+        }
+        Ok(())
+    }
+    fn class_def(
+        &mut self,
+        class: &ClassDef,
+        methods: &mut Vec<(MethodDefIdx, u32)>,
+    ) -> Result<(), PEError> {
+        let cref = self.asm.alloc_class_ref(class.ref_to());
+        let type_row = self.type_defs[&cref];
+        let mut flags = match class.access() {
+            Access::Extern | Access::Public => 0x1,
+            Access::Private => 0x0,
+        };
+        if class.has_explicit_layout() {
+            flags |= 0x10;
+        }
+        if class.is_valuetype() {
+            flags |= 0x100;
+        }
+        let extends = match class.extends() {
+            Some(parent) => parent,
+            None if class.is_valuetype() => {
+                let name = self.asm.alloc_string("System.ValueType");
+                let runtime = self.asm.alloc_string("System.Runtime");
+                self.asm
+                    .alloc_class_ref(ClassRef::new(name, Some(runtime), false, [].into()))
+            }
+            None => ClassRef::object(self.asm),
+        };
+        let (table, row) = self.type_def_or_ref(extends)?;
+        let extends = CodedIndex::TypeDefOrRef.encode(table, row);
+        let full_name = self.asm[class.name()].to_string();
+        let (namespace, name) = split_name(&full_name);
+        let (namespace, name) = (self.heaps.string(namespace), self.heaps.string(name));
+        let field_list = self.tables.len(TableId::Field) + 1;
+        let method_list = self.tables.len(TableId::MethodDef) + 1;
+        let pushed = self.tables.push(
+            TableId::TypeDef,
+            [flags, name, namespace, extends, field_list, method_list],
+        );
+        debug_assert_eq!(pushed, type_row);
+        if let Some(size) = class.explict_size() {
+            self.tables
+                .push(TableId::ClassLayout, [0, size.get(), type_row]);
+        }
+        for (tpe, name, offset) in class.fields() {
+            let sig = self.field_sig(*tpe)?;
+            let field_name = self.heaps.string(&self.asm[*name]);
+            let row = self.tables.push(TableId::Field, [0x0006, field_name, sig]);
+            self.fields.insert((cref, *name, *tpe, false), row);
+            if let Some(offset) = offset {
+                self.tables.push(TableId::FieldLayout, [*offset, row]);
+            }
+        }
+        for StaticFieldDef {
+            tpe,
+            name,
+            is_tls,
+            default_value,
+            is_const,
+        } in class.static_fields()
+        {
+            let sig = self.field_sig(*tpe)?;
+            let field_name = self.heaps.string(&self.asm[*name]);
+            // Public | Static
+            let mut flags = 0x0016;
+            if *is_const {
+                flags |= 0x20;
+            }
+            let data: Option<Box<[u8]>> = match default_value {
+                None => None,
+                Some(Const::Bool(val)) => Some([u8::from(*val)].into()),
+                Some(Const::U8(val)) => Some(val.to_le_bytes().into()),
+                Some(Const::U16(val)) => Some(val.to_le_bytes().into()),
+                Some(Const::U32(val)) => Some(val.to_le_bytes().into()),
+                Some(Const::U64(val)) => Some(val.to_le_bytes().into()),
+                Some(Const::U128(val)) => Some(val.to_le_bytes().into()),
+                Some(other) => {
+                    return Err(PEError::Unsupported(format!(
+                        "static field default value {other:?}"
+                    )))
+                }
+            };
+            if data.is_some() {
+                flags |= 0x100;
+            }
+            let row = self.tables.push(TableId::Field, [flags, field_name, sig]);
+            if self.fields.insert((cref, *name, *tpe, true), row).is_some() {
+                return Err(PEError::Unsupported(format!(
+                    "the class {full_name} contains a duplicate static field {}",
+                    &self.asm[*name]
+                )));
+            }
+            if let Some(data) = data {
+                let align = data.len() as u32;
+                self.field_data.push((row, data, align));
+            }
+            if *is_tls {
+                self.thread_static(row);
+            }
+        }
+        for method in class.methods() {
+            let row = self.method_def(*method)?;
+            methods.push((*method, row));
+        }
+        Ok(())
+    }
+    /// Marks the field `row` with the `ThreadStaticAttribute`.
+    fn thread_static(&mut self, row: u32) {
+        let name = self.asm.alloc_string("System.ThreadStaticAttribute");
+        let runtime = self.asm.alloc_string("System.Runtime");
+        let attr = self
+            .asm
+            .alloc_class_ref(ClassRef::new(name, Some(runtime), false, [].into()));
+        let ctor = self.asm[attr].clone().ctor(&[], self.asm);
+        let ctor = self
+            .method_token(ctor)
+            .expect("Could not encode a constructor");
+        let value = self.heaps.blob(&[0x01, 0x00, 0x00, 0x00]);
+        self.tables.push(
+            TableId::CustomAttribute,
+            [
+                CodedIndex::HasCustomAttribute.encode(TableId::Field, row),
+                CodedIndex::CustomAttributeType.encode(TableId::MemberRef, ctor & 0xFF_FFFF),
+                value,
+            ],
+        );
+    }
+    fn method_def(&mut self, method: MethodDefIdx) -> Result<u32, PEError> {
+        let def = self.asm.method_def(method).clone();
+        let name = self.asm[def.name()].to_string();
+        let mut flags: u32 = match def.access() {
+            Access::Extern | Access::Public => 0x0006,
+            Access::Private => 0x0001,
+        };
+        // HideBySig
+        flags |= 0x0080;
+        match def.kind() {
+            MethodKind::Static => flags |= 0x0010,
+            MethodKind::Instance => (),
+            MethodKind::Virtual => flags |= 0x0040,
+            MethodKind::Constructor => flags |= 0x1800,
+        }
+        if name == ".ctor" || name == ".cctor" {
+            // SpecialName | RTSpecialName
+            flags |= 0x1800;
+        }
+        let mut impl_flags = 0;
+        let extern_info = if let MethodImpl::Extern {
+            lib,
+            preserve_errno,
+        } = def.implementation()
+        {
+            // PinvokeImpl
+            flags |= 0x2000;
+            // PreserveSig
+            impl_flags |= 0x0080;
+            Some((*lib, *preserve_errno))
+        } else {
+            None
+        };
+        let sig = self.asm[def.sig()].clone();
+        let sig = self.method_sig(def.kind(), &sig, 0)?;
+        let name_idx = self.heaps.string(&name);
+        let param_list = self.tables.len(TableId::Param) + 1;
+        let row = self.tables.push(
+            TableId::MethodDef,
+            [0, impl_flags, flags, name_idx, sig, param_list],
+        );
+        // Parameter numbering starts at 1, and does not include `this`.
+        let skip = usize::from(def.kind() != MethodKind::Static);
+        for (seq, arg_name) in def.arg_names().iter().skip(skip).enumerate() {
+            if let Some(arg_name) = arg_name {
+                let arg_name = self.heaps.string(&self.asm[*arg_name]);
+                self.tables
+                    .push(TableId::Param, [0, seq as u32 + 1, arg_name]);
+            }
+        }
+        if let Some((lib, preserve_errno)) = extern_info {
+            let module = match self.module_refs.get(&lib) {
+                Some(module) => *module,
+                None => {
+                    let lib_name = self.heaps.string(&self.asm[lib]);
+                    let module = self.tables.push(TableId::ModuleRef, [lib_name]);
+                    self.module_refs.insert(lib, module);
+                    module
+                }
+            };
+            // CallConvCdecl, and optionaly SupportsLastError
+            let map_flags = if preserve_errno { 0x0240 } else { 0x0200 };
+            self.tables.push(
+                TableId::ImplMap,
+                [
+                    map_flags,
+                    CodedIndex::MemberForwarded.encode(TableId::MethodDef, row),
+                    name_idx,
+                    module,
+                ],
+            );
+        }
+        self.methods.insert(method, row);
+        Ok(row)
+    }
+    fn assembly_ref(&mut self, name: Interned<IString>) -> u32 {
+        if let Some(row) = self.assembly_refs.get(&name) {
+            return *row;
+        }
+        let str_idx = self.heaps.string(&self.asm[name]);
+        let row = self
+            .tables
+            .push(TableId::AssemblyRef, [0, 0, 0, 0, 0, 0, str_idx, 0, 0]);
+        self.assembly_refs.insert(name, row);
+        row
+    }
+    /// Returns the `TypeDef` or `TypeRef` row of a class, ignoring its generic arguments.
+    fn type_def_or_ref(&mut self, cref: Interned<ClassRef>) -> Result<(TableId, u32), PEError> {
+        let class = self.asm[cref].clone();
+        if class.generics().is_empty() {
+            if let Some(row) = self.type_defs.get(&cref) {
+                return Ok((TableId::TypeDef, *row));
+            }
+        }
+        let mut full_name = self.asm[class.name()].to_string();
+        if !class.generics().is_empty() {
+            full_name = format!("{full_name}`{}", class.generics().len());
+        }
+        let key = (class.asm(), full_name.clone().into_boxed_str());
+        if let Some(row) = self.type_refs.get(&key) {
+            return Ok((TableId::TypeRef, *row));
+        }
+        let scope = match class.asm() {
+            Some(asm_name) => CodedIndex::ResolutionScope
+                .encode(TableId::AssemblyRef, self.assembly_ref(asm_name)),
+            // A class, which is not defined in this assembly.
+            None => CodedIndex::ResolutionScope.encode(TableId::Module, 1),
+        };
+        let (namespace, name) = split_name(&full_name);
+        let (namespace, name) = (self.heaps.string(namespace), self.heaps.string(name));
+        let row = self.tables.push(TableId::TypeRef, [scope, name, namespace]);
+        self.type_refs.insert(key, row);
+        Ok((TableId::TypeRef, row))
+    }
+    /// Returns the table and row describing a class, including its generic arguments.
+    fn class_table_row(&mut self, cref: Interned<ClassRef>) -> Result<(TableId, u32), PEError> {
+        if self.asm[cref].generics().is_empty() {
+            self.type_def_or_ref(cref)
+        } else {
+            let mut sig = vec![];
+            self.class_sig(cref, &mut sig)?;
+            Ok((TableId::TypeSpec, self.type_spec(&sig)))
+        }
+    }
+    fn type_spec(&mut self, sig: &[u8]) -> u32 {
+        if let Some(row) = self.type_specs.get(sig) {
+            return *row;
+        }
+        let blob = self.heaps.blob(sig);
+        let row = self.tables.push(TableId::TypeSpec, [blob]);
+        self.type_specs.insert(sig.into(), row);
+        row
+    }
+    /// Returns a token referring to a type, used by instructions like `sizeof` or `ldobj`.
+    fn type_token(&mut self, tpe: Type) -> Result<u32, PEError> {
+        if let Type::ClassRef(cref) = tpe {
+            let (table, row) = self.class_table_row(cref)?;
+            return Ok(token(table, row));
+        }
+        let mut sig = vec![];
+        self.type_sig(tpe, &mut sig)?;
+        Ok(token(TableId::TypeSpec, self.type_spec(&sig)))
+    }
+    fn class_sig(&mut self, cref: Interned<ClassRef>, out: &mut Vec<u8>) -> Result<(), PEError> {
+        let class = self.asm[cref].clone();
+        let kind = if class.is_valuetype() {
+            ELEMENT_TYPE_VALUETYPE
+        } else {
+            ELEMENT_TYPE_CLASS
+        };
+        if !class.generics().is_empty() {
+            out.push(ELEMENT_TYPE_GENERICINST);
+        }
+        out.push(kind);
+        let (table, row) = self.type_def_or_ref(cref)?;
+        compress_u32(CodedIndex::TypeDefOrRef.encode(table, row), out);
+        if !class.generics().is_empty() {
+            compress_u32(class.generics().len() as u32, out);
+            for generic in class.generics() {
+                self.type_sig(*generic, out)?;
+            }
+        }
+        Ok(())
+    }
+    /// Encodes a type(ECMA-335 II.23.2.12).
+    fn type_sig(&mut self, tpe: Type, out: &mut Vec<u8>) -> Result<(), PEError> {
+        match tpe {
+            Type::Void => out.push(ELEMENT_TYPE_VOID),
+            Type::Bool => out.push(ELEMENT_TYPE_BOOLEAN),
+            Type::PlatformChar => out.push(ELEMENT_TYPE_CHAR),
+            Type::Int(int) => match int_elem(int) {
+                Some(elem) => out.push(elem),
+                None if int == Int::I128 => {
+                    let cref = ClassRef::int_128(self.asm);
+                    self.class_sig(cref, out)?;
+                }
+                None => {
+                    let cref = ClassRef::uint_128(self.asm);
+                    self.class_sig(cref, out)?;
+                }
+            },
+            Type::Float(Float::F16) => {
+                let cref = ClassRef::half(self.asm);
+                self.class_sig(cref, out)?;
+            }
+            Type::Float(Float::F32) => out.push(0x0C),
+            Type::Float(Float::F64) => out.push(0x0D),
+            Type::Float(Float::F128) => {
+                let name = self.asm.alloc_string("f128");
+                let cref = self
+                    .asm
+                    .alloc_class_ref(ClassRef::new(name, None, true, [].into()));
+                self.class_sig(cref, out)?;
+            }
+            Type::PlatformString => out.push(ELEMENT_TYPE_STRING),
+            Type::PlatformObject => out.push(ELEMENT_TYPE_OBJECT),
+            Type::Ptr(inner) => {
+                out.push(ELEMENT_TYPE_PTR);
+                self.type_sig(self.asm[inner], out)?;
+            }
+            Type::Ref(inner) => {
+                out.push(ELEMENT_TYPE_BYREF);
+                self.type_sig(self.asm[inner], out)?;
+            }
+            Type::ClassRef(cref) => self.class_sig(cref, out)?,
+            Type::PlatformGeneric(idx, kind) => {
+                out.push(match kind {
+                    GenericKind::MethodGeneric | GenericKind::TypeGeneric => ELEMENT_TYPE_VAR,
+                    GenericKind::CallGeneric => ELEMENT_TYPE_MVAR,
+                });
+                compress_u32(idx, out);
+            }
+            Type::PlatformArray { elem, dims } => {
+                if dims.get() == 1 {
+                    out.push(ELEMENT_TYPE_SZARRAY);
+                    self.type_sig(self.asm[elem], out)?;
+                } else {
+                    out.push(ELEMENT_TYPE_ARRAY);
+                    self.type_sig(self.asm[elem], out)?;
+                    // Rank, followed by no sizes and no lower bounds.
+                    compress_u32(dims.get().into(), out);
+                    out.extend([0, 0]);
+                }
+            }
+            Type::FnPtr(sig) => {
+                out.push(ELEMENT_TYPE_FNPTR);
+                let sig = self.asm[sig].clone();
+                self.method_sig_blob(false, &sig, 0, out)?;
+            }
+            Type::SIMDVector(vec) => {
+                let bits = vec.bits();
+                if !matches!(bits, 64 | 128 | 256 | 512) {
+                    return Err(PEError::Unsupported(format!("SIMD vector of size {bits}")));
+                }
+                let cref = vec.class(self.asm);
+                self.class_sig(cref, out)?;
+            }
+        }
+        Ok(())
+    }
+    /// Encodes the type of a parameter or local, which can't be `void`.
+    fn non_void_sig(&mut self, tpe: Type, out: &mut Vec<u8>) -> Result<(), PEError> {
+        match tpe {
+            Type::Void => {
+                let name = self.asm.alloc_string("RustVoid");
+                let cref = self
+                    .asm
+                    .alloc_class_ref(ClassRef::new(name, None, true, [].into()));
+                self.class_sig(cref, out)
+            }
+            _ => self.type_sig(tpe, out),
+        }
+    }
+    fn field_sig(&mut self, tpe: Type) -> Result<u32, PEError> {
+        let mut sig = vec![0x06];
+        self.non_void_sig(tpe, &mut sig)?;
+        Ok(self.heaps.blob(&sig))
+    }
+    /// Encodes a method signature(ECMA-335 II.23.2.1). `inputs` include `this`, if `has_this` is set.
+    fn method_sig_blob(
+        &mut self,
+        has_this: bool,
+        sig: &FnSig,
+        generics: u32,
+        out: &mut Vec<u8>,
+    ) -> Result<(), PEError> {
+        let mut conv = if has_this { 0x20 } else { 0x00 };
+        if sig.is_variadic() {
+            conv |= 0x05;
+        }
+        if generics > 0 {
+            conv |= 0x10;
+        }
+        out.push(conv);
+        if generics > 0 {
+            compress_u32(generics, out);
+        }
+        let inputs = if has_this {
+            sig.inputs()
+                .get(1..)
+                .ok_or_else(|| PEError::Unsupported("an instance method without `this`".into()))?
+        } else {
+            sig.inputs()
+        };
+        compress_u32(inputs.len() as u32, out);
+        self.type_sig(*sig.output(), out)?;
+        for input in inputs {
+            self.non_void_sig(*input, out)?;
+        }
+        Ok(())
+    }
+    fn method_sig(&mut self, kind: MethodKind, sig: &FnSig, generics: u32) -> Result<u32, PEError> {
+        let mut blob = vec![];
+        self.method_sig_blob(kind != MethodKind::Static, sig, generics, &mut blob)?;
+        Ok(self.heaps.blob(&blob))
+    }
+    fn stand_alone_sig(&mut self, sig: &[u8]) -> u32 {
+        let blob = self.heaps.blob(sig);
+        if let Some(row) = self.stand_alone_sigs.get(&blob) {
+            return *row;
+        }
+        let row = self.tables.push(TableId::StandAloneSig, [blob]);
+        self.stand_alone_sigs.insert(blob, row);
+        row
+    }
+    fn member_ref(
+        &mut self,
+        parent: Interned<ClassRef>,
+        name: &str,
+        sig: u32,
+    ) -> Result<u32, PEError> {
+        let (table, row) = self.class_table_row(parent)?;
+        let parent = CodedIndex::MemberRefParent.encode(table, row);
+        let name = self.heaps.string(name);
+        if let Some(row) = self.member_refs.get(&(parent, name, sig)) {
+            return Ok(*row);
+        }
+        let row = self.tables.push(TableId::MemberRef, [parent, name, sig]);
+        self.member_refs.insert((parent, name, sig), row);
+        Ok(row)
+    }
+    /// Returns a `MethodDef`, `MemberRef` or `MethodSpec` token referring to a method.
+    fn method_token(&mut self, mref: Interned<MethodRef>) -> Result<u32, PEError> {
+        let method = self.asm[mref].clone();
+        if method.generics().is_empty() {
+            if let Some(row) = self
+                .asm
+                .method_ref_to_def(mref)
+                .and_then(|def| self.methods.get(&def))
+            {
+                return Ok(token(TableId::MethodDef, *row));
+            }
+        }
+        let sig = self.asm[method.sig()].clone();
+        let sig = self.method_sig(method.kind(), &sig, method.generics().len() as u32)?;
+        let name = self.asm[method.name()].to_string();
+        let row = self.member_ref(method.class(), &name, sig)?;
+        if method.generics().is_empty() {
+            return Ok(token(TableId::MemberRef, row));
+        }
+        let mut instantiation = vec![0x0A];
+        compress_u32(method.generics().len() as u32, &mut instantiation);
+        for generic in method.generics() {
+            self.type_sig(*generic, &mut instantiation)?;
+        }
+        let instantiation = self.heaps.blob(&instantiation);
+        let parent = CodedIndex::MethodDefOrRef.encode(TableId::MemberRef, row);
+        let row = match self.method_specs.get(&(parent, instantiation)) {
+            Some(row) => *row,
+            None => {
+                let row = self
+                    .tables
+                    .push(TableId::MethodSpec, [parent, instantiation]);
+                self.method_specs.insert((parent, instantiation), row);
+                row
+            }
+        };
+        Ok(token(TableId::MethodSpec, row))
+    }
+    /// Returns a `Field` or `MemberRef` token referring to a field.
+    fn field_token(
+        &mut self,
+        owner: Interned<ClassRef>,
+        name: Interned<IString>,
+        tpe: Type,
+        is_static: bool,
+    ) -> Result<u32, PEError> {
+        if let Some(row) = self.fields.get(&(owner, name, tpe, is_static)) {
+            return Ok(token(TableId::Field, *row));
+        }
+        let sig = self.field_sig(tpe)?;
+        let name = self.asm[name].to_string();
+        let row = self.member_ref(owner, &name, sig)?;
+        Ok(token(TableId::MemberRef, row))
+    }
+    /// Emits code throwing a `System.Exception` with the message `msg`.
+    fn throw_msg(&mut self, il: &mut IlWriter, msg: &str) {
+        let msg = self.heaps.user_string(msg);
+        il.op_token(op::LDSTR, 0x7000_0000 | msg);
+        let exception = ClassRef::exception(self.asm);
+        let ctor = self.asm[exception]
+            .clone()
+            .ctor(&[Type::PlatformString], self.asm);
+        let ctor = self
+            .method_token(ctor)
+            .expect("Could not encode the exception constructor");
+        il.op_token(op::NEWOBJ, ctor);
+        il.op(op::THROW);
+    }
+    fn method_body(
+        &mut self,
+        blocks: &[BasicBlock],
+        locals: &[LocalDef],
+    ) -> Result<(Vec<u8>, MethodDebugInfo), PEError> {
+        let mut info = MethodDebugInfo::default();
+        if !locals.is_empty() {
+            let mut sig = vec![0x07];
+            compress_u32(locals.len() as u32, &mut sig);
+            for (_, tpe) in locals {
+                self.non_void_sig(self.asm[*tpe], &mut sig)?;
+            }
+            info.locals_sig = self.stand_alone_sig(&sig);
+        }
+        let max_stack = blocks
+            .iter()
+            .flat_map(|block| block.roots().iter())
+            .map(|root| CILIter::new(self.asm.get_root(*root).clone(), self.asm).count() + 10)
+            .max()
+            .unwrap_or(0);
+        let mut il = IlWriter::default();
+        for block in blocks {
+            let try_start = il.offset();
+            il.label(Label::Block(block.block_id()))?;
+            for root in block.roots() {
+                self.root(&mut il, &mut info, *root, false, block.handler().is_some())?;
+            }
+            let Some(handler) = block.handler() else {
+                continue;
+            };
+            let handler_start = il.offset();
+            // The exception is on the stack at the start of the handler. Pop it, if it is not used.
+            let uses_exception = handler
+                .iter()
+                .flat_map(BasicBlock::roots)
+                .flat_map(|root| CILIter::new(self.asm.get_root(*root).clone(), self.asm))
+                .any(|elem| matches!(elem, CILIterElem::Node(CILNode::GetException)));
+            if !uses_exception {
+                il.op(op::POP);
+            }
+            for hblock in handler {
+                il.label(Label::Handler(block.block_id(), hblock.block_id()))?;
+                for root in hblock.roots() {
+                    self.root(&mut il, &mut info, *root, true, false)?;
+                }
+            }
+            il.clause(Clause {
+                try_start,
+                try_end: handler_start,
+                handler_start,
+                handler_end: il.offset(),
+            });
+        }
+        let object = ClassRef::object(self.asm);
+        let (table, row) = self.type_def_or_ref(object)?;
+        let max_stack = u16::try_from(max_stack).unwrap_or(u16::MAX);
+        let locals_token = match info.locals_sig {
+            0 => 0,
+            row => token(TableId::StandAloneSig, row),
+        };
+        let body = il.finish(max_stack, locals_token, token(table, row));
+        Ok((body?, info))
+    }
+    /// Pushes a 32 bit integer constant, using the shortest encoding.
+    fn ldc_i4(il: &mut IlWriter, val: i32) {
+        match val {
+            -1 => il.op(op::LDC_I4_M1),
+            0..=8 => il.op(op::LDC_I4_0 + val as u16),
+            -128..=127 => il.op_u8(op::LDC_I4_S, val as i8 as u8),
+            _ => il.op_i32(op::LDC_I4, val),
+        }
+    }
+    fn ldc_i8(il: &mut IlWriter, val: i64) {
+        match i32::try_from(val) {
+            Ok(val) => {
+                Self::ldc_i4(il, val);
+                il.op(op::CONV_I8);
+            }
+            Err(_) => il.op_i64(op::LDC_I8, val),
+        }
+    }
+    /// Converts the value on top of the stack to a 128 bit integer, using `op_Implicit`.
+    fn implicit_128(&mut self, il: &mut IlWriter, int: Int, from: Int) -> Result<(), PEError> {
+        let class = match int {
+            Int::I128 => ClassRef::int_128(self.asm),
+            _ => ClassRef::uint_128(self.asm),
+        };
+        let name = self.asm.alloc_string("op_Implicit");
+        let sig = self.asm.sig([Type::Int(from)], Type::Int(int));
+        let mref = self.asm.alloc_methodref(MethodRef::new(
+            class,
+            name,
+            sig,
+            MethodKind::Static,
+            [].into(),
+        ));
+        let mref = self.method_token(mref)?;
+        il.op_token(op::CALL, mref);
+        Ok(())
+    }
+    /// Builds a 128 bit integer from its high and low halves.
+    fn ctor_128(&mut self, il: &mut IlWriter, int: Int, val: u128) -> Result<(), PEError> {
+        il.op_i64(op::LDC_I8, (val >> 64) as i64);
+        il.op_i64(op::LDC_I8, val as u64 as i64);
+        let class = match int {
+            Int::I128 => ClassRef::int_128(self.asm),
+            _ => ClassRef::uint_128(self.asm),
+        };
+        let ctor = self.asm[class]
+            .clone()
+            .ctor(&[Type::Int(Int::U64), Type::Int(Int::U64)], self.asm);
+        let ctor = self.method_token(ctor)?;
+        il.op_token(op::NEWOBJ, ctor);
+        Ok(())
+    }
+    fn constant(&mut self, il: &mut IlWriter, cst: &Const) -> Result<(), PEError> {
+        match cst {
+            Const::ByteBuffer { data, .. } => {
+                let row = self.const_data[data];
+                il.op_token(op::LDSFLDA, token(TableId::Field, row));
+            }
+            Const::Null(_) => il.op(op::LDNULL),
+            Const::I8(val) => Self::ldc_i4(il, (*val).into()),
+            Const::I16(val) => Self::ldc_i4(il, (*val).into()),
+            Const::I32(val) => Self::ldc_i4(il, *val),
+            Const::I64(val) => Self::ldc_i8(il, *val),
+            Const::ISize(val) => {
+                match i32::try_from(*val) {
+                    Ok(val) => Self::ldc_i4(il, val),
+                    Err(_) => il.op_i64(op::LDC_I8, *val),
+                }
+                il.op(op::CONV_I);
+            }
+            Const::U8(val) => Self::ldc_i4(il, (*val).into()),
+            Const::U16(val) => Self::ldc_i4(il, (*val).into()),
+            Const::U32(val) => Self::ldc_i4(il, *val as i32),
+            Const::U64(val) => match u32::try_from(*val) {
+                Ok(val) => {
+                    Self::ldc_i4(il, val as i32);
+                    il.op(op::CONV_U8);
+                }
+                Err(_) => il.op_i64(op::LDC_I8, *val as i64),
+            },
+            Const::USize(val) => {
+                match i32::try_from(*val) {
+                    Ok(val) => Self::ldc_i4(il, val),
+                    Err(_) => il.op_i64(op::LDC_I8, *val as i64),
+                }
+                il.op(op::CONV_U);
+            }
+            Const::I128(val) => {
+                if let Ok(val) = i32::try_from(*val) {
+                    Self::ldc_i4(il, val);
+                    self.implicit_128(il, Int::I128, Int::I32)?;
+                } else if let Ok(val) = i64::try_from(*val) {
+                    il.op_i64(op::LDC_I8, val);
+                    self.implicit_128(il, Int::I128, Int::I64)?;
+                } else {
+                    self.ctor_128(il, Int::I128, *val as u128)?;
+                }
+            }
+            Const::U128(val) => {
+                if let Ok(val) = u32::try_from(*val) {
+                    Self::ldc_i4(il, val as i32);
+                    self.implicit_128(il, Int::U128, Int::U32)?;
+                } else if let Ok(val) = u64::try_from(*val) {
+                    il.op_i64(op::LDC_I8, val as i64);
+                    self.implicit_128(il, Int::U128, Int::U64)?;
+                } else {
+                    self.ctor_128(il, Int::U128, *val)?;
+                }
+            }
+            Const::PlatformString(msg) => {
+                let msg = self.heaps.user_string(&self.asm[*msg]);
+                il.op_token(op::LDSTR, 0x7000_0000 | msg);
+            }
+            Const::Bool(val) => Self::ldc_i4(il, (*val).into()),
+            Const::F32(float) => il.op_bytes(op::LDC_R4, &float.to_le_bytes()),
+            Const::F64(float) => il.op_bytes(op::LDC_R8, &float.to_le_bytes()),
+        }
+        Ok(())
+    }
+    /// Emits the instruction accessing a local or argument, picking the shortest form.
+    fn var_op(il: &mut IlWriter, idx: u32, short_n: Option<u16>, short: u16, long: u16) {
+        match (idx, short_n) {
+            (0..=3, Some(base)) => il.op(base + idx as u16),
+            (0..=255, _) => il.op_u8(short, idx as u8),
+            _ => il.op_u16(long, idx as u16),
+        }
+    }
+    fn call(
+        &mut self,
+        il: &mut IlWriter,
+        mref: Interned<MethodRef>,
+        args: &[Interned<CILNode>],
+    ) -> Result<(), PEError> {
+        let called_sig = &self.asm[self.asm[mref].sig()];
+        if called_sig.is_variadic() && args.len() > called_sig.inputs().len() {
+            return Err(PEError::Unsupported(
+                "passing additional arguments to a C-variadic method".into(),
+            ));
+        }
+        for arg in args {
+            self.node(il, *arg)?;
+        }
+        let opcode = match self.asm[mref].kind() {
+            MethodKind::Static | MethodKind::Instance => op::CALL,
+            MethodKind::Virtual => op::CALLVIRT,
+            MethodKind::Constructor => op::NEWOBJ,
+        };
+        let mref = self.method_token(mref)?;
+        il.op_token(opcode, mref);
+        Ok(())
+    }
+    fn calli(
+        &mut self,
+        il: &mut IlWriter,
+        fn_ptr: Interned<CILNode>,
+        sig: Interned<FnSig>,
+        args: &[Interned<CILNode>],
+    ) -> Result<(), PEError> {
+        let sig = self.asm[sig].clone();
+        if sig.is_variadic() && args.len() > sig.inputs().len() {
+            return Err(PEError::Unsupported(
+                "passing additional arguments to a C-variadic method".into(),
+            ));
+        }
+        for arg in args {
+            self.node(il, *arg)?;
+        }
+        self.node(il, fn_ptr)?;
+        let mut blob = vec![];
+        self.method_sig_blob(false, &sig, 0, &mut blob)?;
+        let sig = self.stand_alone_sig(&blob);
+        il.op_token(op::CALLI, token(TableId::StandAloneSig, sig));
+        Ok(())
+    }
+    fn node(&mut self, il: &mut IlWriter, node: Interned<CILNode>) -> Result<(), PEError> {
+        let node = self.asm.get_node(node).clone();
+        match node {
+            CILNode::Const(cst) => self.constant(il, &cst)?,
+            CILNode::BinOp(lhs, rhs, binop) => {
+                self.node(il, lhs)?;
+                self.node(il, rhs)?;
+                il.op(match binop {
+                    BinOp::Add => op::ADD,
+                    BinOp::Eq => op::CEQ,
+                    BinOp::Sub => op::SUB,
+                    BinOp::Mul => op::MUL,
+                    BinOp::LtUn => op::CLT_UN,
+                    BinOp::Lt => op::CLT,
+                    BinOp::GtUn => op::CGT_UN,
+                    BinOp::Gt => op::CGT,
+                    BinOp::Or => op::OR,
+                    BinOp::XOr => op::XOR,
+                    BinOp::And => op::AND,
+                    BinOp::Rem => op::REM,
+                    BinOp::RemUn => op::REM_UN,
+                    BinOp::Shl => op::SHL,
+                    BinOp::Shr => op::SHR,
+                    BinOp::ShrUn => op::SHR_UN,
+                    BinOp::DivUn => op::DIV_UN,
+                    BinOp::Div => op::DIV,
+                });
+            }
+            CILNode::UnOp(arg, unop) => {
+                self.node(il, arg)?;
+                il.op(match unop {
+                    UnOp::Not => op::NOT,
+                    UnOp::Neg => op::NEG,
+                });
+            }
+            CILNode::LdLoc(loc) => Self::var_op(il, loc, Some(op::LDLOC_0), op::LDLOC_S, op::LDLOC),
+            CILNode::LdLocA(loc) => Self::var_op(il, loc, None, op::LDLOCA_S, op::LDLOCA),
+            CILNode::LdArg(arg) => Self::var_op(il, arg, Some(op::LDARG_0), op::LDARG_S, op::LDARG),
+            CILNode::LdArgA(arg) => Self::var_op(il, arg, None, op::LDARGA_S, op::LDARGA),
+            CILNode::Call(call) => self.call(il, call.0, &call.1)?,
+            CILNode::IntCast {
+                input,
+                target,
+                extend,
+            } => {
+                self.node(il, input)?;
+                il.op(match (target, extend) {
+                    (Int::U8 | Int::I8, ExtendKind::ZeroExtend) => op::CONV_U1,
+                    (Int::U8 | Int::I8, ExtendKind::SignExtend) => op::CONV_I1,
+                    (Int::U16 | Int::I16, ExtendKind::ZeroExtend) => op::CONV_U2,
+                    (Int::U16 | Int::I16, ExtendKind::SignExtend) => op::CONV_I2,
+                    (Int::U32 | Int::I32, ExtendKind::ZeroExtend) => op::CONV_U4,
+                    (Int::U32 | Int::I32, ExtendKind::SignExtend) => op::CONV_I4,
+                    (Int::U64 | Int::I64, ExtendKind::ZeroExtend) => op::CONV_U8,
+                    (Int::U64 | Int::I64, ExtendKind::SignExtend) => op::CONV_I8,
+                    (Int::USize | Int::ISize, ExtendKind::ZeroExtend) => op::CONV_U,
+                    (Int::USize | Int::ISize, ExtendKind::SignExtend) => op::CONV_I,
+                    (Int::U128 | Int::I128, _) => {
+                        return Err(PEError::Unsupported(format!("cast to {target:?}")))
+                    }
+                });
+            }
+            CILNode::FloatCast {
+                input,
+                target,
+                is_signed,
+            } => {
+                self.node(il, input)?;
+                let conv = match target {
+                    Float::F32 => op::CONV_R4,
+                    Float::F64 => op::CONV_R8,
+                    Float::F16 | Float::F128 => {
+                        return Err(PEError::Unsupported(format!("cast to {target:?}")))
+                    }
+                };
+                if !is_signed {
+                    il.op(op::CONV_R_UN);
+                }
+                il.op(conv);
+            }
+            CILNode::RefToPtr(inner) => {
+                self.node(il, inner)?;
+                il.op(op::CONV_U);
+            }
+            CILNode::PtrCast(val, _) => self.node(il, val)?,
+            CILNode::LdFieldAddress { addr, field } | CILNode::LdField { addr, field } => {
+                self.node(il, addr)?;
+                let opcode = if matches!(node, CILNode::LdField { .. }) {
+                    op::LDFLD
+                } else {
+                    op::LDFLDA
+                };
+                let fld = *self.asm.get_field(field);
+                let fld = self.field_token(fld.owner(), fld.name(), fld.tpe(), false)?;
+                il.op_token(opcode, fld);
+            }
+            CILNode::LdInd {
+                addr,
+                tpe,
+                volatile,
+            } => {
+                self.node(il, addr)?;
+                if volatile {
+                    il.op(op::VOLATILE);
+                }
+                let tpe = self.asm[tpe];
+                let opcode = match tpe {
+                    Type::Ptr(_) | Type::FnPtr(_) => op::LDIND_I,
+                    Type::Int(int) => match int {
+                        Int::U8 => op::LDIND_U1,
+                        Int::U16 => op::LDIND_U2,
+                        Int::U32 => op::LDIND_U4,
+                        Int::U64 | Int::I64 => op::LDIND_I8,
+                        Int::USize | Int::ISize => op::LDIND_I,
+                        Int::I8 => op::LDIND_I1,
+                        Int::I16 => op::LDIND_I2,
+                        Int::I32 => op::LDIND_I4,
+                        Int::U128 | Int::I128 => {
+                            let tpe = self.type_token(tpe)?;
+                            il.op_token(op::LDOBJ, tpe);
+                            return Ok(());
+                        }
+                    },
+                    Type::Float(Float::F32) => op::LDIND_R4,
+                    Type::Float(Float::F64) => op::LDIND_R8,
+                    Type::PlatformString | Type::PlatformObject | Type::PlatformArray { .. } => {
+                        op::LDIND_REF
+                    }
+                    Type::PlatformChar => op::LDIND_I2,
+                    Type::Bool => op::LDIND_I1,
+                    Type::ClassRef(_) | Type::Float(_) | Type::SIMDVector(_) => {
+                        let tpe = self.type_token(tpe)?;
+                        il.op_token(op::LDOBJ, tpe);
+                        return Ok(());
+                    }
+                    Type::Ref(_) | Type::PlatformGeneric(_, _) | Type::Void => {
+                        return Err(PEError::Unsupported(format!("dereferencing {tpe:?}")))
+                    }
+                };
+                il.op(opcode);
+            }
+            CILNode::SizeOf(tpe) => {
+                let tpe = self.asm[tpe];
+                if tpe == Type::Void {
+                    eprintln!("WARNING: attempted to calc size_of(void). This is UB: not all targets support ZSTs. Please use Const::I32(0) instead. Continuing anyway.");
+                    il.op(op::LDC_I4_0);
+                } else {
+                    let tpe = self.type_token(tpe)?;
+                    il.op_token(op::SIZEOF, tpe);
+                }
+            }
+            // The exception is already on the stack.
+            CILNode::GetException => (),
+            CILNode::IsInst(val, tpe) | CILNode::CheckedCast(val, tpe) => {
+                self.node(il, val)?;
+                let opcode = if matches!(node, CILNode::IsInst(..)) {
+                    op::ISINST
+                } else {
+                    op::CASTCLASS
+                };
+                let tpe = self.type_token(self.asm[tpe])?;
+                il.op_token(opcode, tpe);
+            }
+            CILNode::CallI(calli) => {
+                let (fn_ptr, sig, args) = calli.as_ref();
+                self.calli(il, *fn_ptr, *sig, args)?;
+            }
+            CILNode::LocAlloc { size } => {
+                self.node(il, size)?;
+                il.op(op::LOCALLOC);
+            }
+            CILNode::LdStaticField(sfld) | CILNode::LdStaticFieldAddress(sfld) => {
+                let opcode = if matches!(node, CILNode::LdStaticField(_)) {
+                    op::LDSFLD
+                } else {
+                    op::LDSFLDA
+                };
+                let sfld = *self.asm.get_static_field(sfld);
+                let sfld = self.field_token(sfld.owner(), sfld.name(), sfld.tpe(), true)?;
+                il.op_token(opcode, sfld);
+            }
+            CILNode::LdFtn(ftn) => {
+                let ftn = self.method_token(ftn)?;
+                il.op_token(op::LDFTN, ftn);
+            }
+            CILNode::LdTypeToken(tpe) => {
+                let tpe = self.type_token(self.asm[tpe])?;
+                il.op_token(op::LDTOKEN, tpe);
+            }
+            CILNode::LdLen(array) => {
+                self.node(il, array)?;
+                il.op(op::LDLEN);
+            }
+            CILNode::LocAllocAlgined { tpe, align } => {
+                let tpe = self.type_token(self.asm[tpe])?;
+                let align = align as i64;
+                il.op_token(op::SIZEOF, tpe);
+                il.op_i64(op::LDC_I8, align);
+                il.op(op::CONV_I);
+                il.op(op::ADD);
+                il.op(op::LOCALLOC);
+                il.op(op::DUP);
+                il.op_i64(op::LDC_I8, align);
+                il.op(op::ADD);
+                il.op_i64(op::LDC_I8, align);
+                il.op(op::REM);
+                il.op(op::SUB);
+                il.op_i64(op::LDC_I8, align);
+                il.op(op::ADD);
+                il.op(op::CONV_U);
+            }
+            CILNode::LdElelemRef { array, index } => {
+                self.node(il, array)?;
+                self.node(il, index)?;
+                il.op(op::LDELEM_REF);
+            }
+            CILNode::UnboxAny { object, tpe } => {
+                self.node(il, object)?;
+                let tpe = self.type_token(self.asm[tpe])?;
+                il.op_token(op::UNBOX_ANY, tpe);
+            }
+        }
+        Ok(())
+    }
+    /// Returns the label a branch should jump to. Mirrors `branch_cond_to_name`.
+    fn branch_label(target: u32, sub_target: u32, has_handler: bool, is_handler: bool) -> Label {
+        if sub_target == 0 {
+            Label::Block(target)
+        } else if is_handler {
+            Label::Handler(target, sub_target)
+        } else if has_handler {
+            Label::Jump(target, sub_target)
+        } else {
+            // If the handler was removed, we can just jump directly to our target
+            Label::Block(sub_target)
+        }
+    }
+    fn root(
+        &mut self,
+        il: &mut IlWriter,
+        info: &mut MethodDebugInfo,
+        root: Interned<CILRoot>,
+        is_handler: bool,
+        has_handler: bool,
+    ) -> Result<(), PEError> {
+        let root = self.asm.get_root(root).clone();
+        match root {
+            CILRoot::StLoc(loc, val) => {
+                self.node(il, val)?;
+                Self::var_op(il, loc, Some(op::STLOC_0), op::STLOC_S, op::STLOC);
+            }
+            CILRoot::StArg(arg, val) => {
+                self.node(il, val)?;
+                Self::var_op(il, arg, None, op::STARG_S, op::STARG);
+            }
+            CILRoot::Ret(val) => {
+                self.node(il, val)?;
+                il.op(op::RET);
+            }
+            CILRoot::Pop(val) => {
+                self.node(il, val)?;
+                il.op(op::POP);
+            }
+            CILRoot::Throw(val) => {
+                self.node(il, val)?;
+                il.op(op::THROW);
+            }
+            CILRoot::VoidRet => il.op(op::RET),
+            CILRoot::Break => il.op(op::BREAK),
+            CILRoot::Nop => il.op(op::NOP),
+            CILRoot::Branch(branch) => {
+                let (target, sub_target, cond) = branch.as_ref();
+                let label = Self::branch_label(*target, *sub_target, has_handler, is_handler);
+                let opcode = match cond {
+                    Some(BranchCond::True(cond)) => {
+                        self.node(il, *cond)?;
+                        op::BRTRUE
+                    }
+                    Some(BranchCond::False(cond)) => {
+                        self.node(il, *cond)?;
+                        op::BRFALSE
+                    }
+                    Some(
+                        BranchCond::Eq(a, b)
+                        | BranchCond::Ne(a, b)
+                        | BranchCond::Lt(a, b, _)
+                        | BranchCond::Gt(a, b, _)
+                        | BranchCond::Le(a, b, _)
+                        | BranchCond::Ge(a, b, _),
+                    ) => {
+                        self.node(il, *a)?;
+                        self.node(il, *b)?;
+                        let ordered = |kind: &CmpKind, signed: u16, unsigned: u16| match kind {
+                            CmpKind::Ordered | CmpKind::Signed => signed,
+                            CmpKind::Unordered | CmpKind::Unsigned => unsigned,
+                        };
+                        match cond {
+                            Some(BranchCond::Eq(..)) => op::BEQ,
+                            Some(BranchCond::Ne(..)) => op::BNE_UN,
+                            Some(BranchCond::Lt(_, _, kind)) => ordered(kind, op::BLT, op::BLT_UN),
+                            Some(BranchCond::Gt(_, _, kind)) => ordered(kind, op::BGT, op::BGT_UN),
+                            Some(BranchCond::Le(_, _, kind)) => ordered(kind, op::BLE, op::BLE_UN),
+                            Some(BranchCond::Ge(_, _, kind)) => ordered(kind, op::BGE, op::BGE_UN),
+                            _ => unreachable!(),
+                        }
+                    }
+                    None => op::BR,
+                };
+                il.branch(opcode, label);
+            }
+            CILRoot::Switch(switch) => {
+                let (value, targets, default) = switch.as_ref();
+                self.node(il, *value)?;
+                let labels: Vec<_> = targets
+                    .iter()
+                    .map(|(target, sub_target)| {
+                        Self::branch_label(*target, *sub_target, has_handler, is_handler)
+                    })
+                    .collect();
+                il.switch(&labels);
+                il.branch(
+                    op::BR,
+                    Self::branch_label(default.0, default.1, has_handler, is_handler),
+                );
+            }
+            CILRoot::SourceFileInfo {
+                line_start,
+                line_len,
+                col_start,
+                col_len,
+                file,
+            } => info.sequence_points.push(SequencePoint {
+                il_offset: il.offset(),
+                line_start,
+                line_len,
+                col_start,
+                col_len,
+                file,
+            }),
+            CILRoot::SetField(set) => {
+                let (field, addr, val) = set.as_ref();
+                self.node(il, *addr)?;
+                self.node(il, *val)?;
+                let fld = *self.asm.get_field(*field);
+                let fld = self.field_token(fld.owner(), fld.name(), fld.tpe(), false)?;
+                il.op_token(op::STFLD, fld);
+            }
+            CILRoot::Call(call) => {
+                if self.asm[call.0].kind() == MethodKind::Constructor {
+                    return Err(PEError::Unsupported(
+                        "A constructor can't be a CIL root".into(),
+                    ));
+                }
+                self.call(il, call.0, &call.1)?;
+            }
+            CILRoot::StInd(stind) => {
+                let (addr, val, tpe, volatile) = stind.as_ref();
+                self.node(il, *addr)?;
+                self.node(il, *val)?;
+                if *volatile {
+                    il.op(op::VOLATILE);
+                }
+                let opcode = match tpe {
+                    Type::Ptr(_) | Type::FnPtr(_) => op::STIND_I,
+                    Type::Int(int) => match int {
+                        Int::U8 | Int::I8 => op::STIND_I1,
+                        Int::U16 | Int::I16 => op::STIND_I2,
+                        Int::U32 | Int::I32 => op::STIND_I4,
+                        Int::U64 | Int::I64 => op::STIND_I8,
+                        Int::USize | Int::ISize => op::STIND_I,
+                        Int::U128 | Int::I128 => {
+                            let tpe = self.type_token(*tpe)?;
+                            il.op_token(op::STOBJ, tpe);
+                            return Ok(());
+                        }
+                    },
+                    Type::ClassRef(cref) if !self.asm[*cref].is_valuetype() => op::STIND_REF,
+                    Type::ClassRef(_) | Type::Float(Float::F128) | Type::SIMDVector(_) => {
+                        let tpe = self.type_token(*tpe)?;
+                        il.op_token(op::STOBJ, tpe);
+                        return Ok(());
+                    }
+                    Type::Float(Float::F32) => op::STIND_R4,
+                    Type::Float(Float::F64) => op::STIND_R8,
+                    Type::PlatformString | Type::PlatformObject | Type::PlatformArray { .. } => {
+                        op::STIND_REF
+                    }
+                    Type::PlatformChar => op::STIND_I2,
+                    Type::Bool => op::STIND_I1,
+                    Type::Void => {
+                        il.op(op::POP);
+                        il.op(op::POP);
+                        self.throw_msg(il, "Attempted to wrtie to a zero-sized type(void).");
+                        return Ok(());
+                    }
+                    Type::Ref(_) | Type::PlatformGeneric(_, _) | Type::Float(Float::F16) => {
+                        return Err(PEError::Unsupported(format!("writing to {tpe:?}")))
+                    }
+                };
+                il.op(opcode);
+            }
+            CILRoot::InitBlk(ref blk) | CILRoot::CpBlk(ref blk) => {
+                let opcode = if matches!(root, CILRoot::InitBlk(_)) {
+                    op::INITBLK
+                } else {
+                    op::CPBLK
+                };
+                self.node(il, blk.0)?;
+                self.node(il, blk.1)?;
+                self.node(il, blk.2)?;
+                il.op(opcode);
+            }
+            CILRoot::CallI(calli) => {
+                let (fn_ptr, sig, args) = calli.as_ref();
+                self.calli(il, *fn_ptr, *sig, args)?;
+            }
+            CILRoot::ExitSpecialRegion { target, source } => {
+                let label = if is_handler {
+                    Label::Handler(source, target)
+                } else if has_handler {
+                    Label::Jump(source, target)
+                } else {
+                    return Ok(());
+                };
+                il.label(label)?;
+                il.branch(op::LEAVE, Label::Block(target));
+            }
+            CILRoot::ReThrow => il.op(op::RETHROW),
+            CILRoot::SetStaticField { field, val } => {
+                self.node(il, val)?;
+                let sfld = *self.asm.get_static_field(field);
+                let sfld = self.field_token(sfld.owner(), sfld.name(), sfld.tpe(), true)?;
+                il.op_token(op::STSFLD, sfld);
+            }
+            CILRoot::CpObj { src, dst, tpe } => {
+                self.node(il, src)?;
+                self.node(il, dst)?;
+                let tpe = self.type_token(self.asm[tpe])?;
+                il.op_token(op::CPOBJ, tpe);
+            }
+            CILRoot::Unreachable(msg) => {
+                let msg = self.asm[msg].to_string();
+                self.throw_msg(il, &msg);
+            }
+            CILRoot::InitObj(addr, tpe) => {
+                self.node(il, addr)?;
+                let tpe = self.type_token(self.asm[tpe])?;
+                il.op_token(op::INITOBJ, tpe);
+            }
+            CILRoot::InlineAsm(_) => {
+                return Err(PEError::Unsupported("inline assembly".into()));
+            }
+            CILRoot::VaStart(_) | CILRoot::VaArg(_) => {
+                return Err(PEError::Unsupported("C-variadic method definitions".into()));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,233 @@
+//! Writes an [`Assembly`] straight to a .NET PE image(ECMA-335 II.24 - II.25) and a portable PDB, without going
+//! trough a `.il` file and `ilasm`.
+//!
+//! The image is read back and validated before being written out, so that malformed metadata is reported here, and
+//! not as a cryptic `BadImageFormatException` at runtime.
+use fxhash::FxHashSet;
+
+use super::{Assembly, Exporter};
+use crate::calculate_hash;
+
+pub mod bindgen;
+mod heaps;
+mod il;
+mod metadata;
+mod pdb;
+mod pe;
+mod reader;
+mod tables;
+
+use pe::CODE_RVA;
+use tables::TableId;
+
+/// An error encountered while exporting an assembly.
+#[derive(Debug)]
+pub enum PEError {
+    /// Could not write the image or the PDB.
+    Io(std::io::Error),
+    /// The assembly uses a feature, which can't be expressed by this exporter.
+    Unsupported(String),
+    /// The self-check found an issue with the resulting image. This is a bug in the exporter.
+    SelfCheck(String),
+    /// An image, which was not produced by this exporter, could not be read.
+    BadImage(String),
+}
+impl std::fmt::Display for PEError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "IO error: {err}"),
+            Self::Unsupported(msg) => write!(f, "unsupported: {msg}"),
+            Self::SelfCheck(msg) => write!(f, "self-check failed: {msg}"),
+            Self::BadImage(msg) => write!(f, "malformed image: {msg}"),
+        }
+    }
+}
+impl std::error::Error for PEError {}
+impl From<std::io::Error> for PEError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+/// Derives a (version 4) GUID from `data`, so that identical assemblies get identical IDs.
+fn hash_guid(data: &[&[u8]]) -> [u8; 16] {
+    let mut guid = [0; 16];
+    guid[..8].copy_from_slice(&calculate_hash(&(0_u8, data)).to_le_bytes());
+    guid[8..].copy_from_slice(&calculate_hash(&(1_u8, data)).to_le_bytes());
+    guid[7] = (guid[7] & 0x0F) | 0x40;
+    guid[8] = (guid[8] & 0x3F) | 0x80;
+    guid
+}
+/// Builds the PE image and the portable PDB of `asm`. `module_name` is the file name of the image, and `pdb_path`
+/// is the path of the PDB, stored in the image.
+/// # Errors
+/// Returns an error if the assembly can't be encoded, or the resulting image fails the self-check.
+pub fn build_image(
+    asm: &Assembly,
+    module_name: &str,
+    pdb_path: &str,
+    is_lib: bool,
+) -> Result<(Vec<u8>, Vec<u8>), PEError> {
+    let mut asm = asm.clone();
+    let metadata::Metadata {
+        tables,
+        mut heaps,
+        code,
+        entrypoint,
+        mvid,
+        debug,
+    } = metadata::MetadataBuilder::new(&mut asm).build(module_name, is_lib)?;
+    let rows = tables.row_counts();
+    let mut streams = vec![("#~", tables.serialize(heaps.size_flags(), &[0; 64]))];
+    streams.extend(heaps.streams());
+    let unpatched = pe::metadata_root("v4.0.30319", &streams);
+    heaps.set_guid(mvid, hash_guid(&[&code, &unpatched]));
+    streams.truncate(1);
+    streams.extend(heaps.streams());
+    let metadata = pe::metadata_root("v4.0.30319", &streams);
+    let mut pdb = pdb::write_pdb(&asm, &debug, &rows, entrypoint, [0; 20]);
+    let mut id = [0; 20];
+    id[..16].copy_from_slice(&hash_guid(&[&pdb, &metadata]));
+    id[16..].copy_from_slice(&(calculate_hash(&pdb) as u32).to_le_bytes());
+    let id_offset = pdb::id_offset(&pdb);
+    pdb[id_offset..id_offset + 20].copy_from_slice(&id);
+    let image = pe::write_image(
+        &code,
+        &metadata,
+        entrypoint,
+        is_lib,
+        &pe::DebugInfo { id, path: pdb_path },
+    );
+    self_check(&asm, &image, &pdb)?;
+    Ok((image, pdb))
+}
+/// Reads back the image and the PDB, and checks that they contain all the types and methods of `asm`.
+fn self_check(asm: &Assembly, image: &[u8], pdb: &[u8]) -> Result<(), PEError> {
+    let reader = reader::PEReader::new(image)?;
+    reader::check_pdb(pdb, &reader)?;
+    let metadata = &reader.metadata;
+    let type_defs = metadata
+        .rows(TableId::TypeDef)
+        .iter()
+        .map(|row| {
+            let (name, namespace) = (metadata.string(row[1])?, metadata.string(row[2])?);
+            Ok(if namespace.is_empty() {
+                name.to_string()
+            } else {
+                format!("{namespace}.{name}")
+            })
+        })
+        .collect::<Result<FxHashSet<_>, PEError>>()?;
+    let mut methods = 0;
+    for class in asm.class_defs().values() {
+        let name = &asm[class.name()];
+        if !type_defs.contains(name) {
+            return Err(PEError::SelfCheck(format!("the class {name} is missing")));
+        }
+        methods += class.methods().len() as u32;
+    }
+    if reader.row_count(TableId::MethodDef) != methods {
+        return Err(PEError::SelfCheck(format!(
+            "expected {methods} methods, but the image has {}",
+            reader.row_count(TableId::MethodDef)
+        )));
+    }
+    Ok(())
+}
+/// Exports the assembly as a `.exe` file, and a `.pdb` file next to it.
+pub struct PEExporter {
+    is_lib: bool,
+}
+impl PEExporter {
+    #[must_use]
+    pub fn new(is_lib: bool) -> Self {
+        Self { is_lib }
+    }
+}
+impl Exporter for PEExporter {
+    type Error = PEError;
+
+    fn export(&mut self, asm: &Assembly, target: &std::path::Path) -> Result<(), Self::Error> {
+        let exe_path = std::path::absolute(target.with_extension("exe"))?;
+        let pdb_path = exe_path.with_extension("pdb");
+        let module_name = exe_path
+            .file_name()
+            .map_or_else(|| "_.exe".into(), |name| name.to_string_lossy());
+        let (image, pdb) =
+            build_image(asm, &module_name, &pdb_path.to_string_lossy(), self.is_lib)?;
+        std::fs::write(&exe_path, image)?;
+        std::fs::write(&pdb_path, pdb)?;
+        Ok(())
+    }
+}
+#[test]
+fn self_check_roundtrip() {
+    let asm = super::cillytext::parse(
+        r#"
+(classdef public class "MainModule"
+  (method public static "sum" (sig (u32) u32) (args "n")
+    (locals (local u32) (local u32))
+    (block 0
+      (sfi "/src/main.rs" 1 0 1 10)
+      (stloc 0 (const u32 0))
+      (stloc 1 (const u32 0))
+      (br 1 0))
+    (block 1
+      (sfi "/src/main.rs" 2 0 5 10)
+      (br 2 0 (gt unsigned (ldloc 1) (ldarg 0)))
+      (stloc 0 (add (ldloc 0) (ldloc 1)))
+      (stloc 1 (add (ldloc 1) (const u32 1)))
+      (br 1 0))
+    (block 2 (ret (ldloc 0))))
+  (method extern static "puts" (sig ((ptr u8)) i32) (args _) (extern "libc"))
+  (method public static "catch" (sig (bool) i32) (args "throw")
+    (locals)
+    (block 0
+      (br 1 0 (true (ldarg 0)))
+      (ret (const i32 0)))
+    (block 1
+      (throw (const string "oops"))
+      (handler
+        (block 3
+          (br 1 4 (eq (get_exception) (const string "oops")))
+          (rethrow))
+        (block 4 (exit_region 2 3))))
+    (block 2 (ret (const i32 1))))
+  (method public static "pick" (sig (u32) i32) (args "idx")
+    (locals)
+    (block 0 (switch (ldarg 0) (default 3 0) (1 0) (2 0) (1 0)))
+    (block 1 (ret (const i32 1)))
+    (block 2 (ret (const i32 2)))
+    (block 3 (ret (const i32 3))))
+  (method public static "entrypoint" (sig () void) (args)
+    (locals)
+    (block 0 (ret_void))))
+"#,
+    )
+    .unwrap();
+    let (image, pdb) = build_image(&asm, "test.exe", "test.pdb", false).unwrap();
+    let reader = reader::PEReader::new(&image).unwrap();
+    assert_ne!(reader.entrypoint, 0);
+    // `MainModule`, `RustVoid` and `<Module>`
+    assert_eq!(reader.row_count(TableId::TypeDef), 3);
+    assert_eq!(reader.row_count(TableId::MethodDef), 5);
+    assert_eq!(reader.row_count(TableId::ImplMap), 1);
+    // The output is deterministic.
+    assert_eq!(
+        build_image(&asm, "test.exe", "test.pdb", false).unwrap(),
+        (image, pdb)
+    );
+}
+#[test]
+fn pe_vararg_calls() {
+    let mut asm = super::cillytext::parse(super::vararg::VARIADIC).unwrap();
+    assert!(build_image(&asm, "test.exe", "test.pdb", false).is_err());
+    asm.lower_vararg_calls();
+    let (image, _) = build_image(&asm, "test.exe", "test.pdb", false).unwrap();
+    // `printf`, and its declaration with the fixed signature.
+    assert_eq!(
+        reader::PEReader::new(&image)
+            .unwrap()
+            .row_count(TableId::ImplMap),
+        2
+    );
+}
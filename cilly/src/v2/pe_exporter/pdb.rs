@@ -0,0 +1,168 @@
+//! Portable PDB(https://github.com/dotnet/runtime/blob/main/docs/design/specs/PortablePdb-Metadata.md) writer.
+use fxhash::FxHashMap;
+
+use super::super::{bimap::Interned, Assembly};
+use super::{
+    heaps::{compress_i32, compress_u32, Heaps},
+    metadata::{MethodDebugInfo, SequencePoint},
+    pe::metadata_root,
+    tables::{TableId, Tables},
+};
+use crate::IString;
+
+/// Language GUID used for the documents: the Rust language does not have a well-known one.
+const RUST_LANGUAGE: [u8; 16] = [
+    0x3B, 0x2F, 0x9C, 0x1E, 0xB2, 0x4D, 0x4F, 0x4B, 0xA0, 0x64, 0x1B, 0x5E, 0x6E, 0x0A, 0x75, 0x72,
+];
+/// Tables of the type system, which the PDB may refer to.
+const TYPE_SYSTEM_TABLES: u64 = 0x0000_1FFF_FFFF_FFFF;
+/// Largest line number allowed in a sequence point.
+const MAX_LINE: u32 = 0x2000_0000;
+/// Writes a portable PDB. `type_system_rows` are the row counts of the tables of the image.
+pub(super) fn write_pdb(
+    asm: &Assembly,
+    debug: &[MethodDebugInfo],
+    type_system_rows: &[u32; 64],
+    entrypoint: u32,
+    id: [u8; 20],
+) -> Vec<u8> {
+    let mut tables = Tables::default();
+    let mut heaps = Heaps::default();
+    let mut documents: FxHashMap<Interned<IString>, u32> = FxHashMap::default();
+    for info in debug {
+        let points = sequence_points(&info.sequence_points);
+        let Some(first) = points.first() else {
+            tables.push(TableId::MethodDebugInformation, [0, 0]);
+            continue;
+        };
+        let initial = document_row(first.file, asm, &mut documents, &mut tables, &mut heaps);
+        let single_document = points.iter().all(|point| point.file == first.file);
+        let mut blob = vec![];
+        compress_u32(info.locals_sig, &mut blob);
+        if !single_document {
+            compress_u32(initial, &mut blob);
+        }
+        let mut current = first.file;
+        let mut prev: Option<&SequencePoint> = None;
+        for point in &points {
+            if point.file != current {
+                // A document record.
+                blob.push(0);
+                let document =
+                    document_row(point.file, asm, &mut documents, &mut tables, &mut heaps);
+                compress_u32(document, &mut blob);
+                current = point.file;
+            }
+            compress_u32(
+                point.il_offset - prev.map_or(0, |prev| prev.il_offset),
+                &mut blob,
+            );
+            compress_u32(point.line_len.into(), &mut blob);
+            if point.line_len == 0 {
+                compress_u32(point.col_len.into(), &mut blob);
+            } else {
+                compress_i32(point.col_len.into(), &mut blob);
+            }
+            match prev {
+                None => {
+                    compress_u32(point.line_start, &mut blob);
+                    compress_u32(point.col_start.into(), &mut blob);
+                }
+                Some(prev) => {
+                    compress_i32(point.line_start as i32 - prev.line_start as i32, &mut blob);
+                    compress_i32(
+                        i32::from(point.col_start) - i32::from(prev.col_start),
+                        &mut blob,
+                    );
+                }
+            }
+            prev = Some(point);
+        }
+        let document = if single_document { initial } else { 0 };
+        let blob = heaps.blob(&blob);
+        tables.push(TableId::MethodDebugInformation, [document, blob]);
+    }
+    let referenced = type_system_rows
+        .iter()
+        .enumerate()
+        .filter(|(table, rows)| TYPE_SYSTEM_TABLES & (1 << table) != 0 && **rows > 0)
+        .fold(0_u64, |mask, (table, _)| mask | (1 << table));
+    let mut pdb_stream = id.to_vec();
+    pdb_stream.extend(entrypoint.to_le_bytes());
+    pdb_stream.extend(referenced.to_le_bytes());
+    for (table, rows) in type_system_rows.iter().enumerate() {
+        if referenced & (1 << table) != 0 {
+            pdb_stream.extend(rows.to_le_bytes());
+        }
+    }
+    let mut streams = vec![
+        ("#Pdb", pdb_stream),
+        ("#~", tables.serialize(heaps.size_flags(), type_system_rows)),
+    ];
+    streams.extend(heaps.streams());
+    metadata_root("PDB v1.0", &streams)
+}
+/// Offset of the PDB ID within a PDB written by [`write_pdb`].
+pub(super) fn id_offset(pdb: &[u8]) -> usize {
+    // The `#Pdb` stream is always first, and its offset is stored right after the version string.
+    let version_len = u32::from_le_bytes(pdb[12..16].try_into().unwrap()) as usize;
+    let header = 16 + version_len + 4;
+    u32::from_le_bytes(pdb[header..header + 4].try_into().unwrap()) as usize
+}
+/// Sorts sequence points by offset, and drops the ones the format can't express: those with the same offset
+/// (only the last one is kept), and those with invalid line numbers.
+fn sequence_points(points: &[SequencePoint]) -> Vec<SequencePoint> {
+    let mut sorted: Vec<&SequencePoint> = points.iter().collect();
+    sorted.sort_by_key(|point| point.il_offset);
+    let mut out: Vec<SequencePoint> = vec![];
+    for point in sorted {
+        if point.line_start == 0 || point.line_start + u32::from(point.line_len) >= MAX_LINE {
+            continue;
+        }
+        let mut point = *point;
+        // A sequence point spanning no columns would be a hidden one.
+        if point.line_len == 0 && point.col_len == 0 {
+            point.col_len = 1;
+        }
+        match out.last_mut() {
+            Some(last) if last.il_offset == point.il_offset => *last = point,
+            _ => out.push(point),
+        }
+    }
+    out
+}
+/// Returns the row of the document describing `file`, adding it if needed.
+fn document_row(
+    file: Interned<IString>,
+    asm: &Assembly,
+    documents: &mut FxHashMap<Interned<IString>, u32>,
+    tables: &mut Tables,
+    heaps: &mut Heaps,
+) -> u32 {
+    *documents.entry(file).or_insert_with(|| {
+        let name = document_name(&asm[file], heaps);
+        let language = heaps.guid(RUST_LANGUAGE);
+        tables.push(TableId::Document, [name, 0, 0, language])
+    })
+}
+/// Encodes the name of a document, as a list of path segments.
+fn document_name(path: &str, heaps: &mut Heaps) -> u32 {
+    let separator = if path.contains('/') {
+        '/'
+    } else if path.contains('\\') {
+        '\\'
+    } else {
+        '\0'
+    };
+    let mut blob = vec![separator as u8];
+    let parts: Vec<&str> = if separator == '\0' {
+        vec![path]
+    } else {
+        path.split(separator).collect()
+    };
+    for part in parts {
+        let part = heaps.blob(part.as_bytes());
+        compress_u32(part, &mut blob);
+    }
+    heaps.blob(&blob)
+}
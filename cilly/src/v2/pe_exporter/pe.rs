@@ -0,0 +1,177 @@
+//! The PE/COFF container(ECMA-335 II.25), and the metadata root(ECMA-335 II.24.2.1).
+use super::heaps::pad4;
+
+/// RVA of the only section of the image, `.text`.
+pub(super) const TEXT_RVA: u32 = 0x2000;
+/// Size of the CLI header, placed at the very start of `.text`.
+const CLI_HEADER_SIZE: u32 = 72;
+/// RVA of the method bodies and field data. Aligned to 16 bytes, so that field data can be aligned too.
+pub(super) const CODE_RVA: u32 = TEXT_RVA + CLI_HEADER_SIZE.next_multiple_of(16);
+const FILE_ALIGNMENT: u32 = 0x200;
+const SECTION_ALIGNMENT: u32 = 0x2000;
+const IMAGE_BASE: u32 = 0x0040_0000;
+/// Offset of the PE signature. The DOS header, and a stub program fit before it.
+const PE_OFFSET: u32 = 0x80;
+const HEADERS_SIZE: u32 = PE_OFFSET + 4 + 20 + 0xE0 + 40;
+/// A minimal DOS stub program, printing "This program cannot be run in DOS mode."
+const DOS_STUB: [u8; 64] = [
+    0x0E, 0x1F, 0xBA, 0x0E, 0x00, 0xB4, 0x09, 0xCD, 0x21, 0xB8, 0x01, 0x4C, 0xCD, 0x21, 0x54, 0x68,
+    0x69, 0x73, 0x20, 0x70, 0x72, 0x6F, 0x67, 0x72, 0x61, 0x6D, 0x20, 0x63, 0x61, 0x6E, 0x6E, 0x6F,
+    0x74, 0x20, 0x62, 0x65, 0x20, 0x72, 0x75, 0x6E, 0x20, 0x69, 0x6E, 0x20, 0x44, 0x4F, 0x53, 0x20,
+    0x6D, 0x6F, 0x64, 0x65, 0x2E, 0x0D, 0x0D, 0x0A, 0x24, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+/// Builds a metadata root, containing `streams`. `version` is "v4.0.30319" for images, and "PDB v1.0" for PDBs.
+pub(super) fn metadata_root(version: &str, streams: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    let mut version = version.as_bytes().to_vec();
+    version.push(0);
+    pad4(&mut version);
+    let mut out = vec![];
+    out.extend(0x424A_5342_u32.to_le_bytes());
+    out.extend([1, 0, 1, 0]);
+    out.extend(0_u32.to_le_bytes());
+    out.extend((version.len() as u32).to_le_bytes());
+    out.extend(version);
+    out.extend(0_u16.to_le_bytes());
+    out.extend((streams.len() as u16).to_le_bytes());
+    let header_size: usize = out.len()
+        + streams
+            .iter()
+            .map(|(name, _)| 8 + (name.len() + 1).next_multiple_of(4))
+            .sum::<usize>();
+    let mut offset = header_size as u32;
+    for (name, data) in streams {
+        out.extend(offset.to_le_bytes());
+        out.extend((data.len() as u32).to_le_bytes());
+        out.extend(name.as_bytes());
+        out.push(0);
+        pad4(&mut out);
+        offset += data.len() as u32;
+    }
+    for (_, data) in streams {
+        out.extend(data);
+    }
+    out
+}
+/// Identifies the portable PDB belonging to an image.
+pub(super) struct DebugInfo<'a> {
+    /// The PDB ID: a GUID followed by a timestamp.
+    pub id: [u8; 20],
+    pub path: &'a str,
+}
+/// Builds a PE32 image with a single `.text` section, containing the CLI header, `code`(placed at [`CODE_RVA`]),
+/// `metadata`, and a debug directory pointing to the PDB.
+pub(super) fn write_image(
+    code: &[u8],
+    metadata: &[u8],
+    entrypoint: u32,
+    is_lib: bool,
+    debug: &DebugInfo,
+) -> Vec<u8> {
+    let metadata_rva = (CODE_RVA + code.len() as u32).next_multiple_of(4);
+    let debug_dir_rva = (metadata_rva + metadata.len() as u32).next_multiple_of(4);
+    let codeview_rva = debug_dir_rva + 28;
+    let mut codeview = b"RSDS".to_vec();
+    codeview.extend(&debug.id[..16]);
+    codeview.extend(1_u32.to_le_bytes());
+    codeview.extend(debug.path.as_bytes());
+    codeview.push(0);
+    let text_size = codeview_rva + codeview.len() as u32 - TEXT_RVA;
+    let text_raw_size = text_size.next_multiple_of(FILE_ALIGNMENT);
+    let headers_size = HEADERS_SIZE.next_multiple_of(FILE_ALIGNMENT);
+    let image_size = TEXT_RVA + text_size.next_multiple_of(SECTION_ALIGNMENT);
+    let timestamp = u32::from_le_bytes(debug.id[16..].try_into().unwrap());
+    let mut out = vec![];
+    // DOS header: only the magic and the offset of the PE header matter.
+    out.extend(b"MZ");
+    out.resize(0x3C, 0);
+    out.extend(PE_OFFSET.to_le_bytes());
+    out.extend(DOS_STUB);
+    debug_assert_eq!(out.len(), PE_OFFSET as usize);
+    out.extend(b"PE\0\0");
+    // COFF header
+    out.extend(0x014C_u16.to_le_bytes());
+    out.extend(1_u16.to_le_bytes());
+    out.extend(timestamp.to_le_bytes());
+    out.extend(0_u64.to_le_bytes());
+    out.extend(0xE0_u16.to_le_bytes());
+    // EXECUTABLE_IMAGE | 32BIT_MACHINE, and DLL for libraries
+    let characteristics: u16 = if is_lib { 0x2102 } else { 0x0102 };
+    out.extend(characteristics.to_le_bytes());
+    // Optional header
+    out.extend(0x010B_u16.to_le_bytes());
+    out.extend([8, 0]);
+    out.extend(text_raw_size.to_le_bytes());
+    out.extend(0_u64.to_le_bytes());
+    // No native entrypoint: the runtime starts the image using the CLI header.
+    out.extend(0_u32.to_le_bytes());
+    out.extend(TEXT_RVA.to_le_bytes());
+    out.extend(0_u32.to_le_bytes());
+    out.extend(IMAGE_BASE.to_le_bytes());
+    out.extend(SECTION_ALIGNMENT.to_le_bytes());
+    out.extend(FILE_ALIGNMENT.to_le_bytes());
+    for version in [4_u16, 0, 0, 0, 4, 0] {
+        out.extend(version.to_le_bytes());
+    }
+    out.extend(0_u32.to_le_bytes());
+    out.extend(image_size.to_le_bytes());
+    out.extend(headers_size.to_le_bytes());
+    out.extend(0_u32.to_le_bytes());
+    // Console subsystem, with DYNAMIC_BASE | NX_COMPAT | NO_SEH | TERMINAL_SERVER_AWARE
+    out.extend(3_u16.to_le_bytes());
+    out.extend(0x8540_u16.to_le_bytes());
+    for size in [0x0010_0000_u32, 0x1000, 0x0010_0000, 0x1000] {
+        out.extend(size.to_le_bytes());
+    }
+    out.extend(0_u32.to_le_bytes());
+    out.extend(16_u32.to_le_bytes());
+    for dir in 0..16 {
+        let (rva, size) = match dir {
+            6 => (debug_dir_rva, 28),
+            14 => (TEXT_RVA, CLI_HEADER_SIZE),
+            _ => (0, 0),
+        };
+        out.extend(rva.to_le_bytes());
+        out.extend(size.to_le_bytes());
+    }
+    // Section header
+    out.extend(b".text\0\0\0");
+    out.extend(text_size.to_le_bytes());
+    out.extend(TEXT_RVA.to_le_bytes());
+    out.extend(text_raw_size.to_le_bytes());
+    out.extend(headers_size.to_le_bytes());
+    out.extend([0; 12]);
+    // CODE | EXECUTE | READ
+    out.extend(0x6000_0020_u32.to_le_bytes());
+    debug_assert_eq!(out.len(), HEADERS_SIZE as usize);
+    out.resize(headers_size as usize, 0);
+    // CLI header
+    let text_start = out.len();
+    out.extend(CLI_HEADER_SIZE.to_le_bytes());
+    out.extend(2_u16.to_le_bytes());
+    out.extend(5_u16.to_le_bytes());
+    out.extend(metadata_rva.to_le_bytes());
+    out.extend((metadata.len() as u32).to_le_bytes());
+    // ILONLY
+    out.extend(1_u32.to_le_bytes());
+    out.extend(if is_lib { 0 } else { entrypoint }.to_le_bytes());
+    out.extend([0; 48]);
+    let at_rva =
+        |out: &mut Vec<u8>, rva: u32| out.resize(text_start + (rva - TEXT_RVA) as usize, 0);
+    at_rva(&mut out, CODE_RVA);
+    out.extend(code);
+    at_rva(&mut out, metadata_rva);
+    out.extend(metadata);
+    at_rva(&mut out, debug_dir_rva);
+    // Debug directory entry, describing a portable PDB.
+    out.extend(0_u32.to_le_bytes());
+    out.extend(timestamp.to_le_bytes());
+    out.extend(0x0100_u16.to_le_bytes());
+    out.extend(0x504D_u16.to_le_bytes());
+    out.extend(2_u32.to_le_bytes());
+    out.extend((codeview.len() as u32).to_le_bytes());
+    out.extend(codeview_rva.to_le_bytes());
+    out.extend((text_start as u32 + codeview_rva - TEXT_RVA).to_le_bytes());
+    out.extend(codeview);
+    out.resize(text_start + text_raw_size as usize, 0);
+    out
+}
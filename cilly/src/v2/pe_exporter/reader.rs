@@ -0,0 +1,538 @@
+//! Reads back images and PDBs written by the exporter, validating their structure. Used as a self-check, so that
+//! malformed images are caught here, and not by the runtime. Images produced by other compilers can be read too(see
+//! [`PEReader::open`]), which is how [`super::bindgen`] gets at the metadata of reference assemblies.
+use fxhash::FxHashMap;
+
+use super::{
+    heaps::{decompress_i32, decompress_u32},
+    il::{operand, Operand},
+    tables::{Column, Layout, TableId, SORTED_MASK},
+    PEError,
+};
+
+fn error(msg: impl Into<String>) -> PEError {
+    PEError::SelfCheck(msg.into())
+}
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, PEError> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| error(format!("unexpected end of data at {offset:#x}")))
+}
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, PEError> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| error(format!("unexpected end of data at {offset:#x}")))
+}
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, PEError> {
+    Ok(u64::from(read_u32(data, offset)?) | (u64::from(read_u32(data, offset + 4)?) << 32))
+}
+/// The decoded contents of a metadata root.
+pub(super) struct MetadataReader<'data> {
+    pub streams: FxHashMap<&'data str, &'data [u8]>,
+    pub layout: Layout,
+    /// Decoded rows of every table.
+    pub rows: Vec<Vec<Box<[u32]>>>,
+}
+impl<'data> MetadataReader<'data> {
+    /// Parses a metadata root. `external` holds the row counts of tables stored in another image.
+    pub fn new(data: &'data [u8], external: Option<&[u32; 64]>) -> Result<Self, PEError> {
+        Self::read(data, external, true)
+    }
+    /// Parses a metadata root. If `strict` is set, the set of sorted tables must match the one used by the exporter.
+    fn read(
+        data: &'data [u8],
+        external: Option<&[u32; 64]>,
+        strict: bool,
+    ) -> Result<Self, PEError> {
+        if read_u32(data, 0)? != 0x424A_5342 {
+            return Err(error("bad metadata signature"));
+        }
+        let streams = Self::streams_only(data)?;
+        let tables = *streams
+            .get("#~")
+            .ok_or_else(|| error("missing the #~ stream"))?;
+        let heap_sizes = *tables.get(6).ok_or_else(|| error("truncated #~ stream"))?;
+        let valid = read_u64(tables, 8)?;
+        let sorted = read_u64(tables, 16)?;
+        if strict && sorted & valid != SORTED_MASK & valid {
+            return Err(error(format!("wrong sorted table mask {sorted:#x}")));
+        }
+        let mut own = [0; 64];
+        let mut offset = 24;
+        for (table, rows) in own.iter_mut().enumerate() {
+            if valid & (1 << table) != 0 {
+                if TableId::from_u8(table as u8).is_none() {
+                    return Err(error(format!("unknown table {table:#x}")));
+                }
+                *rows = read_u32(tables, offset)?;
+                offset += 4;
+            }
+        }
+        let external = external.unwrap_or(&[0; 64]);
+        let layout = Layout {
+            rows: std::array::from_fn(|table| own[table].max(external[table])),
+            heap_sizes,
+        };
+        let size: usize = TableId::ALL
+            .iter()
+            .map(|table| own[*table as usize] as usize * layout.row_size(*table))
+            .sum();
+        if offset + size > tables.len() {
+            return Err(error("the tables do not fit in the #~ stream"));
+        }
+        let mut rows = vec![vec![]; 64];
+        for table in TableId::ALL {
+            for _ in 0..own[table as usize] {
+                let mut row = vec![];
+                for column in table.schema() {
+                    let val = match layout.column_size(*column) {
+                        2 => u32::from(read_u16(tables, offset)?),
+                        _ => read_u32(tables, offset)?,
+                    };
+                    offset += layout.column_size(*column);
+                    row.push(val);
+                }
+                rows[table as usize].push(row.into_boxed_slice());
+            }
+        }
+        // Only the row counts of this image are used from now on, except for references to external tables.
+        let reader = Self {
+            streams,
+            layout: Layout {
+                rows: own,
+                heap_sizes,
+            },
+            rows,
+        };
+        reader.validate(external)?;
+        Ok(reader)
+    }
+    /// Reads only the stream headers of a metadata root.
+    fn streams_only(data: &'data [u8]) -> Result<FxHashMap<&'data str, &'data [u8]>, PEError> {
+        let version_len = read_u32(data, 12)? as usize;
+        let mut offset = 16 + version_len + 2;
+        let stream_count = read_u16(data, offset)?;
+        offset += 2;
+        let mut streams = FxHashMap::default();
+        for _ in 0..stream_count {
+            let start = read_u32(data, offset)? as usize;
+            let size = read_u32(data, offset + 4)? as usize;
+            let name_len = data[offset + 8..]
+                .iter()
+                .position(|byte| *byte == 0)
+                .ok_or_else(|| error("unterminated stream name"))?;
+            let name = std::str::from_utf8(&data[offset + 8..offset + 8 + name_len])
+                .map_err(|_| error("stream name is not valid UTF-8"))?;
+            offset += 8 + (name_len + 1).next_multiple_of(4);
+            let stream = data
+                .get(start..start + size)
+                .ok_or_else(|| error(format!("stream {name} is out of bounds")))?;
+            streams.insert(name, stream);
+        }
+        Ok(streams)
+    }
+    fn heap(&self, name: &str) -> &'data [u8] {
+        self.streams.get(name).copied().unwrap_or(&[])
+    }
+    /// Returns the string at `idx` of the `#Strings` heap.
+    pub fn string(&self, idx: u32) -> Result<&'data str, PEError> {
+        let heap = self.heap("#Strings");
+        let start = heap
+            .get(idx as usize..)
+            .ok_or_else(|| error(format!("string index {idx:#x} out of bounds")))?;
+        let len = start
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or_else(|| error(format!("unterminated string at {idx:#x}")))?;
+        std::str::from_utf8(&start[..len]).map_err(|_| error(format!("invalid string at {idx:#x}")))
+    }
+    /// Returns the blob at `idx` of the `#Blob` heap.
+    pub fn blob(&self, idx: u32) -> Result<&'data [u8], PEError> {
+        let heap = self.heap("#Blob");
+        let start = heap
+            .get(idx as usize..)
+            .ok_or_else(|| error(format!("blob index {idx:#x} out of bounds")))?;
+        let (len, len_size) =
+            decompress_u32(start).ok_or_else(|| error(format!("bad blob length at {idx:#x}")))?;
+        start
+            .get(len_size..len_size + len as usize)
+            .ok_or_else(|| error(format!("blob at {idx:#x} is out of bounds")))
+    }
+    pub fn rows(&self, table: TableId) -> &[Box<[u32]>] {
+        &self.rows[table as usize]
+    }
+    /// Checks that every heap and table index points to an existing entry, and that tables are properly ordered.
+    fn validate(&self, external: &[u32; 64]) -> Result<(), PEError> {
+        let guids = self.heap("#GUID").len() as u32 / 16;
+        let row_count =
+            |table: TableId| self.layout.rows[table as usize].max(external[table as usize]);
+        for table in TableId::ALL {
+            let rows = self.rows(table);
+            for (idx, row) in rows.iter().enumerate() {
+                for (column_idx, (val, column)) in row.iter().zip(table.schema()).enumerate() {
+                    let ok = match column {
+                        Column::U16 | Column::U32 => true,
+                        Column::Str => self.string(*val).is_ok(),
+                        Column::Guid => *val <= guids,
+                        Column::Blob => self.blob(*val).is_ok(),
+                        // List columns may point one past the end of the target table.
+                        Column::Table(target) if table.is_list(column_idx) => {
+                            *val >= 1 && *val <= row_count(*target) + 1
+                        }
+                        Column::Table(target) => *val <= row_count(*target),
+                        Column::Coded(coded) => {
+                            *val == 0
+                                || coded
+                                    .decode(*val)
+                                    .is_some_and(|(target, row)| row <= row_count(target))
+                        }
+                    };
+                    if !ok {
+                        return Err(error(format!(
+                            "{table:?} row {} column {column_idx}({column:?}) has an invalid value {val:#x}",
+                            idx + 1
+                        )));
+                    }
+                    if table.is_list(column_idx) && idx > 0 && rows[idx - 1][column_idx] > *val {
+                        return Err(error(format!(
+                            "{table:?} row {} column {column_idx} is not monotonic",
+                            idx + 1
+                        )));
+                    }
+                }
+            }
+            if let Some(key) = table.sort_key() {
+                if rows.windows(2).any(|pair| pair[0][key] > pair[1][key]) {
+                    return Err(error(format!("{table:?} is not sorted")));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+/// A PE image, read by [`PEReader::new`] or [`PEReader::open`].
+pub(super) struct PEReader<'data> {
+    data: &'data [u8],
+    /// Virtual address, size and file offset of every section.
+    sections: Vec<(u32, u32, u32)>,
+    pub metadata: MetadataReader<'data>,
+    pub entrypoint: u32,
+}
+impl<'data> PEReader<'data> {
+    /// Reads an image written by the exporter, checking it, and all the method bodies in it.
+    pub fn new(data: &'data [u8]) -> Result<Self, PEError> {
+        let reader = Self::read(data, true)?;
+        reader.validate()?;
+        Ok(reader)
+    }
+    /// Reads the metadata of an image produced by any compiler. Method bodies are not checked.
+    pub fn open(data: &'data [u8]) -> Result<Self, PEError> {
+        Self::read(data, false).map_err(|err| match err {
+            PEError::SelfCheck(msg) => PEError::BadImage(msg),
+            err => err,
+        })
+    }
+    fn read(data: &'data [u8], strict: bool) -> Result<Self, PEError> {
+        if data.get(..2) != Some(b"MZ") {
+            return Err(error("missing the DOS header"));
+        }
+        let pe = read_u32(data, 0x3C)? as usize;
+        if data.get(pe..pe + 4) != Some(b"PE\0\0") {
+            return Err(error("missing the PE signature"));
+        }
+        let section_count = read_u16(data, pe + 6)?;
+        let optional_size = read_u16(data, pe + 20)? as usize;
+        let optional = pe + 24;
+        // The data directories of PE32+ images start 16 bytes later.
+        let data_dirs = match read_u16(data, optional)? {
+            0x010B => optional + 96,
+            0x020B => optional + 112,
+            magic => return Err(error(format!("unknown optional header magic {magic:#x}"))),
+        };
+        let cli_dir = data_dirs + 14 * 8;
+        let cli_rva = read_u32(data, cli_dir)?;
+        let mut sections = vec![];
+        for section in 0..section_count as usize {
+            let header = optional + optional_size + section * 40;
+            sections.push((
+                read_u32(data, header + 12)?,
+                read_u32(data, header + 8)?,
+                read_u32(data, header + 20)?,
+            ));
+        }
+        let mut reader = Self {
+            data,
+            sections,
+            metadata: MetadataReader {
+                streams: FxHashMap::default(),
+                layout: Layout {
+                    rows: [0; 64],
+                    heap_sizes: 0,
+                },
+                rows: vec![],
+            },
+            entrypoint: 0,
+        };
+        let cli = reader.rva_to_offset(cli_rva)?;
+        if read_u32(data, cli)? != 72 {
+            return Err(error("bad CLI header size"));
+        }
+        let metadata_rva = read_u32(data, cli + 8)?;
+        let metadata_size = read_u32(data, cli + 12)? as usize;
+        reader.entrypoint = read_u32(data, cli + 20)?;
+        let metadata = reader.rva_to_offset(metadata_rva)?;
+        let metadata = data
+            .get(metadata..metadata + metadata_size)
+            .ok_or_else(|| error("metadata out of bounds"))?;
+        reader.metadata = MetadataReader::read(metadata, None, strict)?;
+        Ok(reader)
+    }
+    fn rva_to_offset(&self, rva: u32) -> Result<usize, PEError> {
+        self.sections
+            .iter()
+            .find(|(start, size, _)| (*start..start + size).contains(&rva))
+            .map(|(start, _, offset)| (rva - start + offset) as usize)
+            .ok_or_else(|| error(format!("RVA {rva:#x} is not within any section")))
+    }
+    /// Returns the number of rows in `table`.
+    pub fn row_count(&self, table: TableId) -> u32 {
+        self.metadata.layout.rows[table as usize]
+    }
+    fn validate(&self) -> Result<(), PEError> {
+        if self.entrypoint != 0 {
+            let (table, row) = (self.entrypoint >> 24, self.entrypoint & 0xFF_FFFF);
+            if table != TableId::MethodDef as u32
+                || row == 0
+                || row > self.row_count(TableId::MethodDef)
+            {
+                return Err(error(format!("bad entrypoint {:#x}", self.entrypoint)));
+            }
+        }
+        for (idx, method) in self.metadata.rows(TableId::MethodDef).iter().enumerate() {
+            if method[0] != 0 {
+                let name = self.metadata.string(method[3])?;
+                self.method_body(method[0])
+                    .map_err(|err| error(format!("method {name}(row {}): {err}", idx + 1)))?;
+            }
+        }
+        for field in self.metadata.rows(TableId::FieldRVA) {
+            self.rva_to_offset(field[0])?;
+        }
+        Ok(())
+    }
+    /// Checks the header, the instructions and the exception handling clauses of a method body.
+    fn method_body(&self, rva: u32) -> Result<(), PEError> {
+        let start = self.rva_to_offset(rva)?;
+        let header = *self
+            .data
+            .get(start)
+            .ok_or_else(|| error("body out of bounds"))?;
+        let (code_start, code_len, has_sections, locals) = match header & 0x3 {
+            0x2 => (start + 1, (header >> 2) as usize, false, 0),
+            0x3 => {
+                let flags = read_u16(self.data, start)?;
+                if flags >> 12 != 3 {
+                    return Err(error("bad fat header size"));
+                }
+                (
+                    start + 12,
+                    read_u32(self.data, start + 4)? as usize,
+                    flags & 0x8 != 0,
+                    read_u32(self.data, start + 8)?,
+                )
+            }
+            _ => return Err(error(format!("bad method header {header:#x}"))),
+        };
+        if locals != 0 {
+            self.check_token(locals)?;
+        }
+        let code = self
+            .data
+            .get(code_start..code_start + code_len)
+            .ok_or_else(|| error("body out of bounds"))?;
+        let mut boundaries = vec![false; code_len + 1];
+        let mut targets = vec![];
+        let mut pc = 0;
+        while pc < code.len() {
+            boundaries[pc] = true;
+            let (opcode, opcode_len) = match code[pc] {
+                0xFE => (0xFE00 | u16::from(*code.get(pc + 1).unwrap_or(&0xFF)), 2),
+                byte => (u16::from(byte), 1),
+            };
+            let operand = operand(opcode)
+                .ok_or_else(|| error(format!("invalid opcode {opcode:#x} at {pc:#x}")))?;
+            let mut next = pc + opcode_len;
+            let operand_len = match operand {
+                Operand::None => 0,
+                Operand::I8 | Operand::U8 | Operand::ShortTarget => 1,
+                Operand::U16 => 2,
+                Operand::I32 | Operand::F32 | Operand::Token | Operand::Target => 4,
+                Operand::I64 | Operand::F64 => 8,
+                Operand::Switch => 4 + 4 * read_u32(code, next)? as usize,
+            };
+            if next + operand_len > code.len() {
+                return Err(error(format!("truncated instruction at {pc:#x}")));
+            }
+            match operand {
+                Operand::ShortTarget => {
+                    targets.push(next as i64 + 1 + i64::from(code[next] as i8));
+                }
+                Operand::Target => {
+                    targets.push(next as i64 + 4 + i64::from(read_u32(code, next)? as i32));
+                }
+                Operand::Switch => {
+                    let base = (next + operand_len) as i64;
+                    for case in 0..read_u32(code, next)? as usize {
+                        targets.push(base + i64::from(read_u32(code, next + 4 + case * 4)? as i32));
+                    }
+                }
+                // `ldstr` refers to the `#US` heap.
+                Operand::Token if opcode == 0x72 => {
+                    let idx = read_u32(code, next)? & 0xFF_FFFF;
+                    if idx as usize >= self.metadata.heap("#US").len() {
+                        return Err(error(format!("bad string token at {pc:#x}")));
+                    }
+                }
+                Operand::Token => self.check_token(read_u32(code, next)?)?,
+                _ => (),
+            }
+            next += operand_len;
+            pc = next;
+        }
+        boundaries[code_len] = true;
+        if let Some(target) = targets
+            .iter()
+            .find(|target| !(0..code_len as i64).contains(target) || !boundaries[**target as usize])
+        {
+            return Err(error(format!(
+                "branch to {target:#x}, which is not an instruction"
+            )));
+        }
+        if has_sections {
+            let section = (code_start + code_len).next_multiple_of(4);
+            let kind = read_u32(self.data, section)?;
+            if kind & 0xFF != 0x41 {
+                return Err(error(format!("unsupported data section {kind:#x}")));
+            }
+            let clauses = ((kind >> 8) as usize - 4) / 24;
+            for clause in 0..clauses {
+                let clause = section + 4 + clause * 24;
+                let try_start = read_u32(self.data, clause + 4)? as usize;
+                let try_len = read_u32(self.data, clause + 8)? as usize;
+                let handler_start = read_u32(self.data, clause + 12)? as usize;
+                let handler_len = read_u32(self.data, clause + 16)? as usize;
+                for offset in [
+                    try_start,
+                    try_start + try_len,
+                    handler_start,
+                    handler_start + handler_len,
+                ] {
+                    if !boundaries.get(offset).copied().unwrap_or(false) {
+                        return Err(error(format!(
+                            "exception clause bound {offset:#x} is not an instruction"
+                        )));
+                    }
+                }
+                self.check_token(read_u32(self.data, clause + 20)?)?;
+            }
+        }
+        Ok(())
+    }
+    fn check_token(&self, token: u32) -> Result<(), PEError> {
+        let (table, row) = (token >> 24, token & 0xFF_FFFF);
+        let valid = TableId::from_u8(table as u8)
+            .is_some_and(|table| row >= 1 && row <= self.row_count(table));
+        if valid {
+            Ok(())
+        } else {
+            Err(error(format!("invalid token {token:#x}")))
+        }
+    }
+}
+/// Reads a portable PDB, checking that it describes `image`.
+pub(super) fn check_pdb(pdb: &[u8], image: &PEReader) -> Result<(), PEError> {
+    // The `#Pdb` stream determines the size of references into the image, so it has to be read first.
+    let root = MetadataReader::streams_only(pdb)?;
+    let pdb_stream = root
+        .get("#Pdb")
+        .ok_or_else(|| error("missing the #Pdb stream"))?;
+    let entrypoint = read_u32(pdb_stream, 20)?;
+    if entrypoint != image.entrypoint {
+        return Err(error("the PDB and the image have different entrypoints"));
+    }
+    let referenced = read_u64(pdb_stream, 24)?;
+    let mut external = [0; 64];
+    let mut offset = 32;
+    for (table, rows) in external.iter_mut().enumerate() {
+        if referenced & (1 << table) != 0 {
+            *rows = read_u32(pdb_stream, offset)?;
+            offset += 4;
+        }
+    }
+    if external != image.metadata.layout.rows {
+        return Err(error("the PDB and the image have different row counts"));
+    }
+    let metadata = MetadataReader::new(pdb, Some(&external))?;
+    let debug_rows = metadata.rows(TableId::MethodDebugInformation);
+    if debug_rows.len() as u32 != image.row_count(TableId::MethodDef) {
+        return Err(error(format!(
+            "{} methods have debug information, but the image has {}",
+            debug_rows.len(),
+            image.row_count(TableId::MethodDef)
+        )));
+    }
+    for (idx, row) in debug_rows.iter().enumerate() {
+        if row[1] != 0 {
+            sequence_points(metadata.blob(row[1])?, row[0] == 0)
+                .map_err(|err| error(format!("sequence points of method {}: {err}", idx + 1)))?;
+        }
+    }
+    Ok(())
+}
+/// Decodes a sequence point blob, returning the number of sequence points.
+fn sequence_points(mut blob: &[u8], has_initial_document: bool) -> Result<usize, PEError> {
+    let next = |blob: &mut &[u8], signed: bool| -> Result<i64, PEError> {
+        let (val, len) = if signed {
+            decompress_i32(blob).map(|(val, len)| (i64::from(val), len))
+        } else {
+            decompress_u32(blob).map(|(val, len)| (i64::from(val), len))
+        }
+        .ok_or_else(|| error("bad compressed integer"))?;
+        *blob = &blob[len..];
+        Ok(val)
+    };
+    // Local signature, and the initial document.
+    next(&mut blob, false)?;
+    if has_initial_document {
+        next(&mut blob, false)?;
+    }
+    let mut count = 0;
+    let mut line: i64 = 0;
+    while !blob.is_empty() {
+        let il_offset = next(&mut blob, false)?;
+        if count > 0 && il_offset == 0 {
+            // A document record.
+            next(&mut blob, false)?;
+            continue;
+        }
+        let lines = next(&mut blob, false)?;
+        let columns = next(&mut blob, lines != 0)?;
+        if lines == 0 && columns <= 0 {
+            return Err(error("empty sequence point"));
+        }
+        line = if count == 0 {
+            let start = next(&mut blob, false)?;
+            next(&mut blob, false)?;
+            start
+        } else {
+            let delta = next(&mut blob, true)?;
+            next(&mut blob, true)?;
+            line + delta
+        };
+        if line <= 0 {
+            return Err(error(format!("invalid line {line}")));
+        }
+        count += 1;
+    }
+    Ok(count)
+}
@@ -0,0 +1,521 @@
+//! Schema of the metadata tables(ECMA-335 II.22, and the Portable PDB spec), shared by the writer and the reader.
+use super::heaps::pad4;
+
+/// Identifies a metadata table. The discriminant is the table number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(u8)]
+pub(super) enum TableId {
+    Module = 0x00,
+    TypeRef = 0x01,
+    TypeDef = 0x02,
+    FieldPtr = 0x03,
+    Field = 0x04,
+    MethodPtr = 0x05,
+    MethodDef = 0x06,
+    ParamPtr = 0x07,
+    Param = 0x08,
+    InterfaceImpl = 0x09,
+    MemberRef = 0x0A,
+    Constant = 0x0B,
+    CustomAttribute = 0x0C,
+    FieldMarshal = 0x0D,
+    DeclSecurity = 0x0E,
+    ClassLayout = 0x0F,
+    FieldLayout = 0x10,
+    StandAloneSig = 0x11,
+    EventMap = 0x12,
+    EventPtr = 0x13,
+    Event = 0x14,
+    PropertyMap = 0x15,
+    PropertyPtr = 0x16,
+    Property = 0x17,
+    MethodSemantics = 0x18,
+    MethodImpl = 0x19,
+    ModuleRef = 0x1A,
+    TypeSpec = 0x1B,
+    ImplMap = 0x1C,
+    FieldRVA = 0x1D,
+    EncLog = 0x1E,
+    EncMap = 0x1F,
+    Assembly = 0x20,
+    AssemblyProcessor = 0x21,
+    AssemblyOS = 0x22,
+    AssemblyRef = 0x23,
+    AssemblyRefProcessor = 0x24,
+    AssemblyRefOS = 0x25,
+    File = 0x26,
+    ExportedType = 0x27,
+    ManifestResource = 0x28,
+    NestedClass = 0x29,
+    GenericParam = 0x2A,
+    MethodSpec = 0x2B,
+    GenericParamConstraint = 0x2C,
+    Document = 0x30,
+    MethodDebugInformation = 0x31,
+    LocalScope = 0x32,
+    LocalVariable = 0x33,
+    LocalConstant = 0x34,
+    ImportScope = 0x35,
+    StateMachineMethod = 0x36,
+    CustomDebugInformation = 0x37,
+}
+impl TableId {
+    /// All the known tables, in order.
+    pub const ALL: [Self; 53] = [
+        Self::Module,
+        Self::TypeRef,
+        Self::TypeDef,
+        Self::FieldPtr,
+        Self::Field,
+        Self::MethodPtr,
+        Self::MethodDef,
+        Self::ParamPtr,
+        Self::Param,
+        Self::InterfaceImpl,
+        Self::MemberRef,
+        Self::Constant,
+        Self::CustomAttribute,
+        Self::FieldMarshal,
+        Self::DeclSecurity,
+        Self::ClassLayout,
+        Self::FieldLayout,
+        Self::StandAloneSig,
+        Self::EventMap,
+        Self::EventPtr,
+        Self::Event,
+        Self::PropertyMap,
+        Self::PropertyPtr,
+        Self::Property,
+        Self::MethodSemantics,
+        Self::MethodImpl,
+        Self::ModuleRef,
+        Self::TypeSpec,
+        Self::ImplMap,
+        Self::FieldRVA,
+        Self::EncLog,
+        Self::EncMap,
+        Self::Assembly,
+        Self::AssemblyProcessor,
+        Self::AssemblyOS,
+        Self::AssemblyRef,
+        Self::AssemblyRefProcessor,
+        Self::AssemblyRefOS,
+        Self::File,
+        Self::ExportedType,
+        Self::ManifestResource,
+        Self::NestedClass,
+        Self::GenericParam,
+        Self::MethodSpec,
+        Self::GenericParamConstraint,
+        Self::Document,
+        Self::MethodDebugInformation,
+        Self::LocalScope,
+        Self::LocalVariable,
+        Self::LocalConstant,
+        Self::ImportScope,
+        Self::StateMachineMethod,
+        Self::CustomDebugInformation,
+    ];
+    pub fn from_u8(id: u8) -> Option<Self> {
+        Self::ALL.iter().copied().find(|table| *table as u8 == id)
+    }
+    /// The columns of this table.
+    pub fn schema(self) -> &'static [Column] {
+        use CodedIndex as C;
+        use Column::{Blob, Coded, Guid, Str, Table as T, U16, U32};
+        match self {
+            Self::Module => &[U16, Str, Guid, Guid, Guid],
+            Self::TypeRef => &[Coded(C::ResolutionScope), Str, Str],
+            Self::TypeDef => &[
+                U32,
+                Str,
+                Str,
+                Coded(C::TypeDefOrRef),
+                T(Self::Field),
+                T(Self::MethodDef),
+            ],
+            Self::FieldPtr => &[T(Self::Field)],
+            Self::Field => &[U16, Str, Blob],
+            Self::MethodPtr => &[T(Self::MethodDef)],
+            Self::MethodDef => &[U32, U16, U16, Str, Blob, T(Self::Param)],
+            Self::ParamPtr => &[T(Self::Param)],
+            Self::Param => &[U16, U16, Str],
+            Self::InterfaceImpl => &[T(Self::TypeDef), Coded(C::TypeDefOrRef)],
+            Self::MemberRef => &[Coded(C::MemberRefParent), Str, Blob],
+            Self::Constant => &[U16, Coded(C::HasConstant), Blob],
+            Self::CustomAttribute => &[
+                Coded(C::HasCustomAttribute),
+                Coded(C::CustomAttributeType),
+                Blob,
+            ],
+            Self::FieldMarshal => &[Coded(C::HasFieldMarshal), Blob],
+            Self::DeclSecurity => &[U16, Coded(C::HasDeclSecurity), Blob],
+            Self::ClassLayout => &[U16, U32, T(Self::TypeDef)],
+            Self::FieldLayout => &[U32, T(Self::Field)],
+            Self::StandAloneSig => &[Blob],
+            Self::EventMap => &[T(Self::TypeDef), T(Self::Event)],
+            Self::EventPtr => &[T(Self::Event)],
+            Self::Event => &[U16, Str, Coded(C::TypeDefOrRef)],
+            Self::PropertyMap => &[T(Self::TypeDef), T(Self::Property)],
+            Self::PropertyPtr => &[T(Self::Property)],
+            Self::Property => &[U16, Str, Blob],
+            Self::MethodSemantics => &[U16, T(Self::MethodDef), Coded(C::HasSemantics)],
+            Self::MethodImpl => &[
+                T(Self::TypeDef),
+                Coded(C::MethodDefOrRef),
+                Coded(C::MethodDefOrRef),
+            ],
+            Self::ModuleRef => &[Str],
+            Self::TypeSpec => &[Blob],
+            Self::ImplMap => &[U16, Coded(C::MemberForwarded), Str, T(Self::ModuleRef)],
+            Self::FieldRVA => &[U32, T(Self::Field)],
+            Self::EncLog => &[U32, U32],
+            Self::EncMap => &[U32],
+            Self::Assembly => &[U32, U16, U16, U16, U16, U32, Blob, Str, Str],
+            Self::AssemblyProcessor => &[U32],
+            Self::AssemblyOS => &[U32, U32, U32],
+            Self::AssemblyRef => &[U16, U16, U16, U16, U32, Blob, Str, Str, Blob],
+            Self::AssemblyRefProcessor => &[U32, T(Self::AssemblyRef)],
+            Self::AssemblyRefOS => &[U32, U32, U32, T(Self::AssemblyRef)],
+            Self::File => &[U32, Str, Blob],
+            Self::ExportedType => &[U32, U32, Str, Str, Coded(C::Implementation)],
+            Self::ManifestResource => &[U32, U32, Str, Coded(C::Implementation)],
+            Self::NestedClass => &[T(Self::TypeDef), T(Self::TypeDef)],
+            Self::GenericParam => &[U16, U16, Coded(C::TypeOrMethodDef), Str],
+            Self::MethodSpec => &[Coded(C::MethodDefOrRef), Blob],
+            Self::GenericParamConstraint => &[T(Self::GenericParam), Coded(C::TypeDefOrRef)],
+            Self::Document => &[Blob, Guid, Blob, Guid],
+            Self::MethodDebugInformation => &[T(Self::Document), Blob],
+            Self::LocalScope => &[
+                T(Self::MethodDef),
+                T(Self::ImportScope),
+                T(Self::LocalVariable),
+                T(Self::LocalConstant),
+                U32,
+                U32,
+            ],
+            Self::LocalVariable => &[U16, U16, Str],
+            Self::LocalConstant => &[Str, Blob],
+            Self::ImportScope => &[T(Self::ImportScope), Blob],
+            Self::StateMachineMethod => &[T(Self::MethodDef), T(Self::MethodDef)],
+            Self::CustomDebugInformation => &[Coded(C::HasCustomDebugInformation), Guid, Blob],
+        }
+    }
+    /// The column this table is sorted by, if it is one of the tables which must be sorted.
+    pub fn sort_key(self) -> Option<usize> {
+        if SORTED_MASK & (1 << self as u8) == 0 {
+            return None;
+        }
+        Some(match self {
+            Self::ClassLayout | Self::MethodSemantics | Self::GenericParam => 2,
+            Self::FieldLayout
+            | Self::FieldRVA
+            | Self::Constant
+            | Self::ImplMap
+            | Self::DeclSecurity => 1,
+            _ => 0,
+        })
+    }
+    /// Columns, which point to the first entry of a run of rows in another table.
+    pub fn is_list(self, column: usize) -> bool {
+        matches!(
+            (self, column),
+            (Self::TypeDef, 4 | 5)
+                | (Self::MethodDef, 5)
+                | (Self::EventMap, 1)
+                | (Self::PropertyMap, 1)
+                | (Self::LocalScope, 2 | 3)
+        )
+    }
+}
+/// Bitmask of the tables, which must be sorted.
+pub(super) const SORTED_MASK: u64 = 0x00C4_1600_3301_FA00;
+/// A coded index(ECMA-335 II.24.2.6) - a reference to a row in one of several tables.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(super) enum CodedIndex {
+    TypeDefOrRef,
+    HasConstant,
+    HasCustomAttribute,
+    HasFieldMarshal,
+    HasDeclSecurity,
+    MemberRefParent,
+    HasSemantics,
+    MethodDefOrRef,
+    MemberForwarded,
+    Implementation,
+    CustomAttributeType,
+    ResolutionScope,
+    TypeOrMethodDef,
+    HasCustomDebugInformation,
+}
+impl CodedIndex {
+    /// The tables this index may point to, by tag. `None` marks unused tags.
+    pub fn tables(self) -> &'static [Option<TableId>] {
+        use TableId as T;
+        match self {
+            Self::TypeDefOrRef => &[Some(T::TypeDef), Some(T::TypeRef), Some(T::TypeSpec)],
+            Self::HasConstant => &[Some(T::Field), Some(T::Param), Some(T::Property)],
+            Self::HasCustomAttribute => &[
+                Some(T::MethodDef),
+                Some(T::Field),
+                Some(T::TypeRef),
+                Some(T::TypeDef),
+                Some(T::Param),
+                Some(T::InterfaceImpl),
+                Some(T::MemberRef),
+                Some(T::Module),
+                Some(T::DeclSecurity),
+                Some(T::Property),
+                Some(T::Event),
+                Some(T::StandAloneSig),
+                Some(T::ModuleRef),
+                Some(T::TypeSpec),
+                Some(T::Assembly),
+                Some(T::AssemblyRef),
+                Some(T::File),
+                Some(T::ExportedType),
+                Some(T::ManifestResource),
+                Some(T::GenericParam),
+                Some(T::GenericParamConstraint),
+                Some(T::MethodSpec),
+            ],
+            Self::HasFieldMarshal => &[Some(T::Field), Some(T::Param)],
+            Self::HasDeclSecurity => &[Some(T::TypeDef), Some(T::MethodDef), Some(T::Assembly)],
+            Self::MemberRefParent => &[
+                Some(T::TypeDef),
+                Some(T::TypeRef),
+                Some(T::ModuleRef),
+                Some(T::MethodDef),
+                Some(T::TypeSpec),
+            ],
+            Self::HasSemantics => &[Some(T::Event), Some(T::Property)],
+            Self::MethodDefOrRef => &[Some(T::MethodDef), Some(T::MemberRef)],
+            Self::MemberForwarded => &[Some(T::Field), Some(T::MethodDef)],
+            Self::Implementation => &[Some(T::File), Some(T::AssemblyRef), Some(T::ExportedType)],
+            Self::CustomAttributeType => {
+                &[None, None, Some(T::MethodDef), Some(T::MemberRef), None]
+            }
+            Self::ResolutionScope => &[
+                Some(T::Module),
+                Some(T::ModuleRef),
+                Some(T::AssemblyRef),
+                Some(T::TypeRef),
+            ],
+            Self::TypeOrMethodDef => &[Some(T::TypeDef), Some(T::MethodDef)],
+            Self::HasCustomDebugInformation => &[
+                Some(T::MethodDef),
+                Some(T::Field),
+                Some(T::TypeRef),
+                Some(T::TypeDef),
+                Some(T::Param),
+                Some(T::InterfaceImpl),
+                Some(T::MemberRef),
+                Some(T::Module),
+                Some(T::DeclSecurity),
+                Some(T::Property),
+                Some(T::Event),
+                Some(T::StandAloneSig),
+                Some(T::ModuleRef),
+                Some(T::TypeSpec),
+                Some(T::Assembly),
+                Some(T::AssemblyRef),
+                Some(T::File),
+                Some(T::ExportedType),
+                Some(T::ManifestResource),
+                Some(T::GenericParam),
+                Some(T::GenericParamConstraint),
+                Some(T::MethodSpec),
+                Some(T::Document),
+                Some(T::LocalScope),
+                Some(T::LocalVariable),
+                Some(T::LocalConstant),
+                Some(T::ImportScope),
+            ],
+        }
+    }
+    /// Number of bits used by the tag.
+    pub fn tag_bits(self) -> u32 {
+        (self.tables().len() as u32)
+            .next_power_of_two()
+            .trailing_zeros()
+    }
+    /// Encodes a reference to `row` of `table`.
+    pub fn encode(self, table: TableId, row: u32) -> u32 {
+        let tag = self
+            .tables()
+            .iter()
+            .position(|tag| *tag == Some(table))
+            .unwrap_or_else(|| panic!("{table:?} can't be referenced by a {self:?} index"));
+        (row << self.tag_bits()) | tag as u32
+    }
+    /// Decodes an index into the table and row it points to.
+    pub fn decode(self, val: u32) -> Option<(TableId, u32)> {
+        let tag = val & ((1 << self.tag_bits()) - 1);
+        let table = (*self.tables().get(tag as usize)?)?;
+        Some((table, val >> self.tag_bits()))
+    }
+}
+/// The kind of a table column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Column {
+    U16,
+    U32,
+    /// Index into the `#Strings` heap
+    Str,
+    /// Index into the `#GUID` heap
+    Guid,
+    /// Index into the `#Blob` heap
+    Blob,
+    /// Index of a row in a table
+    Table(TableId),
+    Coded(CodedIndex),
+}
+/// Row counts and heap sizes, which determine the width of every column.
+#[derive(Clone)]
+pub(super) struct Layout {
+    pub rows: [u32; 64],
+    pub heap_sizes: u8,
+}
+impl Layout {
+    pub fn column_size(&self, column: Column) -> usize {
+        let wide = |flag: u8| if self.heap_sizes & flag != 0 { 4 } else { 2 };
+        match column {
+            Column::U16 => 2,
+            Column::U32 => 4,
+            Column::Str => wide(0x1),
+            Column::Guid => wide(0x2),
+            Column::Blob => wide(0x4),
+            Column::Table(table) => {
+                if self.rows[table as usize] < 1 << 16 {
+                    2
+                } else {
+                    4
+                }
+            }
+            Column::Coded(coded) => {
+                let max = coded
+                    .tables()
+                    .iter()
+                    .flatten()
+                    .map(|table| self.rows[*table as usize])
+                    .max()
+                    .unwrap_or(0);
+                if max < 1 << (16 - coded.tag_bits()) {
+                    2
+                } else {
+                    4
+                }
+            }
+        }
+    }
+    pub fn row_size(&self, table: TableId) -> usize {
+        table
+            .schema()
+            .iter()
+            .map(|column| self.column_size(*column))
+            .sum()
+    }
+}
+/// The rows of all tables of a `#~` stream, in the process of being built.
+pub(super) struct Tables {
+    rows: Vec<Vec<Box<[u32]>>>,
+}
+impl Default for Tables {
+    fn default() -> Self {
+        Self {
+            rows: vec![vec![]; 64],
+        }
+    }
+}
+impl Tables {
+    /// Adds a row to `table`, returning its(1-based) index.
+    pub fn push(&mut self, table: TableId, row: impl Into<Box<[u32]>>) -> u32 {
+        let row = row.into();
+        debug_assert_eq!(row.len(), table.schema().len(), "{table:?}");
+        self.rows[table as usize].push(row);
+        self.len(table)
+    }
+    pub fn len(&self, table: TableId) -> u32 {
+        self.rows[table as usize].len() as u32
+    }
+    /// Sets a column of an already existing row.
+    pub fn set(&mut self, table: TableId, row: u32, column: usize, val: u32) {
+        self.rows[table as usize][row as usize - 1][column] = val;
+    }
+    /// Row counts of all tables.
+    pub fn row_counts(&self) -> [u32; 64] {
+        std::array::from_fn(|table| self.rows[table].len() as u32)
+    }
+    /// Serializes the `#~` stream. `external` contains the row counts of tables stored in another image, which are
+    /// referenced by this one(used by portable PDBs).
+    pub fn serialize(mut self, heap_sizes: u8, external: &[u32; 64]) -> Vec<u8> {
+        for table in TableId::ALL {
+            if let Some(key) = table.sort_key() {
+                // Stable, so that rows with equal keys keep their order.
+                self.rows[table as usize].sort_by_key(|row| row[key]);
+            }
+        }
+        let own = self.row_counts();
+        let layout = Layout {
+            rows: std::array::from_fn(|table| own[table].max(external[table])),
+            heap_sizes,
+        };
+        let valid = own
+            .iter()
+            .enumerate()
+            .filter(|(_, rows)| **rows > 0)
+            .fold(0_u64, |valid, (table, _)| valid | (1 << table));
+        let mut out = vec![];
+        out.extend(0_u32.to_le_bytes());
+        out.extend([2, 0, heap_sizes, 1]);
+        out.extend(valid.to_le_bytes());
+        out.extend((SORTED_MASK & valid).to_le_bytes());
+        for rows in own.iter().filter(|rows| **rows > 0) {
+            out.extend(rows.to_le_bytes());
+        }
+        for table in TableId::ALL {
+            for row in &self.rows[table as usize] {
+                for (val, column) in row.iter().zip(table.schema()) {
+                    match layout.column_size(*column) {
+                        2 => out.extend(
+                            u16::try_from(*val)
+                                .unwrap_or_else(|_| panic!("{val} does not fit {column:?}"))
+                                .to_le_bytes(),
+                        ),
+                        _ => out.extend(val.to_le_bytes()),
+                    }
+                }
+            }
+        }
+        pad4(&mut out);
+        out
+    }
+}
+#[test]
+fn coded_indices() {
+    assert_eq!(CodedIndex::TypeDefOrRef.tag_bits(), 2);
+    assert_eq!(CodedIndex::HasCustomAttribute.tag_bits(), 5);
+    assert_eq!(CodedIndex::CustomAttributeType.tag_bits(), 3);
+    assert_eq!(CodedIndex::MethodDefOrRef.tag_bits(), 1);
+    let val = CodedIndex::MemberRefParent.encode(TableId::TypeSpec, 7);
+    assert_eq!(val, (7 << 3) | 4);
+    assert_eq!(
+        CodedIndex::MemberRefParent.decode(val),
+        Some((TableId::TypeSpec, 7))
+    );
+    assert_eq!(CodedIndex::CustomAttributeType.decode(0), None);
+    let mut layout = Layout {
+        rows: [0; 64],
+        heap_sizes: 0,
+    };
+    assert_eq!(layout.row_size(TableId::TypeDef), 14);
+    layout.rows[TableId::TypeRef as usize] = 1 << 14;
+    assert_eq!(
+        layout.column_size(Column::Coded(CodedIndex::TypeDefOrRef)),
+        4
+    );
+    assert_eq!(
+        layout.column_size(Column::Coded(CodedIndex::MethodDefOrRef)),
+        2
+    );
+}
@@ -149,7 +149,7 @@ impl Int {
             Int::I128 => {
                 let min_value = asm.alloc_string("get_MinValue");
                 let sig = asm.sig([], Type::Int(*self));
-                let class = ClassRef::uint_128(asm);
+                let class = ClassRef::int_128(asm);
                 CILNode::call(
                     asm.alloc_methodref(MethodRef::new(
                         class,
@@ -222,7 +222,7 @@ impl Int {
             Int::I128 => {
                 let max_value = asm.alloc_string("get_MaxValue");
                 let sig = asm.sig([], Type::Int(*self));
-                let class = ClassRef::uint_128(asm);
+                let class = ClassRef::int_128(asm);
                 CILNode::call(
                     asm.alloc_methodref(MethodRef::new(
                         class,
@@ -141,7 +141,9 @@ impl Type {
                     .iter()
                     .map(|input| input.mangle(asm))
                     .collect::<String>();
-                format!("{argc}{inputs}{output}")
+                // Like in the Itanium ABI, `z` stands for the `...` of a C-variadic function.
+                let variadic = if sig.is_variadic() { "z" } else { "" };
+                format!("{argc}{inputs}{variadic}{output}")
             }
         }
     }
@@ -202,6 +204,12 @@ impl Type {
     /// # let ptru8 = asm.nptr(Int::U8);
     /// assert!(ptru8.is_assignable_to(refu8,&asm));
     /// assert!(!refu8.is_assignable_to(ptru8,&asm));
+    /// // A class can be assigned to the classes it derives from, but not the other way round.
+    /// # let base = ClassRef::exception(&mut asm);
+    /// # let derived = asm.alloc_string("DerivedException");
+    /// # let derived = asm.class_def(ClassDef::new(derived, false, 0, Some(base), vec![], vec![], Access::Public, None, None, true)).unwrap();
+    /// assert!(Type::ClassRef(*derived).is_assignable_to(Type::ClassRef(base),&asm));
+    /// assert!(!Type::ClassRef(base).is_assignable_to(Type::ClassRef(*derived),&asm));
     /// //     Ignores partial matches.
     /// # let u128_name = asm.alloc_string("System.UInt128");
     /// # let i128_name = asm.alloc_string("System.Int128");
@@ -247,6 +255,27 @@ impl Type {
                     && cref.asm().map(|s| asm[s].as_ref()) == Some("System.Runtime")
                     && &asm[cref.name()] == "System.UInt128"
             }
+            // Instantiations of the same generic class, with some arguments left as generic parameters.
+            (Type::ClassRef(cref), Type::ClassRef(base))
+                if asm
+                    .class_ref(cref)
+                    .is_generic_instance_of(asm.class_ref(base), asm) =>
+            {
+                true
+            }
+            (Type::ClassRef(cref), Type::ClassRef(base)) => {
+                let mut curr = cref;
+                while let Some(parent) = asm
+                    .class_ref_to_def(curr)
+                    .and_then(|def| asm.get_class_def(def).extends())
+                {
+                    if parent == base {
+                        return true;
+                    }
+                    curr = parent;
+                }
+                false
+            }
             (Type::Int(Int::U16 | Int::I16), Type::PlatformChar) => true,
             (Type::Ptr(ptr), Type::Ref(rf)) => ptr == rf,
             // TODO: check generics propely?
@@ -167,6 +167,11 @@ pub enum TypeCheckError {
         /// The wrong, not-bool type.
         cond: Type,
     },
+    /// A switch over a value, which is not a 32 bit or pointer-sized integer.
+    SwitchValueNotInt {
+        /// The type of the value.
+        value: Type,
+    },
     /// A comparsion instruction was used on a pair of types that can't be compared.
     CantCompareTypes {
         /// Lhs type
@@ -671,13 +676,18 @@ impl CILNode {
                 let (mref, args, _is_pure) = call_info.as_ref();
                 let mref = asm[*mref].clone();
                 let inputs: Box<[_]> = mref.stack_inputs(asm).into();
-                if args.len() != inputs.len() {
+                // C-variadic functions take any number of additional arguments, of any type.
+                let is_variadic = asm[mref.sig()].is_variadic();
+                if args.len() != inputs.len() && !(is_variadic && args.len() > inputs.len()) {
                     return Err(TypeCheckError::CallArgcWrong {
                         expected: inputs.len(),
                         got: args.len(),
                         mname: asm[mref.name()].into(),
                     });
                 }
+                for arg in &args[inputs.len()..] {
+                    asm.get_node(*arg).clone().typecheck(sig, locals, asm)?;
+                }
                 for (idx, (arg, input_type)) in args.iter().zip(inputs.iter()).enumerate() {
                     let arg = asm.get_node(*arg).clone();
                     let arg_type = arg.typecheck(sig, locals, asm)?;
@@ -701,12 +711,16 @@ impl CILNode {
                 let fn_ptr = asm.get_node(*fn_ptr).clone();
                 let fn_ptr = fn_ptr.typecheck(sig, locals, asm)?;
                 let called_sig = asm[*called_sig].clone();
-                if args.len() != called_sig.inputs().len() {
+                let expected = called_sig.inputs().len();
+                if args.len() != expected && !(called_sig.is_variadic() && args.len() > expected) {
                     return Err(TypeCheckError::IndirectCallArgcWrong {
-                        expected: called_sig.inputs().len(),
+                        expected,
                         got: args.len(),
                     });
                 }
+                for arg in &args[expected..] {
+                    asm.get_node(*arg).clone().typecheck(sig, locals, asm)?;
+                }
 
                 for (idx, (arg, input_type)) in
                     args.iter().zip(called_sig.inputs().iter()).enumerate()
@@ -1092,7 +1106,12 @@ impl CILRoot {
                         val,
                     });
                 }
-                let Some(pointed_tpe) = addr.pointed_to().map(|tpe| asm[tpe]) else {
+                // Fields of objects can be set directly, without taking their address.
+                let pointed_tpe = match addr {
+                    Type::ClassRef(_) => Some(addr),
+                    _ => addr.pointed_to().map(|tpe| asm[tpe]),
+                };
+                let Some(pointed_tpe) = pointed_tpe else {
                     return Err(TypeCheckError::TypeNotPtr { tpe: addr });
                 };
                 let Type::ClassRef(pointed_owner) = pointed_tpe else {
@@ -1132,7 +1151,7 @@ impl CILRoot {
                     crate::cilnode::MethodKind::Static => {
                         let expected = call_sig.inputs().len();
                         let got = args.len();
-                        if expected != got {
+                        if expected != got && !(call_sig.is_variadic() && got > expected) {
                             return Err(TypeCheckError::CallArgcWrong {
                                 expected,
                                 got,
@@ -1157,8 +1176,18 @@ impl CILRoot {
                         });
                     }
                 }
+                for arg in args.iter().skip(call_sig.inputs().len()) {
+                    asm[*arg].clone().typecheck(sig, locals, asm)?;
+                }
                 Ok(())
             }
+            Self::Switch(switch) => {
+                let value = asm[switch.0].clone().typecheck(sig, locals, asm)?;
+                match value {
+                    Type::Int(Int::I32 | Int::U32 | Int::ISize | Int::USize) => Ok(()),
+                    _ => Err(TypeCheckError::SwitchValueNotInt { value }),
+                }
+            }
             _ => {
                 for node in self.nodes() {
                     asm.get_node(*node).clone().typecheck(sig, locals, asm)?;
@@ -1176,3 +1205,35 @@ fn test() {
     asm.biop(lhs, rhs, BinOp::Add);
     let _sig = asm.sig([], Type::Void);
 }
+#[test]
+fn variadic_call() {
+    use super::{cilnode::MethodKind, Const, FnSig};
+    let mut asm = Assembly::default();
+    let main_module = asm.main_module();
+    let variadic = asm.alloc_sig(FnSig::new_variadic(
+        [Type::Int(Int::I32)],
+        Type::Int(Int::I32),
+    ));
+    let mref = asm.new_methodref(*main_module, "sum", variadic, MethodKind::Static, []);
+    let sig = asm.sig([], Type::Void);
+    let args = [
+        Const::I32(2),
+        Const::I64(3),
+        Const::F64(super::hashable::HashableF64(1.0)),
+    ]
+    .map(|arg| asm.alloc_node(arg));
+    // Any number of additional arguments may be passed...
+    let call = CILNode::call(mref, args);
+    assert_eq!(
+        call.typecheck(sig, &[], &mut asm).unwrap(),
+        Type::Int(Int::I32)
+    );
+    let call = CILNode::call(mref, &args[..1]);
+    assert_eq!(
+        call.typecheck(sig, &[], &mut asm).unwrap(),
+        Type::Int(Int::I32)
+    );
+    // ...but the fixed ones are still required.
+    let call = CILNode::call(mref, []);
+    assert!(call.typecheck(sig, &[], &mut asm).is_err());
+}
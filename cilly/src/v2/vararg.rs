@@ -0,0 +1,203 @@
+//! Calls to C-variadic functions, on runtimes without `call vararg`.
+//!
+//! CoreCLR only supports passing additional arguments to an unmanaged, C-variadic method on Windows. Elsewhere,
+//! [`Assembly::lower_vararg_calls`] gives each call its own fixed signature, made from the types of the arguments it
+//! passes. This matches the calling convention of the platforms .NET runs on for integer and pointer arguments.
+use super::{
+    bimap::Interned, Assembly, CILNode, CILRoot, FnSig, MethodDefIdx, MethodImpl, MethodRef,
+};
+use crate::v2::method::LocalDef;
+use std::cell::Cell;
+
+impl Assembly {
+    /// Replaces every call passing additional arguments to a C-variadic method with a call to the same method, with a
+    /// fixed signature. Extern methods get a declaration for each such signature.
+    /// Returns the number of calls which were lowered.
+    pub fn lower_vararg_calls(&mut self) -> usize {
+        let method_def_idxs: Box<[_]> = self.method_defs().keys().copied().collect();
+        let lowered = Cell::new(0);
+        for method in method_def_idxs {
+            if !matches!(self[method].implementation(), MethodImpl::MethodBody { .. }) {
+                continue;
+            }
+            let mut def = self.borrow_methoddef(method);
+            let sig = def.sig();
+            let locals: Vec<LocalDef> = def.iter_locals(self).copied().collect();
+            def.map_roots(
+                self,
+                &mut |root, asm| match root {
+                    CILRoot::Call(info) => {
+                        let (mref, args, is_pure) = *info;
+                        let mref = asm.fixed_vararg_mref(mref, &args, sig, &locals, &lowered);
+                        CILRoot::Call(Box::new((mref, args, is_pure)))
+                    }
+                    CILRoot::CallI(info) => {
+                        let (ptr, called, args) = *info;
+                        let called = asm.fixed_vararg_sig(called, &args, sig, &locals, &lowered);
+                        CILRoot::CallI(Box::new((ptr, called, args)))
+                    }
+                    _ => root,
+                },
+                &mut |node, asm| match node {
+                    CILNode::Call(info) => {
+                        let (mref, args, is_pure) = *info;
+                        let mref = asm.fixed_vararg_mref(mref, &args, sig, &locals, &lowered);
+                        CILNode::Call(Box::new((mref, args, is_pure)))
+                    }
+                    CILNode::CallI(info) => {
+                        let (ptr, called, args) = *info;
+                        let called = asm.fixed_vararg_sig(called, &args, sig, &locals, &lowered);
+                        CILNode::CallI(Box::new((ptr, called, args)))
+                    }
+                    _ => node,
+                },
+            );
+            self.return_methoddef(method, def);
+        }
+        lowered.get()
+    }
+    /// Returns the fixed signature of a call passing `args` to a method with the signature `called`, or `called` if
+    /// the method is not C-variadic.
+    fn fixed_vararg_sig(
+        &mut self,
+        called: Interned<FnSig>,
+        args: &[Interned<CILNode>],
+        sig: Interned<FnSig>,
+        locals: &[LocalDef],
+        lowered: &Cell<usize>,
+    ) -> Interned<FnSig> {
+        if !self[called].is_variadic() {
+            return called;
+        }
+        let fixed = self[called].inputs().len().min(args.len());
+        let extra: Vec<_> = args[fixed..]
+            .iter()
+            .map(|arg| {
+                self[*arg]
+                    .clone()
+                    .typecheck(sig, locals, self)
+                    .expect("Could not typecheck an additional argument of a vararg call")
+            })
+            .collect();
+        let inputs: Box<[_]> = self[called].inputs().iter().copied().chain(extra).collect();
+        let output = *self[called].output();
+        lowered.set(lowered.get() + 1);
+        self.alloc_sig(FnSig::new(inputs, output))
+    }
+    /// Like [`Self::fixed_vararg_sig`], for a direct call to `mref`. If `mref` is an extern method, it is also
+    /// declared with the fixed signature.
+    fn fixed_vararg_mref(
+        &mut self,
+        mref: Interned<MethodRef>,
+        args: &[Interned<CILNode>],
+        sig: Interned<FnSig>,
+        locals: &[LocalDef],
+        lowered: &Cell<usize>,
+    ) -> Interned<MethodRef> {
+        let called = self[mref].sig();
+        let fixed = self.fixed_vararg_sig(called, args, sig, locals, lowered);
+        if fixed == called {
+            return mref;
+        }
+        let method = self[mref].clone();
+        let fixed_mref = self.alloc_methodref(MethodRef::new(
+            method.class(),
+            method.name(),
+            fixed,
+            method.kind(),
+            method.generics().into(),
+        ));
+        if let Some(def) = self.method_ref_to_def(mref) {
+            let def = &self[def];
+            if let MethodImpl::Extern {
+                lib,
+                preserve_errno,
+            } = def.implementation()
+            {
+                let implementation = MethodImpl::Extern {
+                    lib: *lib,
+                    preserve_errno: *preserve_errno,
+                };
+                let access = *def.access();
+                if !self
+                    .method_defs()
+                    .contains_key(&MethodDefIdx::from_raw(fixed_mref))
+                {
+                    let fixed_def = self[fixed_mref].into_def(implementation, access, self);
+                    self.new_method(fixed_def);
+                }
+            }
+        }
+        fixed_mref
+    }
+}
+#[cfg(test)]
+pub(crate) const VARIADIC: &str = r#"
+(classdef public class "MainModule"
+  (method extern static "printf" (sig ((ptr u8) ...) i32) (args "fmt") (extern "libc"))
+  (method public static "entrypoint" (sig () i32) (args)
+    (locals)
+    (block 0
+      (pop (call (mref (class "MainModule") "printf" static (sig ((ptr u8) ...) i32)) (const u64 0) (const i32 1) (const f64 2.0)))
+      (ret (calli (sig ((ptr u8) ...) i32) (ldftn (mref (class "MainModule") "printf" static (sig ((ptr u8) ...) i32))) (const u64 0) (const i64 3))))))
+"#;
+#[test]
+fn lower_calls() {
+    let mut asm = super::cillytext::parse(VARIADIC).unwrap();
+    assert_eq!(asm.lower_vararg_calls(), 2);
+    let text = super::cillytext::print(&asm);
+    assert!(text.contains(r#"(method extern static "printf" (sig ((ptr u8) i32 f64) i32) (args _ _ _) (extern "libc"))"#));
+    assert!(text.contains("(sig ((ptr u8) i64) i32)"));
+    // The variadic declaration, and taking its address, are left alone.
+    assert!(
+        text.contains(r#"(method extern static "printf" (sig ((ptr u8) ...) i32) (args "fmt")"#)
+    );
+    assert!(text.contains(
+        r#"(ldftn (mref (class "MainModule") "printf" static (sig ((ptr u8) ...) i32)))"#
+    ));
+    assert_eq!(asm.lower_vararg_calls(), 0);
+}
+#[test]
+fn il_vararg_calls() {
+    use super::{asm::IlasmFlavour, il_exporter::ILExporter};
+    let mut asm = super::cillytext::parse(VARIADIC).unwrap();
+    let export = |asm: &Assembly| {
+        let mut out = vec![];
+        ILExporter::new(IlasmFlavour::Modern, false)
+            .export_to_write(asm, &mut out)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    };
+    let il = export(&asm);
+    assert!(il.contains("call vararg int32 class 'MainModule'::'printf'(uint8*,...,int32,float64)"));
+    assert!(il.contains("calli vararg int32 (uint8*,...,int64)"));
+    asm.lower_vararg_calls();
+    let il = export(&asm);
+    assert!(il.contains("call int32 class 'MainModule'::'printf'(uint8*,int32,float64)"));
+    assert!(il.contains("calli int32 (uint8*,int64)"));
+    assert!(il.contains("ldftn vararg int32 class 'MainModule'::'printf'(uint8*)"));
+}
+#[test]
+fn c_vararg_calls() {
+    use super::c_exporter::CExporter;
+    // C supports both calling and defining C-variadic functions, so nothing is lowered.
+    let src = VARIADIC.replacen(
+        r#"(method public static "entrypoint""#,
+        r#"(method public static "sum" (sig (i32 ...) i32) (args "count")
+    (locals (local u64 "list") (local i32 "val"))
+    (block 0
+      (va_start (ldloca 0))
+      (va_arg i32 (ldloca 0) (ldloca 1))
+      (ret (ldloc 1))))
+  (method public static "entrypoint""#,
+        1,
+    );
+    let asm = super::cillytext::parse(&src).unwrap();
+    let mut out = vec![];
+    CExporter::new(false, vec![], vec![])
+        .export_to_write(&asm, &mut out, false, false)
+        .unwrap();
+    let c = String::from_utf8(out).unwrap();
+    assert!(c.contains("va_start("));
+    assert!(c.contains("...)"));
+}
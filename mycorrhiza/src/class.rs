@@ -1,3 +1,5 @@
+use core::marker::Tuple;
+
 use crate::{
     intrinsics::{RustcCLRInteropManagedClass, RustcCLRInteropManagedStruct},
     FromManagedSafe, IntoManagedSafe, ManagedSafe,
@@ -86,6 +88,36 @@ impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str> Class<ASSEMBL
                 .instance2::<NAME, Arg, Arg2, SigRet>(arg.into_managed(), arg2.into_managed()),
         )
     }
+    /// Calls the constructor taking the elements of `args` as its arguments.
+    pub fn ctor<Args: ManagedSafe + Tuple>(args: Args) -> Self {
+        Self::from_naked_ref(Self::NakedRef::ctor(args))
+    }
+    /// Calls the instance method `NAME`, passing the elements of `args` as its arguments.
+    pub fn call_instance<
+        const NAME: &'static str,
+        Args: ManagedSafe + Tuple,
+        SigRet: ManagedSafe,
+        RealRet: FromManagedSafe<SigRet>,
+    >(
+        &mut self,
+        args: Args,
+    ) -> RealRet {
+        RealRet::from_managed(
+            unsafe { self.get_naked_ref() }.call_instance::<NAME, Args, SigRet>(args),
+        )
+    }
+    /// Calls the virtual method `NAME`, passing the elements of `args` as its arguments.
+    pub fn call_virt<
+        const NAME: &'static str,
+        Args: ManagedSafe + Tuple,
+        SigRet: ManagedSafe,
+        RealRet: FromManagedSafe<SigRet>,
+    >(
+        &mut self,
+        args: Args,
+    ) -> RealRet {
+        RealRet::from_managed(unsafe { self.get_naked_ref() }.call_virt::<NAME, Args, SigRet>(args))
+    }
     //pub fn to_mstring(&self)->
 }
 impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str> Drop
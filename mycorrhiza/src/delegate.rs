@@ -0,0 +1,39 @@
+use core::marker::Tuple;
+
+use crate::{intrinsics::rustc_clr_interop_managed_delegate, ManagedSafe};
+/// Calls the closure `closure` points to. This is the function invoked by the delegates created by [`delegate`]: since it
+/// uses the `rust-call` ABI, it takes the arguments of the delegate directly, and not packed in a tuple.
+extern "rust-call" fn invoke_closure<F: Fn<Args, Output = Ret>, Args: Tuple, Ret>(
+    closure: *const F,
+    args: Args,
+) -> Ret {
+    unsafe { (*closure).call(args) }
+}
+/// Drops a closure boxed by [`delegate`]. Called by the finalizer of the object holding the closure.
+unsafe fn drop_closure<F>(closure: *mut ()) {
+    drop(unsafe { Box::from_raw(closure.cast::<F>()) });
+}
+/// Wraps `closure` in a new instance of the delegate type `Delegate`(e.g. `Action`, `Func<T, TResult>`, `EventHandler`).
+/// The arguments and the return type of `closure` must match those of the `Invoke` method of `Delegate`.
+///
+/// The closure is boxed, and dropped once the delegate is collected by the GC. Since .NET may invoke the delegate from any
+/// thread, and the finalizer runs on a separate thread, the closure must be `Send + Sync`.
+/// ```ignore
+/// let callback: Action = delegate(|| Console::writeln_string("Hello from Rust!".into()));
+/// callback.call_virt::<"Invoke", (), ()>(());
+/// ```
+pub fn delegate<Delegate: ManagedSafe, Args: ManagedSafe + Tuple, Ret: ManagedSafe, F>(
+    closure: F,
+) -> Delegate
+where
+    F: Fn<Args, Output = Ret> + Send + Sync + 'static,
+{
+    let invoke = invoke_closure::<F, Args, Ret> as extern "rust-call" fn(*const F, Args) -> Ret;
+    let drop = drop_closure::<F> as unsafe fn(*mut ());
+    let data = Box::into_raw(Box::new(closure)).cast::<()>();
+    rustc_clr_interop_managed_delegate::<Delegate, Args, Ret>(
+        invoke as *const (),
+        drop as *const (),
+        data,
+    )
+}
@@ -1,3 +1,4 @@
+use core::marker::{PhantomData, Tuple};
 use std::ptr::null;
 
 use crate::ManagedSafe;
@@ -98,6 +99,117 @@ impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str>
             Arg2,
         >(self, arg1, arg2)
     }
+    /// Calls the constructor taking the elements of `args` as its arguments. Works for any number of arguments.
+    /// ```ignore
+    /// let builder = StringBuilder::ctor::<(MString, i32)>((text, 16));
+    /// ```
+    #[inline(always)]
+    pub fn ctor<Args: Tuple>(args: Args) -> Self {
+        rustc_clr_interop_managed_tuple_ctor::<ASSEMBLY, CLASS_PATH, false, Args>(args)
+    }
+    /// Calls the static method `METHOD`, passing the elements of `args` as its arguments.
+    #[inline(always)]
+    pub fn call_static<const METHOD: &'static str, Args: Tuple, Ret>(args: Args) -> Ret {
+        rustc_clr_interop_managed_tuple_call_static::<ASSEMBLY, CLASS_PATH, false, METHOD, Ret, Args>(
+            args,
+        )
+    }
+    /// Calls the instance method `METHOD` on `self`, passing the elements of `args` as its arguments.
+    #[inline(always)]
+    pub fn call_instance<const METHOD: &'static str, Args: Tuple, Ret>(self, args: Args) -> Ret {
+        rustc_clr_interop_managed_tuple_call_instance::<
+            ASSEMBLY,
+            CLASS_PATH,
+            false,
+            METHOD,
+            Ret,
+            Self,
+            Args,
+        >(self, args)
+    }
+    /// Calls the virtual method `METHOD` on `self`, passing the elements of `args` as its arguments.
+    #[inline(always)]
+    pub fn call_virt<const METHOD: &'static str, Args: Tuple, Ret>(self, args: Args) -> Ret {
+        rustc_clr_interop_managed_tuple_call_virt::<
+            ASSEMBLY,
+            CLASS_PATH,
+            false,
+            METHOD,
+            Ret,
+            Self,
+            Args,
+        >(self, args)
+    }
+    /// Calls the generic static method `METHOD`, instantiated with `MethodGenerics`. `Sig` is the signature of the method,
+    /// as declared in .NET: generic parameters are written as [`RustcCLRInteropManagedMethodGeneric`].
+    /// ```ignore
+    /// // Enumerable.Count<int>(IEnumerable<int>)
+    /// let count = Enumerable::call_static_generic::<
+    ///     "Count",
+    ///     (i32,),
+    ///     fn(IEnumerable<MethodGeneric<0>>) -> i32,
+    ///     _,
+    ///     i32,
+    /// >((list,));
+    /// ```
+    #[inline(always)]
+    pub fn call_static_generic<
+        const METHOD: &'static str,
+        MethodGenerics: Tuple,
+        Sig,
+        Args: Tuple,
+        Ret,
+    >(
+        args: Args,
+    ) -> Ret {
+        rustc_clr_interop_managed_generic_call_static::<Self, METHOD, MethodGenerics, Sig, Ret, Args>(
+            args,
+        )
+    }
+    /// Calls the generic instance method `METHOD` on `self`, instantiated with `MethodGenerics`.
+    #[inline(always)]
+    pub fn call_instance_generic<
+        const METHOD: &'static str,
+        MethodGenerics: Tuple,
+        Sig,
+        Args: Tuple,
+        Ret,
+    >(
+        self,
+        args: Args,
+    ) -> Ret {
+        rustc_clr_interop_managed_generic_call_instance::<
+            Self,
+            METHOD,
+            MethodGenerics,
+            Sig,
+            Ret,
+            Self,
+            Args,
+        >(self, args)
+    }
+    /// Calls the generic virtual method `METHOD` on `self`, instantiated with `MethodGenerics`.
+    #[inline(always)]
+    pub fn call_virt_generic<
+        const METHOD: &'static str,
+        MethodGenerics: Tuple,
+        Sig,
+        Args: Tuple,
+        Ret,
+    >(
+        self,
+        args: Args,
+    ) -> Ret {
+        rustc_clr_interop_managed_generic_call_virt::<
+            Self,
+            METHOD,
+            MethodGenerics,
+            Sig,
+            Ret,
+            Self,
+            Args,
+        >(self, args)
+    }
     #[inline(always)]
     pub fn to_mstring(self) -> crate::system::MString {
         self.instance0::<"ToString", crate::system::MString>()
@@ -113,8 +225,120 @@ impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str>
     pub fn is_null(self) -> bool {
         self.equality(Self::null())
     }
+    #[inline(always)]
+    pub fn ld_fld<const FIELD: &'static str, T>(self) -> T {
+        rustc_clr_interop_managed_ld_fld::<FIELD, Self, T>(self)
+    }
+    #[inline(always)]
+    pub fn st_fld<const FIELD: &'static str, T>(self, val: T) {
+        rustc_clr_interop_managed_st_fld::<FIELD, Self, T>(self, val)
+    }
 }
 
+/// A reference to an instance of a generic .NET class, e.g. `List<int>`. `Generics` is a tuple of the generic arguments,
+/// and `CLASS_PATH` is the path of the class without the generic arity(`System.Collections.Generic.List`).
+#[repr(C)]
+pub struct RustcCLRInteropManagedGenericClass<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    Generics: Tuple,
+> {
+    size_hint: usize,
+    generics: PhantomData<Generics>,
+}
+impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str, Generics: Tuple> Clone
+    for RustcCLRInteropManagedGenericClass<ASSEMBLY, CLASS_PATH, Generics>
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str, Generics: Tuple> Copy
+    for RustcCLRInteropManagedGenericClass<ASSEMBLY, CLASS_PATH, Generics>
+{
+}
+/// The generic parameter `INDEX` of a class(`!INDEX`). Only used in signatures of methods of generic classes.
+pub struct RustcCLRInteropManagedTypeGeneric<const INDEX: u32>;
+/// The generic parameter `INDEX` of a method(`!!INDEX`). Only used in signatures of generic methods.
+pub struct RustcCLRInteropManagedMethodGeneric<const INDEX: u32>;
+impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str, Generics: Tuple>
+    RustcCLRInteropManagedGenericClass<ASSEMBLY, CLASS_PATH, Generics>
+{
+    /// Calls the constructor with the signature `Sig`, taking the elements of `args` as its arguments.
+    /// ```ignore
+    /// type IntList = RustcCLRInteropManagedGenericClass<"System.Collections", "System.Collections.Generic.List", (i32,)>;
+    /// let list = IntList::ctor::<fn(i32), _>((16,));
+    /// ```
+    #[inline(always)]
+    pub fn ctor<Sig, Args: Tuple>(args: Args) -> Self {
+        rustc_clr_interop_managed_generic_ctor::<Self, Sig, Args>(args)
+    }
+    /// Calls the static method `METHOD`, instantiated with `MethodGenerics`. `Sig` is the signature of the method, as
+    /// declared in .NET: generic parameters of the class are written as [`RustcCLRInteropManagedTypeGeneric`], and those
+    /// of the method as [`RustcCLRInteropManagedMethodGeneric`].
+    #[inline(always)]
+    pub fn call_static<const METHOD: &'static str, MethodGenerics: Tuple, Sig, Args: Tuple, Ret>(
+        args: Args,
+    ) -> Ret {
+        rustc_clr_interop_managed_generic_call_static::<Self, METHOD, MethodGenerics, Sig, Ret, Args>(
+            args,
+        )
+    }
+    /// Calls the instance method `METHOD` on `self`, instantiated with `MethodGenerics`.
+    /// ```ignore
+    /// // List<int>.Add(!0)
+    /// list.call_instance::<"Add", (), fn(TypeGeneric<0>), _, ()>((5,));
+    /// ```
+    #[inline(always)]
+    pub fn call_instance<
+        const METHOD: &'static str,
+        MethodGenerics: Tuple,
+        Sig,
+        Args: Tuple,
+        Ret,
+    >(
+        self,
+        args: Args,
+    ) -> Ret {
+        rustc_clr_interop_managed_generic_call_instance::<
+            Self,
+            METHOD,
+            MethodGenerics,
+            Sig,
+            Ret,
+            Self,
+            Args,
+        >(self, args)
+    }
+    /// Calls the virtual method `METHOD` on `self`, instantiated with `MethodGenerics`.
+    #[inline(always)]
+    pub fn call_virt<const METHOD: &'static str, MethodGenerics: Tuple, Sig, Args: Tuple, Ret>(
+        self,
+        args: Args,
+    ) -> Ret {
+        rustc_clr_interop_managed_generic_call_virt::<
+            Self,
+            METHOD,
+            MethodGenerics,
+            Sig,
+            Ret,
+            Self,
+            Args,
+        >(self, args)
+    }
+    #[inline(always)]
+    pub fn to_mstring(self) -> crate::system::MString {
+        self.call_virt::<"ToString", (), fn() -> crate::system::MString, (), _>(())
+    }
+    #[inline(always)]
+    pub fn null() -> Self {
+        rustc_clr_interop_managed_ld_null::<Self>()
+    }
+}
+unsafe impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str, Generics: Tuple>
+    ManagedSafe for RustcCLRInteropManagedGenericClass<ASSEMBLY, CLASS_PATH, Generics>
+{
+}
 #[derive(Clone, Copy)]
 #[repr(C)]
 pub struct RustcCLRInteropManagedChar {
@@ -249,6 +473,130 @@ pub fn rustc_clr_interop_managed_call_virt2_<
 ) -> Ret {
     core::intrinsics::abort();
 }
+// Tuple-packed calls, taking any number of arguments. The elements of `args` are passed as separate arguments.
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_tuple_call_static<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const METHOD: &'static str,
+    Ret,
+    Args: Tuple,
+>(
+    args: Args,
+) -> Ret {
+    core::intrinsics::abort();
+}
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_tuple_call_instance<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const METHOD: &'static str,
+    Ret,
+    This,
+    Args: Tuple,
+>(
+    this: This,
+    args: Args,
+) -> Ret {
+    core::intrinsics::abort();
+}
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_tuple_call_virt<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const METHOD: &'static str,
+    Ret,
+    This,
+    Args: Tuple,
+>(
+    this: This,
+    args: Args,
+) -> Ret {
+    core::intrinsics::abort();
+}
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_tuple_ctor<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    Args: Tuple,
+>(
+    args: Args,
+) -> RustcCLRInteropManagedClass<ASSEMBLY, CLASS_PATH> {
+    core::intrinsics::abort();
+}
+// Calls to methods of generic classes, or generic methods. `Class` is the type owning the method, `MethodGenerics` is a
+// tuple of the generic arguments of the method, and `Sig` is a function pointer with the signature of the method, as
+// declared in .NET(excluding `this`).
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_generic_call_static<
+    Class,
+    const METHOD: &'static str,
+    MethodGenerics: Tuple,
+    Sig,
+    Ret,
+    Args: Tuple,
+>(
+    args: Args,
+) -> Ret {
+    core::intrinsics::abort();
+}
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_generic_call_instance<
+    Class,
+    const METHOD: &'static str,
+    MethodGenerics: Tuple,
+    Sig,
+    Ret,
+    This,
+    Args: Tuple,
+>(
+    this: This,
+    args: Args,
+) -> Ret {
+    core::intrinsics::abort();
+}
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_generic_call_virt<
+    Class,
+    const METHOD: &'static str,
+    MethodGenerics: Tuple,
+    Sig,
+    Ret,
+    This,
+    Args: Tuple,
+>(
+    this: This,
+    args: Args,
+) -> Ret {
+    core::intrinsics::abort();
+}
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_generic_ctor<Class, Sig, Args: Tuple>(args: Args) -> Class {
+    core::intrinsics::abort();
+}
+/// Creates a delegate of type `Delegate`, taking `Args` and returning `Ret`. The delegate calls `invoke(data, args...)`, and
+/// `drop(data)` is called once it is collected by the GC.
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_delegate<Delegate, Args: Tuple, Ret>(
+    invoke: *const (),
+    drop: *const (),
+    data: *mut (),
+) -> Delegate {
+    core::intrinsics::abort();
+}
 //Ctors
 #[allow(unused_variables)]
 #[inline(never)]
@@ -266,6 +614,16 @@ pub fn rustc_clr_interop_managed_ld_null<T>() -> T {
 }
 #[allow(unused_variables)]
 #[inline(never)]
+pub fn rustc_clr_interop_managed_ld_fld<const FIELD: &'static str, Obj, T>(obj: Obj) -> T {
+    core::intrinsics::abort();
+}
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_st_fld<const FIELD: &'static str, Obj, T>(obj: Obj, val: T) {
+    core::intrinsics::abort();
+}
+#[allow(unused_variables)]
+#[inline(never)]
 pub fn rustc_clr_interop_managed_checked_cast<DST, SRC>(src: SRC) -> DST {
     core::intrinsics::abort();
 }
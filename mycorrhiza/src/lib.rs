@@ -4,11 +4,22 @@
 //! implement an equivalent APIs in standard Rust.
 
 #![allow(internal_features, incomplete_features)]
-#![feature(core_intrinsics, unsized_const_params, inherent_associated_types)]
+#![feature(
+    core_intrinsics,
+    unsized_const_params,
+    inherent_associated_types,
+    tuple_trait,
+    unboxed_closures,
+    fn_traits
+)]
+/// Generated bindings to the .NET class library. Can be regenerated with `dotnet_bindgen <ASSEMBLY.dll> src/bindings.rs --crate crate`
 #[allow(non_snake_case, unused_imports)]
 pub mod bindings;
 pub use bindings::*;
 pub mod class;
+/// Rust closures wrapped in .NET delegates.
+pub mod delegate;
+pub use delegate::delegate;
 /// Very low-level interop stuff. Don't use unless you need to.
 pub mod intrinsics;
 use class::*;
@@ -16,6 +27,12 @@ use class::*;
 pub mod system;
 /// C# `char` type
 pub type DotNetChar = crate::intrinsics::RustcCLRInteropManagedChar;
+/// The generic parameter `INDEX` of a class(`!INDEX`), used in signatures of methods of generic classes.
+pub type TypeGeneric<const INDEX: u32> =
+    crate::intrinsics::RustcCLRInteropManagedTypeGeneric<INDEX>;
+/// The generic parameter `INDEX` of a method(`!!INDEX`), used in signatures of generic methods.
+pub type MethodGeneric<const INDEX: u32> =
+    crate::intrinsics::RustcCLRInteropManagedMethodGeneric<INDEX>;
 
 #[macro_export]
 macro_rules! panic_handler {
@@ -62,6 +79,17 @@ macro_rules! managed_safe {
     };
 }
 managed_safe! {u8,i8,u16,i16,u32,i32,u64,i64,u128,i128,usize,isize,f32,f64}
+/// Tuples of managed-safe types are used to pass arguments to managed methods.
+macro_rules! managed_safe_tuple {
+    () => {
+        unsafe impl ManagedSafe for () {}
+    };
+    ($t:ident $(,$ts:ident)*) => {
+        unsafe impl<$t: ManagedSafe, $($ts: ManagedSafe),*> ManagedSafe for ($t, $($ts,)*) {}
+        managed_safe_tuple! { $($ts),* }
+    };
+}
+managed_safe_tuple! {A1,A2,A3,A4,A5,A6,A7,A8,A9,A10,A11,A12,A13,A14,A15,A16}
 unsafe impl<T> ManagedSafe for *mut T {}
 unsafe impl<T> ManagedSafe for *const T {}
 pub trait IntoManagedSafe<Target: ManagedSafe> {
@@ -0,0 +1,81 @@
+use crate::intrinsics::RustcCLRInteropManagedGenericClass;
+use crate::TypeGeneric;
+pub type IEnumerable<T> = RustcCLRInteropManagedGenericClass<
+    "System.Runtime",
+    "System.Collections.Generic.IEnumerable",
+    (T,),
+>;
+pub type List<T> = RustcCLRInteropManagedGenericClass<
+    "System.Collections",
+    "System.Collections.Generic.List",
+    (T,),
+>;
+impl<T> List<T> {
+    #[inline(always)]
+    pub fn empty() -> Self {
+        Self::ctor::<fn(), ()>(())
+    }
+    #[inline(always)]
+    pub fn with_capacity(capacity: i32) -> Self {
+        Self::ctor::<fn(i32), (i32,)>((capacity,))
+    }
+    #[inline(always)]
+    pub fn add(self, item: T) {
+        self.call_instance::<"Add", (), fn(TypeGeneric<0>), (T,), ()>((item,))
+    }
+    #[inline(always)]
+    pub fn get(self, index: i32) -> T {
+        self.call_instance::<"get_Item", (), fn(i32) -> TypeGeneric<0>, (i32,), T>((index,))
+    }
+    #[inline(always)]
+    pub fn set(self, index: i32, item: T) {
+        self.call_instance::<"set_Item", (), fn(i32, TypeGeneric<0>), (i32, T), ()>((index, item))
+    }
+    #[inline(always)]
+    pub fn count(self) -> i32 {
+        self.call_instance::<"get_Count", (), fn() -> i32, (), i32>(())
+    }
+    #[inline(always)]
+    pub fn clear(self) {
+        self.call_instance::<"Clear", (), fn(), (), ()>(())
+    }
+}
+pub type Dictionary<K, V> = RustcCLRInteropManagedGenericClass<
+    "System.Collections",
+    "System.Collections.Generic.Dictionary",
+    (K, V),
+>;
+impl<K, V> Dictionary<K, V> {
+    #[inline(always)]
+    pub fn empty() -> Self {
+        Self::ctor::<fn(), ()>(())
+    }
+    #[inline(always)]
+    pub fn add(self, key: K, value: V) {
+        self.call_instance::<"Add", (), fn(TypeGeneric<0>, TypeGeneric<1>), (K, V), ()>((
+            key, value,
+        ))
+    }
+    #[inline(always)]
+    pub fn get(self, key: K) -> V {
+        self.call_instance::<"get_Item", (), fn(TypeGeneric<0>) -> TypeGeneric<1>, (K,), V>((key,))
+    }
+    #[inline(always)]
+    pub fn set(self, key: K, value: V) {
+        self.call_instance::<"set_Item", (), fn(TypeGeneric<0>, TypeGeneric<1>), (K, V), ()>((
+            key, value,
+        ))
+    }
+    #[inline(always)]
+    pub fn contains_key(self, key: K) -> bool {
+        self.call_instance::<"ContainsKey", (), fn(TypeGeneric<0>) -> bool, (K,), bool>((key,))
+    }
+    #[inline(always)]
+    pub fn remove(self, key: K) -> bool {
+        self.call_instance::<"Remove", (), fn(TypeGeneric<0>) -> bool, (K,), bool>((key,))
+    }
+    #[inline(always)]
+    pub fn count(self) -> i32 {
+        self.call_instance::<"get_Count", (), fn() -> i32, (), i32>(())
+    }
+}
@@ -1,11 +1,14 @@
 use runtime::interop_services::Marshal;
 
+pub mod collections;
 pub mod console;
 pub mod diagnostics;
 pub mod runtime;
 pub mod text;
 pub type MString =
     crate::intrinsics::RustcCLRInteropManagedClass<"System.Runtime", "System.String">;
+/// A delegate taking no arguments, and returning nothing. Can be created from a Rust closure using [`crate::delegate`].
+pub type Action = crate::intrinsics::RustcCLRInteropManagedClass<"System.Runtime", "System.Action">;
 
 impl From<&str> for MString {
     fn from(val: &str) -> Self {
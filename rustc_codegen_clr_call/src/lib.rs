@@ -7,7 +7,7 @@ use cilly::FnSig;
 use rustc_abi::{CanonAbi, ExternAbi as TargetAbi};
 use rustc_codegen_clr_ctx::MethodCompileCtx;
 use rustc_codegen_clr_type::r#type::get_type;
-use rustc_middle::ty::{Instance, List, PseudoCanonicalInput, TyKind};
+use rustc_middle::ty::{Instance, InstanceKind, List, PseudoCanonicalInput, TyKind};
 pub struct CallInfo {
     sig: FnSig,
     split_last_tuple: bool,
@@ -34,7 +34,6 @@ impl CallInfo {
             CanonAbi::X86(_) => (),
             _ => panic!("ERROR:calling using convention {conv:?} is not supported!"),
         }
-        //assert!(!fn_abi.c_variadic);
         let ret = get_type(fn_abi.ret.layout.ty, ctx);
         let mut args = Vec::with_capacity(fn_abi.args.len());
 
@@ -47,6 +46,8 @@ impl CallInfo {
             rustc_middle::ty::TypingEnv::fully_monomorphized(),
         );
         let internal_abi = match fn_ty.kind() {
+            // A thread local shim is typed like its thread local, but it is a Rust function returning its address.
+            _ if matches!(function.def, InstanceKind::ThreadLocalShim(_)) => TargetAbi::Rust,
             TyKind::FnDef(_, _) => fn_ty.fn_sig(ctx.tcx()).abi(),
             TyKind::Closure(_, args) => args.as_closure().sig().abi(),
             TyKind::Coroutine(_, _) => TargetAbi::Rust, // TODO: this assumes all coroutines have the ABI Rust. This *should* be correct.
@@ -66,15 +67,13 @@ impl CallInfo {
             ))?,*/
             _ => todo!("Unsuported ABI:{internal_abi:?}"),
         };
-        let mut sig = FnSig::new(args, ret);
-        if fn_abi.c_variadic {
-            let remaining = fn_abi.args[(fn_abi.fixed_count as usize)..]
-                .iter()
-                .map(|ty| get_type(ctx.monomorphize(ty.layout.ty), ctx));
-            let mut inputs = sig.inputs().to_vec();
-            inputs.extend(remaining);
-            sig.set_inputs(inputs);
-        }
+        // The ABI is computed without any additional arguments, so `args` only contains the fixed ones. The additional
+        // arguments are passed after them, at each call site.
+        let sig = if fn_abi.c_variadic {
+            FnSig::new_variadic(args, ret)
+        } else {
+            FnSig::new(args, ret)
+        };
         Self {
             sig,
             split_last_tuple,
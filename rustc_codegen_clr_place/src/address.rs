@@ -1,8 +1,8 @@
 use super::PlaceTy;
 use crate::pointer_to_is_fat;
 use cilly::{
-    Assembly, Const, FieldDesc, Int, Interned, IntoAsmIndex, MethodRef, Type, call,
-    cil_node::V1Node, cilnode::MethodKind, conv_usize, ld_field,
+    Const, FieldDesc, Int, Interned, IntoAsmIndex, MethodRef, Type, call, cil_node::V1Node,
+    cilnode::MethodKind, conv_usize, ld_field,
 };
 use rustc_codegen_clr_ctx::MethodCompileCtx;
 use rustc_codegen_clr_type::{
@@ -16,17 +16,11 @@ use rustc_middle::{
 };
 pub fn local_address(
     local: usize,
-    method: &rustc_middle::mir::Body,
-    asm: &mut Assembly,
+    ctx: &mut MethodCompileCtx<'_, '_>,
 ) -> Interned<cilly::v2::CILNode> {
-    let local = if let Some(spread_arg) = method.spread_arg
-        && local == spread_arg.as_usize()
-    {
-        cilly::CILNode::LdLocA(
-            (method.local_decls.len() - method.arg_count)
-                .try_into()
-                .unwrap(),
-        )
+    let method = ctx.body();
+    let local = if let Some(arg_local) = crate::arg_in_local(local, ctx) {
+        cilly::CILNode::LdLocA(arg_local)
     } else if local == 0 {
         cilly::CILNode::LdLocA(0)
     } else if local > method.arg_count {
@@ -34,8 +28,8 @@ pub fn local_address(
     } else {
         cilly::CILNode::LdArgA(u32::try_from(local - 1).unwrap())
     };
-    let local = asm.alloc_node(local);
-    asm.alloc_node(cilly::CILNode::RefToPtr(local))
+    let local = ctx.alloc_node(local);
+    ctx.alloc_node(cilly::CILNode::RefToPtr(local))
 }
 pub fn address_last_dereference<'tcx>(
     target_ty: Ty<'tcx>,
@@ -197,7 +191,7 @@ pub fn place_elem_address<'tcx>(
             let curr_ty = curr_type
                 .as_ty()
                 .expect("INVALID PLACE: Indexing into enum variant???");
-            let index = crate::local_get(index.as_usize(), ctx.body(), ctx);
+            let index = crate::local_get(index.as_usize(), ctx);
             match curr_ty.kind() {
                 TyKind::Slice(inner) => {
                     let inner = ctx.monomorphize(*inner);
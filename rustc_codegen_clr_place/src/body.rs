@@ -20,9 +20,9 @@ pub fn local_body<'tcx>(
     let ty = ctx.body().local_decls[Local::from_usize(local)].ty;
     let ty = ctx.monomorphize(ty);
     if body_ty_is_by_address(ty, ctx) {
-        (super::address::local_address(local, ctx.body(), ctx), ty)
+        (super::address::local_address(local, ctx), ty)
     } else {
-        (super::get::local_get(local, ctx.body(), ctx), ty)
+        (super::get::local_get(local, ctx), ty)
     }
 }
 fn body_field<'a>(
@@ -107,7 +107,7 @@ pub fn place_elem_body_index<'tcx>(
     parrent_node: Interned<cilly::v2::CILNode>,
     index: rustc_middle::mir::Local,
 ) -> (PlaceTy<'tcx>, Interned<cilly::v2::CILNode>) {
-    let index = crate::local_get(index.as_usize(), ctx.body(), ctx);
+    let index = crate::local_get(index.as_usize(), ctx);
     match curr_ty.kind() {
         TyKind::Slice(inner) => {
             let inner = ctx.monomorphize(*inner);
@@ -190,12 +190,7 @@ pub fn place_elem_body<'tcx>(
                 .as_ty()
                 .expect("Can't get enum variant of an enum varaint!");
             let curr_type = ctx.monomorphize(curr_type);
-            if matches!(curr_ty.as_ty().unwrap().kind(), TyKind::Coroutine(_, _)) {
-                eprintln!(
-                    "UNTESTED:  downcaststing coroutines is not fully supported, and the behaviour of corrutines is not yet fully tested! variant:{variant:?} curr_type:{curr_type:?}"
-                );
-                return (curr_type.into(), parrent_node);
-            }
+            // Coroutine states are handled just like enum variants.
             let variant_type = PlaceTy::EnumVariant(curr_type, variant.as_u32());
 
             (variant_type, parrent_node)
@@ -1,5 +1,5 @@
 use cilly::{
-    Assembly, BinOp, Const, FieldDesc, Int, Interned, IntoAsmIndex, MethodRef, Type, call,
+    BinOp, Const, FieldDesc, Int, Interned, IntoAsmIndex, MethodRef, Type, call,
     cil_node::V1Node,
     cilnode::{ExtendKind, MethodKind},
     conv_usize, ld_field,
@@ -21,36 +21,29 @@ use super::body_ty_is_by_address;
 
 pub(super) fn local_get(
     local: usize,
-    method: &rustc_middle::mir::Body,
-    asm: &mut Assembly,
+    ctx: &mut MethodCompileCtx<'_, '_>,
 ) -> Interned<cilly::v2::CILNode> {
-    asm.alloc_node(
-        if let Some(spread_arg) = method.spread_arg
-            && local == spread_arg.as_usize()
-        {
-            cilly::CILNode::LdLoc(
-                (method.local_decls.len() - method.arg_count)
-                    .try_into()
-                    .unwrap(),
-            )
-        } else if local == 0 {
-            cilly::CILNode::LdLoc(0)
-        } else if local > method.arg_count {
-            cilly::CILNode::LdLoc(
-                u32::try_from(local - method.arg_count)
-                    .expect("Method has more than 2^32 local varaibles"),
-            )
-        } else {
-            cilly::CILNode::LdArg(
-                u32::try_from(local - 1).expect("Method has more than 2^32 local variables"),
-            )
-        },
-    )
+    let method = ctx.body();
+    let arg_local = crate::arg_in_local(local, ctx);
+    ctx.alloc_node(if let Some(arg_local) = arg_local {
+        cilly::CILNode::LdLoc(arg_local)
+    } else if local == 0 {
+        cilly::CILNode::LdLoc(0)
+    } else if local > method.arg_count {
+        cilly::CILNode::LdLoc(
+            u32::try_from(local - method.arg_count)
+                .expect("Method has more than 2^32 local varaibles"),
+        )
+    } else {
+        cilly::CILNode::LdArg(
+            u32::try_from(local - 1).expect("Method has more than 2^32 local variables"),
+        )
+    })
 }
 /// Returns the ops for getting the value of place.
 pub fn place_get<'tcx>(place: &Place<'tcx>, ctx: &mut MethodCompileCtx<'tcx, '_>) -> V1Node {
     if place.projection.is_empty() {
-        V1Node::V2(local_get(place.local.as_usize(), ctx.body(), ctx))
+        V1Node::V2(local_get(place.local.as_usize(), ctx))
     } else {
         let (mut op, mut ty) = super::local_body(place.local.as_usize(), ctx);
 
@@ -140,7 +133,7 @@ fn place_elem_get<'a>(
             let curr_ty = curr_type
                 .as_ty()
                 .expect("INVALID PLACE: Indexing into enum variant???");
-            let index = crate::local_get(index.as_usize(), ctx.body(), ctx);
+            let index = crate::local_get(index.as_usize(), ctx);
             match curr_ty.kind() {
                 TyKind::Slice(inner) => {
                     let inner = ctx.monomorphize(*inner);
@@ -21,7 +21,9 @@ mod set;
 pub use address::*;
 pub use body::*;
 pub use get::*;
-use rustc_middle::ty::{FloatTy, IntTy, Ty, TyKind, UintTy};
+use rustc_middle::ty::{
+    DynKind, FloatTy, IntTy, List, PseudoCanonicalInput, Ty, TyKind, TypingEnv, UintTy,
+};
 pub use set::*;
 
 fn slice_head<T>(slice: &[T]) -> (&T, &[T]) {
@@ -42,6 +44,32 @@ fn pointed_type(ty: PlaceTy) -> Ty {
         panic!("Can't dereference enum variant!");
     }
 }
+/// Checks if `local` is the `VaList` of a C-variadic function, initialized by `va_start` on entry.
+pub fn is_va_list(local: usize, ctx: &MethodCompileCtx<'_, '_>) -> bool {
+    local != 0
+        && local == ctx.body().arg_count
+        && ctx
+            .tcx()
+            .fn_abi_of_instance(PseudoCanonicalInput {
+                typing_env: TypingEnv::fully_monomorphized(),
+                value: (ctx.instance(), List::empty()),
+            })
+            .is_ok_and(|abi| abi.c_variadic)
+}
+/// If the MIR argument `local` has no CIL argument of its own, returns the index of the local it is kept in instead.
+/// This is the case for the spread argument of a `rust-call` function, which gets repacked into a tuple, and for the
+/// `VaList` of a C-variadic function. Both are kept in a local placed after all the other ones.
+pub fn arg_in_local(local: usize, ctx: &MethodCompileCtx<'_, '_>) -> Option<u32> {
+    let method = ctx.body();
+    let is_spread_arg = method
+        .spread_arg
+        .is_some_and(|spread_arg| local == spread_arg.as_usize());
+    (is_spread_arg || is_va_list(local, ctx)).then(|| {
+        (method.local_decls.len() - method.arg_count)
+            .try_into()
+            .unwrap()
+    })
+}
 fn body_ty_is_by_address<'tcx>(last_ty: Ty<'tcx>, ctx: &mut MethodCompileCtx<'tcx, '_>) -> bool {
     match *last_ty.kind() {
         // True for non-0 tuples
@@ -51,6 +79,7 @@ fn body_ty_is_by_address<'tcx>(last_ty: Ty<'tcx>, ctx: &mut MethodCompileCtx<'tc
         TyKind::Adt(_, _)
         | TyKind::Closure(_, _)
         | TyKind::Coroutine(_, _)
+        | TyKind::Dynamic(_, _, DynKind::DynStar)
         | TyKind::Array(_, _)
         | TyKind::Slice(_)
         | TyKind::Str => true,
@@ -96,9 +125,9 @@ pub fn place_address<'a>(place: &Place<'a>, ctx: &mut MethodCompileCtx<'a, '_>)
     if place.projection.is_empty() {
         let loc_ty = ctx.monomorphize(ctx.body().local_decls[place.local].ty);
         if pointer_to_is_fat(loc_ty, ctx.tcx(), ctx.instance()) {
-            V1Node::V2(local_get(place.local.as_usize(), ctx.body(), ctx))
+            V1Node::V2(local_get(place.local.as_usize(), ctx))
         } else {
-            V1Node::V2(local_address(place.local.as_usize(), ctx.body(), ctx))
+            V1Node::V2(local_address(place.local.as_usize(), ctx))
         }
     } else {
         let (mut addr_calc, mut ty) = local_body(place.local.as_usize(), ctx);
@@ -126,14 +155,14 @@ pub fn place_address_raw<'a>(place: &Place<'a>, ctx: &mut MethodCompileCtx<'a, '
         return V1Node::V2(ctx.alloc_node(Const::USize(layout.align.abi.bytes())));
     }
     if place.projection.is_empty() {
-        V1Node::V2(local_address(place.local.as_usize(), ctx.body(), ctx))
+        V1Node::V2(local_address(place.local.as_usize(), ctx))
     } else if place.projection.len() == 1
         && matches!(
             slice_head(place.projection).0,
             rustc_middle::mir::PlaceElem::Deref
         )
     {
-        return V1Node::V2(local_address(place.local.as_usize(), ctx.body(), ctx));
+        return V1Node::V2(local_address(place.local.as_usize(), ctx));
     } else {
         let (mut addr_calc, mut ty) = local_body(place.local.as_usize(), ctx);
 
@@ -156,7 +185,7 @@ pub fn place_set<'tcx>(
     ctx: &mut MethodCompileCtx<'tcx, '_>,
 ) -> V1Root {
     if place.projection.is_empty() {
-        set::local_set(place.local.as_usize(), ctx.body(), value_calc)
+        set::local_set(place.local.as_usize(), ctx, value_calc)
     } else {
         let (mut addr_calc, ty) = local_body(place.local.as_usize(), ctx);
 
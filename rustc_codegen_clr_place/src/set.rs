@@ -16,16 +16,13 @@ use cilly::{
 };
 use rustc_middle::{
     mir::PlaceElem,
-    ty::{FloatTy, IntTy, Ty, TyKind, UintTy},
+    ty::{DynKind, FloatTy, IntTy, Ty, TyKind, UintTy},
 };
-pub fn local_set(local: usize, method: &rustc_middle::mir::Body, tree: V1Node) -> V1Root {
-    if let Some(spread_arg) = method.spread_arg
-        && local == spread_arg.as_usize()
-    {
+pub fn local_set(local: usize, ctx: &MethodCompileCtx<'_, '_>, tree: V1Node) -> V1Root {
+    let method = ctx.body();
+    if let Some(arg_local) = crate::arg_in_local(local, ctx) {
         return V1Root::STLoc {
-            local: (method.local_decls.len() - method.arg_count)
-                .try_into()
-                .unwrap(),
+            local: arg_local,
             tree,
         };
     }
@@ -77,7 +74,7 @@ pub fn place_elem_set<'a>(
             let curr_ty = curr_type
                 .as_ty()
                 .expect("INVALID PLACE: Indexing into enum variant???");
-            let index = crate::get::local_get(index.as_usize(), ctx.body(), ctx);
+            let index = crate::get::local_get(index.as_usize(), ctx);
 
             match curr_ty.kind() {
                 TyKind::Slice(inner) => {
@@ -276,7 +273,8 @@ pub fn ptr_set_op<'tcx>(
             | TyKind::Tuple(_)
             | TyKind::Array(_, _)
             | TyKind::Closure(_, _)
-            | TyKind::Coroutine(_, _) => {
+            | TyKind::Coroutine(_, _)
+            | TyKind::Dynamic(_, _, DynKind::DynStar) => {
                 let pointed_type = ctx.type_from_cache(pointed_type);
                 V1Root::STObj {
                     tpe: pointed_type.into(),
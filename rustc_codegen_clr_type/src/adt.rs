@@ -130,7 +130,7 @@ fn primitive_to_type(primitive: rustc_abi::Primitive, asm: &mut Assembly) -> Typ
         Primitive::Float(rustc_abi::Float::F16) => Type::Float(Float::F16),
         Primitive::Float(rustc_abi::Float::F32) => Type::Float(Float::F32),
         Primitive::Float(rustc_abi::Float::F64) => Type::Float(Float::F64),
-        Primitive::Float(rustc_abi::Float::F128) => todo!("No support for 128 bit floats yet!"),
+        Primitive::Float(rustc_abi::Float::F128) => Type::Float(Float::F128),
         Primitive::Pointer(_) => asm.nptr(Type::Void),
     }
 }
@@ -150,6 +150,25 @@ pub fn enum_field_descriptor<'tcx>(
     variant_idx: u32,
     ctx: &mut MethodCompileCtx<'tcx, '_>,
 ) -> Interned<FieldDesc> {
+    if let TyKind::Coroutine(def_id, args) = owner_ty.kind() {
+        // The fields of a coroutine state are the locals saved across its suspension point.
+        let variant = VariantIdx::from_u32(variant_idx);
+        let field_ty = args
+            .as_coroutine()
+            .state_tys(*def_id, ctx.tcx())
+            .nth(variant.as_usize())
+            .expect("No coroutine state with such index!")
+            .nth(field_idx as usize)
+            .expect("No coroutine field with provided index!");
+        let field_ty = ctx.monomorphize(field_ty);
+        let field_ty = ctx.type_from_cache(field_ty);
+        let owner_ty = ctx
+            .type_from_cache(owner_ty)
+            .as_class_ref()
+            .expect("Coroutine type invalid!");
+        let field_name = ctx.alloc_string(crate::r#type::coroutine_field_name(variant, field_idx));
+        return ctx.alloc_field(FieldDesc::new(owner_ty, field_name, field_ty));
+    }
     let (adt, subst) = as_adt(owner_ty).expect("Tried to get a field of a non ADT type!");
     let variant = adt
         .variants()
@@ -213,20 +232,15 @@ pub fn field_descrptor<'tcx>(
             field_type,
         ));
     } else if let TyKind::Coroutine(_, args) = owner_ty.kind() {
-        let coroutine = args.as_coroutine().witness();
-        let TyKind::CoroutineWitness(_, fields) = coroutine.kind() else {
-            panic!("corutine witness is not CoroutineWitness.");
-        };
-        let field_type = fields
+        // Fields of a coroutine which was not downcast to a state are its upvars.
+        let field_type = args
+            .as_coroutine()
+            .upvar_tys()
             .iter()
             .nth(field_idx as usize)
             .expect("Could not find coroutine fields!");
         let field_type = ctx.monomorphize(field_type);
-        let field_type = ctx.type_from_cache(
-            field_type
-                .as_type()
-                .expect("Non type args in coroutine witness."),
-        );
+        let field_type = ctx.type_from_cache(field_type);
         let owner_ty = ctx.monomorphize(owner_ty);
         let owner_type = ctx.type_from_cache(owner_ty);
         let field_name = ctx.alloc_string(format!("f_{field_idx}"));
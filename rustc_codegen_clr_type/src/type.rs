@@ -1,7 +1,8 @@
 use crate::adt::FieldOffsetIterator;
 use crate::utilis::{
-    INTEROP_ARR_TPE_NAME, INTEROP_CHR_TPE_NAME, INTEROP_CLASS_TPE_NAME, INTEROP_STRUCT_TPE_NAME,
-    is_zst, try_resolve_const_size,
+    INTEROP_ARR_TPE_NAME, INTEROP_CHR_TPE_NAME, INTEROP_CLASS_TPE_NAME,
+    INTEROP_GENERIC_CLASS_TPE_NAME, INTEROP_METHOD_GENERIC_TPE_NAME, INTEROP_STRUCT_TPE_NAME,
+    INTEROP_TYPE_GENERIC_TPE_NAME, is_zst, try_resolve_const_size,
 };
 use crate::utilis::{garag_to_usize, garg_to_string, is_name_magic, pointer_to_is_fat, tuple_name};
 use crate::{GetTypeExt, utilis::adt_name};
@@ -12,7 +13,7 @@ use cilly::{
     tpe::simd::SIMDVector,
     {
         Access, BasicBlock, CILNode, CILRoot, ClassDef, ClassRef, Float, Int, MethodDef,
-        MethodImpl, Type, cilnode::MethodKind,
+        MethodImpl, Type, cilnode::MethodKind, tpe::GenericKind,
     },
 };
 use cilly::{FnSig, IString};
@@ -23,8 +24,10 @@ use std::{
     num::{NonZero, NonZeroU32},
 };
 
-use rustc_abi::Layout;
-use rustc_middle::ty::{AdtDef, AdtKind, FloatTy, IntTy, List, Ty, TyKind, UintTy};
+use rustc_abi::{Layout, VariantIdx};
+use rustc_middle::ty::{
+    AdtDef, AdtKind, CoroutineArgs, DynKind, FloatTy, IntTy, List, Ty, TyCtxt, TyKind, UintTy,
+};
 use rustc_span::def_id::DefId;
 
 #[must_use]
@@ -129,7 +132,12 @@ pub fn get_type<'tcx>(ty: Ty<'tcx>, ctx: &mut MethodCompileCtx<'tcx, '_>) -> Typ
             }
             Type::ClassRef(cref)
         }
-        TyKind::Dynamic(_list, _, _) => {
+        TyKind::Dynamic(list, region, DynKind::DynStar) => {
+            // A `dyn*` is laid out just like a pointer to `dyn`: a pointer-sized object, followed by its vtable.
+            let dyn_ty = Ty::new_dynamic(ctx.tcx(), *list, *region, DynKind::Dyn);
+            Type::ClassRef(fat_ptr_to(dyn_ty, ctx))
+        }
+        TyKind::Dynamic(_list, _, DynKind::Dyn) => {
             let name = ctx.alloc_string("Dyn");
             let cref = ctx.alloc_class_ref(ClassRef::new(name, None, true, [].into()));
             if ctx.class_ref_to_def(cref).is_none() {
@@ -239,8 +247,8 @@ pub fn get_type<'tcx>(ty: Ty<'tcx>, ctx: &mut MethodCompileCtx<'tcx, '_>) -> Typ
                     )))
                 } else if name.contains(INTEROP_STRUCT_TPE_NAME) {
                     assert!(
-                        subst.len() == 2,
-                        "Managed struct reference must have exactly 2 generic arguments!"
+                        subst.len() == 3,
+                        "Managed struct reference must have exactly 3 generic arguments!"
                     );
                     let assembly = garg_to_string(subst[0], ctx.tcx());
                     let assembly = Some(assembly)
@@ -268,6 +276,38 @@ pub fn get_type<'tcx>(ty: Ty<'tcx>, ctx: &mut MethodCompileCtx<'tcx, '_>) -> Typ
                     }
                 } else if name.contains(INTEROP_CHR_TPE_NAME) {
                     Type::PlatformChar
+                } else if name.contains(INTEROP_GENERIC_CLASS_TPE_NAME) {
+                    assert!(
+                        subst.len() == 3,
+                        "Managed generic class reference must have exactly 3 generic arguments: assembly, path and a tuple of generic arguments!"
+                    );
+                    let assembly = garg_to_string(subst[0], ctx.tcx());
+                    let assembly = Some(assembly)
+                        .filter(|assembly| !assembly.is_empty())
+                        .map(|asm| ctx.alloc_string(asm));
+                    let name = garg_to_string(subst[1], ctx.tcx());
+                    let name = ctx.alloc_string(name);
+                    let generics = ctx.monomorphize(
+                        subst[2]
+                            .as_type()
+                            .expect("Generic arguments must be specified as a tuple!"),
+                    );
+                    let TyKind::Tuple(generics) = generics.kind() else {
+                        panic!("Generic arguments must be specified as a tuple, not {generics:?}!")
+                    };
+                    let generics: Box<[Type]> = generics
+                        .iter()
+                        .map(|generic| get_type(generic, ctx))
+                        .collect();
+                    Type::ClassRef(ctx.alloc_class_ref(ClassRef::new(
+                        name, assembly, false, generics,
+                    )))
+                } else if name.contains(INTEROP_TYPE_GENERIC_TPE_NAME) {
+                    let index = garag_to_usize(subst[0], ctx.tcx());
+                    Type::PlatformGeneric(index.try_into().unwrap(), GenericKind::TypeGeneric)
+                } else if name.contains(INTEROP_METHOD_GENERIC_TPE_NAME) {
+                    let index = garag_to_usize(subst[0], ctx.tcx());
+                    Type::PlatformGeneric(index.try_into().unwrap(), GenericKind::CallGeneric)
                 } else {
                     todo!("Interop type {name:?} is not yet supported!")
                 }
@@ -299,16 +339,30 @@ pub fn get_type<'tcx>(ty: Ty<'tcx>, ctx: &mut MethodCompileCtx<'tcx, '_>) -> Typ
         }
         TyKind::Alias(_, _) => panic!("Attempted to get the .NET type of an unmorphized type"),
         TyKind::Coroutine(defid, coroutine_args) => {
-            let coroutine_args = coroutine_args.as_coroutine();
+            let coroutine = coroutine_args.as_coroutine();
 
             // Extract the closure fields
-            let fields: Box<[_]> = coroutine_args
+            let upvars: Box<[_]> = coroutine
                 .upvar_tys()
                 .iter()
                 .map(|ty| get_type(ty, ctx))
                 .collect();
-            // Get a coroutine name.
-            let name = coroutine_name(*defid, &fields, ctx);
+            // Extract the locals saved in each state of this coroutine
+            let states: Box<[Box<[_]>]> = coroutine
+                .state_tys(*defid, ctx.tcx())
+                .map(|state| {
+                    state
+                        .map(|ty| get_type(ctx.monomorphize(ty), ctx))
+                        .collect()
+                })
+                .collect();
+            // Get a coroutine name. The saved locals are a part of the name, since they may depend on generic arguments which are not captured.
+            let all_fields: Box<[_]> = upvars
+                .iter()
+                .chain(states.iter().flatten())
+                .copied()
+                .collect();
+            let name = coroutine_name(*defid, &all_fields, ctx);
             let name = ctx.alloc_string(name);
             // Get the layout of the coroutine
             let layout = ctx.layout_of(ty);
@@ -316,8 +370,7 @@ pub fn get_type<'tcx>(ty: Ty<'tcx>, ctx: &mut MethodCompileCtx<'tcx, '_>) -> Typ
             let cref = ctx.alloc_class_ref(ClassRef::new(name, None, true, [].into()));
             // If there is no defition of this coroutine present, create the coroutine.
             if ctx.class_ref_to_def(cref).is_none() {
-                let mut type_def = closure_typedef(&fields, layout.layout, ctx, name);
-                handle_tag(&layout.layout, ctx, ty, type_def.fields_mut());
+                let type_def = coroutine_typedef(&upvars, &states, layout.layout, ty, ctx, name);
                 ctx.class_def(type_def);
             }
 
@@ -476,7 +529,9 @@ pub fn fat_ptr_to<'tcx>(
                 ),
             ],
             vec![],
-            Access::Public,
+            // Fat pointers, tuples and closures are anonymous types, only used within this assembly. They are not
+            // exported, which lets the linker merge them with other value types of the same layout.
+            Access::Private,
             Some(NonZeroU32::new(16).unwrap()),
             Some(NonZeroU32::new(8).unwrap()),
             true,
@@ -510,6 +565,81 @@ pub fn coroutine_name(
         field_count = fields.len()
     )
 }
+/// Returns the name of a field of a coroutine, holding a local saved in the state `variant`.
+pub fn coroutine_field_name(variant: VariantIdx, field_idx: u32) -> String {
+    format!(
+        "{variant_name}_{field_idx}",
+        variant_name = CoroutineArgs::<TyCtxt<'_>>::variant_name(variant)
+    )
+}
+/// Turns a coroutine into a [`ClassDef`]. Coroutines are laid out like enums: all states share the upvars and the tag,
+/// and each state(variant) stores the locals live across its suspension point.
+fn coroutine_typedef<'tcx>(
+    upvars: &[Type],
+    states: &[Box<[Type]>],
+    layout: Layout,
+    coroutine_ty: Ty<'tcx>,
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+    coroutine_name: Interned<IString>,
+) -> ClassDef {
+    let mut fields = Vec::new();
+    // Handle the upvars
+    let offset_iter = FieldOffsetIterator::fields((*layout.0).clone());
+    for ((idx, field), offset) in upvars.iter().enumerate().zip(offset_iter) {
+        if *field == Type::Void {
+            continue;
+        }
+        fields.push((*field, ctx.alloc_string(format!("f_{idx}")), Some(offset)));
+    }
+    // Handle the coroutine state.
+    handle_tag(&layout, ctx, coroutine_ty, &mut fields);
+    // Handle the saved locals. Locals saved in more than one state have the same offset in each of them.
+    for (vidx, state) in states.iter().enumerate() {
+        let vidx = VariantIdx::from_usize(vidx);
+        let offset_iter = FieldOffsetIterator::fields(crate::adt::get_variant_at_index(
+            vidx,
+            (*layout.0).clone(),
+        ));
+        for ((field_idx, field), offset) in state.iter().enumerate().zip(offset_iter) {
+            if *field == Type::Void {
+                continue;
+            }
+            let name = coroutine_field_name(vidx, u32::try_from(field_idx).unwrap());
+            fields.push((*field, ctx.alloc_string(name), Some(offset)));
+        }
+    }
+    ClassDef::new(
+        coroutine_name,
+        true,
+        0,
+        None,
+        fields,
+        vec![],
+        Access::Public,
+        Some(
+            NonZeroU32::new(
+                layout
+                    .size()
+                    .bytes()
+                    .try_into()
+                    .expect("Coroutine size exceeds 2^32"),
+            )
+            .unwrap(),
+        ),
+        Some(
+            NonZeroU32::new(
+                layout
+                    .align()
+                    .abi
+                    .bytes()
+                    .try_into()
+                    .expect("Coroutine alignment exceeds 2^32"),
+            )
+            .unwrap(),
+        ),
+        false,
+    )
+}
 /// Creates a [`ClassDef`] representing a closure with certain layout and fields.
 #[must_use]
 pub fn closure_typedef(
@@ -542,7 +672,7 @@ pub fn closure_typedef(
         None,
         fields,
         vec![],
-        Access::Public,
+        Access::Private,
         Some(
             NonZeroU32::new(
                 layout
@@ -851,7 +981,7 @@ pub fn tuple_typedef(
         None,
         fields,
         vec![],
-        Access::Public,
+        Access::Private,
         Some(
             NonZero::new(
                 layout
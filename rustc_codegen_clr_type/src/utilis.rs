@@ -59,6 +59,9 @@ pub const INTEROP_CLASS_TPE_NAME: &str = "RustcCLRInteropManagedClass";
 pub const INTEROP_STRUCT_TPE_NAME: &str = "RustcCLRInteropManagedStruct";
 pub const INTEROP_CHR_TPE_NAME: &str = "RustcCLRInteropManagedChar";
 pub const INTEROP_ARR_TPE_NAME: &str = "RustcCLRInteropManagedArray";
+pub const INTEROP_GENERIC_CLASS_TPE_NAME: &str = "RustcCLRInteropManagedGenericClass";
+pub const INTEROP_TYPE_GENERIC_TPE_NAME: &str = "RustcCLRInteropManagedTypeGeneric";
+pub const INTEROP_METHOD_GENERIC_TPE_NAME: &str = "RustcCLRInteropManagedMethodGeneric";
 #[must_use]
 /// Checks if a type is a magic interop type.
 pub fn is_name_magic(name: &str) -> bool {
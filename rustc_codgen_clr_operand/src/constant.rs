@@ -18,7 +18,7 @@ use rustc_middle::{
         interpret::Scalar,
         interpret::{AllocId, GlobalAlloc},
     },
-    ty::{FloatTy, IntTy, Ty, TyCtxt, TyKind, UintTy},
+    ty::{FloatTy, Instance, IntTy, List, Ty, TyCtxt, TyKind, UintTy},
 };
 use rustc_span::def_id::DefId;
 
@@ -218,8 +218,24 @@ fn load_scalar_ptr(
                 let cst = ctx.annon_const(cilly::v2::CILNode::LdFtn(site));
                 return ctx.alloc_node(cilly::v2::CILNode::LdStaticFieldAddress(cst));
             }
-            if let Some(section) = attrs.link_section {
-                panic!("static {name} requires special linkage in section {section:?}");
+            if ctx.tcx().is_foreign_item(def_id) {
+                // The bounds of linker sections(used by crates like `linkme`) are provided by the linker.
+                let symbol = ctx
+                    .tcx()
+                    .symbol_name(Instance::new_raw(def_id, List::empty()))
+                    .name;
+                let bound = if let Some(section) = symbol.strip_prefix("__start_") {
+                    Some((section, false))
+                } else {
+                    symbol
+                        .strip_prefix("__stop_")
+                        .map(|section| (section, true))
+                };
+                if let Some((section, end)) = bound {
+                    let section = ctx.alloc_string(section);
+                    let bound = ctx.link_section_bound(section, end);
+                    return ctx.alloc_node(cilly::v2::CILNode::LdStaticField(bound));
+                }
             }
             let alloc = ctx
                 .tcx()
@@ -17,19 +17,23 @@ pub use rustc_codegen_clr_ctx::MethodCompileCtx;
 use rustc_codegen_clr_ctx::function_name;
 use rustc_codegen_clr_type::{GetTypeExt, align_of, r#type::fixed_array};
 use rustc_middle::{
+    middle::codegen_fn_attrs::CodegenFnAttrFlags,
     mir::interpret::{AllocId, Allocation, GlobalAlloc},
     ty::{Instance, List, TypingEnv},
 };
 use rustc_span::def_id::DefId;
 
+/// Checks if the entries of `section` are run by the backend itself(`.init_array`, `.fini_array`), instead of being
+/// placed in a linker section.
+pub fn is_ctor_section(section: &str) -> bool {
+    section.contains(".init_array") || section.contains(".fini_array")
+}
 pub fn add_static(def_id: DefId, ctx: &mut MethodCompileCtx<'_, '_>) -> Interned<CILNode> {
     let main_module_id = ctx.main_module();
     let alloc = ctx.tcx().eval_static_initializer(def_id).unwrap();
     let attrs = ctx.tcx().codegen_fn_attrs(def_id);
 
-    let thread_local = attrs
-        .flags
-        .contains(rustc_middle::middle::codegen_fn_attrs::CodegenFnAttrFlags::THREAD_LOCAL);
+    let thread_local = attrs.flags.contains(CodegenFnAttrFlags::THREAD_LOCAL);
     let align = alloc.0.align.bytes().max(1);
     let ty = static_ty(def_id, ctx.tcx());
     let tpe = ctx.type_from_cache(ty);
@@ -48,6 +52,19 @@ pub fn add_static(def_id: DefId, ctx: &mut MethodCompileCtx<'_, '_>) -> Interned
         None,
         false,
     );
+    if let Some(section) = attrs.link_section {
+        let section = section.to_string();
+        if !is_ctor_section(&section) {
+            let section = ctx.alloc_string(section);
+            ctx.add_to_link_section(section, sfld);
+        }
+    }
+    if attrs
+        .flags
+        .intersects(CodegenFnAttrFlags::USED_COMPILER | CodegenFnAttrFlags::USED_LINKER)
+    {
+        ctx.add_used_static(sfld);
+    }
     let ptr = ctx.alloc_node(CILNode::LdStaticFieldAddress(sfld));
     let ptr = ctx.cast_ptr(ptr, Int::U8);
     let ptr = ptr;
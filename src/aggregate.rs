@@ -18,7 +18,7 @@ use rustc_codgen_clr_operand::{handle_operand, is_uninit};
 use rustc_index::IndexVec;
 use rustc_middle::{
     mir::{AggregateKind, Operand, Place},
-    ty::{AdtDef, AdtKind, GenericArg, List, Ty, TyKind},
+    ty::{AdtDef, AdtKind, CoroutineArgs, GenericArg, List, Ty, TyCtxt, TyKind},
 };
 /// Returns the CIL ops to create the aggreagate value specifed by `aggregate_kind` at `target_location`. Uses indivlidual values specifed by `value_index`
 pub fn handle_aggregate<'tcx>(
@@ -178,7 +178,8 @@ pub fn handle_aggregate<'tcx>(
             if disrc_type != Type::Void {
                 sub_trees.push(set_discr(
                     layout.layout,
-                    rustc_abi::VariantIdx::from_u32(0), // TODO: this assumes all coroutines start with a tag of 0
+                    // A newly created coroutine has not been resumed yet.
+                    rustc_abi::VariantIdx::from_usize(CoroutineArgs::<TyCtxt<'_>>::UNRESUMED),
                     closure_getter,
                     closure_dotnet,
                     layout.ty,
@@ -25,7 +25,7 @@ use rustc_codgen_clr_operand::static_data::add_static;
 use rustc_middle::mir::mono::Linkage;
 use rustc_middle::{
     mir::{interpret::GlobalAlloc, mono::MonoItem, Local, LocalDecl, Statement, Terminator},
-    ty::{TyCtxt, TyKind},
+    ty::{InstanceKind, TyCtxt, TyKind},
 };
 fn linkage_to_access(link: Option<Linkage>) -> Access {
     match link {
@@ -153,6 +153,8 @@ pub fn add_fn<'tcx, 'asm, 'a: 'asm>(
         //ALL OK.
     } else if let TyKind::Closure(_, _) = kind {
     } else if let TyKind::Coroutine(_, _) = kind {
+    } else if let InstanceKind::ThreadLocalShim(_) = ctx.instance().def {
+        // Typed like its thread local, but has a MIR body returning the thread local's address.
     } else {
         println!(
             "fn item {instance:?} is not a function definition type or a closure. Skippping.",
@@ -198,7 +200,7 @@ pub fn add_fn<'tcx, 'asm, 'a: 'asm>(
     let blocks = &mir.basic_blocks;
     let mut normal_bbs = Vec::new();
     let mut cleanup_bbs = Vec::new();
-    // Used for funcrions with the rust_call ABI
+    // Used for funcrions with the rust_call ABI, and for C-variadic functions
     let mut repack_cil = if let Some(spread_arg) = mir.spread_arg {
         // Prepare for repacking the argument tuple, by allocating a local
         let repacked = u32::try_from(locals.len()).expect("More than 2^32 arguments of a function");
@@ -231,6 +233,26 @@ pub fn add_fn<'tcx, 'asm, 'a: 'asm>(
             );
         }
         repack_cil
+    } else if sig.is_variadic() {
+        // .NET has no equivalent of `va_start` for native calls, so C-variadic functions can only be defined in C.
+        if !*crate::config::C_MODE {
+            let span = ctx.tcx().def_span(ctx.instance().def_id());
+            ctx.tcx().dcx().span_err(
+                span,
+                "defining C-variadic functions is not supported when targeting .NET, only when compiling to C",
+            );
+            return Ok(());
+        }
+        // The `VaList` of a C-variadic function is its last MIR argument, but it is not a part of its signature.
+        // Instead, it is kept in a local, initialized by `va_start`.
+        let va_list_local =
+            u32::try_from(locals.len()).expect("More than 2^32 arguments of a function");
+        let va_list_name = arg_names.pop().flatten();
+        let va_list_ty = ctx.monomorphize(mir.local_decls[Local::from_usize(mir.arg_count)].ty);
+        let va_list_type = get_type(va_list_ty, ctx);
+        locals.push((va_list_name, ctx.alloc_type(va_list_type)));
+        let va_list = ctx.alloc_node(cilly::v2::CILNode::LdLocA(va_list_local));
+        vec![V1Root::V2(ctx.alloc_root(CILRoot::VaStart(va_list))).into()]
     } else {
         vec![]
     };
@@ -393,8 +415,8 @@ pub fn add_item<'tcx>(
             drop(function_compile_timer);
             Ok(())
         }
-        MonoItem::GlobalAsm(asm) => {
-            eprintln!("Unsuported item - Global ASM:{asm:?}");
+        MonoItem::GlobalAsm(item_id) => {
+            crate::inline_asm::add_global_asm(asm, item_id, tcx);
             Ok(())
         }
         MonoItem::Static(stotic) => {
@@ -412,37 +434,16 @@ pub fn add_item<'tcx>(
             let int8_ptr = ctx.nptr(Type::Int(Int::I8));
             let int8_ptr_ptr = ctx.nptr(int8_ptr);
             if let Some(section) = attrs.link_section {
-                if section.to_string().contains(".init_array") {
+                let section = section.to_string();
+                if section.contains(".init_array") {
                     let argc = utilis::argc_argv_init_method(&mut ctx);
                     let init_argc = ctx.alloc_root(cilly::CILRoot::call(argc, []));
 
                     ctx.add_user_init(&[init_argc]);
                     let get_environ: Interned<MethodRef> = utilis::get_environ(&mut ctx);
-                    let fn_ptr = alloc.0.provenance().ptrs().iter().next().unwrap();
-                    let fn_ptr = tcx.global_alloc(fn_ptr.1.alloc_id());
-                    let init_call_site = if let GlobalAlloc::Function {
-                        instance: finstance,
-                    } = fn_ptr
-                    {
-                        let mut ctx = MethodCompileCtx::new(tcx, None, finstance, &mut ctx);
-                        // If it is a function, patch its pointer up.
-                        let call_info = CallInfo::sig_from_instance_(finstance, &mut ctx);
-                        let function_name = function_name(tcx.symbol_name(finstance));
-                        MethodRef::new(
-                            *ctx.main_module(),
-                            ctx.alloc_string(function_name),
-                            ctx.alloc_sig(call_info.sig().clone()),
-                            MethodKind::Static,
-                            vec![].into(),
-                        )
-                    } else {
-                        panic!()
-                    };
-
                     let argv = ctx.alloc_string("argv");
                     let argc = ctx.alloc_string("argc");
                     let main_module = ctx.main_module();
-                    let mref = ctx.alloc_methodref(init_call_site);
                     let argv =
                         ctx.alloc_sfld(StaticFieldDesc::new(*main_module, argv, int8_ptr_ptr));
                     let argc = ctx.alloc_sfld(StaticFieldDesc::new(
@@ -461,11 +462,13 @@ pub fn add_item<'tcx>(
                         )),
                         ctx.alloc_node(cilly::CILNode::call(get_environ, [])),
                     ];
-                    let root = ctx.alloc_root(cilly::CILRoot::call(mref, args));
-                    ctx.add_user_init(&[root]);
-                } else {
-                    panic!("Unsuported link section {section}.")
+                    let roots = ctor_section_calls(&alloc, &args, &mut ctx);
+                    ctx.add_user_init(&roots);
+                } else if section.contains(".fini_array") {
+                    let roots = ctor_section_calls(&alloc, &[], &mut ctx);
+                    ctx.add_user_fini(&roots);
                 }
+                // Statics in any other section are placed there by `add_static`.
             }
 
             add_static(stotic, &mut ctx);
@@ -477,6 +480,42 @@ pub fn add_item<'tcx>(
     }
 }
 
+/// Returns calls to all the functions in a `.init_array` or `.fini_array` static, in the order they appear in it.
+/// Each function gets as many of `args` as it takes.
+fn ctor_section_calls<'tcx>(
+    alloc: &rustc_middle::mir::interpret::ConstAllocation<'tcx>,
+    args: &[Interned<cilly::CILNode>],
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+) -> Vec<Interned<CILRoot>> {
+    let tcx = ctx.tcx();
+    alloc
+        .0
+        .provenance()
+        .ptrs()
+        .iter()
+        .map(|(_, fn_ptr)| {
+            let GlobalAlloc::Function {
+                instance: finstance,
+            } = tcx.global_alloc(fn_ptr.alloc_id())
+            else {
+                panic!("{fn_ptr:?} in a constructor section is not a function pointer.")
+            };
+            let mut ctx = MethodCompileCtx::new(tcx, None, finstance, ctx);
+            let call_info = CallInfo::sig_from_instance_(finstance, &mut ctx);
+            let arg_count = call_info.sig().inputs().len().min(args.len());
+            let function_name = function_name(tcx.symbol_name(finstance));
+            let mref = MethodRef::new(
+                *ctx.main_module(),
+                ctx.alloc_string(function_name),
+                ctx.alloc_sig(call_info.sig().clone()),
+                MethodKind::Static,
+                vec![].into(),
+            );
+            let mref = ctx.alloc_methodref(mref);
+            ctx.alloc_root(cilly::CILRoot::call(mref, args[..arg_count].to_vec()))
+        })
+        .collect()
+}
 pub(crate) fn span_source_info(tcx: TyCtxt, span: rustc_span::Span) -> V1Root {
     let (file, lstart, cstart, lend, mut cend) = tcx.sess.source_map().span_to_location_info(span);
     let file = file.map_or(String::new(), |file| {
@@ -74,7 +74,7 @@ pub fn eq_unchecked<'tcx>(
         TyKind::Float(FloatTy::F128) => {
             let mref = MethodRef::new(
                 *ctx.main_module(),
-                ctx.alloc_string("__eqtf2"),
+                ctx.alloc_string("eq_f128"),
                 ctx.sig(
                     [Type::Float(Float::F128), Type::Float(Float::F128)],
                     Type::Bool,
@@ -142,7 +142,7 @@ pub fn lt_unchecked(
         TyKind::Float(FloatTy::F128) => {
             let mref = MethodRef::new(
                 *asm.main_module(),
-                asm.alloc_string("__lttf2"),
+                asm.alloc_string("lt_f128"),
                 asm.sig(
                     [Type::Float(Float::F128), Type::Float(Float::F128)],
                     Type::Bool,
@@ -208,7 +208,7 @@ pub fn gt_unchecked(
         TyKind::Float(FloatTy::F128) => {
             let mref = MethodRef::new(
                 *asm.main_module(),
-                asm.alloc_string("__gttf2"),
+                asm.alloc_string("gt_f128"),
                 asm.sig(
                     [Type::Float(Float::F128), Type::Float(Float::F128)],
                     Type::Bool,
@@ -74,19 +74,11 @@ pub(crate) fn binop<'tcx>(
             TyKind::Float(FloatTy::F32 | FloatTy::F64) => {
                 eq!(lt_un!(ops_a, ops_b), V1Node::V2(ctx.alloc_node(false)))
             }
-            TyKind::Float(FloatTy::F128) => {
-                let mref = MethodRef::new(
-                    *ctx.main_module(),
-                    ctx.alloc_string("__getf2"),
-                    ctx.sig(
-                        [Type::Float(Float::F128), Type::Float(Float::F128)],
-                        Type::Bool,
-                    ),
-                    MethodKind::Static,
-                    vec![].into(),
-                );
-                call!(ctx.alloc_methodref(mref), [ops_a, ops_b])
-            }
+            // Ordered, so that comparisons with NaN are false
+            TyKind::Float(FloatTy::F128) => cilly::or!(
+                gt_unchecked(ty_a, ops_a.clone(), ops_b.clone(), ctx),
+                eq_unchecked(ty_a, ops_a, ops_b, ctx)
+            ),
             _ => eq!(
                 lt_unchecked(ty_a, ops_a, ops_b, ctx),
                 V1Node::V2(ctx.alloc_node(false))
@@ -97,19 +89,11 @@ pub(crate) fn binop<'tcx>(
             TyKind::Float(FloatTy::F32 | FloatTy::F64) => {
                 eq!(gt_un!(ops_a, ops_b), V1Node::V2(ctx.alloc_node(false)))
             }
-            TyKind::Float(FloatTy::F128) => {
-                let mref = MethodRef::new(
-                    *ctx.main_module(),
-                    ctx.alloc_string("__letf2"),
-                    ctx.sig(
-                        [Type::Float(Float::F128), Type::Float(Float::F128)],
-                        Type::Bool,
-                    ),
-                    MethodKind::Static,
-                    vec![].into(),
-                );
-                call!(ctx.alloc_methodref(mref), [ops_a, ops_b])
-            }
+            // Ordered, so that comparisons with NaN are false
+            TyKind::Float(FloatTy::F128) => cilly::or!(
+                lt_unchecked(ty_a, ops_a.clone(), ops_b.clone(), ctx),
+                eq_unchecked(ty_a, ops_a, ops_b, ctx)
+            ),
             _ => eq!(
                 gt_unchecked(ty_a, ops_a, ops_b, ctx),
                 V1Node::V2(ctx.alloc_node(false))
@@ -211,7 +195,7 @@ pub fn add_unchecked<'tcx>(
         TyKind::Float(FloatTy::F128) => {
             let mref = MethodRef::new(
                 *ctx.main_module(),
-                ctx.alloc_string("__addtf3"),
+                ctx.alloc_string("add_f128"),
                 ctx.sig(
                     [Type::Float(Float::F128), Type::Float(Float::F128)],
                     Type::Float(Float::F128),
@@ -286,7 +270,7 @@ pub fn sub_unchecked<'tcx>(
         TyKind::Float(FloatTy::F128) => {
             let mref = MethodRef::new(
                 *ctx.main_module(),
-                ctx.alloc_string("__subtf3"),
+                ctx.alloc_string("sub_f128"),
                 ctx.sig(
                     [Type::Float(Float::F128), Type::Float(Float::F128)],
                     Type::Float(Float::F128),
@@ -353,7 +337,7 @@ fn rem_unchecked<'tcx>(
         TyKind::Float(FloatTy::F128) => {
             let mref = MethodRef::new(
                 *ctx.main_module(),
-                ctx.alloc_string("fmodl"),
+                ctx.alloc_string("mod_f128"),
                 ctx.sig(
                     [Type::Float(Float::F128), Type::Float(Float::F128)],
                     Type::Float(Float::F128),
@@ -419,7 +403,7 @@ fn mul_unchecked<'tcx>(
         TyKind::Float(FloatTy::F128) => {
             let mref = MethodRef::new(
                 *ctx.main_module(),
-                ctx.alloc_string("__multf3"),
+                ctx.alloc_string("mul_f128"),
                 ctx.sig(
                     [Type::Float(Float::F128), Type::Float(Float::F128)],
                     Type::Float(Float::F128),
@@ -486,7 +470,7 @@ fn div_unchecked<'tcx>(
         TyKind::Float(FloatTy::F128) => {
             let mref = MethodRef::new(
                 *ctx.main_module(),
-                ctx.alloc_string("__divtf3"),
+                ctx.alloc_string("div_f128"),
                 ctx.sig(
                     [Type::Float(Float::F128), Type::Float(Float::F128)],
                     Type::Float(Float::F128),
@@ -152,12 +152,14 @@ pub fn int_to_int(src: Type, target: Type, operand: V1Node, asm: &mut Assembly)
             );
             call!(asm.alloc_methodref(mref), [operand])
         }
-        //todo!("Casting to 128 bit intiegers is not supported!"),
         _ => to_int(target, operand),
     }
 }
 /// Returns CIL ops required to convert type src to target
 pub fn float_to_int(src: Type, target: Type, operand: V1Node, asm: &mut Assembly) -> V1Node {
+    if src == Type::Float(Float::F128) {
+        return f128_cast(src, target, operand, asm);
+    }
     match target {
         Type::Int(Int::I128) => {
             let mref = MethodRef::new(
@@ -437,7 +439,9 @@ fn to_int(target: Type, operand: V1Node) -> V1Node {
 }
 /// Returns CIL ops required to casts from intiger type `src` to `target` MOVE TO CILLY
 pub fn int_to_float(src: Type, target: Type, parrent: V1Node, asm: &mut Assembly) -> V1Node {
-    if matches!(src, Type::Int(Int::I128)) {
+    if target == Type::Float(Float::F128) {
+        f128_cast(src, target, parrent, asm)
+    } else if matches!(src, Type::Int(Int::I128)) {
         let mref = MethodRef::new(
             ClassRef::int_128(asm),
             asm.alloc_string("op_Explicit"),
@@ -446,7 +450,6 @@ pub fn int_to_float(src: Type, target: Type, parrent: V1Node, asm: &mut Assembly
             vec![].into(),
         );
         call!(asm.alloc_methodref(mref), [parrent])
-        //todo!("Casting from 128 bit intiegers is not supported!")
     } else if matches!(src, Type::Int(Int::U128)) {
         let mref = MethodRef::new(
             ClassRef::uint_128(asm),
@@ -456,8 +459,6 @@ pub fn int_to_float(src: Type, target: Type, parrent: V1Node, asm: &mut Assembly
             vec![].into(),
         );
         call!(asm.alloc_methodref(mref), [parrent])
-    } else if matches!(target, Type::Int(Int::I128 | Int::U128)) {
-        todo!("Casting to 128 bit intiegers is not supported!")
     } else {
         match (&src, &target) {
             (Type::Int(Int::U32 | Int::U64), Type::Float(Float::F32)) => {
@@ -478,3 +479,19 @@ pub fn int_to_float(src: Type, target: Type, parrent: V1Node, asm: &mut Assembly
         }
     }
 }
+/// Casts to or from a `f128`, using the `cast_{src}_{target}` builtins.
+pub fn f128_cast(src: Type, target: Type, operand: V1Node, asm: &mut Assembly) -> V1Node {
+    let name = |tpe: Type| match tpe {
+        Type::Int(int) => int.name(),
+        Type::Float(float) => float.name(),
+        _ => panic!("Can't cast {tpe:?} to or from a f128"),
+    };
+    let mref = MethodRef::new(
+        *asm.main_module(),
+        asm.alloc_string(format!("cast_{}_{}", name(src), name(target))),
+        asm.sig([src], target),
+        MethodKind::Static,
+        vec![].into(),
+    );
+    call!(asm.alloc_methodref(mref), [operand])
+}
@@ -0,0 +1,150 @@
+use cilly::Assembly;
+use rustc_codegen_ssa::{
+    assert_module_sources::{assert_module_sources, CguReuse},
+    base::determine_cgu_reuse,
+    CompiledModule, ModuleKind,
+};
+use rustc_data_structures::sync::{par_map, IntoDynSyncSend};
+use rustc_middle::{
+    dep_graph::{DepContext, WorkProduct, WorkProductId},
+    mir::mono::CodegenUnit,
+    ty::TyCtxt,
+};
+use rustc_session::{
+    config::{OutputFilenames, OutputType},
+    Session,
+};
+use rustc_span::Symbol;
+
+use crate::assembly;
+/// The extension of the serialized assembly, saved in the incremental cache.
+const WORK_PRODUCT_EXT: &str = "bc";
+/// A codegen unit, either compiled in this session, or reused from the incremental cache.
+pub enum CguModule {
+    Compiled {
+        name: String,
+        asm: Assembly,
+    },
+    Reused {
+        name: String,
+        id: WorkProductId,
+        product: WorkProduct,
+    },
+}
+/// Compiles all codegen units, which can't be reused from the incremental cache. If rustc runs with more than one
+/// thread(`-Zthreads`), those are compiled in parallel, on rustc's own thread pool.
+pub fn codegen_units<'tcx>(tcx: TyCtxt<'tcx>, cgus: &'tcx [CodegenUnit<'tcx>]) -> Vec<CguModule> {
+    // Trying to mark a CGU as green must happen before it is compiled, so this can't be done in parallel.
+    let reuse: Vec<_> = tcx.sess.time("find_cgu_reuse", || {
+        cgus.iter()
+            .map(|cgu| determine_cgu_reuse(tcx, cgu))
+            .collect()
+    });
+    // Checks the `#![rustc_partition_reused]` and `#![rustc_partition_codegened]` annotations of incremental tests.
+    assert_module_sources(tcx, &|tracker| {
+        for (cgu, reuse) in cgus.iter().zip(&reuse) {
+            tracker.set_actual_reuse(cgu.name().as_str(), *reuse);
+        }
+    });
+    let modules: Vec<IntoDynSyncSend<CguModule>> =
+        par_map(cgus.iter().zip(reuse), |(cgu, reuse)| {
+            let name = cgu.name().to_string();
+            IntoDynSyncSend(match reuse {
+                CguReuse::No => {
+                    // Records everything the CGU depends on, so that rustc can tell if it changed in the next session.
+                    let dep_node = cgu.codegen_dep_node(tcx);
+                    let (asm, _) = tcx.dep_graph.with_task(
+                        dep_node,
+                        tcx,
+                        cgu.name(),
+                        compile_codegen_unit,
+                        None,
+                    );
+                    CguModule::Compiled { name, asm }
+                }
+                CguReuse::PreLto | CguReuse::PostLto => {
+                    let id = cgu.work_product_id();
+                    let product = tcx
+                        .dep_graph
+                        .previous_work_product(&id)
+                        .expect("CGU marked as reusable, but it has no work product");
+                    CguModule::Reused { name, id, product }
+                }
+            })
+        });
+    modules.into_iter().map(|module| module.0).collect()
+}
+/// Compiles all the items of a codegen unit into its own assembly.
+fn compile_codegen_unit(tcx: TyCtxt<'_>, cgu_name: Symbol) -> Assembly {
+    let _cgu_compile_timer = tcx
+        .profiler()
+        .generic_activity_with_arg("codegen module", cgu_name.to_string());
+    let cgu = tcx.codegen_unit(cgu_name);
+    let mut asm = Assembly::default();
+    for (item, _data) in cgu.items() {
+        assembly::add_item(&mut asm, *item, tcx).expect("Could not add function");
+    }
+    asm
+}
+/// Optimizes and saves an assembly as the module `name`.
+pub fn save_module(name: &str, asm: &Assembly, outputs: &OutputFilenames) -> CompiledModule {
+    let serialized_asm_path = outputs.temp_path_for_cgu(OutputType::Bitcode, name, None);
+    let mut asm_out = std::fs::File::create(&serialized_asm_path)
+        .expect("Could not create the temporary files necessary for building the assembly!");
+    let mut v2 = cilly::Assembly::from_v1(asm);
+    v2.opt(&mut v2.fuel_from_env());
+    v2.typecheck();
+    v2.save_tmp(&mut asm_out)
+        .expect("Could not save the tmp assembly file!");
+    CompiledModule {
+        name: name.into(),
+        kind: ModuleKind::Regular,
+        object: Some(serialized_asm_path),
+        bytecode: None,
+        dwarf_object: None,
+        llvm_ir: None,
+        assembly: None,
+        links_from_incr_cache: Vec::new(),
+    }
+}
+/// Saves a codegen unit, and returns its module, and the work product it can be reused from in the next session.
+pub fn save_codegen_unit(
+    module: CguModule,
+    sess: &Session,
+    outputs: &OutputFilenames,
+) -> (CompiledModule, Option<(WorkProductId, WorkProduct)>) {
+    match module {
+        CguModule::Compiled { name, asm } => {
+            let module = save_module(&name, &asm, outputs);
+            let work_product = rustc_incremental::copy_cgu_workproduct_to_incr_comp_cache_dir(
+                sess,
+                &name,
+                &[(WORK_PRODUCT_EXT, module.object.as_ref().unwrap())],
+                &[],
+            );
+            (module, work_product)
+        }
+        CguModule::Reused { name, id, product } => {
+            let saved_file = product
+                .saved_files
+                .get(WORK_PRODUCT_EXT)
+                .expect("No saved assembly in work product");
+            let cached = rustc_incremental::in_incr_comp_dir_sess(sess, saved_file);
+            let serialized_asm_path = outputs.temp_path_for_cgu(OutputType::Bitcode, &name, None);
+            rustc_fs_util::link_or_copy(&cached, &serialized_asm_path).unwrap_or_else(|err| {
+                panic!("Could not copy the cached assembly {cached:?} to {serialized_asm_path:?}: {err}")
+            });
+            let module = CompiledModule {
+                name,
+                kind: ModuleKind::Regular,
+                object: Some(serialized_asm_path),
+                bytecode: None,
+                dwarf_object: None,
+                llvm_ir: None,
+                assembly: None,
+                links_from_incr_cache: vec![cached],
+            };
+            (module, Some((id, product)))
+        }
+    }
+}
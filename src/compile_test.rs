@@ -407,6 +407,10 @@ fn compiler(test_name: &str, test_dir: &str, release: bool) -> std::process::Com
 }
 macro_rules! run_test {
     ($prefix:ident,$test_name:ident,$is_stable:ident) => {
+        run_test! {$prefix,$test_name,$is_stable,[]}
+    };
+    // Passes additional flags to rustc, eg. to compile the test with more than one codegen unit.
+    ($prefix:ident,$test_name:ident,$is_stable:ident,[$($flag:literal),*]) => {
         mod $test_name {
             mod $is_stable {
                 #[cfg(test)]
@@ -425,6 +429,8 @@ macro_rules! run_test {
                         .as_ref()
                         .expect("Could not build rustc!");
                     let mut cmd = super::super::compiler(stringify!($test_name), test_dir, true);
+                    let flags: &[&str] = &[$($flag),*];
+                    cmd.args(flags);
 
                     eprintln!("Command: {cmd:?}");
                     let out = cmd.output().expect("failed to execute process");
@@ -462,6 +468,8 @@ macro_rules! run_test {
                         .expect("Could not build rustc!");
                     let test_name = concat!("debug_", stringify!($test_name));
                     let mut cmd = super::super::compiler(stringify!($test_name), test_dir, false);
+                    let flags: &[&str] = &[$($flag),*];
+                    cmd.args(flags);
                     // /eprintln!("out:{out:?}");
                     eprintln!("test_name:{test_name:?}");
                     let out = cmd.output().expect("failed to execute process");
@@ -489,6 +497,65 @@ macro_rules! run_test {
         }
     };
 }
+macro_rules! incremental_test {
+    ($prefix:ident,$test_name:ident,$is_stable:ident) => {
+        mod $test_name {
+            mod $is_stable {
+                #[cfg(test)]
+                static COMPILE_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+                #[test]
+                fn incremental() {
+                    let lock = COMPILE_LOCK.lock();
+                    #[cfg(target_os = "windows")]
+                    let test_dir = concat!(".\\test\\", stringify!($prefix), "\\");
+                    #[cfg(not(target_os = "windows"))]
+                    let test_dir = concat!("./test/", stringify!($prefix), "/");
+                    // Ensures the test directory is present
+                    std::fs::create_dir_all(test_dir).expect("Could not setup the test env");
+                    // Builds the backend if neceasry
+                    super::super::RUSTC_BUILD_STATUS
+                        .as_ref()
+                        .expect("Could not build rustc!");
+                    let incr_dir = concat!(stringify!($test_name), ".incr");
+                    // Starts from an empty cache, so that nothing can be reused in the first session.
+                    let _ = std::fs::remove_dir_all(std::path::Path::new(test_dir).join(incr_dir));
+                    // The second session changes only some of the codegen units. The test checks which of them got
+                    // reused(with `#![rustc_partition_reused]`), and that the program still works.
+                    for session in ["first", "second"] {
+                        let mut cmd =
+                            super::super::compiler(stringify!($test_name), test_dir, false);
+                        cmd.args([
+                            "-Zquery-dep-graph",
+                            "--check-cfg=cfg(first,second)",
+                            "--cfg",
+                            session,
+                        ])
+                        .arg(format!("-Cincremental={incr_dir}"));
+                        eprintln!("Command: {cmd:?}");
+                        let out = cmd.output().expect("failed to execute process");
+                        // If stderr is not empty, then something went wrong, so print the stdout and stderr for debuging.
+                        if String::from_utf8(out.stderr.clone())
+                            .unwrap()
+                            .contains("error:")
+                        {
+                            let stdout = String::from_utf8(out.stdout)
+                                .expect("rustc error contained non-UTF8 characters.");
+                            let stderr = String::from_utf8(out.stderr)
+                                .expect("rustc error contained non-UTF8 characters.");
+                            panic!("session:{session}\nstdout:\n{stdout}\nstderr:\n{stderr}");
+                        }
+                        #[cfg(not(target_os = "windows"))]
+                        let exec_path = concat!("./debug_", stringify!($test_name));
+                        #[cfg(target_os = "windows")]
+                        let exec_path = concat!(".\\debug_", stringify!($test_name));
+                        let _ = super::super::test_dotnet_executable(exec_path, test_dir);
+                    }
+                    drop(lock);
+                }
+            }
+        }
+    };
+}
 macro_rules! cargo_test {
     ($test_name:ident,$is_stable:ident) => {
         mod $test_name { mod $is_stable{
@@ -775,6 +842,7 @@ run_test! {cast,i32_to_u64,stable}
 run_test! {cast,i32_to_usize,stable}
 run_test! {cast,coerce_unsized,unstable}
 run_test! {control_flow,cf_for,stable}
+run_test! {control_flow,coroutine,unstable}
 run_test! {control_flow,drop,stable}
 run_test! {fuzz,test0,stable}
 run_test! {fuzz,test1,stable}
@@ -819,8 +887,12 @@ run_test! {std,sort,stable}
 run_test! {std,mutithreading,stable}
 run_test! {std,once_lock_test,stable}
 run_test! {std,tlocal_key_test,stable}
+run_test! {std,tls_dtor,stable}
 run_test! {std,uninit_fill,stable}
 
+run_test! {cgu,multi_cgu,stable,["-Ccodegen-units=4"]}
+incremental_test! {cgu,incremental,stable}
+
 run_test! {core,ascii_align,unstable}
 run_test! {core,floatfmt,unstable}
 run_test! {core,flt2dec,unstable}
@@ -837,9 +909,12 @@ run_test! {types,arr,stable}
 run_test! {types,async_types,stable}
 run_test! {types,dst,stable}
 run_test! {types,dyns,stable}
+run_test! {types,dyn_star,unstable}
 run_test! {types,enums,stable}
 run_test! {types,int128,stable}
 run_test! {types,interop,stable}
+run_test! {types,interop_delegate,stable}
+run_test! {types,interop_generics,stable}
 run_test! {types,interop_typedef,unstable}
 run_test! {types,maybeuninit,stable}
 run_test! {types,nbody,stable}
@@ -1000,6 +1075,7 @@ cargo_test! {benchmarks,bench}
 // TODO: This trips up some post-link sanity checks, investigate.
 cargo_test! {glam_test,unstable}
 cargo_test! {fastrand_test,stable}
+cargo_test! {tls_cross_crate,stable}
 
 #[cfg(target_os = "windows")]
 const IS_DOTNET_PRESENT: &bool = &true;
@@ -1,32 +1,283 @@
-#![allow(dead_code)]
-use cilly::ClassDef;
-use rustc_codegen_clr_ctx::MethodCompileCtx;
+//! Interpreter for the `rustc_codegen_clr_comptime_entrypoint` functions emitted by `dotnet_typedef!`.
+//!
+//! Those functions are never compiled. Instead, their MIR is interpreted at compile time: each call to one of the
+//! `rustc_codegen_clr_*` builder functions adds something to a .NET class, which is then added to the assembly.
+use crate::{interop::AssemblyRef, utilis::garag_to_bool};
+use cilly::{
+    cilnode::{IsPure, MethodKind},
+    Access, BasicBlock, CILNode, CILRoot, ClassDef, ClassRef, FnSig, IString, Interned, MethodDef,
+    MethodImpl, MethodRef, Type,
+};
+use rustc_codegen_clr_call::CallInfo;
+use rustc_codegen_clr_ctx::{function_name, MethodCompileCtx};
+use rustc_codegen_clr_type::{utilis::garg_to_string, GetTypeExt};
+use rustc_middle::{
+    mir::{
+        Body, Operand, Rvalue, StatementKind, TerminatorKind, VarDebugInfoContents, START_BLOCK,
+    },
+    ty::{GenericArg, Instance, TyKind, TypingEnv},
+};
+use rustc_span::{source_map::Spanned, Span};
+/// Creates a new class. Generic args: name, is valuetype, parent assembly, parent name.
+const NEW_TYPEDEF: &str = "rustc_codegen_clr_new_typedef";
+/// Adds a field to a class. Generic args: field type, field name.
+const ADD_FIELD_DEF: &str = "rustc_codegen_clr_add_field_def";
+/// Adds a method to a class. Generic args: visibility, modifiers, method name, implementation.
+const ADD_METHOD_DEF: &str = "rustc_codegen_clr_add_method_def";
+/// Adds a constructor to a class. Generic args: visibility, implementation.
+const ADD_CTOR_DEF: &str = "rustc_codegen_clr_add_ctor_def";
+/// Adds a finished class to the assembly.
+const FINISH_TYPE: &str = "rustc_codegen_clr_finish_type";
+/// Reported for any construct the interpreter does not understand.
+const UNSUPPORTED: &str = "unsupported operation in an interop type definition";
 #[derive(Clone)]
 enum ComptimeLocalVar {
     NotSet,
     Void,
-    ClassDef(ClassDef),
+    TypeDef(Box<TypeDefBuilder>),
 }
 
 impl ComptimeLocalVar {
-    fn as_type_def(&self) -> Option<&ClassDef> {
-        if let Self::ClassDef(v) = self {
+    fn as_type_def(&self) -> Option<&TypeDefBuilder> {
+        if let Self::TypeDef(v) = self {
             Some(v)
         } else {
             None
         }
     }
 }
-#[allow(unused_variables)]
-pub fn interpret<'tcx>(
+/// A method of a [`TypeDefBuilder`]. Its body just forwards all of its arguments to `target`, a Rust function.
+#[derive(Clone)]
+struct MethodDecl {
+    access: Access,
+    kind: MethodKind,
+    name: Interned<IString>,
+    sig: Interned<FnSig>,
+    target: Interned<MethodRef>,
+    arg_names: Vec<Option<Interned<IString>>>,
+}
+/// A class under construction. Fields are known upfront, but methods can only be added once the class exists.
+#[derive(Clone)]
+struct TypeDefBuilder {
+    name: Interned<IString>,
+    is_valuetype: bool,
+    extends: Option<Interned<ClassRef>>,
+    fields: Vec<(Type, Interned<IString>, Option<u32>)>,
+    methods: Vec<MethodDecl>,
+}
+impl TypeDefBuilder {
+    fn this(&self, ctx: &mut MethodCompileCtx<'_, '_>) -> Interned<ClassRef> {
+        ctx.alloc_class_ref(ClassRef::new(self.name, None, self.is_valuetype, [].into()))
+    }
+    /// Adds this class, and all of its methods to the assembly.
+    fn finish(self, ctx: &mut MethodCompileCtx<'_, '_>) {
+        let class = ctx
+            .class_def(ClassDef::new(
+                self.name,
+                self.is_valuetype,
+                0,
+                self.extends,
+                self.fields,
+                vec![],
+                Access::Extern,
+                None,
+                None,
+                true,
+            ))
+            .expect("ERROR: invalid layout of an interop type definition.");
+        let has_ctor = self
+            .methods
+            .iter()
+            .any(|method| method.kind == MethodKind::Constructor);
+        let this = Type::ClassRef(*class);
+        // Like in C#, classes without a constructor get a parameterless one.
+        if !has_ctor && !self.is_valuetype {
+            let base_ctor = base_ctor(self.extends, ctx);
+            let ldarg_0 = ctx.alloc_node(CILNode::LdArg(0));
+            let base_ctor = ctx.alloc_root(CILRoot::Call(Box::new((
+                base_ctor,
+                [ldarg_0].into(),
+                IsPure::NOT,
+            ))));
+            let void_ret = ctx.alloc_root(CILRoot::VoidRet);
+            let name = ctx.alloc_string(".ctor");
+            let sig = ctx.sig([this], Type::Void);
+            ctx.new_method(MethodDef::new(
+                Access::Extern,
+                class,
+                name,
+                sig,
+                MethodKind::Constructor,
+                MethodImpl::MethodBody {
+                    blocks: vec![BasicBlock::new(vec![base_ctor, void_ret], 0, None)],
+                    locals: vec![],
+                },
+                vec![None],
+            ));
+        }
+        for method in self.methods {
+            let argc = ctx[method.sig].inputs().len();
+            let args: Box<[_]> = (0..argc)
+                .map(|arg| ctx.alloc_node(CILNode::LdArg(arg.try_into().unwrap())))
+                .collect();
+            let mut roots = vec![];
+            // Constructors of reference types must call the constructor of their parent first.
+            if method.kind == MethodKind::Constructor {
+                let base_ctor = base_ctor(self.extends, ctx);
+                roots.push(ctx.alloc_root(CILRoot::Call(Box::new((
+                    base_ctor,
+                    [args[0]].into(),
+                    IsPure::NOT,
+                )))));
+            }
+            if *ctx[method.sig].output() == Type::Void {
+                roots.push(ctx.alloc_root(CILRoot::Call(Box::new((
+                    method.target,
+                    args,
+                    IsPure::NOT,
+                )))));
+                roots.push(ctx.alloc_root(CILRoot::VoidRet));
+            } else {
+                let res =
+                    ctx.alloc_node(CILNode::Call(Box::new((method.target, args, IsPure::NOT))));
+                roots.push(ctx.alloc_root(CILRoot::Ret(res)));
+            }
+            ctx.new_method(MethodDef::new(
+                method.access,
+                class,
+                method.name,
+                method.sig,
+                method.kind,
+                MethodImpl::MethodBody {
+                    blocks: vec![BasicBlock::new(roots, 0, None)],
+                    locals: vec![],
+                },
+                method.arg_names,
+            ));
+        }
+    }
+}
+/// Returns the parameterless constructor of `parent`, called as an instance method.
+fn base_ctor(
+    parent: Option<Interned<ClassRef>>,
+    ctx: &mut MethodCompileCtx<'_, '_>,
+) -> Interned<MethodRef> {
+    let parent = parent.unwrap_or_else(|| ClassRef::object(ctx));
+    let name = ctx.alloc_string(".ctor");
+    let sig = ctx.sig([Type::ClassRef(parent)], Type::Void);
+    ctx.alloc_methodref(MethodRef::new(
+        parent,
+        name,
+        sig,
+        MethodKind::Instance,
+        [].into(),
+    ))
+}
+/// Turns a path to a .NET type(`System::Object`) into its .NET name(`System.Object`).
+fn dotnet_path<'tcx>(garg: GenericArg<'tcx>, ctx: &MethodCompileCtx<'tcx, '_>) -> String {
+    garg_to_string(garg, ctx.tcx())
+        .replace(' ', "")
+        .replace("::", ".")
+}
+/// Returns the class definition a builder function operates on.
+fn class_arg<'tcx>(
+    args: &[Spanned<Operand<'tcx>>],
+    locals: &[ComptimeLocalVar],
+) -> Result<TypeDefBuilder, String> {
+    let local = args[0]
+        .node
+        .place()
+        .and_then(|place| place.as_local())
+        .ok_or_else(|| UNSUPPORTED.to_owned())?;
+    locals[usize::from(local)]
+        .as_type_def()
+        .cloned()
+        .ok_or_else(|| "interop type definition used before it was created".to_owned())
+}
+/// Returns the Rust function implementing a method, together with its signature and argument names.
+fn method_target<'tcx>(
+    garg: GenericArg<'tcx>,
     ctx: &mut MethodCompileCtx<'tcx, '_>,
-    body: &'tcx rustc_middle::mir::Body<'tcx>,
-) {
-    todo!("Can't yet start the comptime assembly editor.");
-    /*
-    let block_id = rustc_middle::mir::BasicBlock::from_usize(0);
-    let locals = vec![ComptimeLocalVar::NotSet; body.local_decls.len()];
-
+) -> Result<
+    (
+        Interned<MethodRef>,
+        Interned<FnSig>,
+        Vec<Option<Interned<IString>>>,
+    ),
+    String,
+> {
+    let fn_type = ctx.monomorphize(garg.as_type().unwrap());
+    let TyKind::FnDef(def_id, subst_ref) = fn_type.kind() else {
+        return Err(format!(
+            "the implementation of an interop method must be a function, not {fn_type:?}"
+        ));
+    };
+    let instance = Instance::try_resolve(
+        ctx.tcx(),
+        TypingEnv::fully_monomorphized(),
+        *def_id,
+        subst_ref,
+    )
+    .expect("Invalid function def")
+    .expect("ERROR: Could not get function instance.");
+    let call_info = CallInfo::sig_from_instance_(instance, ctx);
+    let sig = ctx.alloc_sig(call_info.sig().clone());
+    let name = function_name(ctx.tcx().symbol_name(instance));
+    let name = ctx.alloc_string(name);
+    let main_module = *ctx.main_module();
+    let target = ctx.alloc_methodref(MethodRef::new(
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        [].into(),
+    ));
+    // Keep the names of the arguments, so that they are visible to other .NET languages.
+    let argc = ctx[sig].inputs().len();
+    let mut arg_names = vec![None; argc];
+    let mir = ctx.tcx().instance_mir(instance.def);
+    for var in &mir.var_debug_info {
+        let VarDebugInfoContents::Place(place) = var.value else {
+            continue;
+        };
+        let local = place.local.as_usize();
+        if place.projection.is_empty() && local > 0 && local <= argc {
+            arg_names[local - 1] = Some(ctx.alloc_string(var.name.to_string()));
+        }
+    }
+    Ok((target, sig, arg_names))
+}
+/// Checks that the first argument of the implementation of an instance method is `this`.
+fn check_this(
+    sig: Interned<FnSig>,
+    class: &TypeDefBuilder,
+    name: &str,
+    ctx: &mut MethodCompileCtx<'_, '_>,
+) -> Result<(), String> {
+    if class.is_valuetype {
+        return Err("interop value types can't have instance methods or constructors yet".into());
+    }
+    let this = Type::ClassRef(class.this(ctx));
+    if ctx[sig].inputs().first() != Some(&this) {
+        return Err(format!(
+            "the first argument of the interop method {name:?} must be `this`"
+        ));
+    }
+    Ok(())
+}
+/// Interprets `body`. Invalid interop type definitions are reported as errors, at the statement or terminator they
+/// occur in.
+pub fn interpret<'tcx>(ctx: &mut MethodCompileCtx<'tcx, '_>, body: &'tcx Body<'tcx>) {
+    if let Err((span, msg)) = interpret_body(ctx, body) {
+        ctx.tcx().dcx().span_err(span, msg);
+    }
+}
+fn interpret_body<'tcx>(
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+    body: &'tcx Body<'tcx>,
+) -> Result<(), (Span, String)> {
+    let mut block_id = START_BLOCK;
+    let mut locals = vec![ComptimeLocalVar::NotSet; body.local_decls.len()];
     loop {
         let block_data = &body.basic_blocks[block_id];
         // Skip cleanup in the interpreter
@@ -35,198 +286,196 @@ pub fn interpret<'tcx>(
             "Can't interpret a cleanup block in rustc_codegen_clr comptime"
         );
         for statement in &block_data.statements {
+            let span = statement.source_info.span;
             match &statement.kind {
                 StatementKind::Assign(bx) => {
                     let (target, rvalue) = bx.as_ref();
-                    let src = match rvalue {
-                        Rvalue::Use(src) => src,
-                        Rvalue::Cast(
-                            rustc_middle::mir::CastKind::PointerCoercion(
-                                rustc_middle::ty::adjustment::PointerCoercion::ReifyFnPointer,
-                            ),
-                            _,
-                            _,
-                        ) => continue,
-                        _ => panic!(),
+                    let target = target
+                        .as_local()
+                        .ok_or_else(|| (span, UNSUPPORTED.to_owned()))?;
+                    locals[usize::from(target)] = match rvalue {
+                        Rvalue::Use(Operand::Copy(src) | Operand::Move(src)) => {
+                            let src = src
+                                .as_local()
+                                .ok_or_else(|| (span, UNSUPPORTED.to_owned()))?;
+                            locals[usize::from(src)].clone()
+                        }
+                        // Function pointers and other constants are not needed by the interpreter.
+                        Rvalue::Use(Operand::Constant(_)) | Rvalue::Cast(..) => {
+                            ComptimeLocalVar::NotSet
+                        }
+                        _ => {
+                            return Err((
+                                span,
+                                format!("can't interpret the rvalue {rvalue:?} in an interop type definition"),
+                            ))
+                        }
                     };
-
-                    let src = src.place().unwrap().as_local().unwrap();
-                    let target = target.as_local().unwrap();
-                    locals[usize::from(target)] = locals[usize::from(src)].clone();
                 }
-                StatementKind::StorageLive(_) | StatementKind::StorageDead(_) => (),
-                _ => todo!(
-                    "can't interpret the statement {statement:?} yet in rustc_codegen_clr comptime"
-                ),
+                StatementKind::StorageLive(_)
+                | StatementKind::StorageDead(_)
+                | StatementKind::Nop => (),
+                _ => return Err((
+                    span,
+                    format!(
+                        "can't interpret the statement {statement:?} in an interop type definition"
+                    ),
+                )),
             }
         }
-        match &block_data.terminator {
-            Some(term) => match &term.kind {
-                TerminatorKind::Call {
-                    func,
-                    args,
-                    destination,
-                    target,
-                    unwind: _,
-                    call_source: _,
-                    fn_span: _,
-                } => {
-                    let func_ty = func.ty(body, ctx.tcx());
-                    let func_ty = ctx.monomorphize(func_ty);
-                    let (call_instance, subst_ref) = if let TyKind::FnDef(def_id, subst_ref) =
-                        func_ty.kind()
-                    {
-                        let subst_ref = ctx.monomorphize(*subst_ref);
-                        let env = rustc_middle::ty::TypingEnv::fully_monomorphized();
-                        let Some(call_instance) =
-                            Instance::try_resolve(ctx.tcx(), env, *def_id, subst_ref)
-                                .expect("Invalid function def")
-                        else {
-                            panic!("ERROR: Could not get function instance. fn type:{func_ty:?}")
-                        };
-
-                        (call_instance, subst_ref)
+        let term = block_data.terminator();
+        let span = term.source_info.span;
+        match &term.kind {
+            TerminatorKind::Call {
+                func,
+                args,
+                destination,
+                target,
+                ..
+            } => {
+                let func_ty = ctx.monomorphize(func.ty(body, ctx.tcx()));
+                let TyKind::FnDef(def_id, subst_ref) = func_ty.kind() else {
+                    return Err((
+                        span,
+                        format!(
+                            "interop type definitions can only call functions, not {func_ty:?}"
+                        ),
+                    ));
+                };
+                let subst_ref = ctx.monomorphize(*subst_ref);
+                let call_instance = Instance::try_resolve(
+                    ctx.tcx(),
+                    TypingEnv::fully_monomorphized(),
+                    *def_id,
+                    subst_ref,
+                )
+                .expect("Invalid function def")
+                .unwrap_or_else(|| {
+                    panic!("ERROR: Could not get function instance. fn type:{func_ty:?}")
+                });
+                let function_name = function_name(ctx.tcx().symbol_name(call_instance));
+                let local = destination
+                    .as_local()
+                    .ok_or_else(|| (span, UNSUPPORTED.to_owned()))?;
+                let at_term = |msg: String| (span, msg);
+                locals[usize::from(local)] = if function_name.contains(NEW_TYPEDEF) {
+                    let name = dotnet_path(subst_ref[0], ctx);
+                    let is_valuetype = garag_to_bool(subst_ref[1], ctx.tcx());
+                    let parent_asm = AssemblyRef::decode_assembly_ref(subst_ref[2], ctx.tcx())
+                        .name()
+                        .map(|name| name.replace(' ', "").replace("::", "."));
+                    let parent_name = dotnet_path(subst_ref[3], ctx);
+                    let extends = if parent_name.is_empty() {
+                        None
                     } else {
-                        todo!("Trying to call a type which is not a function definition!");
+                        let parent_asm = parent_asm.map(|name| ctx.alloc_string(name));
+                        let parent_name = ctx.alloc_string(parent_name);
+                        // Value types can't be derived from, so the parent is always a reference type.
+                        Some(ctx.alloc_class_ref(ClassRef::new(
+                            parent_name,
+                            parent_asm,
+                            false,
+                            [].into(),
+                        )))
                     };
-                    let function_name =
-                        function_name(ctx.tcx().symbol_name(call_instance));
-                    let local = destination
-                        .as_local()
-                        .expect("ERROR: unuported operation in interop type definiton.");
-                    locals[usize::from(local)] = if function_name
-                        .contains("rustc_codegen_clr_new_typedef")
-                    {
-                        let name = garg_to_string(subst_ref[0], ctx.tcx())
-                            .replace("::", ".");
-                        let is_value_type = garag_to_bool(subst_ref[1], ctx.tcx());
-                        let superclass_asm = garg_to_string(subst_ref[2], ctx.tcx())
-                            .replace("::", ".");
-                        let superclass_name =
-                            garg_to_string(subst_ref[3], ctx.tcx())
-                                .replace("::", ".");
-                        let inherits = if superclass_name.is_empty() {
-                            None
-                        } else {
-                            Some(ClassRef::new(
-                                Some(superclass_asm).and_then(|superclass_asm| {
-                                    if superclass_asm.is_empty() {
-                                        None
-                                    } else {
-                                        Some(superclass_asm)
-                                    }
-                                }),
-                                superclass_name,
-                                is_value_type,
-                                [].into(),
-                            ))
-                        };
-                        let tdef = ClassDef::new(
-                            cilly::access_modifier::Access::Public,
-                            name.into(),
-                            vec![],
-                            vec![],
-                            vec![],
-                            None,
-                            0,
-                            inherits,
-                            None,
-                        );
-
-                        ComptimeLocalVar::ClassDef(tdef)
-                    } else if function_name.contains("rustc_codegen_clr_finish_type") {
-                        let local = args[0]
-                            .node
-                            .place()
-                            .expect("ERROR: unuported operation in interop type definiton.")
-                            .as_local()
-                            .expect("ERROR: unuported operation in interop type definiton.");
-                        ctx
-                            .class_def(locals[usize::from(local)].as_type_def().unwrap().clone());
-                        ComptimeLocalVar::Void
-                    } else if function_name.as_ref() == "black_box" {
-                        ComptimeLocalVar::NotSet
-                    } else if function_name.contains("rustc_codegen_clr_add_field_def") {
-                        let src = args[0]
-                            .node
-                            .place()
-                            .expect("ERROR: unuported operation in interop type definiton.")
-                            .as_local()
-                            .expect("ERROR: unuported operation in interop type definiton.");
-                        let mut type_def = locals[usize::from(src)].as_type_def().unwrap().clone();
-                        let tpe = monomorphize(
-                            &instance,
-                            subst_ref[0].as_type().unwrap(),
-                            tcx,
-                        );
-                        let tpe = get_type(tpe, ctx);
-                        let name = garg_to_string(subst_ref[1], ctx.tcx());
-                        type_def.add_field(name.into(), tpe);
-                        ComptimeLocalVar::ClassDef(type_def)
-                    } else if function_name.contains("rustc_codegen_clr_add_method_def") {
-                        let src = args[0]
-                            .node
-                            .place()
-                            .expect("ERROR: unuported operation in interop type definiton.")
-                            .as_local()
-                            .expect("ERROR: unuported operation in interop type definiton.");
-
-                        let mut type_def = locals[usize::from(src)].as_type_def().unwrap().clone();
-                        let fn_type = monomorphize(
-                            &instance,
-                            subst_ref[3].as_type().unwrap(),
-                            ctx.tcx(),
-                        );
-                        let this_fname = garg_to_string(subst_ref[2], ctx.tcx())
-                            .replace("::", ".");
-                        let def_instance = if let TyKind::FnDef(def_id, subst_ref) = fn_type.kind()
-                        {
-                            let subst_ref =
-                                monomorphize(&instance, *subst_ref, ctx.tcx());
-                            let env = rustc_middle::ty::TypingEnv::fully_monomorphized();
-                            let Some(instance) =
-                                Instance::try_resolve(ctx.tcx(), env, *def_id, subst_ref)
-                                    .expect("Invalid function def")
-                            else {
-                                panic!(
-                                    "ERROR: Could not get function instance. fn type:{fn_type:?}"
-                                )
-                            };
-
-                            instance
-                        } else {
-                            todo!("Trying to call a type which is not a function definition!");
-                        };
-                        let call_info = CallInfo::sig_from_instance_(def_instance, ctx);
-                        let target_function_name =
-                            function_name(ctx.tcx().symbol_name(def_instance));
-                        let call_site = Interned<MethodRef>::new(
-                            None,
-                            target_function_name,
-                            call_info.sig().clone(),
-                            true,
-                        );
-
-                        let method = Method::alias_for(
-                            cilly::access_modifier::Access::Public,
-                            // Only virtuals for now
-                            cilly::method::MethodType::Virtual,
-                            this_fname.into(),
-                            call_site,
-                        );
-                        type_def.add_method(method);
-
-                        //type_def.add_method(name.into(), tpe);
-                        ComptimeLocalVar::ClassDef(type_def)
-                    } else {
-                        todo!("Can't yet call the rustc_codegen_clr comptime interop fn named {function_name:?}")
+                    ComptimeLocalVar::TypeDef(Box::new(TypeDefBuilder {
+                        name: ctx.alloc_string(name),
+                        is_valuetype,
+                        extends,
+                        fields: vec![],
+                        methods: vec![],
+                    }))
+                } else if function_name.contains(ADD_FIELD_DEF) {
+                    let mut type_def = class_arg(args, &locals).map_err(at_term)?;
+                    let tpe = ctx.monomorphize(subst_ref[0].as_type().unwrap());
+                    let tpe = ctx.type_from_cache(tpe);
+                    let name = garg_to_string(subst_ref[1], ctx.tcx());
+                    type_def.fields.push((tpe, ctx.alloc_string(name), None));
+                    ComptimeLocalVar::TypeDef(Box::new(type_def))
+                } else if function_name.contains(ADD_METHOD_DEF) {
+                    let mut type_def = class_arg(args, &locals).map_err(at_term)?;
+                    let access = match garg_to_string(subst_ref[0], ctx.tcx()).as_str() {
+                        "pub" => Access::Extern,
+                        vis => {
+                            return Err(at_term(format!(
+                                "unsupported visibility of an interop method: {vis:?}"
+                            )))
+                        }
                     };
-                    block_id = target.unwrap();
-                }
-                TerminatorKind::Return => return,
-                _ => todo!("can't interpret the term {term:?} yet in rustc_codegen_clr comptime"),
-            },
-            None => panic!("Terminatorless block"),
+                    let name = garg_to_string(subst_ref[2], ctx.tcx());
+                    let (target, sig, arg_names) =
+                        method_target(subst_ref[3], ctx).map_err(at_term)?;
+                    let kind = match garg_to_string(subst_ref[1], ctx.tcx()).as_str() {
+                        "static" => MethodKind::Static,
+                        "instance" => MethodKind::Instance,
+                        "virtual" => MethodKind::Virtual,
+                        modifiers => {
+                            return Err(at_term(format!(
+                                "unsupported modifiers of an interop method: {modifiers:?}"
+                            )))
+                        }
+                    };
+                    if kind != MethodKind::Static {
+                        check_this(sig, &type_def, &name, ctx).map_err(at_term)?;
+                    }
+                    type_def.methods.push(MethodDecl {
+                        access,
+                        kind,
+                        name: ctx.alloc_string(name),
+                        sig,
+                        target,
+                        arg_names,
+                    });
+                    ComptimeLocalVar::TypeDef(Box::new(type_def))
+                } else if function_name.contains(ADD_CTOR_DEF) {
+                    let mut type_def = class_arg(args, &locals).map_err(at_term)?;
+                    let access = match garg_to_string(subst_ref[0], ctx.tcx()).as_str() {
+                        "pub" => Access::Extern,
+                        vis => {
+                            return Err(at_term(format!(
+                                "unsupported visibility of an interop constructor: {vis:?}"
+                            )))
+                        }
+                    };
+                    let (target, sig, arg_names) =
+                        method_target(subst_ref[1], ctx).map_err(at_term)?;
+                    check_this(sig, &type_def, ".ctor", ctx).map_err(at_term)?;
+                    if *ctx[sig].output() != Type::Void {
+                        return Err(at_term("interop constructors can't return anything".into()));
+                    }
+                    type_def.methods.push(MethodDecl {
+                        access,
+                        kind: MethodKind::Constructor,
+                        name: ctx.alloc_string(".ctor"),
+                        sig,
+                        target,
+                        arg_names,
+                    });
+                    ComptimeLocalVar::TypeDef(Box::new(type_def))
+                } else if function_name.contains(FINISH_TYPE) {
+                    class_arg(args, &locals).map_err(at_term)?.finish(ctx);
+                    ComptimeLocalVar::Void
+                } else if function_name == "black_box" {
+                    ComptimeLocalVar::NotSet
+                } else {
+                    return Err(at_term(format!(
+                        "interop type definitions can't call {function_name:?}"
+                    )));
+                };
+                block_id = target
+                    .ok_or_else(|| at_term("interop type definitions can't diverge".into()))?;
+            }
+            TerminatorKind::Goto { target } => block_id = *target,
+            TerminatorKind::Return => return Ok(()),
+            _ => {
+                return Err((
+                    span,
+                    format!(
+                        "can't interpret the terminator {:?} in an interop type definition",
+                        term.kind
+                    ),
+                ))
+            }
         }
-    } */
+    }
 }
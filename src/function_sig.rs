@@ -4,13 +4,14 @@ use rustc_abi::CanonAbi;
 use rustc_abi::ExternAbi as TargetAbi;
 use rustc_codegen_clr_ctx::MethodCompileCtx;
 use rustc_codegen_clr_type::r#type::get_type;
-use rustc_middle::ty::{Instance, List, Ty, TyCtxt, TyKind};
+use rustc_middle::ty::{Instance, InstanceKind, List, TyCtxt, TyKind};
 
 /// Creates a `FnSig` from ` `. May not match the result of `sig_from_instance_`!
 /// Use ONLY for function pointers!
 pub fn from_poly_sig<'tcx>(
     ctx: &mut MethodCompileCtx<'tcx, '_>,
     sig: rustc_middle::ty::FnSigTys<TyCtxt<'tcx>>,
+    c_variadic: bool,
 ) -> FnSig {
     let output = get_type(ctx.monomorphize(sig.output()), ctx);
     let inputs: Box<[Type]> = sig
@@ -18,7 +19,11 @@ pub fn from_poly_sig<'tcx>(
         .iter()
         .map(|input| get_type(ctx.monomorphize(*input), ctx))
         .collect();
-    FnSig::new(inputs, output)
+    if c_variadic {
+        FnSig::new_variadic(inputs, output)
+    } else {
+        FnSig::new(inputs, output)
+    }
 }
 /// Returns the signature of function behind `function`.
 pub fn sig_from_instance_<'tcx>(
@@ -40,7 +45,6 @@ pub fn sig_from_instance_<'tcx>(
         CanonAbi::Rust | CanonAbi::C => (),
         _ => panic!("ERROR:calling using convention {conv:?} is not supported!"),
     }
-    //assert!(!fn_abi.c_variadic);
     let ret = ctx.monomorphize(fn_abi.ret.layout.ty);
     let ret = get_type(ret, ctx);
     let mut args = Vec::with_capacity(fn_abi.args.len());
@@ -54,11 +58,15 @@ pub fn sig_from_instance_<'tcx>(
         rustc_middle::ty::TypingEnv::fully_monomorphized(),
     );
     let internal_abi = match fn_ty.kind() {
-        TyKind::FnDef(_, _) => fn_ty.fn_sig(ctx.tcx()),
-        TyKind::Closure(_, args) => args.as_closure().sig(),
+        // The type of a thread local shim is the type of its thread local, but the shim itself is a Rust function
+        // returning the address of that thread local.
+        _ if matches!(function.def, InstanceKind::ThreadLocalShim(_)) => TargetAbi::Rust,
+        TyKind::FnDef(_, _) => fn_ty.fn_sig(ctx.tcx()).abi(),
+        TyKind::Closure(_, args) => args.as_closure().sig().abi(),
+        // The `resume` function of a coroutine always uses the Rust ABI.
+        TyKind::Coroutine(_, _) => TargetAbi::Rust,
         _ => todo!("Can't get signature of {fn_ty}"),
-    }
-    .abi();
+    };
     // Only those ABIs are supported
     match internal_abi {
         TargetAbi::C { unwind: _ }
@@ -70,11 +78,10 @@ pub fn sig_from_instance_<'tcx>(
         ))?,*/
         _ => todo!("Unsuported ABI:{internal_abi:?}"),
     }
-    Ok(FnSig::new(args, ret))
-}
-
-/// Checks if this function is variadic.
-#[must_use]
-pub fn is_fn_variadic<'tcx>(ty: Ty<'tcx>, tcx: TyCtxt<'tcx>) -> bool {
-    ty.fn_sig(tcx).skip_binder().c_variadic
+    // Only the fixed arguments are a part of the signature: the additional ones are passed after them.
+    if fn_abi.c_variadic {
+        Ok(FnSig::new_variadic(args, ret))
+    } else {
+        Ok(FnSig::new(args, ret))
+    }
 }
@@ -0,0 +1,624 @@
+use crate::assembly::MethodCompileCtx;
+use cilly::{
+    cil_node::V1Node, cil_root::V1Root, cil_tree::CILTree, cilnode::MethodKind, AsmInput,
+    AsmOutput, AsmPiece, Assembly, CILNode, CILRoot, InlineAsm, Int, Interned, MethodRef,
+    StaticFieldDesc, Type,
+};
+use rustc_ast::{InlineAsmOptions, InlineAsmTemplatePiece};
+use rustc_codegen_clr_ctx::function_name;
+use rustc_codegen_clr_place::place_address;
+use rustc_codgen_clr_operand::{constant::static_ty, handle_operand};
+use rustc_hir::{InlineAsmOperand as HirAsmOperand, ItemId, ItemKind};
+use rustc_middle::{
+    mir::{interpret::Scalar, ConstValue, InlineAsmOperand, Terminator, TerminatorKind},
+    ty::{layout::TyAndLayout, Instance, List, Ty, TyCtxt, TyKind, TypingEnv},
+};
+use rustc_span::{def_id::DefId, Span};
+use rustc_target::asm::{
+    AArch64InlineAsmRegClass, ArmInlineAsmRegClass, InlineAsmArch, InlineAsmReg, InlineAsmRegClass,
+    InlineAsmRegOrRegClass, RiscVInlineAsmRegClass, X86InlineAsmRegClass,
+};
+/// The C compiler reads assembly in the AT&T syntax, while Rust uses the Intel syntax by default on x86. Templates
+/// in the Intel syntax are wrapped in those directives, which switch the assembler to it, and back.
+const INTEL_SYNTAX_INS: &str = ".intel_syntax noprefix\n\t";
+const ATT_SYNTAX_INS: &str = "\n\t.att_syntax prefix";
+/// Builds an assembly template out of text, and symbols.
+#[derive(Default)]
+struct Template {
+    pieces: Vec<AsmPiece>,
+    text: String,
+}
+impl Template {
+    fn push_sym(&mut self, sym: Interned<CILNode>, asm: &mut Assembly) {
+        self.flush(asm);
+        self.pieces.push(AsmPiece::Sym(sym));
+    }
+    fn flush(&mut self, asm: &mut Assembly) {
+        if !self.text.is_empty() {
+            let text = std::mem::take(&mut self.text);
+            self.pieces.push(AsmPiece::Str(asm.alloc_string(text)));
+        }
+    }
+    fn finish(mut self, asm: &mut Assembly) -> Box<[AsmPiece]> {
+        self.flush(asm);
+        self.pieces.into()
+    }
+}
+/// A register operand is either placed in any register of a class(described by a GCC constraint), or in an explicit
+/// register.
+enum Constraint {
+    Class(&'static str),
+    Register(&'static str),
+}
+fn is_x86(tcx: TyCtxt) -> bool {
+    matches!(
+        tcx.sess.asm_arch,
+        Some(InlineAsmArch::X86 | InlineAsmArch::X86_64)
+    )
+}
+/// Converts a register, or a register class, to a GCC constraint. Returns `None` if it is not supported.
+fn reg_to_gcc(reg: InlineAsmRegOrRegClass) -> Option<Constraint> {
+    match reg {
+        InlineAsmRegOrRegClass::Reg(reg) => explicit_reg_to_gcc(reg).map(Constraint::Register),
+        InlineAsmRegOrRegClass::RegClass(class) => reg_class_to_gcc(class).map(Constraint::Class),
+    }
+}
+fn explicit_reg_to_gcc(reg: InlineAsmReg) -> Option<&'static str> {
+    Some(match reg {
+        InlineAsmReg::X86(reg) => match reg.reg_class() {
+            // GCC does not support the `b` suffix of the low byte registers(`r8b`).
+            X86InlineAsmRegClass::reg_byte => reg.name().trim_end_matches('b'),
+            _ => match reg.name() {
+                "st(0)" => "st",
+                name => name,
+            },
+        },
+        InlineAsmReg::Arm(reg) => reg.name(),
+        InlineAsmReg::AArch64(reg) => reg.name(),
+        InlineAsmReg::RiscV(reg) => reg.name(),
+        _ => return None,
+    })
+}
+/// The constraints can be found in https://gcc.gnu.org/onlinedocs/gcc/Machine-Constraints.html
+fn reg_class_to_gcc(class: InlineAsmRegClass) -> Option<&'static str> {
+    Some(match class {
+        InlineAsmRegClass::X86(X86InlineAsmRegClass::reg) => "r",
+        InlineAsmRegClass::X86(X86InlineAsmRegClass::reg_abcd) => "Q",
+        InlineAsmRegClass::X86(X86InlineAsmRegClass::reg_byte) => "q",
+        InlineAsmRegClass::X86(X86InlineAsmRegClass::xmm_reg | X86InlineAsmRegClass::ymm_reg) => {
+            "x"
+        }
+        InlineAsmRegClass::X86(X86InlineAsmRegClass::zmm_reg) => "v",
+        InlineAsmRegClass::X86(X86InlineAsmRegClass::kreg) => "Yk",
+        InlineAsmRegClass::AArch64(AArch64InlineAsmRegClass::reg) => "r",
+        InlineAsmRegClass::AArch64(AArch64InlineAsmRegClass::vreg) => "w",
+        InlineAsmRegClass::AArch64(AArch64InlineAsmRegClass::vreg_low16) => "x",
+        InlineAsmRegClass::Arm(ArmInlineAsmRegClass::reg) => "r",
+        InlineAsmRegClass::Arm(
+            ArmInlineAsmRegClass::sreg
+            | ArmInlineAsmRegClass::sreg_low16
+            | ArmInlineAsmRegClass::dreg
+            | ArmInlineAsmRegClass::dreg_low16
+            | ArmInlineAsmRegClass::dreg_low8
+            | ArmInlineAsmRegClass::qreg
+            | ArmInlineAsmRegClass::qreg_low8
+            | ArmInlineAsmRegClass::qreg_low4,
+        ) => "t",
+        InlineAsmRegClass::RiscV(RiscVInlineAsmRegClass::reg) => "r",
+        InlineAsmRegClass::RiscV(RiscVInlineAsmRegClass::freg) => "f",
+        _ => return None,
+    })
+}
+/// Converts a Rust template modifier to a GCC one. Rust uses the full register by default, while GCC uses the size of
+/// the operand, so x86 registers always get an explicit size.
+/// The modifiers can be found in https://gcc.gnu.org/onlinedocs/gcc/Modifiers.html
+fn modifier_to_gcc(tcx: TyCtxt, class: InlineAsmRegClass, modifier: Option<char>) -> Option<char> {
+    match class {
+        InlineAsmRegClass::X86(X86InlineAsmRegClass::reg | X86InlineAsmRegClass::reg_abcd) => {
+            match modifier {
+                None if tcx.sess.asm_arch == Some(InlineAsmArch::X86_64) => Some('q'),
+                None | Some('e') => Some('k'),
+                Some('l') => Some('b'),
+                Some('x') => Some('w'),
+                Some('r') => Some('q'),
+                modifier => modifier,
+            }
+        }
+        InlineAsmRegClass::X86(
+            class @ (X86InlineAsmRegClass::xmm_reg
+            | X86InlineAsmRegClass::ymm_reg
+            | X86InlineAsmRegClass::zmm_reg),
+        ) => match (class, modifier) {
+            (X86InlineAsmRegClass::xmm_reg, None) | (_, Some('x')) => Some('x'),
+            (X86InlineAsmRegClass::ymm_reg, None) | (_, Some('y')) => Some('t'),
+            _ => Some('g'),
+        },
+        InlineAsmRegClass::AArch64(AArch64InlineAsmRegClass::reg) => modifier,
+        InlineAsmRegClass::AArch64(
+            AArch64InlineAsmRegClass::vreg | AArch64InlineAsmRegClass::vreg_low16,
+        ) => modifier.filter(|modifier| *modifier != 'v'),
+        InlineAsmRegClass::Arm(
+            ArmInlineAsmRegClass::dreg
+            | ArmInlineAsmRegClass::dreg_low16
+            | ArmInlineAsmRegClass::dreg_low8,
+        ) => Some('P'),
+        InlineAsmRegClass::Arm(
+            ArmInlineAsmRegClass::qreg
+            | ArmInlineAsmRegClass::qreg_low8
+            | ArmInlineAsmRegClass::qreg_low4,
+        ) => modifier.or(Some('q')),
+        _ => None,
+    }
+}
+/// Escapes the characters GCC treats specially in an extended assembly template.
+fn escape_template(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '%' => out.push_str("%%"),
+            '{' => out.push_str("%{"),
+            '}' => out.push_str("%}"),
+            _ => out.push(c),
+        }
+    }
+}
+/// Turns an integer constant operand into text.
+fn const_to_str<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    span: Span,
+    value: ConstValue,
+    layout: TyAndLayout<'tcx>,
+) -> Result<String, &'static str> {
+    match value {
+        ConstValue::Scalar(Scalar::Int(int)) => Ok(rustc_codegen_ssa::common::asm_const_to_str(
+            tcx, span, int, layout,
+        )),
+        _ => Err("pointer constants in assembly are not supported"),
+    }
+}
+/// Returns a node taking the address of a function, used to refer to its symbol in a template.
+fn sym_fn<'tcx>(
+    instance: Instance<'tcx>,
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+) -> Interned<CILNode> {
+    let name = function_name(ctx.tcx().symbol_name(instance));
+    let sig = crate::function_sig::sig_from_instance_(instance, ctx)
+        .expect("Could not get the signature of a function used in assembly!");
+    let mref = MethodRef::new(
+        *ctx.main_module(),
+        ctx.alloc_string(name),
+        ctx.alloc_sig(sig),
+        MethodKind::Static,
+        vec![].into(),
+    );
+    let mref = ctx.alloc_methodref(mref);
+    ctx.alloc_node(CILNode::LdFtn(mref))
+}
+/// Returns a node taking the address of a static, used to refer to its symbol in a template. The static itself is
+/// defined by its own mono item.
+fn sym_static(def_id: DefId, ctx: &mut MethodCompileCtx<'_, '_>) -> Interned<CILNode> {
+    let tpe = ctx.type_from_cache(static_ty(def_id, ctx.tcx()));
+    let symbol = ctx
+        .tcx()
+        .symbol_name(Instance::new_raw(def_id, List::empty()))
+        .to_string();
+    let name = ctx.alloc_string(symbol);
+    let sfld = ctx.alloc_sfld(StaticFieldDesc::new(*ctx.main_module(), name, tpe));
+    ctx.alloc_node(CILNode::LdStaticFieldAddress(sfld))
+}
+fn fn_instance<'tcx>(tcx: TyCtxt<'tcx>, ty: Ty<'tcx>) -> Instance<'tcx> {
+    let TyKind::FnDef(def_id, args) = ty.kind() else {
+        panic!("asm sym {ty:?} is not a function");
+    };
+    Instance::resolve_for_fn_ptr(tcx, TypingEnv::fully_monomorphized(), *def_id, args)
+        .expect("Could not resolve a function used in assembly!")
+}
+fn v2_node(node: &V1Node, ctx: &mut MethodCompileCtx<'_, '_>) -> Interned<CILNode> {
+    let node = CILNode::from_v1(node, ctx);
+    ctx.alloc_node(node)
+}
+/// Emits an error, and returns a root which would throw, if this code was ever reached.
+fn asm_error(span: Span, msg: &str, ctx: &mut MethodCompileCtx<'_, '_>) -> Vec<CILTree> {
+    ctx.tcx().dcx().span_err(span, msg.to_string());
+    vec![V1Root::throw(msg, ctx).into()]
+}
+/// An operand of the block, together with the index of the Rust operand it came from.
+struct Operand<T> {
+    rust_idx: usize,
+    operand: T,
+}
+fn output_constraint(late: bool, readwrite: bool, constraint: &str) -> String {
+    let mut res = String::from(if readwrite { "+" } else { "=" });
+    if !late {
+        res.push('&');
+    }
+    res.push_str(constraint);
+    res
+}
+/// Checks if a clobbered register exists on the target. `clobber_abi` clobbers all registers of an ABI, including the
+/// ones only present with some target features(AVX-512), which GCC would reject.
+fn is_supported_clobber(reg: InlineAsmRegOrRegClass, ctx: &MethodCompileCtx<'_, '_>) -> bool {
+    let Some(arch) = ctx.tcx().sess.asm_arch else {
+        return false;
+    };
+    let features = ctx.tcx().asm_target_features(ctx.instance().def_id());
+    reg.reg_class()
+        .supported_types(arch, true)
+        .iter()
+        .any(|(_, feature)| feature.is_none_or(|feature| features.contains(&feature)))
+}
+/// Translates an `asm!` block into GCC extended assembly. Only the C exporter supports it, so this is a compile error
+/// when targeting .NET.
+pub fn handle_inline_asm<'tcx>(
+    terminator: &Terminator<'tcx>,
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+) -> Vec<CILTree> {
+    let TerminatorKind::InlineAsm {
+        template,
+        operands,
+        options,
+        targets,
+        ..
+    } = &terminator.kind
+    else {
+        panic!("{terminator:?} is not an inline assembly terminator")
+    };
+    let span = terminator.source_info.span;
+    if !*crate::config::C_MODE {
+        return asm_error(
+            span,
+            "inline assembly is not supported when targeting .NET, only when compiling to C",
+            ctx,
+        );
+    }
+    if options.contains(InlineAsmOptions::MAY_UNWIND) {
+        return asm_error(
+            span,
+            "inline assembly which may unwind is not supported",
+            ctx,
+        );
+    }
+    if operands
+        .iter()
+        .any(|op| matches!(op, InlineAsmOperand::Label { .. }))
+    {
+        return asm_error(span, "labels in inline assembly are not supported", ctx);
+    }
+    let tcx = ctx.tcx();
+    // The GCC index of an output is its position in `outputs`, and the index of an input is its position in
+    // `inputs`, plus the number of outputs.
+    let mut outputs: Vec<Operand<AsmOutput>> = vec![];
+    let mut inputs: Vec<Operand<AsmInput>> = vec![];
+    // Clobbers collected from `out("explicit register") _` operands.
+    let mut clobbers: Vec<&'static str> = vec![];
+    let mut input_registers = vec![];
+    for op in operands {
+        if let InlineAsmOperand::In { reg, .. } = op {
+            if let Some(Constraint::Register(reg)) = reg_to_gcc(*reg) {
+                input_registers.push(reg);
+            }
+        }
+    }
+    // Operands placed in any register of a class come first, followed by the ones placed in explicit registers,
+    // which mirrors the GCC backend of rustc.
+    for explicit_pass in [false, true] {
+        for (rust_idx, op) in operands.iter().enumerate() {
+            let reg = match op {
+                InlineAsmOperand::In { reg, .. }
+                | InlineAsmOperand::Out { reg, .. }
+                | InlineAsmOperand::InOut { reg, .. } => *reg,
+                _ => continue,
+            };
+            let Some(gcc_reg) = reg_to_gcc(reg) else {
+                return asm_error(
+                    span,
+                    &format!("the register {reg} is not supported in inline assembly"),
+                    ctx,
+                );
+            };
+            let (constraint, explicit) = match gcc_reg {
+                Constraint::Class(constraint) => (constraint, None),
+                Constraint::Register(name) => ("r", Some(name)),
+            };
+            match op {
+                // `out("explicit register") _` is just a clobber, unless that register is also an input. GCC does
+                // not allow clobbering inputs, so such a register becomes an output pinned to it, whose value is
+                // discarded.
+                InlineAsmOperand::Out {
+                    late, place: None, ..
+                } if explicit.is_some() => {
+                    if explicit_pass {
+                        continue;
+                    }
+                    let name = explicit.unwrap();
+                    if input_registers.contains(&name) {
+                        outputs.push(Operand {
+                            rust_idx,
+                            operand: AsmOutput {
+                                constraint: ctx.alloc_string(output_constraint(*late, false, "r")),
+                                reg: Some(ctx.alloc_string(name)),
+                                tpe: ctx.alloc_type(Type::Int(Int::USize)),
+                                init: None,
+                                dst: None,
+                            },
+                        });
+                    } else if is_supported_clobber(reg, ctx) && !clobbers.contains(&name) {
+                        clobbers.push(name);
+                    }
+                    continue;
+                }
+                _ => (),
+            }
+            if explicit.is_some() != explicit_pass {
+                continue;
+            }
+            let reg = explicit.map(|name| ctx.alloc_string(name));
+            match op {
+                InlineAsmOperand::In { value, .. } => {
+                    let ty = ctx.monomorphize(value.ty(ctx.body(), tcx));
+                    let tpe = ctx.type_from_cache(ty);
+                    let val = handle_operand(value, ctx);
+                    inputs.push(Operand {
+                        rust_idx,
+                        operand: AsmInput {
+                            constraint: ctx.alloc_string(constraint),
+                            reg,
+                            tpe: ctx.alloc_type(tpe),
+                            val: v2_node(&val, ctx),
+                        },
+                    });
+                }
+                InlineAsmOperand::Out { late, place, .. } => {
+                    // Discarded outputs get a dummy type, which fits in any register.
+                    let (tpe, dst) = match place {
+                        Some(place) => {
+                            let ty = ctx.monomorphize(place.ty(ctx.body(), tcx).ty);
+                            let addr = place_address(place, ctx);
+                            (ctx.type_from_cache(ty), Some(v2_node(&addr, ctx)))
+                        }
+                        None => (Type::Int(Int::USize), None),
+                    };
+                    outputs.push(Operand {
+                        rust_idx,
+                        operand: AsmOutput {
+                            constraint: ctx
+                                .alloc_string(output_constraint(*late, false, constraint)),
+                            reg,
+                            tpe: ctx.alloc_type(tpe),
+                            init: None,
+                            dst,
+                        },
+                    });
+                }
+                InlineAsmOperand::InOut {
+                    late,
+                    in_value,
+                    out_place,
+                    ..
+                } => {
+                    // Rust guarantees the input and output types are compatible, so the output uses the input type.
+                    let ty = ctx.monomorphize(in_value.ty(ctx.body(), tcx));
+                    let tpe = ctx.type_from_cache(ty);
+                    let tpe = ctx.alloc_type(tpe);
+                    let val = handle_operand(in_value, ctx);
+                    let val = v2_node(&val, ctx);
+                    let dst = out_place.as_ref().map(|place| {
+                        let addr = place_address(place, ctx);
+                        v2_node(&addr, ctx)
+                    });
+                    // `inout(reg) x => _` becomes a single read-write operand. Otherwise, this is an output, and an
+                    // input tied to it.
+                    let readwrite = dst.is_none() && reg.is_none();
+                    outputs.push(Operand {
+                        rust_idx,
+                        operand: AsmOutput {
+                            constraint: ctx
+                                .alloc_string(output_constraint(*late, readwrite, constraint)),
+                            reg,
+                            tpe,
+                            init: readwrite.then_some(val),
+                            dst,
+                        },
+                    });
+                    if !readwrite {
+                        let tied = (outputs.len() - 1).to_string();
+                        inputs.push(Operand {
+                            rust_idx,
+                            operand: AsmInput {
+                                constraint: ctx.alloc_string(tied),
+                                reg: None,
+                                tpe,
+                                val,
+                            },
+                        });
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+    let intel_syntax = is_x86(tcx) && !options.contains(InlineAsmOptions::ATT_SYNTAX);
+    let mut asm_template = Template::default();
+    if intel_syntax {
+        asm_template.text.push_str(INTEL_SYNTAX_INS);
+    }
+    for piece in template.iter() {
+        match piece {
+            InlineAsmTemplatePiece::String(text) => escape_template(text, &mut asm_template.text),
+            InlineAsmTemplatePiece::Placeholder {
+                operand_idx,
+                modifier,
+                span,
+            } => match &operands[*operand_idx] {
+                InlineAsmOperand::In { reg, .. } => {
+                    let modifier = modifier_to_gcc(tcx, reg.reg_class(), *modifier);
+                    let idx = inputs
+                        .iter()
+                        .position(|input| input.rust_idx == *operand_idx)
+                        .expect("No input for an asm operand");
+                    push_placeholder(&mut asm_template.text, modifier, idx + outputs.len());
+                }
+                InlineAsmOperand::Out { reg, .. } | InlineAsmOperand::InOut { reg, .. } => {
+                    let modifier = modifier_to_gcc(tcx, reg.reg_class(), *modifier);
+                    let idx = outputs
+                        .iter()
+                        .position(|output| output.rust_idx == *operand_idx)
+                        .expect("No output for an asm operand");
+                    push_placeholder(&mut asm_template.text, modifier, idx);
+                }
+                InlineAsmOperand::Const { value } => {
+                    let const_ = ctx.monomorphize(value.const_);
+                    let evaluated = const_
+                        .eval(tcx, TypingEnv::fully_monomorphized(), *span)
+                        .expect("Could not evaluate an asm constant!");
+                    let layout = ctx.layout_of(const_.ty());
+                    match const_to_str(tcx, *span, evaluated, layout) {
+                        Ok(text) => asm_template.text.push_str(&text),
+                        Err(msg) => return asm_error(*span, msg, ctx),
+                    }
+                }
+                InlineAsmOperand::SymFn { value } => {
+                    let const_ = ctx.monomorphize(value.const_);
+                    let sym = sym_fn(fn_instance(tcx, const_.ty()), ctx);
+                    asm_template.push_sym(sym, ctx);
+                }
+                InlineAsmOperand::SymStatic { def_id } => {
+                    let sym = sym_static(*def_id, ctx);
+                    asm_template.push_sym(sym, ctx);
+                }
+                InlineAsmOperand::Label { .. } => unreachable!(),
+            },
+        }
+    }
+    if intel_syntax {
+        asm_template.text.push_str(ATT_SYNTAX_INS);
+    }
+    if !options.contains(InlineAsmOptions::PRESERVES_FLAGS)
+        && !matches!(
+            tcx.sess.asm_arch,
+            Some(InlineAsmArch::PowerPC | InlineAsmArch::PowerPC64)
+        )
+    {
+        clobbers.push("cc");
+    }
+    if !options.contains(InlineAsmOptions::NOMEM) {
+        clobbers.push("memory");
+    }
+    let block = CILRoot::InlineAsm(Box::new(InlineAsm {
+        template: asm_template.finish(ctx),
+        outputs: outputs.into_iter().map(|output| output.operand).collect(),
+        inputs: inputs.into_iter().map(|input| input.operand).collect(),
+        clobbers: clobbers
+            .into_iter()
+            .map(|clobber| ctx.alloc_string(clobber))
+            .collect(),
+        is_volatile: !options.contains(InlineAsmOptions::PURE),
+    }));
+    let block = V1Root::V2(ctx.alloc_root(block)).into();
+    if options.contains(InlineAsmOptions::NORETURN) {
+        let msg = ctx.alloc_string(format!("noreturn inline assembly returned at {span:?}!"));
+        vec![
+            block,
+            V1Root::V2(ctx.alloc_root(CILRoot::Unreachable(msg))).into(),
+        ]
+    } else {
+        vec![
+            block,
+            V1Root::GoTo {
+                target: targets[0].as_u32(),
+                sub_target: 0,
+            }
+            .into(),
+        ]
+    }
+}
+fn push_placeholder(text: &mut String, modifier: Option<char>, gcc_idx: usize) {
+    text.push('%');
+    text.extend(modifier);
+    text.push_str(&gcc_idx.to_string());
+}
+/// Adds a `global_asm!` block to the assembly. The C exporter emits it at file scope, so it can't be supported when
+/// targeting .NET.
+pub fn add_global_asm(asm: &mut Assembly, item_id: ItemId, tcx: TyCtxt<'_>) {
+    let span = tcx.def_span(item_id.owner_id);
+    if !*crate::config::C_MODE {
+        tcx.dcx().span_err(
+            span,
+            "global assembly is not supported when targeting .NET, only when compiling to C",
+        );
+        return;
+    }
+    let item = tcx.hir_item(item_id);
+    let ItemKind::GlobalAsm {
+        asm: global_asm, ..
+    } = item.kind
+    else {
+        panic!("{item:?} is not a global_asm! block")
+    };
+    let intel_syntax = is_x86(tcx) && !global_asm.options.contains(InlineAsmOptions::ATT_SYNTAX);
+    let mut template = Template::default();
+    // File scope assembly does not switch to any section by itself.
+    template.text.push_str(".pushsection .text\n");
+    if intel_syntax {
+        template.text.push_str(".intel_syntax noprefix\n");
+    }
+    for piece in global_asm.template {
+        match piece {
+            // This is basic assembly, so nothing needs to be escaped. The GCC backend of rustc also removes `//`
+            // comments, which the assembler does not understand.
+            InlineAsmTemplatePiece::String(text) => {
+                for (idx, line) in text.split('\n').enumerate() {
+                    if idx != 0 {
+                        template.text.push('\n');
+                    }
+                    template
+                        .text
+                        .push_str(line.split_once("//").map_or(line, |(code, _)| code));
+                }
+            }
+            InlineAsmTemplatePiece::Placeholder {
+                operand_idx, span, ..
+            } => match global_asm.operands[*operand_idx].0 {
+                HirAsmOperand::Const { ref anon_const } => {
+                    let value = tcx
+                        .const_eval_poly(anon_const.def_id.to_def_id())
+                        .expect("Could not evaluate an asm constant!");
+                    let ty = tcx
+                        .typeck_body(anon_const.body)
+                        .node_type(anon_const.hir_id);
+                    let layout = tcx
+                        .layout_of(TypingEnv::fully_monomorphized().as_query_input(ty))
+                        .expect("Could not get the layout of an asm constant!");
+                    match const_to_str(tcx, *span, value, layout) {
+                        Ok(text) => template.text.push_str(&text),
+                        Err(msg) => {
+                            tcx.dcx().span_err(*span, msg);
+                            return;
+                        }
+                    }
+                }
+                HirAsmOperand::SymFn { expr } => {
+                    let ty = tcx.typeck(item_id.owner_id).expr_ty(expr);
+                    let instance = fn_instance(tcx, ty);
+                    let sym = sym_fn(
+                        instance,
+                        &mut MethodCompileCtx::new(tcx, None, instance, asm),
+                    );
+                    template.push_sym(sym, asm);
+                }
+                HirAsmOperand::SymStatic { def_id, .. } => {
+                    let instance = Instance::new_raw(def_id, List::empty());
+                    let sym =
+                        sym_static(def_id, &mut MethodCompileCtx::new(tcx, None, instance, asm));
+                    template.push_sym(sym, asm);
+                }
+                _ => panic!("invalid operand of a global_asm! block"),
+            },
+        }
+    }
+    if intel_syntax {
+        template.text.push_str("\n.att_syntax prefix");
+    }
+    template.text.push_str("\n.popsection");
+    let pieces = template.finish(asm);
+    asm.add_global_asm(pieces);
+}
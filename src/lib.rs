@@ -80,13 +80,16 @@
 
 // References to internal rustc crates.
 extern crate rustc_abi;
+extern crate rustc_ast;
 
 extern crate rustc_codegen_ssa;
 extern crate rustc_const_eval;
 extern crate rustc_data_structures;
 extern crate rustc_driver;
 extern crate rustc_errors;
+extern crate rustc_fs_util;
 extern crate rustc_hir;
+extern crate rustc_incremental;
 extern crate rustc_index;
 extern crate rustc_metadata;
 extern crate rustc_middle;
@@ -109,6 +112,8 @@ mod binop;
 mod call_info;
 /// Code hansling rust `as` casts.
 mod casts;
+/// Compilation of codegen units, and reusing them from the incremental cache.
+mod cgu;
 /// Runtime errors and utlity functions/macros related to them
 mod codegen_error;
 /// Test harnesses.
@@ -119,6 +124,8 @@ mod comptime;
 mod fn_ctx;
 /// Signature of a function (inputs)->output
 pub mod function_sig;
+/// Translation of inline assembly, and `global_asm!`, into GCC extended assembly.
+mod inline_asm;
 /// Interop type handling.
 mod interop;
 pub mod native_pastrough;
@@ -150,7 +157,7 @@ use rustc_codegen_clr_ctx::MethodCompileCtx;
 use rustc_codegen_ssa::{
     back::archive::{ArArchiveBuilder, ArchiveBuilder, ArchiveBuilderBuilder},
     traits::CodegenBackend,
-    CodegenResults, CompiledModule, CrateInfo, ModuleKind,
+    CodegenResults, CrateInfo,
 };
 
 use rustc_data_structures::fx::FxIndexMap;
@@ -159,10 +166,7 @@ use rustc_middle::{
     dep_graph::{WorkProduct, WorkProductId},
     ty::TyCtxt,
 };
-use rustc_session::{
-    config::{OutputFilenames, OutputType},
-    Session,
-};
+use rustc_session::{config::OutputFilenames, Session};
 
 use std::{any::Any, path::Path};
 /// Immutable string - used to save a bit of memory on storage.
@@ -197,12 +201,9 @@ impl CodegenBackend for MyBackend {
         }
         let _ = cilly::utilis::get_environ(&mut asm);
 
-        for cgu in cgus.codegen_units {
-            //println!("codegen {} has {} items.", cgu.name(), cgu.items().len());
-            for (item, _data) in cgu.items() {
-                assembly::add_item(&mut asm, *item, tcx).expect("Could not add function");
-            }
-        }
+        // Each codegen unit is compiled into its own assembly, and saved as its own module. The linker then links those
+        // together, which also merges items shared between codegen units(eg. `#[inline]` functions).
+        let cgu_modules = cgu::codegen_units(tcx, cgus.codegen_units);
 
         if let Some((entrypoint, _kind)) = tcx.entry_fn(()) {
             let penv = rustc_middle::ty::TypingEnv::fully_monomorphized();
@@ -227,7 +228,8 @@ impl CodegenBackend for MyBackend {
                 vec![].into(),
             );
 
-            cilly::entrypoint::wrapper(cs, &mut asm);
+            // C registers the finalizer with `atexit`, so that it also runs when the program calls `exit`.
+            cilly::entrypoint::wrapper(cs, &mut asm, !*crate::config::C_MODE);
         }
 
         let ffi_compile_timer = tcx
@@ -235,16 +237,12 @@ impl CodegenBackend for MyBackend {
             .generic_activity("insert .NET FFI functions/types");
         //builtin::insert_ffi_functions(&mut asm, tcx);
         drop(ffi_compile_timer);
-        let name: IString = cgus
-            .codegen_units
-            .iter()
-            .next()
-            .unwrap()
-            .name()
-            .to_string()
-            .into();
-
-        Box::new((name, asm, metadata, CrateInfo::new(tcx, "clr".to_string())))
+        Box::new((
+            asm,
+            cgu_modules,
+            metadata,
+            CrateInfo::new(tcx, "clr".to_string()),
+        ))
     }
 
     fn target_config(&self, sess: &Session) -> rustc_codegen_ssa::TargetConfig {
@@ -276,51 +274,32 @@ impl CodegenBackend for MyBackend {
         rustc_codegen_ssa::TargetConfig {
             target_features,
             unstable_target_features,
-            // Cranelift does not yet support f16 or f128
+            // f16 is not yet supported. f128 arithmetic is supported, but its math functions are not.
             has_reliable_f16: false,
             has_reliable_f16_math: false,
-            has_reliable_f128: false,
+            has_reliable_f128: true,
             has_reliable_f128_math: false,
         }
     }
-    /// Saves an in-memory assemably to codegen specific IR in a .bc file.
+    /// Saves the in-memory assemablies of all codegen units to codegen specific IR in .bc files.
     fn join_codegen(
         &self,
         ongoing_codegen: Box<dyn Any>,
-        _sess: &Session,
+        sess: &Session,
         outputs: &OutputFilenames,
     ) -> (CodegenResults, FxIndexMap<WorkProductId, WorkProduct>) {
         std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-            use std::io::Write;
-            let (_asm_name, asm, metadata, crate_info) = *ongoing_codegen
-                .downcast::<(IString, Assembly, EncodedMetadata, CrateInfo)>()
+            let (asm, cgu_modules, metadata, crate_info) = *ongoing_codegen
+                .downcast::<(Assembly, Vec<cgu::CguModule>, EncodedMetadata, CrateInfo)>()
                 .expect("in join_codegen: ongoing_codegen is not an Assembly");
-            let asm_name = "";
-            let serialized_asm_path =
-                outputs.temp_path_for_cgu(OutputType::Bitcode, asm_name, None);
-            //std::fs::create_dir_all(&serialized_asm_path).expect("Could not create the directory temporary files are supposed to be in.");
-
-            let mut asm_out = std::fs::File::create(&serialized_asm_path).expect(
-                "Could not create the temporary files necessary for building the assembly!",
-            );
-            let mut v2 = cilly::Assembly::from_v1(&asm);
-            v2.opt(&mut v2.fuel_from_env());
-            v2.typecheck();
-            asm_out
-                .write_all(
-                    &postcard::to_stdvec(&v2).expect("Could not serialize the tmp assembly file!"),
-                )
-                .expect("Could not save the tmp assembly file!");
-            let modules = vec![CompiledModule {
-                name: asm_name.into(),
-                kind: ModuleKind::Regular,
-                object: Some(serialized_asm_path),
-                bytecode: None,
-                dwarf_object: None,
-                llvm_ir: None,
-                assembly: None,
-                links_from_incr_cache: Vec::new(),
-            }];
+            // The metadata and entrypoint are not a part of any codegen unit, and are never cached.
+            let mut modules = vec![cgu::save_module("", &asm, outputs)];
+            let mut work_products = FxIndexMap::default();
+            for cgu_module in cgu_modules {
+                let (module, work_product) = cgu::save_codegen_unit(cgu_module, sess, outputs);
+                modules.push(module);
+                work_products.extend(work_product);
+            }
             let codegen_results = CodegenResults {
                 modules,
                 allocator_module: None,
@@ -328,7 +307,7 @@ impl CodegenBackend for MyBackend {
                 metadata,
                 crate_info,
             };
-            (codegen_results, FxIndexMap::default())
+            (codegen_results, work_products)
         }))
         .expect("Could not join_codegen")
     }
@@ -3,6 +3,7 @@ use crate::{
     utilis::{adt::get_discr, compiletime_sizeof},
 };
 use cilly::{
+    call,
     cil_node::V1Node,
     cil_root::V1Root,
     conv_usize, ld_field, size_of, Const, Type,
@@ -111,6 +112,9 @@ pub fn handle_rvalue<'tcx>(
         Rvalue::Cast(CastKind::PointerCoercion(PointerCoercion::Unsize, _), operand, target) => {
             crate::unsize::unsize2(ctx, operand, *target, *target_location)
         }
+        Rvalue::Cast(CastKind::PointerCoercion(PointerCoercion::DynStar, _), operand, target) => {
+            (vec![], crate::unsize::dyn_star(ctx, operand, *target))
+        }
         Rvalue::BinaryOp(binop, operands) => (
             vec![],
             crate::binop::binop(*binop, &operands.0, &operands.1, ctx),
@@ -283,7 +287,7 @@ pub fn handle_rvalue<'tcx>(
                     Type::Int(Int::USize | Int::ISize) | Type::Ptr(_) | Type::FnPtr(_) => {
                         val.cast_ptr(target)
                     }
-                    Type::Int(Int::U64 | Int::I64) => crate::casts::int_to_int(
+                    Type::Int(_) => crate::casts::int_to_int(
                         Type::Int(Int::USize),
                         target,
                         val.cast_ptr(Type::Int(Int::USize)),
@@ -297,7 +301,13 @@ pub fn handle_rvalue<'tcx>(
             let target = ctx.monomorphize(*target);
             let target = ctx.type_from_cache(target);
             let mut ops = handle_operand(operand, ctx);
+            let src = ctx.type_from_cache(ctx.monomorphize(operand.ty(ctx.body(), ctx.tcx())));
             match target {
+                _ if src == target => (),
+                Type::Float(Float::F128) => ops = crate::casts::f128_cast(src, target, ops, ctx),
+                _ if src == Type::Float(Float::F128) => {
+                    ops = crate::casts::f128_cast(src, target, ops, ctx);
+                }
                 Type::Float(Float::F32) => ops = V1Node::ConvF32(ops.into()),
                 Type::Float(Float::F64) => ops = V1Node::ConvF64(ops.into()),
                 _ => panic!("Can't preform a FloatToFloat cast to type {target:?}"),
@@ -389,12 +399,27 @@ pub fn handle_rvalue<'tcx>(
         Rvalue::Repeat(operand, times) => repeat(rvalue, ctx, operand, *times, target_location),
         Rvalue::ThreadLocalRef(def_id) => {
             if !def_id.is_local() && ctx.tcx().needs_thread_local_shim(*def_id) {
-                let _instance = Instance {
+                // The thread local lives in another crate, and can only be accessed trough a shim, returning its address.
+                let instance = Instance {
                     def: InstanceKind::ThreadLocalShim(*def_id),
                     args: GenericArgs::empty(),
                 };
-                // Call instance
-                todo!("Thread locals with shims unsupported!")
+                let function_name = function_name(ctx.tcx().symbol_name(instance));
+                let function_sig = crate::function_sig::sig_from_instance_(instance, ctx)
+                    .expect("Could not get the signature of a thread local shim.");
+                let shim = MethodRef::new(
+                    *ctx.main_module(),
+                    ctx.alloc_string(function_name),
+                    ctx.alloc_sig(function_sig),
+                    MethodKind::Static,
+                    vec![].into(),
+                );
+                let rvalue_ty = ctx.monomorphize(rvalue.ty(ctx.body(), ctx.tcx()));
+                let rvalue_type = ctx.type_from_cache(rvalue_ty);
+                (
+                    vec![],
+                    call!(ctx.alloc_methodref(shim), []).cast_ptr(rvalue_type),
+                )
             } else {
                 let alloc_id = ctx.tcx().reserve_and_set_static_alloc(*def_id);
                 let rvalue_ty = rvalue.ty(ctx.body(), ctx.tcx());
@@ -409,14 +434,6 @@ pub fn handle_rvalue<'tcx>(
             let target = ctx.type_from_cache(*target);
             (vec![], handle_operand(operand, ctx).cast_ptr(target))
         }
-        rustc_middle::mir::Rvalue::Cast(
-            rustc_middle::mir::CastKind::PointerCoercion(
-                rustc_middle::ty::adjustment::PointerCoercion::DynStar,
-                _,
-            ),
-            _,
-            _,
-        ) => todo!("Dyn star casts unspoorted"),
     }
 }
 const SIMPLE_REPEAT_CAP: u64 = 16;
@@ -3,8 +3,11 @@ use crate::{
     interop::AssemblyRef,
     utilis::{
         garag_to_bool, CTOR_FN_NAME, MANAGED_CALL_FN_NAME, MANAGED_CALL_VIRT_FN_NAME,
-        MANAGED_CHECKED_CAST, MANAGED_IS_INST, MANAGED_LD_ELEM_REF, MANAGED_LD_LEN,
-        MANAGED_LD_NULL,
+        MANAGED_CHECKED_CAST, MANAGED_DELEGATE, MANAGED_GENERIC_CALL_INSTANCE,
+        MANAGED_GENERIC_CALL_STATIC, MANAGED_GENERIC_CALL_VIRT, MANAGED_GENERIC_CTOR,
+        MANAGED_IS_INST, MANAGED_LD_ELEM_REF, MANAGED_LD_FLD, MANAGED_LD_LEN, MANAGED_LD_NULL,
+        MANAGED_ST_FLD, MANAGED_TUPLE_CALL_INSTANCE, MANAGED_TUPLE_CALL_STATIC,
+        MANAGED_TUPLE_CALL_VIRT, MANAGED_TUPLE_CTOR,
     },
 };
 use cilly::{
@@ -12,9 +15,9 @@ use cilly::{
     cil_node::{CallOpArgs, V1Node},
     cil_root::V1Root,
     cilnode::{IsPure, MethodKind},
-    conv_usize, ld_field, ClassRef, Const, FieldDesc, FnSig, Int, IntoAsmIndex,
+    conv_usize, ld_field, ClassRef, Const, FieldDesc, FnSig, Int, Interned, IntoAsmIndex,
 };
-use cilly::{MethodRef, Type};
+use cilly::{CILNode, MethodRef, Type};
 use rustc_codegen_clr_call::CallInfo;
 use rustc_codegen_clr_ctx::function_name;
 use rustc_codegen_clr_place::place_set;
@@ -32,6 +35,20 @@ fn argc_from_fn_name(function_name: &str, prefix: &str) -> u32 {
     let argument_count = &function_name[argc_start..argc_end];
     argument_count.parse::<u32>().unwrap()
 }
+/// Returns the field of a managed class accessed by a managed field load or store(used for interop).
+fn managed_field<'tcx>(
+    instance: Instance<'tcx>,
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+) -> Interned<FieldDesc> {
+    let name = garg_to_string(instance.args[0], ctx.tcx());
+    let name = ctx.alloc_string(name);
+    let owner = ctx
+        .type_from_cache(instance.args[1].as_type().unwrap())
+        .as_class_ref()
+        .expect("Only fields of managed classes can be accessed directly.");
+    let tpe = ctx.type_from_cache(instance.args[2].as_type().unwrap());
+    ctx.alloc_field(FieldDesc::new(owner, name, tpe))
+}
 /// Calls a non-virtual managed function(used for interop)
 fn call_managed<'tcx>(
     subst_ref: &[GenericArg<'tcx>],
@@ -58,11 +75,10 @@ fn call_managed<'tcx>(
         .expect("Can't get the function signature");
 
     if argument_count == 0 {
-        let ret = cilly::Type::Void;
         let call_site = MethodRef::new(
             ctx.alloc_class_ref(tpe),
             ctx.alloc_string(managed_fn_name),
-            ctx.sig([], ret),
+            ctx.alloc_sig(signature.clone()),
             MethodKind::Static,
             vec![].into(),
         );
@@ -257,6 +273,281 @@ fn call_ctor<'tcx>(
         )
     }
 }
+/// Calls a managed method or constructor, taking any number of arguments(used for interop). The arguments are packed in a
+/// tuple, passed as the last argument. `this` is passed before the tuple.
+fn call_managed_tuple<'tcx>(
+    subst_ref: &[GenericArg<'tcx>],
+    kind: MethodKind,
+    args: &[Spanned<Operand<'tcx>>],
+    destination: &Place<'tcx>,
+    fn_instance: Instance<'tcx>,
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+) -> V1Root {
+    let asm = AssemblyRef::decode_assembly_ref(subst_ref[0], ctx.tcx());
+    let asm = asm.name().map(|name| ctx.alloc_string(name));
+    let class_name = garg_to_string(subst_ref[1], ctx.tcx());
+    let class_name = ctx.alloc_string(class_name);
+    let is_valuetype = garag_to_bool(subst_ref[2], ctx.tcx());
+    let tpe = ctx.alloc_class_ref(ClassRef::new(class_name, asm, is_valuetype, [].into()));
+    let managed_fn_name = (kind != MethodKind::Constructor)
+        .then(|| garg_to_string(ctx.monomorphize(subst_ref[3]), ctx.tcx()));
+    managed_call(
+        tpe,
+        kind,
+        managed_fn_name,
+        [].into(),
+        None,
+        args,
+        destination,
+        fn_instance,
+        ctx,
+    )
+}
+/// Calls a method of a generic managed class, or a generic managed method(used for interop). The owner of the method,
+/// its generic arguments and its declared signature are passed as generic arguments of the intrinsic. The arguments are
+/// packed in a tuple, just like in [`call_managed_tuple`].
+fn call_managed_generic<'tcx>(
+    subst_ref: &[GenericArg<'tcx>],
+    kind: MethodKind,
+    args: &[Spanned<Operand<'tcx>>],
+    destination: &Place<'tcx>,
+    fn_instance: Instance<'tcx>,
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+) -> V1Root {
+    let owner = ctx.monomorphize(
+        subst_ref[0]
+            .as_type()
+            .expect("The owner of a managed method must be a type"),
+    );
+    let tpe = ctx
+        .type_from_cache(owner)
+        .as_class_ref()
+        .expect("Only methods of managed classes can be called.");
+    // Constructors have no name nor generic arguments: only their signature is specified.
+    let (managed_fn_name, generics, sig) = if kind == MethodKind::Constructor {
+        (None, [].into(), subst_ref[1])
+    } else {
+        let name = garg_to_string(ctx.monomorphize(subst_ref[1]), ctx.tcx());
+        let generics = ctx.monomorphize(
+            subst_ref[2]
+                .as_type()
+                .expect("Generic arguments of a managed method must be a tuple"),
+        );
+        let TyKind::Tuple(generics) = generics.kind() else {
+            panic!("Generic arguments of a managed method must be a tuple, not {generics:?}")
+        };
+        let generics: Box<[Type]> = generics
+            .iter()
+            .map(|generic| ctx.type_from_cache(generic))
+            .collect();
+        (Some(name), generics, subst_ref[3])
+    };
+    let sig = ctx.monomorphize(
+        sig.as_type()
+            .expect("The signature of a managed method must be a type"),
+    );
+    let Type::FnPtr(declared) = ctx.type_from_cache(sig) else {
+        panic!("The signature of a managed method must be a function pointer, not {sig:?}")
+    };
+    managed_call(
+        tpe,
+        kind,
+        managed_fn_name,
+        generics,
+        Some(declared),
+        args,
+        destination,
+        fn_instance,
+        ctx,
+    )
+}
+/// Emits a call to the managed method `name` of `tpe`. If the `declared` signature of the method is not provided, it is
+/// derived from the types of the arguments. `generics` are the generic arguments of the method.
+#[allow(clippy::too_many_arguments)]
+fn managed_call<'tcx>(
+    tpe: Interned<ClassRef>,
+    kind: MethodKind,
+    name: Option<String>,
+    generics: Box<[Type]>,
+    declared: Option<Interned<FnSig>>,
+    args: &[Spanned<Operand<'tcx>>],
+    destination: &Place<'tcx>,
+    fn_instance: Instance<'tcx>,
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+) -> V1Root {
+    let (packed, this) = args
+        .split_last()
+        .expect("Tuple-packed managed calls must take an argument tuple");
+    let mut inputs = Vec::new();
+    let mut call_args = Vec::new();
+    for arg in this {
+        let arg_ty = ctx.monomorphize(arg.node.ty(ctx.body(), ctx.tcx()));
+        inputs.push(ctx.type_from_cache(arg_ty));
+        call_args.push(handle_operand(&arg.node, ctx));
+    }
+    // Zero-sized arguments have no .NET equivalent, so they are not passed.
+    for (tpe, arg) in unpack_tuple(&packed.node, ctx) {
+        if tpe != Type::Void {
+            inputs.push(tpe);
+            call_args.push(arg);
+        }
+    }
+    let mut output = if kind == MethodKind::Constructor {
+        Type::Void
+    } else {
+        *crate::function_sig::sig_from_instance_(fn_instance, ctx)
+            .expect("Can't get the function signature")
+            .output()
+    };
+    // The declared signature may refer to generic parameters(`!0`, `!!0`), instead of the types of the arguments.
+    if let Some(declared) = declared {
+        let declared = ctx[declared].clone();
+        let params = declared.inputs().iter().filter(|tpe| **tpe != Type::Void);
+        assert_eq!(
+            params.clone().count(),
+            inputs.len() - this.len(),
+            "The declared signature of a managed method does not match the number of its arguments."
+        );
+        inputs.truncate(this.len());
+        inputs.extend(params);
+        if kind != MethodKind::Constructor {
+            output = *declared.output();
+        }
+    }
+    if kind == MethodKind::Constructor {
+        inputs.insert(0, Type::ClassRef(tpe));
+        let ctor = MethodRef::new(
+            tpe,
+            ctx.alloc_string(".ctor"),
+            ctx.sig(inputs, Type::Void),
+            MethodKind::Constructor,
+            vec![].into(),
+        );
+        return place_set(
+            destination,
+            V1Node::NewObj(Box::new(CallOpArgs {
+                site: ctx.alloc_methodref(ctor),
+                args: call_args.into(),
+                is_pure: IsPure::NOT,
+            })),
+            ctx,
+        );
+    }
+    let name = name.expect("Managed methods must have a name");
+    let site = MethodRef::new(
+        tpe,
+        ctx.alloc_string(name),
+        ctx.sig(inputs, output),
+        if kind == MethodKind::Static {
+            MethodKind::Static
+        } else {
+            MethodKind::Instance
+        },
+        generics,
+    );
+    let site = ctx.alloc_methodref(site);
+    match (kind == MethodKind::Virtual, output == Type::Void) {
+        (false, true) => V1Root::Call {
+            site,
+            args: call_args.into(),
+        },
+        (true, true) => V1Root::CallVirt {
+            site,
+            args: call_args.into(),
+        },
+        (false, false) => place_set(destination, call!(site, call_args), ctx),
+        (true, false) => place_set(destination, call_virt!(site, call_args), ctx),
+    }
+}
+/// Wraps a Rust closure in a managed delegate(used for interop). The arguments are a thunk calling the closure, a function
+/// dropping it, and a pointer to the closure itself. The signature of the delegate is given by the `Args` tuple and `Ret`.
+fn create_delegate<'tcx>(
+    subst_ref: &[GenericArg<'tcx>],
+    args: &[Spanned<Operand<'tcx>>],
+    destination: &Place<'tcx>,
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+) -> V1Root {
+    assert_eq!(
+        args.len(),
+        3,
+        "Delegates are created from a thunk, a drop function and a closure pointer."
+    );
+    let delegate = ctx.monomorphize(
+        subst_ref[0]
+            .as_type()
+            .expect("The delegate type must be a type"),
+    );
+    let delegate = ctx
+        .type_from_cache(delegate)
+        .as_class_ref()
+        .expect("Delegates must be managed classes.");
+    let inputs = ctx.monomorphize(
+        subst_ref[1]
+            .as_type()
+            .expect("Delegate arguments must be a tuple"),
+    );
+    let TyKind::Tuple(inputs) = inputs.kind() else {
+        panic!("Delegate arguments must be a tuple, not {inputs:?}")
+    };
+    // Zero-sized arguments have no .NET equivalent, so they are not passed.
+    let inputs: Vec<Type> = inputs
+        .iter()
+        .map(|input| ctx.type_from_cache(input))
+        .filter(|input| *input != Type::Void)
+        .collect();
+    let output = ctx.monomorphize(
+        subst_ref[2]
+            .as_type()
+            .expect("Delegate return type must be a type"),
+    );
+    let output = ctx.type_from_cache(output);
+    let sig = ctx.sig(inputs, output);
+    let [invoke, drop, data] = [0, 1, 2].map(|arg| {
+        let arg = handle_operand(&args[arg].node, ctx);
+        let arg = CILNode::from_v1(&arg, ctx);
+        ctx.alloc_node(arg)
+    });
+    let delegate =
+        cilly::builtins::delegate::closure_delegate(delegate, sig, invoke, drop, data, ctx);
+    place_set(destination, V1Node::V2(delegate), ctx)
+}
+/// Returns the types and values of the elements of a tuple operand.
+fn unpack_tuple<'tcx>(
+    tuple: &Operand<'tcx>,
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+) -> Vec<(Type, V1Node)> {
+    let tuple_ty = ctx.monomorphize(tuple.ty(ctx.body(), ctx.tcx()));
+    let TyKind::Tuple(elements) = tuple_ty.kind() else {
+        panic!("Can't unbox type {tuple_ty:?}!")
+    };
+    if elements.is_empty() {
+        return vec![];
+    }
+    let tuple_type = ctx.type_from_cache(tuple_ty);
+    elements
+        .iter()
+        .enumerate()
+        .map(|(index, element)| {
+            let element_type = ctx.type_from_cache(element);
+            if element_type == Type::Void {
+                return (Type::Void, V1Node::uninit_val(Type::Void, ctx));
+            }
+            let tuple_element_name = format!("Item{}", index + 1);
+            let field_descriptor = FieldDesc::new(
+                tuple_type.as_class_ref().expect("Invalid tuple type"),
+                ctx.alloc_string(tuple_element_name),
+                element_type,
+            );
+            (
+                element_type,
+                ld_field!(
+                    handle_operand(tuple, ctx),
+                    ctx.alloc_field(field_descriptor)
+                ),
+            )
+        })
+        .collect()
+}
 pub fn call_closure<'tcx>(
     args: &[Spanned<Operand<'tcx>>],
     destination: &Place<'tcx>,
@@ -275,37 +566,11 @@ pub fn call_closure<'tcx>(
     }
     // "Rust call" is wierd, and not at all optimized for .NET. Passing all the arguments in a tuple is bad for performance and simplicty. Thus, unpacking this tuple and forcing "Rust call" to be
     // "normal" is far easier and better for performance.
-    let last_arg_type = ctx.monomorphize(last_arg.node.ty(ctx.body(), ctx.tcx()));
-    match last_arg_type.kind() {
-        TyKind::Tuple(elements) => {
-            if elements.is_empty() {
-            } else {
-                let tuple_type = ctx.type_from_cache(last_arg_type);
-
-                for (index, element) in elements.iter().enumerate() {
-                    let element_type = ctx.type_from_cache(element);
-                    if element_type == Type::Void {
-                        call_args.push(V1Node::uninit_val(Type::Void, ctx));
-                        continue;
-                    }
-                    let tuple_element_name = format!("Item{}", index + 1);
-                    let field_descriptor = FieldDesc::new(
-                        tuple_type.as_class_ref().expect("Invalid tuple type"),
-                        ctx.alloc_string(tuple_element_name),
-                        element_type,
-                    );
-
-                    call_args.push(ld_field!(
-                        handle_operand(&last_arg.node, ctx),
-                        ctx.alloc_field(field_descriptor)
-                    ));
-                }
-
-                //todo!("Can't unbox tupels yet!")
-            }
-        }
-        _ => panic!("Can't unbox type {last_arg_type:?}!"),
-    }
+    call_args.extend(
+        unpack_tuple(&last_arg.node, ctx)
+            .into_iter()
+            .map(|(_, arg)| arg),
+    );
     //panic!("Last arg:{last_arg:?}last_arg_type:{last_arg_type:?}");
     //assert_eq!(args.len(),signature.inputs().len(),"CALL SIGNATURE ARG COUNT MISMATCH!");
     let is_void = matches!(sig.output(), cilly::Type::Void);
@@ -328,6 +593,46 @@ pub fn call_closure<'tcx>(
         place_set(destination, call!(call, call_args), ctx)
     }
 }
+/// Returns the object pointer and the vtable of the receiver of a virtual call.
+/// Both pointers to `dyn Trait` and `dyn*` values use the `FatPtrDyn` layout: the object pointer(or, for `dyn*`, the object itself) followed by the vtable.
+fn virtual_receiver<'tcx>(
+    receiver: &Operand<'tcx>,
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+) -> (V1Node, V1Node) {
+    let fat_ptr_dyn = ctx.alloc_string("FatPtrn3Dyn");
+    let fat_ptr_dyn = ctx.alloc_class_ref(ClassRef::new(fat_ptr_dyn, None, true, [].into()));
+    let vtable_ptr_field_desc = FieldDesc::new(
+        fat_ptr_dyn,
+        ctx.alloc_string(crate::METADATA),
+        Type::Int(Int::USize),
+    );
+    let receiver_ty = ctx.monomorphize(receiver.ty(ctx.body(), ctx.tcx()));
+    if receiver_ty
+        .builtin_deref(true)
+        .is_some_and(|pointed| pointed.is_dyn_star())
+    {
+        // A `&dyn* Trait` receiver: the object is stored inline, so the method gets its address.
+        let dyn_star_address = handle_operand(receiver, ctx);
+        let vtable_ptr = ld_field!(
+            dyn_star_address.clone(),
+            ctx.alloc_field(vtable_ptr_field_desc)
+        );
+        return (dyn_star_address.cast_ptr(ctx.nptr(Type::Void)), vtable_ptr);
+    }
+    let fat_ptr_address = operand_address(receiver, ctx);
+    let vtable_ptr = ld_field!(
+        fat_ptr_address.clone(),
+        ctx.alloc_field(vtable_ptr_field_desc)
+    );
+    let obj_ptr_field_desc = FieldDesc::new(
+        fat_ptr_dyn,
+        ctx.alloc_string(crate::DATA_PTR),
+        ctx.nptr(Type::Void),
+    );
+    // Get the addres of the object
+    let obj_ptr = ld_field!(fat_ptr_address, ctx.alloc_field(obj_ptr_field_desc));
+    (obj_ptr, vtable_ptr)
+}
 pub fn call_inner<'tcx>(
     fn_type: Ty<'tcx>,
     instance: Instance<'tcx>,
@@ -338,32 +643,13 @@ pub fn call_inner<'tcx>(
 ) -> Vec<V1Root> {
     if let rustc_middle::ty::InstanceKind::Virtual(_def, fn_idx) = instance.def {
         assert!(!args.is_empty());
-
-        let fat_ptr_address = operand_address(&args[0].node, ctx);
-        let fat_ptr_dyn = ctx.alloc_string("FatPtrn3Dyn");
-        let vtable_ptr_field_desc = FieldDesc::new(
-            ctx.alloc_class_ref(ClassRef::new(fat_ptr_dyn, None, true, [].into())),
-            ctx.alloc_string(crate::METADATA),
-            Type::Int(Int::USize),
-        );
-        let vtable_ptr = ld_field!(
-            fat_ptr_address.clone(),
-            ctx.alloc_field(vtable_ptr_field_desc)
-        );
+        let (obj_ptr, vtable_ptr) = virtual_receiver(&args[0].node, ctx);
 
         let vtable_index = V1Node::V2(
             ctx.alloc_node(i32::try_from(fn_idx).expect("More tahn 2^31 functions in a vtable!")),
         );
         let vtable_offset =
             conv_usize!(vtable_index * V1Node::V2(ctx.size_of(Int::ISize).into_idx(ctx)));
-        // Get the address of the function ptr, and load it
-        let obj_ptr_field_desc = FieldDesc::new(
-            ctx.alloc_class_ref(ClassRef::new(fat_ptr_dyn, None, true, [].into())),
-            ctx.alloc_string(crate::DATA_PTR),
-            ctx.nptr(Type::Void),
-        );
-        // Get the addres of the object
-        let obj_ptr = ld_field!(fat_ptr_address, ctx.alloc_field(obj_ptr_field_desc));
         // Get the call info
         let call_info = CallInfo::sig_from_instance_(instance, ctx);
 
@@ -452,8 +738,60 @@ pub fn call_inner<'tcx>(
             ctx,
         );
     }
-    let mut signature = call_info.sig().clone();
+    let signature = call_info.sig().clone();
     // Checks if function is "magic"
+    let tuple_call_kind = if function_name.contains(MANAGED_TUPLE_CTOR) {
+        Some(MethodKind::Constructor)
+    } else if function_name.contains(MANAGED_TUPLE_CALL_STATIC) {
+        Some(MethodKind::Static)
+    } else if function_name.contains(MANAGED_TUPLE_CALL_INSTANCE) {
+        Some(MethodKind::Instance)
+    } else if function_name.contains(MANAGED_TUPLE_CALL_VIRT) {
+        Some(MethodKind::Virtual)
+    } else {
+        None
+    };
+    let generic_call_kind = if function_name.contains(MANAGED_GENERIC_CTOR) {
+        Some(MethodKind::Constructor)
+    } else if function_name.contains(MANAGED_GENERIC_CALL_STATIC) {
+        Some(MethodKind::Static)
+    } else if function_name.contains(MANAGED_GENERIC_CALL_INSTANCE) {
+        Some(MethodKind::Instance)
+    } else if function_name.contains(MANAGED_GENERIC_CALL_VIRT) {
+        Some(MethodKind::Virtual)
+    } else {
+        None
+    };
+    if let Some(kind) = generic_call_kind {
+        assert!(
+            !call_info.split_last_tuple(),
+            "Managed calls may not use the `rust_call` calling convention!"
+        );
+        // Calls to generic managed methods, or methods of generic managed classes (for interop)
+        return vec![call_managed_generic(
+            instance.args,
+            kind,
+            args,
+            destination,
+            instance,
+            ctx,
+        )];
+    }
+    if let Some(kind) = tuple_call_kind {
+        assert!(
+            !call_info.split_last_tuple(),
+            "Managed calls may not use the `rust_call` calling convention!"
+        );
+        // Tuple-packed managed calls (for interop)
+        return vec![call_managed_tuple(
+            instance.args,
+            kind,
+            args,
+            destination,
+            instance,
+            ctx,
+        )];
+    }
     if function_name.contains(CTOR_FN_NAME) {
         assert!(
             !call_info.split_last_tuple(),
@@ -524,6 +862,12 @@ pub fn call_inner<'tcx>(
             ctx.alloc_node(Const::Null(tpe)).into(),
             ctx,
         )];
+    } else if function_name.contains(MANAGED_DELEGATE) {
+        assert!(
+            !call_info.split_last_tuple(),
+            "Managed calls may not use the `rust_call` calling convention!"
+        );
+        return vec![create_delegate(instance.args, args, destination, ctx)];
     } else if function_name.contains(MANAGED_CHECKED_CAST) {
         let tpe = ctx
             .type_from_cache(instance.args[0].as_type().unwrap())
@@ -562,6 +906,15 @@ pub fn call_inner<'tcx>(
             },
             ctx,
         )];
+    } else if function_name.contains(MANAGED_LD_FLD) {
+        let field = managed_field(instance, ctx);
+        let obj = handle_operand(&args[0].node, ctx);
+        return vec![place_set(destination, ld_field!(obj, field), ctx)];
+    } else if function_name.contains(MANAGED_ST_FLD) {
+        let field = managed_field(instance, ctx);
+        let obj = handle_operand(&args[0].node, ctx);
+        let val = handle_operand(&args[1].node, ctx);
+        return vec![V1Root::set_field(obj, val, field)];
     }
     if call_info.split_last_tuple() {
         return vec![call_closure(
@@ -578,15 +931,6 @@ pub fn call_inner<'tcx>(
         let res_calc = handle_operand(&arg.node, ctx);
         call_args.push(res_calc);
     }
-    if crate::function_sig::is_fn_variadic(fn_type, ctx.tcx()) {
-        signature.set_inputs(
-            args.iter()
-                .map(|operand| {
-                    ctx.type_from_cache(ctx.monomorphize(operand.node.ty(ctx.body(), ctx.tcx())))
-                })
-                .collect::<Box<_>>(),
-        );
-    }
     if args.len() < signature.inputs().len() {
         let tpe: cilly::Type = signature.inputs()[signature.inputs().len() - 1];
         // let arg_len = args.len();
@@ -37,6 +37,7 @@ mod mem;
 use mem::{copy, raw_eq, write_bytes};
 mod atomic;
 mod tpe;
+mod va_list;
 mod vtable;
 fn call_atomic<'tcx>(
     args: &[Spanned<Operand<'tcx>>],
@@ -190,6 +191,7 @@ pub fn handle_intrinsic<'tcx>(
             )]
         }
         "type_id" => vec![tpe::type_id(destination, call_instance, ctx)],
+        "va_arg" => vec![va_list::va_arg(args, destination, call_instance, ctx)],
         "atomic_load_acquire"
         | "atomic_load_seqcst"
         | "atomic_load_unordered"
@@ -0,0 +1,38 @@
+use crate::assembly::MethodCompileCtx;
+use cilly::{cil_root::V1Root, CILNode, CILRoot};
+use rustc_codegen_clr_place::place_address;
+use rustc_codegen_clr_type::GetTypeExt;
+use rustc_codgen_clr_operand::handle_operand;
+use rustc_middle::{
+    mir::{Operand, Place},
+    ty::Instance,
+};
+use rustc_span::source_map::Spanned;
+/// Reads the next additional argument of a C-variadic function from a `VaList`, and writes it to `destination`.
+/// `va_copy` and `va_end` need no special handling: their fallback bodies copy the `VaList`, and do nothing.
+pub fn va_arg<'tcx>(
+    args: &[Spanned<Operand<'tcx>>],
+    destination: &Place<'tcx>,
+    call_instance: Instance<'tcx>,
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+) -> V1Root {
+    debug_assert_eq!(
+        args.len(),
+        1,
+        "The intrinsic `va_arg` MUST take in exactly 1 argument!"
+    );
+    let arg_ty = ctx.monomorphize(
+        call_instance.args[0]
+            .as_type()
+            .expect("va_arg works only on types!"),
+    );
+    let arg_type = ctx.type_from_cache(arg_ty);
+    let arg_type = ctx.alloc_type(arg_type);
+    let list = handle_operand(&args[0].node, ctx);
+    let list = CILNode::from_v1(&list, ctx);
+    let list = ctx.alloc_node(list);
+    let dst = place_address(destination, ctx);
+    let dst = CILNode::from_v1(&dst, ctx);
+    let dst = ctx.alloc_node(dst);
+    V1Root::V2(ctx.alloc_root(CILRoot::VaArg(Box::new((list, dst, arg_type)))))
+}
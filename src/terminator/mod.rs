@@ -48,11 +48,11 @@ pub fn handle_call_terminator<'tycxt>(
             //eprintln!("\nCalling FnDef:{fn_ty:?}. call_ops:{call_ops:?}");
             trees.extend(call_ops.into_iter().map(std::convert::Into::into));
         }
-        TyKind::FnPtr(sig, _) => {
+        TyKind::FnPtr(sig, hdr) => {
             //eprintln!("Calling FnPtr:{func_ty:?}");
 
             let sig = ctx.tcx().instantiate_bound_regions_with_erased(*sig);
-            let sig = crate::function_sig::from_poly_sig(ctx, sig);
+            let sig = crate::function_sig::from_poly_sig(ctx, sig, hdr.c_variadic());
             let mut arg_operands = Vec::new();
             for arg in args {
                 arg_operands.push(handle_operand(&arg.node, ctx));
@@ -229,13 +229,17 @@ pub fn handle_terminator<'tcx>(
                 .into()]
             } else {
                 match ty.kind() {
-                    TyKind::Dynamic(_, _, rustc_middle::ty::DynKind::Dyn) => {
+                    TyKind::Dynamic(_, _, dyn_kind) => {
                         let fat_ptr_address = place_address(place, ctx);
-                        let fat_ptr_type = ctx.type_from_cache(Ty::new_ptr(
-                            ctx.tcx(),
-                            ty,
-                            rustc_middle::ty::Mutability::Mut,
-                        ));
+                        // A `dyn*` has the same layout as a pointer to `dyn`.
+                        let fat_ptr_type = match dyn_kind {
+                            rustc_middle::ty::DynKind::Dyn => ctx.type_from_cache(Ty::new_ptr(
+                                ctx.tcx(),
+                                ty,
+                                rustc_middle::ty::Mutability::Mut,
+                            )),
+                            rustc_middle::ty::DynKind::DynStar => ctx.type_from_cache(ty),
+                        };
                         let desc = FieldDesc::new(
                             fat_ptr_type.as_class_ref().unwrap(),
                             ctx.alloc_string(crate::METADATA),
@@ -245,12 +249,18 @@ pub fn handle_terminator<'tcx>(
                         let vtable_ptr = ld_field!(fat_ptr_address.clone(), ctx.alloc_field(desc));
                         let void_ptr = ctx.nptr(Type::Void);
                         // Get the addres of the object
-                        let desc = FieldDesc::new(
-                            fat_ptr_type.as_class_ref().unwrap(),
-                            ctx.alloc_string(crate::DATA_PTR),
-                            void_ptr,
-                        );
-                        let obj_ptr = ld_field!(fat_ptr_address, ctx.alloc_field(desc));
+                        let obj_ptr = match dyn_kind {
+                            rustc_middle::ty::DynKind::Dyn => {
+                                let desc = FieldDesc::new(
+                                    fat_ptr_type.as_class_ref().unwrap(),
+                                    ctx.alloc_string(crate::DATA_PTR),
+                                    void_ptr,
+                                );
+                                ld_field!(fat_ptr_address, ctx.alloc_field(desc))
+                            }
+                            // The object is stored inline in the `dyn*`, so it gets dropped in place.
+                            rustc_middle::ty::DynKind::DynStar => fat_ptr_address.cast_ptr(void_ptr),
+                        };
                         // We asusme the drop is the first method in the vtable
                         assert_eq!(
                             rustc_middle::ty::vtable::COMMON_VTABLE_ENTRIES_DROPINPLACE,
@@ -282,9 +292,6 @@ pub fn handle_terminator<'tcx>(
                             .into(),
                         ]
                     }
-                    TyKind::Dynamic(_, _, rustc_middle::ty::DynKind::DynStar) => {
-                        todo!("Can't drop dyn star yet!")
-                    }
                     _ => {
                         let sig =
                             crate::function_sig::sig_from_instance_(drop_instance, ctx).unwrap();
@@ -320,18 +327,7 @@ pub fn handle_terminator<'tcx>(
                 rustc_middle::ty::print::with_no_trimmed_paths! {V1Root::V2(ctx.alloc_root(cilly::CILRoot::Unreachable(msg))).into()},
             ]
         }
-        TerminatorKind::InlineAsm {
-            template: _,
-            operands: _,
-            options: _,
-            line_spans: _,
-            unwind: _,
-            targets: _,
-            asm_macro: _,
-        } => {
-            eprintln!("Inline assembly is not yet supported!");
-            vec![V1Root::throw("Inline assembly is not yet supported!", ctx).into()]
-        }
+        TerminatorKind::InlineAsm { .. } => crate::inline_asm::handle_inline_asm(terminator, ctx),
         TerminatorKind::UnwindTerminate(_) => {
             let loc = terminator.source_info.span;
             vec![
@@ -362,13 +358,14 @@ pub fn handle_terminator<'tcx>(
             }
             .into()]
         }
-        TerminatorKind::CoroutineDrop {} => todo!("Can't drop corutines yet!"),
-        TerminatorKind::Yield {
-            value: _,
-            resume: _,
-            resume_arg: _,
-            drop: _,
-        } => todo!("Can't yeld yet!"), //_ => todo!("Unhandled terminator kind {kind:?}", kind = terminator.kind),
+        // The `StateTransform` MIR pass turns each coroutine into a state machine: its body becomes a `resume` function,
+        // which switches on the state stored in the coroutine, and saves the state + returns instead of yielding.
+        // Dropping a coroutine goes trough its drop glue, built from the drop shim emitted by that pass.
+        // Those are compiled just like any other function, so those terminators never reach codegen.
+        TerminatorKind::CoroutineDrop | TerminatorKind::Yield { .. } => panic!(
+            "Internal error: coroutine terminator {kind:?} was not lowered by the `StateTransform` MIR pass!",
+            kind = terminator.kind
+        ),
     };
     let last = res.last().unwrap().root();
     assert!(
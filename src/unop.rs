@@ -4,14 +4,14 @@ use cilly::cil_node::V1Node;
 
 use cilly::cilnode::MethodKind;
 use cilly::{call, ld_field, Type};
-use cilly::{ClassRef, FieldDesc, Int, MethodRef};
+use cilly::{ClassRef, FieldDesc, Float, Int, MethodRef};
 
 use rustc_codegen_clr_type::r#type::get_type;
 
 use rustc_codgen_clr_operand::handle_operand;
 use rustc_middle::mir::Rvalue;
 use rustc_middle::mir::{Operand, UnOp};
-use rustc_middle::ty::{IntTy, TyKind, UintTy};
+use rustc_middle::ty::{FloatTy, IntTy, TyKind, UintTy};
 /// Implements an unary operation, such as negation.
 pub fn unop<'tcx>(
     unnop: UnOp,
@@ -45,6 +45,16 @@ pub fn unop<'tcx>(
                 );
                 call!(ctx.alloc_methodref(mref), [parrent_node])
             }
+            TyKind::Float(FloatTy::F128) => {
+                let mref = MethodRef::new(
+                    *ctx.main_module(),
+                    ctx.alloc_string("neg_f128"),
+                    ctx.sig([Type::Float(Float::F128)], Type::Float(Float::F128)),
+                    MethodKind::Static,
+                    vec![].into(),
+                );
+                call!(ctx.alloc_methodref(mref), [parrent_node])
+            }
             _ => V1Node::Neg(parrent_node.into()),
         },
         UnOp::Not => match ty.kind() {
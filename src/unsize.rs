@@ -16,7 +16,7 @@ use rustc_codgen_clr_operand::constant::get_vtable;
 use rustc_codgen_clr_operand::{handle_operand, operand_address};
 use rustc_middle::{
     mir::{Operand, Place},
-    ty::{layout::TyAndLayout, Ty, TyKind, UintTy},
+    ty::{layout::TyAndLayout, DynKind, Ty, TyKind, UintTy},
 };
 /// Preforms an unsizing cast on operand `operand`, converting it to the `target` type.
 pub fn unsize2<'tcx>(
@@ -180,6 +180,44 @@ fn unsized_info<'tcx>(
     }
 }
 
+/// Preforms a `dyn*` cast on operand `operand`, converting it to the `target` type. The operand, which is pointer-sized,
+/// is stored in place of the data pointer of a fat pointer to `dyn`, and the vtable of its type becomes the metadata.
+pub fn dyn_star<'tcx>(
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+    operand: &Operand<'tcx>,
+    target: Ty<'tcx>,
+) -> V1Node {
+    let target = ctx.monomorphize(target);
+    let source = ctx.monomorphize(operand.ty(ctx.body(), ctx.tcx()));
+    let TyKind::Dynamic(data, _, DynKind::DynStar) = target.kind() else {
+        panic!("Invalid dyn* cast target {target:?}");
+    };
+    let principal = data
+        .principal()
+        .map(|principal| ctx.tcx().instantiate_bound_regions_with_erased(principal));
+    let vtable = get_vtable(ctx, source, principal);
+    let obj = if source.is_any_ptr() {
+        handle_operand(operand, ctx)
+    } else {
+        // `source` is `PointerLike`, so its layout *should* be equivalent to a pointer.
+        let source_type = ctx.type_from_cache(source);
+        V1Node::transmute_on_stack(
+            handle_operand(operand, ctx),
+            source_type,
+            Type::Int(Int::USize),
+            ctx,
+        )
+    };
+    let target_type = ctx.type_from_cache(target);
+    V1Node::create_slice(
+        target_type
+            .as_class_ref()
+            .expect("dyn* type is not a fat pointer!"),
+        ctx,
+        V1Node::V2(vtable).cast_ptr(Type::Int(Int::USize)),
+        obj.cast_ptr(ctx.nptr(Type::Void)),
+    )
+}
 fn load_scalar_pair(addr: V1Node, ctx: &mut MethodCompileCtx<'_, '_>) -> (V1Node, V1Node) {
     (
         V1Node::LDIndUSize {
@@ -1,6 +1,6 @@
 use cilly::{
-    call, cil_node::V1Node, cil_root::V1Root, cilnode::MethodKind, eq, gt_un, Assembly, ClassRef,
-    Const, FieldDesc, Float, Int, Interned, MethodRef, Type,
+    call, cil_node::V1Node, cil_root::V1Root, cilnode::MethodKind, eq, gt_un, Assembly, BinOp,
+    ClassRef, Const, FieldDesc, Float, Int, Interned, MethodRef, Type,
 };
 use rustc_abi::{FieldIdx, FieldsShape, Layout, LayoutData, TagEncoding, VariantIdx, Variants};
 use rustc_middle::ty::Ty;
@@ -124,7 +124,7 @@ fn primitive_to_type(primitive: rustc_abi::Primitive, asm: &mut Assembly) -> Typ
         Primitive::Float(rustc_abi::Float::F16) => Type::Float(Float::F16),
         Primitive::Float(rustc_abi::Float::F32) => Type::Float(Float::F32),
         Primitive::Float(rustc_abi::Float::F64) => Type::Float(Float::F64),
-        Primitive::Float(rustc_abi::Float::F128) => todo!("No support for 128 bit floats yet!"),
+        Primitive::Float(rustc_abi::Float::F128) => Type::Float(Float::F128),
         Primitive::Pointer(_) => asm.nptr(Type::Void),
     }
 }
@@ -138,6 +138,40 @@ pub fn get_variant_at_index(
         Variants::Empty => todo!("Empty variants have no variants."),
     }
 }
+/// Loads `value` as a constant of the enum tag type `tag_tpe`.
+fn tag_const(value: u128, tag_tpe: Type, asm: &mut Assembly) -> V1Node {
+    match tag_tpe {
+        Type::Int(Int::U128) => V1Node::const_u128(value, asm),
+        Type::Int(Int::I128) => V1Node::const_i128(value, asm),
+        _ => {
+            let value = V1Node::V2(
+                asm.alloc_node(
+                    std::convert::TryInto::<u64>::try_into(value)
+                        .expect("Tag does not fit within a u64"),
+                ),
+            );
+            crate::casts::int_to_int(Type::Int(Int::U64), tag_tpe, value, asm)
+        }
+    }
+}
+/// Calls the builtin implementing `op` for 128 bit intiegers of type `int`.
+fn int128_op(
+    op: BinOp,
+    int: Int,
+    lhs: V1Node,
+    rhs: V1Node,
+    ret: Type,
+    asm: &mut Assembly,
+) -> V1Node {
+    let mref = MethodRef::new(
+        *asm.main_module(),
+        asm.alloc_string(format!("{op}_{int}", op = op.name(), int = int.name())),
+        asm.sig([Type::Int(int), Type::Int(int)], ret),
+        MethodKind::Static,
+        vec![].into(),
+    );
+    call!(asm.alloc_methodref(mref), [lhs, rhs])
+}
 pub fn set_discr<'tcx>(
     layout: Layout<'tcx>,
     variant_index: VariantIdx,
@@ -166,14 +200,11 @@ pub fn set_discr<'tcx>(
             ..
         } => {
             let (tag_tpe, _) = enum_tag_info(layout, ctx);
-            let tag_val = std::convert::TryInto::<u64>::try_into(
-                ty.discriminant_for_variant(ctx.tcx(), variant_index)
-                    .unwrap()
-                    .val,
-            )
-            .expect("Enum varaint id can't fit in u64.");
-            let tag_val = V1Node::V2(ctx.alloc_node(tag_val));
-            let tag_val = crate::casts::int_to_int(Type::Int(Int::U64), tag_tpe, tag_val, ctx);
+            let tag_val = ty
+                .discriminant_for_variant(ctx.tcx(), variant_index)
+                .unwrap()
+                .val;
+            let tag_val = tag_const(tag_val, tag_tpe, ctx);
             let enum_tag_name = ctx.alloc_string(crate::ENUM_TAG);
             V1Root::SetField {
                 addr: Box::new(enum_addr),
@@ -198,13 +229,7 @@ pub fn set_discr<'tcx>(
                 //let niche_llty = bx.cx().immediate_backend_type(niche.layout);
                 let niche_value = variant_index.as_u32() - niche_variants.start().as_u32();
                 let niche_value = u128::from(niche_value).wrapping_add(niche_start);
-                let tag_val = V1Node::V2(
-                    ctx.alloc_node(
-                        std::convert::TryInto::<u64>::try_into(niche_value)
-                            .expect("Enum varaint id can't fit in u64."),
-                    ),
-                );
-                let tag_val = crate::casts::int_to_int(Type::Int(Int::U64), tag_tpe, tag_val, ctx);
+                let tag_val = tag_const(niche_value, tag_tpe, ctx);
                 let enum_tag_name = ctx.alloc_string(crate::ENUM_TAG);
                 V1Root::SetField {
                     addr: Box::new(enum_addr),
@@ -231,13 +256,7 @@ pub fn get_discr<'tcx>(
             let discr_val = ty
                 .discriminant_for_variant(ctx.tcx(), index)
                 .map_or(u128::from(index.as_u32()), |discr| discr.val);
-            let tag_val = V1Node::V2(
-                ctx.alloc_node(
-                    std::convert::TryInto::<u64>::try_into(discr_val)
-                        .expect("Tag does not fit within a u64"),
-                ),
-            );
-            return crate::casts::int_to_int(Type::Int(Int::U64), tag_tpe, tag_val, ctx);
+            return tag_const(discr_val, tag_tpe, ctx);
         }
         Variants::Multiple {
             ref tag_encoding, ..
@@ -302,135 +321,49 @@ pub fn get_discr<'tcx>(
                 // } else {
                 //     untagged_variant
                 // }
-                let main_module = *ctx.main_module();
+                let niche_start = tag_const(niche_start, disrc_type, ctx);
                 let is_niche = match tag_tpe {
-                    Type::Int(Int::U128) => {
-                        let mref = MethodRef::new(
-                            main_module,
-                            ctx.alloc_string("eq_u128"),
-                            ctx.sig([Type::Int(Int::U128), Type::Int(Int::U128)], Type::Bool),
-                            MethodKind::Static,
-                            vec![].into(),
-                        );
-                        call!(
-                            ctx.alloc_methodref(mref),
-                            [
-                                tag,
-                                V1Node::const_u128(
-                                    u128::from(niche_variants.start().as_u32(),),
-                                    ctx
-                                )
-                            ]
-                        )
-                    }
-                    Type::Int(Int::I128) => {
-                        let mref = MethodRef::new(
-                            main_module,
-                            ctx.alloc_string("eq_i128"),
-                            ctx.sig([Type::Int(Int::I128), Type::Int(Int::I128)], Type::Bool),
-                            MethodKind::Static,
-                            vec![].into(),
-                        );
-                        call!(
-                            ctx.alloc_methodref(mref),
-                            [
-                                tag,
-                                V1Node::const_i128(
-                                    u128::from(niche_variants.start().as_u32()),
-                                    ctx
-                                )
-                            ]
-                        )
+                    Type::Int(int @ (Int::U128 | Int::I128)) => {
+                        int128_op(BinOp::Eq, int, tag, niche_start, Type::Bool, ctx)
                     }
-
-                    _ => eq!(
-                        tag,
-                        crate::casts::int_to_int(
-                            Type::Int(Int::U64),
-                            disrc_type,
-                            V1Node::V2(
-                                ctx.alloc_node(
-                                    std::convert::TryInto::<u64>::try_into(niche_start)
-                                        .expect("tag is too big to fit within u64")
-                                )
-                            ),
-                            ctx
-                        )
-                    ),
-                }; //bx.icmp(IntPredicate::IntEQ, tag, niche_start);
-
-                let tagged_discr = crate::casts::int_to_int(
-                    Type::Int(Int::U64),
-                    disrc_type,
-                    V1Node::V2(ctx.alloc_node(u64::from(niche_variants.start().as_u32()))),
-                    ctx,
-                );
+                    _ => eq!(tag, niche_start),
+                };
+                let tagged_discr =
+                    tag_const(u128::from(niche_variants.start().as_u32()), disrc_type, ctx);
                 (is_niche, tagged_discr, 0)
             } else {
                 // The special cases don't apply, so we'll have to go with
                 // the general algorithm.
                 //let tag = crate::casts::int_to_int(disrc_type.clone(), &Type::Int(Int::U64), tag);
+                let niche_start = tag_const(niche_start, disrc_type, ctx);
                 let relative_discr = match tag_tpe {
-                    Type::Int(Int::I128 | Int::U128) => {
-                        todo!("niche encoidng of 128 bit wide tags is not fully supported yet")
+                    Type::Int(int @ (Int::U128 | Int::I128)) => {
+                        int128_op(BinOp::Sub, int, tag, niche_start, tag_tpe, ctx)
                     }
-                    _ => V1Node::Sub(
-                        Box::new(tag),
-                        Box::new(crate::casts::int_to_int(
-                            Type::Int(Int::U64),
-                            disrc_type,
-                            V1Node::V2(
-                                ctx.alloc_node(
-                                    std::convert::TryInto::<u64>::try_into(niche_start)
-                                        .expect("tag is too big to fit within u64"),
-                                ),
-                            ),
-                            ctx,
-                        )),
-                    ),
+                    _ => V1Node::Sub(Box::new(tag), Box::new(niche_start)),
                 };
                 let gt = match tag_tpe {
-                    Type::Int(Int::U128) => {
-                        let mref = MethodRef::new(
-                            ClassRef::uint_128(ctx),
-                            ctx.alloc_string("op_GreaterThan"),
-                            ctx.sig([Type::Int(Int::U128), Type::Int(Int::U128)], Type::Bool),
-                            MethodKind::Static,
-                            vec![].into(),
-                        );
-                        call!(
-                            ctx.alloc_methodref(mref),
-                            [
-                                relative_discr.clone(),
-                                V1Node::const_u128(u128::from(relative_max), ctx)
-                            ]
-                        )
-                    }
-                    Type::Int(Int::I128) => {
-                        let mref = MethodRef::new(
-                            ClassRef::int_128(ctx),
-                            ctx.alloc_string("op_GreaterThan"),
-                            ctx.sig([Type::Int(Int::I128), Type::Int(Int::I128)], Type::Bool),
-                            MethodKind::Static,
-                            vec![].into(),
+                    // The relative tag is compared as an unsigned value, so that tags below `niche_start` wrap around.
+                    Type::Int(Int::U128 | Int::I128) => {
+                        let relative_discr = crate::casts::int_to_int(
+                            tag_tpe,
+                            Type::Int(Int::U128),
+                            relative_discr.clone(),
+                            ctx,
                         );
-                        call!(
-                            ctx.alloc_methodref(mref),
-                            [
-                                relative_discr.clone(),
-                                V1Node::const_i128(u128::from(relative_max), ctx)
-                            ]
+                        let relative_max = V1Node::const_u128(u128::from(relative_max), ctx);
+                        int128_op(
+                            BinOp::Gt,
+                            Int::U128,
+                            relative_discr,
+                            relative_max,
+                            Type::Bool,
+                            ctx,
                         )
                     }
-
                     _ => gt_un!(
                         relative_discr.clone(),
-                        crate::casts::int_to_int(
-                            Type::Int(Int::U64),
-                            disrc_type,
-                            V1Node::V2(ctx.alloc_node(u64::from(relative_max))),
-                            ctx
-                        )
+                        tag_const(u128::from(relative_max), disrc_type, ctx)
                     ),
                 };
                 let is_niche = eq!(gt, V1Node::V2(ctx.alloc_node(false)));
@@ -444,33 +377,26 @@ pub fn get_discr<'tcx>(
             let tagged_discr = if delta == 0 {
                 tagged_discr
             } else {
-                let delta = crate::casts::int_to_int(
-                    Type::Int(Int::U64),
-                    disrc_type,
-                    V1Node::V2(
-                        ctx.alloc_node(
-                            std::convert::TryInto::<u64>::try_into(delta)
-                                .expect("Tag does not fit within u64"),
-                        ),
-                    ),
-                    ctx,
-                );
-                assert!(matches!(
-                    disrc_type,
+                let delta = tag_const(delta, disrc_type, ctx);
+                match disrc_type {
+                    Type::Int(int @ (Int::U128 | Int::I128)) => {
+                        int128_op(BinOp::Add, int, tagged_discr, delta, disrc_type, ctx)
+                    }
                     Type::Int(
                         Int::U8
-                            | Int::I8
-                            | Int::U16
-                            | Int::I16
-                            | Int::U32
-                            | Int::I32
-                            | Int::U64
-                            | Int::I64
-                            | Int::USize
-                            | Int::ISize
-                    ) | Type::Ptr(_)
-                ));
-                tagged_discr + delta
+                        | Int::I8
+                        | Int::U16
+                        | Int::I16
+                        | Int::U32
+                        | Int::I32
+                        | Int::U64
+                        | Int::I64
+                        | Int::USize
+                        | Int::ISize,
+                    )
+                    | Type::Ptr(_) => tagged_discr + delta,
+                    _ => panic!("Unsuported niche tag type {disrc_type:?}"),
+                }
             };
 
             // In principle we could insert assumes on the possible range of `discr`, but
@@ -479,12 +405,7 @@ pub fn get_discr<'tcx>(
             V1Node::select(
                 disrc_type,
                 tagged_discr,
-                crate::casts::int_to_int(
-                    Type::Int(Int::U64),
-                    disrc_type,
-                    V1Node::V2(ctx.alloc_node(u64::from(untagged_variant.as_u32()))),
-                    ctx,
-                ),
+                tag_const(u128::from(untagged_variant.as_u32()), disrc_type, ctx),
                 is_niche,
                 ctx,
             )
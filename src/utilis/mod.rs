@@ -15,8 +15,29 @@ pub const MANAGED_LD_NULL: &str = "rustc_clr_interop_managed_ld_null";
 pub const MANAGED_CHECKED_CAST: &str = "rustc_clr_interop_managed_checked_cast";
 pub const MANAGED_IS_INST: &str = "rustc_clr_interop_managed_is_inst";
 pub const MANAGED_LD_ELEM_REF: &str = "rustc_clr_interop_managed_ld_elem_ref";
+pub const MANAGED_LD_FLD: &str = "rustc_clr_interop_managed_ld_fld";
+pub const MANAGED_ST_FLD: &str = "rustc_clr_interop_managed_st_fld";
+pub const MANAGED_TUPLE_CTOR: &str = "rustc_clr_interop_managed_tuple_ctor";
+pub const MANAGED_TUPLE_CALL_STATIC: &str = "rustc_clr_interop_managed_tuple_call_static";
+pub const MANAGED_TUPLE_CALL_INSTANCE: &str = "rustc_clr_interop_managed_tuple_call_instance";
+pub const MANAGED_TUPLE_CALL_VIRT: &str = "rustc_clr_interop_managed_tuple_call_virt";
+pub const MANAGED_DELEGATE: &str = "rustc_clr_interop_managed_delegate";
+pub const MANAGED_GENERIC_CTOR: &str = "rustc_clr_interop_managed_generic_ctor";
+pub const MANAGED_GENERIC_CALL_STATIC: &str = "rustc_clr_interop_managed_generic_call_static";
+pub const MANAGED_GENERIC_CALL_INSTANCE: &str = "rustc_clr_interop_managed_generic_call_instance";
+pub const MANAGED_GENERIC_CALL_VIRT: &str = "rustc_clr_interop_managed_generic_call_virt";
 pub fn is_function_magic(name: &str) -> bool {
-    name.contains(CTOR_FN_NAME) || name.contains(MANAGED_CALL_FN_NAME)
+    name.contains(CTOR_FN_NAME)
+        || name.contains(MANAGED_CALL_FN_NAME)
+        || name.contains(MANAGED_TUPLE_CTOR)
+        || name.contains(MANAGED_TUPLE_CALL_STATIC)
+        || name.contains(MANAGED_TUPLE_CALL_INSTANCE)
+        || name.contains(MANAGED_TUPLE_CALL_VIRT)
+        || name.contains(MANAGED_DELEGATE)
+        || name.contains(MANAGED_GENERIC_CTOR)
+        || name.contains(MANAGED_GENERIC_CALL_STATIC)
+        || name.contains(MANAGED_GENERIC_CALL_INSTANCE)
+        || name.contains(MANAGED_GENERIC_CALL_VIRT)
 }
 
 // WARNING: this is *wrong*: For some reason, `Instance::try_resolve` should not operate on structs(why?), and this just silences the newly introduced warning.
@@ -0,0 +1,39 @@
+#![feature(
+    lang_items,
+    adt_const_params,
+    associated_type_defaults,
+    core_intrinsics,
+    unsized_const_params,
+    rustc_attrs
+)]
+#![allow(internal_features, incomplete_features, unused_variables, dead_code)]
+// Only `changed` differs between the two sessions, so `unchanged` must come from the incremental cache.
+#![rustc_partition_reused(module = "incremental-unchanged", cfg = "second")]
+#![rustc_partition_codegened(module = "incremental-changed", cfg = "second")]
+include!("../common.rs");
+mod unchanged {
+    #[inline(never)]
+    pub fn triple(val: u32) -> u32 {
+        val * 3
+    }
+    pub static PRIMES: [u32; 4] = [2, 3, 5, 7];
+}
+mod changed {
+    #[cfg(first)]
+    #[inline(never)]
+    pub fn offset() -> u32 {
+        1
+    }
+    #[cfg(second)]
+    #[inline(never)]
+    pub fn offset() -> u32 {
+        2
+    }
+}
+fn main() {
+    let expected = if cfg!(second) { 21 } else { 20 };
+    test_eq!(
+        unchanged::triple(black_box(4)) + unchanged::PRIMES[3] + changed::offset(),
+        expected
+    );
+}
@@ -0,0 +1,99 @@
+#![feature(
+    lang_items,
+    adt_const_params,
+    associated_type_defaults,
+    core_intrinsics,
+    unsized_const_params
+)]
+#![allow(internal_features, incomplete_features, unused_variables, dead_code)]
+include!("../common.rs");
+// Each module gets its own codegen unit, so calls, statics, vtables and generics all cross assembly boundaries, and
+// only work if the linker stitches the codegen units back together.
+mod shapes {
+    pub trait Shape {
+        fn area(&self) -> u32;
+        fn name(&self) -> &'static str;
+    }
+    pub struct Rect {
+        pub w: u32,
+        pub h: u32,
+    }
+    impl Shape for Rect {
+        fn area(&self) -> u32 {
+            self.w * self.h
+        }
+        fn name(&self) -> &'static str {
+            "rect"
+        }
+    }
+    pub struct Square(pub u32);
+    impl Shape for Square {
+        fn area(&self) -> u32 {
+            self.0 * self.0
+        }
+        fn name(&self) -> &'static str {
+            "square"
+        }
+    }
+    pub fn boxed(square: bool, side: u32) -> Box<dyn Shape> {
+        if square {
+            Box::new(Square(side))
+        } else {
+            Box::new(Rect {
+                w: side,
+                h: side + 1,
+            })
+        }
+    }
+}
+mod counters {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    pub static CALLS: AtomicU32 = AtomicU32::new(0);
+    pub static TABLE: [u32; 4] = [1, 2, 4, 8];
+    #[inline(never)]
+    pub fn bump() -> u32 {
+        CALLS.fetch_add(1, Ordering::SeqCst) + 1
+    }
+    // Instantiated in every codegen unit which calls it, and merged again when linking.
+    #[inline]
+    pub fn sum<T: Copy + Into<u64>>(items: &[T]) -> u64 {
+        items.iter().map(|item| (*item).into()).sum()
+    }
+}
+mod users {
+    use super::counters;
+    use super::shapes;
+    #[inline(never)]
+    pub fn total_area(sides: &[u32]) -> u32 {
+        counters::bump();
+        sides
+            .iter()
+            .enumerate()
+            .map(|(idx, side)| shapes::boxed(idx % 2 == 0, *side).area())
+            .sum()
+    }
+    #[inline(never)]
+    pub fn names(sides: &[u32]) -> String {
+        counters::bump();
+        let mut names = String::new();
+        for (idx, side) in sides.iter().enumerate() {
+            names.push_str(shapes::boxed(idx % 2 == 0, *side).name());
+        }
+        names
+    }
+    #[inline(never)]
+    pub fn table_sum() -> u64 {
+        counters::bump();
+        counters::sum(&counters::TABLE)
+    }
+}
+fn main() {
+    let sides = black_box([2_u32, 3, 4]);
+    // 2*2 + 3*4 + 4*4
+    test_eq!(users::total_area(&sides), 32);
+    test_eq!(users::names(&sides), "squarerectsquare");
+    test_eq!(users::table_sum(), 15);
+    test_eq!(counters::sum(&[black_box(1_u8), 2, 3]), 6);
+    test_eq!(counters::CALLS.load(std::sync::atomic::Ordering::SeqCst), 3);
+    test_eq!(counters::bump(), 4);
+}
@@ -0,0 +1,70 @@
+#![feature(
+    lang_items,
+    adt_const_params,
+    associated_type_defaults,
+    core_intrinsics,
+    unsized_const_params,
+    coroutines,
+    coroutine_trait,
+    stmt_expr_attributes
+)]
+#![allow(
+    internal_features,
+    incomplete_features,
+    unused_variables,
+    dead_code,
+    unused_imports
+)]
+include!("../common.rs");
+use core::ops::{Coroutine, CoroutineState};
+use core::pin::pin;
+struct DecrementOnDrop<'a>(&'a mut u32);
+impl Drop for DecrementOnDrop<'_> {
+    fn drop(&mut self) {
+        *self.0 -= 1;
+    }
+}
+fn main() {
+    // Locals live across a yield are saved in the coroutine.
+    let step = black_box(3_u64);
+    let mut counter = pin!(
+        #[coroutine]
+        move |start: u64| {
+            let mut acc = start;
+            let mut next = yield acc;
+            acc += next * step;
+            next = yield acc;
+            acc += next * step;
+            acc
+        }
+    );
+    match counter.as_mut().resume(black_box(1)) {
+        CoroutineState::Yielded(val) => test_eq!(val, 1),
+        CoroutineState::Complete(_) => test!(false),
+    }
+    match counter.as_mut().resume(black_box(2)) {
+        CoroutineState::Yielded(val) => test_eq!(val, 7),
+        CoroutineState::Complete(_) => test!(false),
+    }
+    match counter.as_mut().resume(black_box(4)) {
+        CoroutineState::Yielded(_) => test!(false),
+        CoroutineState::Complete(val) => test_eq!(val, 19),
+    }
+    // Dropping a suspended coroutine drops the locals saved in it.
+    let mut count = 1;
+    {
+        let mut suspended = pin!(
+            #[coroutine]
+            || {
+                let guard = DecrementOnDrop(&mut count);
+                yield;
+                black_box(guard);
+            }
+        );
+        match suspended.as_mut().resume(()) {
+            CoroutineState::Yielded(()) => (),
+            CoroutineState::Complete(()) => test!(false),
+        }
+    }
+    test_eq!(black_box(count), 0);
+}
@@ -0,0 +1,40 @@
+#![feature(
+    lang_items,
+    adt_const_params,
+    associated_type_defaults,
+    core_intrinsics,
+    unsized_const_params
+)]
+#![allow(internal_features, incomplete_features, unused_variables, dead_code)]
+include!("../common.rs");
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+/// Records the order in which the thread locals were dropped.
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+struct Guard(usize);
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let prev = DROPPED.load(Ordering::SeqCst);
+        DROPPED.store(prev * 10 + self.0, Ordering::SeqCst);
+    }
+}
+thread_local! {
+    static COUNTER: Cell<u32> = Cell::new(black_box(7));
+    static FIRST: Guard = Guard(black_box(1));
+    static SECOND: Guard = Guard(black_box(2));
+}
+fn main() {
+    COUNTER.with(|counter| counter.set(counter.get() + 1));
+    test_eq!(COUNTER.with(Cell::get), 8);
+    let handle = std::thread::spawn(|| {
+        // Each thread gets its own, lazily initialized, copy of a thread local.
+        test_eq!(COUNTER.with(Cell::get), 7);
+        FIRST.with(|guard| test_eq!(guard.0, 1));
+        SECOND.with(|guard| test_eq!(guard.0, 2));
+        test_eq!(DROPPED.load(Ordering::SeqCst), 0);
+    });
+    handle.join().unwrap();
+    // Destructors run when the thread exits, in the reverse order of initialization.
+    test_eq!(DROPPED.load(Ordering::SeqCst), 21);
+    test_eq!(COUNTER.with(Cell::get), 8);
+}
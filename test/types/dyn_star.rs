@@ -0,0 +1,47 @@
+#![feature(
+    lang_items,
+    adt_const_params,
+    associated_type_defaults,
+    core_intrinsics,
+    unsized_const_params,
+    dyn_star
+)]
+#![allow(
+    internal_features,
+    incomplete_features,
+    unused_variables,
+    dead_code
+)]
+include!("../common.rs");
+pub trait Get {
+    fn get(&self) -> usize;
+    fn add(&self, other: usize) -> usize;
+}
+impl Get for usize {
+    fn get(&self) -> usize {
+        *self
+    }
+    fn add(&self, other: usize) -> usize {
+        *self + other
+    }
+}
+impl Get for &u32 {
+    fn get(&self) -> usize {
+        **self as usize
+    }
+    fn add(&self, other: usize) -> usize {
+        **self as usize + other
+    }
+}
+fn get_dyn_star(val: dyn* Get) -> usize {
+    val.get()
+}
+fn main() {
+    let val: dyn* Get = black_box(64_usize) as dyn* Get;
+    test_eq!(val.get(), 64);
+    test_eq!(val.add(black_box(8)), 72);
+    let num = black_box(33_u32);
+    let val: dyn* Get = (&num) as dyn* Get;
+    test_eq!(val.get(), 33);
+    test_eq!(get_dyn_star(black_box(7_usize) as dyn* Get), 7);
+}
@@ -11,14 +11,41 @@ include!("../common.rs");
 fn main() {
     // 1st. Check that a const f128 can be created.
     let zero = black_box(0_f128);
-    let one = black_box(0_f128);
-    let two = black_box(0_f128);
+    let one = black_box(1_f128);
+    let two = black_box(2_f128);
+    let half = black_box(0.5_f128);
     // Check that addtion works
     test_eq!(black_box(one + one), two);
     // Check that subtraction works
     test_eq!(black_box(one - one), zero);
+    test_eq!(black_box(one - two), black_box(-one));
     // Check that multiplaction works
     test_eq!(black_box(one * one), one);
+    test_eq!(black_box(two * half), one);
     // Check that division works
     test_eq!(black_box(two / one), two);
+    test_eq!(black_box(one / two), half);
+    // Check that reminders work
+    test_eq!(black_box(black_box(7_f128) % two), one);
+    // Check that comparisons work
+    test!(one < two);
+    test!(two > one);
+    test!(one <= one);
+    test!(two >= one);
+    test!(-one < zero);
+    test!(black_box(-0.0_f128) == zero);
+    let nan = black_box(zero / zero);
+    test!(nan != nan);
+    test!(!(nan < one));
+    test!(!(nan >= one));
+    test!((one / zero) > two);
+    // Check that casts work
+    test_eq!(black_box(two as f64), 2.0_f64);
+    test_eq!(black_box(0.1_f64 as f128) as f64, 0.1_f64);
+    test_eq!(black_box(-3.0_f32 as f128), black_box(-3_f128));
+    test_eq!(black_box(-3.75_f128) as i32, -3);
+    test_eq!(black_box(1e40_f128) as u64, u64::MAX);
+    test_eq!(nan as i8, 0);
+    test_eq!(black_box(u128::MAX) as f128 as u128, u128::MAX);
+    test_eq!(black_box(-7_i64) as f128, black_box(-7_f128));
 }
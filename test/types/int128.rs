@@ -15,6 +15,34 @@
 )]
 #![feature(unboxed_closures)]
 include!("../common.rs");
+#[repr(u128)]
+#[derive(Clone, Copy, PartialEq)]
+enum Wide {
+    A = 0,
+    B = 1,
+}
+// `Wide` only uses 2 values of its 128 bit tag, so the tags of `Niched` are stored in its niche.
+#[derive(Clone, Copy)]
+enum Niched {
+    Wide(Wide),
+    C,
+    D,
+}
+#[repr(i128)]
+#[derive(Clone, Copy)]
+enum Signed {
+    Min = i128::MIN,
+    Big = 0x1_0000_0000_0000_0000,
+    Zero = 0,
+}
+fn niche_discr(val: Niched) -> u8 {
+    match val {
+        Niched::Wide(Wide::A) => 0,
+        Niched::Wide(Wide::B) => 1,
+        Niched::C => 2,
+        Niched::D => 3,
+    }
+}
 fn main() {
     let val = black_box(0xDEAD_BEFF_DEAD_C0FFE_BEFF_BABE_i128);
     let x = match val {
@@ -23,4 +51,43 @@ fn main() {
         _ => black_box(11),
     };
     black_box(x);
+    // Switches on values, which don't fit in 64 bits.
+    let x = match val {
+        0xDEAD_BEFF_DEAD_C0FFE_BEFF_BABE_i128 => black_box(1),
+        -1 => black_box(2),
+        _ => black_box(3),
+    };
+    test_eq!(x, 1);
+    let x = match black_box(u128::MAX) {
+        0 => black_box(1),
+        0xFFFF_FFFF_FFFF_FFFF => black_box(2),
+        u128::MAX => black_box(3),
+        _ => black_box(4),
+    };
+    test_eq!(x, 3);
+    // Casts to and from 128 bit intiegers.
+    let ptr = black_box(&val) as *const i128;
+    test_eq!(black_box(ptr as u128) as usize, ptr as usize);
+    test_eq!(black_box('a') as u128, 97);
+    test_eq!(black_box(true) as i128, 1);
+    test_eq!(black_box(-1_i8) as u128, u128::MAX);
+    test_eq!(black_box(-1_isize) as i128, -1);
+    test_eq!(black_box(u128::MAX) as i128, -1);
+    test_eq!(black_box(0x1_0000_0001_u128) as u32, 1);
+    test_eq!(black_box(-2_i128) as u8, 254);
+    test_eq!(black_box(1_u128 << 64) as f64, 18446744073709551616.0);
+    test_eq!(black_box(-1.5_f32) as i128, -1);
+    test_eq!(black_box(-1.5_f32) as u128, 0);
+    test_eq!(black_box(f64::NAN) as i128, 0);
+    // Niche-encoded enums with 128 bit wide tags.
+    test_eq!(niche_discr(black_box(Niched::Wide(Wide::A))), 0);
+    test_eq!(niche_discr(black_box(Niched::Wide(Wide::B))), 1);
+    test_eq!(niche_discr(black_box(Niched::C)), 2);
+    test_eq!(niche_discr(black_box(Niched::D)), 3);
+    test!(black_box(Some(Wide::B)) == Some(Wide::B));
+    test!(black_box(None::<Wide>).is_none());
+    // Enums with 128 bit wide tags, which don't fit in 64 bits.
+    test_eq!(black_box(Signed::Min) as i128, i128::MIN);
+    test_eq!(black_box(Signed::Big) as i128, 0x1_0000_0000_0000_0000);
+    test_eq!(black_box(Signed::Zero) as i128, 0);
 }
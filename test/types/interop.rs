@@ -3,7 +3,8 @@
     adt_const_params,
     associated_type_defaults,
     core_intrinsics,
-    unsized_const_params
+    unsized_const_params,
+    tuple_trait
 )]
 #![allow(
     internal_features,
@@ -131,6 +132,64 @@ fn rustc_clr_interop_managed_ctor3_<
 ) -> RustcCLRInteropManagedClass<ASSEMBLY, CLASS_PATH> {
     core::intrinsics::abort();
 }
+#[allow(dead_code)]
+#[inline(never)]
+fn rustc_clr_interop_managed_tuple_ctor<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    Args: core::marker::Tuple,
+>(
+    args: Args,
+) -> RustcCLRInteropManagedClass<ASSEMBLY, CLASS_PATH> {
+    core::intrinsics::abort();
+}
+#[allow(dead_code)]
+#[inline(never)]
+fn rustc_clr_interop_managed_tuple_call_static<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const METHOD: &'static str,
+    Ret,
+    Args: core::marker::Tuple,
+>(
+    args: Args,
+) -> Ret {
+    core::intrinsics::abort();
+}
+#[allow(dead_code)]
+#[inline(never)]
+fn rustc_clr_interop_managed_tuple_call_instance<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const METHOD: &'static str,
+    Ret,
+    This,
+    Args: core::marker::Tuple,
+>(
+    this: This,
+    args: Args,
+) -> Ret {
+    core::intrinsics::abort();
+}
+#[allow(dead_code)]
+#[inline(never)]
+fn rustc_clr_interop_managed_tuple_call_virt<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const METHOD: &'static str,
+    Ret,
+    This,
+    Args: core::marker::Tuple,
+>(
+    this: This,
+    args: Args,
+) -> Ret {
+    core::intrinsics::abort();
+}
 //struct RustcCLRInteropManagedBool;
 fn main() {
     let chr: *mut RustcCLRInteropManagedChar = core::ptr::null_mut();
@@ -158,6 +217,7 @@ fn main() {
     //test_ctors();
     console_writeline();
     new_helloworld();
+    tuple_calls();
 }
 fn test_ctors() {
     let obj = black_box(rustc_clr_interop_managed_ctor0_::<
@@ -212,3 +272,102 @@ fn new_helloworld() {
         RustcCLRInteropManagedChar,
     >(sb, m_char);
 }
+fn tuple_calls() {
+    // Constructors, instance, static and virtual methods with arguments packed in a tuple.
+    let sb = rustc_clr_interop_managed_tuple_ctor::<
+        "System.Runtime",
+        "System.Text.StringBuilder",
+        false,
+        (i32,),
+    >((16,));
+    let m_char = unsafe { core::mem::transmute::<u16, RustcCLRInteropManagedChar>(black_box(64)) };
+    let sb = rustc_clr_interop_managed_tuple_call_instance::<
+        "System.Runtime",
+        "System.Text.StringBuilder",
+        false,
+        "Append",
+        StringBuilder,
+        StringBuilder,
+        (RustcCLRInteropManagedChar, i32),
+    >(sb, (m_char, 3));
+    let string = rustc_clr_interop_managed_tuple_call_virt::<
+        "System.Runtime",
+        "System.Object",
+        false,
+        "ToString",
+        MString,
+        StringBuilder,
+        (),
+    >(sb, ());
+    // A method taking 4 arguments: "@@@" repeated 4 times.
+    let string = rustc_clr_interop_managed_tuple_call_static::<
+        "System.Runtime",
+        "System.String",
+        false,
+        "Concat",
+        MString,
+        (MString, MString, MString, MString),
+    >((string, string, string, string));
+    let len = rustc_clr_interop_managed_tuple_call_instance::<
+        "System.Runtime",
+        "System.String",
+        false,
+        "get_Length",
+        i32,
+        MString,
+        (),
+    >(string, ());
+    test_eq!(len, 12);
+    // 16 arguments, so the fields of the tuple past `Item9` are used too. The method is `sum16`, defined below.
+    let args = (
+        1_u32,
+        2,
+        3,
+        4,
+        5,
+        6,
+        7,
+        8,
+        9,
+        10,
+        11,
+        12,
+        13,
+        14,
+        15,
+        black_box(16),
+    );
+    let sum = rustc_clr_interop_managed_tuple_call_static::<"", "MainModule", false, "sum16", u32, _>(
+        args,
+    );
+    test_eq!(sum, 136);
+}
+/// Sums its arguments, but only counts the ones passed in the right position.
+#[no_mangle]
+#[inline(never)]
+pub extern "C" fn sum16(
+    a0: u32,
+    a1: u32,
+    a2: u32,
+    a3: u32,
+    a4: u32,
+    a5: u32,
+    a6: u32,
+    a7: u32,
+    a8: u32,
+    a9: u32,
+    a10: u32,
+    a11: u32,
+    a12: u32,
+    a13: u32,
+    a14: u32,
+    a15: u32,
+) -> u32 {
+    [
+        a0, a1, a2, a3, a4, a5, a6, a7, a8, a9, a10, a11, a12, a13, a14, a15,
+    ]
+    .iter()
+    .enumerate()
+    .map(|(idx, arg)| if *arg == idx as u32 + 1 { *arg } else { 1000 })
+    .sum()
+}
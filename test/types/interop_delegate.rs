@@ -0,0 +1,137 @@
+#![feature(
+    lang_items,
+    adt_const_params,
+    core_intrinsics,
+    unsized_const_params,
+    tuple_trait,
+    unboxed_closures,
+    fn_traits
+)]
+#![allow(
+    internal_features,
+    incomplete_features,
+    unused_variables,
+    dead_code,
+    improper_ctypes_definitions,
+    improper_ctypes
+)]
+include!("../common.rs");
+use core::marker::Tuple;
+use core::sync::atomic::{AtomicI32, Ordering};
+#[derive(Clone, Copy)]
+struct RustcCLRInteropManagedClass<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str> {
+    prevent_construction: usize,
+}
+struct RustcCLRInteropManagedGenericClass<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    Generics: Tuple,
+> {
+    prevent_construction: usize,
+    pd: core::marker::PhantomData<Generics>,
+}
+impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str, Generics: Tuple> Clone
+    for RustcCLRInteropManagedGenericClass<ASSEMBLY, CLASS_PATH, Generics>
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str, Generics: Tuple> Copy
+    for RustcCLRInteropManagedGenericClass<ASSEMBLY, CLASS_PATH, Generics>
+{
+}
+struct RustcCLRInteropManagedTypeGeneric<const INDEX: u32>;
+type Action = RustcCLRInteropManagedClass<"System.Runtime", "System.Action">;
+type Func<T, R> = RustcCLRInteropManagedGenericClass<"System.Runtime", "System.Func", (T, R)>;
+#[inline(never)]
+fn rustc_clr_interop_managed_delegate<Delegate, Args: Tuple, Ret>(
+    invoke: *const (),
+    drop: *const (),
+    data: *mut (),
+) -> Delegate {
+    core::intrinsics::abort();
+}
+#[inline(never)]
+fn rustc_clr_interop_managed_tuple_call_virt<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const METHOD: &'static str,
+    Ret,
+    This,
+    Args: Tuple,
+>(
+    this: This,
+    args: Args,
+) -> Ret {
+    core::intrinsics::abort();
+}
+#[inline(never)]
+fn rustc_clr_interop_managed_generic_call_virt<
+    Class,
+    const METHOD: &'static str,
+    MethodGenerics: Tuple,
+    Sig,
+    Ret,
+    This,
+    Args: Tuple,
+>(
+    this: This,
+    args: Args,
+) -> Ret {
+    core::intrinsics::abort();
+}
+extern "rust-call" fn invoke_closure<F: Fn<Args, Output = Ret>, Args: Tuple, Ret>(
+    closure: *const F,
+    args: Args,
+) -> Ret {
+    unsafe { (*closure).call(args) }
+}
+unsafe fn drop_closure<F>(closure: *mut ()) {
+    drop(unsafe { Box::from_raw(closure.cast::<F>()) });
+}
+fn delegate<Delegate, Args: Tuple, Ret, F: Fn<Args, Output = Ret> + Send + Sync + 'static>(
+    closure: F,
+) -> Delegate {
+    let invoke = invoke_closure::<F, Args, Ret> as extern "rust-call" fn(*const F, Args) -> Ret;
+    let drop = drop_closure::<F> as unsafe fn(*mut ());
+    let data = Box::into_raw(Box::new(closure)).cast::<()>();
+    rustc_clr_interop_managed_delegate::<Delegate, Args, Ret>(
+        invoke as *const (),
+        drop as *const (),
+        data,
+    )
+}
+static CALLS: AtomicI32 = AtomicI32::new(0);
+fn main() {
+    // A closure without captures, called through `Action.Invoke()`.
+    let action: Action = delegate(|| {
+        CALLS.fetch_add(1, Ordering::Relaxed);
+    });
+    for _ in 0..3 {
+        rustc_clr_interop_managed_tuple_call_virt::<
+            "System.Runtime",
+            "System.Action",
+            false,
+            "Invoke",
+            (),
+            Action,
+            (),
+        >(action, ());
+    }
+    test_eq!(CALLS.load(Ordering::Relaxed), 3);
+    // A closure capturing its environment, called through `Func<int, int>.Invoke(!0)`.
+    let offset = black_box(40);
+    let add: Func<i32, i32> = delegate(move |value: i32| value + offset);
+    let sum = rustc_clr_interop_managed_generic_call_virt::<
+        Func<i32, i32>,
+        "Invoke",
+        (),
+        fn(RustcCLRInteropManagedTypeGeneric<0>) -> RustcCLRInteropManagedTypeGeneric<1>,
+        i32,
+        Func<i32, i32>,
+        (i32,),
+    >(add, (2,));
+    test_eq!(sum, 42);
+}
@@ -0,0 +1,206 @@
+#![feature(
+    lang_items,
+    adt_const_params,
+    associated_type_defaults,
+    core_intrinsics,
+    unsized_const_params,
+    tuple_trait
+)]
+#![allow(
+    internal_features,
+    incomplete_features,
+    unused_variables,
+    dead_code,
+    improper_ctypes_definitions,
+    improper_ctypes
+)]
+include!("../common.rs");
+use core::marker::Tuple;
+#[derive(Clone, Copy)]
+struct RustcCLRInteropManagedClass<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str> {
+    prevent_construction: usize,
+}
+struct RustcCLRInteropManagedGenericClass<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    Generics: Tuple,
+> {
+    prevent_construction: usize,
+    pd: core::marker::PhantomData<Generics>,
+}
+impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str, Generics: Tuple> Clone
+    for RustcCLRInteropManagedGenericClass<ASSEMBLY, CLASS_PATH, Generics>
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str, Generics: Tuple> Copy
+    for RustcCLRInteropManagedGenericClass<ASSEMBLY, CLASS_PATH, Generics>
+{
+}
+struct RustcCLRInteropManagedTypeGeneric<const INDEX: u32>;
+struct RustcCLRInteropManagedMethodGeneric<const INDEX: u32>;
+type TypeGeneric<const INDEX: u32> = RustcCLRInteropManagedTypeGeneric<INDEX>;
+type MethodGeneric<const INDEX: u32> = RustcCLRInteropManagedMethodGeneric<INDEX>;
+type List<T> = RustcCLRInteropManagedGenericClass<
+    "System.Collections",
+    "System.Collections.Generic.List",
+    (T,),
+>;
+type Dictionary<K, V> = RustcCLRInteropManagedGenericClass<
+    "System.Collections",
+    "System.Collections.Generic.Dictionary",
+    (K, V),
+>;
+type IEnumerable<T> = RustcCLRInteropManagedGenericClass<
+    "System.Runtime",
+    "System.Collections.Generic.IEnumerable",
+    (T,),
+>;
+type Enumerable = RustcCLRInteropManagedClass<"System.Linq", "System.Linq.Enumerable">;
+#[inline(never)]
+fn rustc_clr_interop_managed_generic_ctor<Class, Sig, Args: Tuple>(args: Args) -> Class {
+    core::intrinsics::abort();
+}
+#[inline(never)]
+fn rustc_clr_interop_managed_generic_call_static<
+    Class,
+    const METHOD: &'static str,
+    MethodGenerics: Tuple,
+    Sig,
+    Ret,
+    Args: Tuple,
+>(
+    args: Args,
+) -> Ret {
+    core::intrinsics::abort();
+}
+#[inline(never)]
+fn rustc_clr_interop_managed_generic_call_instance<
+    Class,
+    const METHOD: &'static str,
+    MethodGenerics: Tuple,
+    Sig,
+    Ret,
+    This,
+    Args: Tuple,
+>(
+    this: This,
+    args: Args,
+) -> Ret {
+    core::intrinsics::abort();
+}
+#[inline(never)]
+fn rustc_clr_interop_managed_checked_cast<DST, SRC>(src: SRC) -> DST {
+    core::intrinsics::abort();
+}
+fn main() {
+    list();
+    dictionary();
+    generic_methods();
+}
+fn list() {
+    // new List<int>(4)
+    let list = rustc_clr_interop_managed_generic_ctor::<List<i32>, fn(i32), (i32,)>((4,));
+    for value in [3, 5, 8] {
+        // List<int>.Add(!0)
+        rustc_clr_interop_managed_generic_call_instance::<
+            List<i32>,
+            "Add",
+            (),
+            fn(TypeGeneric<0>),
+            (),
+            List<i32>,
+            (i32,),
+        >(list, (black_box(value),));
+    }
+    let count = rustc_clr_interop_managed_generic_call_instance::<
+        List<i32>,
+        "get_Count",
+        (),
+        fn() -> i32,
+        i32,
+        List<i32>,
+        (),
+    >(list, ());
+    test_eq!(count, 3);
+    // !0 List<int>.get_Item(int32)
+    let item = rustc_clr_interop_managed_generic_call_instance::<
+        List<i32>,
+        "get_Item",
+        (),
+        fn(i32) -> TypeGeneric<0>,
+        i32,
+        List<i32>,
+        (i32,),
+    >(list, (1,));
+    test_eq!(item, 5);
+}
+fn dictionary() {
+    let dict = rustc_clr_interop_managed_generic_ctor::<Dictionary<i32, u64>, fn(), ()>(());
+    rustc_clr_interop_managed_generic_call_instance::<
+        Dictionary<i32, u64>,
+        "Add",
+        (),
+        fn(TypeGeneric<0>, TypeGeneric<1>),
+        (),
+        Dictionary<i32, u64>,
+        (i32, u64),
+    >(dict, (black_box(7), 49));
+    let contains = rustc_clr_interop_managed_generic_call_instance::<
+        Dictionary<i32, u64>,
+        "ContainsKey",
+        (),
+        fn(TypeGeneric<0>) -> bool,
+        bool,
+        Dictionary<i32, u64>,
+        (i32,),
+    >(dict, (7,));
+    test!(contains);
+    let value = rustc_clr_interop_managed_generic_call_instance::<
+        Dictionary<i32, u64>,
+        "get_Item",
+        (),
+        fn(TypeGeneric<0>) -> TypeGeneric<1>,
+        u64,
+        Dictionary<i32, u64>,
+        (i32,),
+    >(dict, (7,));
+    test_eq!(value, 49);
+}
+fn generic_methods() {
+    let list = rustc_clr_interop_managed_generic_ctor::<List<i64>, fn(), ()>(());
+    for value in [11_i64, 22, 33] {
+        rustc_clr_interop_managed_generic_call_instance::<
+            List<i64>,
+            "Add",
+            (),
+            fn(TypeGeneric<0>),
+            (),
+            List<i64>,
+            (i64,),
+        >(list, (black_box(value),));
+    }
+    let seq: IEnumerable<i64> = rustc_clr_interop_managed_checked_cast(list);
+    // int32 Enumerable.Count<int64>(IEnumerable<!!0>)
+    let count = rustc_clr_interop_managed_generic_call_static::<
+        Enumerable,
+        "Count",
+        (i64,),
+        fn(IEnumerable<MethodGeneric<0>>) -> i32,
+        i32,
+        (IEnumerable<i64>,),
+    >((seq,));
+    test_eq!(count, 3);
+    // !!0 Enumerable.ElementAt<int64>(IEnumerable<!!0>, int32)
+    let last = rustc_clr_interop_managed_generic_call_static::<
+        Enumerable,
+        "ElementAt",
+        (i64,),
+        fn(IEnumerable<MethodGeneric<0>>, i32) -> MethodGeneric<0>,
+        i64,
+        (IEnumerable<i64>, i32),
+    >((seq, 2));
+    test_eq!(last, 33);
+}
@@ -59,6 +59,18 @@ pub fn rustc_codegen_clr_add_method_def<
     }
 }
 #[inline(never)]
+pub fn rustc_codegen_clr_add_ctor_def<const VIS: &'static str, FnType>(
+    class: ClassDef,
+    fn_type: FnType,
+) -> ClassDef {
+    black_box(());
+    loop {
+        if black_box(true) {
+            core::intrinsics::abort()
+        }
+    }
+}
+#[inline(never)]
 pub fn rustc_codegen_clr_new_typedef<
     const NAME: &'static str,
     const IS_VALUETYPE: bool,
@@ -81,7 +93,39 @@ pub fn rustc_codegen_clr_finish_type(class: ClassDef) {
         }
     }
 }
-
+#[inline(never)]
+pub fn rustc_clr_interop_managed_ld_fld<const FIELD: &'static str, Obj, T>(obj: Obj) -> T {
+    core::intrinsics::abort();
+}
+#[inline(never)]
+pub fn rustc_clr_interop_managed_st_fld<const FIELD: &'static str, Obj, T>(obj: Obj, val: T) {
+    core::intrinsics::abort();
+}
+#[inline(never)]
+pub fn rustc_clr_interop_managed_ctor1_<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    Arg1,
+>(
+    arg1: Arg1,
+) -> RustcCLRInteropManagedClass<ASSEMBLY, CLASS_PATH> {
+    core::intrinsics::abort();
+}
+#[inline(never)]
+pub fn rustc_clr_interop_managed_call_virt1_<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const METHOD: &'static str,
+    const IS_STATIC: bool,
+    Ret,
+    Arg1,
+>(
+    arg1: Arg1,
+) -> Ret {
+    core::intrinsics::abort();
+}
 macro_rules! typedef_fields {
     ($typedef:ident,)=>{};
     ($typedef:ident, $field_name:ident : $field_type:ty, $($tail:tt)*) => {
@@ -89,28 +133,61 @@ macro_rules! typedef_fields {
         $typedef = $crate::rustc_codegen_clr_add_field_def::<$field_type, $field_name>($typedef);
         typedef_fields!($typedef, $($tail)*)
     };
-    ($typedef:ident, virtual fn $fname:ident($($args:tt)*)->$ret:ty{$($inner:tt)*}, $($tail:tt)*) => {
-        use super::*;
-        mod $fname{
-            use super::super::*;
-            #[inline(never)]
+    ($typedef:ident, ctor($($args:tt)*){$($inner:tt)*}, $($tail:tt)*) => {
+        {
+            mod ctor{
+                use super::super::*;
+                #[inline(never)]
+                pub extern "C" fn rustc_codegen_clr_not_magic ($($args)*){
+                    $($inner)*
+                }
+            }
+            #[used]
+            static KEEP_FN: extern "C" fn ($($args)*) = ctor::rustc_codegen_clr_not_magic;
 
-            pub extern "C" fn rustc_codegen_clr_not_magic ($($args)*)->$ret{
-                $($inner)*
+            $typedef = $crate::rustc_codegen_clr_add_ctor_def::<"pub",_>($typedef,ctor::rustc_codegen_clr_not_magic);
+        }
+        typedef_fields!($typedef, $($tail)*)
+    };
+    ($typedef:ident, virtual fn $fname:ident($($args:tt)*)->$ret:ty{$($inner:tt)*}, $($tail:tt)*) => {
+        {
+            mod $fname{
+                use super::super::*;
+                #[inline(never)]
+                pub extern "C" fn rustc_codegen_clr_not_magic ($($args)*)->$ret{
+                    $($inner)*
+                }
             }
+            const FNAME:&str = stringify!($fname);
+            #[used]
+            static KEEP_FN: extern "C" fn ($($args)*)->$ret = $fname::rustc_codegen_clr_not_magic;
+
+            $typedef = $crate::rustc_codegen_clr_add_method_def::<"pub","virtual",FNAME,_>($typedef,$fname::rustc_codegen_clr_not_magic);
         }
-        const FNAME:&str = stringify!($fname);
-        #[used]
-        static KEEP_FN: extern "C" fn ($($args)*)->$ret = $fname::rustc_codegen_clr_not_magic;
+        typedef_fields!($typedef, $($tail)*)
+    };
+    ($typedef:ident, static fn $fname:ident($($args:tt)*)->$ret:ty{$($inner:tt)*}, $($tail:tt)*) => {
+        {
+            mod $fname{
+                use super::super::*;
+                #[inline(never)]
+                pub extern "C" fn rustc_codegen_clr_not_magic ($($args)*)->$ret{
+                    $($inner)*
+                }
+            }
+            const FNAME:&str = stringify!($fname);
+            #[used]
+            static KEEP_FN: extern "C" fn ($($args)*)->$ret = $fname::rustc_codegen_clr_not_magic;
 
-        $typedef = $crate::rustc_codegen_clr_add_method_def::<"pub","virtual",FNAME,_>($typedef,$fname::rustc_codegen_clr_not_magic);
+            $typedef = $crate::rustc_codegen_clr_add_method_def::<"pub","static",FNAME,_>($typedef,$fname::rustc_codegen_clr_not_magic);
+        }
         typedef_fields!($typedef, $($tail)*)
     };
 }
 macro_rules! dotnet_typedef {
     () => {};
 
-    (class $name:ident inherits [$superasm:path] $superclass:path {  $($inner:tt)* }) => {
+    (class $name:ident inherits [$($superasm:path)?] $superclass:path {  $($inner:tt)* }) => {
         mod $name {
             #[used]
             static PREVENT_DEAD_CODE_REMOVAL: fn() = rustc_codegen_clr_comptime_entrypoint;
@@ -118,7 +195,7 @@ macro_rules! dotnet_typedef {
             pub fn rustc_codegen_clr_comptime_entrypoint() {
                 const NAME: &str = stringify!($name);
                 const SUPER_CLASS: &str = stringify!($superclass);
-                const SUPER_ASM: &str = stringify!($superasm);
+                const SUPER_ASM: &str = stringify!($($superasm)?);
                 let mut class =
                     $crate::rustc_codegen_clr_new_typedef::<NAME, false, SUPER_ASM, SUPER_CLASS>();
                 typedef_fields!(class,   $($inner)*);
@@ -127,7 +204,7 @@ macro_rules! dotnet_typedef {
         }
     };
 
-    (struct $name:ident inherits [$superasm:path]  $superclass:path { $($inner:tt)* }) => {
+    (struct $name:ident inherits [$($superasm:path)?] $superclass:path { $($inner:tt)* }) => {
         mod $name {
             #[used]
             static PREVENT_DEAD_CODE_REMOVAL: fn() = rustc_codegen_clr_comptime_entrypoint;
@@ -136,10 +213,10 @@ macro_rules! dotnet_typedef {
             pub fn rustc_codegen_clr_comptime_entrypoint() {
                 const NAME: &str = stringify!($name);
                 const SUPER_CLASS: &str = stringify!($superclass);
-                const SUPER_ASM: &str = stringify!($superasm);
-                let class =
+                const SUPER_ASM: &str = stringify!($($superasm)?);
+                let mut class =
                     $crate::rustc_codegen_clr_new_typedef::<NAME, true, SUPER_ASM, SUPER_CLASS>();
-                //typedef_fields!(class,  $($inner:tt)*);
+                typedef_fields!(class, $($inner)*);
                 $crate::rustc_codegen_clr_finish_type(class);
             }
         }
@@ -147,11 +224,20 @@ macro_rules! dotnet_typedef {
 }
 
 dotnet_typedef! {
-    class RustObj inherits [System::Runtime]System::Runtime::Object{
+    class RustObj inherits [System::Runtime]System::Object{
         a : f32,
+        ctor(this:RustObj_, a:f32){
+            rustc_clr_interop_managed_st_fld::<"a", RustObj_, f32>(this, a);
+        },
         virtual fn ToString(this:RustObj_)->MString{
             "This is a .NET class - defined in Rust !".into()
         },
+        virtual fn GetHashCode(this:RustObj_)->i32{
+            rustc_clr_interop_managed_ld_fld::<"a", RustObj_, f32>(this) as i32
+        },
+        static fn Create(a:f32)->RustObj_{
+            rustc_clr_interop_managed_ctor1_::<"", "RustObj", false, f32>(a)
+        },
         // NOTE: the current shape of this macro, and it's implementation is
         // highly experimental. All of this is subject to change,
         // This is a **very** early prototype!
@@ -159,25 +245,27 @@ dotnet_typedef! {
 }
 
 dotnet_typedef! {
-    class RustObj2 inherits [System::Runtime]System::Runtime::Object{
-        a : f32,
-        virtual fn ToString(this:RustObj2_)->MString{
+    class RustObj2 inherits [System::Runtime]System::Exception{
+        b : u64,
+        virtual fn get_Message(this:RustObj2_)->MString{
             panic!()
         },
-
     }
 }
-/*dotnet_typedef! {
-    class RustObj2 inherits RustObj{
-
-    }
-}*/
 dotnet_typedef! {
-    struct RustStruct inherits [System::Runtime]System::Runtime::ValueType{
-
+    struct RustStruct inherits [System::Runtime]System::ValueType{
+        a : u32,
+        b : f64,
     }
 }
 fn main() {
+    let obj = rustc_clr_interop_managed_ctor1_::<"", "RustObj", false, f32>(7.5);
+    let hash = rustc_clr_interop_managed_call_virt1_::<"", "RustObj", false, "GetHashCode", false, i32, RustObj_>(
+        obj,
+    );
+    if hash != 7 {
+        core::intrinsics::abort();
+    }
     let chr: *mut RustObj_ = core::ptr::null_mut();
     black_box(chr);
 }